@@ -0,0 +1,60 @@
+//! Build script.
+//!
+//! With the `embedded-tablebases` feature, bundles every `<name>.uxtb` file in
+//! the tablebase directory (`tablebases/`, or `UNDERCHEX_TABLEBASE_DIR`) into
+//! the library, so builds ship with tablebases instead of generating them per
+//! session. The repository ships only `KvK`; generate more with
+//! `cargo run --release --example generate_tablebases -- tablebases KQvK KLvK KCvK`.
+//! A directory without tablebase files embeds none, with a warning.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=UNDERCHEX_TABLEBASE_DIR");
+
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    let mut source = String::from("pub static EMBEDDED_TABLEBASES: &[(&str, &[u8])] = &[\n");
+
+    if env::var_os("CARGO_FEATURE_EMBEDDED_TABLEBASES").is_some() {
+        let dir = env::var_os("UNDERCHEX_TABLEBASE_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| {
+                Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("tablebases")
+            });
+        println!("cargo:rerun-if-changed={}", dir.display());
+
+        let mut files: Vec<PathBuf> = fs::read_dir(&dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok().map(|e| e.path()))
+                    .filter(|path| path.extension().is_some_and(|ext| ext == "uxtb"))
+                    .collect()
+            })
+            .unwrap_or_default();
+        files.sort();
+        if files.is_empty() {
+            println!(
+                "cargo:warning=embedded-tablebases is enabled but {} has no .uxtb files; \
+                 no tablebases are embedded",
+                dir.display()
+            );
+        }
+
+        for path in files {
+            let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+            let path = fs::canonicalize(&path).unwrap();
+            println!("cargo:rerun-if-changed={}", path.display());
+            source.push_str(&format!(
+                "    ({:?}, include_bytes!({:?})),\n",
+                name,
+                path.display().to_string()
+            ));
+        }
+    }
+
+    source.push_str("];\n");
+    fs::write(out_dir.join("embedded_tablebases.rs"), source).unwrap();
+}
@@ -0,0 +1,60 @@
+//! Evaluation development tool.
+//!
+//! Usage:
+//!   eval self-test [positions] [seed]   Check evaluation invariants
+//!   eval score <hexfen>                 Evaluate a position
+
+use std::env;
+use std::process;
+
+use underchex_wasm::ai::evaluate_position;
+use underchex_wasm::eval::{self_test_with, DEFAULT_SELF_TEST_POSITIONS};
+use underchex_wasm::notation::parse_hexfen;
+
+fn usage() -> ! {
+    eprintln!("usage: eval self-test [positions] [seed] | eval score <hexfen>");
+    process::exit(2);
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("self-test") => {
+            let positions = args
+                .get(1)
+                .map(|n| n.parse().unwrap_or_else(|_| usage()))
+                .unwrap_or(DEFAULT_SELF_TEST_POSITIONS);
+            let seed = args
+                .get(2)
+                .map(|n| n.parse().unwrap_or_else(|_| usage()))
+                .unwrap_or(1);
+            let report = self_test_with(positions, seed);
+            for failure in &report.failures {
+                println!(
+                    "FAIL {}: expected {}, got {} for {}",
+                    failure.transform, failure.expected, failure.actual, failure.fen
+                );
+            }
+            println!(
+                "{} positions, {} checks, {} failures",
+                report.positions,
+                report.checks,
+                report.failures.len()
+            );
+            if !report.passed() {
+                process::exit(1);
+            }
+        }
+        Some("score") => {
+            let fen = args.get(1).unwrap_or_else(|| usage());
+            match parse_hexfen(fen) {
+                Some((board, _)) => println!("{}", evaluate_position(&board)),
+                None => {
+                    eprintln!("invalid HexFEN: {}", fen);
+                    process::exit(1);
+                }
+            }
+        }
+        _ => usage(),
+    }
+}
@@ -0,0 +1,39 @@
+//! Generate tablebase files for embedding with the `embedded-tablebases`
+//! feature (see build.rs).
+//!
+//! Usage: generate_tablebases <dir> [names...]
+//! Names default to KQvK, KLvK and KCvK. Dependencies (e.g. KvK) are generated
+//! too, but only the named tablebases are written.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use underchex_wasm::tablebase::{export_tablebase_to_bytes, generate_tablebase_on_demand};
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let dir = args.next().unwrap_or_else(|| "tablebases".to_string());
+    let mut names: Vec<String> = args.collect();
+    if names.is_empty() {
+        names = ["KQvK", "KLvK", "KCvK"].map(String::from).to_vec();
+    }
+
+    fs::create_dir_all(&dir).expect("cannot create output directory");
+    for name in names {
+        let Some(tablebase) = generate_tablebase_on_demand(&name) else {
+            eprintln!("unknown configuration: {}", name);
+            std::process::exit(1);
+        };
+        let path = Path::new(&dir).join(format!("{}.uxtb", name));
+        let bytes = export_tablebase_to_bytes(&tablebase);
+        fs::write(&path, &bytes).expect("cannot write tablebase");
+        println!(
+            "{}: {} positions, {} bytes, {} ms",
+            path.display(),
+            tablebase.size,
+            bytes.len(),
+            tablebase.metadata.generation_time_ms
+        );
+    }
+}
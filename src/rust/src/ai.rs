@@ -5,42 +5,78 @@
 //! - Positional bonuses (centrality, mobility)
 //! - Alpha-beta pruning with move ordering
 //! - Transposition table for caching evaluations
-//! - Quiescence search for tactical accuracy
+//! - Quiescence search for tactical accuracy (captures, checks, evasions)
+//! - An optional swindle mode for lost positions (see [`AIOptions`])
+//!
+//! Evaluation weights, quiescence limits and pruning switches are read from
+//! the engine options (see [`crate::options`]).
 //!
 //! Signed-by: agent #22 claude-sonnet-4 via opencode 20260122T06:43:39
 
 use std::collections::HashMap;
-
-use crate::board::hex_distance;
-use crate::moves::{apply_move, generate_all_legal_moves, is_in_check};
-use crate::tablebase::{detect_configuration, get_tablebase_score, probe_tablebase};
+use std::mem::size_of;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::attacks::get_attacked_cells;
+use crate::board::{hex_distance, occupied_cells};
+use crate::endgames::defense_bonus;
+use crate::game::{
+    create_new_game, create_position_record, get_position_key, RepetitionStack,
+    REPETITION_DRAW_COUNT,
+};
+use crate::humanlike::{choose_humanlike_move, HumanlikeSettings};
+use crate::mates::mating_net_bonus;
+use crate::memory::{board_bytes, eval_cache_capacity};
+use crate::moves::{
+    apply_move, find_king, generate_all_legal_moves, generate_all_legal_moves_with_drops,
+    is_in_check,
+};
+use crate::options::{options_generation, EngineOption};
+use crate::pieces::{demoted_type, get_type_definition};
+use crate::rng::SeededRng;
+use crate::tablebase::{detect_configuration, get_tablebase_score, probe_tablebase, WDLOutcome};
+use crate::trace::{trace_event, trace_span};
 use crate::types::BOARD_RADIUS;
-use crate::types::{BoardState, Color, HexCoord, Move, Piece, PieceType};
+use crate::types::{
+    BoardState, Color, GameState, Hand, Hands, HexCoord, Move, Piece, PieceType, PositionRecord,
+    StalemateRule, Variant, WinConditions,
+};
+use crate::variants::{
+    generate_boulder_placements, is_move_allowed, variant_outcome, win_condition_outcome,
+    with_piece_promotions, VariantOutcome, KING_OF_THE_HILL_CENTER, RACING_KINGS_GOAL_ROW,
+};
+use crate::zobrist::{
+    boards_equal, hash_board, hash_board_checked, hash_hands, hash_pawns_checked, hash_position,
+    random_game_positions,
+};
 
 // ============================================================================
 // Piece Values
 // ============================================================================
 
 /// Base material values for pieces (in centipawns).
-pub const PIECE_VALUES: [(PieceType, i32); 6] = [
+pub const PIECE_VALUES: [(PieceType, i32); 13] = [
     (PieceType::Pawn, 100),
     (PieceType::Knight, 300),
     (PieceType::Lance, 450),
     (PieceType::Chariot, 450),
     (PieceType::Queen, 900),
     (PieceType::King, 0),
+    (PieceType::Boulder, 0),
+    (PieceType::Wazir, 200),
+    (PieceType::Ferz, 150),
+    (PieceType::Dabbaba, 200),
+    (PieceType::Cannon, 400),
+    (PieceType::PromotedKnight, 500),
+    (PieceType::PromotedLance, 650),
 ];
 
-/// Get piece value in centipawns.
+/// Get piece value in centipawns (from the piece's definition).
 pub fn get_piece_value(piece_type: PieceType) -> i32 {
-    match piece_type {
-        PieceType::Pawn => 100,
-        PieceType::Knight => 300,
-        PieceType::Lance => 450,
-        PieceType::Chariot => 450,
-        PieceType::Queen => 900,
-        PieceType::King => 0,
-    }
+    get_type_definition(piece_type).value
 }
 
 /// Value for checkmate (high enough to always prefer it).
@@ -49,17 +85,45 @@ pub const CHECKMATE_VALUE: i32 = 100000;
 /// Value for stalemate (draw).
 pub const STALEMATE_VALUE: i32 = 0;
 
+/// Value of each quarter point a fractional stalemate rule gives the
+/// stalemating side beyond an even share.
+pub const STALEMATE_QUARTER_VALUE: i32 = 1000;
+
+/// Score (from White's side) of a stalemate of `stalemated` under a rule,
+/// found with `depth` plies left. A decisive stalemate scores like a mate.
+pub fn stalemate_score(rule: StalemateRule, stalemated: Color, depth: i32) -> i32 {
+    let stalemater_score = match rule.stalemater_quarters() {
+        4 => CHECKMATE_VALUE - depth,
+        0 => -CHECKMATE_VALUE + depth,
+        quarters => (i32::from(quarters) - 2) * STALEMATE_QUARTER_VALUE,
+    };
+    match stalemated {
+        Color::White => -stalemater_score,
+        Color::Black => stalemater_score,
+    }
+}
+
+/// Score (from White's side) of a draw found with `depth` plies left: even,
+/// or like a mate for the side a draw counts as a win for (armageddon).
+pub fn draw_score(draw_winner: Option<Color>, depth: i32) -> i32 {
+    match draw_winner {
+        None => STALEMATE_VALUE,
+        Some(Color::White) => CHECKMATE_VALUE - depth,
+        Some(Color::Black) => -CHECKMATE_VALUE + depth,
+    }
+}
+
 // ============================================================================
 // Position Evaluation
 // ============================================================================
 
 /// Calculate centrality bonus for a position.
 /// Pieces closer to the center are generally stronger on a hex board.
-pub fn get_centrality_bonus(coord: HexCoord) -> i32 {
+pub(crate) fn get_centrality_bonus(coord: HexCoord) -> i32 {
     let center = HexCoord::new(0, 0);
     let distance_from_center = hex_distance(coord, center);
     let centrality_score = BOARD_RADIUS - distance_from_center;
-    centrality_score * 5 // 5 centipawns per ring closer to center
+    centrality_score * EngineOption::CentralityWeight.get()
 }
 
 /// Calculate pawn advancement bonus.
@@ -80,7 +144,7 @@ pub fn get_pawn_advancement_bonus(coord: HexCoord, color: Color) -> i32 {
     let distance_from_start = (coord.r - start_r).abs() as f64;
     let progress = distance_from_start / total_distance;
 
-    (progress * progress * 50.0) as i32
+    (progress * progress * f64::from(EngineOption::PawnAdvancementWeight.get())) as i32
 }
 
 /// Get position bonus for a piece.
@@ -94,19 +158,40 @@ pub fn get_piece_position_bonus(piece: &Piece, coord: HexCoord) -> i32 {
     bonus
 }
 
+/// Evaluate the pawns alone (material and placement), the part of the
+/// evaluation a pawn hash table caches.
+/// Returns value from white's perspective in centipawns.
+pub fn evaluate_pawns(board: &BoardState) -> i32 {
+    occupied_cells(board)
+        .filter(|(_, piece)| piece.piece_type == PieceType::Pawn)
+        .map(|(coord, piece)| {
+            let value = get_piece_value(piece.piece_type) + get_piece_position_bonus(&piece, coord);
+            if piece.color == Color::White {
+                value
+            } else {
+                -value
+            }
+        })
+        .sum()
+}
+
 /// Evaluate material balance for a board position.
 /// Returns value from white's perspective in centipawns.
 pub fn evaluate_material(board: &BoardState) -> i32 {
-    let mut score = 0;
+    material_with_pawns(board, evaluate_pawns(board))
+}
 
-    for (pos_str, piece) in board.iter() {
-        let parts: Vec<&str> = pos_str.split(',').collect();
-        let q: i32 = parts.get(0).and_then(|s| s.parse().ok()).unwrap_or(0);
-        let r: i32 = parts.get(1).and_then(|s| s.parse().ok()).unwrap_or(0);
-        let coord = HexCoord::new(q, r);
+/// Material balance given the pawns' evaluation.
+fn material_with_pawns(board: &BoardState, pawns: i32) -> i32 {
+    let mut score = pawns;
+
+    for (coord, piece) in occupied_cells(board) {
+        if piece.is_boulder() || piece.piece_type == PieceType::Pawn {
+            continue;
+        }
 
         let value = get_piece_value(piece.piece_type);
-        let position_bonus = get_piece_position_bonus(piece, coord);
+        let position_bonus = get_piece_position_bonus(&piece, coord);
         let total_value = value + position_bonus;
 
         if piece.color == Color::White {
@@ -122,13 +207,18 @@ pub fn evaluate_material(board: &BoardState) -> i32 {
 /// Evaluate mobility (number of legal moves).
 pub fn evaluate_mobility(board: &BoardState, color: Color) -> i32 {
     let moves = generate_all_legal_moves(board, color);
-    (moves.len() * 2) as i32 // 2 centipawns per legal move
+    moves.len() as i32 * EngineOption::MobilityWeight.get()
 }
 
 /// Full position evaluation.
 /// Returns value from white's perspective in centipawns.
 pub fn evaluate_position(board: &BoardState) -> i32 {
-    let mut score = evaluate_material(board);
+    position_with_material(board, evaluate_material(board))
+}
+
+/// Full position evaluation given the material balance.
+fn position_with_material(board: &BoardState, material: i32) -> i32 {
+    let mut score = material;
 
     // Add mobility difference
     let white_mobility = evaluate_mobility(board, Color::White);
@@ -136,14 +226,15 @@ pub fn evaluate_position(board: &BoardState) -> i32 {
     score += white_mobility - black_mobility;
 
     // Check bonus (being in check is bad)
+    let check_penalty = EngineOption::CheckPenalty.get();
     if is_in_check(board, Color::White) {
-        score -= 50;
+        score -= check_penalty;
     }
     if is_in_check(board, Color::Black) {
-        score += 50;
+        score += check_penalty;
     }
 
-    score
+    score + mating_net_bonus(board) + defense_bonus(board)
 }
 
 /// Evaluate position from the perspective of a specific color.
@@ -156,6 +247,143 @@ pub fn evaluate_for_color(board: &BoardState, color: Color) -> i32 {
     }
 }
 
+/// Positions below which a thread of a batch evaluation is not worth
+/// spawning.
+const BATCH_CHUNK: usize = 64;
+
+/// Evaluate many positions (see [`evaluate_position`]), from White's
+/// perspective. Native builds spread large batches over threads.
+pub fn evaluate_batch(positions: &[BoardState]) -> Vec<i32> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let chunk = positions.len().div_ceil(threads).max(BATCH_CHUNK);
+        if positions.len() > chunk {
+            return std::thread::scope(|scope| {
+                let handles: Vec<_> = positions
+                    .chunks(chunk)
+                    .map(|chunk| {
+                        scope.spawn(move || chunk.iter().map(evaluate_position).collect::<Vec<_>>())
+                    })
+                    .collect();
+                handles
+                    .into_iter()
+                    .flat_map(|handle| {
+                        handle
+                            .join()
+                            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+                    })
+                    .collect()
+            });
+        }
+    }
+    positions.iter().map(evaluate_position).collect()
+}
+
+/// Evaluate material held in hand (drop variants).
+/// Pieces in hand are counted at full value since they can be dropped anywhere.
+pub fn evaluate_hand(hand: &Hand) -> i32 {
+    hand.iter()
+        .map(|(&piece_type, &count)| get_piece_value(piece_type) * count as i32)
+        .sum()
+}
+
+/// Default bonus per ring a king stands closer to the hill in king of the
+/// hill (the `kingOfTheHillStep` option).
+pub const KING_OF_THE_HILL_STEP_BONUS: i32 = 40;
+
+/// King-to-center race bonus for king of the hill.
+/// Returns value from white's perspective in centipawns.
+pub fn get_king_of_the_hill_bonus(board: &BoardState) -> i32 {
+    let race = |color: Color| {
+        find_king(board, color)
+            .map(|king| BOARD_RADIUS - hex_distance(king, KING_OF_THE_HILL_CENTER))
+            .unwrap_or(0)
+            * EngineOption::KingOfTheHillStep.get()
+    };
+    race(Color::White) - race(Color::Black)
+}
+
+/// Default bonus per row a king has advanced towards the goal in racing
+/// kings (the `racingKingsStep` option).
+pub const RACING_KINGS_STEP_BONUS: i32 = 60;
+
+/// King progress bonus for racing kings.
+/// Returns value from white's perspective in centipawns.
+pub fn get_racing_kings_bonus(board: &BoardState) -> i32 {
+    let progress = |color: Color| {
+        find_king(board, color)
+            .map(|king| 2 * BOARD_RADIUS - (king.r - RACING_KINGS_GOAL_ROW))
+            .unwrap_or(0)
+            * EngineOption::RacingKingsStep.get()
+    };
+    progress(Color::White) - progress(Color::Black)
+}
+
+/// Full position evaluation under a variant's rules.
+/// Returns value from white's perspective in centipawns.
+pub fn evaluate_for_variant(board: &BoardState, variant: Variant) -> i32 {
+    variant_with_position(board, variant, evaluate_position(board))
+}
+
+/// Variant evaluation given the full position evaluation.
+fn variant_with_position(board: &BoardState, variant: Variant, score: i32) -> i32 {
+    match variant {
+        Variant::KingOfTheHill => score + get_king_of_the_hill_bonus(board),
+        Variant::RacingKings => score + get_racing_kings_bonus(board),
+        Variant::Standard
+        | Variant::Crazyhouse { .. }
+        | Variant::Boulder
+        | Variant::Cannons
+        | Variant::Shogi { .. } => score,
+    }
+}
+
+/// Score for a position decided by a variant end condition.
+fn variant_outcome_score(outcome: VariantOutcome, depth: i32) -> i32 {
+    match outcome {
+        VariantOutcome::Win(Color::White, _) => CHECKMATE_VALUE - depth,
+        VariantOutcome::Win(Color::Black, _) => -CHECKMATE_VALUE + depth,
+        VariantOutcome::Draw(_) => STALEMATE_VALUE,
+    }
+}
+
+/// Full position evaluation including pieces in hand.
+/// Returns value from white's perspective in centipawns.
+pub fn evaluate_position_with_hands(board: &BoardState, hands: &Hands) -> i32 {
+    evaluate_position(board) + evaluate_hand(&hands.white) - evaluate_hand(&hands.black)
+}
+
+// ============================================================================
+// Cancellation
+// ============================================================================
+
+/// A flag for stopping searches and tablebase builds from outside, such as a
+/// Ctrl-C handler or another thread. Clones share the flag. A cancelled
+/// search returns the deepest iteration it finished.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the work holding this token to stop.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Clear the flag for the next search.
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+
 // ============================================================================
 // Transposition Table
 // ============================================================================
@@ -175,12 +403,38 @@ pub struct TTEntry {
     pub depth: i32,
     pub entry_type: TTEntryType,
     pub best_move: Option<Move>,
+    /// [`TranspositionTable::age`] of the search that stored it
+    pub age: u32,
 }
 
 /// Transposition table - caches position evaluations.
+/// Entries are keyed by Zobrist hash and keep their board, so a hash collision
+/// is detected instead of returning another position's entry.
+/// The table also keeps the killer moves and history scores used to order
+/// quiet moves, and the evaluation cache, which live as long as its entries.
+/// Entries from earlier searches (see [`TranspositionTable::new_search`]) are
+/// reused but replaced first.
 pub struct TranspositionTable {
-    table: HashMap<String, TTEntry>,
+    table: HashMap<u64, (BoardState, TTEntry)>,
     max_size: usize,
+    age: u32,
+    /// Two most recent quiet cutoff moves per remaining depth
+    killers: HashMap<i32, [Option<(HexCoord, HexCoord)>; 2]>,
+    /// Quiet cutoff moves by mover, weighted by depth squared
+    history: HashMap<(Color, HexCoord, HexCoord), i32>,
+    /// Last quiet cutoff reply to each move, by the move's piece and cells
+    countermoves: HashMap<(Piece, HexCoord, HexCoord), (HexCoord, HexCoord)>,
+    eval_cache: EvalCache,
+    /// Rule the stored scores assume for stalemates
+    stalemate_rule: StalemateRule,
+    /// Optional win conditions the stored scores assume
+    win_conditions: WinConditions,
+    /// Side the stored scores count draws as wins for (armageddon)
+    draw_winner: Option<Color>,
+    /// Pieces in hand at the node being searched (drop variants)
+    hands: Hands,
+    /// Stops searches with this table
+    cancel: CancelToken,
 }
 
 impl TranspositionTable {
@@ -189,10 +443,20 @@ impl TranspositionTable {
         Self {
             table: HashMap::with_capacity(max_size),
             max_size,
+            age: 0,
+            killers: HashMap::new(),
+            history: HashMap::new(),
+            countermoves: HashMap::new(),
+            eval_cache: EvalCache::new(),
+            stalemate_rule: StalemateRule::default(),
+            win_conditions: WinConditions::default(),
+            draw_winner: None,
+            hands: Hands::default(),
+            cancel: CancelToken::new(),
         }
     }
 
-    /// Generate a hash key for a board position.
+    /// Generate a canonical string key for a board position.
     pub fn generate_hash(board: &BoardState) -> String {
         let mut pieces: Vec<String> = board
             .iter()
@@ -202,14 +466,9 @@ impl TranspositionTable {
                 } else {
                     'b'
                 };
-                let type_char = match piece.piece_type {
-                    PieceType::Pawn => 'p',
-                    PieceType::Knight => 'n',
-                    PieceType::Lance => 'l',
-                    PieceType::Chariot => 'c',
-                    PieceType::Queen => 'q',
-                    PieceType::King => 'k',
-                };
+                let type_char = get_type_definition(piece.piece_type)
+                    .symbol
+                    .to_ascii_lowercase();
                 let variant = piece
                     .variant
                     .as_ref()
@@ -218,7 +477,11 @@ impl TranspositionTable {
                         crate::types::LanceVariant::B => "B",
                     })
                     .unwrap_or("");
-                format!("{}:{}{}{}", pos_str, color_char, type_char, variant)
+                // Normalize the cell so differently formatted keys agree
+                let cell = HexCoord::from_key(pos_str)
+                    .map(|cell| cell.to_key())
+                    .unwrap_or_else(|| pos_str.clone());
+                format!("{}:{}{}{}", cell, color_char, type_char, variant)
             })
             .collect();
         pieces.sort();
@@ -234,47 +497,266 @@ impl TranspositionTable {
         entry_type: TTEntryType,
         best_move: Option<Move>,
     ) {
-        // Simple size management - clear half the table when full
+        // When full, drop the entries of earlier searches, or else half the
+        // table
         if self.table.len() >= self.max_size {
-            let keys_to_remove: Vec<String> =
-                self.table.keys().take(self.max_size / 2).cloned().collect();
+            let age = self.age;
+            self.table.retain(|_, (_, entry)| entry.age == age);
+        }
+        if self.table.len() >= self.max_size {
+            let keys_to_remove: Vec<u64> =
+                self.table.keys().take(self.max_size / 2).copied().collect();
             for key in keys_to_remove {
                 self.table.remove(&key);
             }
         }
 
-        let hash = Self::generate_hash(board);
+        let hash = hash_board(board) ^ hash_hands(&self.hands);
         let existing = self.table.get(&hash);
 
-        // Only replace if new entry has equal or greater depth
-        if existing.is_none() || existing.unwrap().depth <= depth {
+        // Only replace if new entry has equal or greater depth, the entry is
+        // from an earlier search, or the slot holds a different position
+        // with the same hash
+        let replace = match existing {
+            None => true,
+            Some((stored, entry)) => {
+                entry.depth <= depth || entry.age != self.age || !boards_equal(stored, board)
+            }
+        };
+        if replace {
             self.table.insert(
                 hash,
-                TTEntry {
-                    score,
-                    depth,
-                    entry_type,
-                    best_move,
-                },
+                (
+                    board.clone(),
+                    TTEntry {
+                        score,
+                        depth,
+                        entry_type,
+                        best_move,
+                        age: self.age,
+                    },
+                ),
             );
         }
     }
 
     /// Probe the transposition table for a position.
     pub fn probe(&self, board: &BoardState) -> Option<&TTEntry> {
-        let hash = Self::generate_hash(board);
-        self.table.get(&hash)
+        self.table
+            .get(&(hash_board(board) ^ hash_hands(&self.hands)))
+            .filter(|(stored, _)| boards_equal(stored, board))
+            .map(|(_, entry)| entry)
     }
 
     /// Clear the transposition table.
     pub fn clear(&mut self) {
         self.table.clear();
+        self.clear_ordering();
+        self.eval_cache.clear();
+    }
+
+    /// Rule searches with this table score stalemates by.
+    pub fn stalemate_rule(&self) -> StalemateRule {
+        self.stalemate_rule
+    }
+
+    /// Score stalemates by a game's rule. Entries stored under another rule
+    /// are dropped, as their scores no longer hold.
+    pub fn set_stalemate_rule(&mut self, rule: StalemateRule) {
+        if self.stalemate_rule != rule {
+            self.table.clear();
+            self.stalemate_rule = rule;
+        }
+    }
+
+    /// Optional win conditions searches with this table play by.
+    pub fn win_conditions(&self) -> WinConditions {
+        self.win_conditions
+    }
+
+    /// Play by a game's optional win conditions. Entries stored under other
+    /// conditions are dropped, as their scores no longer hold.
+    pub fn set_win_conditions(&mut self, conditions: WinConditions) {
+        if self.win_conditions != conditions {
+            self.table.clear();
+            self.win_conditions = conditions;
+        }
+    }
+
+    /// Side searches with this table count draws as wins for.
+    pub fn draw_winner(&self) -> Option<Color> {
+        self.draw_winner
+    }
+
+    /// Count draws as wins for a side (armageddon), or as draws with None.
+    /// Entries stored otherwise are dropped, as their scores no longer hold.
+    pub fn set_draw_winner(&mut self, draw_winner: Option<Color>) {
+        if self.draw_winner != draw_winner {
+            self.table.clear();
+            self.draw_winner = draw_winner;
+        }
+    }
+
+    /// Search by a game's stalemate rule and optional win conditions, from
+    /// its pieces in hand.
+    pub fn set_game_rules(&mut self, state: &GameState) {
+        self.set_stalemate_rule(state.stalemate_rule);
+        self.set_win_conditions(state.win_conditions);
+        self.hands = state.hands.clone();
+    }
+
+    /// Pieces in hand at the node being searched.
+    pub fn hands(&self) -> &Hands {
+        &self.hands
+    }
+
+    /// Update the hands for a move searched in a drop variant: a drop leaves
+    /// the mover's hand and a capture joins it. Returns the hands to restore
+    /// with [`Self::leave_hands`] once the move is searched.
+    pub(crate) fn enter_hands(&mut self, mv: &Move, variant: Variant) -> Option<Hands> {
+        if !variant.has_drops() {
+            return None;
+        }
+        let saved = self.hands.clone();
+        let color = mv.piece.color;
+        if mv.is_drop {
+            self.hands.remove(color, mv.piece.piece_type);
+        }
+        if let Some(captured) = mv.captured {
+            self.hands.add(color, demoted_type(captured.piece_type));
+        }
+        Some(saved)
+    }
+
+    pub(crate) fn leave_hands(&mut self, saved: Option<Hands>) {
+        if let Some(hands) = saved {
+            self.hands = hands;
+        }
+    }
+
+    /// Material in hand, in centipawns from White's side.
+    fn hand_balance(&self) -> i32 {
+        evaluate_hand(&self.hands.white) - evaluate_hand(&self.hands.black)
+    }
+
+    /// Token that stops searches with this table.
+    pub fn cancel_token(&self) -> CancelToken {
+        self.cancel.clone()
+    }
+
+    /// Stop searches with this table when `token` is cancelled.
+    pub fn set_cancel_token(&mut self, token: CancelToken) {
+        self.cancel = token;
+    }
+
+    /// Whether searches with this table are to stop.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    /// Start a search that keeps the earlier searches' work: their entries
+    /// age (and are replaced first), history scores halve, and killer moves,
+    /// which are kept by remaining depth, are forgotten.
+    pub fn new_search(&mut self) {
+        self.age = self.age.wrapping_add(1);
+        self.killers.clear();
+        self.history.retain(|_, score| {
+            *score /= 2;
+            *score > 0
+        });
+    }
+
+    /// Forget the killer, history and countermove tables, keeping the
+    /// entries (e.g. for a position from another game).
+    pub fn clear_ordering(&mut self) {
+        self.killers.clear();
+        self.history.clear();
+        self.countermoves.clear();
+    }
+
+    /// Number of [`new_search`](Self::new_search) calls since creation.
+    pub fn age(&self) -> u32 {
+        self.age
+    }
+
+    /// Whether `mv` is the remembered countermove to `previous`.
+    pub fn is_countermove(&self, mv: &Move, previous: Option<&Move>) -> bool {
+        previous.is_some_and(|previous| {
+            self.countermoves
+                .get(&(previous.piece, previous.from, previous.to))
+                .is_some_and(|&cells| cells == (mv.from, mv.to))
+        })
+    }
+
+    /// Ordering bonus of a quiet move from the killer, history and
+    /// countermove tables; `previous` is the move being replied to.
+    pub fn ordering_bonus(&self, mv: &Move, depth: i32, previous: Option<&Move>) -> i32 {
+        if mv.captured.is_some() || mv.promotion.is_some() {
+            return 0;
+        }
+        let cells = (mv.from, mv.to);
+        let killer = self
+            .killers
+            .get(&depth)
+            .is_some_and(|killers| killers.contains(&Some(cells)));
+        let history = self
+            .history
+            .get(&(mv.piece.color, mv.from, mv.to))
+            .copied()
+            .unwrap_or(0);
+        let mut bonus = history.saturating_mul(EngineOption::HistoryScale.get()) / 100;
+        if killer {
+            bonus += EngineOption::KillerBonus.get();
+        }
+        if self.is_countermove(mv, previous) {
+            bonus += EngineOption::CountermoveBonus.get();
+        }
+        bonus.min(MAX_HISTORY_BONUS)
+    }
+
+    /// Remember a move that caused a beta cutoff in reply to `previous`, if
+    /// it is quiet.
+    pub fn record_cutoff(&mut self, mv: &Move, depth: i32, previous: Option<&Move>) {
+        if mv.captured.is_some() || mv.promotion.is_some() {
+            return;
+        }
+        if let Some(previous) = previous {
+            self.countermoves.insert(
+                (previous.piece, previous.from, previous.to),
+                (mv.from, mv.to),
+            );
+        }
+        let cells = Some((mv.from, mv.to));
+        let killers = self.killers.entry(depth).or_default();
+        if killers[0] != cells {
+            killers[1] = killers[0];
+            killers[0] = cells;
+        }
+        let score = self
+            .history
+            .entry((mv.piece.color, mv.from, mv.to))
+            .or_insert(0);
+        *score = score.saturating_add(depth * depth);
     }
 
     /// Get table size.
     pub fn size(&self) -> usize {
         self.table.len()
     }
+
+    /// Estimated bytes held by the entries, including their boards.
+    pub fn memory_usage(&self) -> usize {
+        self.table.capacity() * (size_of::<(u64, (BoardState, TTEntry))>() + 1)
+            + self
+                .table
+                .values()
+                .map(|(board, _)| board_bytes(board))
+                .sum::<usize>()
+    }
+
+    pub fn eval_cache(&self) -> &EvalCache {
+        &self.eval_cache
+    }
 }
 
 impl Default for TranspositionTable {
@@ -283,6 +765,176 @@ impl Default for TranspositionTable {
     }
 }
 
+/// Result of [`tt_stress_test`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TTStressReport {
+    pub stores: usize,
+    pub probes: usize,
+    pub hits: usize,
+    /// Hits whose entry belongs to another position (must be 0)
+    pub wrong_hits: usize,
+    /// Largest size the table reached (must not exceed its maximum)
+    pub peak_size: usize,
+}
+
+impl TTStressReport {
+    pub fn passed(&self, max_size: usize) -> bool {
+        self.wrong_hits == 0 && self.peak_size <= max_size
+    }
+}
+
+/// Store the positions of `games` random games (up to `plies` plies each) in
+/// a table of `max_size` entries, then probe every one of them. Each entry's
+/// score is the position's evaluation, so a hit with a different score is an
+/// entry returned for the wrong position.
+pub fn tt_stress_test(games: usize, plies: usize, max_size: usize, seed: u64) -> TTStressReport {
+    let start = create_new_game().board;
+    let mut rng = SeededRng::new(seed);
+    let mut tt = TranspositionTable::new(max_size);
+    let mut report = TTStressReport::default();
+
+    for _ in 0..games {
+        let positions = random_game_positions(&start, Color::White, plies, &mut rng);
+        for (depth, (board, _)) in positions.iter().enumerate() {
+            let score = evaluate_position(board);
+            tt.store(board, depth as i32, score, TTEntryType::Exact, None);
+            report.stores += 1;
+            report.peak_size = report.peak_size.max(tt.size());
+        }
+        for (board, _) in &positions {
+            report.probes += 1;
+            if let Some(entry) = tt.probe(board) {
+                report.hits += 1;
+                if entry.score != evaluate_position(board) {
+                    report.wrong_hits += 1;
+                }
+            }
+        }
+    }
+    report
+}
+
+// ============================================================================
+// Evaluation Cache
+// ============================================================================
+
+/// Default entries of the pawn hash table.
+pub const PAWN_CACHE_SIZE: usize = 16_384;
+
+/// Default entries of the evaluation cache.
+pub const EVAL_CACHE_SIZE: usize = 65_536;
+
+/// Caches of static evaluations: the pawn evaluation by pawns-only Zobrist
+/// hash, and the full evaluation by board hash and variant. Entries keep a
+/// second, independent hash of their pieces and are only used when it
+/// matches too, so a collision of the index hash cannot return another
+/// position's score. Changing an engine option empties both, since weights
+/// feed the evaluation. Each is emptied when full.
+#[derive(Debug)]
+pub struct EvalCache {
+    /// Pawn evaluations with their check hash, by pawn hash
+    pawns: HashMap<u64, (u64, i32)>,
+    /// Evaluations with their check hash, by board hash and variant
+    evals: HashMap<(u64, Variant), (u64, i32)>,
+    pawn_capacity: usize,
+    eval_capacity: usize,
+    /// [`options_generation`] the entries were computed under
+    generation: u64,
+}
+
+impl EvalCache {
+    /// Create caches of the sizes set by the memory budget (see
+    /// [`eval_cache_capacity`]).
+    pub fn new() -> Self {
+        let (pawn_capacity, eval_capacity) = eval_cache_capacity();
+        Self::with_capacity(pawn_capacity, eval_capacity)
+    }
+
+    pub fn with_capacity(pawn_capacity: usize, eval_capacity: usize) -> Self {
+        Self {
+            pawns: HashMap::new(),
+            evals: HashMap::new(),
+            pawn_capacity: pawn_capacity.max(1),
+            eval_capacity: eval_capacity.max(1),
+            generation: 0,
+        }
+    }
+
+    /// Evaluate a position like [`evaluate_for_variant`], from the caches
+    /// when possible, counting hits and misses in `stats`.
+    pub fn evaluate(
+        &mut self,
+        board: &BoardState,
+        variant: Variant,
+        stats: &mut SearchStats,
+    ) -> i32 {
+        let generation = options_generation();
+        if self.generation != generation {
+            self.clear();
+            self.generation = generation;
+        }
+
+        let (hash, check) = hash_board_checked(board);
+        let key = (hash, variant);
+        if let Some(&(_, score)) = self.evals.get(&key).filter(|(stored, _)| *stored == check) {
+            stats.eval_cache_hits += 1;
+            return score;
+        }
+        stats.eval_cache_misses += 1;
+
+        let (pawn_key, pawn_check) = hash_pawns_checked(board);
+        let cached_pawns = self
+            .pawns
+            .get(&pawn_key)
+            .filter(|(stored, _)| *stored == pawn_check);
+        let pawns = match cached_pawns {
+            Some(&(_, pawns)) => {
+                stats.pawn_cache_hits += 1;
+                pawns
+            }
+            None => {
+                stats.pawn_cache_misses += 1;
+                let pawns = evaluate_pawns(board);
+                if self.pawns.len() >= self.pawn_capacity {
+                    self.pawns.clear();
+                }
+                self.pawns.insert(pawn_key, (pawn_check, pawns));
+                pawns
+            }
+        };
+
+        let material = material_with_pawns(board, pawns);
+        let score = variant_with_position(board, variant, position_with_material(board, material));
+        if self.evals.len() >= self.eval_capacity {
+            self.evals.clear();
+        }
+        self.evals.insert(key, (check, score));
+        score
+    }
+
+    pub fn clear(&mut self) {
+        self.pawns.clear();
+        self.evals.clear();
+    }
+
+    /// Entries in the pawn and evaluation caches.
+    pub fn sizes(&self) -> (usize, usize) {
+        (self.pawns.len(), self.evals.len())
+    }
+
+    /// Estimated bytes held by both caches.
+    pub fn memory_usage(&self) -> usize {
+        self.pawns.capacity() * (size_of::<(u64, (u64, i32))>() + 1)
+            + self.evals.capacity() * (size_of::<((u64, Variant), (u64, i32))>() + 1)
+    }
+}
+
+impl Default for EvalCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 // ============================================================================
 // Move Ordering
 // ============================================================================
@@ -295,7 +947,7 @@ pub fn estimate_move_value(mv: &Move) -> i32 {
     if let Some(captured) = &mv.captured {
         let victim_value = get_piece_value(captured.piece_type);
         let attacker_value = get_piece_value(mv.piece.piece_type);
-        score += 10000 + victim_value * 10 - attacker_value;
+        score += 10000 + victim_value * EngineOption::MvvLvaScale.get() - attacker_value;
     }
 
     // Promotions are very valuable
@@ -311,101 +963,400 @@ pub fn estimate_move_value(mv: &Move) -> i32 {
 
 /// Sort moves by estimated value (best first).
 pub fn order_moves(moves: &mut [Move]) {
-    moves.sort_by(|a, b| estimate_move_value(b).cmp(&estimate_move_value(a)));
+    moves.sort_by_key(|m| std::cmp::Reverse(estimate_move_value(m)));
+}
+
+/// Estimate move value for ordering under a variant's rules.
+/// In king of the hill and racing kings, king moves toward the goal are tried
+/// early and reaching it first of all.
+pub fn estimate_variant_move_value(mv: &Move, variant: Variant) -> i32 {
+    let score = estimate_move_value(mv);
+    match variant {
+        Variant::KingOfTheHill if mv.piece.piece_type == PieceType::King => {
+            if mv.to == KING_OF_THE_HILL_CENTER {
+                score + 50000
+            } else {
+                let gained = hex_distance(mv.from, KING_OF_THE_HILL_CENTER)
+                    - hex_distance(mv.to, KING_OF_THE_HILL_CENTER);
+                score + gained * KING_OF_THE_HILL_STEP_BONUS
+            }
+        }
+        Variant::RacingKings if mv.piece.piece_type == PieceType::King => {
+            if mv.to.r == RACING_KINGS_GOAL_ROW {
+                score + 50000
+            } else {
+                score + (mv.from.r - mv.to.r) * RACING_KINGS_STEP_BONUS
+            }
+        }
+        _ => score,
+    }
+}
+
+/// Sort moves by estimated value under a variant's rules (best first).
+pub fn order_moves_for_variant(moves: &mut [Move], variant: Variant) {
+    moves.sort_by_key(|m| std::cmp::Reverse(estimate_variant_move_value(m, variant)));
+}
+
+/// Highest killer and history bonus, kept below the capture and promotion
+/// bands so quiet moves never jump ahead of them.
+const MAX_HISTORY_BONUS: i32 = 8999;
+
+/// Sort moves for the search at `depth`, adding the killer, history and
+/// countermove bonuses of `tt` to the variant estimate (best first).
+fn order_search_moves(
+    moves: &mut [Move],
+    variant: Variant,
+    tt: &TranspositionTable,
+    depth: i32,
+    previous: Option<&Move>,
+) {
+    moves.sort_by_key(|m| {
+        std::cmp::Reverse(
+            estimate_variant_move_value(m, variant) + tt.ordering_bonus(m, depth, previous),
+        )
+    });
+}
+
+// ============================================================================
+// Static Exchange
+// ============================================================================
+
+/// King value in static exchanges: it may only end an exchange.
+const SEE_KING_VALUE: i32 = 10000;
+
+fn exchange_value(piece_type: PieceType) -> i32 {
+    match piece_type {
+        PieceType::King => SEE_KING_VALUE,
+        piece_type => get_piece_value(piece_type),
+    }
+}
+
+/// Static exchange evaluation of a move: the material (centipawns, mover's
+/// side) it wins or loses once both sides have recaptured on its
+/// destination with their least valuable attackers for as long as that
+/// pays. Pins are ignored. Negative for a move that gives up material.
+pub fn static_exchange(board: &BoardState, mv: &Move) -> i32 {
+    let mut board = apply_move(board, mv);
+    let target = mv.to.to_key();
+    let mut gains = vec![mv
+        .captured
+        .map_or(0, |piece| get_piece_value(piece.piece_type))];
+    let mut on_target = exchange_value(mv.promotion.unwrap_or(mv.piece.piece_type));
+    let mut side = mv.piece.color.opposite();
+    loop {
+        let attacker = occupied_cells(&board)
+            .filter(|(_, piece)| piece.color == side && !piece.is_boulder())
+            .filter(|(from, piece)| get_attacked_cells(&board, piece, *from).contains(&mv.to))
+            .min_by_key(|(_, piece)| exchange_value(piece.piece_type));
+        let Some((from, piece)) = attacker else {
+            break;
+        };
+        gains.push(on_target - gains.last().copied().unwrap_or(0));
+        on_target = exchange_value(piece.piece_type);
+        board.remove(&from.to_key());
+        board.insert(target.clone(), piece);
+        side = side.opposite();
+    }
+    // Each side stops recapturing when that would lose
+    gains
+        .into_iter()
+        .rev()
+        .reduce(|later, gain| -(-gain).max(later))
+        .unwrap_or(0)
+}
+
+// ============================================================================
+// Boulder Placement
+// ============================================================================
+
+/// Number of boulder placements searched per move in the main search.
+pub const BOULDER_PLACEMENT_CANDIDATES: usize = 4;
+
+/// Rank boulder placements for the side that just moved, most promising
+/// first: cells closest to the enemy king, restricting its escape squares.
+pub fn rank_boulder_placements(board: &BoardState, mover: Color) -> Vec<HexCoord> {
+    let mut placements = generate_boulder_placements(board);
+    if let Some(enemy_king) = find_king(board, mover.opposite()) {
+        placements.sort_by_key(|&cell| hex_distance(cell, enemy_king));
+    }
+    placements
+}
+
+/// Expand moves into move-plus-placement pairs for the boulder variant,
+/// keeping the `limit` best-ranked placements after each move.
+pub fn with_boulder_placements(board: &BoardState, moves: Vec<Move>, limit: usize) -> Vec<Move> {
+    moves
+        .into_iter()
+        .flat_map(|mv| {
+            let after = apply_move(board, &mv);
+            let placements = rank_boulder_placements(&after, mv.piece.color);
+            if placements.is_empty() {
+                return vec![mv];
+            }
+            placements
+                .into_iter()
+                .take(limit)
+                .map(|cell| mv.clone().with_boulder(cell))
+                .collect()
+        })
+        .collect()
+}
+
+/// Legal moves at a search node, with drops from the table's hands in drop
+/// variants.
+pub(crate) fn search_moves(
+    board: &BoardState,
+    color: Color,
+    variant: Variant,
+    tt: &TranspositionTable,
+) -> Vec<Move> {
+    if variant.has_drops() {
+        generate_all_legal_moves_with_drops(board, color, &tt.hands, variant.allows_drop_mate())
+    } else {
+        generate_all_legal_moves(board, color)
+    }
+}
+
+/// Restrict moves by a variant's rules, add optional piece promotions and, in
+/// the boulder variant, attach boulder placements to them.
+pub(crate) fn prepare_variant_moves(
+    board: &BoardState,
+    mut moves: Vec<Move>,
+    variant: Variant,
+    placements: usize,
+) -> Vec<Move> {
+    moves.retain(|mv| is_move_allowed(board, mv, variant));
+    if variant.has_piece_promotion() {
+        moves = with_piece_promotions(moves);
+    }
+    if variant == Variant::Boulder {
+        moves = with_boulder_placements(board, moves, placements);
+    }
+    moves
 }
 
 // ============================================================================
 // Quiescence Search
 // ============================================================================
 
-const MAX_QUIESCENCE_DEPTH: i32 = 8;
+/// Default number of quiescence plies (from the horizon) at which quiet
+/// checking moves are also searched (the `quiescenceCheckPlies` option).
+/// Kept small to bound the explosion of check sequences.
+pub const QUIESCENCE_CHECK_PLIES: i32 = 1;
 
 /// Check if a move is a capture or promotion (tactical move).
 pub fn is_tactical_move(mv: &Move) -> bool {
     mv.captured.is_some() || mv.promotion.is_some()
 }
 
-/// Generate only tactical moves (captures and promotions).
-pub fn generate_tactical_moves(board: &BoardState, color: Color) -> Vec<Move> {
-    generate_all_legal_moves(board, color)
-        .into_iter()
-        .filter(is_tactical_move)
-        .collect()
+/// Check if a move gives check to the opponent.
+pub fn gives_check(board: &BoardState, mv: &Move) -> bool {
+    let new_board = apply_move(board, mv);
+    is_in_check(&new_board, mv.piece.color.opposite())
+}
+
+/// Generate moves for quiescence search.
+///
+/// - When `color` is in check, all legal moves (evasions) are returned.
+/// - Otherwise captures and promotions are returned, plus quiet checking
+///   moves if `include_checks` is set.
+///
+/// Captures and promotions are ordered first, quiet checks after them.
+pub fn generate_tactical_moves(
+    board: &BoardState,
+    color: Color,
+    include_checks: bool,
+) -> Vec<Move> {
+    let all_moves = generate_all_legal_moves(board, color);
+
+    if is_in_check(board, color) {
+        let mut evasions = all_moves;
+        order_moves(&mut evasions);
+        return evasions;
+    }
+
+    let (mut tactical, quiet): (Vec<Move>, Vec<Move>) =
+        all_moves.into_iter().partition(is_tactical_move);
+    order_moves(&mut tactical);
+
+    if include_checks {
+        let mut checks: Vec<Move> = quiet
+            .into_iter()
+            .filter(|mv| gives_check(board, mv))
+            .collect();
+        order_moves(&mut checks);
+        tactical.extend(checks);
+    }
+
+    tactical
 }
 
 /// Quiescence search - extends search until position is "quiet".
+///
+/// Captures and promotions are searched at every ply, quiet checks during the
+/// first `quiescenceCheckPlies` plies, and all evasions when in check (no
+/// stand-pat is allowed while in check).
 pub fn quiescence_search(
+    board: &BoardState,
+    alpha: i32,
+    beta: i32,
+    maximizing: bool,
+    stats: &mut SearchStats,
+    q_depth: i32,
+    variant: Variant,
+) -> i32 {
+    quiescence(
+        board,
+        alpha,
+        beta,
+        maximizing,
+        stats,
+        q_depth,
+        variant,
+        WinConditions::default(),
+        &mut EvalCache::new(),
+    )
+}
+
+/// [`quiescence_search`] taking static evaluations from `cache`.
+#[allow(clippy::too_many_arguments)]
+fn quiescence(
     board: &BoardState,
     mut alpha: i32,
     mut beta: i32,
     maximizing: bool,
     stats: &mut SearchStats,
     q_depth: i32,
+    variant: Variant,
+    win_conditions: WinConditions,
+    cache: &mut EvalCache,
 ) -> i32 {
     stats.nodes_searched += 1;
     stats.quiescence_nodes += 1;
 
-    // Stand-pat score (evaluation if we don't make any tactical move)
-    let stand_pat = evaluate_position(board);
-
-    if maximizing {
-        if stand_pat >= beta {
-            return beta;
-        }
-        alpha = alpha.max(stand_pat);
-    } else {
-        if stand_pat <= alpha {
-            return alpha;
-        }
-        beta = beta.min(stand_pat);
+    let color = if maximizing {
+        Color::White
+    } else {
+        Color::Black
+    };
+
+    if let Some(outcome) = variant_outcome(board, variant, color)
+        .or_else(|| win_condition_outcome(board, win_conditions))
+    {
+        return variant_outcome_score(outcome, 0);
     }
 
+    let in_check = is_in_check(board, color);
+
+    // Stand-pat score (evaluation if we don't make any tactical move)
+    let stand_pat = cache.evaluate(board, variant, stats);
+
     // Stop if we've searched too deep in quiescence
-    if q_depth >= MAX_QUIESCENCE_DEPTH {
+    if q_depth >= EngineOption::QuiescenceDepth.get() {
         return stand_pat;
     }
 
-    let color = if maximizing {
-        Color::White
-    } else {
-        Color::Black
-    };
-    let mut tactical_moves = generate_tactical_moves(board, color);
+    if !in_check {
+        if maximizing {
+            if stand_pat >= beta {
+                return beta;
+            }
+            alpha = alpha.max(stand_pat);
+        } else {
+            if stand_pat <= alpha {
+                return alpha;
+            }
+            beta = beta.min(stand_pat);
+        }
+    }
+
+    let include_checks = q_depth < EngineOption::QuiescenceCheckPlies.get();
+    let tactical_moves = prepare_variant_moves(
+        board,
+        generate_tactical_moves(board, color, include_checks),
+        variant,
+        1,
+    );
 
-    // No tactical moves - position is quiet
     if tactical_moves.is_empty() {
+        if in_check {
+            // No evasions - checkmate
+            return if maximizing {
+                -CHECKMATE_VALUE
+            } else {
+                CHECKMATE_VALUE
+            };
+        }
+        // No tactical moves - position is quiet
         return stand_pat;
     }
 
-    order_moves(&mut tactical_moves);
-
     if maximizing {
+        let mut best = if in_check { -CHECKMATE_VALUE } else { alpha };
         for mv in &tactical_moves {
             let new_board = apply_move(board, mv);
-            let score = quiescence_search(&new_board, alpha, beta, false, stats, q_depth + 1);
+            let score = quiescence(
+                &new_board,
+                alpha,
+                beta,
+                false,
+                stats,
+                q_depth + 1,
+                variant,
+                win_conditions,
+                cache,
+            );
 
             if score >= beta {
                 stats.cutoffs += 1;
                 return beta;
             }
+            best = best.max(score);
             alpha = alpha.max(score);
         }
-        alpha
+        best
     } else {
+        let mut best = if in_check { CHECKMATE_VALUE } else { beta };
         for mv in &tactical_moves {
             let new_board = apply_move(board, mv);
-            let score = quiescence_search(&new_board, alpha, beta, true, stats, q_depth + 1);
+            let score = quiescence(
+                &new_board,
+                alpha,
+                beta,
+                true,
+                stats,
+                q_depth + 1,
+                variant,
+                win_conditions,
+                cache,
+            );
 
             if score <= alpha {
                 stats.cutoffs += 1;
                 return alpha;
             }
+            best = best.min(score);
             beta = beta.min(score);
         }
-        beta
+        best
     }
 }
 
+/// Evaluation of a position after resolving captures and checks with a
+/// quiescence search. Returns value from white's perspective in centipawns.
+pub fn evaluate_quiet(board: &BoardState, side_to_move: Color, variant: Variant) -> i32 {
+    let bound = CHECKMATE_VALUE + 1;
+    quiescence_search(
+        board,
+        -bound,
+        bound,
+        side_to_move == Color::White,
+        &mut SearchStats::default(),
+        0,
+        variant,
+    )
+}
+
 // ============================================================================
 // Alpha-Beta Search
 // ============================================================================
@@ -418,6 +1369,92 @@ pub struct SearchStats {
     pub max_depth_reached: i32,
     pub tt_hits: u64,
     pub quiescence_nodes: u64,
+    /// Repetitions scored as draws, and how many of those were perpetual checks
+    pub repetitions: u64,
+    pub perpetual_checks: u64,
+    /// Cutoffs in the main search (not quiescence), how many came from the
+    /// first move tried, and the sum of the cutoff moves' list positions
+    pub beta_cutoffs: u64,
+    pub first_move_cutoffs: u64,
+    pub cutoff_index_sum: u64,
+    /// Moves searched one ply deeper for being singular
+    pub singular_extensions: u64,
+    /// Main search cutoffs made by the countermove of the previous move
+    pub countermove_cutoffs: u64,
+    /// Static evaluations found in (or missing from) the evaluation cache,
+    /// and pawn evaluations found in (or missing from) the pawn hash table
+    pub eval_cache_hits: u64,
+    pub eval_cache_misses: u64,
+    pub pawn_cache_hits: u64,
+    pub pawn_cache_misses: u64,
+    /// Positions inside the search scored by a tablebase
+    pub tablebase_hits: u64,
+    /// Singular extensions on the current search path
+    extension_nesting: i32,
+}
+
+impl SearchStats {
+    /// Count a main search cutoff by the move at `index` in the move list.
+    fn record_cutoff(&mut self, index: usize) {
+        self.cutoffs += 1;
+        self.beta_cutoffs += 1;
+        if index == 0 {
+            self.first_move_cutoffs += 1;
+        }
+        self.cutoff_index_sum += index as u64;
+    }
+
+    /// Share of main search cutoffs made by the first move, or None if there
+    /// were none. Good move ordering keeps this above 0.9.
+    pub fn first_move_cutoff_rate(&self) -> Option<f64> {
+        (self.beta_cutoffs > 0).then(|| self.first_move_cutoffs as f64 / self.beta_cutoffs as f64)
+    }
+
+    /// Share of static evaluations served by the evaluation cache, or None
+    /// if there were none.
+    pub fn eval_cache_hit_rate(&self) -> Option<f64> {
+        let probes = self.eval_cache_hits + self.eval_cache_misses;
+        (probes > 0).then(|| self.eval_cache_hits as f64 / probes as f64)
+    }
+
+    /// Share of pawn evaluations served by the pawn hash table, or None if
+    /// there were none.
+    pub fn pawn_cache_hit_rate(&self) -> Option<f64> {
+        let probes = self.pawn_cache_hits + self.pawn_cache_misses;
+        (probes > 0).then(|| self.pawn_cache_hits as f64 / probes as f64)
+    }
+
+    /// Add another search's counters to these.
+    pub fn accumulate(&mut self, other: &SearchStats) {
+        self.nodes_searched += other.nodes_searched;
+        self.cutoffs += other.cutoffs;
+        self.tt_hits += other.tt_hits;
+        self.quiescence_nodes += other.quiescence_nodes;
+        self.repetitions += other.repetitions;
+        self.perpetual_checks += other.perpetual_checks;
+        self.beta_cutoffs += other.beta_cutoffs;
+        self.first_move_cutoffs += other.first_move_cutoffs;
+        self.cutoff_index_sum += other.cutoff_index_sum;
+        self.singular_extensions += other.singular_extensions;
+        self.countermove_cutoffs += other.countermove_cutoffs;
+        self.eval_cache_hits += other.eval_cache_hits;
+        self.eval_cache_misses += other.eval_cache_misses;
+        self.pawn_cache_hits += other.pawn_cache_hits;
+        self.pawn_cache_misses += other.pawn_cache_misses;
+        self.tablebase_hits += other.tablebase_hits;
+    }
+}
+
+/// What a search score measures.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ScoreKind {
+    /// The evaluation's estimate, in centipawns (or a mate found by search)
+    #[default]
+    Centipawns,
+    /// Exact, from a tablebase: a draw, or a win or loss with the mate
+    /// distance in plies below [`CHECKMATE_VALUE`]
+    Tablebase,
 }
 
 /// Search result containing best move and evaluation.
@@ -425,11 +1462,162 @@ pub struct SearchStats {
 pub struct SearchResult {
     pub best_move: Option<Move>,
     pub score: i32,
+    /// Whether the score is a tablebase's, reached along the best line
+    pub score_kind: ScoreKind,
     pub stats: SearchStats,
 }
 
+/// Most pieces, kings included, of a position the tablebases cover.
+const TABLEBASE_MAX_PIECES: usize = 5;
+
+/// A loaded tablebase's score of a position inside the search, from White's
+/// perspective. Only standard games with stalemates drawn and no optional
+/// win conditions are scored, as the tablebases assume those rules.
+fn tablebase_node_score(
+    board: &BoardState,
+    color: Color,
+    variant: Variant,
+    tt: &TranspositionTable,
+) -> Option<i32> {
+    if board.len() > TABLEBASE_MAX_PIECES
+        || variant != Variant::Standard
+        || tt.stalemate_rule != StalemateRule::Draw
+        || tt.win_conditions != WinConditions::default()
+    {
+        return None;
+    }
+    let score = get_tablebase_score(board, color)?;
+    Some(if color == Color::White { score } else { -score })
+}
+
+/// Whether a root score was backed by a tablebase: following the best moves
+/// stored in the table from the root leads to a tablebase position with that
+/// score.
+fn tablebase_backed(
+    board: &BoardState,
+    color: Color,
+    best_move: &Move,
+    score: i32,
+    depth: i32,
+    variant: Variant,
+    tt: &TranspositionTable,
+) -> bool {
+    let mut board = apply_move(board, best_move);
+    let mut color = color.opposite();
+    for _ in 1..depth.max(1) {
+        if let Some(tablebase_score) = tablebase_node_score(&board, color, variant, tt) {
+            return tablebase_score == score;
+        }
+        let Some(mv) = tt.probe(&board).and_then(|entry| entry.best_move.clone()) else {
+            return false;
+        };
+        board = apply_move(&board, &mv);
+        color = color.opposite();
+    }
+    tablebase_node_score(&board, color, variant, tt) == Some(score)
+}
+
+/// Shallowest depth at which singular extensions are tried.
+pub const SINGULAR_MIN_DEPTH: i32 = 3;
+
+/// Most singular extensions on one search path, so a line of singular moves
+/// cannot extend the search without end.
+const MAX_SINGULAR_NESTING: i32 = 2;
+
+/// Whether a node's TT entry makes its TT move worth checking for
+/// singularity: recent enough, not a mate score, and a bound on the right
+/// side for the side to move.
+fn singular_candidate(
+    entry: Option<&TTEntry>,
+    depth: i32,
+    maximizing: bool,
+    stats: &SearchStats,
+) -> bool {
+    let Some(entry) = entry else {
+        return false;
+    };
+    let bound_holds = match entry.entry_type {
+        TTEntryType::Exact => true,
+        TTEntryType::Lower => maximizing,
+        TTEntryType::Upper => !maximizing,
+    };
+    EngineOption::SingularExtensions.enabled()
+        && depth >= SINGULAR_MIN_DEPTH
+        && stats.extension_nesting < MAX_SINGULAR_NESTING
+        && entry.depth >= depth - 3
+        && entry.score.abs() < CHECKMATE_VALUE - 1000
+        && bound_holds
+}
+
+/// Verification search for a singular extension: whether every move after
+/// the first (the TT move) scores at least the singular margin worse than the
+/// TT entry's score, each searched to about half of `depth` with a null
+/// window.
+#[allow(clippy::too_many_arguments)]
+fn is_singular(
+    board: &BoardState,
+    moves: &[Move],
+    depth: i32,
+    maximizing: bool,
+    stats: &mut SearchStats,
+    tt: &mut TranspositionTable,
+    use_quiescence: bool,
+    history: &mut RepetitionStack,
+    variant: Variant,
+) -> bool {
+    let Some(tt_score) = tt.probe(board).map(|entry| entry.score) else {
+        return false;
+    };
+    let margin = EngineOption::SingularMargin.get();
+    let reduced = (depth - 1) / 2;
+    moves.len() > 1
+        && moves.iter().skip(1).all(|mv| {
+            let new_board = apply_move(board, mv);
+            let saved = tt.enter_hands(mv, variant);
+            let below = if maximizing {
+                let bound = tt_score - margin;
+                alpha_beta(
+                    &new_board,
+                    reduced,
+                    bound - 1,
+                    bound,
+                    false,
+                    stats,
+                    tt,
+                    use_quiescence,
+                    history,
+                    variant,
+                    Some(mv),
+                ) < bound
+            } else {
+                let bound = tt_score + margin;
+                alpha_beta(
+                    &new_board,
+                    reduced,
+                    bound,
+                    bound + 1,
+                    true,
+                    stats,
+                    tt,
+                    use_quiescence,
+                    history,
+                    variant,
+                    Some(mv),
+                ) > bound
+            };
+            tt.leave_hands(saved);
+            below
+        })
+}
+
 /// Alpha-beta search with pruning and transposition table.
-pub fn alpha_beta(
+///
+/// `history` holds the positions leading to this node (game positions plus the
+/// current search path). A position repeating one of them is scored as a draw,
+/// which lets the search seek perpetual check when losing and avoid it when winning.
+/// `previous` is the move that led here, whose countermove is tried early.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn alpha_beta(
     board: &BoardState,
     depth: i32,
     mut alpha: i32,
@@ -438,10 +1626,18 @@ pub fn alpha_beta(
     stats: &mut SearchStats,
     tt: &mut TranspositionTable,
     use_quiescence: bool,
+    history: &mut RepetitionStack,
+    variant: Variant,
+    previous: Option<&Move>,
 ) -> i32 {
+    // A cancelled search's scores are discarded, so any value will do
+    if tt.is_cancelled() {
+        return 0;
+    }
     stats.nodes_searched += 1;
 
     let original_alpha = alpha;
+    let original_beta = beta;
     let color = if maximizing {
         Color::White
     } else {
@@ -449,8 +1645,42 @@ pub fn alpha_beta(
     };
     let in_check = is_in_check(board, color);
 
+    // Repetition detection (must precede the TT probe)
+    let record = PositionRecord {
+        key: get_position_key(board, color),
+        hash: hash_position(board, color),
+        side_to_move: color,
+        in_check,
+    };
+    if history.occurrences(&record) > 0 {
+        stats.repetitions += 1;
+        if history.perpetual_check(&record).is_some() {
+            stats.perpetual_checks += 1;
+        }
+        return draw_score(tt.draw_winner, depth);
+    }
+
+    // Variant end conditions finish the game before any move is made
+    if let Some(outcome) = variant_outcome(board, variant, color)
+        .or_else(|| win_condition_outcome(board, tt.win_conditions))
+    {
+        return match outcome {
+            VariantOutcome::Draw(_) => draw_score(tt.draw_winner, depth),
+            _ => variant_outcome_score(outcome, depth),
+        };
+    }
+
+    // Positions a loaded tablebase covers have exact scores
+    if let Some(score) = tablebase_node_score(board, color, variant, tt) {
+        stats.tablebase_hits += 1;
+        return score;
+    }
+
     // Probe transposition table
-    if let Some(tt_entry) = tt.probe(board) {
+    if let Some(tt_entry) = tt
+        .probe(board)
+        .filter(|_| EngineOption::TTCutoffs.enabled())
+    {
         if tt_entry.depth >= depth {
             stats.tt_hits += 1;
             match tt_entry.entry_type {
@@ -465,7 +1695,12 @@ pub fn alpha_beta(
         }
     }
 
-    let mut moves = generate_all_legal_moves(board, color);
+    let mut moves = prepare_variant_moves(
+        board,
+        search_moves(board, color, variant, tt),
+        variant,
+        BOULDER_PLACEMENT_CANDIDATES,
+    );
 
     // Terminal node checks
     if moves.is_empty() {
@@ -476,23 +1711,39 @@ pub fn alpha_beta(
             } else {
                 CHECKMATE_VALUE - depth
             };
+        } else if tt.stalemate_rule.stalemater_quarters() == 2 {
+            // Stalemate, drawn by the game's rule
+            return draw_score(tt.draw_winner, depth);
         } else {
-            // Stalemate
-            return STALEMATE_VALUE;
+            // Stalemate, scored by the game's rule
+            return stalemate_score(tt.stalemate_rule, color, depth);
         }
     }
 
-    // Leaf node
+    // Leaf node: the board's evaluation plus the material in hand, which
+    // quiescence leaves as it is
     if depth == 0 {
-        if use_quiescence {
-            return quiescence_search(board, alpha, beta, maximizing, stats, 0);
+        let in_hand = tt.hand_balance();
+        if use_quiescence && EngineOption::Quiescence.enabled() {
+            return quiescence(
+                board,
+                alpha - in_hand,
+                beta - in_hand,
+                maximizing,
+                stats,
+                0,
+                variant,
+                tt.win_conditions,
+                &mut tt.eval_cache,
+            ) + in_hand;
         }
-        return evaluate_position(board);
+        return tt.eval_cache.evaluate(board, variant, stats) + in_hand;
     }
 
     // Order moves for better pruning
     // Check if TT has a best move to try first
     let tt_best_move = tt.probe(board).and_then(|e| e.best_move.clone());
+    let mut tt_move_first = false;
 
     if let Some(ref best_move) = tt_best_move {
         // Put TT best move first
@@ -501,29 +1752,60 @@ pub fn alpha_beta(
             .position(|m| m.from == best_move.from && m.to == best_move.to);
         if let Some(idx) = best_idx {
             moves.swap(0, idx);
+            tt_move_first = true;
+        }
+        if let Some(rest) = moves.get_mut(1..) {
+            order_search_moves(rest, variant, tt, depth, previous); // Order the rest
         }
-        order_moves(&mut moves[1..]); // Order the rest
     } else {
-        order_moves(&mut moves);
+        order_search_moves(&mut moves, variant, tt, depth, previous);
     }
 
     let mut best_move: Option<Move> = None;
+    history.push(record);
+
+    let extend_first = tt_move_first
+        && singular_candidate(tt.probe(board), depth, maximizing, stats)
+        && is_singular(
+            board,
+            &moves,
+            depth,
+            maximizing,
+            stats,
+            tt,
+            use_quiescence,
+            history,
+            variant,
+        );
 
     if maximizing {
         let mut max_eval = -CHECKMATE_VALUE - 1;
 
-        for mv in &moves {
+        for (index, mv) in moves.iter().enumerate() {
             let new_board = apply_move(board, mv);
+            let extended = index == 0 && extend_first;
+            if extended {
+                stats.singular_extensions += 1;
+                stats.extension_nesting += 1;
+            }
+            let saved = tt.enter_hands(mv, variant);
             let eval_score = alpha_beta(
                 &new_board,
-                depth - 1,
+                if extended { depth } else { depth - 1 },
                 alpha,
                 beta,
                 false,
                 stats,
                 tt,
                 use_quiescence,
+                history,
+                variant,
+                Some(mv),
             );
+            tt.leave_hands(saved);
+            if extended {
+                stats.extension_nesting -= 1;
+            }
 
             if eval_score > max_eval {
                 max_eval = eval_score;
@@ -532,8 +1814,16 @@ pub fn alpha_beta(
 
             alpha = alpha.max(eval_score);
 
+            if tt.is_cancelled() {
+                history.pop();
+                return max_eval;
+            }
             if beta <= alpha {
-                stats.cutoffs += 1;
+                stats.record_cutoff(index);
+                if tt.is_countermove(mv, previous) {
+                    stats.countermove_cutoffs += 1;
+                }
+                tt.record_cutoff(mv, depth, previous);
                 break;
             }
         }
@@ -547,23 +1837,37 @@ pub fn alpha_beta(
             TTEntryType::Exact
         };
         tt.store(board, depth, max_eval, tt_type, best_move);
+        history.pop();
 
         max_eval
     } else {
         let mut min_eval = CHECKMATE_VALUE + 1;
 
-        for mv in &moves {
+        for (index, mv) in moves.iter().enumerate() {
             let new_board = apply_move(board, mv);
+            let extended = index == 0 && extend_first;
+            if extended {
+                stats.singular_extensions += 1;
+                stats.extension_nesting += 1;
+            }
+            let saved = tt.enter_hands(mv, variant);
             let eval_score = alpha_beta(
                 &new_board,
-                depth - 1,
+                if extended { depth } else { depth - 1 },
                 alpha,
                 beta,
                 true,
                 stats,
                 tt,
                 use_quiescence,
+                history,
+                variant,
+                Some(mv),
             );
+            tt.leave_hands(saved);
+            if extended {
+                stats.extension_nesting -= 1;
+            }
 
             if eval_score < min_eval {
                 min_eval = eval_score;
@@ -572,14 +1876,22 @@ pub fn alpha_beta(
 
             beta = beta.min(eval_score);
 
+            if tt.is_cancelled() {
+                history.pop();
+                return min_eval;
+            }
             if beta <= alpha {
-                stats.cutoffs += 1;
+                stats.record_cutoff(index);
+                if tt.is_countermove(mv, previous) {
+                    stats.countermove_cutoffs += 1;
+                }
+                tt.record_cutoff(mv, depth, previous);
                 break;
             }
         }
 
         // Store in TT
-        let tt_type = if min_eval >= beta {
+        let tt_type = if min_eval >= original_beta {
             TTEntryType::Lower
         } else if min_eval <= original_alpha {
             TTEntryType::Upper
@@ -587,6 +1899,7 @@ pub fn alpha_beta(
             TTEntryType::Exact
         };
         tt.store(board, depth, min_eval, tt_type, best_move);
+        history.pop();
 
         min_eval
     }
@@ -599,21 +1912,91 @@ pub fn find_best_move(
     depth: i32,
     tt: &mut TranspositionTable,
     use_quiescence: bool,
+) -> SearchResult {
+    find_best_move_with_history(board, color, depth, tt, use_quiescence, &[])
+}
+
+/// Find the best move, treating repetitions of `history` (the game's earlier
+/// positions) as draws.
+pub fn find_best_move_with_history(
+    board: &BoardState,
+    color: Color,
+    depth: i32,
+    tt: &mut TranspositionTable,
+    use_quiescence: bool,
+    history: &[PositionRecord],
+) -> SearchResult {
+    find_best_move_for_variant(
+        board,
+        color,
+        depth,
+        tt,
+        use_quiescence,
+        history,
+        Variant::Standard,
+    )
+}
+
+/// Find the best move under a variant's rules, treating repetitions of
+/// `history` as draws.
+pub fn find_best_move_for_variant(
+    board: &BoardState,
+    color: Color,
+    depth: i32,
+    tt: &mut TranspositionTable,
+    use_quiescence: bool,
+    history: &[PositionRecord],
+    variant: Variant,
+) -> SearchResult {
+    search_root(
+        board,
+        color,
+        depth,
+        tt,
+        use_quiescence,
+        history,
+        variant,
+        &[],
+    )
+}
+
+/// Whether `listed` names `mv`: the same move and promotion, and the same
+/// boulder placement if `listed` gives one.
+fn names_move(listed: &Move, mv: &Move) -> bool {
+    listed.from == mv.from
+        && listed.to == mv.to
+        && listed.is_drop == mv.is_drop
+        && (!mv.is_drop || listed.piece.piece_type == mv.piece.piece_type)
+        && listed.promotion == mv.promotion
+        && listed.boulder.is_none_or(|cell| mv.boulder == Some(cell))
+}
+
+/// [`find_best_move_for_variant`] over the root moves not named in
+/// `excluded`. The root is only stored in the table when nothing is
+/// excluded, as its score is otherwise not the position's.
+#[allow(clippy::too_many_arguments)]
+fn search_root(
+    board: &BoardState,
+    color: Color,
+    depth: i32,
+    tt: &mut TranspositionTable,
+    use_quiescence: bool,
+    history: &[PositionRecord],
+    variant: Variant,
+    excluded: &[Move],
 ) -> SearchResult {
     let mut stats = SearchStats {
         max_depth_reached: depth,
         ..Default::default()
     };
 
-    let mut moves = generate_all_legal_moves(board, color);
-
-    if moves.is_empty() {
-        return SearchResult {
-            best_move: None,
-            score: 0,
-            stats,
-        };
-    }
+    let mut moves = prepare_variant_moves(
+        board,
+        search_moves(board, color, variant, tt),
+        variant,
+        BOULDER_PLACEMENT_CANDIDATES,
+    );
+    moves.retain(|mv| !excluded.iter().any(|listed| names_move(listed, mv)));
 
     let maximizing = color == Color::White;
 
@@ -626,15 +2009,24 @@ pub fn find_best_move(
             if let Some(idx) = best_idx {
                 moves.swap(0, idx);
             }
-            order_moves(&mut moves[1..]);
+            if let Some(rest) = moves.get_mut(1..) {
+                order_moves_for_variant(rest, variant);
+            }
         } else {
-            order_moves(&mut moves);
+            order_moves_for_variant(&mut moves, variant);
         }
     } else {
-        order_moves(&mut moves);
+        order_moves_for_variant(&mut moves, variant);
     }
 
-    let mut best_move = moves[0].clone();
+    let Some(mut best_move) = moves.first().cloned() else {
+        return SearchResult {
+            best_move: None,
+            score: 0,
+            score_kind: ScoreKind::Centipawns,
+            stats,
+        };
+    };
     let mut best_score = if maximizing {
         -CHECKMATE_VALUE - 1
     } else {
@@ -643,8 +2035,11 @@ pub fn find_best_move(
     let mut alpha = -CHECKMATE_VALUE - 1;
     let mut beta = CHECKMATE_VALUE + 1;
 
+    let mut path = RepetitionStack::from_root(history, create_position_record(board, color));
+
     for mv in &moves {
         let new_board = apply_move(board, mv);
+        let saved = tt.enter_hands(mv, variant);
         let eval_score = alpha_beta(
             &new_board,
             depth - 1,
@@ -654,7 +2049,11 @@ pub fn find_best_move(
             &mut stats,
             tt,
             use_quiescence,
+            &mut path,
+            variant,
+            Some(mv),
         );
+        tt.leave_hands(saved);
 
         if maximizing {
             if eval_score > best_score {
@@ -669,25 +2068,40 @@ pub fn find_best_move(
             }
             beta = beta.min(eval_score);
         }
+        if tt.is_cancelled() {
+            break;
+        }
     }
 
-    // Store in TT
-    tt.store(
-        board,
-        depth,
-        best_score,
-        TTEntryType::Exact,
-        Some(best_move.clone()),
-    );
+    // Store in TT, unless the search was cut short
+    if excluded.is_empty() && !tt.is_cancelled() {
+        tt.store(
+            board,
+            depth,
+            best_score,
+            TTEntryType::Exact,
+            Some(best_move.clone()),
+        );
+    }
 
+    let backed = stats.tablebase_hits > 0
+        && tablebase_backed(board, color, &best_move, best_score, depth, variant, tt);
     SearchResult {
         best_move: Some(best_move),
         score: best_score,
+        score_kind: if backed {
+            ScoreKind::Tablebase
+        } else {
+            ScoreKind::Centipawns
+        },
         stats,
     }
 }
 
-/// Find best move using iterative deepening.
+/// Find best move using iterative deepening. A search cancelled through
+/// the table's [`CancelToken`] returns the deepest iteration it finished;
+/// depth 1 always finishes, so that there is a move to play.
+#[allow(clippy::too_many_arguments)]
 pub fn find_best_move_iterative(
     board: &BoardState,
     color: Color,
@@ -695,119 +2109,973 @@ pub fn find_best_move_iterative(
     time_limit_ms: u64,
     tt: &mut TranspositionTable,
     use_quiescence: bool,
+    history: &[PositionRecord],
+    variant: Variant,
 ) -> SearchResult {
     use std::time::Instant;
 
     let start_time = Instant::now();
 
     // Track accumulated stats
-    let mut total_nodes = 0u64;
-    let mut total_cutoffs = 0u64;
-    let mut total_tt_hits = 0u64;
-    let mut total_q_nodes = 0u64;
+    let mut totals = SearchStats::default();
 
-    // Get initial move quickly at depth 1
-    let initial_result = find_best_move(board, color, 1, tt, use_quiescence);
+    // Get initial move quickly at depth 1, whether cancelled or not
+    let cancel = std::mem::take(&mut tt.cancel);
+    let initial_result =
+        find_best_move_for_variant(board, color, 1, tt, use_quiescence, history, variant);
+    tt.cancel = cancel;
     let mut best_result = initial_result.clone();
-    total_nodes += initial_result.stats.nodes_searched;
-    total_cutoffs += initial_result.stats.cutoffs;
-    total_tt_hits += initial_result.stats.tt_hits;
-    total_q_nodes += initial_result.stats.quiescence_nodes;
+    totals.accumulate(&initial_result.stats);
 
     for depth in 2..=max_depth {
         let elapsed = start_time.elapsed().as_millis() as u64;
         if elapsed > time_limit_ms {
+            trace_event!(depth, elapsed, "time limit reached");
+            break;
+        }
+        if tt.is_cancelled() {
+            trace_event!(depth, "search cancelled");
             break;
         }
 
-        let result = find_best_move(board, color, depth, tt, use_quiescence);
+        trace_span!("search_iteration", depth);
+        let result =
+            find_best_move_for_variant(board, color, depth, tt, use_quiescence, history, variant);
+        trace_event!(
+            score = result.score,
+            nodes = result.stats.nodes_searched,
+            best_move = ?result.best_move.as_ref().map(|m| (m.from.to_key(), m.to.to_key()))
+        );
+
+        totals.accumulate(&result.stats);
 
+        // An unfinished iteration's move may not have been searched properly
+        if tt.is_cancelled() {
+            trace_event!(depth, "search cancelled");
+            break;
+        }
         if result.best_move.is_some() {
-            best_result = result.clone();
+            best_result = result;
             best_result.stats.max_depth_reached = depth;
         }
-
-        total_nodes += result.stats.nodes_searched;
-        total_cutoffs += result.stats.cutoffs;
-        total_tt_hits += result.stats.tt_hits;
-        total_q_nodes += result.stats.quiescence_nodes;
     }
 
     // Update accumulated stats
-    best_result.stats.nodes_searched = total_nodes;
-    best_result.stats.cutoffs = total_cutoffs;
-    best_result.stats.tt_hits = total_tt_hits;
-    best_result.stats.quiescence_nodes = total_q_nodes;
+    totals.max_depth_reached = best_result.stats.max_depth_reached;
+    best_result.stats = totals;
 
     best_result
 }
 
-// ============================================================================
-// AI Difficulty Levels
-// ============================================================================
-
-/// AI difficulty level.
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub enum AIDifficulty {
-    Easy,
-    Medium,
-    Hard,
+/// Iterative deepening run one iteration at a time, for hosts that cannot
+/// block or be interrupted while a search runs (a page's main thread): the
+/// host steps it and may cancel it between steps, keeping the deepest
+/// iteration finished.
+pub struct IterativeSearch {
+    state: GameState,
+    tt: TranspositionTable,
+    max_depth: i32,
+    /// Deepest iteration finished
+    depth: i32,
+    result: Option<SearchResult>,
+    totals: SearchStats,
 }
 
-/// Get AI move based on difficulty level.
-/// First probes tablebase for endgame positions, then falls back to search.
-pub fn get_ai_move(
-    board: &BoardState,
-    color: Color,
-    difficulty: AIDifficulty,
-    tt: &mut TranspositionTable,
-) -> SearchResult {
-    // Try tablebase probe first for endgame positions
-    if detect_configuration(board).is_some() {
-        let probe_result = probe_tablebase(board, color);
-        if probe_result.found {
-            if let Some(entry) = &probe_result.entry {
-                if let Some(best_move) = &entry.best_move {
-                    // Get the piece at the source coordinate
-                    let from_coord = HexCoord::new(best_move.from_q, best_move.from_r);
-                    let to_coord = HexCoord::new(best_move.to_q, best_move.to_r);
-                    let from_key = format!("{},{}", from_coord.q, from_coord.r);
+impl IterativeSearch {
+    /// Start a search of a game position to `max_depth` with a table of
+    /// `tt_size` entries.
+    pub fn new(state: &GameState, max_depth: i32, tt_size: usize) -> Self {
+        let mut tt = TranspositionTable::new(tt_size);
+        tt.set_game_rules(state);
+        Self {
+            state: state.clone(),
+            tt,
+            max_depth: max_depth.max(1),
+            depth: 0,
+            result: None,
+            totals: SearchStats::default(),
+        }
+    }
 
-                    if let Some(piece) = board.get(&from_key) {
-                        let to_key = format!("{},{}", to_coord.q, to_coord.r);
-                        let captured = board.get(&to_key).cloned();
+    /// Token that stops the search. Depth 1 is searched even when cancelled,
+    /// so that there is a move to play.
+    pub fn cancel_token(&self) -> CancelToken {
+        self.tt.cancel_token()
+    }
 
-                        let mv = Move {
-                            from: from_coord,
-                            to: to_coord,
-                            piece: piece.clone(),
-                            captured,
-                            promotion: best_move.promotion,
-                        };
+    pub fn is_cancelled(&self) -> bool {
+        self.tt.is_cancelled()
+    }
 
-                        let score = get_tablebase_score(board, color).unwrap_or(0);
+    /// Whether every iteration is searched, the search was cancelled after
+    /// depth 1, or the position has no moves.
+    pub fn is_done(&self) -> bool {
+        self.depth >= self.max_depth
+            || (self.depth > 0 && self.is_cancelled())
+            || self
+                .result
+                .as_ref()
+                .is_some_and(|result| result.best_move.is_none())
+    }
 
-                        return SearchResult {
-                            best_move: Some(mv),
-                            score,
-                            stats: SearchStats::default(),
-                        };
-                    }
-                }
-            }
+    /// Deepest iteration finished.
+    pub fn depth(&self) -> i32 {
+        self.depth
+    }
+
+    /// Search the next iteration. Returns true once the search is done.
+    pub fn step(&mut self) -> bool {
+        if self.is_done() {
+            return true;
+        }
+        let depth = self.depth + 1;
+        let cancel = (depth == 1).then(|| std::mem::take(&mut self.tt.cancel));
+        let result = find_best_move_for_variant(
+            &self.state.board,
+            self.state.turn,
+            depth,
+            &mut self.tt,
+            true,
+            &self.state.position_history,
+            self.state.variant,
+        );
+        if let Some(cancel) = cancel {
+            self.tt.cancel = cancel;
         }
+        self.totals.accumulate(&result.stats);
+        // An unfinished iteration is dropped, like in find_best_move_iterative
+        if depth == 1 || !self.is_cancelled() {
+            self.depth = depth;
+            self.result = Some(result);
+        }
+        self.is_done()
+    }
+
+    /// The deepest iteration's result, with the counters of every
+    /// iteration. None before the first step.
+    pub fn result(&self) -> Option<SearchResult> {
+        let mut result = self.result.clone()?;
+        result.stats = SearchStats {
+            max_depth_reached: self.depth,
+            ..self.totals.clone()
+        };
+        Some(result)
     }
+}
 
-    // Fall back to regular search
-    match difficulty {
-        AIDifficulty::Easy => find_best_move(board, color, 2, tt, false),
-        AIDifficulty::Medium => find_best_move(board, color, 4, tt, true),
-        AIDifficulty::Hard => find_best_move_iterative(board, color, 6, 5000, tt, true),
+/// Entries of the transposition table an [`analyze_excluding`] search gets.
+const EXCLUSION_TT_SIZE: usize = 100_000;
+
+/// Find the best move of a game position other than the `excluded` ones (a
+/// listed move without a boulder excludes every placement after it), by
+/// iterative deepening to `depth`. Comparing the score with the full
+/// search's shows how much worse the alternatives are: an only move, or a
+/// puzzle with a second solution. The best move is None if every move is
+/// excluded.
+pub fn analyze_excluding(state: &GameState, excluded: &[Move], depth: i32) -> SearchResult {
+    let mut tt = TranspositionTable::new(EXCLUSION_TT_SIZE);
+    tt.set_game_rules(state);
+    let mut totals = SearchStats::default();
+    let mut result = SearchResult {
+        best_move: None,
+        score: 0,
+        score_kind: ScoreKind::Centipawns,
+        stats: SearchStats::default(),
+    };
+    for iteration in 1..=depth.max(1) {
+        result = search_root(
+            &state.board,
+            state.turn,
+            iteration,
+            &mut tt,
+            true,
+            &state.position_history,
+            state.variant,
+            excluded,
+        );
+        totals.accumulate(&result.stats);
     }
+    totals.max_depth_reached = result.stats.max_depth_reached;
+    result.stats = totals;
+    result
 }
 
 // ============================================================================
-// Tests
+// AI Difficulty Levels
+// ============================================================================
+
+/// AI difficulty level.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AIDifficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl AIDifficulty {
+    /// Search depth of the level (Hard may stop short on its time limit).
+    pub fn search_depth(&self) -> i32 {
+        match self {
+            AIDifficulty::Easy => 2,
+            AIDifficulty::Medium => 3,
+            AIDifficulty::Hard => 6,
+        }
+    }
+
+    /// Skill of the level, compared with [`WEAK_PLAY_SKILL_THRESHOLD`].
+    pub fn skill(&self) -> u8 {
+        match self {
+            AIDifficulty::Easy => 1,
+            AIDifficulty::Medium => 2,
+            AIDifficulty::Hard => 3,
+        }
+    }
+}
+
+// ============================================================================
+// Calibration
+// ============================================================================
+
+/// Time limit of the hard level's iterative deepening without calibration.
+pub const HARD_TIME_LIMIT_MS: u64 = 5000;
+
+/// Milliseconds a move should take at each level (easy, medium, hard) on a
+/// calibrated device.
+pub const TARGET_MOVE_MS: [u64; 3] = [250, 1_000, 3_000];
+
+/// Node growth per ply assumed when calibration only reached depth 1.
+const DEFAULT_BRANCHING_FACTOR: f64 = 6.0;
+
+/// Deepest search calibration runs.
+const MAX_CALIBRATION_DEPTH: i32 = 8;
+
+/// Entries of the table calibration searches with.
+const CALIBRATION_TT_SIZE: usize = 50_000;
+
+/// Search limits of a level on a calibrated device.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchBudget {
+    pub depth: i32,
+    /// Time limit of iterative deepening (the hard level)
+    pub time_limit_ms: u64,
+}
+
+/// Measured search speed of a device, and the levels' limits on it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Calibration {
+    pub nodes_per_second: u64,
+    /// Nodes of a depth 1 search of the starting position
+    pub base_nodes: u64,
+    /// Average growth of the node count per extra ply
+    pub branching_factor: f64,
+    pub easy: SearchBudget,
+    pub medium: SearchBudget,
+    pub hard: SearchBudget,
+}
+
+impl Calibration {
+    /// Limits for a measured speed: each level gets the deepest search
+    /// (never deeper than its usual depth) expected to finish within its
+    /// [`TARGET_MOVE_MS`].
+    pub fn from_measurement(nodes_per_second: u64, base_nodes: u64, branching_factor: f64) -> Self {
+        let budget = |difficulty: AIDifficulty, target_ms: u64| {
+            let affordable = nodes_per_second as f64 * target_ms as f64 / 1000.0;
+            let mut depth = 1;
+            let mut nodes = base_nodes.max(1) as f64 * branching_factor;
+            while depth < difficulty.search_depth() && nodes <= affordable {
+                depth += 1;
+                nodes *= branching_factor;
+            }
+            SearchBudget {
+                depth,
+                time_limit_ms: target_ms,
+            }
+        };
+        Self {
+            nodes_per_second,
+            base_nodes,
+            branching_factor,
+            easy: budget(AIDifficulty::Easy, TARGET_MOVE_MS[0]),
+            medium: budget(AIDifficulty::Medium, TARGET_MOVE_MS[1]),
+            hard: budget(AIDifficulty::Hard, TARGET_MOVE_MS[2]),
+        }
+    }
+
+    pub fn budget(&self, difficulty: AIDifficulty) -> SearchBudget {
+        match difficulty {
+            AIDifficulty::Easy => self.easy,
+            AIDifficulty::Medium => self.medium,
+            AIDifficulty::Hard => self.hard,
+        }
+    }
+}
+
+/// Measure this device's search speed for about `ms_budget` milliseconds,
+/// searching the starting position one ply deeper at a time while the next
+/// ply is expected to fit.
+pub fn calibrate(ms_budget: u64) -> Calibration {
+    let start = create_new_game();
+    let mut tt = TranspositionTable::new(CALIBRATION_TT_SIZE);
+    let started = chrono::Utc::now().timestamp_millis();
+    let elapsed = || (chrono::Utc::now().timestamp_millis() - started).max(0) as u64;
+
+    let mut node_counts: Vec<u64> = Vec::new();
+    let mut total_nodes = 0;
+    let mut last_ms = 0;
+    for depth in 1..=MAX_CALIBRATION_DEPTH {
+        let before = elapsed();
+        let result = find_best_move_for_variant(
+            &start.board,
+            start.turn,
+            depth,
+            &mut tt,
+            true,
+            &[],
+            Variant::Standard,
+        );
+        node_counts.push(result.stats.nodes_searched.max(1));
+        total_nodes += result.stats.nodes_searched;
+        last_ms = elapsed() - before;
+        let growth = branching_factor(&node_counts);
+        if elapsed() as f64 + last_ms.max(1) as f64 * growth > ms_budget as f64 {
+            break;
+        }
+    }
+
+    let nodes_per_second = total_nodes * 1000 / elapsed().max(last_ms).max(1);
+    Calibration::from_measurement(
+        nodes_per_second,
+        node_counts.first().copied().unwrap_or(1),
+        branching_factor(&node_counts),
+    )
+}
+
+/// Geometric mean growth of successive node counts.
+fn branching_factor(node_counts: &[u64]) -> f64 {
+    match (node_counts.first(), node_counts.last()) {
+        (Some(&first), Some(&last)) if node_counts.len() > 1 => {
+            let ratio = last as f64 / first as f64;
+            ratio.powf(1.0 / (node_counts.len() - 1) as f64).max(1.5)
+        }
+        _ => DEFAULT_BRANCHING_FACTOR,
+    }
+}
+
+// ============================================================================
+// Weak Play
+// ============================================================================
+
+/// Levels with a skill below this play with evaluation noise and scheduled
+/// mistakes rather than only a shallower search.
+pub const WEAK_PLAY_SKILL_THRESHOLD: u8 = 3;
+
+/// How a weak level misjudges positions and picks worse moves.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WeakPlaySettings {
+    /// Standard deviation, in centipawns, of the Gaussian noise added to each
+    /// move's score
+    pub noise_sigma: f64,
+    /// Score gap, in centipawns, at which a move is e times less likely to be
+    /// picked than the best one
+    pub mistake_scale: f64,
+}
+
+impl WeakPlaySettings {
+    /// Settings of a level below [`WEAK_PLAY_SKILL_THRESHOLD`], None for
+    /// stronger levels.
+    pub fn for_difficulty(difficulty: AIDifficulty) -> Option<Self> {
+        match difficulty.skill() {
+            skill if skill >= WEAK_PLAY_SKILL_THRESHOLD => None,
+            1 => Some(Self {
+                noise_sigma: 80.0,
+                mistake_scale: 120.0,
+            }),
+            _ => Some(Self {
+                noise_sigma: 30.0,
+                mistake_scale: 40.0,
+            }),
+        }
+    }
+}
+
+/// Pick an index of `scores` (from the mover's side): the k-th best is chosen
+/// with a weight falling exponentially with its gap to the best score.
+pub fn schedule_mistake(scores: &[f64], mistake_scale: f64, rng: &mut SeededRng) -> Option<usize> {
+    let best = scores.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let weights: Vec<f64> = scores
+        .iter()
+        .map(|score| (-(best - score) / mistake_scale.max(1.0)).exp())
+        .collect();
+    let mut roll = rng.next_f64() * weights.iter().sum::<f64>();
+    for (index, weight) in weights.iter().enumerate() {
+        if roll < *weight {
+            return Some(index);
+        }
+        roll -= weight;
+    }
+    scores.len().checked_sub(1)
+}
+
+/// Search every move to `depth`, add Gaussian noise to the scores and pick
+/// one with [`schedule_mistake`]. The result's score is the chosen move's
+/// score without noise.
+#[allow(clippy::too_many_arguments)]
+pub fn find_weak_move(
+    board: &BoardState,
+    color: Color,
+    depth: i32,
+    use_quiescence: bool,
+    settings: &WeakPlaySettings,
+    seed: u64,
+    tt: &mut TranspositionTable,
+    history: &[PositionRecord],
+    variant: Variant,
+) -> SearchResult {
+    let mut stats = SearchStats {
+        max_depth_reached: depth,
+        ..Default::default()
+    };
+    let mut moves = prepare_variant_moves(
+        board,
+        search_moves(board, color, variant, tt),
+        variant,
+        BOULDER_PLACEMENT_CANDIDATES,
+    );
+    // A fixed order makes seeded choices repeatable
+    moves.sort_by_key(|mv| (mv.from.q, mv.from.r, mv.to.q, mv.to.r));
+
+    let mut path = RepetitionStack::from_root(history, create_position_record(board, color));
+
+    let maximizing = color == Color::White;
+    let scores: Vec<i32> = moves
+        .iter()
+        .map(|mv| {
+            let saved = tt.enter_hands(mv, variant);
+            let score = alpha_beta(
+                &apply_move(board, mv),
+                depth - 1,
+                -CHECKMATE_VALUE - 1,
+                CHECKMATE_VALUE + 1,
+                !maximizing,
+                &mut stats,
+                tt,
+                use_quiescence,
+                &mut path,
+                variant,
+                Some(mv),
+            );
+            tt.leave_hands(saved);
+            score
+        })
+        .collect();
+
+    let mut rng = SeededRng::new(seed);
+    let sign = if maximizing { 1.0 } else { -1.0 };
+    let noisy: Vec<f64> = scores
+        .iter()
+        .map(|&score| sign * f64::from(score) + settings.noise_sigma * rng.next_gaussian())
+        .collect();
+    let chosen = schedule_mistake(&noisy, settings.mistake_scale, &mut rng);
+
+    SearchResult {
+        best_move: chosen.and_then(|index| moves.get(index).cloned()),
+        score: chosen
+            .and_then(|index| scores.get(index).copied())
+            .unwrap_or(0),
+        score_kind: ScoreKind::Centipawns,
+        stats,
+    }
+}
+
+/// Get AI move based on difficulty level.
+/// First probes tablebase for endgame positions, then falls back to search.
+pub fn get_ai_move(
+    board: &BoardState,
+    color: Color,
+    difficulty: AIDifficulty,
+    tt: &mut TranspositionTable,
+) -> SearchResult {
+    get_ai_move_with_history(board, color, difficulty, tt, &[])
+}
+
+/// Get AI move based on difficulty level, aware of the game's earlier positions
+/// so that repetitions (including perpetual check) are scored as draws.
+pub fn get_ai_move_with_history(
+    board: &BoardState,
+    color: Color,
+    difficulty: AIDifficulty,
+    tt: &mut TranspositionTable,
+    history: &[PositionRecord],
+) -> SearchResult {
+    get_ai_move_for_variant(board, color, difficulty, tt, history, Variant::Standard)
+}
+
+/// Get AI move based on difficulty level under a variant's rules.
+/// Tablebases are only consulted for the standard rules. Levels below
+/// [`WEAK_PLAY_SKILL_THRESHOLD`] play [`find_weak_move`], seeded by the
+/// position so the same position gets the same answer.
+pub fn get_ai_move_for_variant(
+    board: &BoardState,
+    color: Color,
+    difficulty: AIDifficulty,
+    tt: &mut TranspositionTable,
+    history: &[PositionRecord],
+    variant: Variant,
+) -> SearchResult {
+    get_ai_move_with_budget(board, color, difficulty, None, tt, history, variant)
+}
+
+/// Extra plies a best move that loses material (see [`static_exchange`])
+/// is searched to before the AI plays it.
+pub const SACRIFICE_VERIFICATION_PLIES: i32 = 2;
+
+/// [`get_ai_move_for_variant`] searching to a calibrated budget's depth and
+/// time limit instead of the level's usual ones. A sacrifice is verified
+/// with a deeper search (see [`SACRIFICE_VERIFICATION_PLIES`]).
+#[allow(clippy::too_many_arguments)]
+pub fn get_ai_move_with_budget(
+    board: &BoardState,
+    color: Color,
+    difficulty: AIDifficulty,
+    budget: Option<SearchBudget>,
+    tt: &mut TranspositionTable,
+    history: &[PositionRecord],
+    variant: Variant,
+) -> SearchResult {
+    // Try tablebase probe first for endgame positions. The tablebases score
+    // stalemates as draws, and draws as draws
+    if variant == Variant::Standard
+        && tt.stalemate_rule() == StalemateRule::Draw
+        && tt.win_conditions() == WinConditions::default()
+        && tt.draw_winner().is_none()
+        && detect_configuration(board).is_some()
+    {
+        let probe_result = probe_tablebase(board, color);
+        if probe_result.found {
+            if let Some(entry) = &probe_result.entry {
+                if let Some(best_move) = &entry.best_move {
+                    // Get the piece at the source coordinate
+                    let from_coord = HexCoord::new(best_move.from_q, best_move.from_r);
+                    let to_coord = HexCoord::new(best_move.to_q, best_move.to_r);
+                    let from_key = format!("{},{}", from_coord.q, from_coord.r);
+
+                    if let Some(piece) = board.get(&from_key) {
+                        let to_key = format!("{},{}", to_coord.q, to_coord.r);
+                        let captured = board.get(&to_key).cloned();
+
+                        let mv = Move {
+                            from: from_coord,
+                            to: to_coord,
+                            piece: *piece,
+                            captured,
+                            promotion: best_move.promotion,
+                            is_drop: false,
+                            boulder: None,
+                        };
+
+                        // A winning move completing a repetition would draw
+                        let after =
+                            create_position_record(&apply_move(board, &mv), color.opposite());
+                        let repeats = RepetitionStack::new(history).occurrences(&after) + 1
+                            >= REPETITION_DRAW_COUNT;
+                        if !(repeats && entry.wdl == WDLOutcome::Win) {
+                            let score = get_tablebase_score(board, color).unwrap_or(0);
+
+                            return SearchResult {
+                                best_move: Some(mv),
+                                score,
+                                score_kind: ScoreKind::Tablebase,
+                                stats: SearchStats::default(),
+                            };
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Fall back to regular search, with noise and mistakes for weak levels
+    let depth = budget.map_or(difficulty.search_depth(), |budget| budget.depth);
+    if let Some(settings) = WeakPlaySettings::for_difficulty(difficulty) {
+        let use_quiescence = difficulty != AIDifficulty::Easy;
+        let seed = hash_position(board, color);
+        return find_weak_move(
+            board,
+            color,
+            depth,
+            use_quiescence,
+            &settings,
+            seed,
+            tt,
+            history,
+            variant,
+        );
+    }
+    let time_limit_ms = budget.map_or(HARD_TIME_LIMIT_MS, |budget| budget.time_limit_ms);
+    let search = |depth: i32, tt: &mut TranspositionTable| match difficulty {
+        AIDifficulty::Easy => {
+            find_best_move_for_variant(board, color, depth, tt, false, history, variant)
+        }
+        AIDifficulty::Medium => {
+            find_best_move_for_variant(board, color, depth, tt, true, history, variant)
+        }
+        AIDifficulty::Hard => find_best_move_iterative(
+            board,
+            color,
+            depth,
+            time_limit_ms,
+            tt,
+            true,
+            history,
+            variant,
+        ),
+    };
+    let result = search(depth, tt);
+
+    // A move giving up material is only played if a deeper search agrees
+    match &result.best_move {
+        Some(mv) if static_exchange(board, mv) < 0 => {
+            trace_event!(depth, "verifying sacrifice");
+            search(depth + SACRIFICE_VERIFICATION_PLIES, tt)
+        }
+        _ => result,
+    }
+}
+
+// ============================================================================
+// Swindle Mode
+// ============================================================================
+
+/// Score (from the mover's side) at or below which the AI considers itself
+/// lost and, if asked to, swindles.
+pub const SWINDLE_THRESHOLD: i32 = -500;
+
+/// How much worse than the best defense (in centipawns, against the best
+/// reply) a swindle move may be.
+pub const SWINDLE_TOLERANCE: i32 = 300;
+
+/// How much an opponent reply may fall short of their best reply before it
+/// counts as a mistake.
+pub const SWINDLE_ERROR_MARGIN: i32 = 200;
+
+/// Options for the AI's move choice.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AIOptions {
+    pub difficulty: AIDifficulty,
+    /// When the search finds the position lost, play the move that gives the
+    /// opponent the most ways to go wrong instead of the longest defense.
+    pub swindle: bool,
+    /// Choose moves with the human-like model (see [`crate::humanlike`])
+    /// instead of the best search move
+    pub humanlike: bool,
+    /// Calibrated limits replacing the level's usual depth and time
+    pub budget: Option<SearchBudget>,
+}
+
+impl AIOptions {
+    /// Options for a difficulty level. The intermediate level plays
+    /// human-like moves.
+    pub fn new(difficulty: AIDifficulty) -> Self {
+        Self {
+            difficulty,
+            swindle: false,
+            humanlike: difficulty == AIDifficulty::Medium,
+            budget: None,
+        }
+    }
+
+    pub fn with_budget(mut self, budget: Option<SearchBudget>) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Depth searched: the budget's, or the level's usual one.
+    pub fn search_depth(&self) -> i32 {
+        self.budget
+            .map_or(self.difficulty.search_depth(), |budget| budget.depth)
+    }
+
+    pub fn with_swindle(mut self, swindle: bool) -> Self {
+        self.swindle = swindle;
+        self
+    }
+
+    pub fn with_humanlike(mut self, humanlike: bool) -> Self {
+        self.humanlike = humanlike;
+        self
+    }
+}
+
+impl Default for AIOptions {
+    fn default() -> Self {
+        Self::new(AIDifficulty::Medium)
+    }
+}
+
+/// How hard a candidate move makes the opponent's task.
+#[derive(Clone, Debug)]
+struct SwindleCandidate {
+    mv: Move,
+    /// Score after the opponent's best reply, from the mover's side
+    worst_case: i32,
+    /// Share of the opponent's replies that are mistakes
+    error_rate: f64,
+}
+
+/// Judge a move by the opponent's replies, each scored by a quiescence search
+/// (a reduced-depth look at what the reply leaves).
+fn swindle_candidate(
+    board: &BoardState,
+    mv: &Move,
+    color: Color,
+    variant: Variant,
+    stalemate_rule: StalemateRule,
+) -> SwindleCandidate {
+    let sign = if color == Color::White { 1 } else { -1 };
+    let after = apply_move(board, mv);
+    let opponent = color.opposite();
+    let replies = prepare_variant_moves(
+        &after,
+        generate_all_legal_moves(&after, opponent),
+        variant,
+        BOULDER_PLACEMENT_CANDIDATES,
+    );
+    if replies.is_empty() {
+        let worst_case = if is_in_check(&after, opponent) {
+            CHECKMATE_VALUE
+        } else {
+            sign * stalemate_score(stalemate_rule, opponent, 0)
+        };
+        return SwindleCandidate {
+            mv: mv.clone(),
+            worst_case,
+            error_rate: 1.0,
+        };
+    }
+
+    let scores: Vec<i32> = replies
+        .iter()
+        .map(|reply| sign * evaluate_quiet(&apply_move(&after, reply), color, variant))
+        .collect();
+    let worst_case = scores.iter().copied().min().unwrap_or(STALEMATE_VALUE);
+    let mistakes = scores
+        .iter()
+        .filter(|&&score| score >= worst_case + SWINDLE_ERROR_MARGIN)
+        .count();
+    SwindleCandidate {
+        mv: mv.clone(),
+        worst_case,
+        error_rate: mistakes as f64 / scores.len() as f64,
+    }
+}
+
+/// The move giving the opponent the most chances to go wrong, among those
+/// within [`SWINDLE_TOLERANCE`] of the best defense, with stalemates scored
+/// by `stalemate_rule`. None without legal moves.
+pub fn find_swindle_move(
+    board: &BoardState,
+    color: Color,
+    variant: Variant,
+    stalemate_rule: StalemateRule,
+) -> Option<Move> {
+    let mut moves = prepare_variant_moves(
+        board,
+        generate_all_legal_moves(board, color),
+        variant,
+        BOULDER_PLACEMENT_CANDIDATES,
+    );
+    // A fixed order makes ties repeatable
+    moves.sort_by_key(|mv| (mv.from.q, mv.from.r, mv.to.q, mv.to.r));
+    let candidates: Vec<SwindleCandidate> = moves
+        .iter()
+        .map(|mv| swindle_candidate(board, mv, color, variant, stalemate_rule))
+        .collect();
+
+    let best_defense = candidates.iter().map(|c| c.worst_case).max()?;
+    candidates
+        .into_iter()
+        .filter(|c| c.worst_case >= best_defense - SWINDLE_TOLERANCE)
+        .max_by(|a, b| {
+            a.error_rate
+                .total_cmp(&b.error_rate)
+                .then(a.worst_case.cmp(&b.worst_case))
+        })
+        .map(|c| c.mv)
+}
+
+/// Get an AI move with options. Behaves as [`get_ai_move_for_variant`], or
+/// draws a human-like move (seeded by the position, so the same position gets
+/// the same answer) if `humanlike` is set. If swindle mode is on and the score
+/// is at or below [`SWINDLE_THRESHOLD`] for the mover, the move comes from
+/// [`find_swindle_move`] instead, while the score stays the search's.
+pub fn get_ai_move_with_options(
+    board: &BoardState,
+    color: Color,
+    options: &AIOptions,
+    tt: &mut TranspositionTable,
+    history: &[PositionRecord],
+    variant: Variant,
+) -> SearchResult {
+    let result = if options.humanlike {
+        let mut settings = HumanlikeSettings::for_difficulty(options.difficulty);
+        if let Some(budget) = options.budget {
+            settings.depth = settings.depth.min(budget.depth);
+        }
+        let mut rng = SeededRng::new(hash_position(board, color));
+        let choice = choose_humanlike_move(board, color, &settings, &mut rng, tt, history, variant);
+        let sign = if color == Color::White { 1 } else { -1 };
+        SearchResult {
+            score: choice.as_ref().map_or(0, |choice| sign * choice.score),
+            best_move: choice.map(|choice| choice.mv),
+            score_kind: ScoreKind::Centipawns,
+            stats: SearchStats {
+                max_depth_reached: settings.depth,
+                ..Default::default()
+            },
+        }
+    } else {
+        get_ai_move_with_budget(
+            board,
+            color,
+            options.difficulty,
+            options.budget,
+            tt,
+            history,
+            variant,
+        )
+    };
+    let mover_score = if color == Color::White {
+        result.score
+    } else {
+        -result.score
+    };
+    if !options.swindle || result.best_move.is_none() || mover_score > SWINDLE_THRESHOLD {
+        return result;
+    }
+    trace_event!(score = result.score, "swindling");
+    SearchResult {
+        best_move: find_swindle_move(board, color, variant, tt.stalemate_rule())
+            .or(result.best_move),
+        ..result
+    }
+}
+
+// ============================================================================
+// Analysis Cache
+// ============================================================================
+
+/// Search results and evaluations of positions, for UIs that ask about the
+/// same position again and again. A search answers later requests with the
+/// same [`AIOptions`], keeping the deepest one reached. Search results also
+/// depend on the moves
+/// that led to a position, so owners clear the cache whenever the game moves.
+/// Entries computed before an engine option changed are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct AnalysisCache {
+    entries: HashMap<u64, CachedAnalysis>,
+}
+
+#[derive(Debug, Clone)]
+struct CachedAnalysis {
+    board: BoardState,
+    turn: Color,
+    hands: Hands,
+    /// Searches by options, with the depth each reached
+    search: Vec<(AIOptions, i32, SearchResult)>,
+    evaluation: Option<i32>,
+    /// [`options_generation`] when computed
+    generation: u64,
+}
+
+impl AnalysisCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn entry(&self, state: &GameState) -> Option<&CachedAnalysis> {
+        self.entries.get(&state.position_hash()).filter(|entry| {
+            entry.generation == options_generation()
+                && entry.turn == state.turn
+                && entry.hands == state.hands
+                && boards_equal(&entry.board, &state.board)
+        })
+    }
+
+    fn entry_mut(&mut self, state: &GameState) -> &mut CachedAnalysis {
+        let fresh = CachedAnalysis {
+            board: state.board.clone(),
+            turn: state.turn,
+            hands: state.hands.clone(),
+            search: Vec::new(),
+            evaluation: None,
+            generation: options_generation(),
+        };
+        let entry = self
+            .entries
+            .entry(state.position_hash())
+            .or_insert(fresh.clone());
+        // A colliding or outdated position replaces the stored one
+        if entry.generation != fresh.generation
+            || entry.turn != state.turn
+            || entry.hands != state.hands
+            || !boards_equal(&entry.board, &state.board)
+        {
+            *entry = fresh;
+        }
+        entry
+    }
+
+    /// A cached search of the position with these options.
+    pub fn search(&self, state: &GameState, options: &AIOptions) -> Option<&SearchResult> {
+        self.entry(state)?
+            .search
+            .iter()
+            .find(|(searched, _, _)| searched == options)
+            .map(|(_, _, result)| result)
+    }
+
+    /// Cache a search of the position with these options, unless one with
+    /// the same options already reached a greater depth.
+    pub fn store_search(&mut self, state: &GameState, options: &AIOptions, result: SearchResult) {
+        let depth = result.stats.max_depth_reached;
+        let entry = self.entry_mut(state);
+        match entry
+            .search
+            .iter_mut()
+            .find(|(searched, _, _)| searched == options)
+        {
+            Some(cached) if cached.1 <= depth => *cached = (*options, depth, result),
+            Some(_) => {}
+            None => entry.search.push((*options, depth, result)),
+        }
+    }
+
+    /// The static evaluation of the position, computed on first request.
+    pub fn evaluation(&mut self, state: &GameState) -> i32 {
+        let entry = self.entry_mut(state);
+        *entry
+            .evaluation
+            .get_or_insert_with(|| evaluate_position_with_hands(&state.board, &state.hands))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+// ============================================================================
+// Tests
 // ============================================================================
 
 #[cfg(test)]
@@ -822,6 +3090,38 @@ mod tests {
         assert_eq!(get_piece_value(PieceType::King), 0);
     }
 
+    #[test]
+    fn test_static_exchange() {
+        let piece = |piece_type, color| Piece::new(piece_type, color);
+        let mut board = BoardState::new();
+        board.insert("0,0".to_string(), piece(PieceType::Pawn, Color::White));
+        board.insert("0,-1".to_string(), piece(PieceType::Knight, Color::Black));
+        let mut take = Move::new(
+            piece(PieceType::Pawn, Color::White),
+            HexCoord::new(0, 0),
+            HexCoord::new(0, -1),
+        );
+        take.captured = Some(piece(PieceType::Knight, Color::Black));
+        // An undefended knight is won outright
+        assert_eq!(static_exchange(&board, &take), 300);
+
+        // A defended queen still wins the pawn's worth less
+        board.insert("0,-1".to_string(), piece(PieceType::Queen, Color::Black));
+        board.insert("0,-2".to_string(), piece(PieceType::Pawn, Color::Black));
+        take.captured = Some(piece(PieceType::Queen, Color::Black));
+        assert_eq!(static_exchange(&board, &take), 800);
+
+        // A queen stepping next to an enemy pawn hangs
+        board.remove("0,-1");
+        board.insert("2,0".to_string(), piece(PieceType::Queen, Color::White));
+        let hang = Move::new(
+            piece(PieceType::Queen, Color::White),
+            HexCoord::new(2, 0),
+            HexCoord::new(0, -1),
+        );
+        assert_eq!(static_exchange(&board, &hang), 100 - 900);
+    }
+
     #[test]
     fn test_centrality_bonus() {
         let center = HexCoord::new(0, 0);
@@ -843,6 +3143,190 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_evaluate_hands() {
+        let game = create_new_game();
+        let mut hands = Hands::default();
+        hands.add(Color::White, PieceType::Knight);
+        hands.add(Color::White, PieceType::Pawn);
+        hands.add(Color::Black, PieceType::Pawn);
+
+        assert_eq!(evaluate_hand(&hands.white), 400);
+        assert_eq!(
+            evaluate_position_with_hands(&game.board, &hands),
+            evaluate_position(&game.board) + 300
+        );
+    }
+
+    #[test]
+    fn test_search_drops_from_hand() {
+        // A knight dropped on the smothered king mates
+        let (board, _) = crate::notation::parse_hexfen("4n4/3kn4/2nn5/9/K8/9/9/9/9 w").unwrap();
+        let mut state = crate::game::create_game_from_position(board, Color::White);
+        state.variant = Variant::Crazyhouse { drop_mate: true };
+        state.hands.add(Color::White, PieceType::Knight);
+        let search = |state: &GameState| {
+            let mut tt = TranspositionTable::new(10_000);
+            tt.set_game_rules(state);
+            find_best_move_for_variant(
+                &state.board,
+                Color::White,
+                2,
+                &mut tt,
+                true,
+                &state.position_history,
+                state.variant,
+            )
+        };
+
+        let result = search(&state);
+        let mv = result.best_move.unwrap();
+        assert!(mv.is_drop);
+        assert!(result.score > CHECKMATE_VALUE - 10);
+        let after = crate::session::replay_move(&state, &mv).unwrap();
+        assert!(matches!(
+            after.status,
+            crate::types::GameStatus::Checkmate { .. }
+        ));
+
+        // Without drop mates the knight still counts in hand
+        state.variant = Variant::from_name("crazyhouseNoDropMate").unwrap();
+        let with_knight = search(&state).score;
+        assert!(with_knight < CHECKMATE_VALUE - 10);
+        state.hands = Hands::default();
+        let without = search(&state).score;
+        assert!(with_knight > without + get_piece_value(PieceType::Knight) / 2);
+    }
+
+    #[test]
+    fn test_king_of_the_hill_search() {
+        // White can grab a knight or step onto the hill and win.
+        let mut board = BoardState::new();
+        board.insert("0,1".to_string(), Piece::new(PieceType::King, Color::White));
+        board.insert(
+            "3,0".to_string(),
+            Piece::new(PieceType::Queen, Color::White),
+        );
+        board.insert(
+            "0,-4".to_string(),
+            Piece::new(PieceType::King, Color::Black),
+        );
+        board.insert(
+            "3,-3".to_string(),
+            Piece::new(PieceType::Knight, Color::Black),
+        );
+
+        let mut tt = TranspositionTable::new(1000);
+        let result = find_best_move_for_variant(
+            &board,
+            Color::White,
+            2,
+            &mut tt,
+            false,
+            &[],
+            Variant::KingOfTheHill,
+        );
+        let best = result.best_move.unwrap();
+        assert_eq!(best.to, KING_OF_THE_HILL_CENTER);
+        assert!(result.score > CHECKMATE_VALUE - 10);
+
+        assert!(
+            evaluate_for_variant(&board, Variant::KingOfTheHill)
+                > evaluate_for_variant(&board, Variant::Standard)
+        );
+    }
+
+    #[test]
+    fn test_racing_kings_search() {
+        // White can win the race immediately but must not give check doing so.
+        let mut board = BoardState::new();
+        board.insert(
+            "1,-3".to_string(),
+            Piece::new(PieceType::King, Color::White),
+        );
+        board.insert(
+            "-2,2".to_string(),
+            Piece::new(PieceType::King, Color::Black),
+        );
+        board.insert(
+            "2,-2".to_string(),
+            Piece::new(PieceType::Chariot, Color::Black),
+        );
+
+        let mut tt = TranspositionTable::new(1000);
+        let result = find_best_move_for_variant(
+            &board,
+            Color::White,
+            2,
+            &mut tt,
+            false,
+            &[],
+            Variant::RacingKings,
+        );
+        let best = result.best_move.unwrap();
+        assert_eq!(best.to.r, RACING_KINGS_GOAL_ROW);
+        assert!(result.score > CHECKMATE_VALUE - 10);
+    }
+
+    #[test]
+    fn test_shogi_search_promotes() {
+        let mut board = BoardState::new();
+        board.insert("0,4".to_string(), Piece::new(PieceType::King, Color::White));
+        board.insert("4,0".to_string(), Piece::new(PieceType::King, Color::Black));
+        board.insert(
+            "0,-1".to_string(),
+            Piece::new(PieceType::Knight, Color::White),
+        );
+        board.insert(
+            "1,-3".to_string(),
+            Piece::new(PieceType::Pawn, Color::Black),
+        );
+
+        let mut tt = TranspositionTable::new(1000);
+        let result = find_best_move_for_variant(
+            &board,
+            Color::White,
+            1,
+            &mut tt,
+            false,
+            &[],
+            Variant::Shogi { drops: false },
+        );
+        let best = result.best_move.unwrap();
+        assert_eq!(best.to, HexCoord::new(1, -3));
+        assert_eq!(best.promotion, Some(PieceType::PromotedKnight));
+    }
+
+    #[test]
+    fn test_boulder_search_places_boulder() {
+        let game = crate::game::create_variant_game(Variant::Boulder);
+        let mut tt = TranspositionTable::new(1000);
+        let result = find_best_move_for_variant(
+            &game.board,
+            Color::White,
+            1,
+            &mut tt,
+            false,
+            &[],
+            Variant::Boulder,
+        );
+
+        let best = result.best_move.unwrap();
+        let boulder = best.boulder.unwrap();
+        let after = apply_move(&game.board, &best);
+        assert_eq!(after.get(&boulder.to_key()), Some(&Piece::boulder()));
+        assert_eq!(
+            evaluate_material(&after),
+            evaluate_material(&apply_move(
+                &game.board,
+                &Move {
+                    boulder: None,
+                    ..best
+                }
+            ))
+        );
+    }
+
     #[test]
     fn test_move_ordering() {
         let game = create_new_game();
@@ -886,6 +3370,15 @@ mod tests {
         assert_eq!(entry.unwrap().depth, 3);
     }
 
+    #[test]
+    fn test_tt_stress() {
+        // A small table keeps evicting while the games are stored
+        let report = tt_stress_test(10, 30, 64, 5);
+        assert!(report.passed(64));
+        assert_eq!(report.stores, report.probes);
+        assert!(report.hits > 0 && report.hits <= report.probes);
+    }
+
     #[test]
     fn test_quiescence_search() {
         let game = create_new_game();
@@ -898,6 +3391,7 @@ mod tests {
             true,
             &mut stats,
             0,
+            Variant::Standard,
         );
 
         // Should return a valid score
@@ -905,6 +3399,121 @@ mod tests {
         assert!(stats.nodes_searched > 0);
     }
 
+    fn create_quiet_mate_in_one() -> BoardState {
+        // Black king boxed in at the top corner by its own pawns;
+        // Qc(2,-4) -> (0,-2) is a quiet checkmate.
+        let mut board = BoardState::new();
+        board.insert("0,4".to_string(), Piece::new(PieceType::King, Color::White));
+        board.insert(
+            "2,-4".to_string(),
+            Piece::new(PieceType::Queen, Color::White),
+        );
+        board.insert(
+            "0,-4".to_string(),
+            Piece::new(PieceType::King, Color::Black),
+        );
+        board.insert(
+            "-1,-3".to_string(),
+            Piece::new(PieceType::Pawn, Color::Black),
+        );
+        board.insert(
+            "1,-4".to_string(),
+            Piece::new(PieceType::Pawn, Color::Black),
+        );
+        board
+    }
+
+    #[test]
+    fn test_tactical_moves_include_checks() {
+        let board = create_quiet_mate_in_one();
+
+        let without_checks = generate_tactical_moves(&board, Color::White, false);
+        assert!(!without_checks.iter().any(|m| m.to == HexCoord::new(0, -2)));
+
+        let with_checks = generate_tactical_moves(&board, Color::White, true);
+        assert!(with_checks.iter().any(|m| m.to == HexCoord::new(0, -2)));
+        // Captures still come first
+        assert!(with_checks[0].captured.is_some());
+    }
+
+    #[test]
+    fn test_tactical_moves_all_evasions_in_check() {
+        let mut board = BoardState::new();
+        board.insert("0,0".to_string(), Piece::new(PieceType::King, Color::White));
+        board.insert(
+            "0,-3".to_string(),
+            Piece::new(PieceType::Queen, Color::Black),
+        );
+        board.insert("0,4".to_string(), Piece::new(PieceType::King, Color::Black));
+
+        let evasions = generate_tactical_moves(&board, Color::White, false);
+        assert_eq!(
+            evasions.len(),
+            generate_all_legal_moves(&board, Color::White).len()
+        );
+    }
+
+    #[test]
+    fn test_quiescence_finds_quiet_mate() {
+        let board = create_quiet_mate_in_one();
+        let mut stats = SearchStats::default();
+
+        let score = quiescence_search(
+            &board,
+            -CHECKMATE_VALUE - 1,
+            CHECKMATE_VALUE + 1,
+            true,
+            &mut stats,
+            0,
+            Variant::Standard,
+        );
+
+        assert_eq!(score, CHECKMATE_VALUE);
+    }
+
+    fn create_perpetual_position(losing: bool) -> BoardState {
+        let mut board = BoardState::new();
+        board.insert("0,4".to_string(), Piece::new(PieceType::King, Color::White));
+        board.insert(
+            "1,-1".to_string(),
+            Piece::new(PieceType::Queen, Color::White),
+        );
+        board.insert(
+            "0,-4".to_string(),
+            Piece::new(PieceType::King, Color::Black),
+        );
+        let extra = if losing { Color::Black } else { Color::White };
+        board.insert("-4,0".to_string(), Piece::new(PieceType::Queen, extra));
+        board.insert("-4,2".to_string(), Piece::new(PieceType::Chariot, extra));
+        board
+    }
+
+    #[test]
+    fn test_search_seeks_and_avoids_perpetual() {
+        let checking_move = |board: &BoardState| {
+            let queen = *board.get("1,-1").unwrap();
+            Move::new(queen, HexCoord::new(1, -1), HexCoord::new(0, -1))
+        };
+
+        for losing in [true, false] {
+            let board = create_perpetual_position(losing);
+            let after_check = apply_move(&board, &checking_move(&board));
+            let history = vec![crate::game::create_position_record(
+                &after_check,
+                Color::Black,
+            )];
+            let mut tt = TranspositionTable::new(1000);
+
+            let result =
+                find_best_move_with_history(&board, Color::White, 1, &mut tt, false, &history);
+            let best = result.best_move.unwrap();
+            let repeats = best.to == HexCoord::new(0, -1);
+
+            assert_eq!(repeats, losing, "losing side should seek the perpetual");
+            assert!(result.stats.perpetual_checks > 0);
+        }
+    }
+
     #[test]
     fn test_ai_difficulty() {
         let game = create_new_game();
@@ -919,4 +3528,588 @@ mod tests {
         assert!(medium_result.best_move.is_some());
         assert!(medium_result.stats.nodes_searched >= easy_result.stats.nodes_searched);
     }
+
+    #[test]
+    fn test_schedule_mistake() {
+        let mut rng = SeededRng::new(1);
+        let picks: Vec<usize> = (0..1000)
+            .map(|_| schedule_mistake(&[0.0, -40.0, -400.0], 40.0, &mut rng).unwrap())
+            .collect();
+        let count = |index| picks.iter().filter(|&&pick| pick == index).count();
+        // The second best is e times less likely than the best
+        assert!(count(0) > 2 * count(1) && count(1) > 150);
+        assert!(count(2) < 5);
+        assert_eq!(schedule_mistake(&[], 40.0, &mut rng), None);
+    }
+
+    #[test]
+    fn test_weak_play_levels() {
+        assert!(WeakPlaySettings::for_difficulty(AIDifficulty::Hard).is_none());
+        let easy = WeakPlaySettings::for_difficulty(AIDifficulty::Easy).unwrap();
+        let medium = WeakPlaySettings::for_difficulty(AIDifficulty::Medium).unwrap();
+        assert!(easy.noise_sigma > medium.noise_sigma);
+
+        // A loose pawn that Easy sometimes misses
+        let mut board = BoardState::new();
+        board.insert("0,4".to_string(), Piece::new(PieceType::King, Color::White));
+        board.insert(
+            "0,1".to_string(),
+            Piece::new(PieceType::Queen, Color::White),
+        );
+        board.insert(
+            "0,-4".to_string(),
+            Piece::new(PieceType::King, Color::Black),
+        );
+        board.insert(
+            "0,-2".to_string(),
+            Piece::new(PieceType::Pawn, Color::Black),
+        );
+        board.insert(
+            "3,-4".to_string(),
+            Piece::new(PieceType::Pawn, Color::Black),
+        );
+
+        let mut tt = TranspositionTable::new(1000);
+        let mut captures = |settings: &WeakPlaySettings| {
+            (0..30)
+                .filter(|&seed| {
+                    let result = find_weak_move(
+                        &board,
+                        Color::White,
+                        2,
+                        false,
+                        settings,
+                        seed,
+                        &mut tt,
+                        &[],
+                        Variant::Standard,
+                    );
+                    result
+                        .best_move
+                        .is_some_and(|mv| mv.to == HexCoord::new(0, -2))
+                })
+                .count()
+        };
+        let easy_captures = captures(&easy);
+        let medium_captures = captures(&medium);
+        assert!(easy_captures > 0);
+        assert!(medium_captures > easy_captures && medium_captures < 30);
+
+        let first = find_weak_move(
+            &board,
+            Color::White,
+            2,
+            false,
+            &easy,
+            3,
+            &mut tt,
+            &[],
+            Variant::Standard,
+        );
+        let again = find_weak_move(
+            &board,
+            Color::White,
+            2,
+            false,
+            &easy,
+            3,
+            &mut tt,
+            &[],
+            Variant::Standard,
+        );
+        assert_eq!(first.best_move, again.best_move);
+    }
+
+    #[test]
+    fn test_humanlike_options() {
+        assert!(AIOptions::new(AIDifficulty::Medium).humanlike);
+        assert!(!AIOptions::new(AIDifficulty::Hard).humanlike);
+
+        let game = create_new_game();
+        let mut tt = TranspositionTable::new(1000);
+        let options = AIOptions::new(AIDifficulty::Medium);
+        let mut play = || {
+            get_ai_move_with_options(
+                &game.board,
+                Color::White,
+                &options,
+                &mut tt,
+                &game.position_history,
+                Variant::Standard,
+            )
+        };
+        let first = play();
+        let mv = first.best_move.clone().unwrap();
+        assert!(generate_all_legal_moves(&game.board, Color::White).contains(&mv));
+        // Seeded by the position
+        assert_eq!(play().best_move, Some(mv));
+    }
+
+    #[test]
+    fn test_stalemate_rule_scoring() {
+        assert_eq!(stalemate_score(StalemateRule::Draw, Color::Black, 3), 0);
+        assert_eq!(
+            stalemate_score(StalemateRule::StalematerWins, Color::Black, 3),
+            CHECKMATE_VALUE - 3
+        );
+        let three_quarters = StalemateRule::Fractional {
+            stalemater_quarters: 3,
+        };
+        assert_eq!(
+            stalemate_score(three_quarters, Color::White, 0),
+            -STALEMATE_QUARTER_VALUE
+        );
+
+        // A lone queen can stalemate the cornered king in one move
+        let mut board = BoardState::new();
+        board.insert("0,4".to_string(), Piece::new(PieceType::King, Color::White));
+        board.insert(
+            "0,-4".to_string(),
+            Piece::new(PieceType::King, Color::Black),
+        );
+        board.insert(
+            "-4,1".to_string(),
+            Piece::new(PieceType::Queen, Color::White),
+        );
+        let mut tt = TranspositionTable::new(1000);
+        tt.set_stalemate_rule(StalemateRule::StalematerWins);
+        let result = find_best_move(&board, Color::White, 2, &mut tt, false);
+        assert!(result.score > CHECKMATE_VALUE / 2);
+        let mv = result.best_move.unwrap();
+        assert_eq!(mv.to, HexCoord::new(-1, -2));
+
+        // Changing the rule drops the scores stored under the old one
+        tt.set_stalemate_rule(StalemateRule::Draw);
+        assert!(tt.probe(&board).is_none());
+    }
+
+    #[test]
+    fn test_draw_winner_scoring() {
+        assert_eq!(draw_score(None, 3), STALEMATE_VALUE);
+        assert_eq!(draw_score(Some(Color::Black), 3), -CHECKMATE_VALUE + 3);
+
+        // The stalemate of the cornered king wins when a draw suffices, and
+        // is avoided when a draw loses
+        let mut board = BoardState::new();
+        board.insert("0,4".to_string(), Piece::new(PieceType::King, Color::White));
+        board.insert(
+            "0,-4".to_string(),
+            Piece::new(PieceType::King, Color::Black),
+        );
+        board.insert(
+            "-4,1".to_string(),
+            Piece::new(PieceType::Queen, Color::White),
+        );
+        let mut tt = TranspositionTable::new(1000);
+        tt.set_draw_winner(Some(Color::White));
+        let result = find_best_move(&board, Color::White, 2, &mut tt, false);
+        assert!(result.score > CHECKMATE_VALUE / 2);
+        assert_eq!(result.best_move.unwrap().to, HexCoord::new(-1, -2));
+
+        tt.set_draw_winner(Some(Color::Black));
+        assert!(tt.probe(&board).is_none());
+        let result = find_best_move(&board, Color::White, 2, &mut tt, false);
+        assert!(result.score.abs() < CHECKMATE_VALUE / 2);
+        assert_ne!(result.best_move.unwrap().to, HexCoord::new(-1, -2));
+    }
+
+    #[test]
+    fn test_win_conditions_scoring() {
+        // Taking the lone knight bares the black king
+        let mut board = BoardState::new();
+        board.insert("0,4".to_string(), Piece::new(PieceType::King, Color::White));
+        board.insert(
+            "0,-4".to_string(),
+            Piece::new(PieceType::King, Color::Black),
+        );
+        board.insert(
+            "-4,1".to_string(),
+            Piece::new(PieceType::Queen, Color::White),
+        );
+        board.insert(
+            "-2,-1".to_string(),
+            Piece::new(PieceType::Knight, Color::Black),
+        );
+        let mut tt = TranspositionTable::new(1000);
+        tt.set_win_conditions(WinConditions {
+            bare_king: true,
+            capture_all: None,
+        });
+        let result = find_best_move(&board, Color::White, 1, &mut tt, true);
+        assert!(result.score > CHECKMATE_VALUE / 2);
+        assert_eq!(result.best_move.unwrap().to, HexCoord::new(-2, -1));
+    }
+
+    #[test]
+    fn test_swindle_mode() {
+        let game = create_new_game();
+        let mut tt = TranspositionTable::new(1000);
+        let options = AIOptions::new(AIDifficulty::Easy);
+        let plain = get_ai_move_with_options(
+            &game.board,
+            Color::White,
+            &options,
+            &mut tt,
+            &[],
+            Variant::Standard,
+        );
+        let swindle = options.with_swindle(true);
+        let even = get_ai_move_with_options(
+            &game.board,
+            Color::White,
+            &swindle,
+            &mut tt,
+            &[],
+            Variant::Standard,
+        );
+        // Not lost: swindle mode changes nothing
+        assert_eq!(even.best_move, plain.best_move);
+
+        // Black is two queens down
+        let mut board = BoardState::new();
+        board.insert("0,4".to_string(), Piece::new(PieceType::King, Color::White));
+        board.insert(
+            "2,-1".to_string(),
+            Piece::new(PieceType::Queen, Color::White),
+        );
+        board.insert(
+            "-2,1".to_string(),
+            Piece::new(PieceType::Queen, Color::White),
+        );
+        board.insert(
+            "0,-4".to_string(),
+            Piece::new(PieceType::King, Color::Black),
+        );
+        board.insert(
+            "0,-2".to_string(),
+            Piece::new(PieceType::Knight, Color::Black),
+        );
+        board.insert(
+            "-1,-2".to_string(),
+            Piece::new(PieceType::Pawn, Color::Black),
+        );
+
+        let plain = get_ai_move_with_options(
+            &board,
+            Color::Black,
+            &options,
+            &mut tt,
+            &[],
+            Variant::Standard,
+        );
+        let lost = get_ai_move_with_options(
+            &board,
+            Color::Black,
+            &swindle,
+            &mut tt,
+            &[],
+            Variant::Standard,
+        );
+        assert!(plain.score >= -SWINDLE_THRESHOLD);
+        assert_eq!(lost.score, plain.score);
+        let chosen = lost.best_move.unwrap();
+        assert_eq!(
+            find_swindle_move(&board, Color::Black, Variant::Standard, StalemateRule::Draw),
+            Some(chosen.clone())
+        );
+
+        let judge = |mv: &Move| {
+            swindle_candidate(
+                &board,
+                mv,
+                Color::Black,
+                Variant::Standard,
+                StalemateRule::Draw,
+            )
+        };
+        let chosen = judge(&chosen);
+        let longest = find_best_move(&board, Color::Black, 2, &mut tt, false);
+        let defense = judge(&longest.best_move.unwrap());
+        // A trickier move than the longest defense, but not a giveaway
+        assert_ne!(chosen.mv, defense.mv);
+        assert!(chosen.error_rate > defense.error_rate);
+        assert!(chosen.worst_case >= defense.worst_case - SWINDLE_TOLERANCE);
+    }
+
+    #[test]
+    fn test_analysis_cache_options() {
+        let game = create_new_game();
+        let mut cache = AnalysisCache::new();
+        let result = |score, depth| SearchResult {
+            best_move: None,
+            score,
+            score_kind: ScoreKind::Centipawns,
+            stats: SearchStats {
+                max_depth_reached: depth,
+                ..SearchStats::default()
+            },
+        };
+        let hard = AIOptions::new(AIDifficulty::Hard);
+        let swindle = hard.with_swindle(true);
+
+        cache.store_search(&game, &hard, result(10, 4));
+        assert_eq!(cache.search(&game, &hard).map(|r| r.score), Some(10));
+        // Other options, even at a lower level, search afresh
+        assert!(cache.search(&game, &swindle).is_none());
+        assert!(cache
+            .search(&game, &AIOptions::new(AIDifficulty::Easy))
+            .is_none());
+        cache.store_search(&game, &swindle, result(20, 4));
+        assert_eq!(cache.search(&game, &swindle).map(|r| r.score), Some(20));
+        assert_eq!(cache.search(&game, &hard).map(|r| r.score), Some(10));
+
+        // A search that reached less deep never replaces a deeper one
+        cache.store_search(&game, &hard, result(30, 2));
+        assert_eq!(cache.search(&game, &hard).map(|r| r.score), Some(10));
+        cache.store_search(&game, &hard, result(40, 6));
+        assert_eq!(cache.search(&game, &hard).map(|r| r.score), Some(40));
+
+        let mut black = game.clone();
+        black.turn = Color::Black;
+        assert!(cache.search(&black, &hard).is_none());
+        assert_eq!(
+            cache.evaluation(&game),
+            evaluate_position_with_hands(&game.board, &game.hands)
+        );
+        cache.evaluation(&black);
+        assert_eq!(cache.len(), 2);
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_countermove_table() {
+        let knight = Piece::new(PieceType::Knight, Color::White);
+        let pawn = Piece::new(PieceType::Pawn, Color::Black);
+        let previous = Move::new(knight, HexCoord::new(1, 2), HexCoord::new(2, 0));
+        let reply = Move::new(pawn, HexCoord::new(0, -2), HexCoord::new(0, -1));
+        let other = Move::new(pawn, HexCoord::new(1, -2), HexCoord::new(1, -1));
+
+        let mut tt = TranspositionTable::new(10);
+        assert!(!tt.is_countermove(&reply, Some(&previous)));
+        tt.record_cutoff(&reply, 3, Some(&previous));
+        assert!(tt.is_countermove(&reply, Some(&previous)));
+        assert!(!tt.is_countermove(&other, Some(&previous)));
+        assert!(!tt.is_countermove(&reply, None));
+
+        // Captures are ordered by MVV-LVA and are not remembered
+        let mut capture = other.clone();
+        capture.captured = Some(knight);
+        tt.record_cutoff(&capture, 3, Some(&previous));
+        assert!(tt.is_countermove(&reply, Some(&previous)));
+
+        tt.clear();
+        assert!(!tt.is_countermove(&reply, Some(&previous)));
+    }
+
+    #[test]
+    fn test_eval_cache() {
+        let board = create_new_game().board;
+        let mut cache = EvalCache::new();
+        let mut stats = SearchStats::default();
+
+        let expected = evaluate_for_variant(&board, Variant::Standard);
+        assert_eq!(
+            cache.evaluate(&board, Variant::Standard, &mut stats),
+            expected
+        );
+        assert_eq!((stats.eval_cache_misses, stats.pawn_cache_misses), (1, 1));
+        assert_eq!(
+            cache.evaluate(&board, Variant::Standard, &mut stats),
+            expected
+        );
+        assert_eq!(stats.eval_cache_hits, 1);
+        assert_eq!(stats.eval_cache_hit_rate(), Some(0.5));
+
+        // A move that leaves the pawns alone reuses their evaluation
+        let mv = generate_all_legal_moves(&board, Color::White)
+            .into_iter()
+            .find(|mv| mv.piece.piece_type == PieceType::Knight && mv.captured.is_none())
+            .unwrap();
+        let moved = apply_move(&board, &mv);
+        assert_eq!(
+            cache.evaluate(&moved, Variant::Standard, &mut stats),
+            evaluate_for_variant(&moved, Variant::Standard)
+        );
+        assert_eq!((stats.eval_cache_misses, stats.pawn_cache_hits), (2, 1));
+        assert_eq!(cache.sizes(), (1, 2));
+
+        // An entry whose check hash differs, as after an index collision,
+        // is not trusted
+        cache
+            .evals
+            .insert((hash_board(&board), Variant::Standard), (0, 12345));
+        cache.pawns.insert(hash_pawns_checked(&board).0, (0, 12345));
+        assert_eq!(
+            cache.evaluate(&board, Variant::Standard, &mut stats),
+            expected
+        );
+        assert_eq!((stats.eval_cache_misses, stats.pawn_cache_misses), (3, 2));
+
+        cache.clear();
+        assert_eq!(cache.sizes(), (0, 0));
+    }
+
+    #[test]
+    fn test_analyze_excluding() {
+        // White's queen can take Black's undefended queen
+        let mut board = BoardState::new();
+        board.insert("0,4".to_string(), Piece::new(PieceType::King, Color::White));
+        board.insert(
+            "0,0".to_string(),
+            Piece::new(PieceType::Queen, Color::White),
+        );
+        board.insert(
+            "4,-4".to_string(),
+            Piece::new(PieceType::King, Color::Black),
+        );
+        board.insert(
+            "0,-2".to_string(),
+            Piece::new(PieceType::Queen, Color::Black),
+        );
+        let mut state = create_new_game();
+        state.board = board;
+        state.position_history.clear();
+
+        let best = analyze_excluding(&state, &[], 2);
+        let capture = best.best_move.clone().unwrap();
+        assert_eq!(
+            (capture.from, capture.to),
+            (HexCoord::new(0, 0), HexCoord::new(0, -2))
+        );
+
+        let alternative = analyze_excluding(&state, std::slice::from_ref(&capture), 2);
+        let other = alternative.best_move.unwrap();
+        assert!(!names_move(&capture, &other));
+        assert!(alternative.score < best.score - 500);
+
+        let all = generate_all_legal_moves(&state.board, Color::White);
+        let none = analyze_excluding(&state, &all, 2);
+        assert!(none.best_move.is_none());
+    }
+
+    #[test]
+    fn test_cancelled_search() {
+        let state = crate::game::create_new_game();
+        let mut tt = TranspositionTable::new(100_000);
+        tt.cancel_token().cancel();
+        let result = find_best_move_iterative(
+            &state.board,
+            state.turn,
+            6,
+            u64::MAX,
+            &mut tt,
+            true,
+            &[],
+            Variant::Standard,
+        );
+        assert!(result.best_move.is_some());
+        assert_eq!(result.stats.max_depth_reached, 1);
+
+        // Cancelled from another thread, a deep search stops early
+        tt.cancel_token().reset();
+        let cancel = tt.cancel_token();
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            cancel.cancel();
+        });
+        let result = find_best_move_iterative(
+            &state.board,
+            state.turn,
+            30,
+            u64::MAX,
+            &mut tt,
+            true,
+            &[],
+            Variant::Standard,
+        );
+        canceller.join().unwrap();
+        assert!(result.best_move.is_some());
+        assert!(result.stats.max_depth_reached < 30);
+    }
+
+    #[test]
+    fn test_iterative_search_steps() {
+        let state = crate::game::create_new_game();
+        let mut search = IterativeSearch::new(&state, 3, 100_000);
+        assert!(search.result().is_none());
+        assert!(!search.step());
+        assert_eq!(search.depth(), 1);
+        assert!(!search.step());
+        search.cancel_token().cancel();
+        assert!(search.is_done());
+        assert!(search.step());
+        let result = search.result().unwrap();
+        assert_eq!(result.stats.max_depth_reached, 2);
+        assert!(result.best_move.is_some());
+
+        let mut full = IterativeSearch::new(&state, 2, 100_000);
+        while !full.step() {}
+        assert_eq!(full.result().unwrap().score, result.score);
+    }
+
+    #[test]
+    fn test_tablebase_backed_score() {
+        use crate::tablebase::{generate_tablebase, parse_configuration, set_tablebase};
+
+        // Taking Black's last piece reaches a bare-kings draw
+        let mut board = BoardState::new();
+        for (cell, piece) in [
+            ((0, 0), Piece::new(PieceType::King, Color::White)),
+            ((0, -4), Piece::new(PieceType::King, Color::Black)),
+            ((1, 0), Piece::new(PieceType::Knight, Color::Black)),
+        ] {
+            board.insert(HexCoord::new(cell.0, cell.1).to_key(), piece);
+        }
+        set_tablebase(generate_tablebase(&parse_configuration("KvK").unwrap()));
+
+        let mut tt = TranspositionTable::new(10_000);
+        let result = find_best_move(&board, Color::White, 2, &mut tt, true);
+        assert_eq!(result.best_move.unwrap().to, HexCoord::new(1, 0));
+        assert_eq!(result.score, 0);
+        assert_eq!(result.score_kind, ScoreKind::Tablebase);
+        assert!(result.stats.tablebase_hits > 0);
+
+        // Under another stalemate rule the tablebases do not apply
+        let mut tt = TranspositionTable::new(10_000);
+        tt.set_stalemate_rule(StalemateRule::StalematerWins);
+        let result = find_best_move(&board, Color::White, 2, &mut tt, true);
+        assert_eq!(result.score_kind, ScoreKind::Centipawns);
+        assert_eq!(result.stats.tablebase_hits, 0);
+    }
+
+    #[test]
+    fn test_calibration_budgets() {
+        // A fast device reaches every level's usual depth
+        let fast = Calibration::from_measurement(10_000_000, 30, 6.0);
+        assert_eq!(fast.easy.depth, AIDifficulty::Easy.search_depth());
+        assert_eq!(fast.hard.depth, AIDifficulty::Hard.search_depth());
+        assert_eq!(fast.hard.time_limit_ms, TARGET_MOVE_MS[2]);
+
+        // A slow one searches the hard level shallower: 3000 nodes in 3s
+        let slow = Calibration::from_measurement(1_000, 30, 6.0);
+        assert_eq!(slow.budget(AIDifficulty::Hard).depth, 3);
+        assert!(slow.easy.depth >= 1);
+
+        assert_eq!(branching_factor(&[30]), DEFAULT_BRANCHING_FACTOR);
+        assert!((branching_factor(&[30, 300, 3000]) - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_evaluate_batch() {
+        use crate::rng::SeededRng;
+        use crate::zobrist::random_game_positions;
+
+        let start = create_new_game().board;
+        let boards: Vec<BoardState> = (0..4)
+            .flat_map(|seed| {
+                random_game_positions(&start, Color::White, 60, &mut SeededRng::new(seed))
+            })
+            .map(|(board, _)| board)
+            .collect();
+        let expected: Vec<i32> = boards.iter().map(evaluate_position).collect();
+
+        assert_eq!(evaluate_batch(&boards), expected);
+        assert!(evaluate_batch(&[]).is_empty());
+    }
 }
@@ -0,0 +1,1753 @@
+//! Underchex Analysis Board
+//!
+//! A variation tree of positions for studying games: moves can be played from
+//! any node (creating side variations), the position can be edited freely and
+//! there is no game-over or turn bookkeeping beyond the side to move.
+//!
+//! Also builds the post-game summary of a played game: its key moments and
+//! each side's accuracy, as data for the report screen to fill its templates
+//! with, and picks its critical positions as practice positions for the
+//! "learn from your mistakes" trainer.
+//!
+//! During a game, the coach reports on each move as it is played: the
+//! evaluation it gained or lost, a better move after a mistake, threats it
+//! made or overlooked and advice for the phase of the game.
+//!
+//! When a position is lost, a resignation advisory says why (a tablebase
+//! loss, a mate coming, or a lost evaluation with no counterplay), for
+//! gentle "this position is lost" prompts.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ai::{
+    analyze_excluding, evaluate_quiet, find_best_move_for_variant, find_best_move_iterative,
+    get_piece_value, ScoreKind, SearchResult, TranspositionTable, CHECKMATE_VALUE,
+};
+use crate::annotations::{AnnotationColor, Annotations, Arrow, ArrowStyle, HighlightedCell};
+use crate::attacks::{build_attack_map, get_attacked_cells};
+use crate::board::pieces_of;
+use crate::game::{create_new_game, try_make_move, GameResult};
+use crate::messages::{color_key, Message};
+use crate::moves::{apply_move, generate_all_legal_moves, is_in_check};
+use crate::notation::{board_to_hexfen, game_to_hexfen};
+use crate::profile::{move_accuracy, winning_chances};
+use crate::puzzles::{can_force_mate, find_shortest_mate};
+use crate::session::replay_move;
+use crate::stats::{game_phase, GamePhase, DEFAULT_BLUNDER_DROP};
+use crate::tablebase::{probe_tablebase, WDLOutcome};
+use crate::types::{
+    BoardState, Color, GameState, GameStatus, Hands, HexCoord, Move, Piece, PieceType,
+    StalemateRule, TurnPhase, Variant, WinConditions,
+};
+use crate::variants::generate_variant_legal_moves;
+use crate::zobrist::hash_position;
+
+// ============================================================================
+// Variation Tree
+// ============================================================================
+
+/// A position in the variation tree.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VariationNode {
+    pub id: usize,
+    pub parent: Option<usize>,
+    /// Child nodes; the first child is the main line
+    pub children: Vec<usize>,
+    /// Move leading to this node from its parent (None for the root)
+    pub mv: Option<Move>,
+    pub board: BoardState,
+    pub turn: Color,
+    /// Arrows and highlighted cells drawn on this position
+    #[serde(default)]
+    pub annotations: Annotations,
+}
+
+/// Tree of variations rooted at a starting position, with a cursor.
+///
+/// Nodes are never reused: deleting a variation detaches it, so node ids stay
+/// stable for the lifetime of the tree.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VariationTree {
+    pub variant: Variant,
+    pub nodes: Vec<VariationNode>,
+    pub current: usize,
+}
+
+lazy_static::lazy_static! {
+    /// Stands in for the root of a tree without nodes, which only a
+    /// hand-built tree can be.
+    static ref EMPTY_ROOT: VariationNode = VariationNode {
+        id: 0,
+        parent: None,
+        children: Vec::new(),
+        mv: None,
+        board: BoardState::new(),
+        turn: Color::White,
+        annotations: Annotations::default(),
+    };
+}
+
+impl Default for VariationTree {
+    fn default() -> Self {
+        let game = create_new_game();
+        Self::new(game.board, game.turn, game.variant)
+    }
+}
+
+impl VariationTree {
+    /// Create a tree rooted at a position.
+    pub fn new(board: BoardState, turn: Color, variant: Variant) -> Self {
+        Self {
+            variant,
+            nodes: vec![VariationNode {
+                id: 0,
+                parent: None,
+                children: Vec::new(),
+                mv: None,
+                board,
+                turn,
+                annotations: Annotations::default(),
+            }],
+            current: 0,
+        }
+    }
+
+    /// The node at the cursor (the root if the cursor is off the tree).
+    pub fn current_node(&self) -> &VariationNode {
+        self.nodes
+            .get(self.current)
+            .or(self.nodes.first())
+            .unwrap_or(&EMPTY_ROOT)
+    }
+
+    pub fn board(&self) -> &BoardState {
+        &self.current_node().board
+    }
+
+    pub fn turn(&self) -> Color {
+        self.current_node().turn
+    }
+
+    /// Legal moves in the current position (ignores game-over conditions).
+    pub fn legal_moves(&self) -> Vec<Move> {
+        generate_variant_legal_moves(self.board(), self.turn(), self.variant, &Hands::default())
+    }
+
+    /// Play a move from the current node and move the cursor to the result.
+    /// An existing child with the same move is reused instead of duplicated.
+    /// Returns the new node's id, or None if the move is illegal.
+    pub fn play(
+        &mut self,
+        from: HexCoord,
+        to: HexCoord,
+        promotion: Option<PieceType>,
+    ) -> Option<usize> {
+        let mv = self
+            .legal_moves()
+            .into_iter()
+            .find(|m| m.from == from && m.to == to && m.promotion == promotion)?;
+
+        let parent = self.current;
+        let existing = self
+            .nodes
+            .get(parent)?
+            .children
+            .iter()
+            .copied()
+            .find(|&child| {
+                self.nodes.get(child).is_some_and(|node| {
+                    node.mv
+                        .as_ref()
+                        .is_some_and(|m| m.from == from && m.to == to && m.promotion == promotion)
+                })
+            });
+        if let Some(existing) = existing {
+            self.current = existing;
+            return Some(existing);
+        }
+
+        let id = self.nodes.len();
+        self.nodes.push(VariationNode {
+            id,
+            parent: Some(parent),
+            children: Vec::new(),
+            board: apply_move(self.board(), &mv),
+            turn: self.turn().opposite(),
+            mv: Some(mv),
+            annotations: Annotations::default(),
+        });
+        if let Some(node) = self.nodes.get_mut(parent) {
+            node.children.push(id);
+        }
+        self.current = id;
+        Some(id)
+    }
+
+    /// Move the cursor to the parent node (undo). Returns false at the root.
+    pub fn back(&mut self) -> bool {
+        match self.current_node().parent {
+            Some(parent) => {
+                self.current = parent;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Move the cursor along the main line (redo). Returns false at a leaf.
+    pub fn forward(&mut self) -> bool {
+        match self.current_node().children.first() {
+            Some(&child) => {
+                self.current = child;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Move the cursor to any node that is still attached to the tree.
+    pub fn go_to(&mut self, id: usize) -> bool {
+        if self.is_attached(id) {
+            self.current = id;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn is_attached(&self, id: usize) -> bool {
+        let mut node = match self.nodes.get(id) {
+            Some(node) => node,
+            None => return false,
+        };
+        while let Some(parent) = node.parent {
+            match self.nodes.get(parent) {
+                Some(parent) if parent.children.contains(&node.id) => node = parent,
+                _ => return false,
+            }
+        }
+        node.id == 0
+    }
+
+    /// Detach a node and its subtree. The root cannot be deleted; if the
+    /// cursor was inside the subtree it moves to the deleted node's parent.
+    pub fn delete_variation(&mut self, id: usize) -> bool {
+        if !self.is_attached(id) {
+            return false;
+        }
+        let Some(parent) = self.nodes.get(id).and_then(|node| node.parent) else {
+            return false;
+        };
+        let cursor_inside = self.path_to(self.current).contains(&id);
+        if let Some(node) = self.nodes.get_mut(parent) {
+            node.children.retain(|&child| child != id);
+        }
+        if cursor_inside {
+            self.current = parent;
+        }
+        true
+    }
+
+    /// Make a node's line the main line at every branch point above it.
+    pub fn promote_variation(&mut self, id: usize) -> bool {
+        if !self.is_attached(id) {
+            return false;
+        }
+        let path = self.path_to(id);
+        for (&parent, &id) in path.iter().zip(path.iter().skip(1)) {
+            let Some(node) = self.nodes.get_mut(parent) else {
+                continue;
+            };
+            if let Some(index) = node.children.iter().position(|&child| child == id) {
+                let child = node.children.remove(index);
+                node.children.insert(0, child);
+            }
+        }
+        true
+    }
+
+    /// Node ids from the root to `id` (inclusive).
+    pub fn path_to(&self, id: usize) -> Vec<usize> {
+        let mut path = vec![id];
+        let mut parent = self.nodes.get(id).and_then(|node| node.parent);
+        while let Some(id) = parent {
+            path.push(id);
+            parent = self.nodes.get(id).and_then(|node| node.parent);
+        }
+        path.reverse();
+        path
+    }
+
+    /// Moves from the root to the current node.
+    pub fn current_line(&self) -> Vec<Move> {
+        self.path_to(self.current)
+            .into_iter()
+            .filter_map(|id| self.nodes.get(id)?.mv.clone())
+            .collect()
+    }
+
+    /// Replace the tree with one rooted at an edited position.
+    pub fn reset(&mut self, board: BoardState, turn: Color) {
+        *self = Self::new(board, turn, self.variant);
+    }
+
+    /// Edit the current position: put `piece` on a cell (or clear it with
+    /// None). The edited position becomes the root of a fresh tree, since the
+    /// existing moves no longer lead to it.
+    pub fn set_piece(&mut self, coord: HexCoord, piece: Option<Piece>) {
+        let mut board = self.board().clone();
+        match piece {
+            Some(piece) => board.insert(coord.to_key(), piece),
+            None => board.remove(&coord.to_key()),
+        };
+        self.reset(board, self.turn());
+    }
+
+    /// Edit the side to move (starts a fresh tree like [`Self::set_piece`]).
+    pub fn set_turn(&mut self, turn: Color) {
+        self.reset(self.board().clone(), turn);
+    }
+
+    /// Annotations of the current node.
+    pub fn annotations(&self) -> &Annotations {
+        &self.current_node().annotations
+    }
+
+    /// Mutable annotations of the current node (None if the cursor is off
+    /// the tree).
+    pub fn annotations_mut(&mut self) -> Option<&mut Annotations> {
+        self.nodes
+            .get_mut(self.current)
+            .map(|node| &mut node.annotations)
+    }
+
+    /// Serialize the tree (including annotations) for saving. Deleted
+    /// variations are left out and the remaining nodes renumbered in order.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self.compacted()).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// Load a tree saved with [`Self::to_json`]. Returns None if the JSON is
+    /// invalid or its node links are inconsistent: the root must be node 0
+    /// and every other node a child of an earlier node.
+    pub fn from_json(json: &str) -> Option<Self> {
+        let tree: Self = serde_json::from_str(json).ok()?;
+        let consistent = !tree.nodes.is_empty()
+            && tree.current < tree.nodes.len()
+            && tree.nodes.iter().enumerate().all(|(index, node)| {
+                let linked = match node.parent {
+                    None => index == 0,
+                    Some(parent) => {
+                        parent < index
+                            && tree
+                                .nodes
+                                .get(parent)
+                                .is_some_and(|parent| parent.children.contains(&index))
+                    }
+                };
+                node.id == index
+                    && linked
+                    && node.children.iter().all(|&child| {
+                        tree.nodes
+                            .get(child)
+                            .is_some_and(|c| c.parent == Some(index))
+                    })
+            });
+        consistent.then_some(tree)
+    }
+
+    /// The tree without detached nodes, renumbered in depth-first order.
+    fn compacted(&self) -> Self {
+        let mut order = Vec::new();
+        let mut stack = vec![0];
+        while let Some(id) = stack.pop() {
+            if let Some(node) = self.nodes.get(id) {
+                order.push((id, node));
+                stack.extend(node.children.iter().rev());
+            }
+        }
+        let mut new_ids = vec![None; self.nodes.len()];
+        for (new_id, &(id, _)) in order.iter().enumerate() {
+            if let Some(slot) = new_ids.get_mut(id) {
+                *slot = Some(new_id);
+            }
+        }
+        let renumber = |id: usize| new_ids.get(id).copied().flatten().unwrap_or(0);
+        let nodes = order
+            .iter()
+            .map(|&(id, node)| VariationNode {
+                id: renumber(id),
+                parent: node.parent.map(renumber),
+                children: node.children.iter().map(|&child| renumber(child)).collect(),
+                ..node.clone()
+            })
+            .collect();
+        Self {
+            variant: self.variant,
+            nodes,
+            current: renumber(self.current),
+        }
+    }
+
+    /// HexFEN of the current position.
+    pub fn hexfen(&self) -> String {
+        board_to_hexfen(self.board(), self.turn())
+    }
+
+    /// Check if the side to move is in check.
+    pub fn is_in_check(&self) -> bool {
+        is_in_check(self.board(), self.turn())
+    }
+}
+
+// ============================================================================
+// Engine Analysis
+// ============================================================================
+
+/// One iteration of engine analysis on a position.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnalysisInfo {
+    pub node: usize,
+    pub depth: i32,
+    /// Score from the side to move's perspective, in centipawns
+    pub score: i32,
+    /// Whether the score is a tablebase's exact one rather than centipawns
+    #[serde(default)]
+    pub score_kind: ScoreKind,
+    pub best_move: Option<Move>,
+    pub nodes_searched: u64,
+}
+
+/// Incremental analysis of a tree's current node: each [`Self::step`]
+/// searches one ply deeper, so a UI can poll it to stream deeper results.
+/// Moving to another node, or editing the position, restarts at depth 1.
+#[derive(Debug, Default)]
+pub struct AnalysisSession {
+    /// Node and position hash last searched
+    node: Option<(usize, u64)>,
+    depth: i32,
+}
+
+impl AnalysisSession {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Search the tree's current node one ply deeper than last time.
+    /// Returns None once `max_depth` has been reached.
+    pub fn step(
+        &mut self,
+        tree: &VariationTree,
+        tt: &mut TranspositionTable,
+        max_depth: i32,
+    ) -> Option<AnalysisInfo> {
+        let node = (tree.current, hash_position(tree.board(), tree.turn()));
+        if self.node != Some(node) {
+            self.node = Some(node);
+            self.depth = 0;
+        }
+        if self.depth >= max_depth {
+            return None;
+        }
+        self.depth += 1;
+
+        let SearchResult {
+            best_move,
+            score,
+            score_kind,
+            stats,
+        } = find_best_move_for_variant(
+            tree.board(),
+            tree.turn(),
+            self.depth,
+            tt,
+            true,
+            &[],
+            tree.variant,
+        );
+        Some(AnalysisInfo {
+            node: tree.current,
+            depth: self.depth,
+            score,
+            score_kind,
+            best_move,
+            nodes_searched: stats.nodes_searched,
+        })
+    }
+}
+
+// ============================================================================
+// Game Summary
+// ============================================================================
+
+/// Evaluation drop (centipawns, mover's side) that counts as a mistake.
+pub const MISTAKE_DROP: i32 = 100;
+
+/// Evaluation drop (centipawns, mover's side) that counts as an inaccuracy.
+pub const INACCURACY_DROP: i32 = 50;
+
+/// Smallest change in winning chances, in percentage points, that counts as
+/// a turning point.
+pub const TURNING_POINT_SWING: f64 = 10.0;
+
+/// Net material change over a move and its reply that counts as a swing.
+pub const MATERIAL_SWING: i32 = 300;
+
+/// A played game to summarize.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayedGame {
+    /// Moves played from the standard starting position
+    pub moves: Vec<Move>,
+    pub status: GameStatus,
+    /// Time spent on each move, if recorded
+    #[serde(default)]
+    pub times_ms: Vec<u64>,
+}
+
+impl MomentKind {
+    /// Message key of the kind.
+    pub fn key(&self) -> &'static str {
+        match self {
+            MomentKind::FirstBlunder => "firstBlunder",
+            MomentKind::TurningPoint => "turningPoint",
+            MomentKind::MaterialSwing => "materialSwing",
+            MomentKind::LongestThink => "longestThink",
+        }
+    }
+}
+
+impl PlayedGame {
+    /// A game's moves and status, without times.
+    pub fn from_state(state: &GameState) -> Self {
+        Self {
+            moves: state.history.clone(),
+            status: state.status.clone(),
+            times_ms: Vec::new(),
+        }
+    }
+}
+
+/// Kind of a key moment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MomentKind {
+    /// The game's first blunder
+    FirstBlunder,
+    /// The move that changed the winning chances the most, if by at least
+    /// [`TURNING_POINT_SWING`]
+    TurningPoint,
+    /// A move after which, with the reply, material changed hands
+    MaterialSwing,
+    /// The move its player spent the longest on
+    LongestThink,
+}
+
+/// A key moment: the template slots of one line of the report.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyMoment {
+    pub kind: MomentKind,
+    /// Index of the move in the game (0 is White's first move)
+    pub ply: usize,
+    pub move_number: usize,
+    pub color: Color,
+    pub mv: Move,
+    /// Evaluation before and after the move, in centipawns from White's side
+    pub eval_before: i32,
+    pub eval_after: i32,
+    /// Evaluation the mover lost (negative if gained)
+    pub eval_drop: i32,
+    /// Net material the mover won over the move and reply (material swings)
+    pub material_change: Option<i32>,
+    /// Time spent on the move, if recorded
+    pub spent_ms: Option<u64>,
+    /// The kind with the move number and color, for display
+    pub message: Message,
+}
+
+/// One side's accuracy over a game.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SideSummary {
+    pub moves: u32,
+    /// Mean move accuracy, 0-100 (see [`move_accuracy`])
+    pub accuracy: f64,
+    pub average_centipawn_loss: f64,
+    pub inaccuracies: u32,
+    pub mistakes: u32,
+    pub blunders: u32,
+}
+
+/// Post-game summary.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GameSummary {
+    /// None while the game is ongoing
+    pub result: Option<GameResult>,
+    pub plies: usize,
+    /// Evaluation of the start and after every move, from White's side
+    pub evaluations: Vec<i32>,
+    /// Key moments in game order
+    pub moments: Vec<KeyMoment>,
+    pub white: SideSummary,
+    pub black: SideSummary,
+}
+
+impl GameSummary {
+    pub fn side(&self, color: Color) -> &SideSummary {
+        match color {
+            Color::White => &self.white,
+            Color::Black => &self.black,
+        }
+    }
+
+    /// Serialize to JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|_| "{}".to_string())
+    }
+}
+
+/// Material on the board (kings aside), in centipawns from White's side.
+fn material_balance(board: &BoardState) -> i32 {
+    let side = |color| -> i32 {
+        pieces_of(board, color)
+            .filter(|(_, piece)| piece.piece_type != PieceType::King)
+            .map(|(_, piece)| get_piece_value(piece.piece_type))
+            .sum()
+    };
+    side(Color::White) - side(Color::Black)
+}
+
+/// Summarize a game: every position is evaluated with a quiescence search,
+/// each move scored by the evaluation it lost, and the key moments picked.
+pub fn summarize(game: &PlayedGame) -> GameSummary {
+    let mut board = create_new_game().board;
+    let mut boards = vec![board.clone()];
+    for mv in &game.moves {
+        board = apply_move(&board, mv);
+        boards.push(board.clone());
+    }
+    let evaluations: Vec<i32> = boards
+        .iter()
+        .enumerate()
+        .map(|(ply, board)| {
+            let turn = if ply % 2 == 0 {
+                Color::White
+            } else {
+                Color::Black
+            };
+            evaluate_quiet(board, turn, Variant::Standard)
+        })
+        .collect();
+    let materials: Vec<i32> = boards.iter().map(material_balance).collect();
+
+    let mut white = SideTotals::default();
+    let mut black = SideTotals::default();
+    let mut moments = Vec::new();
+    let mut first_blunder = None;
+    let mut turning_point: Option<(f64, usize)> = None;
+    let mut longest_think: Option<(u64, usize)> = None;
+
+    let moment = |kind: MomentKind, ply: usize| {
+        let mv: &Move = game.moves.get(ply)?;
+        let (&before, &after) = (evaluations.get(ply)?, evaluations.get(ply + 1)?);
+        let sign = if mv.piece.color == Color::White {
+            1
+        } else {
+            -1
+        };
+        Some(KeyMoment {
+            message: Message::new(kind.key())
+                .with("moveNumber", ply / 2 + 1)
+                .with("color", color_key(mv.piece.color)),
+            kind,
+            ply,
+            move_number: ply / 2 + 1,
+            color: mv.piece.color,
+            mv: mv.clone(),
+            eval_before: before,
+            eval_after: after,
+            eval_drop: sign * (before - after),
+            material_change: None,
+            spent_ms: game.times_ms.get(ply).copied(),
+        })
+    };
+
+    let steps = evaluations.iter().zip(evaluations.iter().skip(1));
+    for (ply, (mv, (&eval_before, &eval_after))) in game.moves.iter().zip(steps).enumerate() {
+        let color = mv.piece.color;
+        let sign = if color == Color::White { 1 } else { -1 };
+        let (before, after) = (sign * eval_before, sign * eval_after);
+        let totals = match color {
+            Color::White => &mut white,
+            Color::Black => &mut black,
+        };
+        totals.add(before, after);
+
+        if before - after >= DEFAULT_BLUNDER_DROP && first_blunder.is_none() {
+            first_blunder = Some(ply);
+        }
+        let swing = (winning_chances(eval_after) - winning_chances(eval_before)).abs();
+        if swing >= TURNING_POINT_SWING && turning_point.is_none_or(|(best, _)| swing > best) {
+            turning_point = Some((swing, ply));
+        }
+        if let Some(&spent) = game.times_ms.get(ply) {
+            if spent > longest_think.map_or(0, |(longest, _)| longest) {
+                longest_think = Some((spent, ply));
+            }
+        }
+    }
+
+    // Material swings, skipping the reply of a swing so a trade counts once
+    let mut ply = 0;
+    while let (Some(mv), Some(&material)) = (game.moves.get(ply), materials.get(ply)) {
+        let sign = if mv.piece.color == Color::White {
+            1
+        } else {
+            -1
+        };
+        let settled = materials
+            .get(ply + 2)
+            .or(materials.last())
+            .copied()
+            .unwrap_or(material);
+        let change = sign * (settled - material);
+        if change.abs() >= MATERIAL_SWING {
+            if let Some(swing) = moment(MomentKind::MaterialSwing, ply) {
+                moments.push(KeyMoment {
+                    material_change: Some(change),
+                    ..swing
+                });
+            }
+            ply += 2;
+        } else {
+            ply += 1;
+        }
+    }
+    if let Some(ply) = first_blunder {
+        moments.extend(moment(MomentKind::FirstBlunder, ply));
+    }
+    if let Some((_, ply)) = turning_point {
+        moments.extend(moment(MomentKind::TurningPoint, ply));
+    }
+    if let Some((_, ply)) = longest_think {
+        moments.extend(moment(MomentKind::LongestThink, ply));
+    }
+    moments.sort_by_key(|moment| (moment.ply, moment.kind as u8));
+
+    GameSummary {
+        result: GameResult::from_status(&game.status),
+        plies: game.moves.len(),
+        evaluations,
+        moments,
+        white: white.summary(),
+        black: black.summary(),
+    }
+}
+
+/// Summarize a game given as JSON (see [`PlayedGame`]), or None if the JSON
+/// is invalid.
+pub fn summarize_from_json(json: &str) -> Option<GameSummary> {
+    let game: PlayedGame = serde_json::from_str(json).ok()?;
+    Some(summarize(&game))
+}
+
+/// Running totals of one side's moves.
+#[derive(Default)]
+struct SideTotals {
+    moves: u32,
+    accuracy_sum: f64,
+    loss_sum: i64,
+    inaccuracies: u32,
+    mistakes: u32,
+    blunders: u32,
+}
+
+impl SideTotals {
+    /// Count a move from `before` to `after` centipawns, mover's side.
+    fn add(&mut self, before: i32, after: i32) {
+        let lost = (before - after).max(0);
+        self.moves += 1;
+        self.accuracy_sum += move_accuracy(before, after);
+        self.loss_sum += i64::from(lost);
+        if lost >= DEFAULT_BLUNDER_DROP {
+            self.blunders += 1;
+        } else if lost >= MISTAKE_DROP {
+            self.mistakes += 1;
+        } else if lost >= INACCURACY_DROP {
+            self.inaccuracies += 1;
+        }
+    }
+
+    fn summary(&self) -> SideSummary {
+        let moves = f64::from(self.moves.max(1));
+        SideSummary {
+            moves: self.moves,
+            accuracy: if self.moves > 0 {
+                self.accuracy_sum / moves
+            } else {
+                0.0
+            },
+            average_centipawn_loss: self.loss_sum as f64 / moves,
+            inaccuracies: self.inaccuracies,
+            mistakes: self.mistakes,
+            blunders: self.blunders,
+        }
+    }
+}
+
+// ============================================================================
+// Critical Positions
+// ============================================================================
+
+/// Smallest lead (centipawns) of the best move over the second best that
+/// makes it the only good move.
+pub const ONLY_MOVE_GAP: i32 = 150;
+
+/// Plies of the best line given with a critical position.
+pub const BEST_LINE_PLIES: usize = 3;
+
+/// Entries of the transposition table for following best lines.
+const STUDY_TT_SIZE: usize = 100_000;
+
+/// A position of a game worth practicing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CriticalPosition {
+    /// Index in the game of the move played from the position
+    pub ply: usize,
+    /// The position in HexFEN, including the side to move
+    pub hexfen: String,
+    pub turn: Color,
+    /// The move played in the game
+    pub played: Move,
+    /// Best move and the engine's expected continuation
+    pub best_line: Vec<Move>,
+    /// Score of the best move, in centipawns from White's side
+    pub best_score: i32,
+    /// Evaluation the played move lost, for its player
+    pub eval_drop: i32,
+    /// How much better the best move is than the second best, for the side
+    /// to move; None if there is only one legal move
+    pub best_move_gap: Option<i32>,
+    /// Whether the best move is the only good one (see [`ONLY_MOVE_GAP`])
+    pub only_move: bool,
+}
+
+impl CriticalPosition {
+    /// How critical the position is: the larger of the evaluation lost and
+    /// the best move's lead.
+    pub fn importance(&self) -> i32 {
+        self.eval_drop.max(self.best_move_gap.unwrap_or(0))
+    }
+}
+
+/// The `first` move and the best replies after it, searched to `depth`.
+fn best_line(state: &GameState, first: &Move, depth: i32) -> Vec<Move> {
+    let mut tt = TranspositionTable::new(STUDY_TT_SIZE);
+    tt.set_game_rules(state);
+    let mut line = vec![first.clone()];
+    let mut state = state.clone();
+    while line.len() < BEST_LINE_PLIES {
+        let Some(mv) = line.last() else { break };
+        let Ok(next) = try_make_move(&state, mv.from, mv.to, mv.promotion) else {
+            break;
+        };
+        state = next;
+        if state.status != GameStatus::Ongoing {
+            break;
+        }
+        let result = find_best_move_iterative(
+            &state.board,
+            state.turn,
+            depth,
+            u64::MAX,
+            &mut tt,
+            true,
+            &state.position_history,
+            state.variant,
+        );
+        match result.best_move {
+            Some(mv) => line.push(mv),
+            None => break,
+        }
+    }
+    line
+}
+
+/// The `k` most critical positions of a game, most critical first: those
+/// where the played move lost the most evaluation (at least
+/// [`INACCURACY_DROP`]) or where only one move was good. Each position is
+/// searched to `depth` for its best and second best moves; replay stops at
+/// the first move that is not legal.
+pub fn critical_positions(game: &PlayedGame, depth: i32, k: usize) -> Vec<CriticalPosition> {
+    let evaluations = summarize(game).evaluations;
+    let mut positions = Vec::new();
+    let mut state = create_new_game();
+    let steps = evaluations.iter().zip(evaluations.iter().skip(1));
+    for (ply, (played, (&before, &after))) in game.moves.iter().zip(steps).enumerate() {
+        let sign = if state.turn == Color::White { 1 } else { -1 };
+        let eval_drop = sign * (before - after);
+        let best = analyze_excluding(&state, &[], depth);
+        if let Some(best_move) = best.best_move {
+            let second = analyze_excluding(&state, std::slice::from_ref(&best_move), depth);
+            let best_move_gap = second.best_move.map(|_| sign * (best.score - second.score));
+            let only_move = best_move_gap.is_some_and(|gap| gap >= ONLY_MOVE_GAP);
+            if eval_drop >= INACCURACY_DROP || only_move {
+                positions.push(CriticalPosition {
+                    ply,
+                    hexfen: game_to_hexfen(&state),
+                    turn: state.turn,
+                    played: played.clone(),
+                    best_line: best_line(&state, &best_move, depth),
+                    best_score: best.score,
+                    eval_drop,
+                    best_move_gap,
+                    only_move,
+                });
+            }
+        }
+        match try_make_move(&state, played.from, played.to, played.promotion) {
+            Ok(next) => state = next,
+            Err(_) => break,
+        }
+    }
+    positions.sort_by_key(|position| (std::cmp::Reverse(position.importance()), position.ply));
+    positions.truncate(k);
+    positions
+}
+
+/// [`critical_positions`] of a game given as JSON (see [`PlayedGame`]), or
+/// None if the JSON is invalid.
+pub fn critical_positions_from_json(
+    json: &str,
+    depth: i32,
+    k: usize,
+) -> Option<Vec<CriticalPosition>> {
+    let game: PlayedGame = serde_json::from_str(json).ok()?;
+    Some(critical_positions(&game, depth, k))
+}
+
+// ============================================================================
+// Engine Plans
+// ============================================================================
+
+/// The engine's principal variation from a game position: the best move of
+/// a search to `depth`, then the best replies stored by that search, up to
+/// `plies` moves. Empty if the game is over.
+pub fn principal_variation(state: &GameState, depth: i32, plies: usize) -> Vec<Move> {
+    if state.status != GameStatus::Ongoing {
+        return Vec::new();
+    }
+    let mut tt = TranspositionTable::new(STUDY_TT_SIZE);
+    tt.set_game_rules(state);
+    let result = find_best_move_iterative(
+        &state.board,
+        state.turn,
+        depth,
+        u64::MAX,
+        &mut tt,
+        true,
+        &state.position_history,
+        state.variant,
+    );
+
+    let mut line = Vec::new();
+    let mut state = state.clone();
+    let mut next = result.best_move;
+    while let Some(mv) = next.filter(|_| line.len() < plies) {
+        // Stored moves may belong to a position reached with the other side to move
+        let Some(after) = replay_move(&state, &mv) else {
+            break;
+        };
+        line.push(mv);
+        state = after;
+        if state.status != GameStatus::Ongoing {
+            break;
+        }
+        next = tt
+            .probe(&state.board)
+            .and_then(|entry| entry.best_move.clone());
+    }
+    line
+}
+
+/// Annotations drawing a principal variation in priority order, at most
+/// `max_arrows` of them: the first move as a solid arrow and the rest as
+/// dashed ones, green for the side to move and red for the opponent. Drops
+/// are highlighted cells, and a move already drawn is not drawn again.
+pub fn pv_to_arrows(pv: &[Move], max_arrows: usize) -> Annotations {
+    let mut annotations = Annotations::default();
+    let Some(mover) = pv.first().map(|mv| mv.piece.color) else {
+        return annotations;
+    };
+    for (ply, mv) in pv.iter().enumerate() {
+        if annotations.arrows.len() + annotations.cells.len() >= max_arrows {
+            break;
+        }
+        let color = if mv.piece.color == mover {
+            AnnotationColor::Green
+        } else {
+            AnnotationColor::Red
+        };
+        if mv.is_drop {
+            if annotations.cells.iter().all(|cell| cell.cell != mv.to) {
+                annotations
+                    .cells
+                    .push(HighlightedCell { cell: mv.to, color });
+            }
+        } else if annotations
+            .arrows
+            .iter()
+            .all(|arrow| (arrow.from, arrow.to) != (mv.from, mv.to))
+        {
+            annotations.arrows.push(Arrow {
+                from: mv.from,
+                to: mv.to,
+                color,
+                style: if ply == 0 {
+                    ArrowStyle::Solid
+                } else {
+                    ArrowStyle::Dashed
+                },
+            });
+        }
+    }
+    annotations
+}
+
+// ============================================================================
+// Position Difficulty
+// ============================================================================
+
+/// Evaluation (centipawns) within which of the best a move counts as near
+/// best.
+pub const NEAR_BEST_MARGIN: i32 = 50;
+
+/// Difficulty at and above which a position is instructive.
+pub const INSTRUCTIVE_DIFFICULTY: f64 = 50.0;
+
+/// Gap between the best and second best moves at which a position counts as
+/// fully "only move" hard.
+const DIFFICULTY_GAP_CAP: i32 = 500;
+
+/// Grade of a position's difficulty, e.g. for puzzles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DifficultyGrade {
+    Easy,
+    Medium,
+    Hard,
+}
+
+/// How hard a position is for a human to find the best move in.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DifficultyScore {
+    pub best_move: Option<Move>,
+    /// How much better the best move is than the second best, for the side
+    /// to move; None with fewer than two legal moves
+    pub best_move_gap: Option<i32>,
+    /// Moves within [`NEAR_BEST_MARGIN`] of the best, itself included
+    pub near_best_moves: usize,
+    pub legal_moves: usize,
+    /// Share of the legal moves that capture or give check
+    pub tactical_density: f64,
+    /// Overall difficulty, 0-100
+    pub score: f64,
+    pub grade: DifficultyGrade,
+}
+
+impl DifficultyGrade {
+    /// Grade of a difficulty score.
+    pub fn from_score(score: f64) -> Self {
+        if score >= 70.0 {
+            DifficultyGrade::Hard
+        } else if score >= 40.0 {
+            DifficultyGrade::Medium
+        } else {
+            DifficultyGrade::Easy
+        }
+    }
+}
+
+impl DifficultyScore {
+    /// Whether the position is worth showing as an instructive moment.
+    pub fn is_instructive(&self) -> bool {
+        self.score >= INSTRUCTIVE_DIFFICULTY
+    }
+}
+
+/// Estimate how hard a position is for `color` to play: every legal move is
+/// searched to `depth`, and a position is harder the more the best move
+/// stands out, the fewer moves come close to it, the more tactical the
+/// moves are and when the best move is a quiet one.
+pub fn difficulty(board: &BoardState, color: Color, depth: i32) -> DifficultyScore {
+    let moves = generate_all_legal_moves(board, color);
+    let sign = if color == Color::White { 1 } else { -1 };
+    let mut tt = TranspositionTable::new(STUDY_TT_SIZE);
+    let mut scored: Vec<(i32, &Move, bool)> = moves
+        .iter()
+        .map(|mv| {
+            let after = apply_move(board, mv);
+            let opponent = color.opposite();
+            let check = is_in_check(&after, opponent);
+            let forcing = mv.captured.is_some() || check;
+            let score = if !generate_all_legal_moves(&after, opponent).is_empty() {
+                let result = find_best_move_iterative(
+                    &after,
+                    opponent,
+                    (depth - 1).max(1),
+                    u64::MAX,
+                    &mut tt,
+                    true,
+                    &[],
+                    Variant::Standard,
+                );
+                sign * result.score
+            } else if check {
+                CHECKMATE_VALUE
+            } else {
+                0
+            };
+            (score, mv, forcing)
+        })
+        .collect();
+    scored.sort_by_key(|&(score, _, _)| std::cmp::Reverse(score));
+
+    let best_score = scored.first().map(|&(score, _, _)| score);
+    let best_move_gap = match scored.as_slice() {
+        [(best, _, _), (second, _, _), ..] => Some(best - second),
+        _ => None,
+    };
+    let near_best_moves = best_score.map_or(0, |best| {
+        scored
+            .iter()
+            .filter(|&&(score, _, _)| best - score <= NEAR_BEST_MARGIN)
+            .count()
+    });
+    let tactical = scored.iter().filter(|&&(_, _, forcing)| forcing).count();
+    let tactical_density = if moves.is_empty() {
+        0.0
+    } else {
+        tactical as f64 / moves.len() as f64
+    };
+
+    let score = if near_best_moves == 0 {
+        0.0
+    } else {
+        let gap = f64::from(best_move_gap.unwrap_or(0).clamp(0, DIFFICULTY_GAP_CAP))
+            / f64::from(DIFFICULTY_GAP_CAP);
+        let quiet_best = scored.first().is_some_and(|&(_, _, forcing)| !forcing);
+        40.0 * gap
+            + 30.0 / near_best_moves as f64
+            + 20.0 * tactical_density
+            + if quiet_best { 10.0 } else { 0.0 }
+    };
+
+    DifficultyScore {
+        best_move: scored.first().map(|&(_, mv, _)| mv.clone()),
+        best_move_gap,
+        near_best_moves,
+        legal_moves: moves.len(),
+        tactical_density,
+        score,
+        grade: DifficultyGrade::from_score(score),
+    }
+}
+
+// ============================================================================
+// Coach Commentary
+// ============================================================================
+
+/// Evaluation a move must lose (centipawns, mover's side), beyond which the
+/// coach suggests the engine's move instead.
+pub const COACH_ALTERNATIVE_LOSS: i32 = MISTAKE_DROP;
+
+/// A piece under threat: attacked while undefended, or by a cheaper piece.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Threat {
+    pub cell: HexCoord,
+    pub piece: Piece,
+}
+
+/// The coach's report on a move, for the coach panel.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoachReport {
+    /// Index of the move in the game
+    pub ply: usize,
+    pub mv: Move,
+    /// Evaluation before and after the move, in centipawns from White's side
+    pub eval_before: i32,
+    pub eval_after: i32,
+    /// Evaluation the mover gained (negative if lost)
+    pub eval_change: i32,
+    /// The engine's move, if the played one lost more than
+    /// [`COACH_ALTERNATIVE_LOSS`]
+    pub better: Option<Move>,
+    /// Most valuable opponent piece the move newly threatens
+    pub threat_created: Option<Threat>,
+    /// Most valuable of the mover's pieces left under threat
+    pub threat_missed: Option<Threat>,
+    /// Advice for the phase of the game reached: "adviceOpening",
+    /// "adviceMiddlegame" or "adviceEndgame"
+    pub advice: Message,
+}
+
+/// Pieces of a color (kings aside) under threat, most valuable first.
+fn threats(board: &BoardState, color: Color) -> Vec<Threat> {
+    let defended = build_attack_map(board, color);
+    let attackers: Vec<(HexCoord, Piece)> = pieces_of(board, color.opposite()).collect();
+    let mut threats: Vec<Threat> = pieces_of(board, color)
+        .filter(|(_, piece)| piece.piece_type != PieceType::King)
+        .filter(|&(cell, piece)| {
+            let value = get_piece_value(piece.piece_type);
+            let mut attacked_by = attackers
+                .iter()
+                .filter(|(from, attacker)| {
+                    get_attacked_cells(board, attacker, *from).contains(&cell)
+                })
+                .map(|(_, attacker)| get_piece_value(attacker.piece_type));
+            let undefended = !defended.contains_key(&cell.to_key());
+            if undefended {
+                attacked_by.next().is_some()
+            } else {
+                attacked_by.any(|attacker| attacker < value)
+            }
+        })
+        .map(|(cell, piece)| Threat { cell, piece })
+        .collect();
+    threats.sort_by_key(|threat| std::cmp::Reverse(get_piece_value(threat.piece.piece_type)));
+    threats
+}
+
+/// Evaluation of a game position from White's side: a search to `depth`,
+/// or the result of a finished game.
+fn position_score(state: &GameState, depth: i32) -> i32 {
+    match GameResult::from_status(&state.status) {
+        None => analyze_excluding(state, &[], depth).score,
+        Some(result) => match result.winner {
+            Some(Color::White) => CHECKMATE_VALUE,
+            Some(Color::Black) => -CHECKMATE_VALUE,
+            None => 0,
+        },
+    }
+}
+
+/// The coach's report on the move that took the game from `before` to
+/// `after`, searching `before` to `depth` and `after` a ply shallower. None
+/// unless `after` is `before` with one more move, or while a boulder
+/// placement is pending.
+pub fn coach_report(before: &GameState, after: &GameState, depth: i32) -> Option<CoachReport> {
+    if after.history.len() != before.history.len() + 1
+        || before.phase != TurnPhase::Move
+        || after.phase != TurnPhase::Move
+    {
+        return None;
+    }
+    let mv = after.history.last()?.clone();
+    let mover = mv.piece.color;
+    let sign = if mover == Color::White { 1 } else { -1 };
+
+    let best = analyze_excluding(before, &[], depth);
+    let eval_before = best.score;
+    let eval_after = position_score(after, depth - 1);
+    let eval_change = sign * (eval_after - eval_before);
+    let better = best.best_move.filter(|best| {
+        -eval_change > COACH_ALTERNATIVE_LOSS
+            && (best.from, best.to, best.promotion, best.is_drop)
+                != (mv.from, mv.to, mv.promotion, mv.is_drop)
+    });
+
+    let threatened_before = threats(&before.board, mover.opposite());
+    let threat_created = threats(&after.board, mover.opposite())
+        .into_iter()
+        .find(|threat| !threatened_before.contains(threat));
+    let threat_missed = threats(&after.board, mover).into_iter().next();
+
+    let advice = match game_phase(&after.board, after.history.len()) {
+        GamePhase::Opening => "adviceOpening",
+        GamePhase::Middlegame => "adviceMiddlegame",
+        GamePhase::Endgame => "adviceEndgame",
+    };
+
+    Some(CoachReport {
+        ply: before.history.len(),
+        mv,
+        eval_before,
+        eval_after,
+        eval_change,
+        better,
+        threat_created,
+        threat_missed,
+        advice: Message::new(advice),
+    })
+}
+
+// ============================================================================
+// Resignation Advisories
+// ============================================================================
+
+/// Evaluation (centipawns, the player's side) at or below which a position
+/// counts as lost.
+pub const RESIGN_EVAL: i32 = -1000;
+
+/// Most opponent moves to a forced mate that make a position lost.
+pub const RESIGN_MATE_MOVES: u32 = 2;
+
+/// Why a position is lost.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ResignReason {
+    /// A loaded tablebase scores the position as lost
+    TablebaseLoss,
+    /// The opponent mates within [`RESIGN_MATE_MOVES`] moves
+    MateIncoming,
+    /// The evaluation is at or below [`RESIGN_EVAL`], with no forcing move
+    /// that holds it above
+    LostEval,
+}
+
+/// A "this position is lost" advisory for a player, for teaching prompts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResignAdvisory {
+    pub reason: ResignReason,
+    /// Evaluation in centipawns from the player's side
+    pub eval: i32,
+    /// Opponent moves to mate, when known
+    pub mate_in: Option<u32>,
+    /// "resignTablebaseLoss", "resignMateIncoming" or "resignLostEval"
+    pub message: Message,
+}
+
+/// Opponent moves to a forced mate of `player`, within `max_moves`.
+fn mate_against(board: &BoardState, turn: Color, player: Color, max_moves: u32) -> Option<u32> {
+    let opponent = player.opposite();
+    if turn == opponent {
+        return find_shortest_mate(board, opponent, max_moves);
+    }
+    let moves = generate_all_legal_moves(board, player);
+    if moves.is_empty() {
+        return None;
+    }
+    (1..=max_moves).find(|&n| {
+        moves
+            .iter()
+            .all(|mv| can_force_mate(&apply_move(board, mv), opponent, n))
+    })
+}
+
+/// Whether `player` has a capture or check, in the position where it next
+/// moves, that a search a ply shallower than `depth` keeps above
+/// [`RESIGN_EVAL`].
+fn has_counterplay(board: &BoardState, player: Color, depth: i32, variant: Variant) -> bool {
+    let sign = if player == Color::White { 1 } else { -1 };
+    let opponent = player.opposite();
+    let mut tt = TranspositionTable::new(STUDY_TT_SIZE);
+    generate_all_legal_moves(board, player).iter().any(|mv| {
+        let after = apply_move(board, mv);
+        if mv.captured.is_none() && !is_in_check(&after, opponent) {
+            return false;
+        }
+        if generate_all_legal_moves(&after, opponent).is_empty() {
+            return true;
+        }
+        let result = find_best_move_iterative(
+            &after,
+            opponent,
+            (depth - 1).max(1),
+            u64::MAX,
+            &mut tt,
+            true,
+            &[],
+            variant,
+        );
+        sign * result.score > RESIGN_EVAL
+    })
+}
+
+/// Whether the game position is lost for `for_color`, searched to `depth`:
+/// a loaded tablebase says so, the opponent mates within
+/// [`RESIGN_MATE_MOVES`] moves, or the evaluation is at or below
+/// [`RESIGN_EVAL`] and no capture or check holds it above. None for an
+/// ongoing game that is not lost, a finished one, or while a boulder
+/// placement is pending.
+pub fn is_resignable(state: &GameState, for_color: Color, depth: i32) -> Option<ResignAdvisory> {
+    if state.status != GameStatus::Ongoing || state.phase != TurnPhase::Move {
+        return None;
+    }
+    let opponent = for_color.opposite();
+    let standard = state.variant == Variant::Standard;
+
+    // Tablebases assume standard rules with stalemates drawn
+    let tablebase = if standard
+        && state.stalemate_rule == StalemateRule::Draw
+        && state.win_conditions == WinConditions::default()
+    {
+        probe_tablebase(&state.board, state.turn).entry
+    } else {
+        None
+    };
+    if let Some(entry) = tablebase {
+        let lost = match entry.wdl {
+            WDLOutcome::Win => state.turn == opponent,
+            WDLOutcome::Loss => state.turn == for_color,
+            WDLOutcome::Draw => false,
+        };
+        return lost.then(|| ResignAdvisory {
+            reason: ResignReason::TablebaseLoss,
+            eval: -CHECKMATE_VALUE + entry.dtm,
+            mate_in: Some((entry.dtm as u32).div_ceil(2)),
+            message: Message::new("resignTablebaseLoss"),
+        });
+    }
+
+    let sign = if for_color == Color::White { 1 } else { -1 };
+    let result = analyze_excluding(state, &[], depth);
+    let eval = sign * result.score;
+    if eval > RESIGN_EVAL {
+        return None;
+    }
+    // Only a mate score is worth confirming with the mate solver
+    let mate_in = (standard && eval < -(CHECKMATE_VALUE - 1000))
+        .then(|| mate_against(&state.board, state.turn, for_color, RESIGN_MATE_MOVES))
+        .flatten();
+    if let Some(moves) = mate_in {
+        return Some(ResignAdvisory {
+            reason: ResignReason::MateIncoming,
+            eval,
+            mate_in: Some(moves),
+            message: Message::new("resignMateIncoming").with("moves", moves),
+        });
+    }
+
+    let board = if state.turn == for_color {
+        state.board.clone()
+    } else {
+        apply_move(&state.board, result.best_move.as_ref()?)
+    };
+    if has_counterplay(&board, for_color, depth, state.variant) {
+        return None;
+    }
+    Some(ResignAdvisory {
+        reason: ResignReason::LostEval,
+        eval,
+        mate_in: None,
+        message: Message::new("resignLostEval"),
+    })
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{play, CHARIOT_BLUNDER};
+
+    #[test]
+    fn test_summarize_game() {
+        let state = play(&CHARIOT_BLUNDER);
+        let mut game = PlayedGame::from_state(&state);
+        game.status = GameStatus::Resigned {
+            winner: Color::Black,
+        };
+        game.times_ms = vec![1_000, 2_000, 9_000, 500];
+        let summary = summarize(&game);
+
+        assert_eq!(summary.result.unwrap().winner, Some(Color::Black));
+        assert_eq!(summary.plies, 4);
+        assert_eq!(summary.evaluations.len(), 5);
+        assert_eq!((summary.white.moves, summary.black.moves), (2, 2));
+        assert_eq!(summary.white.blunders, 1);
+        assert!(summary.white.accuracy < summary.black.accuracy);
+        assert!(summary.white.average_centipawn_loss > summary.black.average_centipawn_loss);
+
+        let find = |kind| summary.moments.iter().find(|m| m.kind == kind).unwrap();
+        let blunder = find(MomentKind::FirstBlunder);
+        assert_eq!((blunder.ply, blunder.color), (2, Color::White));
+        assert!(blunder.eval_drop >= DEFAULT_BLUNDER_DROP);
+        assert_eq!(find(MomentKind::TurningPoint).ply, 2);
+        let longest = find(MomentKind::LongestThink);
+        assert_eq!((longest.ply, longest.spent_ms), (2, Some(9_000)));
+        // The chariot is lost over the blunder and its reply, counted once
+        let swings: Vec<_> = summary
+            .moments
+            .iter()
+            .filter(|m| m.kind == MomentKind::MaterialSwing)
+            .collect();
+        assert_eq!(swings.len(), 1);
+        assert_eq!(swings[0].ply, 2);
+        assert!(swings[0].material_change.unwrap() <= -MATERIAL_SWING);
+        assert!(summary.moments.windows(2).all(|w| w[0].ply <= w[1].ply));
+    }
+
+    #[test]
+    fn test_summarize_quiet_game() {
+        let summary = summarize(&PlayedGame::from_state(&create_new_game()));
+        assert_eq!(summary.result, None);
+        assert!(summary.moments.is_empty());
+        assert_eq!(summary.white, SideSummary::default());
+
+        let summary =
+            summarize_from_json(r#"{"moves": [], "status": {"Resigned": {"winner": "White"}}}"#);
+        assert_eq!(summary.unwrap().result.unwrap().score, "1-0");
+        assert!(summarize_from_json("nope").is_none());
+    }
+
+    #[test]
+    fn test_critical_positions() {
+        let state = play(&CHARIOT_BLUNDER);
+        let positions = critical_positions(&PlayedGame::from_state(&state), 1, 3);
+        assert!(!positions.is_empty() && positions.len() <= 3);
+        assert!(positions
+            .windows(2)
+            .all(|w| w[0].importance() >= w[1].importance()));
+
+        // Taking the chariot is Black's only good move
+        let capture = positions.iter().find(|p| p.ply == 3).unwrap();
+        assert!(capture.only_move);
+        assert_eq!(capture.best_line[0], capture.played);
+
+        let blunder = positions.iter().find(|p| p.ply == 2).unwrap();
+        assert_eq!(blunder.turn, Color::White);
+        assert!(blunder.eval_drop >= DEFAULT_BLUNDER_DROP);
+        assert_ne!(blunder.best_line[0], blunder.played);
+        assert_eq!(blunder.best_line.len(), BEST_LINE_PLIES);
+        // The position loads back for practice
+        let practice = crate::notation::parse_game_hexfen(&blunder.hexfen).unwrap();
+        assert_eq!(practice.turn, Color::White);
+        assert!(try_make_move(
+            &practice,
+            blunder.best_line[0].from,
+            blunder.best_line[0].to,
+            None
+        )
+        .is_ok());
+
+        assert!(critical_positions(&PlayedGame::from_state(&state), 1, 0).is_empty());
+        assert!(critical_positions_from_json("nope", 2, 3).is_none());
+    }
+
+    #[test]
+    fn test_variation_tree() {
+        let mut tree = VariationTree::default();
+        let a = tree
+            .play(HexCoord::new(0, 2), HexCoord::new(0, 1), None)
+            .unwrap();
+        assert_eq!(tree.turn(), Color::Black);
+        assert!(tree
+            .play(HexCoord::new(0, 1), HexCoord::new(0, 0), None)
+            .is_none());
+
+        assert!(tree.back());
+        let b = tree
+            .play(HexCoord::new(1, 2), HexCoord::new(1, 1), None)
+            .unwrap();
+        assert_eq!(tree.nodes[0].children, vec![a, b]);
+
+        // Replaying an existing move reuses its node
+        tree.back();
+        assert_eq!(
+            tree.play(HexCoord::new(0, 2), HexCoord::new(0, 1), None),
+            Some(a)
+        );
+
+        assert!(tree.promote_variation(b));
+        assert_eq!(tree.nodes[0].children, vec![b, a]);
+        assert!(tree.delete_variation(a));
+        assert_eq!(tree.current, 0);
+        assert!(!tree.go_to(a));
+        assert!(!tree.back());
+        assert!(tree.forward());
+        assert_eq!(tree.current_line().len(), 1);
+
+        // Saving drops the deleted variation and renumbers the rest
+        let restored = VariationTree::from_json(&tree.to_json()).unwrap();
+        assert_eq!(restored.nodes.len(), 2);
+        assert_eq!(restored.nodes[0].children, vec![1]);
+        assert_eq!(restored.current, 1);
+        assert_eq!(restored.board(), tree.board());
+
+        // A node missing from its parent's children, or a second root, is rejected
+        let mut broken = restored.clone();
+        broken.nodes[0].children.clear();
+        assert!(VariationTree::from_json(&serde_json::to_string(&broken).unwrap()).is_none());
+        let mut broken = restored;
+        broken.nodes[0].children.clear();
+        broken.nodes[1].parent = None;
+        assert!(VariationTree::from_json(&serde_json::to_string(&broken).unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_annotations_survive_save() {
+        let mut tree = VariationTree::default();
+        tree.play(HexCoord::new(0, 2), HexCoord::new(0, 1), None);
+        tree.annotations_mut().unwrap().toggle_arrow(
+            HexCoord::new(0, -2),
+            HexCoord::new(0, -1),
+            crate::annotations::AnnotationColor::Red,
+        );
+        tree.back();
+        assert!(tree.annotations().is_empty());
+
+        let restored = VariationTree::from_json(&tree.to_json()).unwrap();
+        assert_eq!(restored, tree);
+        assert_eq!(restored.nodes[1].annotations.arrows.len(), 1);
+        assert!(VariationTree::from_json("{}").is_none());
+    }
+
+    #[test]
+    fn test_free_editing_and_analysis() {
+        let mut tree = VariationTree::default();
+        tree.set_piece(
+            HexCoord::new(0, 0),
+            Some(Piece::new(PieceType::Queen, Color::Black)),
+        );
+        tree.set_turn(Color::Black);
+        assert_eq!(tree.nodes.len(), 1);
+        assert_eq!(tree.turn(), Color::Black);
+
+        let mut session = AnalysisSession::new();
+        let mut tt = TranspositionTable::new(1000);
+        let first = session.step(&tree, &mut tt, 2).unwrap();
+        let second = session.step(&tree, &mut tt, 2).unwrap();
+        assert_eq!((first.depth, second.depth), (1, 2));
+        assert!(second.best_move.is_some());
+        assert!(session.step(&tree, &mut tt, 2).is_none());
+
+        // Editing the position restarts the analysis at the same root node
+        tree.set_piece(HexCoord::new(0, 0), None);
+        assert_eq!(tree.current, 0);
+        assert_eq!(session.step(&tree, &mut tt, 2).unwrap().depth, 1);
+    }
+
+    #[test]
+    fn test_engine_plan_arrows() {
+        let game = create_new_game();
+        let pv = principal_variation(&game, 2, 4);
+        assert!(!pv.is_empty() && pv.len() <= 4);
+        let arrows = pv_to_arrows(&pv, 3);
+        assert!(arrows.arrows.len() <= 3);
+        let first = arrows.arrows[0];
+        assert_eq!((first.from, first.to), (pv[0].from, pv[0].to));
+        assert_eq!(
+            (first.color, first.style),
+            (AnnotationColor::Green, ArrowStyle::Solid)
+        );
+        if let Some(reply) = arrows.arrows.get(1) {
+            assert_eq!(
+                (reply.color, reply.style),
+                (AnnotationColor::Red, ArrowStyle::Dashed)
+            );
+        }
+        assert!(pv_to_arrows(&pv, 0).is_empty());
+        assert!(pv_to_arrows(&[], 3).is_empty());
+
+        // A move repeated later in the line is drawn once
+        let repeated = [pv[0].clone(), pv[0].clone()];
+        assert_eq!(pv_to_arrows(&repeated, 5).arrows.len(), 1);
+    }
+
+    #[test]
+    fn test_difficulty() {
+        // A lone queen move mates; nothing else comes close
+        let (board, side) = crate::notation::parse_hexfen("4kpQ2/3p5/9/9/9/9/9/9/4K4 w").unwrap();
+        let puzzle = difficulty(&board, side, 2);
+        let best = puzzle.best_move.clone().unwrap();
+        assert_eq!(
+            (best.from, best.to),
+            (HexCoord::new(2, -4), HexCoord::new(0, -2))
+        );
+        assert!(puzzle.best_move_gap.unwrap() >= DIFFICULTY_GAP_CAP);
+        assert_eq!(puzzle.near_best_moves, 1);
+        assert!(puzzle.is_instructive());
+
+        // The opening has many playable moves and no tactics
+        let start = create_new_game();
+        let opening = difficulty(&start.board, start.turn, 1);
+        assert_eq!(
+            opening.legal_moves,
+            generate_all_legal_moves(&start.board, start.turn).len()
+        );
+        assert!(opening.near_best_moves > 1);
+        assert_eq!(opening.tactical_density, 0.0);
+        assert!(opening.score < puzzle.score);
+    }
+
+    #[test]
+    fn test_coach_report() {
+        let before = play(&CHARIOT_BLUNDER[..2]);
+        let blunder = play(&CHARIOT_BLUNDER[..3]);
+        let report = coach_report(&before, &blunder, 2).unwrap();
+        assert_eq!(report.ply, 2);
+        assert_eq!(report.mv.to, HexCoord::new(2, 0));
+        assert!(report.eval_change < -COACH_ALTERNATIVE_LOSS);
+        let better = report.better.unwrap();
+        assert_ne!((better.from, better.to), (report.mv.from, report.mv.to));
+        let missed = report.threat_missed.unwrap();
+        assert_eq!(missed.cell, HexCoord::new(2, 0));
+        assert_eq!(missed.piece.piece_type, PieceType::Chariot);
+        assert_eq!(report.advice.key, "adviceOpening");
+
+        // Taking the chariot loses nothing, so no better move is offered
+        let capture = play(&CHARIOT_BLUNDER);
+        let report = coach_report(&blunder, &capture, 2).unwrap();
+        assert!(report.eval_change >= -COACH_ALTERNATIVE_LOSS);
+        assert_eq!(report.better, None);
+
+        // The states must be one move apart
+        assert!(coach_report(&before, &capture, 2).is_none());
+    }
+
+    #[test]
+    fn test_resign_advisories() {
+        use crate::game::create_game_from_position;
+
+        let position = |pieces: &[((i32, i32), PieceType, Color)], turn| {
+            let mut board = BoardState::new();
+            for &((q, r), piece_type, color) in pieces {
+                board.insert(HexCoord::new(q, r).to_key(), Piece::new(piece_type, color));
+            }
+            create_game_from_position(board, turn)
+        };
+
+        // Black's queen and chariot against a lone pawn
+        let lost = position(
+            &[
+                ((0, 4), PieceType::King, Color::White),
+                ((-2, 4), PieceType::Pawn, Color::White),
+                ((0, -4), PieceType::King, Color::Black),
+                ((-3, 0), PieceType::Queen, Color::Black),
+                ((3, -2), PieceType::Chariot, Color::Black),
+                ((1, -4), PieceType::Pawn, Color::Black),
+            ],
+            Color::White,
+        );
+        let advisory = is_resignable(&lost, Color::White, 3).unwrap();
+        assert_eq!(advisory.reason, ResignReason::LostEval);
+        assert!(advisory.eval <= RESIGN_EVAL);
+        assert_eq!(advisory.message.key, "resignLostEval");
+        assert_eq!(is_resignable(&lost, Color::Black, 3), None);
+
+        // White's queen mates on (0, -2)
+        let mated = position(
+            &[
+                ((0, 4), PieceType::King, Color::White),
+                ((2, -4), PieceType::Queen, Color::White),
+                ((0, -4), PieceType::King, Color::Black),
+                ((-1, -3), PieceType::Pawn, Color::Black),
+                ((1, -4), PieceType::Pawn, Color::Black),
+            ],
+            Color::White,
+        );
+        let advisory = is_resignable(&mated, Color::Black, 3).unwrap();
+        assert_eq!(advisory.reason, ResignReason::MateIncoming);
+        assert_eq!(advisory.mate_in, Some(1));
+
+        assert_eq!(is_resignable(&create_new_game(), Color::White, 2), None);
+    }
+}
@@ -0,0 +1,269 @@
+//! Underchex Board Annotations
+//!
+//! Arrows between hexes and highlighted cells drawn on a position for study.
+//! Annotations are saved as JSON with the rest of a variation tree. The crate
+//! writes no PGN itself; for clients that do, annotations also encode as text
+//! commands for move comments, following the `[%csl ...]` / `[%cal ...]`
+//! convention used by chess tools:
+//!
+//! - `[%csl G0:2,R-1:3]` highlights cells (q, r) = (0, 2) green and (-1, 3) red
+//! - `[%cal G0:2>0:0]` draws a green arrow from (0, 2) to (0, 0)
+//!
+//! Cells are written `q:r` and colors as `G`, `R`, `Y` or `B`. Arrow styles
+//! are not written in comments, so dashed arrows come back solid.
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::HexCoord;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// Color of an arrow or highlighted cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum AnnotationColor {
+    #[default]
+    Green,
+    Red,
+    Yellow,
+    Blue,
+}
+
+impl AnnotationColor {
+    /// Single-letter code used in comments.
+    pub fn letter(&self) -> char {
+        match self {
+            AnnotationColor::Green => 'G',
+            AnnotationColor::Red => 'R',
+            AnnotationColor::Yellow => 'Y',
+            AnnotationColor::Blue => 'B',
+        }
+    }
+
+    /// Parse a color letter (either case).
+    pub fn from_letter(letter: char) -> Option<Self> {
+        match letter.to_ascii_uppercase() {
+            'G' => Some(AnnotationColor::Green),
+            'R' => Some(AnnotationColor::Red),
+            'Y' => Some(AnnotationColor::Yellow),
+            'B' => Some(AnnotationColor::Blue),
+            _ => None,
+        }
+    }
+}
+
+/// How an arrow is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum ArrowStyle {
+    #[default]
+    Solid,
+    /// For planned moves further ahead
+    Dashed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Arrow {
+    pub from: HexCoord,
+    pub to: HexCoord,
+    pub color: AnnotationColor,
+    #[serde(default)]
+    pub style: ArrowStyle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HighlightedCell {
+    pub cell: HexCoord,
+    pub color: AnnotationColor,
+}
+
+/// Arrows and highlighted cells attached to a position.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Annotations {
+    #[serde(default)]
+    pub arrows: Vec<Arrow>,
+    #[serde(default)]
+    pub cells: Vec<HighlightedCell>,
+}
+
+// ============================================================================
+// Editing
+// ============================================================================
+
+impl Annotations {
+    pub fn is_empty(&self) -> bool {
+        self.arrows.is_empty() && self.cells.is_empty()
+    }
+
+    /// Toggle an arrow: drawing the same arrow again removes it, and drawing
+    /// it in another color recolors it.
+    pub fn toggle_arrow(&mut self, from: HexCoord, to: HexCoord, color: AnnotationColor) {
+        match self
+            .arrows
+            .iter_mut()
+            .enumerate()
+            .find(|(_, a)| a.from == from && a.to == to)
+        {
+            Some((index, arrow)) if arrow.color == color => {
+                self.arrows.remove(index);
+            }
+            Some((_, arrow)) => arrow.color = color,
+            None => self.arrows.push(Arrow {
+                from,
+                to,
+                color,
+                style: ArrowStyle::Solid,
+            }),
+        }
+    }
+
+    /// Toggle a highlighted cell (same rules as [`Self::toggle_arrow`]).
+    pub fn toggle_cell(&mut self, cell: HexCoord, color: AnnotationColor) {
+        match self
+            .cells
+            .iter_mut()
+            .enumerate()
+            .find(|(_, c)| c.cell == cell)
+        {
+            Some((index, highlighted)) if highlighted.color == color => {
+                self.cells.remove(index);
+            }
+            Some((_, highlighted)) => highlighted.color = color,
+            None => self.cells.push(HighlightedCell { cell, color }),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.arrows.clear();
+        self.cells.clear();
+    }
+}
+
+// ============================================================================
+// Comment Encoding
+// ============================================================================
+
+fn format_cell(cell: HexCoord) -> String {
+    format!("{}:{}", cell.q, cell.r)
+}
+
+fn parse_cell(text: &str) -> Option<HexCoord> {
+    let (q, r) = text.split_once(':')?;
+    Some(HexCoord::new(q.parse().ok()?, r.parse().ok()?))
+}
+
+impl Annotations {
+    /// Encode as comment commands (empty string when there is nothing to draw).
+    pub fn to_comment(&self) -> String {
+        let mut comment = String::new();
+        if !self.cells.is_empty() {
+            let cells: Vec<String> = self
+                .cells
+                .iter()
+                .map(|c| format!("{}{}", c.color.letter(), format_cell(c.cell)))
+                .collect();
+            comment.push_str(&format!("[%csl {}]", cells.join(",")));
+        }
+        if !self.arrows.is_empty() {
+            let arrows: Vec<String> = self
+                .arrows
+                .iter()
+                .map(|a| {
+                    format!(
+                        "{}{}>{}",
+                        a.color.letter(),
+                        format_cell(a.from),
+                        format_cell(a.to)
+                    )
+                })
+                .collect();
+            comment.push_str(&format!("[%cal {}]", arrows.join(",")));
+        }
+        comment
+    }
+
+    /// Parse the `[%csl ...]` and `[%cal ...]` commands out of a comment,
+    /// ignoring any other text. Returns None if a command is malformed.
+    pub fn from_comment(comment: &str) -> Option<Self> {
+        let mut annotations = Annotations::default();
+        let mut rest = comment;
+        while let Some(start) = rest.find("[%") {
+            let end = start + rest[start..].find(']')?;
+            let command = &rest[start + 2..end];
+            rest = &rest[end + 1..];
+
+            let (name, args) = command.split_once(' ').unwrap_or((command, ""));
+            if name != "csl" && name != "cal" {
+                continue; // Other commands (e.g. [%clk ...]) are not ours
+            }
+            for entry in args.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+                let color = AnnotationColor::from_letter(entry.chars().next()?)?;
+                let body = &entry[1..];
+                match name {
+                    "csl" => annotations.cells.push(HighlightedCell {
+                        cell: parse_cell(body)?,
+                        color,
+                    }),
+                    _ => {
+                        let (from, to) = body.split_once('>')?;
+                        annotations.arrows.push(Arrow {
+                            from: parse_cell(from)?,
+                            to: parse_cell(to)?,
+                            color,
+                            style: ArrowStyle::Solid,
+                        });
+                    }
+                }
+            }
+        }
+        Some(annotations)
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toggle() {
+        let mut annotations = Annotations::default();
+        let (a, b) = (HexCoord::new(0, 2), HexCoord::new(0, 0));
+        annotations.toggle_arrow(a, b, AnnotationColor::Green);
+        annotations.toggle_arrow(a, b, AnnotationColor::Red);
+        assert_eq!(annotations.arrows.len(), 1);
+        assert_eq!(annotations.arrows[0].color, AnnotationColor::Red);
+        annotations.toggle_arrow(a, b, AnnotationColor::Red);
+        assert!(annotations.is_empty());
+
+        annotations.toggle_cell(a, AnnotationColor::Blue);
+        annotations.toggle_cell(a, AnnotationColor::Blue);
+        assert!(annotations.is_empty());
+    }
+
+    #[test]
+    fn test_comment_round_trip() {
+        let mut annotations = Annotations::default();
+        annotations.toggle_cell(HexCoord::new(-1, 3), AnnotationColor::Red);
+        annotations.toggle_cell(HexCoord::new(0, 0), AnnotationColor::Yellow);
+        annotations.toggle_arrow(
+            HexCoord::new(0, 2),
+            HexCoord::new(-2, 0),
+            AnnotationColor::Green,
+        );
+
+        let comment = annotations.to_comment();
+        assert_eq!(comment, "[%csl R-1:3,Y0:0][%cal G0:2>-2:0]");
+        assert_eq!(Annotations::from_comment(&comment), Some(annotations));
+
+        let parsed =
+            Annotations::from_comment("Good move [%clk 0:01:00] [%cal B1:1>1:-1]").unwrap();
+        assert_eq!(parsed.arrows.len(), 1);
+        assert!(parsed.cells.is_empty());
+        assert_eq!(Annotations::from_comment("[%csl Q0:0]"), None);
+        assert_eq!(Annotations::from_comment(""), Some(Annotations::default()));
+    }
+}
@@ -0,0 +1,204 @@
+//! Underchex Attack Maps
+//!
+//! Which cells each side attacks (and how many times), and legal moves
+//! annotated with the risk of their destination for highlighting in UIs.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::board::{get_neighbor, get_ray, is_valid_cell};
+use crate::moves::{apply_move, get_pawn_capture_directions, is_in_check, is_occupied};
+use crate::pieces::get_piece_definition;
+use crate::types::{BoardState, Color, HexCoord, Move, Piece, PieceType};
+
+// ============================================================================
+// Attack Maps
+// ============================================================================
+
+/// Number of attackers per cell, keyed by "q,r" like [`BoardState`].
+/// Cells nobody attacks are absent.
+pub type AttackMap = HashMap<String, u32>;
+
+/// Cells a piece attacks from a square: where it could capture an enemy piece,
+/// including cells holding friendly pieces (which it defends).
+pub fn get_attacked_cells(board: &BoardState, piece: &Piece, from: HexCoord) -> Vec<HexCoord> {
+    if piece.piece_type == PieceType::Pawn {
+        return get_pawn_capture_directions(piece.color)
+            .iter()
+            .filter_map(|&dir| get_neighbor(from, dir))
+            .collect();
+    }
+
+    let definition = get_piece_definition(piece);
+    let mut cells: Vec<HexCoord> = definition
+        .steps
+        .iter()
+        .filter_map(|&dir| get_neighbor(from, dir))
+        .collect();
+    cells.extend(
+        definition
+            .leaps
+            .iter()
+            .map(|&(dq, dr)| HexCoord::new(from.q + dq, from.r + dr))
+            .filter(|&cell| is_valid_cell(cell)),
+    );
+
+    for &dir in definition.slides {
+        for cell in get_ray(from, dir) {
+            cells.push(cell);
+            if is_occupied(board, cell) {
+                break;
+            }
+        }
+    }
+
+    for &dir in definition.hops {
+        let beyond_screen = get_ray(from, dir)
+            .into_iter()
+            .skip_while(|&cell| !is_occupied(board, cell))
+            .skip(1);
+        for cell in beyond_screen {
+            cells.push(cell);
+            if is_occupied(board, cell) {
+                break;
+            }
+        }
+    }
+
+    cells
+}
+
+/// Build the attack map of every piece of a color.
+pub fn build_attack_map(board: &BoardState, color: Color) -> AttackMap {
+    let mut map = AttackMap::new();
+    let pieces = board
+        .iter()
+        .filter(|(_, piece)| piece.color == color && !piece.is_boulder())
+        .filter_map(|(key, piece)| HexCoord::from_key(key).map(|from| (piece, from)));
+    for (piece, from) in pieces {
+        for cell in get_attacked_cells(board, piece, from) {
+            *map.entry(cell.to_key()).or_insert(0) += 1;
+        }
+    }
+    map
+}
+
+// ============================================================================
+// Annotated Moves
+// ============================================================================
+
+/// A legal move with information about its destination.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnnotatedMove {
+    #[serde(flatten)]
+    pub mv: Move,
+    pub capture: bool,
+    /// Number of opponent pieces attacking the destination after the move
+    pub defenders: u32,
+    /// Whether the destination is defended by the opponent (defenders > 0)
+    pub defended: bool,
+    pub gives_check: bool,
+}
+
+/// Annotate moves with capture, defence and check information. Defence is
+/// measured after the move, so pieces uncovered by the move are counted.
+pub fn annotate_moves(board: &BoardState, moves: Vec<Move>) -> Vec<AnnotatedMove> {
+    moves
+        .into_iter()
+        .map(|mv| {
+            let after = apply_move(board, &mv);
+            let opponent = mv.piece.color.opposite();
+            let defenders = build_attack_map(&after, opponent)
+                .get(&mv.to.to_key())
+                .copied()
+                .unwrap_or(0);
+            AnnotatedMove {
+                capture: mv.captured.is_some(),
+                defenders,
+                defended: defenders > 0,
+                gives_check: is_in_check(&after, opponent),
+                mv,
+            }
+        })
+        .collect()
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::get_all_cells;
+    use crate::game::create_new_game;
+    use crate::moves::{generate_legal_moves, is_attacked};
+
+    #[test]
+    fn test_attack_map_matches_is_attacked() {
+        let mut board = create_new_game().board;
+        board.insert(
+            "0,0".to_string(),
+            Piece::new(PieceType::Cannon, Color::White),
+        );
+        board.insert(
+            "-1,0".to_string(),
+            Piece::new(PieceType::Dabbaba, Color::Black),
+        );
+
+        for color in [Color::White, Color::Black] {
+            let map = build_attack_map(&board, color);
+            for cell in get_all_cells() {
+                assert_eq!(
+                    map.contains_key(&cell.to_key()),
+                    is_attacked(&board, cell, color),
+                    "{:?} {:?}",
+                    color,
+                    cell
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_annotate_moves() {
+        let mut board = BoardState::new();
+        board.insert("0,4".to_string(), Piece::new(PieceType::King, Color::White));
+        board.insert(
+            "0,-4".to_string(),
+            Piece::new(PieceType::King, Color::Black),
+        );
+        let queen = Piece::new(PieceType::Queen, Color::White);
+        board.insert("0,0".to_string(), queen);
+        board.insert(
+            "2,-2".to_string(),
+            Piece::new(PieceType::Pawn, Color::Black),
+        );
+        board.insert(
+            "3,-3".to_string(),
+            Piece::new(PieceType::Pawn, Color::Black),
+        );
+
+        let annotated = annotate_moves(
+            &board,
+            generate_legal_moves(&board, &queen, HexCoord::new(0, 0)),
+        );
+        let at = |q, r| {
+            annotated
+                .iter()
+                .find(|a| a.mv.to == HexCoord::new(q, r))
+                .unwrap()
+        };
+
+        let capture = at(2, -2);
+        assert!(capture.capture && capture.defended);
+        assert_eq!(capture.defenders, 1);
+        assert!(at(0, -3).gives_check);
+        assert!(!at(0, 1).defended && !at(0, 1).capture);
+
+        let json: serde_json::Value =
+            serde_json::from_str(&serde_json::to_string(capture).unwrap()).unwrap();
+        assert_eq!(json["to"]["q"], 2);
+        assert_eq!(json["defended"], true);
+    }
+}
@@ -0,0 +1,508 @@
+//! Developer tool for checking the engine.
+//!
+//! Usage:
+//!   underchex-devtool perft <depth> [hexfen] [--divide]
+//!       Count move-tree leaves (from the starting position by default)
+//!   underchex-devtool zobrist [games] [plies] [seed]
+//!       Count hash collisions over positions from random games
+//!   underchex-devtool tt-stress [games] [plies] [size] [seed]
+//!       Store and probe random-game positions in a small transposition table
+//!   underchex-devtool eval-symmetry [positions] [seed]
+//!       Check evaluation invariants under color flips and board symmetries
+//!   underchex-devtool options
+//!       List the engine options
+//!   underchex-devtool simulate [white] [black] [plies]
+//!       Play an AI-vs-AI game (levels easy, medium or hard, or two bot
+//!       names such as mcts) and print it
+//!   underchex-devtool bench [positions] [depth] [seed]
+//!       Search the bench suite and report nodes and move ordering quality
+//!   underchex-devtool tactics [depth]
+//!       Search the tactics suite and report the positions solved
+//!   underchex-devtool tune-ordering [positions] [depth] [rounds] [config]
+//!       Tune the move ordering weights on the bench suite and write the
+//!       resulting options to a config file (ordering.json by default)
+//!   underchex-devtool serve
+//!       Answer analysis requests on stdin, keeping the engine warm between
+//!       them. Commands, one per line: `id`, `position <game HexFEN>`,
+//!       `go [depth]`, `setoption name <name> value <value>`, `newgame`,
+//!       `clear` and `quit`. Ctrl-C stops a `go`, which answers with its
+//!       deepest finished iteration, and otherwise quits
+//!
+//! Any command takes `--set <name>=<value>` to set an engine option, and
+//! `--config <file>` to load an options config, first.
+//! Exits with status 1 if a check fails.
+
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use underchex_wasm::ai::{tt_stress_test, AIDifficulty, AIOptions};
+use underchex_wasm::bots::{create_bot, play_bot_game, SearchLimits};
+use underchex_wasm::engine::{engine_info, Engine};
+use underchex_wasm::eval::{self_test_with, DEFAULT_SELF_TEST_POSITIONS};
+use underchex_wasm::game::create_new_game;
+use underchex_wasm::moves::{perft, perft_divide};
+use underchex_wasm::notation::{parse_game_hexfen, parse_hexfen};
+use underchex_wasm::options::{
+    list_options, load_options_config, options_config, set_option, EngineOption,
+};
+use underchex_wasm::simulation::simulate_game;
+use underchex_wasm::tactics::{run_tactics_suite, DEFAULT_TACTICS_DEPTH};
+use underchex_wasm::tuning::{
+    bench_suite, run_bench, tune_move_ordering, BenchReport, DEFAULT_BENCH_DEPTH,
+    DEFAULT_BENCH_POSITIONS,
+};
+use underchex_wasm::types::{Color, GameState};
+use underchex_wasm::zobrist::collision_stats;
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: underchex-devtool <command> [args]\n\
+         \x20 perft <depth> [hexfen] [--divide]\n\
+         \x20 zobrist [games] [plies] [seed]\n\
+         \x20 tt-stress [games] [plies] [size] [seed]\n\
+         \x20 eval-symmetry [positions] [seed]\n\
+         \x20 options\n\
+         \x20 simulate [white] [black] [plies]\n\
+         \x20 bench [positions] [depth] [seed]\n\
+         \x20 tactics [depth]\n\
+         \x20 tune-ordering [positions] [depth] [rounds] [config]\n\
+         \x20 serve\n\
+         options: --set <name>=<value>, --config <file>"
+    );
+    process::exit(2);
+}
+
+/// The n-th positional argument as a number, or `default` if absent.
+fn number_arg<T: std::str::FromStr>(args: &[String], n: usize, default: T) -> T {
+    args.get(n)
+        .map(|arg| arg.parse().unwrap_or_else(|_| usage()))
+        .unwrap_or(default)
+}
+
+/// Apply and remove the `--set <name>=<value>` and `--config <file>`
+/// arguments.
+fn apply_option_args(args: Vec<String>) -> Vec<String> {
+    let mut rest = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            let path = args.next().unwrap_or_else(|| usage());
+            let loaded = fs::read_to_string(&path)
+                .map_err(|error| error.to_string())
+                .and_then(|json| load_options_config(&json));
+            if let Err(reason) = loaded {
+                eprintln!("cannot load {}: {}", path, reason);
+                process::exit(2);
+            }
+            continue;
+        }
+        if arg != "--set" {
+            rest.push(arg);
+            continue;
+        }
+        let setting = args.next().unwrap_or_else(|| usage());
+        let (name, value) = setting.split_once('=').unwrap_or_else(|| usage());
+        if let Err(reason) = set_option(name, value) {
+            eprintln!("cannot set {}: {}", name, reason);
+            process::exit(2);
+        }
+    }
+    rest
+}
+
+fn main() {
+    let args = apply_option_args(env::args().skip(1).collect());
+    match args.first().map(String::as_str) {
+        Some("perft") => run_perft(&args[1..]),
+        Some("zobrist") => {
+            let games = number_arg(&args, 1, 1000);
+            let plies = number_arg(&args, 2, 80);
+            let seed = number_arg(&args, 3, 1);
+            let start = create_new_game().board;
+            let report = collision_stats(&start, Color::White, games, plies, seed);
+            println!(
+                "{} games, {} positions ({} distinct)",
+                report.games, report.positions, report.distinct_positions
+            );
+            println!(
+                "64-bit collisions: {} (expected {:.2e})",
+                report.collisions, report.expected_collisions
+            );
+            println!(
+                "32-bit collisions: {} (expected {:.1})",
+                report.collisions_32, report.expected_collisions_32
+            );
+            if report.collisions > 0 {
+                process::exit(1);
+            }
+        }
+        Some("tt-stress") => {
+            let games = number_arg(&args, 1, 200);
+            let plies = number_arg(&args, 2, 80);
+            let size = number_arg(&args, 3, 1000);
+            let seed = number_arg(&args, 4, 1);
+            let report = tt_stress_test(games, plies, size, seed);
+            println!(
+                "{} stores, {} probes, {} hits, {} wrong hits, peak size {} of {}",
+                report.stores,
+                report.probes,
+                report.hits,
+                report.wrong_hits,
+                report.peak_size,
+                size
+            );
+            if !report.passed(size) {
+                process::exit(1);
+            }
+        }
+        Some("eval-symmetry") => {
+            let positions = number_arg(&args, 1, DEFAULT_SELF_TEST_POSITIONS);
+            let seed = number_arg(&args, 2, 1);
+            let report = self_test_with(positions, seed);
+            for failure in &report.failures {
+                println!(
+                    "FAIL {}: expected {}, got {} for {}",
+                    failure.transform, failure.expected, failure.actual, failure.fen
+                );
+            }
+            println!(
+                "{} positions, {} checks, {} failures",
+                report.positions,
+                report.checks,
+                report.failures.len()
+            );
+            if !report.passed() {
+                process::exit(1);
+            }
+        }
+        Some("options") => {
+            for option in list_options() {
+                println!(
+                    "{} {:?} = {} (default {}, {}..{}): {}",
+                    option.name,
+                    option.kind,
+                    option.value,
+                    option.default,
+                    option.min,
+                    option.max,
+                    option.description
+                );
+            }
+        }
+        Some("simulate") => {
+            let name = |n: usize| args.get(n).map_or("medium", String::as_str);
+            let level = |n: usize| match name(n) {
+                "medium" => Some(AIOptions::new(AIDifficulty::Medium)),
+                "easy" => Some(AIOptions::new(AIDifficulty::Easy)),
+                "hard" => Some(AIOptions::new(AIDifficulty::Hard)),
+                _ => None,
+            };
+            let plies = number_arg(&args, 3, 200);
+            let Some((white, black)) = level(1).zip(level(2)) else {
+                simulate_bots(name(1), name(2), plies);
+                return;
+            };
+            let started = Instant::now();
+            let game = simulate_game(&white, &black, plies);
+            for (ply, (mv, score)) in game.moves.iter().zip(&game.scores).enumerate() {
+                println!(
+                    "{:3}. {} -> {} ({})",
+                    ply + 1,
+                    mv.from.to_key(),
+                    mv.to.to_key(),
+                    score
+                );
+            }
+            println!(
+                "{:?}{} after {} plies in {:.1}s",
+                game.status,
+                game.adjudication
+                    .map(|reason| format!(" (adjudicated: {})", reason))
+                    .unwrap_or_default(),
+                game.moves.len(),
+                started.elapsed().as_secs_f64()
+            );
+        }
+        Some("bench") => {
+            let positions = number_arg(&args, 1, DEFAULT_BENCH_POSITIONS);
+            let depth = number_arg(&args, 2, DEFAULT_BENCH_DEPTH);
+            let seed = number_arg(&args, 3, 1);
+            let started = Instant::now();
+            let report = run_bench(&bench_suite(positions, seed), depth);
+            print_bench("bench", &report);
+            println!("{:.1}s", started.elapsed().as_secs_f64());
+        }
+        Some("tactics") => {
+            let depth = number_arg(&args, 1, DEFAULT_TACTICS_DEPTH);
+            let report = run_tactics_suite(depth);
+            for result in &report.results {
+                println!(
+                    "{} {} ({}): {} score {} nodes {}",
+                    if result.solved { "ok  " } else { "FAIL" },
+                    result.id,
+                    result.theme,
+                    result.found.as_deref().unwrap_or("none"),
+                    result.score,
+                    result.nodes
+                );
+            }
+            println!(
+                "{}/{} solved at depth {}, {} nodes",
+                report.solved, report.positions, report.depth, report.nodes
+            );
+            if report.solved < report.positions {
+                process::exit(1);
+            }
+        }
+        Some("tune-ordering") => {
+            let positions = number_arg(&args, 1, DEFAULT_BENCH_POSITIONS);
+            let depth = number_arg(&args, 2, DEFAULT_BENCH_DEPTH);
+            let rounds = number_arg(&args, 3, 4);
+            let path = args.get(4).map_or("ordering.json", String::as_str);
+            let result = tune_move_ordering(&bench_suite(positions, 1), depth, rounds);
+            print_bench("before", &result.baseline);
+            print_bench("after", &result.tuned);
+            for (name, value) in &result.weights {
+                println!("{} = {}", name, value);
+                // Tuned values are within the options' bounds
+                let _ = set_option(name, &value.to_string());
+            }
+            if let Err(error) = fs::write(path, options_config()) {
+                eprintln!("cannot write {}: {}", path, error);
+                process::exit(1);
+            }
+            println!("wrote {}", path);
+        }
+        Some("serve") => serve(),
+        _ => usage(),
+    }
+}
+
+/// Search depth of a `go` without one.
+const SERVE_DEPTH: i32 = 4;
+
+/// Answer `serve` commands from stdin until `quit` or end of input. Each
+/// answer is one line: `ok`, `error <reason>`, or for `go`
+/// `bestmove <from> <to> score <score> depth <depth> nodes <nodes>` followed
+/// by `continued` and `reused` flags (`bestmove none` without legal moves).
+/// A `go` stopped by Ctrl-C gives the depth it finished.
+/// `id` is answered with the engine's `id` and `option` lines, then `ok`.
+/// `setoption` sets one of those options for the following searches, which
+/// start from empty tables.
+fn serve() {
+    let mut engine = Engine::new();
+    let mut state = create_new_game();
+    let searching = Arc::new(AtomicBool::new(false));
+    let handler = {
+        let cancel = engine.cancel_token();
+        let searching = Arc::clone(&searching);
+        ctrlc::set_handler(move || {
+            if searching.load(Ordering::SeqCst) {
+                cancel.cancel();
+            } else {
+                process::exit(130);
+            }
+        })
+    };
+    if let Err(error) = handler {
+        eprintln!("cannot handle Ctrl-C: {}", error);
+    }
+    let mut out = io::stdout().lock();
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let (command, rest) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+        let answer = match command {
+            "" => continue,
+            "quit" => break,
+            "id" => {
+                let mut lines = engine_info().protocol_lines();
+                lines.push("ok".to_string());
+                lines.join("\n")
+            }
+            "position" => match parse_game_hexfen(rest) {
+                Some(next) => {
+                    state = next;
+                    "ok".to_string()
+                }
+                None => "error invalid HexFEN".to_string(),
+            },
+            "go" => {
+                let depth = match rest.trim() {
+                    "" => Some(SERVE_DEPTH),
+                    depth => depth.parse().ok().filter(|&depth| depth > 0),
+                };
+                match depth {
+                    Some(depth) => {
+                        searching.store(true, Ordering::SeqCst);
+                        let answer = go(&mut engine, &state, depth);
+                        searching.store(false, Ordering::SeqCst);
+                        answer
+                    }
+                    None => "error invalid depth".to_string(),
+                }
+            }
+            "setoption" => {
+                let setting = rest
+                    .trim()
+                    .strip_prefix("name ")
+                    .and_then(|setting| setting.split_once(" value "));
+                match setting {
+                    Some((name, value)) => match set_option(name.trim(), value.trim()) {
+                        Ok(()) => {
+                            // Earlier results may not hold under the new setting
+                            if EngineOption::from_name(name.trim()) == Some(EngineOption::TTSize) {
+                                engine.resize(EngineOption::TTSize.get() as usize);
+                            } else {
+                                engine.clear();
+                            }
+                            "ok".to_string()
+                        }
+                        Err(reason) => format!("error {}", reason),
+                    },
+                    None => "error expected setoption name <name> value <value>".to_string(),
+                }
+            }
+            "newgame" => {
+                engine.new_game();
+                state = create_new_game();
+                "ok".to_string()
+            }
+            "clear" => {
+                engine.clear();
+                "ok".to_string()
+            }
+            _ => format!("error unknown command {}", command),
+        };
+        if writeln!(out, "{}", answer)
+            .and_then(|_| out.flush())
+            .is_err()
+        {
+            break;
+        }
+    }
+}
+
+fn go(engine: &mut Engine, state: &GameState, depth: i32) -> String {
+    let search = engine.analyze(state, depth);
+    let best = search.result.best_move.as_ref().map_or_else(
+        || "none".to_string(),
+        |mv| format!("{} {}", mv.from.to_key(), mv.to.to_key()),
+    );
+    format!(
+        "bestmove {} score {} depth {} nodes {} continued {} reused {}",
+        best,
+        search.result.score,
+        search.depth,
+        search.result.stats.nodes_searched,
+        search.continued,
+        search.reused
+    )
+}
+
+fn print_bench(label: &str, report: &BenchReport) {
+    println!(
+        "{}: {} positions at depth {}: {} nodes, {} cutoffs, {:.1}% on the first move, \
+         average cutoff move {:.2}, {} by countermoves",
+        label,
+        report.positions,
+        report.depth,
+        report.nodes,
+        report.beta_cutoffs,
+        report.first_move_cutoff_rate * 100.0,
+        report.average_cutoff_index,
+        report.countermove_cutoffs
+    );
+    if let (Some(without), Some(cut)) = (
+        report.nodes_without_countermoves,
+        report.countermove_node_cut(),
+    ) {
+        println!(
+            "{}: {} nodes without countermoves, {:.1}% cut by them",
+            label,
+            without,
+            cut * 100.0
+        );
+    }
+}
+
+/// Play a game between two bots by name and print it.
+fn simulate_bots(white: &str, black: &str, plies: usize) {
+    let bot = |name: &str, seed: u64| create_bot(name, seed).unwrap_or_else(|| usage());
+    let (mut white, mut black) = (bot(white, 1), bot(black, 2));
+    let started = Instant::now();
+    let game = play_bot_game(
+        &create_new_game(),
+        white.as_mut(),
+        black.as_mut(),
+        &SearchLimits::default(),
+        plies,
+    );
+    for (ply, mv) in game.history.iter().enumerate() {
+        println!("{:3}. {} -> {}", ply + 1, mv.from.to_key(), mv.to.to_key());
+    }
+    println!(
+        "{:?} after {} plies in {:.1}s",
+        game.status,
+        game.history.len(),
+        started.elapsed().as_secs_f64()
+    );
+}
+
+fn run_perft(args: &[String]) {
+    let divide = args.iter().any(|arg| arg == "--divide");
+    let args: Vec<String> = args
+        .iter()
+        .filter(|arg| *arg != "--divide")
+        .cloned()
+        .collect();
+    let depth = args
+        .first()
+        .and_then(|depth| depth.parse().ok())
+        .unwrap_or_else(|| usage());
+    let (board, turn) = match args.get(1) {
+        Some(fen) => parse_hexfen(fen).unwrap_or_else(|| {
+            eprintln!("invalid HexFEN: {}", fen);
+            process::exit(1);
+        }),
+        None => {
+            let game = create_new_game();
+            (game.board, game.turn)
+        }
+    };
+
+    let started = Instant::now();
+    let total = if divide {
+        let counts = perft_divide(&board, turn, depth);
+        for (mv, count) in &counts {
+            let promotion = mv
+                .promotion
+                .map(|piece_type| format!("={:?}", piece_type))
+                .unwrap_or_default();
+            println!(
+                "{} -> {}{}: {}",
+                mv.from.to_key(),
+                mv.to.to_key(),
+                promotion,
+                count
+            );
+        }
+        counts.iter().map(|(_, count)| count).sum()
+    } else {
+        perft(&board, turn, depth)
+    };
+    let elapsed = started.elapsed();
+    println!(
+        "perft({}) = {} in {:.3}s ({:.0} nodes/s)",
+        depth,
+        total,
+        elapsed.as_secs_f64(),
+        total as f64 / elapsed.as_secs_f64().max(1e-9)
+    );
+}
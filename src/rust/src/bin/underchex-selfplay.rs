@@ -0,0 +1,200 @@
+//! Self-play data generator, meant to run unattended (e.g. nightly).
+//!
+//! Usage:
+//!   underchex-selfplay <output dir> [--games <n>] [--seed <n>]
+//!       [--levels easy,medium,hard] [--opening-plies <n>] [--max-plies <n>]
+//!       [--positions <n>] [--bots <name>,<name>]
+//!
+//! Plays self-play games until `--games` new games are written (forever by
+//! default), appending to the output directory:
+//! - `games.txt`: one game per line, its compact record in base64url and its
+//!   result (see `selfplay::game_record_line`)
+//! - `positions.jsonl`: one training position per line, as JSON
+//!   { fen, score, result }
+//!
+//! `--bots` draws each side from the named bots (see `bots::BOT_NAMES`, e.g.
+//! `mcts,alphabeta`) instead of the levels.
+//!
+//! Games and positions already in the files are not written again, so runs
+//! can be restarted on the same directory. The seed defaults to the time.
+//! Any command takes `--set <name>=<value>` to set an engine option first.
+
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use underchex_wasm::ai::AIDifficulty;
+use underchex_wasm::bots::BOT_NAMES;
+use underchex_wasm::options::set_option;
+use underchex_wasm::rng::SeededRng;
+use underchex_wasm::selfplay::{
+    game_record_line, play_self_play_game, training_positions, SelfPlayConfig, SelfPlayDedup,
+    TrainingPosition,
+};
+
+const GAMES_FILE: &str = "games.txt";
+const POSITIONS_FILE: &str = "positions.jsonl";
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: underchex-selfplay <output dir> [--games <n>] [--seed <n>]\n\
+         \x20 [--levels easy,medium,hard] [--opening-plies <n>] [--max-plies <n>]\n\
+         \x20 [--positions <n>] [--bots <name>,<name>] [--set <name>=<value>]"
+    );
+    process::exit(2);
+}
+
+struct Args {
+    dir: PathBuf,
+    games: Option<usize>,
+    seed: u64,
+    config: SelfPlayConfig,
+}
+
+fn parse_level(name: &str) -> AIDifficulty {
+    match name {
+        "easy" => AIDifficulty::Easy,
+        "medium" => AIDifficulty::Medium,
+        "hard" => AIDifficulty::Hard,
+        _ => usage(),
+    }
+}
+
+fn parse_bot(name: &str) -> String {
+    if !BOT_NAMES.contains(&name) {
+        usage();
+    }
+    name.to_string()
+}
+
+fn parse_args() -> Args {
+    let mut dir = None;
+    let mut games = None;
+    let mut seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+    let mut config = SelfPlayConfig::default();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            if dir.replace(PathBuf::from(arg)).is_some() {
+                usage();
+            }
+            continue;
+        }
+        let value = args.next().unwrap_or_else(|| usage());
+        let number = || value.parse::<usize>().unwrap_or_else(|_| usage());
+        match arg.as_str() {
+            "--games" => games = Some(number()),
+            "--seed" => seed = value.parse().unwrap_or_else(|_| usage()),
+            "--levels" => config.levels = value.split(',').map(parse_level).collect(),
+            "--bots" => config.bots = value.split(',').map(parse_bot).collect(),
+            "--opening-plies" => config.opening_plies = number(),
+            "--max-plies" => config.max_plies = number(),
+            "--positions" => config.positions_per_game = number(),
+            "--set" => {
+                let (name, value) = value.split_once('=').unwrap_or_else(|| usage());
+                if let Err(reason) = set_option(name, value) {
+                    eprintln!("cannot set {}: {}", name, reason);
+                    process::exit(2);
+                }
+            }
+            _ => usage(),
+        }
+    }
+    Args {
+        dir: dir.unwrap_or_else(|| usage()),
+        games,
+        seed,
+        config,
+    }
+}
+
+/// Note the games and positions of earlier runs.
+fn load_existing(dir: &Path, dedup: &mut SelfPlayDedup) -> io::Result<()> {
+    let lines = |name: &str| -> io::Result<Vec<String>> {
+        match File::open(dir.join(name)) {
+            Ok(file) => BufReader::new(file).lines().collect(),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(error) => Err(error),
+        }
+    };
+    for line in lines(GAMES_FILE)? {
+        dedup.insert_game(&line);
+    }
+    for line in lines(POSITIONS_FILE)? {
+        if let Ok(position) = serde_json::from_str::<TrainingPosition>(&line) {
+            dedup.insert_position(&position.fen);
+        }
+    }
+    Ok(())
+}
+
+fn append(dir: &Path, name: &str) -> io::Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(name))
+}
+
+fn run(args: Args) -> io::Result<()> {
+    fs::create_dir_all(&args.dir)?;
+    let mut dedup = SelfPlayDedup::new();
+    load_existing(&args.dir, &mut dedup)?;
+    eprintln!(
+        "{}: {} games and {} positions already, seed {}",
+        args.dir.display(),
+        dedup.games(),
+        dedup.positions(),
+        args.seed
+    );
+    let mut games_file = append(&args.dir, GAMES_FILE)?;
+    let mut positions_file = append(&args.dir, POSITIONS_FILE)?;
+
+    let mut rng = SeededRng::new(args.seed);
+    let mut written = 0;
+    while args.games.is_none_or(|games| written < games) {
+        let Some(game) = play_self_play_game(&args.config, &mut rng) else {
+            usage();
+        };
+        let Some(line) = game_record_line(&game) else {
+            continue;
+        };
+        if !dedup.insert_game(&line) {
+            continue;
+        }
+        writeln!(games_file, "{}", line)?;
+        let mut positions = 0;
+        for position in training_positions(&game, args.config.positions_per_game, &mut rng) {
+            if dedup.insert_position(&position.fen) {
+                let json = serde_json::to_string(&position).map_err(io::Error::other)?;
+                writeln!(positions_file, "{}", json)?;
+                positions += 1;
+            }
+        }
+        games_file.flush()?;
+        positions_file.flush()?;
+        written += 1;
+        eprintln!(
+            "game {}: {} vs {}, {} plies, {} ({} new positions)",
+            dedup.games(),
+            game.white,
+            game.black,
+            game.state.history.len(),
+            line.split_once(' ').map_or("*", |(_, result)| result),
+            positions
+        );
+    }
+    Ok(())
+}
+
+fn main() {
+    if let Err(error) = run(parse_args()) {
+        eprintln!("underchex-selfplay: {}", error);
+        process::exit(1);
+    }
+}
@@ -2,7 +2,13 @@
 //!
 //! Signed-by: agent #21 claude-sonnet-4 via opencode 20260122T06:31:01
 
-use crate::types::{Direction, HexCoord, BOARD_RADIUS};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::types::{
+    BoardState, Color, Direction, HexCoord, Move, OffsetCoord, OffsetLayout, Piece, PieceType,
+    BOARD_RADIUS,
+};
 
 // ============================================================================
 // Board Validation
@@ -68,42 +74,37 @@ pub fn hex_distance(a: HexCoord, b: HexCoord) -> i32 {
 
 /// Get the direction from one cell to another (if aligned), or None if not aligned.
 pub fn get_direction(from: HexCoord, to: HexCoord) -> Option<Direction> {
-    let dq = to.q - from.q;
-    let dr = to.r - from.r;
+    get_line(from, to).map(|(direction, _)| direction)
+}
 
-    if dq == 0 && dr == 0 {
+/// Get the line from one cell to another: its direction and the number of
+/// steps along it, or None if the cells are equal or not aligned.
+pub fn get_line(from: HexCoord, to: HexCoord) -> Option<(Direction, u32)> {
+    let steps = hex_distance(from, to);
+    if steps == 0 {
         return None;
     }
+    let (dq, dr) = (to.q - from.q, to.r - from.r);
+    Direction::all()
+        .iter()
+        .find(|dir| {
+            let (delta_q, delta_r) = dir.delta();
+            (delta_q * steps, delta_r * steps) == (dq, dr)
+        })
+        .map(|&dir| (dir, steps.unsigned_abs()))
+}
 
-    for &dir in Direction::all() {
-        let (delta_q, delta_r) = dir.delta();
-
-        // Check if (dq, dr) is a positive multiple of (delta_q, delta_r)
-        if delta_q == 0 && delta_r == 0 {
-            continue;
-        }
-
-        if delta_q == 0 {
-            if dq == 0 && dr.signum() == delta_r.signum() {
-                return Some(dir);
-            }
-        } else if delta_r == 0 {
-            if dr == 0 && dq.signum() == delta_q.signum() {
-                return Some(dir);
-            }
-        } else {
-            // Both non-zero, check ratio
-            if dq % delta_q == 0 && dr % delta_r == 0 {
-                let ratio_q = dq / delta_q;
-                let ratio_r = dr / delta_r;
-                if ratio_q == ratio_r && ratio_q > 0 {
-                    return Some(dir);
-                }
-            }
-        }
+/// Check whether three cells lie on one line, in any order. Equal cells
+/// count as aligned with anything the others are aligned with.
+pub fn are_aligned(a: HexCoord, b: HexCoord, c: HexCoord) -> bool {
+    let axis =
+        |from, to| get_line(from, to).map(|(dir, _)| dir.delta().min(dir.opposite().delta()));
+    match (axis(a, b), axis(a, c)) {
+        (Some(ab), Some(ac)) => ab == ac,
+        (None, _) if a == b => a == c || axis(a, c).is_some(),
+        (_, None) if a == c => axis(a, b).is_some(),
+        _ => false,
     }
-
-    None
 }
 
 /// Get all cells along a direction from a starting point (exclusive of start).
@@ -150,7 +151,7 @@ pub fn get_cells_between(from: HexCoord, to: HexCoord) -> Option<Vec<HexCoord>>
 
 /// Knight leap offsets.
 /// Knight moves 1 step in one direction, then 1 step in an adjacent (non-opposite) direction.
-const KNIGHT_OFFSETS: [(i32, i32); 6] = [
+pub const KNIGHT_OFFSETS: [(i32, i32); 6] = [
     (1, -2),  // N then NE, or NE then N
     (-1, -1), // N then NW, or NW then N
     (2, -1),  // NE then SE, or SE then NE
@@ -168,6 +169,282 @@ pub fn get_knight_targets(from: HexCoord) -> Vec<HexCoord> {
         .collect()
 }
 
+/// A shortest knight route from `from` to `to` that avoids the `blocked`
+/// cells: the cells leapt to, ending with `to` (empty if they are the same
+/// cell). Returns None if `to` cannot be reached; a knight never leaves
+/// its shade (see [`CellClass`]).
+pub fn knight_path(
+    from: HexCoord,
+    to: HexCoord,
+    blocked: &HashSet<HexCoord>,
+) -> Option<Vec<HexCoord>> {
+    let mut came_from = HashMap::from([(from, from)]);
+    let mut queue = VecDeque::from([from]);
+    while let Some(cell) = queue.pop_front() {
+        if cell == to {
+            let mut path = Vec::new();
+            let mut current = to;
+            while current != from {
+                path.push(current);
+                current = *came_from.get(&current)?;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        for next in get_knight_targets(cell) {
+            if !blocked.contains(&next) && !came_from.contains_key(&next) {
+                came_from.insert(next, cell);
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+// ============================================================================
+// Cell Classification
+// ============================================================================
+
+/// Where a cell lies on the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CellRegion {
+    /// The central cell
+    Center,
+    /// Neither center nor outer ring
+    Interior,
+    /// The outer ring, except its corners
+    Edge,
+    /// One of the six corners of the hexagon
+    Corner,
+}
+
+/// How to render a cell.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CellClass {
+    pub q: i32,
+    pub r: i32,
+    /// Shade of the three-coloring (0, 1 or 2): neighboring cells never share
+    /// a shade, and the center has shade 0
+    pub shade: u8,
+    pub region: CellRegion,
+    /// Distance from the center
+    pub ring: i32,
+    /// Name of the cell (see [`cell_name`])
+    pub name: String,
+}
+
+/// Name of a cell: a file letter `a`-`i` for q = -4..=4 and a rank `1`-`9`
+/// counted from White's side (r = 4 is rank 1), so the center is "e5".
+/// Returns None for a cell off the board.
+pub fn cell_name(coord: HexCoord) -> Option<String> {
+    if !is_valid_cell(coord) {
+        return None;
+    }
+    let file = (b'a' + (coord.q + BOARD_RADIUS) as u8) as char;
+    Some(format!("{}{}", file, BOARD_RADIUS - coord.r + 1))
+}
+
+/// Parse a cell name written by [`cell_name`].
+pub fn parse_cell_name(name: &str) -> Option<HexCoord> {
+    let mut chars = name.chars();
+    let file = chars.next().filter(char::is_ascii_lowercase)?;
+    let rank: i32 = chars.as_str().parse().ok()?;
+    let coord = HexCoord::new(
+        i32::from(file as u8 - b'a') - BOARD_RADIUS,
+        (BOARD_RADIUS + 1).checked_sub(rank)?,
+    );
+    is_valid_cell(coord).then_some(coord)
+}
+
+/// Classify a cell for rendering. Returns None for a cell off the board.
+pub fn cell_class(coord: HexCoord) -> Option<CellClass> {
+    let name = cell_name(coord)?;
+    let ring = hex_distance(coord, HexCoord::new(0, 0));
+    // Corners are the outer cells with two coordinates at the radius
+    let at_radius = [coord.q, coord.r, coord.s()]
+        .iter()
+        .filter(|c| c.abs() == BOARD_RADIUS)
+        .count();
+    let region = match ring {
+        0 => CellRegion::Center,
+        BOARD_RADIUS if at_radius == 2 => CellRegion::Corner,
+        BOARD_RADIUS => CellRegion::Edge,
+        _ => CellRegion::Interior,
+    };
+    Some(CellClass {
+        q: coord.q,
+        r: coord.r,
+        shade: (coord.q - coord.r).rem_euclid(3) as u8,
+        region,
+        ring,
+        name,
+    })
+}
+
+// ============================================================================
+// Board Queries
+// ============================================================================
+
+/// Occupied cells with their pieces (including the boulder). Entries whose
+/// key is not a cell are not on the board and are skipped.
+pub fn occupied_cells(board: &BoardState) -> impl Iterator<Item = (HexCoord, Piece)> + '_ {
+    board
+        .iter()
+        .filter_map(|(key, &piece)| HexCoord::from_key(key).map(|cell| (cell, piece)))
+}
+
+/// Board cells without a piece.
+pub fn empty_cells(board: &BoardState) -> impl Iterator<Item = HexCoord> + '_ {
+    get_all_cells()
+        .into_iter()
+        .filter(|cell| !board.contains_key(&cell.to_key()))
+}
+
+/// A color's pieces with their cells (never the boulder).
+pub fn pieces_of(board: &BoardState, color: Color) -> impl Iterator<Item = (HexCoord, Piece)> + '_ {
+    occupied_cells(board).filter(move |(_, piece)| piece.color == color && !piece.is_boulder())
+}
+
+/// A color's pieces of one type with their cells.
+pub fn pieces_of_type(
+    board: &BoardState,
+    color: Color,
+    piece_type: PieceType,
+) -> impl Iterator<Item = (HexCoord, Piece)> + '_ {
+    pieces_of(board, color).filter(move |(_, piece)| piece.piece_type == piece_type)
+}
+
+// ============================================================================
+// Coordinate Interop
+// ============================================================================
+
+/// A piece at offset coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OffsetPiece {
+    pub col: i32,
+    pub row: i32,
+    pub piece: Piece,
+}
+
+/// A move's cells at offset coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OffsetMove {
+    pub from: OffsetCoord,
+    pub to: OffsetCoord,
+    /// Cell the boulder was placed on (boulder variant)
+    pub boulder: Option<OffsetCoord>,
+}
+
+/// A board's pieces at offset coordinates, sorted by row then column.
+pub fn board_to_offset(board: &BoardState, layout: OffsetLayout) -> Vec<OffsetPiece> {
+    let mut pieces: Vec<OffsetPiece> = board
+        .iter()
+        .filter_map(|(key, &piece)| {
+            let OffsetCoord { col, row } = HexCoord::from_key(key)?.to_offset(layout);
+            Some(OffsetPiece { col, row, piece })
+        })
+        .collect();
+    pieces.sort_by_key(|p| (p.row, p.col));
+    pieces
+}
+
+/// A board from pieces at offset coordinates.
+pub fn board_from_offset(pieces: &[OffsetPiece], layout: OffsetLayout) -> BoardState {
+    pieces
+        .iter()
+        .map(|p| {
+            let cell = HexCoord::from_offset(
+                OffsetCoord {
+                    col: p.col,
+                    row: p.row,
+                },
+                layout,
+            );
+            (cell.to_key(), p.piece)
+        })
+        .collect()
+}
+
+/// The cells of a list of moves at offset coordinates.
+pub fn moves_to_offset(moves: &[Move], layout: OffsetLayout) -> Vec<OffsetMove> {
+    moves
+        .iter()
+        .map(|mv| OffsetMove {
+            from: mv.from.to_offset(layout),
+            to: mv.to.to_offset(layout),
+            boulder: mv.boulder.map(|cell| cell.to_offset(layout)),
+        })
+        .collect()
+}
+
+// ============================================================================
+// Board Differences
+// ============================================================================
+
+/// One change between two board states.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BoardDelta {
+    /// A piece left a cell (captured, or replaced by a promotion)
+    Removed { cell: HexCoord, piece: Piece },
+    /// A piece moved from one cell to another
+    Moved {
+        from: HexCoord,
+        to: HexCoord,
+        piece: Piece,
+    },
+    /// A piece appeared on a cell (dropped, placed or promoted)
+    Added { cell: HexCoord, piece: Piece },
+}
+
+/// Sorted (r, q) cells of `a` whose piece is absent or different in `b`.
+fn changed_cells(a: &BoardState, b: &BoardState) -> Vec<(HexCoord, Piece)> {
+    let mut cells: Vec<(HexCoord, Piece)> = a
+        .iter()
+        .filter(|(key, piece)| b.get(*key) != Some(piece))
+        .filter_map(|(key, &piece)| HexCoord::from_key(key).map(|cell| (cell, piece)))
+        .collect();
+    cells.sort_by_key(|(cell, _)| (cell.r, cell.q));
+    cells
+}
+
+/// Compute the changes that turn board `a` into board `b`.
+///
+/// A piece that disappears from one cell and appears on another is reported
+/// as a move; everything else is a removal or an addition. Deltas are ordered
+/// removals, moves, additions, so applying them in order reproduces `b`.
+pub fn diff(a: &BoardState, b: &BoardState) -> Vec<BoardDelta> {
+    let mut removed = changed_cells(a, b);
+    let mut added = changed_cells(b, a);
+
+    let mut moves = Vec::new();
+    added.retain(|&(to, piece)| {
+        let source = removed
+            .iter()
+            .filter(|&&(_, p)| p == piece)
+            .min_by_key(|&&(from, _)| hex_distance(from, to))
+            .copied();
+        match source {
+            Some((from, _)) => {
+                removed.retain(|&(cell, _)| cell != from);
+                moves.push(BoardDelta::Moved { from, to, piece });
+                false
+            }
+            None => true,
+        }
+    });
+
+    removed
+        .into_iter()
+        .map(|(cell, piece)| BoardDelta::Removed { cell, piece })
+        .chain(moves)
+        .chain(
+            added
+                .into_iter()
+                .map(|(cell, piece)| BoardDelta::Added { cell, piece }),
+        )
+        .collect()
+}
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -236,9 +513,249 @@ mod tests {
         assert_eq!(get_direction(origin, HexCoord::new(1, 1)), None);
     }
 
+    #[test]
+    fn test_get_line() {
+        let origin = HexCoord::new(0, 0);
+        assert_eq!(
+            get_line(origin, HexCoord::new(-3, 0)),
+            Some((Direction::NW, 3))
+        );
+        assert_eq!(
+            get_line(HexCoord::new(1, 2), HexCoord::new(2, 1)),
+            Some((Direction::NE, 1))
+        );
+        assert_eq!(get_line(origin, origin), None);
+        assert_eq!(get_line(origin, HexCoord::new(2, 1)), None);
+
+        // Every aligned pair agrees with get_cells_between
+        for from in get_all_cells() {
+            for to in get_all_cells() {
+                if let Some((_, steps)) = get_line(from, to) {
+                    let between = get_cells_between(from, to).unwrap();
+                    assert_eq!(between.len() as u32, steps - 1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_are_aligned() {
+        let cell = HexCoord::new;
+        assert!(are_aligned(cell(0, 0), cell(0, 2), cell(0, -3)));
+        assert!(are_aligned(cell(-1, 1), cell(1, -1), cell(0, 0)));
+        assert!(!are_aligned(cell(0, 0), cell(0, 2), cell(1, 1)));
+        // Pairwise aligned, but along different lines
+        assert!(!are_aligned(cell(0, 0), cell(0, 2), cell(2, 0)));
+        assert!(are_aligned(cell(0, 0), cell(0, 0), cell(3, -3)));
+        assert!(are_aligned(cell(0, 0), cell(2, 0), cell(0, 0)));
+        assert!(!are_aligned(cell(0, 0), cell(0, 0), cell(1, 1)));
+    }
+
+    #[test]
+    fn test_cell_classes() {
+        let classes: Vec<CellClass> = get_all_cells().into_iter().filter_map(cell_class).collect();
+        assert_eq!(classes.len(), 61);
+        let count = |region| classes.iter().filter(|c| c.region == region).count();
+        assert_eq!(count(CellRegion::Center), 1);
+        assert_eq!(count(CellRegion::Corner), 6);
+        assert_eq!(count(CellRegion::Edge), 18);
+
+        // A proper three-coloring
+        for cell in get_all_cells() {
+            let shade = cell_class(cell).unwrap().shade;
+            for neighbor in get_neighbors(cell) {
+                assert_ne!(cell_class(neighbor).unwrap().shade, shade);
+            }
+        }
+        let shades: Vec<usize> = (0..3)
+            .map(|shade| classes.iter().filter(|c| c.shade == shade).count())
+            .collect();
+        assert_eq!(shades, vec![19, 21, 21]);
+
+        let center = cell_class(HexCoord::new(0, 0)).unwrap();
+        assert_eq!((center.shade, center.name.as_str()), (0, "e5"));
+        assert_eq!(
+            cell_class(HexCoord::new(4, -4)).unwrap().region,
+            CellRegion::Corner
+        );
+        assert!(cell_class(HexCoord::new(3, 3)).is_none());
+    }
+
+    #[test]
+    fn test_cell_names() {
+        let names: std::collections::HashSet<String> =
+            get_all_cells().into_iter().filter_map(cell_name).collect();
+        assert_eq!(names.len(), 61);
+        for cell in get_all_cells() {
+            assert_eq!(parse_cell_name(&cell_name(cell).unwrap()), Some(cell));
+        }
+        assert_eq!(cell_name(HexCoord::new(0, 4)).as_deref(), Some("e1"));
+        assert_eq!(parse_cell_name("a1"), Some(HexCoord::new(-4, 4)));
+        assert_eq!(parse_cell_name("a9"), None);
+        assert_eq!(parse_cell_name("e10"), None);
+        assert_eq!(parse_cell_name("E5"), None);
+        assert_eq!(parse_cell_name("e-2147483648"), None);
+    }
+
     #[test]
     fn test_knight_targets() {
         let targets = get_knight_targets(HexCoord::new(0, 0));
         assert_eq!(targets.len(), 6);
     }
+
+    #[test]
+    fn test_knight_path() {
+        let center = HexCoord::new(0, 0);
+        let none = HashSet::new();
+        assert_eq!(knight_path(center, center, &none), Some(vec![]));
+        assert_eq!(
+            knight_path(center, HexCoord::new(1, -2), &none),
+            Some(vec![HexCoord::new(1, -2)])
+        );
+
+        let far = HexCoord::new(3, 0);
+        let path = knight_path(center, far, &none).unwrap();
+        assert_eq!(path.len(), 2);
+        assert_eq!(path.last(), Some(&far));
+        assert!(get_knight_targets(center).contains(&path[0]));
+        assert!(get_knight_targets(path[0]).contains(&far));
+
+        // The knight keeps its shade, so a neighbor is out of reach
+        assert_eq!(knight_path(center, HexCoord::new(0, -1), &none), None);
+
+        // Blocking every leap from the start cuts it off
+        let blocked: HashSet<_> = get_knight_targets(center).into_iter().collect();
+        assert_eq!(knight_path(center, far, &blocked), None);
+    }
+
+    #[test]
+    fn test_diff() {
+        let pawn = Piece::new(PieceType::Pawn, Color::White);
+        let knight = Piece::new(PieceType::Knight, Color::Black);
+        let queen = Piece::new(PieceType::Queen, Color::White);
+        let mut a = BoardState::new();
+        a.insert("0,1".to_string(), pawn);
+        a.insert("1,-1".to_string(), knight);
+        a.insert("-2,0".to_string(), queen);
+
+        // Pawn captures the knight and promotes; the queen stays put
+        let mut b = a.clone();
+        b.remove("0,1");
+        b.insert("1,-1".to_string(), queen);
+        assert_eq!(
+            diff(&a, &b),
+            vec![
+                BoardDelta::Removed {
+                    cell: HexCoord::new(1, -1),
+                    piece: knight
+                },
+                BoardDelta::Removed {
+                    cell: HexCoord::new(0, 1),
+                    piece: pawn
+                },
+                BoardDelta::Added {
+                    cell: HexCoord::new(1, -1),
+                    piece: queen
+                },
+            ]
+        );
+
+        let mut c = a.clone();
+        c.remove("0,1");
+        c.insert("0,0".to_string(), pawn);
+        assert_eq!(
+            diff(&a, &c),
+            vec![BoardDelta::Moved {
+                from: HexCoord::new(0, 1),
+                to: HexCoord::new(0, 0),
+                piece: pawn
+            }]
+        );
+        assert!(diff(&a, &a).is_empty());
+    }
+
+    #[test]
+    fn test_board_queries() {
+        let mut board = BoardState::new();
+        let knight = Piece::new(PieceType::Knight, Color::White);
+        let king = Piece::new(PieceType::King, Color::White);
+        board.insert("0,4".to_string(), king);
+        board.insert("1,2".to_string(), knight);
+        board.insert("-1,2".to_string(), knight);
+        board.insert(
+            "0,-4".to_string(),
+            Piece::new(PieceType::King, Color::Black),
+        );
+        board.insert("0,0".to_string(), Piece::boulder());
+        board.insert("hand".to_string(), knight);
+
+        assert_eq!(occupied_cells(&board).count(), 5);
+        assert_eq!(empty_cells(&board).count(), 61 - 5);
+        assert!(empty_cells(&board).all(|cell| !board.contains_key(&cell.to_key())));
+        assert_eq!(pieces_of(&board, Color::White).count(), 3);
+        assert_eq!(pieces_of(&board, Color::Black).count(), 1);
+
+        let mut knights: Vec<HexCoord> = pieces_of_type(&board, Color::White, PieceType::Knight)
+            .map(|(cell, _)| cell)
+            .collect();
+        knights.sort_by_key(|cell| cell.q);
+        assert_eq!(knights, vec![HexCoord::new(-1, 2), HexCoord::new(1, 2)]);
+        assert_eq!(
+            pieces_of_type(&board, Color::White, PieceType::King).collect::<Vec<_>>(),
+            vec![(HexCoord::new(0, 4), king)]
+        );
+    }
+
+    #[test]
+    fn test_coordinate_conversions_roundtrip() {
+        let layouts = [
+            OffsetLayout::OddQ,
+            OffsetLayout::EvenQ,
+            OffsetLayout::OddR,
+            OffsetLayout::EvenR,
+        ];
+        for cell in get_all_cells() {
+            let (q, r, s) = cell.to_cube();
+            assert_eq!(HexCoord::from_cube(q, r, s), Some(cell));
+            for layout in layouts {
+                assert_eq!(HexCoord::from_offset(cell.to_offset(layout), layout), cell);
+            }
+        }
+        assert_eq!(HexCoord::from_cube(1, 1, 1), None);
+
+        // Odd-q: odd columns are shoved down half a cell
+        let offset = |q, r| HexCoord::new(q, r).to_offset(OffsetLayout::OddQ);
+        assert_eq!(offset(0, 0), OffsetCoord { col: 0, row: 0 });
+        assert_eq!(offset(1, -1), OffsetCoord { col: 1, row: -1 });
+        assert_eq!(offset(1, 0), OffsetCoord { col: 1, row: 0 });
+        assert_eq!(offset(-1, 0), OffsetCoord { col: -1, row: -1 });
+        assert_eq!(offset(2, -1), OffsetCoord { col: 2, row: 0 });
+        assert_eq!(OffsetLayout::from_name("evenR"), Some(OffsetLayout::EvenR));
+        assert_eq!(OffsetLayout::from_name("nope"), None);
+    }
+
+    #[test]
+    fn test_bulk_offset_conversion() {
+        let mut board = BoardState::new();
+        let king = Piece::new(PieceType::King, Color::White);
+        let pawn = Piece::new(PieceType::Pawn, Color::Black);
+        board.insert("0,4".to_string(), king);
+        board.insert("-3,-1".to_string(), pawn);
+
+        let pieces = board_to_offset(&board, OffsetLayout::EvenQ);
+        assert_eq!(pieces.len(), 2);
+        assert!(pieces[0].row <= pieces[1].row);
+        assert_eq!(board_from_offset(&pieces, OffsetLayout::EvenQ), board);
+
+        let mv = Move::new(pawn, HexCoord::new(1, 0), HexCoord::new(1, 1));
+        let converted = moves_to_offset(&[mv], OffsetLayout::OddQ);
+        assert_eq!(
+            converted,
+            vec![OffsetMove {
+                from: OffsetCoord { col: 1, row: 0 },
+                to: OffsetCoord { col: 1, row: 1 },
+                boulder: None,
+            }]
+        );
+    }
 }
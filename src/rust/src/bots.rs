@@ -0,0 +1,907 @@
+//! Underchex Bots
+//!
+//! A common interface for computer opponents so the engine, simple scripted
+//! players and external or experimental engines can be swapped freely in
+//! bot matches and online sessions. A [`ConsultationBot`] lets a weighted
+//! panel of bots vote on each move.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ai::{
+    evaluate_material, evaluate_position, evaluate_position_with_hands, find_best_move_for_variant,
+    find_best_move_iterative, get_piece_value, with_boulder_placements, AIDifficulty,
+    TranspositionTable,
+};
+use crate::game::{game_result, get_legal_moves};
+use crate::humanlike::{choose_humanlike_move, HumanlikeSettings};
+use crate::moves::{apply_move, generate_all_legal_moves};
+use crate::profile::winning_chances;
+use crate::rng::SeededRng;
+use crate::session::replay_move;
+use crate::types::{BoardState, Color, GameState, GameStatus, Move, PieceType, Variant};
+
+// ============================================================================
+// Bot Interface
+// ============================================================================
+
+/// Limits for a bot's move choice. Bots that do not search ignore them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchLimits {
+    pub max_depth: i32,
+    /// Stop deepening once this much time has passed (iterative deepening)
+    pub time_limit_ms: Option<u64>,
+    pub use_quiescence: bool,
+    /// Side a draw counts as a win for (armageddon)
+    pub draw_winner: Option<Color>,
+}
+
+impl SearchLimits {
+    /// Fixed-depth search with quiescence.
+    pub fn depth(max_depth: i32) -> Self {
+        Self {
+            max_depth,
+            time_limit_ms: None,
+            use_quiescence: true,
+            draw_winner: None,
+        }
+    }
+}
+
+impl Default for SearchLimits {
+    fn default() -> Self {
+        Self::depth(4)
+    }
+}
+
+/// A computer player.
+pub trait Bot {
+    /// Short name for logs and match results.
+    fn name(&self) -> &str;
+
+    /// Choose a move for the side to move, or None if there is none. The move
+    /// must be complete: in the boulder variant it carries its placement.
+    fn choose_move(&mut self, state: &GameState, limits: &SearchLimits) -> Option<Move>;
+}
+
+/// Every complete legal move in a state, with all boulder placements attached
+/// in the boulder variant.
+pub fn get_bot_moves(state: &GameState) -> Vec<Move> {
+    let moves = get_legal_moves(state);
+    if state.variant == Variant::Boulder {
+        with_boulder_placements(&state.board, moves, usize::MAX)
+    } else {
+        moves
+    }
+}
+
+// ============================================================================
+// Bots
+// ============================================================================
+
+/// The alpha-beta search engine.
+pub struct AlphaBetaBot {
+    tt: TranspositionTable,
+}
+
+impl AlphaBetaBot {
+    pub fn new(tt_size: usize) -> Self {
+        Self {
+            tt: TranspositionTable::new(tt_size),
+        }
+    }
+}
+
+impl Bot for AlphaBetaBot {
+    fn name(&self) -> &str {
+        "alphabeta"
+    }
+
+    fn choose_move(&mut self, state: &GameState, limits: &SearchLimits) -> Option<Move> {
+        if state.status != GameStatus::Ongoing {
+            return None;
+        }
+        self.tt.set_game_rules(state);
+        self.tt.set_draw_winner(limits.draw_winner);
+        let result = match limits.time_limit_ms {
+            Some(time_limit_ms) => find_best_move_iterative(
+                &state.board,
+                state.turn,
+                limits.max_depth,
+                time_limit_ms,
+                &mut self.tt,
+                limits.use_quiescence,
+                &state.position_history,
+                state.variant,
+            ),
+            None => find_best_move_for_variant(
+                &state.board,
+                state.turn,
+                limits.max_depth,
+                &mut self.tt,
+                limits.use_quiescence,
+                &state.position_history,
+                state.variant,
+            ),
+        };
+        result.best_move
+    }
+}
+
+/// Plays a uniformly random legal move.
+#[derive(Debug, Clone)]
+pub struct RandomBot {
+    rng: SeededRng,
+}
+
+impl RandomBot {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: SeededRng::new(seed),
+        }
+    }
+}
+
+impl Bot for RandomBot {
+    fn name(&self) -> &str {
+        "random"
+    }
+
+    fn choose_move(&mut self, state: &GameState, _limits: &SearchLimits) -> Option<Move> {
+        self.rng.choose(&get_bot_moves(state)).cloned()
+    }
+}
+
+/// Takes the most valuable piece it can (with the cheapest attacker),
+/// otherwise moves at random. Never looks at what it leaves hanging.
+#[derive(Debug, Clone)]
+pub struct GreedyCaptureBot {
+    rng: SeededRng,
+}
+
+impl GreedyCaptureBot {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: SeededRng::new(seed),
+        }
+    }
+}
+
+impl Bot for GreedyCaptureBot {
+    fn name(&self) -> &str {
+        "capture"
+    }
+
+    fn choose_move(&mut self, state: &GameState, _limits: &SearchLimits) -> Option<Move> {
+        let moves = get_bot_moves(state);
+        let capture_score = |mv: &Move| {
+            mv.captured.map(|victim| {
+                get_piece_value(victim.piece_type) * 10 - get_piece_value(mv.piece.piece_type) / 10
+            })
+        };
+        let best = moves.iter().filter_map(capture_score).max();
+        match best {
+            Some(best) => {
+                let captures: Vec<Move> = moves
+                    .into_iter()
+                    .filter(|mv| capture_score(mv) == Some(best))
+                    .collect();
+                self.rng.choose(&captures).cloned()
+            }
+            None => self.rng.choose(&moves).cloned(),
+        }
+    }
+}
+
+/// Score from `color`'s point of view (evaluations are white-positive).
+fn score_for(color: Color, white_score: i32) -> i32 {
+    match color {
+        Color::White => white_score,
+        Color::Black => -white_score,
+    }
+}
+
+/// Pick at random among the moves with the highest score.
+fn choose_best(
+    rng: &mut SeededRng,
+    moves: Vec<Move>,
+    score: impl Fn(&Move) -> i32,
+) -> Option<Move> {
+    let scored: Vec<(i32, Move)> = moves.into_iter().map(|mv| (score(&mv), mv)).collect();
+    let best = scored.iter().map(|(score, _)| *score).max()?;
+    let best_moves: Vec<Move> = scored
+        .into_iter()
+        .filter(|(score, _)| *score == best)
+        .map(|(_, mv)| mv)
+        .collect();
+    rng.choose(&best_moves).cloned()
+}
+
+/// Plays the move with the best static evaluation right after it, without
+/// considering any reply: it grabs material even when it is defended.
+#[derive(Debug, Clone)]
+pub struct OnePlyGreedyBot {
+    rng: SeededRng,
+}
+
+impl OnePlyGreedyBot {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: SeededRng::new(seed),
+        }
+    }
+}
+
+impl Bot for OnePlyGreedyBot {
+    fn name(&self) -> &str {
+        "greedy"
+    }
+
+    fn choose_move(&mut self, state: &GameState, _limits: &SearchLimits) -> Option<Move> {
+        let color = state.turn;
+        choose_best(&mut self.rng, get_bot_moves(state), |mv| {
+            score_for(color, evaluate_position(&apply_move(&state.board, mv)))
+        })
+    }
+}
+
+/// Looks one reply ahead for material, but cannot see enemy knights: it
+/// walks into knight forks and captures, and never takes a knight on purpose.
+#[derive(Debug, Clone)]
+pub struct KnightBlindBot {
+    rng: SeededRng,
+}
+
+impl KnightBlindBot {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: SeededRng::new(seed),
+        }
+    }
+}
+
+impl Bot for KnightBlindBot {
+    fn name(&self) -> &str {
+        "knightBlind"
+    }
+
+    fn choose_move(&mut self, state: &GameState, _limits: &SearchLimits) -> Option<Move> {
+        let color = state.turn;
+        let mut seen = state.board.clone();
+        seen.retain(|_, piece| !(piece.color != color && piece.piece_type == PieceType::Knight));
+
+        let worst_reply = |board: &BoardState| {
+            generate_all_legal_moves(board, color.opposite())
+                .iter()
+                .map(|reply| score_for(color, evaluate_material(&apply_move(board, reply))))
+                .min()
+                .unwrap_or_else(|| score_for(color, evaluate_material(board)))
+        };
+        choose_best(&mut self.rng, get_bot_moves(state), |mv| {
+            // Captures of unseen knights land on what it thinks is an empty cell
+            let mut unseen = mv.clone();
+            unseen.captured = seen.get(&mv.to.to_key()).copied();
+            worst_reply(&apply_move(&seen, &unseen))
+        })
+    }
+}
+
+/// Plays like a club player with the human-like move model (see
+/// [`crate::humanlike`]) at a difficulty level.
+pub struct HumanlikeBot {
+    name: String,
+    rng: SeededRng,
+    settings: HumanlikeSettings,
+    tt: TranspositionTable,
+}
+
+impl HumanlikeBot {
+    pub fn new(seed: u64, difficulty: AIDifficulty) -> Self {
+        Self::with_personality(
+            seed,
+            "humanlike",
+            HumanlikeSettings::for_difficulty(difficulty),
+        )
+    }
+
+    /// A personality: the model with its own settings, named in votes and
+    /// match results.
+    pub fn with_personality(seed: u64, name: &str, settings: HumanlikeSettings) -> Self {
+        Self {
+            name: name.to_string(),
+            rng: SeededRng::new(seed),
+            settings,
+            tt: TranspositionTable::new(10_000),
+        }
+    }
+}
+
+impl Bot for HumanlikeBot {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn choose_move(&mut self, state: &GameState, _limits: &SearchLimits) -> Option<Move> {
+        if state.status != GameStatus::Ongoing {
+            return None;
+        }
+        self.tt.set_game_rules(state);
+        choose_humanlike_move(
+            &state.board,
+            state.turn,
+            &self.settings,
+            &mut self.rng,
+            &mut self.tt,
+            &state.position_history,
+            state.variant,
+        )
+        .map(|choice| choice.mv)
+    }
+}
+
+/// Playouts per move of an [`MctsBot`] made by [`create_bot`].
+pub const MCTS_ITERATIONS: usize = 200;
+
+/// Random moves a playout makes before the position is scored by evaluation.
+const MCTS_PLAYOUT_PLIES: usize = 12;
+
+/// Weight of exploration against the observed score in UCT selection.
+const MCTS_EXPLORATION: f64 = 1.4;
+
+/// A position in an [`MctsBot`]'s search tree.
+struct MctsNode {
+    state: GameState,
+    /// Move leading here from the parent
+    mv: Option<Move>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    /// Moves not yet expanded into children
+    untried: Vec<Move>,
+    visits: u32,
+    /// Total score for the side that moved into this node
+    score: f64,
+}
+
+impl MctsNode {
+    fn new(state: GameState, mv: Option<Move>, parent: Option<usize>) -> Self {
+        let untried = if state.status == GameStatus::Ongoing {
+            get_bot_moves(&state)
+        } else {
+            Vec::new()
+        };
+        Self {
+            state,
+            mv,
+            parent,
+            children: Vec::new(),
+            untried,
+            visits: 0,
+            score: 0.0,
+        }
+    }
+}
+
+/// Monte Carlo tree search: UCT selection over seeded random playouts, each
+/// cut short after a few plies and scored by the evaluation's winning
+/// chances. Plays the most visited move.
+#[derive(Debug, Clone)]
+pub struct MctsBot {
+    rng: SeededRng,
+    iterations: usize,
+}
+
+impl MctsBot {
+    pub fn new(seed: u64, iterations: usize) -> Self {
+        Self {
+            rng: SeededRng::new(seed),
+            iterations,
+        }
+    }
+
+    /// White's score (0 to 1) of a random playout from `state`.
+    fn playout(&mut self, state: &GameState) -> f64 {
+        let mut state = state.clone();
+        for _ in 0..MCTS_PLAYOUT_PLIES {
+            if state.status != GameStatus::Ongoing {
+                break;
+            }
+            let moves = get_bot_moves(&state);
+            match self
+                .rng
+                .choose(&moves)
+                .and_then(|mv| replay_move(&state, mv))
+            {
+                Some(next) => state = next,
+                None => break,
+            }
+        }
+        match state.status {
+            GameStatus::Ongoing => {
+                winning_chances(evaluate_position_with_hands(&state.board, &state.hands)) / 100.0
+            }
+            _ => match game_result(&state).and_then(|result| result.winner) {
+                Some(Color::White) => 1.0,
+                Some(Color::Black) => 0.0,
+                None => 0.5,
+            },
+        }
+    }
+}
+
+impl Bot for MctsBot {
+    fn name(&self) -> &str {
+        "mcts"
+    }
+
+    fn choose_move(&mut self, state: &GameState, _limits: &SearchLimits) -> Option<Move> {
+        if state.status != GameStatus::Ongoing {
+            return None;
+        }
+        let mut nodes = vec![MctsNode::new(state.clone(), None, None)];
+        for _ in 0..self.iterations {
+            // Select: descend through fully expanded nodes by UCT
+            let mut current = 0;
+            loop {
+                let node = nodes.get(current)?;
+                if !node.untried.is_empty() || node.children.is_empty() {
+                    break;
+                }
+                let parent_visits = f64::from(node.visits.max(1)).ln();
+                let uct = |child: usize| {
+                    nodes.get(child).map_or(f64::NEG_INFINITY, |child| {
+                        let visits = f64::from(child.visits.max(1));
+                        child.score / visits + MCTS_EXPLORATION * (parent_visits / visits).sqrt()
+                    })
+                };
+                current = *node
+                    .children
+                    .iter()
+                    .max_by(|&&a, &&b| uct(a).total_cmp(&uct(b)))?;
+            }
+
+            // Expand one untried move
+            let child = nodes.len();
+            let node = nodes.get_mut(current)?;
+            if !node.untried.is_empty() {
+                let index = self.rng.next_below(node.untried.len());
+                let mv = node.untried.swap_remove(index);
+                if let Some(next) = replay_move(&node.state, &mv) {
+                    node.children.push(child);
+                    nodes.push(MctsNode::new(next, Some(mv), Some(current)));
+                    current = child;
+                }
+            }
+
+            // Simulate and back the result up the path
+            let white_score = self.playout(&nodes.get(current)?.state);
+            let mut next = Some(current);
+            while let Some(node) = next.and_then(|index| nodes.get_mut(index)) {
+                node.visits += 1;
+                node.score += match node.state.turn.opposite() {
+                    Color::White => white_score,
+                    Color::Black => 1.0 - white_score,
+                };
+                next = node.parent;
+            }
+        }
+
+        nodes
+            .first()?
+            .children
+            .iter()
+            .filter_map(|&child| nodes.get(child))
+            .max_by_key(|child| child.visits)
+            .and_then(|child| child.mv.clone())
+            .or_else(|| get_bot_moves(state).into_iter().next())
+    }
+}
+
+// ============================================================================
+// Consultation
+// ============================================================================
+
+/// A panel member's proposal.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Vote {
+    pub bot: String,
+    pub weight: f64,
+    /// None if the bot had no move
+    pub mv: Option<Move>,
+}
+
+/// The move with the most total weight behind it. Ties go to the move with
+/// the heaviest single supporter, then to the earliest proposal.
+pub fn tally_votes(votes: &[Vote]) -> Option<Move> {
+    // (move, total weight, heaviest supporter)
+    let mut tallies: Vec<(&Move, f64, f64)> = Vec::new();
+    for vote in votes {
+        let Some(mv) = &vote.mv else {
+            continue;
+        };
+        match tallies.iter_mut().find(|(proposed, _, _)| *proposed == mv) {
+            Some(tally) => {
+                tally.1 += vote.weight;
+                tally.2 = tally.2.max(vote.weight);
+            }
+            None => tallies.push((mv, vote.weight, vote.weight)),
+        }
+    }
+    let mut best: Option<(&Move, f64, f64)> = None;
+    for tally in tallies {
+        if best.is_none_or(|(_, total, heaviest)| (tally.1, tally.2) > (total, heaviest)) {
+            best = Some(tally);
+        }
+    }
+    best.map(|(mv, _, _)| mv.clone())
+}
+
+/// Asks every bot of a weighted panel for a move and plays the vote winner
+/// (see [`tally_votes`]). Any [`Bot`] can sit on the panel.
+#[derive(Default)]
+pub struct ConsultationBot {
+    members: Vec<(Box<dyn Bot>, f64)>,
+    last_votes: Vec<Vote>,
+}
+
+impl ConsultationBot {
+    /// An empty panel (which never has a move).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a bot whose vote counts `weight` times. None unless the weight is
+    /// finite and positive.
+    pub fn with_member(mut self, bot: Box<dyn Bot>, weight: f64) -> Option<Self> {
+        if !weight.is_finite() || weight <= 0.0 {
+            return None;
+        }
+        self.members.push((bot, weight));
+        Some(self)
+    }
+
+    /// The engine, MCTS and two human-like personalities (a careful "club"
+    /// player and a "casual" one), with the engine's vote counting most: the
+    /// others outvote it only when MCTS agrees with a personality.
+    pub fn default_panel(seed: u64) -> Self {
+        Self {
+            members: vec![
+                (Box::new(AlphaBetaBot::new(10_000)), 2.0),
+                (Box::new(MctsBot::new(seed, MCTS_ITERATIONS)), 1.5),
+                (
+                    Box::new(HumanlikeBot::with_personality(
+                        seed,
+                        "club",
+                        HumanlikeSettings::for_difficulty(AIDifficulty::Hard),
+                    )),
+                    1.0,
+                ),
+                (
+                    Box::new(HumanlikeBot::with_personality(
+                        seed,
+                        "casual",
+                        HumanlikeSettings::for_difficulty(AIDifficulty::Easy),
+                    )),
+                    0.5,
+                ),
+            ],
+            last_votes: Vec::new(),
+        }
+    }
+
+    /// The votes cast for the latest move.
+    pub fn last_votes(&self) -> &[Vote] {
+        &self.last_votes
+    }
+}
+
+impl Bot for ConsultationBot {
+    fn name(&self) -> &str {
+        "consult"
+    }
+
+    fn choose_move(&mut self, state: &GameState, limits: &SearchLimits) -> Option<Move> {
+        self.last_votes = self
+            .members
+            .iter_mut()
+            .map(|(bot, weight)| Vote {
+                bot: bot.name().to_string(),
+                weight: *weight,
+                mv: bot.choose_move(state, limits),
+            })
+            .collect();
+        tally_votes(&self.last_votes)
+    }
+}
+
+/// Names accepted by [`create_bot`], weakest first.
+pub const BOT_NAMES: &[&str] = &[
+    "random",
+    "capture",
+    "greedy",
+    "knightBlind",
+    "humanlike",
+    "mcts",
+    "alphabeta",
+    "consult",
+];
+
+/// Create a bot by name. Seeded bots are reproducible for the same seed.
+pub fn create_bot(name: &str, seed: u64) -> Option<Box<dyn Bot>> {
+    match name {
+        "random" => Some(Box::new(RandomBot::new(seed))),
+        "capture" => Some(Box::new(GreedyCaptureBot::new(seed))),
+        "greedy" => Some(Box::new(OnePlyGreedyBot::new(seed))),
+        "knightBlind" => Some(Box::new(KnightBlindBot::new(seed))),
+        "humanlike" => Some(Box::new(HumanlikeBot::new(seed, AIDifficulty::Medium))),
+        "mcts" => Some(Box::new(MctsBot::new(seed, MCTS_ITERATIONS))),
+        "alphabeta" => Some(Box::new(AlphaBetaBot::new(10_000))),
+        "consult" => Some(Box::new(ConsultationBot::default_panel(seed))),
+        _ => None,
+    }
+}
+
+// ============================================================================
+// Bot Matches
+// ============================================================================
+
+/// Play a game between two bots from `state` until it ends or `max_plies`
+/// moves are played. A bot that returns no move or an illegal move forfeits.
+pub fn play_bot_game(
+    state: &GameState,
+    white: &mut dyn Bot,
+    black: &mut dyn Bot,
+    limits: &SearchLimits,
+    max_plies: usize,
+) -> GameState {
+    let mut state = state.clone();
+    for _ in 0..max_plies {
+        if state.status != GameStatus::Ongoing {
+            break;
+        }
+        let bot: &mut dyn Bot = match state.turn {
+            Color::White => &mut *white,
+            Color::Black => &mut *black,
+        };
+        match bot
+            .choose_move(&state, limits)
+            .and_then(|mv| replay_move(&state, &mv))
+        {
+            Some(next) => state = next,
+            None => {
+                state.status = GameStatus::Resigned {
+                    winner: state.turn.opposite(),
+                };
+            }
+        }
+    }
+    state
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{create_new_game, create_variant_game};
+    use crate::types::{BoardState, HexCoord, Piece, PieceType};
+
+    #[test]
+    fn test_bots_play_legal_games() {
+        let limits = SearchLimits::depth(1);
+        for variant in [Variant::Standard, Variant::Boulder] {
+            let start = create_variant_game(variant);
+            let mut white = RandomBot::new(1);
+            let mut black = GreedyCaptureBot::new(2);
+            let end = play_bot_game(&start, &mut white, &mut black, &limits, 40);
+            // Neither bot forfeited by producing an illegal move
+            assert!(!matches!(end.status, GameStatus::Resigned { .. }));
+            assert!(!end.history.is_empty());
+        }
+
+        let mut engine = AlphaBetaBot::new(1000);
+        let mut random = RandomBot::new(3);
+        let end = play_bot_game(&create_new_game(), &mut engine, &mut random, &limits, 4);
+        assert_eq!(end.history.len(), 4);
+    }
+
+    #[test]
+    fn test_mcts_bot_finds_mate() {
+        // Q(2,-4)-(0,-2) mates the king boxed in by its pawns
+        let (board, turn) = crate::notation::parse_hexfen("4kpQ2/3p5/9/9/9/9/9/9/4K4 w").unwrap();
+        let state = crate::game::create_game_from_position(board, turn);
+        let mut bot = MctsBot::new(1, 300);
+        let mv = bot.choose_move(&state, &SearchLimits::default()).unwrap();
+        let after = replay_move(&state, &mv).unwrap();
+        assert!(matches!(after.status, GameStatus::Checkmate { .. }));
+
+        // Seeded playouts make it repeatable, and it plays whole games legally
+        let again = MctsBot::new(1, 300).choose_move(&state, &SearchLimits::default());
+        assert_eq!(again, Some(mv));
+        let mut white = MctsBot::new(2, 20);
+        let mut black = create_bot("mcts", 3).unwrap();
+        let end = play_bot_game(
+            &create_new_game(),
+            &mut white,
+            black.as_mut(),
+            &SearchLimits::default(),
+            6,
+        );
+        assert_eq!(end.history.len(), 6);
+    }
+
+    #[test]
+    fn test_greedy_capture_prefers_valuable_victims() {
+        let mut state = create_new_game();
+        let mut board = BoardState::new();
+        board.insert("0,4".to_string(), Piece::new(PieceType::King, Color::White));
+        board.insert(
+            "0,-4".to_string(),
+            Piece::new(PieceType::King, Color::Black),
+        );
+        board.insert(
+            "0,0".to_string(),
+            Piece::new(PieceType::Queen, Color::White),
+        );
+        board.insert(
+            "0,-2".to_string(),
+            Piece::new(PieceType::Pawn, Color::Black),
+        );
+        board.insert(
+            "2,-2".to_string(),
+            Piece::new(PieceType::Knight, Color::Black),
+        );
+        state.board = board;
+
+        let mv = GreedyCaptureBot::new(0)
+            .choose_move(&state, &SearchLimits::default())
+            .unwrap();
+        assert_eq!(mv.to, HexCoord::new(2, -2));
+    }
+
+    #[test]
+    fn test_one_ply_greedy_takes_defended_material() {
+        let mut state = create_new_game();
+        let mut board = BoardState::new();
+        board.insert("0,4".to_string(), Piece::new(PieceType::King, Color::White));
+        board.insert(
+            "0,-4".to_string(),
+            Piece::new(PieceType::King, Color::Black),
+        );
+        board.insert(
+            "0,0".to_string(),
+            Piece::new(PieceType::Queen, Color::White),
+        );
+        // A rook-like chariot defended by a pawn
+        board.insert(
+            "0,-2".to_string(),
+            Piece::new(PieceType::Pawn, Color::Black),
+        );
+        board.insert(
+            "1,-1".to_string(),
+            Piece::new(PieceType::Chariot, Color::Black),
+        );
+        state.board = board;
+
+        let greedy = OnePlyGreedyBot::new(0)
+            .choose_move(&state, &SearchLimits::default())
+            .unwrap();
+        assert_eq!(greedy.to, HexCoord::new(1, -1));
+    }
+
+    #[test]
+    fn test_knight_blind_takes_knight_defended_pawn() {
+        let mut state = create_new_game();
+        let mut board = BoardState::new();
+        board.insert("0,4".to_string(), Piece::new(PieceType::King, Color::White));
+        board.insert(
+            "0,-4".to_string(),
+            Piece::new(PieceType::King, Color::Black),
+        );
+        board.insert(
+            "0,0".to_string(),
+            Piece::new(PieceType::Queen, Color::White),
+        );
+        board.insert(
+            "0,-2".to_string(),
+            Piece::new(PieceType::Pawn, Color::Black),
+        );
+        // Defends the pawn, and could simply be taken by the queen
+        board.insert(
+            "-1,0".to_string(),
+            Piece::new(PieceType::Knight, Color::Black),
+        );
+        state.board = board;
+
+        let mv = KnightBlindBot::new(0)
+            .choose_move(&state, &SearchLimits::default())
+            .unwrap();
+        assert_eq!(mv.to, HexCoord::new(0, -2));
+    }
+
+    #[test]
+    fn test_create_bot() {
+        for name in BOT_NAMES {
+            assert_eq!(create_bot(name, 0).unwrap().name(), *name);
+        }
+        assert!(create_bot("stockfish", 0).is_none());
+    }
+
+    fn vote(bot: &str, weight: f64, mv: Option<&Move>) -> Vote {
+        Vote {
+            bot: bot.to_string(),
+            weight,
+            mv: mv.cloned(),
+        }
+    }
+
+    #[test]
+    fn test_tally_votes() {
+        let moves = get_bot_moves(&create_new_game());
+        let (a, b) = (&moves[0], &moves[1]);
+        // Two lighter votes outweigh a heavier one
+        let votes = [
+            vote("engine", 2.0, Some(a)),
+            vote("x", 1.5, Some(b)),
+            vote("y", 1.0, Some(b)),
+        ];
+        assert_eq!(tally_votes(&votes).as_ref(), Some(b));
+        // On equal totals the heaviest single supporter wins
+        let votes = [
+            vote("x", 1.0, Some(b)),
+            vote("y", 1.0, Some(b)),
+            vote("engine", 2.0, Some(a)),
+        ];
+        assert_eq!(tally_votes(&votes).as_ref(), Some(a));
+        assert_eq!(tally_votes(&[vote("none", 1.0, None)]), None);
+    }
+
+    #[test]
+    fn test_consultation_bot() {
+        let state = create_new_game();
+        let mut panel = ConsultationBot::new()
+            .with_member(Box::new(OnePlyGreedyBot::new(1)), 1.0)
+            .and_then(|panel| panel.with_member(Box::new(RandomBot::new(2)), 1.0))
+            .and_then(|panel| panel.with_member(Box::new(OnePlyGreedyBot::new(1)), 1.0))
+            .unwrap();
+        let mv = panel.choose_move(&state, &SearchLimits::depth(1)).unwrap();
+        let votes = panel.last_votes();
+        assert_eq!(votes.len(), 3);
+        assert_eq!(votes[0].bot, "greedy");
+        // The two identical greedy bots agree and carry the vote
+        assert_eq!(votes[0].mv.as_ref(), Some(&mv));
+        assert_eq!(votes[2].mv.as_ref(), Some(&mv));
+
+        assert!(ConsultationBot::new()
+            .choose_move(&state, &SearchLimits::depth(1))
+            .is_none());
+        for weight in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(ConsultationBot::new()
+                .with_member(Box::new(RandomBot::new(1)), weight)
+                .is_none());
+        }
+        let mut humanlike = HumanlikeBot::new(3, AIDifficulty::Easy);
+        let end = play_bot_game(
+            &state,
+            &mut humanlike,
+            &mut panel,
+            &SearchLimits::depth(1),
+            4,
+        );
+        assert_eq!(end.history.len(), 4);
+    }
+
+    #[test]
+    fn test_default_panel() {
+        let mut panel = ConsultationBot::default_panel(1);
+        let state = create_new_game();
+        let mv = panel.choose_move(&state, &SearchLimits::depth(2)).unwrap();
+        let names: Vec<&str> = panel
+            .last_votes()
+            .iter()
+            .map(|vote| vote.bot.as_str())
+            .collect();
+        assert_eq!(names, ["alphabeta", "mcts", "club", "casual"]);
+        assert!(get_bot_moves(&state).contains(&mv));
+    }
+}
@@ -0,0 +1,281 @@
+//! Underchex Game Clocks
+//!
+//! Chess clocks for online play with Fischer increment and bounded lag
+//! compensation. The server measures the time between sending a position and
+//! receiving the reply; the client reports how long its player actually
+//! thought. The difference is network lag, and up to a configured maximum of
+//! it is refunded so players on slow connections are not charged for transit.
+//! Every clock press is recorded for auditing.
+//!
+//! Armageddon tiebreaks give White more time than Black, and Black the game
+//! if it is drawn.
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::Color;
+
+// ============================================================================
+// Configuration
+// ============================================================================
+
+/// Default upper bound on the lag refunded per move.
+pub const DEFAULT_MAX_LAG_COMPENSATION_MS: u64 = 500;
+
+/// Time control settings, in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClockSettings {
+    pub initial_ms: u64,
+    /// Added after each completed move (Fischer increment)
+    pub increment_ms: u64,
+    /// Most lag refunded for a single move
+    pub max_lag_compensation_ms: u64,
+    /// Black's initial time, if it differs from White's
+    #[serde(default)]
+    pub black_initial_ms: Option<u64>,
+    /// A draw counts as a win for Black (armageddon)
+    #[serde(default)]
+    pub armageddon: bool,
+}
+
+impl ClockSettings {
+    pub fn new(initial_ms: u64, increment_ms: u64) -> Self {
+        Self {
+            initial_ms,
+            increment_ms,
+            max_lag_compensation_ms: DEFAULT_MAX_LAG_COMPENSATION_MS,
+            black_initial_ms: None,
+            armageddon: false,
+        }
+    }
+
+    /// Armageddon time control: unequal initial times, and a draw counts as
+    /// a win for Black.
+    pub fn armageddon(white_ms: u64, black_ms: u64, increment_ms: u64) -> Self {
+        Self {
+            black_initial_ms: Some(black_ms),
+            armageddon: true,
+            ..Self::new(white_ms, increment_ms)
+        }
+    }
+
+    /// A side's initial time.
+    pub fn initial_for(&self, color: Color) -> u64 {
+        match color {
+            Color::White => self.initial_ms,
+            Color::Black => self.black_initial_ms.unwrap_or(self.initial_ms),
+        }
+    }
+
+    /// Side a draw counts as a win for, under armageddon.
+    pub fn draw_winner(&self) -> Option<Color> {
+        self.armageddon.then_some(Color::Black)
+    }
+}
+
+// ============================================================================
+// Clock
+// ============================================================================
+
+/// Audit record of one clock press.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClockRecord {
+    pub color: Color,
+    /// Index of the move in the game history
+    pub move_index: usize,
+    /// Time between the turn starting and the move arriving at the server
+    pub server_elapsed_ms: u64,
+    /// Thinking time reported by the client, if any
+    pub client_elapsed_ms: Option<u64>,
+    /// Time refunded as lag
+    pub compensation_ms: u64,
+    /// Time deducted from the clock
+    pub charged_ms: u64,
+    /// Time left after the move, including the increment
+    pub remaining_ms: u64,
+}
+
+/// Clock for both players, driven by server timestamps in milliseconds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameClock {
+    pub settings: ClockSettings,
+    pub white_ms: u64,
+    pub black_ms: u64,
+    /// Side whose clock is running, None before the start and after a flag
+    pub running: Option<Color>,
+    /// Server time at which the running side's turn started
+    pub turn_started_at: u64,
+    pub records: Vec<ClockRecord>,
+}
+
+impl GameClock {
+    pub fn new(settings: ClockSettings) -> Self {
+        Self {
+            settings,
+            white_ms: settings.initial_for(Color::White),
+            black_ms: settings.initial_for(Color::Black),
+            running: None,
+            turn_started_at: 0,
+            records: Vec::new(),
+        }
+    }
+
+    /// Start `color`'s clock at server time `now`.
+    pub fn start(&mut self, color: Color, now: u64) {
+        self.running = Some(color);
+        self.turn_started_at = now;
+    }
+
+    fn stored_ms(&self, color: Color) -> u64 {
+        match color {
+            Color::White => self.white_ms,
+            Color::Black => self.black_ms,
+        }
+    }
+
+    fn set_stored_ms(&mut self, color: Color, ms: u64) {
+        match color {
+            Color::White => self.white_ms = ms,
+            Color::Black => self.black_ms = ms,
+        }
+    }
+
+    /// Time left for a side at server time `now`, counting the running turn.
+    pub fn remaining(&self, color: Color, now: u64) -> u64 {
+        let stored = self.stored_ms(color);
+        if self.running == Some(color) {
+            stored.saturating_sub(now.saturating_sub(self.turn_started_at))
+        } else {
+            stored
+        }
+    }
+
+    /// Side whose time has run out at `now` (ignoring lag compensation, which
+    /// only applies to moves that arrive).
+    pub fn flagged(&self, now: u64) -> Option<Color> {
+        self.running
+            .filter(|&color| self.remaining(color, now) == 0)
+    }
+
+    /// Stop the running side's clock for a move received at server time
+    /// `now`, and start the opponent's. `client_elapsed_ms` is the thinking
+    /// time the client reported. Returns the record, or None if no clock is
+    /// running or the mover's time ran out even after compensation (the clock
+    /// then stops).
+    pub fn press(
+        &mut self,
+        now: u64,
+        client_elapsed_ms: Option<u64>,
+        move_index: usize,
+    ) -> Option<ClockRecord> {
+        let color = self.running?;
+        let server_elapsed_ms = now.saturating_sub(self.turn_started_at);
+        // A client claiming more time than the server measured gets no refund
+        let compensation_ms = client_elapsed_ms
+            .map(|client| server_elapsed_ms.saturating_sub(client))
+            .unwrap_or(0)
+            .min(self.settings.max_lag_compensation_ms);
+        let charged_ms = server_elapsed_ms - compensation_ms;
+
+        let stored = self.stored_ms(color);
+        if charged_ms >= stored {
+            self.set_stored_ms(color, 0);
+            self.running = None;
+            return None;
+        }
+
+        let remaining_ms = stored - charged_ms + self.settings.increment_ms;
+        self.set_stored_ms(color, remaining_ms);
+        self.start(color.opposite(), now);
+
+        let record = ClockRecord {
+            color,
+            move_index,
+            server_elapsed_ms,
+            client_elapsed_ms,
+            compensation_ms,
+            charged_ms,
+            remaining_ms,
+        };
+        self.records.push(record.clone());
+        Some(record)
+    }
+
+    /// Total lag refunded to a side over the game.
+    pub fn total_compensation(&self, color: Color) -> u64 {
+        self.records
+            .iter()
+            .filter(|record| record.color == color)
+            .map(|record| record.compensation_ms)
+            .sum()
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_press_with_increment_and_compensation() {
+        let mut clock = GameClock::new(ClockSettings::new(60_000, 2_000));
+        clock.start(Color::White, 1_000);
+
+        // 5s at the server, 4.8s reported: 200ms lag refunded
+        let record = clock.press(6_000, Some(4_800), 0).unwrap();
+        assert_eq!(record.compensation_ms, 200);
+        assert_eq!(record.charged_ms, 4_800);
+        assert_eq!(clock.white_ms, 60_000 - 4_800 + 2_000);
+        assert_eq!(clock.running, Some(Color::Black));
+
+        // Claimed lag beyond the maximum is capped
+        let record = clock.press(16_000, Some(1_000), 1).unwrap();
+        assert_eq!(record.compensation_ms, DEFAULT_MAX_LAG_COMPENSATION_MS);
+        assert_eq!(record.charged_ms, 9_500);
+
+        // Overstated thinking time and missing reports get no refund
+        assert_eq!(
+            clock.press(17_000, Some(5_000), 2).unwrap().compensation_ms,
+            0
+        );
+        assert_eq!(clock.press(18_000, None, 3).unwrap().compensation_ms, 0);
+        assert_eq!(clock.records.len(), 4);
+        assert_eq!(clock.total_compensation(Color::White), 200);
+    }
+
+    #[test]
+    fn test_flag() {
+        let mut clock = GameClock::new(ClockSettings::new(1_000, 0));
+        clock.start(Color::White, 0);
+        assert_eq!(clock.remaining(Color::White, 400), 600);
+        assert_eq!(clock.flagged(999), None);
+        assert_eq!(clock.flagged(1_000), Some(Color::White));
+
+        // Compensation can save a move that arrived late only because of lag
+        let mut saved = clock.clone();
+        assert!(saved.press(1_300, Some(900), 0).is_some());
+        assert_eq!(saved.white_ms, 100);
+
+        assert!(clock.press(2_000, Some(1_900), 0).is_none());
+        assert_eq!(clock.white_ms, 0);
+        assert_eq!(clock.running, None);
+    }
+
+    #[test]
+    fn test_armageddon_settings() {
+        let settings = ClockSettings::armageddon(5 * 60_000, 4 * 60_000, 0);
+        let clock = GameClock::new(settings);
+        assert_eq!(clock.white_ms, 300_000);
+        assert_eq!(clock.black_ms, 240_000);
+        assert_eq!(settings.draw_winner(), Some(Color::Black));
+        assert_eq!(ClockSettings::new(1_000, 0).draw_winner(), None);
+
+        // Settings saved before armageddon was added load as a normal clock
+        let json = r#"{"initial_ms":1000,"increment_ms":0,"max_lag_compensation_ms":500}"#;
+        let settings: ClockSettings = serde_json::from_str(json).unwrap();
+        assert_eq!(settings.initial_for(Color::Black), 1_000);
+        assert!(!settings.armageddon);
+    }
+}
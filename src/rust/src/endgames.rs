@@ -0,0 +1,536 @@
+//! Underchex Endgame Practice Positions
+//!
+//! Generates sound practice positions for a named endgame class (e.g. "KQvK",
+//! "KLvK", "KPvK"). White is always the side with the extra material and is
+//! to move. When a tablebase exists for the class, positions can be filtered
+//! by difficulty, expressed as a distance-to-mate range.
+//!
+//! King activity in pawnless endings: opposition between the kings along a
+//! hex line, how far a king is confined towards the edge, and a defense score
+//! for the losing side that the evaluation adds (the `defenseWeight` option)
+//! and the trainer grades defensive moves with.
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ai::get_piece_value;
+use crate::attacks::build_attack_map;
+use crate::board::{get_all_cells, get_line, get_neighbors, hex_distance};
+use crate::game::create_game_from_position;
+use crate::moves::{apply_move, find_king, generate_all_legal_moves, is_in_check};
+use crate::options::EngineOption;
+use crate::rng::SeededRng;
+use crate::stats::ENDGAME_MATERIAL;
+use crate::tablebase::{generate_tablebase_on_demand, get_tablebase, TablebaseEntry, WDLOutcome};
+use crate::types::{
+    is_promotion_zone, BoardState, Color, GameState, HexCoord, LanceVariant, Piece, PieceType,
+    BOARD_RADIUS,
+};
+
+/// Maximum placement attempts before giving up on a class/difficulty.
+pub const MAX_GENERATION_ATTEMPTS: usize = 20000;
+
+// ============================================================================
+// Endgame Classes
+// ============================================================================
+
+/// Piece makeup of an endgame class, kings excluded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndgameClass {
+    pub name: String,
+    pub stronger_side: Vec<PieceType>,
+    pub weaker_side: Vec<PieceType>,
+}
+
+/// Parse an endgame class name of the form `K[QLCNP]*vK[QLCNP]*`.
+pub fn parse_endgame_class(name: &str) -> Option<EndgameClass> {
+    let (stronger, weaker) = name.split_once('v')?;
+    let parse_side = |side: &str| -> Option<Vec<PieceType>> {
+        side.strip_prefix('K')?
+            .chars()
+            .map(|c| match c {
+                'Q' => Some(PieceType::Queen),
+                'L' => Some(PieceType::Lance),
+                'C' => Some(PieceType::Chariot),
+                'N' => Some(PieceType::Knight),
+                'P' => Some(PieceType::Pawn),
+                _ => None,
+            })
+            .collect()
+    };
+
+    Some(EndgameClass {
+        name: name.to_string(),
+        stronger_side: parse_side(stronger)?,
+        weaker_side: parse_side(weaker)?,
+    })
+}
+
+/// Requested difficulty as an inclusive distance-to-mate range (in plies).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EndgameDifficulty {
+    pub min_dtm: i32,
+    pub max_dtm: i32,
+}
+
+impl EndgameDifficulty {
+    pub fn new(min_dtm: i32, max_dtm: i32) -> Self {
+        Self { min_dtm, max_dtm }
+    }
+
+    /// Named difficulty presets: "easy", "medium", "hard".
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "easy" => Some(Self::new(1, 5)),
+            "medium" => Some(Self::new(6, 13)),
+            "hard" => Some(Self::new(14, i32::MAX)),
+            _ => None,
+        }
+    }
+
+    /// Check whether a tablebase entry (for white to move) fits this difficulty.
+    pub fn matches(&self, entry: &TablebaseEntry) -> bool {
+        entry.wdl == WDLOutcome::Win && entry.dtm >= self.min_dtm && entry.dtm <= self.max_dtm
+    }
+}
+
+// ============================================================================
+// Position Generation
+// ============================================================================
+
+fn random_piece(rng: &mut SeededRng, piece_type: PieceType, color: Color) -> Piece {
+    if piece_type == PieceType::Lance {
+        let variant = if rng.next_below(2) == 0 {
+            LanceVariant::A
+        } else {
+            LanceVariant::B
+        };
+        Piece::lance(color, variant)
+    } else {
+        Piece::new(piece_type, color)
+    }
+}
+
+/// Randomly place the pieces of a class. Returns None if a piece could not be
+/// placed on an allowed cell.
+fn place_pieces(class: &EndgameClass, rng: &mut SeededRng) -> Option<BoardState> {
+    let cells = get_all_cells();
+    let mut board = BoardState::new();
+
+    let pieces = [
+        (Color::White, PieceType::King),
+        (Color::Black, PieceType::King),
+    ]
+    .into_iter()
+    .chain(class.stronger_side.iter().map(|&p| (Color::White, p)))
+    .chain(class.weaker_side.iter().map(|&p| (Color::Black, p)));
+
+    for (color, piece_type) in pieces {
+        let free: Vec<HexCoord> = cells
+            .iter()
+            .copied()
+            .filter(|c| !board.contains_key(&c.to_key()))
+            .filter(|&c| piece_type != PieceType::Pawn || !is_promotion_zone(c, color))
+            .collect();
+        let cell = *rng.choose(&free)?;
+        board.insert(cell.to_key(), random_piece(rng, piece_type, color));
+    }
+
+    Some(board)
+}
+
+/// Check that a position with white to move is legal and not already decided.
+fn is_sound_position(board: &BoardState) -> bool {
+    !is_in_check(board, Color::Black) && !generate_all_legal_moves(board, Color::White).is_empty()
+}
+
+/// Generate a practice position for an endgame class, white to move.
+///
+/// With a `difficulty`, the tablebase for the class is used (generated on
+/// demand if not loaded, which can be slow) and only winning positions whose
+/// distance to mate falls in the range are accepted. Returns None for an
+/// unknown class or if no matching position was found.
+pub fn random_position_with_difficulty(
+    class: &str,
+    seed: u64,
+    difficulty: Option<EndgameDifficulty>,
+) -> Option<GameState> {
+    let class = parse_endgame_class(class)?;
+    let mut rng = SeededRng::new(seed);
+
+    let tablebase = match difficulty {
+        Some(_) => {
+            let tablebase =
+                get_tablebase(&class.name).or_else(|| generate_tablebase_on_demand(&class.name))?;
+            if tablebase.metadata.win_count == 0 {
+                return None;
+            }
+            Some(tablebase)
+        }
+        None => None,
+    };
+
+    for _ in 0..MAX_GENERATION_ATTEMPTS {
+        let board = match place_pieces(&class, &mut rng) {
+            Some(board) => board,
+            None => continue,
+        };
+        if !is_sound_position(&board) {
+            continue;
+        }
+
+        if let (Some(difficulty), Some(tablebase)) = (difficulty, &tablebase) {
+            if !tablebase
+                .probe(&board, Color::White)
+                .is_some_and(|entry| difficulty.matches(&entry))
+            {
+                continue;
+            }
+        }
+
+        return Some(create_game_from_position(board, Color::White));
+    }
+
+    None
+}
+
+/// Generate a practice position for an endgame class with no difficulty filter.
+pub fn random_position(class: &str, seed: u64) -> Option<GameState> {
+    random_position_with_difficulty(class, seed, None)
+}
+
+// ============================================================================
+// King Activity
+// ============================================================================
+
+/// Centipawns per ring a defending king stands nearer the center.
+const DEFENSE_CENTER_WEIGHT: i32 = 15;
+
+/// Centipawns per cell of a defending king's region.
+const DEFENSE_ROOM_WEIGHT: i32 = 4;
+
+/// Centipawns per step a defending king keeps from the attacking king.
+const DEFENSE_DISTANCE_WEIGHT: i32 = 5;
+
+/// How close the opposing kings stand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OppositionKind {
+    /// One cell between the kings
+    Direct,
+    /// Three or more cells between the kings
+    Distant,
+}
+
+/// Kings facing each other along a hex line with an odd number of cells
+/// between them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Opposition {
+    pub kind: OppositionKind,
+    /// The side not to move, whose king the other cannot get past
+    pub holder: Color,
+    /// Steps between the kings
+    pub distance: u32,
+}
+
+/// The opposition between the kings, if they stand on a common line an even
+/// number of steps apart.
+pub fn hex_opposition(board: &BoardState, side_to_move: Color) -> Option<Opposition> {
+    let white = find_king(board, Color::White)?;
+    let black = find_king(board, Color::Black)?;
+    let (_, distance) = get_line(white, black)?;
+    if !distance.is_multiple_of(2) {
+        return None;
+    }
+    Some(Opposition {
+        kind: if distance == 2 {
+            OppositionKind::Direct
+        } else {
+            OppositionKind::Distant
+        },
+        holder: side_to_move.opposite(),
+        distance,
+    })
+}
+
+/// How far a king is confined towards the edge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Confinement {
+    pub king: HexCoord,
+    /// Ring of the king's cell: 0 in the center, 4 on the edge
+    pub ring: i32,
+    /// Cells the king can walk to without stepping onto an attacked cell or
+    /// its own pieces, its own cell included
+    pub region: usize,
+}
+
+/// How confined `color`'s king is.
+pub fn king_confinement(board: &BoardState, color: Color) -> Option<Confinement> {
+    let king = find_king(board, color)?;
+    let attacked = build_attack_map(board, color.opposite());
+    let mut region = HashSet::from([king]);
+    let mut frontier = vec![king];
+    while let Some(cell) = frontier.pop() {
+        for next in get_neighbors(cell) {
+            let key = next.to_key();
+            let blocked = attacked.contains_key(&key)
+                || board
+                    .get(&key)
+                    .is_some_and(|piece| piece.color == color || piece.is_boulder());
+            if !blocked && region.insert(next) {
+                frontier.push(next);
+            }
+        }
+    }
+    Some(Confinement {
+        king,
+        ring: hex_distance(king, HexCoord::new(0, 0)),
+        region: region.len(),
+    })
+}
+
+/// How well `defender`'s king resists being mated, in centipawns from its
+/// side: more the nearer the center it stands, the more room it has and the
+/// farther it keeps from the attacking king.
+pub fn losing_side_defense_score(board: &BoardState, defender: Color) -> i32 {
+    let Some(confinement) = king_confinement(board, defender) else {
+        return 0;
+    };
+    let distance = find_king(board, defender.opposite())
+        .map_or(0, |king| hex_distance(king, confinement.king));
+    (BOARD_RADIUS - confinement.ring).max(0) * DEFENSE_CENTER_WEIGHT
+        + confinement.region as i32 * DEFENSE_ROOM_WEIGHT
+        + distance * DEFENSE_DISTANCE_WEIGHT
+}
+
+/// Evaluation bonus for the losing side's defense in a pawnless ending (less
+/// material on both sides than [`ENDGAME_MATERIAL`]): its
+/// [`losing_side_defense_score`], scaled by the `defenseWeight` option
+/// (percent). Returns value from white's perspective in centipawns.
+pub(crate) fn defense_bonus(board: &BoardState) -> i32 {
+    let weight = EngineOption::DefenseWeight.get();
+    if weight == 0
+        || board
+            .values()
+            .any(|piece| piece.piece_type == PieceType::Pawn)
+    {
+        return 0;
+    }
+    let material = |color: Color| -> i32 {
+        board
+            .values()
+            .filter(|piece| piece.color == color && !piece.is_boulder())
+            .filter(|piece| piece.piece_type != PieceType::King)
+            .map(|piece| get_piece_value(piece.piece_type))
+            .sum()
+    };
+    let (white, black) = (material(Color::White), material(Color::Black));
+    if white == black || white + black > ENDGAME_MATERIAL {
+        return 0;
+    }
+    let defender = if white < black {
+        Color::White
+    } else {
+        Color::Black
+    };
+    let bonus = losing_side_defense_score(board, defender) * weight / 100;
+    if defender == Color::White {
+        bonus
+    } else {
+        -bonus
+    }
+}
+
+/// A defensive move graded against the defender's other moves.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DefenseGrade {
+    /// [`losing_side_defense_score`] after the move
+    pub score: i32,
+    /// Best and worst scores of the legal moves
+    pub best: i32,
+    pub worst: i32,
+    /// Where the move stands between the worst and the best, 0-100
+    pub accuracy: f64,
+}
+
+/// Grade a move of the piece on `from` by the defense it keeps, for the
+/// endgame trainer. None if the move is not legal.
+pub fn grade_defense(board: &BoardState, from: HexCoord, to: HexCoord) -> Option<DefenseGrade> {
+    let defender = board.get(&from.to_key())?.color;
+    let scored: Vec<(i32, bool)> = generate_all_legal_moves(board, defender)
+        .iter()
+        .map(|mv| {
+            let score = losing_side_defense_score(&apply_move(board, mv), defender);
+            (score, (mv.from, mv.to) == (from, to))
+        })
+        .collect();
+    let score = scored.iter().find(|&&(_, played)| played)?.0;
+    let best = scored.iter().map(|&(score, _)| score).max()?;
+    let worst = scored.iter().map(|&(score, _)| score).min()?;
+    let accuracy = if best == worst {
+        100.0
+    } else {
+        f64::from(score - worst) * 100.0 / f64::from(best - worst)
+    };
+    Some(DefenseGrade {
+        score,
+        best,
+        worst,
+        accuracy,
+    })
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::GameStatus;
+
+    #[test]
+    fn test_parse_endgame_class() {
+        let class = parse_endgame_class("KQvK").unwrap();
+        assert_eq!(class.stronger_side, vec![PieceType::Queen]);
+        assert!(class.weaker_side.is_empty());
+
+        let class = parse_endgame_class("KPvKP").unwrap();
+        assert_eq!(class.weaker_side, vec![PieceType::Pawn]);
+
+        assert!(parse_endgame_class("KXvK").is_none());
+        assert!(parse_endgame_class("QvK").is_none());
+    }
+
+    #[test]
+    fn test_random_position_is_sound_and_deterministic() {
+        for seed in 0..20 {
+            let game = random_position("KPvK", seed).unwrap();
+            assert_eq!(game.status, GameStatus::Ongoing);
+            assert_eq!(game.turn, Color::White);
+            assert_eq!(game.board.len(), 3);
+            assert!(!is_in_check(&game.board, Color::Black));
+
+            let (pawn_key, _) = game
+                .board
+                .iter()
+                .find(|(_, p)| p.piece_type == PieceType::Pawn)
+                .unwrap();
+            let pawn = HexCoord::from_key(pawn_key).unwrap();
+            assert!(!is_promotion_zone(pawn, Color::White));
+
+            let again = random_position("KPvK", seed).unwrap();
+            assert_eq!(again.board, game.board);
+        }
+    }
+
+    #[test]
+    fn test_unknown_class() {
+        assert!(random_position("KZvK", 1).is_none());
+    }
+
+    #[test]
+    fn test_difficulty_matches() {
+        let medium = EndgameDifficulty::from_name("medium").unwrap();
+        let entry = |wdl, dtm| TablebaseEntry {
+            wdl,
+            dtm,
+            best_move: None,
+        };
+        assert!(medium.matches(&entry(WDLOutcome::Win, 7)));
+        assert!(!medium.matches(&entry(WDLOutcome::Win, 3)));
+        assert!(!medium.matches(&entry(WDLOutcome::Draw, 7)));
+        assert!(EndgameDifficulty::from_name("impossible").is_none());
+    }
+
+    #[test]
+    fn test_difficulty_with_drawn_class() {
+        // KvK is always a draw, so no winning position can match.
+        assert!(
+            random_position_with_difficulty("KvK", 3, Some(EndgameDifficulty::new(1, 5))).is_none()
+        );
+    }
+
+    fn kings_and(pieces: &[((i32, i32), Piece)]) -> BoardState {
+        let mut board = BoardState::new();
+        for &((q, r), piece) in pieces {
+            board.insert(HexCoord::new(q, r).to_key(), piece);
+        }
+        board
+    }
+
+    #[test]
+    fn test_hex_opposition() {
+        let white = Piece::new(PieceType::King, Color::White);
+        let black = Piece::new(PieceType::King, Color::Black);
+
+        let direct = kings_and(&[((0, 1), white), ((0, -1), black)]);
+        let opposition = hex_opposition(&direct, Color::Black).unwrap();
+        assert_eq!(opposition.kind, OppositionKind::Direct);
+        assert_eq!(opposition.holder, Color::White);
+
+        let distant = kings_and(&[((-2, 0), white), ((2, 0), black)]);
+        let opposition = hex_opposition(&distant, Color::White).unwrap();
+        assert_eq!(opposition.kind, OppositionKind::Distant);
+        assert_eq!((opposition.holder, opposition.distance), (Color::Black, 4));
+
+        // Three steps apart, or off a common line
+        assert!(hex_opposition(
+            &kings_and(&[((0, 2), white), ((0, -1), black)]),
+            Color::White
+        )
+        .is_none());
+        assert!(hex_opposition(
+            &kings_and(&[((1, 1), white), ((0, -1), black)]),
+            Color::White
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_defense_score() {
+        let white = Piece::new(PieceType::King, Color::White);
+        let black = Piece::new(PieceType::King, Color::Black);
+        let queen = Piece::new(PieceType::Queen, Color::White);
+
+        // The queen fences a cornered king in; a central one roams free
+        let cornered = kings_and(&[((0, 4), white), ((0, -4), black), ((-1, -2), queen)]);
+        let central = kings_and(&[((0, 4), white), ((0, 0), black), ((3, -1), queen)]);
+        let confinement = king_confinement(&cornered, Color::Black).unwrap();
+        assert_eq!(confinement.ring, 4);
+        assert!(confinement.region < king_confinement(&central, Color::Black).unwrap().region);
+        assert!(
+            losing_side_defense_score(&central, Color::Black)
+                > losing_side_defense_score(&cornered, Color::Black)
+        );
+
+        // The evaluation credits Black's defense, and not with pawns around
+        assert!(defense_bonus(&central) < 0);
+        let mut with_pawn = central.clone();
+        with_pawn.insert("2,2".to_string(), Piece::new(PieceType::Pawn, Color::White));
+        assert_eq!(defense_bonus(&with_pawn), 0);
+    }
+
+    #[test]
+    fn test_grade_defense() {
+        let white = Piece::new(PieceType::King, Color::White);
+        let black = Piece::new(PieceType::King, Color::Black);
+        let queen = Piece::new(PieceType::Queen, Color::White);
+        let board = kings_and(&[((0, 4), white), ((1, -2), black), ((-3, 1), queen)]);
+
+        let moves = generate_all_legal_moves(&board, Color::Black);
+        let grades: Vec<DefenseGrade> = moves
+            .iter()
+            .map(|mv| grade_defense(&board, mv.from, mv.to).unwrap())
+            .collect();
+        assert!(grades.iter().any(|grade| grade.accuracy == 100.0));
+        assert!(grades
+            .iter()
+            .all(|grade| (0.0..=100.0).contains(&grade.accuracy) && grade.score <= grade.best));
+        assert!(grade_defense(&board, HexCoord::new(1, -2), HexCoord::new(1, 2)).is_none());
+    }
+}
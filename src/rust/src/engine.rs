@@ -0,0 +1,401 @@
+//! Underchex Persistent Engine
+//!
+//! An engine that stays warm between requests, for native front ends that
+//! analyze one game position after another. The transposition table, move
+//! ordering tables and last search are kept, so the analysis of a position
+//! reached from an earlier one reuses that search's tree; older entries age
+//! out instead of the table being cleared.
+//!
+//! [`engine_info`] tells GUIs and servers what this build of the engine
+//! supports, so they can negotiate instead of assuming.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ai::{find_best_move_iterative, CancelToken, SearchResult, TranspositionTable};
+use crate::game::{count_repetitions, create_position_record};
+use crate::options::{list_options, EngineOption, OptionInfo, OptionKind};
+use crate::tablebase::{embedded_tablebases, get_loaded_tablebases};
+use crate::types::{GameState, PieceType, PositionRecord, STALEMATE_RULE_NAMES, VARIANT_NAMES};
+
+/// A search by [`Engine::analyze`].
+#[derive(Clone, Debug)]
+pub struct EngineSearch {
+    pub result: SearchResult,
+    /// Depth the position was searched to, short of the depth asked for if
+    /// the search was cancelled
+    pub depth: i32,
+    /// Whether the position continues the previous one's game: it was in
+    /// the previous search tree, or is the same position or an earlier one
+    pub continued: bool,
+    /// Whether the result is the previous search's, as the position was
+    /// already searched at least as deep
+    pub reused: bool,
+}
+
+/// A search engine keeping its tables between requests.
+pub struct Engine {
+    tt: TranspositionTable,
+    /// The last position searched, and the search
+    last: Option<(PositionRecord, EngineSearch)>,
+}
+
+impl Engine {
+    /// Create an engine with a table of the `ttSize` option's size.
+    pub fn new() -> Self {
+        Self::with_tt_size(EngineOption::TTSize.get() as usize)
+    }
+
+    pub fn with_tt_size(tt_size: usize) -> Self {
+        Self {
+            tt: TranspositionTable::new(tt_size),
+            last: None,
+        }
+    }
+
+    /// Search a position by iterative deepening to `depth`. A continuation
+    /// of the previous position's game keeps the table and ordering tables
+    /// (aging the entries); any other position only keeps the table.
+    /// Cancelling [`Engine::cancel_token`] stops the search with the deepest
+    /// iteration finished; the token is reset when the next search starts.
+    pub fn analyze(&mut self, state: &GameState, depth: i32) -> EngineSearch {
+        let root = create_position_record(&state.board, state.turn);
+        if let Some((last_root, search)) = &self.last {
+            if last_root.same_position(&root) && search.depth >= depth {
+                return EngineSearch {
+                    continued: true,
+                    reused: true,
+                    ..search.clone()
+                };
+            }
+        }
+
+        let continued = self.last.as_ref().is_some_and(|(last_root, _)| {
+            last_root.same_position(&root)
+                || count_repetitions(&state.position_history, last_root) > 0
+        }) || self.tt.probe(&state.board).is_some();
+        self.tt.set_game_rules(state);
+        self.tt.new_search();
+        self.tt.cancel_token().reset();
+        if !continued {
+            self.tt.clear_ordering();
+        }
+
+        let result = find_best_move_iterative(
+            &state.board,
+            state.turn,
+            depth,
+            u64::MAX,
+            &mut self.tt,
+            true,
+            &state.position_history,
+            state.variant,
+        );
+        let depth = if self.tt.is_cancelled() {
+            result.stats.max_depth_reached
+        } else {
+            depth
+        };
+        let search = EngineSearch {
+            result,
+            depth,
+            continued,
+            reused: false,
+        };
+        self.last = Some((root, search.clone()));
+        search
+    }
+
+    /// Start a new game: the next position is not a continuation, but the
+    /// table's entries are kept to age out.
+    pub fn new_game(&mut self) {
+        self.last = None;
+        self.tt.clear_ordering();
+    }
+
+    /// Empty every table.
+    pub fn clear(&mut self) {
+        self.last = None;
+        self.tt.clear();
+    }
+
+    /// Replace the table with an empty one of `tt_size` entries, keeping
+    /// the cancel token.
+    pub fn resize(&mut self, tt_size: usize) {
+        let cancel = self.tt.cancel_token();
+        self.tt = TranspositionTable::new(tt_size);
+        self.tt.set_cancel_token(cancel);
+        self.last = None;
+    }
+
+    /// Token that stops the running search, for another thread or a signal
+    /// handler.
+    pub fn cancel_token(&self) -> CancelToken {
+        self.tt.cancel_token()
+    }
+
+    /// Entries in the transposition table.
+    pub fn tt_size(&self) -> usize {
+        self.tt.size()
+    }
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ============================================================================
+// Engine Identity
+// ============================================================================
+
+pub const ENGINE_NAME: &str = "Underchex";
+
+/// Piece types a `captureAll` win condition may name.
+const CAPTURE_ALL_TYPES: [PieceType; 11] = [
+    PieceType::Pawn,
+    PieceType::Queen,
+    PieceType::Knight,
+    PieceType::Lance,
+    PieceType::Chariot,
+    PieceType::Wazir,
+    PieceType::Ferz,
+    PieceType::Dabbaba,
+    PieceType::Cannon,
+    PieceType::PromotedKnight,
+    PieceType::PromotedLance,
+];
+
+/// A rule of a game that can be set besides its variant.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleOption {
+    pub name: String,
+    /// Values the rule accepts, the default first
+    pub values: Vec<String>,
+}
+
+/// Tablebases available to the engine.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TablebaseAvailability {
+    /// Configurations bundled into the build
+    pub embedded: Vec<String>,
+    /// Configurations loaded now
+    pub loaded: Vec<String>,
+}
+
+/// What this build of the engine is and supports.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EngineInfo {
+    pub name: String,
+    pub version: String,
+    /// Variant names, as `Variant::from_name` accepts them
+    pub variants: Vec<String>,
+    pub rule_options: Vec<RuleOption>,
+    pub tablebases: TablebaseAvailability,
+    /// Cargo features the crate was built with
+    pub features: Vec<String>,
+    /// Tunable engine options (see `crate::options`)
+    pub options: Vec<OptionInfo>,
+}
+
+fn to_strings(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+/// Cargo features the crate was built with.
+fn build_features() -> Vec<String> {
+    let features = [
+        ("gamedb", cfg!(feature = "gamedb")),
+        ("embedded-tablebases", cfg!(feature = "embedded-tablebases")),
+        ("trace", cfg!(feature = "trace")),
+        ("signed-saves", cfg!(feature = "signed-saves")),
+    ];
+    features
+        .iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| name.to_string())
+        .collect()
+}
+
+/// Describe the engine.
+pub fn engine_info() -> EngineInfo {
+    let piece_names = CAPTURE_ALL_TYPES.iter().filter_map(|piece_type| {
+        serde_json::to_value(piece_type)
+            .ok()
+            .and_then(|name| name.as_str().map(str::to_string))
+    });
+    EngineInfo {
+        name: ENGINE_NAME.to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        variants: to_strings(&VARIANT_NAMES),
+        rule_options: vec![
+            RuleOption {
+                name: "stalemateRule".to_string(),
+                values: to_strings(&STALEMATE_RULE_NAMES),
+            },
+            RuleOption {
+                name: "bareKing".to_string(),
+                values: to_strings(&["false", "true"]),
+            },
+            RuleOption {
+                name: "captureAll".to_string(),
+                values: std::iter::once("none".to_string())
+                    .chain(piece_names)
+                    .collect(),
+            },
+        ],
+        tablebases: TablebaseAvailability {
+            embedded: embedded_tablebases()
+                .iter()
+                .map(|(name, _)| name.to_string())
+                .collect(),
+            loaded: get_loaded_tablebases(),
+        },
+        features: build_features(),
+        options: list_options(),
+    }
+}
+
+impl EngineInfo {
+    /// The engine's introduction in the engine protocol, in the manner of
+    /// UCI: `id name`, `id version`, `id variants`, `id rules`,
+    /// `id tablebases`, `id features` and one `option` line per option.
+    pub fn protocol_lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!("id name {}", self.name),
+            format!("id version {}", self.version),
+            format!("id variants {}", self.variants.join(" ")),
+        ];
+        for rule in &self.rule_options {
+            lines.push(format!("id rule {} {}", rule.name, rule.values.join(" ")));
+        }
+        let mut tablebases = self.tablebases.embedded.clone();
+        for name in &self.tablebases.loaded {
+            if !tablebases.contains(name) {
+                tablebases.push(name.clone());
+            }
+        }
+        lines.push(
+            format!("id tablebases {}", tablebases.join(" "))
+                .trim_end()
+                .to_string(),
+        );
+        lines.push(
+            format!("id features {}", self.features.join(" "))
+                .trim_end()
+                .to_string(),
+        );
+        for option in &self.options {
+            lines.push(match option.kind {
+                OptionKind::Spin => format!(
+                    "option name {} type spin default {} min {} max {}",
+                    option.name, option.default, option.min, option.max
+                ),
+                OptionKind::Check => format!(
+                    "option name {} type check default {}",
+                    option.name,
+                    option.default != 0
+                ),
+            });
+        }
+        lines
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{create_new_game, make_move};
+    use crate::types::PieceType;
+
+    #[test]
+    fn test_engine_continues_game() {
+        let mut engine = Engine::with_tt_size(100_000);
+        let start = create_new_game();
+        let first = engine.analyze(&start, 3);
+        assert!(!first.continued && !first.reused);
+        assert!(first.result.best_move.is_some());
+
+        // The same position again is answered from the last search
+        let again = engine.analyze(&start, 2);
+        assert!(again.reused);
+        assert_eq!(again.result.score, first.result.score);
+
+        // The position after the best move is found in the kept tree
+        let mv = first.result.best_move.unwrap();
+        let next = make_move(&start, mv.from, mv.to).unwrap();
+        let second = engine.analyze(&next, 3);
+        assert!(second.continued && !second.reused);
+        assert!(second.result.stats.tt_hits > 0);
+
+        // Another game's position is not a continuation
+        engine.new_game();
+        let mut other = start.clone();
+        let queen = other
+            .board
+            .iter()
+            .find(|(_, piece)| piece.piece_type == PieceType::Queen)
+            .map(|(key, _)| key.clone())
+            .unwrap();
+        other.board.remove(&queen);
+        other.position_history.clear();
+        let unrelated = engine.analyze(&other, 2);
+        assert!(!unrelated.continued);
+        assert!(engine.tt_size() > 0);
+
+        engine.clear();
+        assert_eq!(engine.tt_size(), 0);
+
+        // A resized engine starts over but keeps its cancel token
+        engine.analyze(&start, 2);
+        let cancel = engine.cancel_token();
+        engine.resize(1000);
+        assert_eq!(engine.tt_size(), 0);
+        assert!(!engine.analyze(&start, 2).reused);
+        cancel.cancel();
+        assert!(engine.cancel_token().is_cancelled());
+    }
+
+    #[test]
+    fn test_engine_info() {
+        let info = engine_info();
+        assert_eq!(info.name, ENGINE_NAME);
+        assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+        assert!(info
+            .variants
+            .iter()
+            .all(|name| crate::types::Variant::from_name(name).is_some()));
+        let stalemate = &info.rule_options[0];
+        assert!(stalemate
+            .values
+            .iter()
+            .all(|name| crate::types::StalemateRule::from_name(name).is_some()));
+        assert!(info.rule_options[2].values.contains(&"Knight".to_string()));
+        assert_eq!(
+            info.features.contains(&"gamedb".to_string()),
+            cfg!(feature = "gamedb")
+        );
+
+        let lines = info.protocol_lines();
+        assert_eq!(lines[0], "id name Underchex");
+        assert!(lines.contains(&"id rule bareKing false true".to_string()));
+        assert!(lines.contains(&"option name quiescence type check default true".to_string()));
+        assert!(lines
+            .iter()
+            .any(|line| line.starts_with("option name ttSize type spin default 50000")));
+        assert_eq!(
+            lines
+                .iter()
+                .filter(|line| line.starts_with("option "))
+                .count(),
+            info.options.len()
+        );
+    }
+}
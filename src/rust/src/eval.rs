@@ -0,0 +1,167 @@
+//! Underchex Evaluation Self-Test
+//!
+//! Sanity checks for the evaluation function over random quiet positions:
+//! - Color flip: swapping the colors and turning the board half a turn (so
+//!   each side's pieces face the same way) must negate the score
+//! - Symmetry: rotating or reflecting the board by a symmetry that preserves
+//!   every piece's movement must leave the score unchanged
+//!
+//! Run it after changing the evaluation, or from the `eval` example
+//! (`cargo run --example eval -- self-test`).
+
+use serde::{Deserialize, Serialize};
+
+use crate::ai::evaluate_position;
+use crate::notation::board_to_hexfen;
+use crate::positions::{generate_random_position, PositionConstraints};
+use crate::rng::SeededRng;
+use crate::tablebase::{apply_symmetry, symmetry_preserves_piece, HALF_TURN, SYMMETRY_COUNT};
+use crate::types::{BoardState, Color, HexCoord, Piece};
+
+// ============================================================================
+// Configuration
+// ============================================================================
+
+/// Positions checked by [`self_test`].
+pub const DEFAULT_SELF_TEST_POSITIONS: usize = 200;
+
+// ============================================================================
+// Report
+// ============================================================================
+
+/// A position whose evaluation broke an invariant.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EvalFailure {
+    /// HexFEN of the original position
+    pub fen: String,
+    /// "colorFlip", or "symmetry N" for a board symmetry (see [`apply_symmetry`])
+    pub transform: String,
+    pub expected: i32,
+    pub actual: i32,
+}
+
+/// Result of an evaluation self-test.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EvalSelfTestReport {
+    pub positions: usize,
+    /// Invariants checked (one color flip per position plus each symmetry)
+    pub checks: usize,
+    pub failures: Vec<EvalFailure>,
+}
+
+impl EvalSelfTestReport {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+// ============================================================================
+// Self-Test
+// ============================================================================
+
+/// Check the evaluation invariants over the default number of positions.
+pub fn self_test() -> EvalSelfTestReport {
+    self_test_with(DEFAULT_SELF_TEST_POSITIONS, 1)
+}
+
+/// Check the evaluation invariants over `positions` random positions.
+pub fn self_test_with(positions: usize, seed: u64) -> EvalSelfTestReport {
+    let mut rng = SeededRng::new(seed);
+    let mut report = EvalSelfTestReport {
+        positions,
+        ..Default::default()
+    };
+
+    for _ in 0..positions {
+        let constraints = PositionConstraints::default();
+        let Some(game) = generate_random_position(rng.next_u64(), &constraints) else {
+            continue;
+        };
+        let board = game.board;
+        let score = evaluate_position(&board);
+        let mut check = |transform: String, expected: i32, transformed: &BoardState| {
+            report.checks += 1;
+            let actual = evaluate_position(transformed);
+            if actual != expected {
+                report.failures.push(EvalFailure {
+                    fen: board_to_hexfen(&board, Color::White),
+                    transform,
+                    expected,
+                    actual,
+                });
+            }
+        };
+
+        check("colorFlip".to_string(), -score, &flip_colors(&board));
+        for symmetry in 1..SYMMETRY_COUNT {
+            if board
+                .values()
+                .all(|piece| symmetry_preserves_piece(symmetry, piece))
+            {
+                let transformed = transform_board(&board, symmetry);
+                check(format!("symmetry {}", symmetry), score, &transformed);
+            }
+        }
+    }
+
+    report
+}
+
+/// Swap the colors and turn the board half a turn.
+pub fn flip_colors(board: &BoardState) -> BoardState {
+    board
+        .iter()
+        .filter_map(|(key, piece)| {
+            let cell = apply_symmetry(HALF_TURN, HexCoord::from_key(key)?);
+            let color = piece.color.opposite();
+            Some((cell.to_key(), Piece { color, ..*piece }))
+        })
+        .collect()
+}
+
+/// Apply a board symmetry to every piece.
+pub fn transform_board(board: &BoardState, symmetry: u8) -> BoardState {
+    board
+        .iter()
+        .filter_map(|(key, piece)| {
+            let cell = apply_symmetry(symmetry, HexCoord::from_key(key)?);
+            Some((cell.to_key(), *piece))
+        })
+        .collect()
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::PieceType;
+
+    #[test]
+    fn test_self_test_passes() {
+        let report = self_test_with(40, 7);
+        assert_eq!(report.positions, 40);
+        assert!(report.checks > 40);
+        assert!(report.passed(), "{:?}", report.failures);
+    }
+
+    #[test]
+    fn test_transforms() {
+        let mut board = BoardState::new();
+        board.insert(
+            HexCoord::new(1, 2).to_key(),
+            Piece::new(PieceType::Pawn, Color::White),
+        );
+        let flipped = flip_colors(&board);
+        assert_eq!(
+            flipped.get(&HexCoord::new(-1, -2).to_key()),
+            Some(&Piece::new(PieceType::Pawn, Color::Black))
+        );
+        assert_eq!(flip_colors(&flipped), board);
+        assert_eq!(transform_board(&board, 0), board);
+        // Pawns only keep their movement under the identity
+        assert!((1..SYMMETRY_COUNT).all(|s| !symmetry_preserves_piece(s, &board["1,2"])));
+    }
+}
@@ -0,0 +1,349 @@
+//! Underchex Opening Explorer
+//!
+//! Aggregates a corpus of games (imported or self-play) into per-position
+//! statistics: how often each move was played, the results it led to, and the
+//! average rating of the players who reached the position.
+//!
+//! Positions are keyed by `get_position_key` (board hash plus side to move), so
+//! transpositions share statistics.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::game::{create_new_game, get_position_key, GameResult};
+use crate::moves::apply_move;
+use crate::types::{BoardState, Color, GameStatus, HexCoord, Move, PieceType};
+
+// ============================================================================
+// Openings
+// ============================================================================
+
+/// Number of plies used to derive an opening classification.
+pub const OPENING_SIGNATURE_PLIES: usize = 4;
+
+/// Classify an opening by its first `OPENING_SIGNATURE_PLIES` moves,
+/// e.g. "0,2-0,1 0,-2-0,-1".
+pub fn classify_opening(moves: &[Move]) -> String {
+    moves
+        .iter()
+        .take(OPENING_SIGNATURE_PLIES)
+        .map(|mv| format!("{}-{}", mv.from.to_key(), mv.to.to_key()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// ============================================================================
+// Explorer Types
+// ============================================================================
+
+/// A game to feed into the explorer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExplorerGame {
+    /// Moves played from the standard starting position
+    pub moves: Vec<Move>,
+    /// Final status of the game (ongoing games count as unfinished)
+    pub status: GameStatus,
+    #[serde(default)]
+    pub white_rating: Option<u32>,
+    #[serde(default)]
+    pub black_rating: Option<u32>,
+}
+
+/// Result totals for a position or move, from white's perspective.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResultCounts {
+    pub white_wins: u32,
+    pub black_wins: u32,
+    pub draws: u32,
+    pub unfinished: u32,
+}
+
+impl ResultCounts {
+    fn record(&mut self, status: &GameStatus) {
+        match get_game_winner(status) {
+            Some(Some(Color::White)) => self.white_wins += 1,
+            Some(Some(Color::Black)) => self.black_wins += 1,
+            Some(None) => self.draws += 1,
+            None => self.unfinished += 1,
+        }
+    }
+
+    /// Total number of games counted.
+    pub fn total(&self) -> u32 {
+        self.white_wins + self.black_wins + self.draws + self.unfinished
+    }
+}
+
+/// Statistics for one move played from a position.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExplorerMoveStats {
+    pub from: HexCoord,
+    pub to: HexCoord,
+    pub promotion: Option<PieceType>,
+    pub results: ResultCounts,
+    /// Average rating of the player who made the move (None if unrated)
+    pub average_rating: Option<f64>,
+    #[serde(skip)]
+    rating_sum: u64,
+    #[serde(skip)]
+    rated_count: u32,
+}
+
+/// Aggregated statistics for a single position.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExplorerEntry {
+    /// Number of games reaching this position
+    pub games: u32,
+    pub results: ResultCounts,
+    /// Average rating of both players over rated games reaching this position
+    pub average_rating: Option<f64>,
+    /// Moves played from this position, most frequent first
+    pub moves: Vec<ExplorerMoveStats>,
+    #[serde(skip)]
+    rating_sum: u64,
+    #[serde(skip)]
+    rated_count: u32,
+}
+
+fn average(sum: u64, count: u32) -> Option<f64> {
+    (count > 0).then(|| sum as f64 / count as f64)
+}
+
+/// Winner of a finished game: `Some(Some(color))` for a win, `Some(None)` for a
+/// draw, `None` if the game is still ongoing.
+pub fn get_game_winner(status: &GameStatus) -> Option<Option<Color>> {
+    GameResult::from_status(status).map(|result| result.winner)
+}
+
+// ============================================================================
+// Explorer
+// ============================================================================
+
+/// Opening explorer built from a game corpus.
+#[derive(Debug, Clone, Default)]
+pub struct Explorer {
+    entries: HashMap<String, ExplorerEntry>,
+    games_count: usize,
+    max_ply: Option<usize>,
+}
+
+impl Explorer {
+    /// Create an empty explorer that indexes every ply of each game.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an empty explorer that only indexes the first `max_ply` plies.
+    pub fn with_max_ply(max_ply: usize) -> Self {
+        Self {
+            max_ply: Some(max_ply),
+            ..Self::default()
+        }
+    }
+
+    /// Add a game to the explorer by replaying it from the starting position.
+    pub fn add_game(&mut self, game: &ExplorerGame) {
+        let mut board: BoardState = create_new_game().board;
+        let mut turn = Color::White;
+        let plies = self.max_ply.unwrap_or(usize::MAX);
+        let both_rated = game.white_rating.zip(game.black_rating);
+
+        for (ply, mv) in game.moves.iter().enumerate() {
+            if ply >= plies {
+                break;
+            }
+            let mover_rating = match turn {
+                Color::White => game.white_rating,
+                Color::Black => game.black_rating,
+            };
+
+            let entry = self
+                .entries
+                .entry(get_position_key(&board, turn))
+                .or_default();
+            entry.games += 1;
+            entry.results.record(&game.status);
+            if let Some((white, black)) = both_rated {
+                entry.rating_sum += (white + black) as u64;
+                entry.rated_count += 2;
+                entry.average_rating = average(entry.rating_sum, entry.rated_count);
+            }
+
+            let index = match entry
+                .moves
+                .iter()
+                .position(|m| m.from == mv.from && m.to == mv.to && m.promotion == mv.promotion)
+            {
+                Some(index) => index,
+                None => {
+                    entry.moves.push(ExplorerMoveStats {
+                        from: mv.from,
+                        to: mv.to,
+                        promotion: mv.promotion,
+                        results: ResultCounts::default(),
+                        average_rating: None,
+                        rating_sum: 0,
+                        rated_count: 0,
+                    });
+                    entry.moves.len() - 1
+                }
+            };
+            let Some(move_stats) = entry.moves.get_mut(index) else {
+                continue;
+            };
+            move_stats.results.record(&game.status);
+            if let Some(rating) = mover_rating {
+                move_stats.rating_sum += rating as u64;
+                move_stats.rated_count += 1;
+                move_stats.average_rating = average(move_stats.rating_sum, move_stats.rated_count);
+            }
+            entry
+                .moves
+                .sort_by_key(|m| std::cmp::Reverse(m.results.total()));
+
+            board = apply_move(&board, mv);
+            turn = turn.opposite();
+        }
+
+        self.games_count += 1;
+    }
+
+    /// Query statistics by position key (see `get_position_key`).
+    pub fn query_key(&self, key: &str) -> Option<&ExplorerEntry> {
+        self.entries.get(key)
+    }
+
+    /// Query statistics for a position.
+    pub fn query(&self, board: &BoardState, side_to_move: Color) -> Option<&ExplorerEntry> {
+        self.query_key(&get_position_key(board, side_to_move))
+    }
+
+    /// Number of games added.
+    pub fn games_count(&self) -> usize {
+        self.games_count
+    }
+
+    /// Number of distinct positions indexed.
+    pub fn positions_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Remove all games.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.games_count = 0;
+    }
+}
+
+// ============================================================================
+// Global Explorer Storage
+// ============================================================================
+
+lazy_static::lazy_static! {
+    static ref EXPLORER: std::sync::Mutex<Explorer> = std::sync::Mutex::new(Explorer::new());
+}
+
+/// Add games to the global explorer. Returns the number of games added.
+pub fn add_games_to_explorer(games: &[ExplorerGame]) -> usize {
+    match EXPLORER.lock() {
+        Ok(mut explorer) => {
+            for game in games {
+                explorer.add_game(game);
+            }
+            games.len()
+        }
+        Err(_) => 0,
+    }
+}
+
+/// Import games (JSON array of `ExplorerGame`) into the global explorer.
+/// Returns the number of games added, or None if the JSON is invalid.
+pub fn import_explorer_games_from_json(json: &str) -> Option<usize> {
+    let games: Vec<ExplorerGame> = serde_json::from_str(json).ok()?;
+    Some(add_games_to_explorer(&games))
+}
+
+/// Query the global explorer for a position.
+pub fn query_explorer(board: &BoardState, side_to_move: Color) -> Option<ExplorerEntry> {
+    EXPLORER.lock().ok()?.query(board, side_to_move).cloned()
+}
+
+/// Clear the global explorer.
+pub fn clear_explorer() {
+    if let Ok(mut explorer) = EXPLORER.lock() {
+        explorer.clear();
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::make_move;
+
+    fn play_game(moves: &[[i32; 4]], status: GameStatus) -> ExplorerGame {
+        let mut state = create_new_game();
+        for &[fq, fr, tq, tr] in moves {
+            state = make_move(&state, HexCoord::new(fq, fr), HexCoord::new(tq, tr)).unwrap();
+        }
+        ExplorerGame {
+            moves: state.history,
+            status,
+            white_rating: Some(1500),
+            black_rating: Some(1700),
+        }
+    }
+
+    #[test]
+    fn test_explorer_aggregates_moves_and_results() {
+        let mut explorer = Explorer::new();
+        let white_win = GameStatus::Checkmate {
+            winner: Color::White,
+        };
+        explorer.add_game(&play_game(
+            &[[0, 2, 0, 1], [0, -2, 0, -1]],
+            white_win.clone(),
+        ));
+        explorer.add_game(&play_game(
+            &[[0, 2, 0, 1], [1, -2, 1, -1]],
+            GameStatus::Stalemate,
+        ));
+        explorer.add_game(&play_game(&[[1, 2, 1, 1]], white_win));
+
+        let start = create_new_game();
+        let entry = explorer.query(&start.board, Color::White).unwrap();
+        assert_eq!(entry.games, 3);
+        assert_eq!(entry.results.white_wins, 2);
+        assert_eq!(entry.results.draws, 1);
+        assert_eq!(entry.average_rating, Some(1600.0));
+
+        // Most frequent move first
+        assert_eq!(entry.moves[0].to, HexCoord::new(0, 1));
+        assert_eq!(entry.moves[0].results.total(), 2);
+        assert_eq!(entry.moves[0].average_rating, Some(1500.0));
+
+        let after = make_move(&start, HexCoord::new(0, 2), HexCoord::new(0, 1)).unwrap();
+        let reply = explorer.query(&after.board, Color::Black).unwrap();
+        assert_eq!(reply.games, 2);
+        assert_eq!(reply.moves.len(), 2);
+        assert_eq!(reply.moves[0].average_rating, Some(1700.0));
+    }
+
+    #[test]
+    fn test_explorer_max_ply() {
+        let mut explorer = Explorer::with_max_ply(1);
+        explorer.add_game(&play_game(
+            &[[0, 2, 0, 1], [0, -2, 0, -1]],
+            GameStatus::Ongoing,
+        ));
+
+        assert_eq!(explorer.positions_count(), 1);
+        assert_eq!(explorer.games_count(), 1);
+        let start = create_new_game();
+        let entry = explorer.query(&start.board, Color::White).unwrap();
+        assert_eq!(entry.results.unfinished, 1);
+    }
+}
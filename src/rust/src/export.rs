@@ -0,0 +1,293 @@
+//! Underchex History Export
+//!
+//! A game's moves as a table, one row per half-move, for users who analyze
+//! their games in spreadsheets and other external tools. Rows hold the move
+//! number, the move in short algebraic notation (see
+//! [`move_to_san`]), its cells by name, whether it captured or gave check,
+//! and, when known, the mover's clock and the evaluation after the move.
+//! Each row is tagged with the game phase it was played in (see
+//! [`game_phase`]), and moves that took the game into a new phase are
+//! flagged, so timelines split where the engine's phase model does.
+//! Tables are written as CSV with a header row, or as a JSON array.
+
+use serde::{Deserialize, Serialize};
+
+use crate::board::cell_name;
+use crate::clock::GameClock;
+use crate::game::reconstruct_positions;
+use crate::messages::color_key;
+use crate::moves::is_in_check;
+use crate::notation::move_to_san;
+use crate::stats::game_phase;
+use crate::types::{Color, GameState, HexCoord};
+
+// ============================================================================
+// History Records
+// ============================================================================
+
+/// A game with what is known about its moves beyond the moves themselves.
+#[derive(Debug, Clone)]
+pub struct HistoryRecord<'a> {
+    pub game: &'a GameState,
+    /// Clock of a timed game, for the time left after each move
+    pub clock: Option<&'a GameClock>,
+    /// Evaluation after each move, in centipawns from White's side, where
+    /// annotated
+    pub evals: Vec<Option<i32>>,
+}
+
+impl<'a> HistoryRecord<'a> {
+    pub fn new(game: &'a GameState) -> Self {
+        Self {
+            game,
+            clock: None,
+            evals: Vec::new(),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: &'a GameClock) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    pub fn with_evals(mut self, evals: Vec<Option<i32>>) -> Self {
+        self.evals = evals;
+        self
+    }
+}
+
+// ============================================================================
+// History Tables
+// ============================================================================
+
+/// Format of an exported table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TableFormat {
+    Csv,
+    Json,
+}
+
+impl TableFormat {
+    /// Parse "csv" or "json", in any case.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "csv" => Some(TableFormat::Csv),
+            "json" => Some(TableFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// One half-move of an exported history.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryRow {
+    /// Index of the move in the game (0 is the first move)
+    pub ply: usize,
+    pub move_number: u32,
+    /// "white" or "black"
+    pub color: String,
+    pub san: String,
+    pub from: String,
+    pub to: String,
+    pub capture: bool,
+    pub check: bool,
+    /// Time the mover had left after the move, if the game was timed
+    pub clock_ms: Option<u64>,
+    /// Evaluation after the move, in centipawns from White's side
+    pub eval: Option<i32>,
+    /// Phase the move was played in: "opening", "middlegame" or "endgame"
+    pub phase: String,
+    /// Whether the position after the move is in a different phase
+    pub phase_change: bool,
+}
+
+/// Columns of a CSV export, in order.
+pub const HISTORY_COLUMNS: [&str; 12] = [
+    "ply",
+    "moveNumber",
+    "color",
+    "san",
+    "from",
+    "to",
+    "capture",
+    "check",
+    "clockMs",
+    "eval",
+    "phase",
+    "phaseChange",
+];
+
+/// The rows of a game's history.
+pub fn history_rows(record: &HistoryRecord) -> Vec<HistoryRow> {
+    let game = record.game;
+    let cell = |coord: HexCoord| cell_name(coord).unwrap_or_else(|| coord.to_key());
+    let positions = reconstruct_positions(game);
+    // Move numbers count back from the game's current one
+    let black_moves = game
+        .history
+        .iter()
+        .filter(|mv| mv.piece.color == Color::Black)
+        .count() as u32;
+    let mut move_number = game.move_number.saturating_sub(black_moves).max(1);
+
+    game.history
+        .iter()
+        .zip(positions.iter().zip(positions.iter().skip(1)))
+        .enumerate()
+        .map(|(ply, (mv, (before, after)))| {
+            let phase = game_phase(before, ply);
+            let row = HistoryRow {
+                ply,
+                move_number,
+                color: color_key(mv.piece.color).to_string(),
+                san: move_to_san(before, mv),
+                from: if mv.is_drop {
+                    String::new()
+                } else {
+                    cell(mv.from)
+                },
+                to: cell(mv.to),
+                capture: mv.captured.is_some(),
+                check: is_in_check(after, mv.piece.color.opposite()),
+                clock_ms: record.clock.and_then(|clock| {
+                    clock
+                        .records
+                        .iter()
+                        .find(|r| r.move_index == ply)
+                        .map(|r| r.remaining_ms)
+                }),
+                eval: record.evals.get(ply).copied().flatten(),
+                phase: phase.key().to_string(),
+                phase_change: game_phase(after, ply + 1) != phase,
+            };
+            if mv.piece.color == Color::Black {
+                move_number += 1;
+            }
+            row
+        })
+        .collect()
+}
+
+/// Export a game's history as a table: CSV with a header row of
+/// [`HISTORY_COLUMNS`] (unknown clocks and evaluations left empty), or a
+/// JSON array of [`HistoryRow`]s.
+pub fn export_history_table(record: &HistoryRecord, format: TableFormat) -> String {
+    let rows = history_rows(record);
+    match format {
+        TableFormat::Json => serde_json::to_string(&rows).unwrap_or_else(|_| "[]".to_string()),
+        TableFormat::Csv => {
+            let optional = |value: Option<String>| value.unwrap_or_default();
+            let mut csv = HISTORY_COLUMNS.join(",");
+            csv.push('\n');
+            for row in rows {
+                let fields = [
+                    row.ply.to_string(),
+                    row.move_number.to_string(),
+                    row.color,
+                    row.san,
+                    row.from,
+                    row.to,
+                    row.capture.to_string(),
+                    row.check.to_string(),
+                    optional(row.clock_ms.map(|ms| ms.to_string())),
+                    optional(row.eval.map(|eval| eval.to_string())),
+                    row.phase,
+                    row.phase_change.to_string(),
+                ];
+                csv.push_str(&fields.join(","));
+                csv.push('\n');
+            }
+            csv
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ClockSettings;
+    use crate::game::{create_new_game, make_move};
+    use crate::test_support::{play, CHARIOT_BLUNDER};
+
+    #[test]
+    fn test_export_csv() {
+        let game = play(&CHARIOT_BLUNDER);
+        let record = HistoryRecord::new(&game).with_evals(vec![Some(10), None, Some(-400)]);
+        let csv = export_history_table(&record, TableFormat::Csv);
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], HISTORY_COLUMNS.join(","));
+        let first: Vec<&str> = lines[1].split(',').collect();
+        assert_eq!(first.len(), HISTORY_COLUMNS.len());
+        assert_eq!(&first[..3], &["0", "1", "white"]);
+        assert_eq!(first[9], "10");
+        assert_eq!(first[8], "");
+        let last: Vec<&str> = lines[4].split(',').collect();
+        assert_eq!(&last[..3], &["3", "2", "black"]);
+        assert!(last[3].contains('x'));
+        assert_eq!(last[6], "true");
+        assert_eq!(last[9], "");
+    }
+
+    #[test]
+    fn test_export_json_with_clock() {
+        let mut game = play(&[[0, 2, 0, 1]]);
+        let mut clock = GameClock::new(ClockSettings::new(60_000, 0));
+        clock.start(Color::White, 0);
+        clock.press(1_500, None, 0);
+        game.move_number = 7;
+        let record = HistoryRecord::new(&game).with_clock(&clock);
+        let rows: Vec<HistoryRow> =
+            serde_json::from_str(&export_history_table(&record, TableFormat::Json)).unwrap();
+
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].move_number, 7);
+        assert_eq!(rows[0].from, cell_name(HexCoord::new(0, 2)).unwrap());
+        assert_eq!(rows[0].clock_ms, Some(58_500));
+        assert!(!rows[0].capture && !rows[0].check);
+        assert_eq!(TableFormat::parse("CSV"), Some(TableFormat::Csv));
+        assert_eq!(TableFormat::parse("xlsx"), None);
+    }
+
+    #[test]
+    fn test_export_phases() {
+        use crate::moves::generate_all_legal_moves;
+        use crate::stats::OPENING_PLIES;
+        use crate::types::Move;
+
+        // Quiet moves past the opening; the full army stays on the board
+        let mut game = create_new_game();
+        for ply in 0..OPENING_PLIES + 2 {
+            let quiet: Vec<Move> = generate_all_legal_moves(&game.board, game.turn)
+                .into_iter()
+                .filter(|mv| mv.captured.is_none())
+                .collect();
+            let mv = &quiet[ply * 7 % quiet.len()];
+            game = make_move(&game, mv.from, mv.to).unwrap();
+        }
+        let rows = history_rows(&HistoryRecord::new(&game));
+
+        assert!(rows[..OPENING_PLIES]
+            .iter()
+            .all(|row| row.phase == "opening"));
+        assert!(rows[OPENING_PLIES..]
+            .iter()
+            .all(|row| row.phase == "middlegame"));
+        let changes: Vec<usize> = rows
+            .iter()
+            .filter(|row| row.phase_change)
+            .map(|row| row.ply)
+            .collect();
+        assert_eq!(changes, vec![OPENING_PLIES - 1]);
+        let csv = export_history_table(&HistoryRecord::new(&game), TableFormat::Csv);
+        let boundary: Vec<&str> = csv.lines().nth(OPENING_PLIES).unwrap().split(',').collect();
+        assert_eq!(&boundary[10..], &["opening", "true"]);
+    }
+}
@@ -2,10 +2,24 @@
 //!
 //! Signed-by: agent #21 claude-sonnet-4 via opencode 20260122T06:31:01
 
-use crate::moves::{apply_move, generate_all_legal_moves, is_in_check, validate_move};
+use serde::{Deserialize, Serialize};
+
+use crate::ai::TranspositionTable;
+use crate::messages::{color_key, move_message, Message};
+use crate::moves::{
+    apply_move, is_in_check, place_boulder, validate_drop, validate_move, MoveValidation,
+};
+use crate::pieces::{demoted_type, mirror_army, ArmyPlacement};
+use crate::session::replay_move;
 use crate::types::{
-    BoardState, Color, GameState, GameStatus, HexCoord, LanceVariant, Move, Piece, PieceType,
+    BoardState, Color, GameState, GameStatus, Hands, HexCoord, LanceVariant, Move, Piece,
+    PieceType, PositionRecord, StalemateRule, TurnPhase, Variant, WinConditions,
 };
+use crate::variants::{
+    can_place_boulder, generate_boulder_placements, generate_variant_legal_moves, is_move_allowed,
+    variant_outcome, win_condition_outcome, VariantOutcome,
+};
+use crate::zobrist::{boards_equal, hash_hands, hash_position};
 
 // ============================================================================
 // Initial Setup
@@ -18,6 +32,7 @@ struct PiecePlacement {
 }
 
 /// Standard starting position for Underchex.
+#[allow(clippy::vec_init_then_push)]
 fn get_starting_position() -> Vec<PiecePlacement> {
     let mut pieces = Vec::new();
 
@@ -133,11 +148,88 @@ fn create_board_from_placements(placements: &[PiecePlacement]) -> BoardState {
     board
 }
 
+/// Racing kings starting position: both armies share the back rows, white on
+/// the low-q side and black mirrored across the board's r-axis, with no pawns.
+fn get_racing_kings_position() -> Vec<PiecePlacement> {
+    let white = [
+        (
+            Piece::new(PieceType::King, Color::White),
+            HexCoord::new(-4, 3),
+        ),
+        (
+            Piece::new(PieceType::Queen, Color::White),
+            HexCoord::new(-4, 4),
+        ),
+        (
+            Piece::new(PieceType::Chariot, Color::White),
+            HexCoord::new(-3, 3),
+        ),
+        (
+            Piece::lance(Color::White, LanceVariant::A),
+            HexCoord::new(-2, 3),
+        ),
+        (
+            Piece::new(PieceType::Knight, Color::White),
+            HexCoord::new(-3, 4),
+        ),
+    ];
+
+    white
+        .into_iter()
+        .flat_map(|(piece, position)| {
+            // Mirror (q, r) -> (-q - r, r); lance variants swap under the reflection
+            let variant = piece.variant.map(|v| match v {
+                LanceVariant::A => LanceVariant::B,
+                LanceVariant::B => LanceVariant::A,
+            });
+            let mirrored = Piece {
+                color: Color::Black,
+                variant,
+                ..piece
+            };
+            [
+                PiecePlacement { piece, position },
+                PiecePlacement {
+                    piece: mirrored,
+                    position: HexCoord::new(-position.q - position.r, position.r),
+                },
+            ]
+        })
+        .collect()
+}
+
+/// Cannons starting position: the standard setup with knights replaced by
+/// cannons.
+fn get_cannons_position() -> Vec<PiecePlacement> {
+    get_starting_position()
+        .into_iter()
+        .map(|placement| match placement.piece.piece_type {
+            PieceType::Knight => PiecePlacement {
+                piece: Piece::new(PieceType::Cannon, placement.piece.color),
+                ..placement
+            },
+            _ => placement,
+        })
+        .collect()
+}
+
 /// Create a new game with standard starting position.
 pub fn create_new_game() -> GameState {
-    let placements = get_starting_position();
+    create_variant_game(Variant::Standard)
+}
+
+/// Create a new game under a variant's rules, using the variant's starting
+/// position (the standard one unless the variant defines its own).
+pub fn create_variant_game(variant: Variant) -> GameState {
+    let placements = match variant {
+        Variant::RacingKings => get_racing_kings_position(),
+        Variant::Cannons => get_cannons_position(),
+        _ => get_starting_position(),
+    };
     let board = create_board_from_placements(&placements);
 
+    let position_history = vec![create_position_record(&board, Color::White)];
+
     GameState {
         board,
         turn: Color::White,
@@ -145,16 +237,325 @@ pub fn create_new_game() -> GameState {
         half_move_clock: 0,
         history: Vec::new(),
         status: GameStatus::Ongoing,
+        position_history,
+        variant,
+        hands: Hands::default(),
+        phase: TurnPhase::Move,
+        stalemate_rule: StalemateRule::default(),
+        win_conditions: WinConditions::default(),
+    }
+}
+
+/// Create a game with a custom army: `white_army` is placed for white and
+/// mirrored through the center for black. Returns None if the army is not a
+/// valid setup (see [`mirror_army`]).
+pub fn create_army_game(white_army: &[ArmyPlacement]) -> Option<GameState> {
+    let mut board = BoardState::new();
+    for (piece, position) in mirror_army(white_army)? {
+        board.insert(position.to_key(), piece);
+    }
+    Some(create_game_from_position(board, Color::White))
+}
+
+/// Create a game starting from an arbitrary position.
+pub fn create_game_from_position(board: BoardState, turn: Color) -> GameState {
+    let variant = Variant::Standard;
+    let hands = Hands::default();
+    let stalemate_rule = StalemateRule::default();
+    let win_conditions = WinConditions::default();
+    let status = determine_status(
+        &board,
+        turn,
+        variant,
+        &hands,
+        stalemate_rule,
+        win_conditions,
+    );
+    let position_history = vec![create_position_record(&board, turn)];
+
+    GameState {
+        board,
+        turn,
+        move_number: 1,
+        half_move_clock: 0,
+        history: Vec::new(),
+        status,
+        position_history,
+        variant,
+        hands,
+        phase: TurnPhase::Move,
+        stalemate_rule,
+        win_conditions,
+    }
+}
+
+/// The game under another stalemate rule. A game that ended in stalemate is
+/// scored again under the new rule.
+pub fn with_stalemate_rule(state: &GameState, rule: StalemateRule) -> GameState {
+    let mut state = GameState {
+        stalemate_rule: rule,
+        ..state.clone()
+    };
+    let stalemated = match &state.status {
+        GameStatus::Stalemate => true,
+        GameStatus::VariantWin { reason, .. } => reason == STALEMATE_WIN,
+        _ => false,
+    };
+    if stalemated {
+        state.status = determine_status(
+            &state.board,
+            state.turn,
+            state.variant,
+            &state.hands,
+            rule,
+            state.win_conditions,
+        );
+    }
+    state
+}
+
+/// The game under other optional win conditions. An ongoing game the new
+/// conditions decide ends, and a game the old ones decided is judged again.
+pub fn with_win_conditions(state: &GameState, conditions: WinConditions) -> GameState {
+    let decided_by_old = win_condition_outcome(&state.board, state.win_conditions).is_some();
+    let mut state = GameState {
+        win_conditions: conditions,
+        ..state.clone()
+    };
+    if state.phase == TurnPhase::Move && (state.status == GameStatus::Ongoing || decided_by_old) {
+        state.status = match determine_status(
+            &state.board,
+            state.turn,
+            state.variant,
+            &state.hands,
+            state.stalemate_rule,
+            conditions,
+        ) {
+            GameStatus::Ongoing => {
+                determine_repetition_status(&state.position_history).unwrap_or(GameStatus::Ongoing)
+            }
+            status => status,
+        };
     }
+    state
+}
+
+// ============================================================================
+// Repetition Detection
+// ============================================================================
+
+/// Number of occurrences of a position that ends the game in a draw.
+pub const REPETITION_DRAW_COUNT: usize = 3;
+
+/// Get the repetition key for a position (board plus side to move).
+pub fn get_position_key(board: &BoardState, side_to_move: Color) -> String {
+    let side = match side_to_move {
+        Color::White => "w",
+        Color::Black => "b",
+    };
+    format!("{}-{}", TranspositionTable::generate_hash(board), side)
+}
+
+/// Build the repetition record for a position.
+pub fn create_position_record(board: &BoardState, side_to_move: Color) -> PositionRecord {
+    PositionRecord {
+        key: get_position_key(board, side_to_move),
+        hash: hash_position(board, side_to_move),
+        side_to_move,
+        in_check: is_in_check(board, side_to_move),
+    }
+}
+
+/// Get a key for the pieces in hand, appended to position keys in drop variants
+/// since the same board with different hands is a different position.
+pub fn get_hands_key(hands: &Hands) -> String {
+    [Color::White, Color::Black]
+        .iter()
+        .map(|&color| {
+            let mut held: Vec<(u8, u8)> = hands
+                .get(color)
+                .iter()
+                .filter(|(_, &count)| count > 0)
+                .map(|(&piece_type, &count)| (piece_type as u8, count))
+                .collect();
+            held.sort_unstable();
+            held.iter()
+                .map(|(piece_type, count)| format!("{}x{}", piece_type, count))
+                .collect::<Vec<_>>()
+                .join(".")
+        })
+        .collect::<Vec<_>>()
+        .join("|")
+}
+
+/// Count how many times a position occurs in a history.
+pub fn count_repetitions(history: &[PositionRecord], position: &PositionRecord) -> usize {
+    history.iter().filter(|r| r.same_position(position)).count()
+}
+
+/// Detect a perpetual check: `current` repeats an earlier position in `history`
+/// and, since the first occurrence, one side was in check every time it was to move.
+/// Returns the color delivering the perpetual check.
+pub fn find_perpetual_check(history: &[PositionRecord], current: &PositionRecord) -> Option<Color> {
+    let first = history.iter().position(|r| r.same_position(current))?;
+    perpetual_checker(history.get(first..)?.iter().chain(std::iter::once(current)))
+}
+
+/// The color checking the other every time it was to move over a cycle of
+/// positions, if any.
+fn perpetual_checker<'r>(cycle: impl Iterator<Item = &'r PositionRecord> + Clone) -> Option<Color> {
+    [Color::White, Color::Black]
+        .into_iter()
+        .find_map(|checked| {
+            let mut positions = cycle
+                .clone()
+                .filter(|r| r.side_to_move == checked)
+                .peekable();
+            let always_in_check = positions.peek().is_some() && positions.all(|r| r.in_check);
+            always_in_check.then(|| checked.opposite())
+        })
+}
+
+/// The positions leading to a search node: the game's history, borrowed
+/// rather than copied, then the search's own line, pushed and popped as it
+/// goes. The game, the search and the AI's tablebase moves all count
+/// repetitions with the same records.
+#[derive(Debug, Clone)]
+pub struct RepetitionStack<'a> {
+    game: &'a [PositionRecord],
+    line: Vec<PositionRecord>,
+}
+
+impl<'a> RepetitionStack<'a> {
+    pub fn new(game: &'a [PositionRecord]) -> Self {
+        Self {
+            game,
+            line: Vec::new(),
+        }
+    }
+
+    /// A stack for searching from `root`, which is added unless the game's
+    /// history already ends with it.
+    pub fn from_root(game: &'a [PositionRecord], root: PositionRecord) -> Self {
+        let mut stack = Self::new(game);
+        if game
+            .last()
+            .is_none_or(|record| !record.same_position(&root))
+        {
+            stack.push(root);
+        }
+        stack
+    }
+
+    pub fn push(&mut self, record: PositionRecord) {
+        self.line.push(record);
+    }
+
+    pub fn pop(&mut self) -> Option<PositionRecord> {
+        self.line.pop()
+    }
+
+    /// Game positions followed by the search line.
+    pub fn records(&self) -> impl Iterator<Item = &PositionRecord> + Clone {
+        self.game.iter().chain(&self.line)
+    }
+
+    /// How many times a position occurred, in the game or the line.
+    pub fn occurrences(&self, position: &PositionRecord) -> usize {
+        self.records().filter(|r| r.same_position(position)).count()
+    }
+
+    /// [`find_perpetual_check`] over the game and the line.
+    pub fn perpetual_check(&self, current: &PositionRecord) -> Option<Color> {
+        let first = self.records().position(|r| r.same_position(current))?;
+        perpetual_checker(self.records().skip(first).chain(std::iter::once(current)))
+    }
+}
+
+/// How many times the current position of a game has occurred, itself
+/// included.
+pub fn position_occurrences(state: &GameState) -> usize {
+    state.position_history.last().map_or(0, |current| {
+        count_repetitions(&state.position_history, current)
+    })
+}
+
+/// Determine whether the last position of a history ends the game by repetition.
+pub(crate) fn determine_repetition_status(history: &[PositionRecord]) -> Option<GameStatus> {
+    let (current, earlier) = history.split_last()?;
+    if count_repetitions(history, current) < REPETITION_DRAW_COUNT {
+        return None;
+    }
+
+    let reason = if find_perpetual_check(earlier, current).is_some() {
+        "perpetualCheck"
+    } else {
+        "threefoldRepetition"
+    };
+    Some(GameStatus::Draw {
+        reason: reason.to_string(),
+    })
+}
+
+// ============================================================================
+// Draw Claims
+// ============================================================================
+
+/// Plies without a pawn move or capture after which either player may claim
+/// a draw (the fifty-move rule).
+pub const FIFTY_MOVE_PLIES: u32 = 100;
+
+/// The draw the side to move may claim, if any ("fiftyMoveRule").
+pub fn claimable_draw(state: &GameState) -> Option<&'static str> {
+    (state.status == GameStatus::Ongoing && state.half_move_clock >= FIFTY_MOVE_PLIES)
+        .then_some("fiftyMoveRule")
+}
+
+/// Claim a draw. Returns None if no draw is claimable.
+pub fn claim_draw(state: &GameState) -> Option<GameState> {
+    let reason = claimable_draw(state)?;
+    let mut new_state = state.clone();
+    new_state.status = GameStatus::Draw {
+        reason: reason.to_string(),
+    };
+    Some(new_state)
 }
 
 // ============================================================================
 // Game State Updates
 // ============================================================================
 
+/// Reason of a game decided by a stalemate under a rule that scores it as a
+/// win or a loss.
+pub const STALEMATE_WIN: &str = "stalemateWin";
+
 /// Determine game status after a move.
-fn determine_status(board: &BoardState, next_turn: Color) -> GameStatus {
-    let legal_moves = generate_all_legal_moves(board, next_turn);
+fn determine_status(
+    board: &BoardState,
+    next_turn: Color,
+    variant: Variant,
+    hands: &Hands,
+    stalemate_rule: StalemateRule,
+    win_conditions: WinConditions,
+) -> GameStatus {
+    let outcome = variant_outcome(board, variant, next_turn)
+        .or_else(|| win_condition_outcome(board, win_conditions));
+    match outcome {
+        Some(VariantOutcome::Win(winner, reason)) => {
+            return GameStatus::VariantWin {
+                winner,
+                reason: reason.to_string(),
+            }
+        }
+        Some(VariantOutcome::Draw(reason)) => {
+            return GameStatus::Draw {
+                reason: reason.to_string(),
+            }
+        }
+        None => {}
+    }
+
+    let legal_moves = generate_variant_legal_moves(board, next_turn, variant, hands);
 
     if legal_moves.is_empty() {
         if is_in_check(board, next_turn) {
@@ -162,7 +563,15 @@ fn determine_status(board: &BoardState, next_turn: Color) -> GameStatus {
                 winner: next_turn.opposite(),
             }
         } else {
-            GameStatus::Stalemate
+            let winner = match stalemate_rule.stalemater_quarters() {
+                4 => next_turn.opposite(),
+                0 => next_turn,
+                _ => return GameStatus::Stalemate,
+            };
+            GameStatus::VariantWin {
+                winner,
+                reason: STALEMATE_WIN.to_string(),
+            }
         }
     } else {
         GameStatus::Ongoing
@@ -172,16 +581,58 @@ fn determine_status(board: &BoardState, next_turn: Color) -> GameStatus {
 /// Make a move and return the new game state.
 /// Returns None if the move is invalid.
 pub fn make_move(state: &GameState, from: HexCoord, to: HexCoord) -> Option<GameState> {
+    make_move_with_promotion(state, from, to, None)
+}
+
+/// Make a move, optionally promoting the moving piece (e.g. a knight entering
+/// the far zone in the shogi variant), and return the new game state.
+/// Returns None if the move or the promotion is invalid.
+pub fn make_move_with_promotion(
+    state: &GameState,
+    from: HexCoord,
+    to: HexCoord,
+    promotion: Option<PieceType>,
+) -> Option<GameState> {
+    try_make_move(state, from, to, promotion).ok()
+}
+
+/// Like [`make_move_with_promotion`], but says why a move is rejected:
+/// "gameOver", "boulderPending", a [`validate_move`] reason,
+/// "forbiddenByVariant" or "invalidPromotion".
+pub fn try_make_move(
+    state: &GameState,
+    from: HexCoord,
+    to: HexCoord,
+    promotion: Option<PieceType>,
+) -> Result<GameState, String> {
+    check_move_for_game(state, from, to, promotion).map(|mv| advance_game(state, mv))
+}
+
+/// The checks of [`try_make_move`], returning the move if it may be played.
+fn check_move_for_game(
+    state: &GameState,
+    from: HexCoord,
+    to: HexCoord,
+    promotion: Option<PieceType>,
+) -> Result<Move, String> {
     if state.status != GameStatus::Ongoing {
-        return None; // Game is over
+        return Err("gameOver".to_string());
+    }
+    if state.phase != TurnPhase::Move {
+        return Err("boulderPending".to_string()); // Boulder must be placed first
     }
 
     let validation = validate_move(&state.board, from, to, state.turn);
     if !validation.legal {
-        return None;
+        return Err(validation
+            .reason
+            .unwrap_or_else(|| "illegalMove".to_string()));
     }
 
-    let piece = *state.board.get(&from.to_key())?;
+    let piece = *state
+        .board
+        .get(&from.to_key())
+        .ok_or_else(|| "noPieceAtSource".to_string())?;
     let captured = state.board.get(&to.to_key()).copied();
 
     let mv = Move {
@@ -189,15 +640,162 @@ pub fn make_move(state: &GameState, from: HexCoord, to: HexCoord) -> Option<Game
         from,
         to,
         captured,
-        promotion: None, // TODO: Handle promotion selection
+        promotion,
+        is_drop: false,
+        boulder: None,
     };
+    if !is_move_allowed(&state.board, &mv, state.variant) {
+        return Err("forbiddenByVariant".to_string());
+    }
+    if promotion.is_some()
+        && !get_legal_moves(state)
+            .iter()
+            .any(|m| m.from == from && m.to == to && m.promotion == promotion)
+    {
+        return Err("invalidPromotion".to_string());
+    }
+
+    Ok(mv)
+}
+
+/// Validate a batch of moves (from, to, promotion) against one position
+/// without playing them, with the same rules and reasons as
+/// [`try_make_move`].
+pub fn validate_many(
+    state: &GameState,
+    moves: &[(HexCoord, HexCoord, Option<PieceType>)],
+) -> Vec<MoveValidation> {
+    moves
+        .iter()
+        .map(
+            |&(from, to, promotion)| match check_move_for_game(state, from, to, promotion) {
+                Ok(mv) => MoveValidation::legal(mv.captured.is_some()),
+                Err(reason) => {
+                    MoveValidation::rejected(move_message(&reason, &state.board, from, to))
+                }
+            },
+        )
+        .collect()
+}
 
+/// Drop a piece from the current player's hand onto an empty cell.
+/// Returns None if the game has no drops or the drop is invalid.
+pub fn make_drop(state: &GameState, piece: Piece, to: HexCoord) -> Option<GameState> {
+    if state.status != GameStatus::Ongoing || !state.variant.has_drops() {
+        return None;
+    }
+    if state.phase != TurnPhase::Move {
+        return None;
+    }
+    if piece.color != state.turn {
+        return None;
+    }
+
+    let validation = validate_drop(
+        &state.board,
+        &state.hands,
+        piece.piece_type,
+        to,
+        state.turn,
+        state.variant.allows_drop_mate(),
+    );
+    if !validation.legal {
+        return None;
+    }
+
+    Some(advance_game(state, Move::new_drop(piece, to)))
+}
+
+/// Place the boulder after a move in the boulder variant, ending the turn.
+/// The boulder may go on any empty cell; it cannot stay where it stood.
+/// Returns None if no placement is pending or the cell is not available.
+pub fn make_boulder_placement(state: &GameState, to: HexCoord) -> Option<GameState> {
+    if state.status != GameStatus::Ongoing || state.phase != TurnPhase::PlaceBoulder {
+        return None;
+    }
+    if !can_place_boulder(&state.board, to) {
+        return None;
+    }
+
+    let mut board = state.board.clone();
+    place_boulder(&mut board, to);
+
+    let mut history = state.history.clone();
+    if let Some(last) = history.last_mut() {
+        last.boulder = Some(to);
+    }
+
+    Some(finish_turn(state, board, state.hands.clone(), history))
+}
+
+/// Apply a validated move and compute the resulting game state.
+fn advance_game(state: &GameState, mv: Move) -> GameState {
     let new_board = apply_move(&state.board, &mv);
+
+    let mut hands = state.hands.clone();
+    if state.variant.has_drops() {
+        if mv.is_drop {
+            hands.remove(state.turn, mv.piece.piece_type);
+        }
+        if let Some(captured) = mv.captured {
+            hands.add(state.turn, demoted_type(captured.piece_type));
+        }
+    }
+
+    let mut history = state.history.clone();
+    history.push(mv);
+
+    if state.variant == Variant::Boulder {
+        // The turn continues until the boulder is placed
+        return GameState {
+            board: new_board,
+            history,
+            hands,
+            phase: TurnPhase::PlaceBoulder,
+            ..state.clone()
+        };
+    }
+
+    finish_turn(state, new_board, hands, history)
+}
+
+/// End the current player's turn after its move (the last entry of `history`):
+/// record the position and update status and clocks.
+fn finish_turn(
+    state: &GameState,
+    new_board: BoardState,
+    hands: Hands,
+    history: Vec<Move>,
+) -> GameState {
     let next_turn = state.turn.opposite();
-    let status = determine_status(&new_board, next_turn);
+
+    let mut record = create_position_record(&new_board, next_turn);
+    if state.variant.has_drops() {
+        record.key = format!("{}-{}", record.key, get_hands_key(&hands));
+        record.hash ^= hash_hands(&hands);
+    }
+    let mut position_history = state.position_history.clone();
+    position_history.push(record);
+
+    let status = match determine_status(
+        &new_board,
+        next_turn,
+        state.variant,
+        &hands,
+        state.stalemate_rule,
+        state.win_conditions,
+    ) {
+        GameStatus::Ongoing => {
+            determine_repetition_status(&position_history).unwrap_or(GameStatus::Ongoing)
+        }
+        status => status,
+    };
 
     // Update half-move clock (reset on pawn move or capture)
-    let half_move_clock = if piece.piece_type == PieceType::Pawn || captured.is_some() {
+    let resets_clock = history
+        .last()
+        .is_some_and(|mv| mv.piece.piece_type == PieceType::Pawn || mv.captured.is_some());
+    let half_move_clock = if resets_clock {
         0
     } else {
         state.half_move_clock + 1
@@ -210,17 +808,20 @@ pub fn make_move(state: &GameState, from: HexCoord, to: HexCoord) -> Option<Game
         state.move_number
     };
 
-    let mut history = state.history.clone();
-    history.push(mv);
-
-    Some(GameState {
+    GameState {
         board: new_board,
         turn: next_turn,
         move_number,
         half_move_clock,
         history,
         status,
-    })
+        position_history,
+        variant: state.variant,
+        hands,
+        phase: TurnPhase::Move,
+        stalemate_rule: state.stalemate_rule,
+        win_conditions: state.win_conditions,
+    }
 }
 
 /// Resign the game.
@@ -233,6 +834,141 @@ pub fn resign(state: &GameState, color: Color) -> GameState {
     }
 }
 
+// ============================================================================
+// Game Results
+// ============================================================================
+
+/// How a finished game was decided.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    /// A draw rule (repetition, fifty moves, perpetual check, ...)
+    DrawRule,
+    Resignation,
+    Timeout,
+    /// A variant-specific win or draw
+    Variant,
+    /// Decided by the move limit of a simulated game
+    Adjudication,
+}
+
+/// Result of a finished game.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameResult {
+    /// None for a draw; the side with the larger share of a fractional
+    /// stalemate
+    pub winner: Option<Color>,
+    pub termination: Termination,
+    /// Specific reason, e.g. "checkmate", "threefoldRepetition" or
+    /// "kingOfTheHill"
+    pub reason: String,
+    /// "1-0", "0-1" or "1/2-1/2" (or quarter points, e.g. "3/4-1/4", for a
+    /// fractional stalemate)
+    pub score: String,
+    /// The reason with the winner, for display
+    #[serde(default)]
+    pub message: Message,
+}
+
+impl GameResult {
+    /// Result of a game status, or None while the game is ongoing.
+    pub fn from_status(status: &GameStatus) -> Option<Self> {
+        let (winner, termination, reason) = match status {
+            GameStatus::Ongoing => return None,
+            GameStatus::Checkmate { winner } => {
+                (Some(*winner), Termination::Checkmate, "checkmate")
+            }
+            GameStatus::Stalemate => (None, Termination::Stalemate, "stalemate"),
+            GameStatus::Draw { reason } => {
+                let termination = match reason.as_str() {
+                    "moveLimit" => Termination::Adjudication,
+                    "threefoldRepetition" | "fiftyMoveRule" | "perpetualCheck" => {
+                        Termination::DrawRule
+                    }
+                    _ => Termination::Variant,
+                };
+                (None, termination, reason.as_str())
+            }
+            GameStatus::Resigned { winner } => {
+                (Some(*winner), Termination::Resignation, "resignation")
+            }
+            GameStatus::Timeout { winner } => (Some(*winner), Termination::Timeout, "timeout"),
+            GameStatus::VariantWin { winner, reason } if reason == STALEMATE_WIN => {
+                (Some(*winner), Termination::Stalemate, STALEMATE_WIN)
+            }
+            GameStatus::VariantWin { winner, reason } => {
+                (Some(*winner), Termination::Variant, reason.as_str())
+            }
+        };
+        let score = match winner {
+            Some(Color::White) => "1-0",
+            Some(Color::Black) => "0-1",
+            None => "1/2-1/2",
+        };
+        let message = match winner {
+            Some(winner) => Message::new(reason).with("winner", color_key(winner)),
+            None => Message::new(reason),
+        };
+        Some(GameResult {
+            winner,
+            termination,
+            reason: reason.to_string(),
+            score: score.to_string(),
+            message,
+        })
+    }
+}
+
+/// A score in quarter points, e.g. "3/4".
+fn quarter_points(quarters: u8) -> String {
+    match quarters {
+        0 => "0".to_string(),
+        2 => "1/2".to_string(),
+        4 => "1".to_string(),
+        _ => format!("{}/4", quarters),
+    }
+}
+
+/// Result of a game, or None while it is ongoing. A stalemate under a
+/// fractional rule goes to the side with the larger share, with the shares
+/// as the score.
+pub fn game_result(state: &GameState) -> Option<GameResult> {
+    let mut result = GameResult::from_status(&state.status)?;
+    let quarters = state.stalemate_rule.stalemater_quarters();
+    if state.status == GameStatus::Stalemate && quarters != 2 {
+        let stalemater = state.turn.opposite();
+        let winner = if quarters > 2 { stalemater } else { state.turn };
+        let white = match stalemater {
+            Color::White => quarters,
+            Color::Black => 4 - quarters,
+        };
+        result.winner = Some(winner);
+        result.score = format!("{}-{}", quarter_points(white), quarter_points(4 - white));
+        result.message = Message::new("stalemateShare")
+            .with("winner", color_key(winner))
+            .with("points", quarter_points(quarters.max(4 - quarters)));
+    }
+    Some(result)
+}
+
+/// Result of an armageddon game, or None while it is ongoing: as
+/// [`game_result`], except that a draw is a win for `draw_winner`.
+pub fn armageddon_result(state: &GameState, draw_winner: Color) -> Option<GameResult> {
+    let mut result = game_result(state)?;
+    if result.winner.is_none() {
+        result.winner = Some(draw_winner);
+        result.score = match draw_winner {
+            Color::White => "1-0",
+            Color::Black => "0-1",
+        }
+        .to_string();
+        result.message = Message::new("armageddonDraw").with("winner", color_key(draw_winner));
+    }
+    Some(result)
+}
+
 // ============================================================================
 // Game Queries
 // ============================================================================
@@ -244,10 +980,46 @@ pub fn is_player_turn(state: &GameState, color: Color) -> bool {
 
 /// Get all legal moves for the current player.
 pub fn get_legal_moves(state: &GameState) -> Vec<Move> {
-    if state.status != GameStatus::Ongoing {
+    if state.status != GameStatus::Ongoing || state.phase != TurnPhase::Move {
         return Vec::new();
     }
-    generate_all_legal_moves(&state.board, state.turn)
+    generate_variant_legal_moves(&state.board, state.turn, state.variant, &state.hands)
+}
+
+/// Semantic events of the last move, so frontends can pick sounds or haptics
+/// without re-deriving game logic. Underchex has no castling, so there is no
+/// castle event.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MoveEvents {
+    pub capture: bool,
+    pub check: bool,
+    pub promotion: bool,
+    pub drop: bool,
+    /// The boulder was placed (boulder variant)
+    pub boulder: bool,
+    /// The game ended with this move
+    pub game_end: bool,
+}
+
+/// Events of the last move played in a game, or None before the first move.
+pub fn get_last_move_events(state: &GameState) -> Option<MoveEvents> {
+    let mv = state.history.last()?;
+    Some(MoveEvents {
+        capture: mv.captured.is_some(),
+        check: is_in_check(&state.board, mv.piece.color.opposite()),
+        promotion: mv.promotion.is_some(),
+        drop: mv.is_drop,
+        boulder: mv.boulder.is_some(),
+        game_end: state.status != GameStatus::Ongoing,
+    })
+}
+
+/// Get the cells the boulder can be placed on, if a placement is pending.
+pub fn get_boulder_placements(state: &GameState) -> Vec<HexCoord> {
+    if state.status != GameStatus::Ongoing || state.phase != TurnPhase::PlaceBoulder {
+        return Vec::new();
+    }
+    generate_boulder_placements(&state.board)
 }
 
 /// Check if the current player is in check.
@@ -255,6 +1027,182 @@ pub fn is_current_player_in_check(state: &GameState) -> bool {
     is_in_check(&state.board, state.turn)
 }
 
+/// Reconstruct the board before each move of a game, plus the final board,
+/// by unwinding the move history from the current position.
+/// Works for games started from any position.
+pub fn reconstruct_positions(state: &GameState) -> Vec<BoardState> {
+    let mut positions = vec![state.board.clone()];
+    let mut board = state.board.clone();
+
+    for (index, mv) in state.history.iter().enumerate().rev() {
+        if let Some(boulder) = mv.boulder {
+            // Put the boulder back where the previous placement left it
+            board.remove(&boulder.to_key());
+            let previous = index.checked_sub(1).and_then(|i| state.history.get(i));
+            if let Some(previous) = previous.and_then(|mv| mv.boulder) {
+                board.insert(previous.to_key(), Piece::boulder());
+            }
+        }
+        board.remove(&mv.to.to_key());
+        if !mv.is_drop {
+            board.insert(mv.from.to_key(), mv.piece);
+        }
+        if let Some(captured) = mv.captured {
+            board.insert(mv.to.to_key(), captured);
+        }
+        positions.push(board.clone());
+    }
+
+    positions.reverse();
+    positions
+}
+
+// ============================================================================
+// Invariants
+// ============================================================================
+
+/// Status key for invariant messages: the result reason, or "ongoing".
+fn status_key(status: &GameStatus) -> String {
+    GameResult::from_status(status).map_or_else(|| "ongoing".to_string(), |result| result.reason)
+}
+
+impl GameState {
+    /// The game before its first recorded move, found by unwinding the
+    /// history from the current position.
+    pub(crate) fn unwound_start(&self) -> GameState {
+        let board = reconstruct_positions(self).swap_remove(0);
+        let mut hands = self.hands.clone();
+        if self.variant.has_drops() {
+            for mv in self.history.iter().rev() {
+                if let Some(captured) = mv.captured {
+                    hands.remove(mv.piece.color, demoted_type(captured.piece_type));
+                }
+                if mv.is_drop {
+                    hands.add(mv.piece.color, mv.piece.piece_type);
+                }
+            }
+        }
+        let turn = self.history.first().map_or(self.turn, |mv| mv.piece.color);
+        GameState {
+            position_history: vec![create_position_record(&board, turn)],
+            board,
+            turn,
+            move_number: 1,
+            half_move_clock: 0,
+            history: Vec::new(),
+            status: GameStatus::Ongoing,
+            variant: self.variant,
+            hands,
+            phase: TurnPhase::Move,
+            stalemate_rule: self.stalemate_rule,
+            win_conditions: self.win_conditions,
+        }
+    }
+
+    /// Check the state for internal contradictions, for hunting desyncs
+    /// between frontends, servers and the engine. Returns one message per
+    /// violation, empty for a sound state:
+    /// - each side has exactly one king, and the side not to move is not in
+    ///   check;
+    /// - the history replays legally to the current board, side to move and
+    ///   hands, through the recorded positions;
+    /// - the move number and half-move clock are no lower than the replayed
+    ///   history implies;
+    /// - a status of ongoing, checkmate or stalemate agrees with the legal
+    ///   moves of the side to move.
+    pub fn check_invariants(&self) -> Vec<Message> {
+        let mut violations = Vec::new();
+
+        for color in [Color::White, Color::Black] {
+            let kings = self
+                .board
+                .values()
+                .filter(|piece| piece.color == color && piece.piece_type == PieceType::King)
+                .count();
+            match kings {
+                0 => violations.push(Message::new("missingKing").with("color", color_key(color))),
+                1 => {}
+                _ => violations.push(Message::new("extraKing").with("color", color_key(color))),
+            }
+        }
+        let waiting = self.turn.opposite();
+        if self.phase == TurnPhase::Move && is_in_check(&self.board, waiting) {
+            violations.push(Message::new("kingInCheckOffTurn").with("color", color_key(waiting)));
+        }
+
+        let mut replayed = Some(self.unwound_start());
+        for (ply, mv) in self.history.iter().enumerate() {
+            replayed = replayed.and_then(|state| replay_move(&state, mv));
+            if replayed.is_none() {
+                violations.push(Message::new("illegalHistoryMove").with("ply", ply + 1));
+                break;
+            }
+        }
+        if let Some(replayed) = replayed {
+            if !boards_equal(&replayed.board, &self.board)
+                || replayed.turn != self.turn
+                || replayed.hands != self.hands
+                || replayed.phase != self.phase
+                || !self
+                    .position_history
+                    .iter()
+                    .rev()
+                    .zip(replayed.position_history.iter().rev())
+                    .all(|(recorded, replayed)| recorded.same_position(replayed))
+            {
+                violations.push(Message::new("historyMismatch"));
+            }
+            if self.move_number < replayed.move_number {
+                violations
+                    .push(Message::new("moveNumberTooLow").with("expected", replayed.move_number));
+            }
+            // A pawn move or capture in the history fixes the clock exactly
+            let resets = self
+                .history
+                .iter()
+                .any(|mv| mv.piece.piece_type == PieceType::Pawn || mv.captured.is_some());
+            let clock_wrong = if resets {
+                self.half_move_clock != replayed.half_move_clock
+            } else {
+                self.half_move_clock < replayed.half_move_clock
+            };
+            if clock_wrong {
+                violations.push(
+                    Message::new("halfMoveClockMismatch")
+                        .with("expected", replayed.half_move_clock),
+                );
+            }
+        }
+
+        if self.phase == TurnPhase::Move {
+            let derived = determine_status(
+                &self.board,
+                self.turn,
+                self.variant,
+                &self.hands,
+                self.stalemate_rule,
+                self.win_conditions,
+            );
+            let consistent = match &self.status {
+                GameStatus::Ongoing | GameStatus::Checkmate { .. } | GameStatus::Stalemate => {
+                    derived == self.status
+                }
+                // Draw rules, resignation and time end games with moves left
+                _ => true,
+            };
+            if !consistent {
+                violations.push(
+                    Message::new("statusMismatch")
+                        .with("status", status_key(&self.status))
+                        .with("expected", status_key(&derived)),
+                );
+            }
+        }
+
+        violations
+    }
+}
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -262,6 +1210,8 @@ pub fn is_current_player_in_check(state: &GameState) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::board::is_valid_cell;
+    use crate::test_support::play_from;
 
     #[test]
     fn test_create_new_game() {
@@ -317,8 +1267,8 @@ mod tests {
         assert_eq!(new_game.history.len(), 1);
 
         // Pawn should be at new position
-        assert!(new_game.board.get("0,1").is_some());
-        assert!(new_game.board.get("0,2").is_none());
+        assert!(new_game.board.contains_key("0,1"));
+        assert!(!new_game.board.contains_key("0,2"));
     }
 
     #[test]
@@ -346,10 +1296,615 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_stalemate_rules() {
+        // The queen takes the black king's last moves away
+        let mut board = BoardState::new();
+        board.insert("0,4".to_string(), Piece::new(PieceType::King, Color::White));
+        board.insert(
+            "0,-4".to_string(),
+            Piece::new(PieceType::King, Color::Black),
+        );
+        board.insert(
+            "-4,1".to_string(),
+            Piece::new(PieceType::Queen, Color::White),
+        );
+        let game = create_game_from_position(board, Color::White);
+        let (from, to) = (HexCoord::new(-4, 1), HexCoord::new(-1, -2));
+        let stalemate = make_move(&game, from, to).unwrap();
+        assert_eq!(stalemate.status, GameStatus::Stalemate);
+        assert_eq!(game_result(&stalemate).unwrap().score, "1/2-1/2");
+
+        let win = make_move(
+            &with_stalemate_rule(&game, StalemateRule::StalematerWins),
+            from,
+            to,
+        )
+        .unwrap();
+        assert_eq!(
+            win.status,
+            GameStatus::VariantWin {
+                winner: Color::White,
+                reason: STALEMATE_WIN.to_string()
+            }
+        );
+        let result = game_result(&win).unwrap();
+        assert_eq!(
+            (result.termination, result.score.as_str()),
+            (Termination::Stalemate, "1-0")
+        );
+        assert!(win.check_invariants().is_empty());
+
+        let loss = with_stalemate_rule(&stalemate, StalemateRule::StalematerLoses);
+        assert_eq!(game_result(&loss).unwrap().winner, Some(Color::Black));
+        let share = with_stalemate_rule(
+            &loss,
+            StalemateRule::Fractional {
+                stalemater_quarters: 1,
+            },
+        );
+        assert_eq!(share.status, GameStatus::Stalemate);
+        let result = game_result(&share).unwrap();
+        assert_eq!(
+            (result.winner, result.score.as_str()),
+            (Some(Color::Black), "1/4-3/4")
+        );
+        assert_eq!(result.message.params["points"], "3/4");
+    }
+
+    #[test]
+    fn test_win_conditions() {
+        let mut board = BoardState::new();
+        board.insert("0,4".to_string(), Piece::new(PieceType::King, Color::White));
+        board.insert(
+            "0,-4".to_string(),
+            Piece::new(PieceType::King, Color::Black),
+        );
+        board.insert(
+            "-4,1".to_string(),
+            Piece::new(PieceType::Queen, Color::White),
+        );
+        board.insert(
+            "-2,-1".to_string(),
+            Piece::new(PieceType::Knight, Color::Black),
+        );
+        let game = create_game_from_position(board, Color::White);
+        let (from, to) = (HexCoord::new(-4, 1), HexCoord::new(-2, -1));
+        assert_eq!(
+            make_move(&game, from, to).unwrap().status,
+            GameStatus::Ongoing
+        );
+
+        let bare_king = WinConditions {
+            bare_king: true,
+            capture_all: None,
+        };
+        let won = make_move(&with_win_conditions(&game, bare_king), from, to).unwrap();
+        assert_eq!(
+            won.status,
+            GameStatus::VariantWin {
+                winner: Color::White,
+                reason: "bareKing".to_string()
+            }
+        );
+        assert!(won.check_invariants().is_empty());
+        let knights = WinConditions {
+            bare_king: false,
+            capture_all: Some(PieceType::Knight),
+        };
+        // Neither side has a knight left
+        let drawn = with_win_conditions(&won, knights);
+        assert_eq!(game_result(&drawn).unwrap().reason, "bothArmiesReduced");
+
+        // Lifting the conditions resumes the game
+        let resumed = with_win_conditions(&drawn, WinConditions::default());
+        assert_eq!(resumed.status, GameStatus::Ongoing);
+    }
+
+    #[test]
+    fn test_game_result() {
+        let game = create_new_game();
+        assert_eq!(game_result(&game), None);
+
+        let result = game_result(&resign(&game, Color::Black)).unwrap();
+        assert_eq!(result.winner, Some(Color::White));
+        assert_eq!(result.termination, Termination::Resignation);
+        assert_eq!(result.score, "1-0");
+
+        let result = |status| GameResult::from_status(&status).unwrap();
+        let draw = |reason: &str| GameStatus::Draw {
+            reason: reason.to_string(),
+        };
+        assert_eq!(
+            result(draw("fiftyMoveRule")).termination,
+            Termination::DrawRule
+        );
+        assert_eq!(
+            result(draw("moveLimit")).termination,
+            Termination::Adjudication
+        );
+        let repetition = result(draw("threefoldRepetition"));
+        assert_eq!(repetition.winner, None);
+        assert_eq!(repetition.reason, "threefoldRepetition");
+        assert_eq!(repetition.score, "1/2-1/2");
+        assert_eq!(result(GameStatus::Stalemate).score, "1/2-1/2");
+        let hill = result(GameStatus::VariantWin {
+            winner: Color::Black,
+            reason: "kingOfTheHill".to_string(),
+        });
+        assert_eq!(
+            (hill.termination, hill.reason.as_str(), hill.score.as_str()),
+            (Termination::Variant, "kingOfTheHill", "0-1")
+        );
+    }
+
+    #[test]
+    fn test_armageddon_result() {
+        let game = create_new_game();
+        assert_eq!(armageddon_result(&game, Color::Black), None);
+
+        let drawn = GameState {
+            status: GameStatus::Draw {
+                reason: "threefoldRepetition".to_string(),
+            },
+            ..game.clone()
+        };
+        let result = armageddon_result(&drawn, Color::Black).unwrap();
+        assert_eq!(result.winner, Some(Color::Black));
+        assert_eq!(result.score, "0-1");
+        assert_eq!(result.reason, "threefoldRepetition");
+        assert_eq!(result.message.key, "armageddonDraw");
+
+        // Decisive games are unaffected
+        let resigned = resign(&game, Color::Black);
+        assert_eq!(
+            armageddon_result(&resigned, Color::Black),
+            game_result(&resigned)
+        );
+    }
+
+    #[test]
+    fn test_threefold_repetition() {
+        let game = create_new_game();
+        // Knights shuffle back and forth
+        let shuffle = [
+            [-2, 3, -1, 1],
+            [2, -3, 1, -1],
+            [-1, 1, -2, 3],
+            [1, -1, 2, -3],
+        ];
+
+        let once = play_from(&game, &shuffle);
+        assert_eq!(once.status, GameStatus::Ongoing);
+        assert_eq!(
+            count_repetitions(&once.position_history, &once.position_history[0]),
+            2
+        );
+
+        assert_eq!(position_occurrences(&once), 2);
+        assert_eq!(position_occurrences(&game), 1);
+
+        let twice = play_from(&once, &shuffle);
+        assert_eq!(
+            twice.status,
+            GameStatus::Draw {
+                reason: "threefoldRepetition".to_string()
+            }
+        );
+        assert_eq!(position_occurrences(&twice), 3);
+    }
+
+    #[test]
+    fn test_fifty_move_claim() {
+        let mut game = create_new_game();
+        game.half_move_clock = FIFTY_MOVE_PLIES - 2;
+        // A pawn move resets the clock
+        assert_eq!(play_from(&game, &[[0, 2, 0, 1]]).half_move_clock, 0);
+
+        let knight_out = play_from(&game, &[[-2, 3, -1, 1]]);
+        assert_eq!(claimable_draw(&knight_out), None);
+        assert!(claim_draw(&knight_out).is_none());
+
+        let claimable = play_from(&knight_out, &[[2, -3, 1, -1]]);
+        assert_eq!(claimable.half_move_clock, FIFTY_MOVE_PLIES);
+        assert_eq!(claimable.status, GameStatus::Ongoing);
+        assert_eq!(
+            claim_draw(&claimable).unwrap().status,
+            GameStatus::Draw {
+                reason: "fiftyMoveRule".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_perpetual_check() {
+        // White queen checks the black king back and forth between two squares.
+        let mut board = BoardState::new();
+        board.insert("0,4".to_string(), Piece::new(PieceType::King, Color::White));
+        board.insert(
+            "1,-1".to_string(),
+            Piece::new(PieceType::Queen, Color::White),
+        );
+        board.insert(
+            "0,-4".to_string(),
+            Piece::new(PieceType::King, Color::Black),
+        );
+        let game = GameState {
+            position_history: vec![create_position_record(&board, Color::White)],
+            board,
+            ..create_new_game()
+        };
+        let cycle = [
+            [1, -1, 0, -1],
+            [0, -4, 1, -4],
+            [0, -1, 1, -1],
+            [1, -4, 0, -4],
+        ];
+
+        let once = play_from(&game, &cycle);
+        assert_eq!(once.status, GameStatus::Ongoing);
+        let (current, earlier) = once.position_history.split_last().unwrap();
+        assert_eq!(find_perpetual_check(earlier, current), Some(Color::White));
+
+        let twice = play_from(&once, &cycle);
+        assert_eq!(
+            twice.status,
+            GameStatus::Draw {
+                reason: "perpetualCheck".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_repetition_stack() {
+        let game = create_new_game();
+        let shuffle = [[-2, 3, -1, 1], [2, -3, 1, -1], [-1, 1, -2, 3]];
+        let played = play_from(&game, &shuffle[..2]);
+        let (current, _) = played.position_history.split_last().unwrap();
+
+        // The root is not added twice when the game already ends with it
+        let mut stack = RepetitionStack::from_root(&played.position_history, current.clone());
+        assert_eq!(stack.records().count(), played.position_history.len());
+        assert_eq!(stack.occurrences(current), 1);
+
+        // Positions of the search line count with the game's
+        let line = play_from(&played, &shuffle[2..]);
+        let start = &game.position_history[0];
+        assert_eq!(stack.occurrences(start), 1);
+        stack.push(line.position_history.last().unwrap().clone());
+        stack.push(start.clone());
+        assert_eq!(stack.occurrences(start), 2);
+        assert_eq!(stack.pop().as_ref(), Some(start));
+        assert_eq!(stack.occurrences(start), 1);
+        assert_eq!(stack.perpetual_check(start), None);
+    }
+
+    #[test]
+    fn test_reconstruct_positions() {
+        let game = create_new_game();
+        let played = play_from(
+            &game,
+            &[[0, 2, 0, 1], [0, -2, 0, -1], [0, 1, 0, 0], [0, -1, 0, 0]],
+        );
+
+        let positions = reconstruct_positions(&played);
+        assert_eq!(positions.len(), 5);
+        assert_eq!(positions[0], game.board);
+        assert_eq!(positions[4], played.board);
+        assert_eq!(positions[1], apply_move(&game.board, &played.history[0]));
+    }
+
+    #[test]
+    fn test_crazyhouse_captures_go_to_hand() {
+        let game = create_variant_game(Variant::Crazyhouse { drop_mate: true });
+        let played = play_from(
+            &game,
+            &[
+                [0, 2, 0, 1],
+                [0, -2, 0, -1],
+                [0, 1, 0, 0],
+                [0, -1, 0, 0],
+                [1, 2, 1, 1],
+            ],
+        );
+        assert_eq!(played.hands.black.get(&PieceType::Pawn), Some(&1));
+        assert!(get_legal_moves(&played).iter().any(|m| m.is_drop));
+
+        let pawn = Piece::new(PieceType::Pawn, Color::Black);
+        assert!(make_drop(&played, pawn, HexCoord::new(0, 4)).is_none()); // occupied
+        assert!(make_drop(&played, pawn, HexCoord::new(-3, 4)).is_none()); // promotion hex
+        let dropped = make_drop(&played, pawn, HexCoord::new(-3, 0)).unwrap();
+        assert!(dropped.hands.black.is_empty());
+        assert_eq!(dropped.board.get("-3,0"), Some(&pawn));
+
+        let positions = reconstruct_positions(&dropped);
+        assert_eq!(positions[positions.len() - 2], played.board);
+    }
+
+    #[test]
+    fn test_check_invariants() {
+        let moves = [[0, 2, 0, 1], [0, -2, 0, -1], [0, 1, 0, 0], [0, -1, 0, 0]];
+        let played = play_from(&create_new_game(), &moves);
+        assert!(played.check_invariants().is_empty());
+        let crazyhouse = play_from(
+            &play_from(
+                &create_variant_game(Variant::Crazyhouse { drop_mate: true }),
+                &moves,
+            ),
+            &[[1, 2, 1, 1]],
+        );
+        let pawn = Piece::new(PieceType::Pawn, Color::Black);
+        let dropped = make_drop(&crazyhouse, pawn, HexCoord::new(-3, 0)).unwrap();
+        assert!(dropped.check_invariants().is_empty());
+        let mut boulder = play_from(&create_variant_game(Variant::Boulder), &moves[..1]);
+        assert!(boulder.check_invariants().is_empty());
+        boulder = make_boulder_placement(&boulder, HexCoord::new(2, 0)).unwrap();
+        assert!(boulder.check_invariants().is_empty());
+
+        let keys = |state: &GameState| -> Vec<String> {
+            state
+                .check_invariants()
+                .into_iter()
+                .map(|message| message.key)
+                .collect()
+        };
+        let mut corrupt = played.clone();
+        corrupt.board.remove("0,-4");
+        assert_eq!(keys(&corrupt), vec!["missingKing", "historyMismatch"]);
+
+        let mut corrupt = played.clone();
+        let knight = corrupt.board.remove("-1,4").unwrap();
+        corrupt.board.insert("-1,2".to_string(), knight);
+        assert_eq!(keys(&corrupt), vec!["historyMismatch"]);
+
+        let mut corrupt = played.clone();
+        corrupt.move_number = 1;
+        corrupt.half_move_clock = 5;
+        corrupt.status = GameStatus::Stalemate;
+        assert_eq!(
+            keys(&corrupt),
+            vec![
+                "moveNumberTooLow",
+                "halfMoveClockMismatch",
+                "statusMismatch"
+            ]
+        );
+        // Resignation ends a game with moves left
+        assert!(resign(&played, Color::White).check_invariants().is_empty());
+    }
+
+    #[test]
+    fn test_standard_game_has_no_drops() {
+        let played = play_from(
+            &create_new_game(),
+            &[[0, 2, 0, 1], [0, -2, 0, -1], [0, 1, 0, 0], [0, -1, 0, 0]],
+        );
+        assert!(played.hands.black.is_empty());
+        let pawn = Piece::new(PieceType::Pawn, Color::White);
+        assert!(make_drop(&played, pawn, HexCoord::new(-3, 0)).is_none());
+    }
+
+    #[test]
+    fn test_king_of_the_hill() {
+        let mut board = BoardState::new();
+        board.insert("0,2".to_string(), Piece::new(PieceType::King, Color::White));
+        board.insert(
+            "0,-4".to_string(),
+            Piece::new(PieceType::King, Color::Black),
+        );
+        let game = GameState {
+            variant: Variant::KingOfTheHill,
+            ..create_game_from_position(board, Color::White)
+        };
+
+        let played = play_from(&game, &[[0, 2, 0, 1], [0, -4, 0, -3], [0, 1, 0, 0]]);
+        assert_eq!(
+            played.status,
+            GameStatus::VariantWin {
+                winner: Color::White,
+                reason: "kingOfTheHill".to_string()
+            }
+        );
+        assert!(get_legal_moves(&played).is_empty());
+
+        let json = serde_json::to_string(&played.status).unwrap();
+        assert_eq!(
+            json,
+            r#"{"VariantWin":{"winner":"White","reason":"kingOfTheHill"}}"#
+        );
+    }
+
+    #[test]
+    fn test_racing_kings_setup() {
+        let game = create_variant_game(Variant::RacingKings);
+        assert_eq!(game.board.len(), 10);
+        assert!(game
+            .board
+            .keys()
+            .all(|k| is_valid_cell(HexCoord::from_key(k).unwrap())));
+        assert!(!is_in_check(&game.board, Color::White));
+        assert!(!is_in_check(&game.board, Color::Black));
+        assert_eq!(
+            game.board.get("1,3"),
+            Some(&Piece::new(PieceType::King, Color::Black))
+        );
+        assert_eq!(
+            game.board.get("-1,3"),
+            Some(&Piece::lance(Color::Black, LanceVariant::B))
+        );
+        assert!(get_legal_moves(&game).iter().all(|mv| is_move_allowed(
+            &game.board,
+            mv,
+            Variant::RacingKings
+        )));
+    }
+
+    #[test]
+    fn test_cannons_setup() {
+        let game = create_variant_game(Variant::Cannons);
+        assert_eq!(game.board.len(), create_new_game().board.len());
+        assert_eq!(
+            game.board.get("-2,3"),
+            Some(&Piece::new(PieceType::Cannon, Color::White))
+        );
+        assert!(!game
+            .board
+            .values()
+            .any(|p| p.piece_type == PieceType::Knight));
+        assert_eq!(Variant::from_name("cannons"), Some(Variant::Cannons));
+    }
+
+    #[test]
+    fn test_shogi_promotion_and_demotion() {
+        let mut board = BoardState::new();
+        board.insert("0,4".to_string(), Piece::new(PieceType::King, Color::White));
+        board.insert(
+            "0,-4".to_string(),
+            Piece::new(PieceType::King, Color::Black),
+        );
+        board.insert(
+            "0,-1".to_string(),
+            Piece::new(PieceType::Knight, Color::White),
+        );
+        board.insert(
+            "2,-4".to_string(),
+            Piece::new(PieceType::Queen, Color::Black),
+        );
+        let game = GameState {
+            variant: Variant::Shogi { drops: true },
+            ..create_game_from_position(board, Color::White)
+        };
+
+        let (from, to) = (HexCoord::new(0, -1), HexCoord::new(1, -3));
+        assert!(make_move_with_promotion(&game, from, to, Some(PieceType::Queen)).is_none());
+        let promoted =
+            make_move_with_promotion(&game, from, to, Some(PieceType::PromotedKnight)).unwrap();
+        assert_eq!(
+            promoted.board.get("1,-3"),
+            Some(&Piece::new(PieceType::PromotedKnight, Color::White))
+        );
+
+        // The promoted knight is captured and goes to the hand as a knight
+        let captured = make_move(&promoted, HexCoord::new(2, -4), to).unwrap();
+        assert_eq!(captured.hands.black.get(&PieceType::Knight), Some(&1));
+        assert_eq!(captured.hands.black.get(&PieceType::PromotedKnight), None);
+
+        let positions = reconstruct_positions(&captured);
+        assert_eq!(positions[0], game.board);
+        assert_eq!(positions[1], promoted.board);
+
+        // Standard games reject the promotion
+        let standard = GameState {
+            variant: Variant::Standard,
+            ..game
+        };
+        assert!(
+            make_move_with_promotion(&standard, from, to, Some(PieceType::PromotedKnight))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_boulder_turns() {
+        let game = create_variant_game(Variant::Boulder);
+        let moved = make_move(&game, HexCoord::new(0, 2), HexCoord::new(0, 1)).unwrap();
+        assert_eq!(moved.phase, TurnPhase::PlaceBoulder);
+        assert_eq!(moved.turn, Color::White);
+        assert!(get_legal_moves(&moved).is_empty());
+        let occupied = moved
+            .board
+            .keys()
+            .filter(|k| is_valid_cell(HexCoord::from_key(k).unwrap()))
+            .count();
+        assert_eq!(get_boulder_placements(&moved).len(), 61 - occupied);
+        assert!(make_move(&moved, HexCoord::new(0, -2), HexCoord::new(0, -1)).is_none());
+
+        let placed = make_boulder_placement(&moved, HexCoord::new(0, 0)).unwrap();
+        assert_eq!(placed.turn, Color::Black);
+        assert_eq!(placed.history[0].boulder, Some(HexCoord::new(0, 0)));
+        assert!(make_move(&placed, HexCoord::new(0, -2), HexCoord::new(0, -1)).is_some());
+
+        let black_moved = make_move(&placed, HexCoord::new(1, -2), HexCoord::new(1, -1)).unwrap();
+        assert!(make_boulder_placement(&black_moved, HexCoord::new(0, 0)).is_none());
+        let second = make_boulder_placement(&black_moved, HexCoord::new(-1, 0)).unwrap();
+        assert!(!second.board.contains_key("0,0"));
+        assert_eq!(second.board.get("-1,0"), Some(&Piece::boulder()));
+
+        let positions = reconstruct_positions(&second);
+        assert_eq!(positions[0], game.board);
+        assert_eq!(positions[1], placed.board);
+        assert_eq!(positions[2], second.board);
+    }
+
+    #[test]
+    fn test_army_game() {
+        let army = [
+            ArmyPlacement::new(PieceType::King, HexCoord::new(0, 4)),
+            ArmyPlacement::new(PieceType::Wazir, HexCoord::new(-1, 4)),
+            ArmyPlacement::new(PieceType::Dabbaba, HexCoord::new(1, 3)),
+            ArmyPlacement::new(PieceType::Pawn, HexCoord::new(0, 2)),
+        ];
+        let game = create_army_game(&army).unwrap();
+        assert_eq!(game.board.len(), 8);
+        assert_eq!(
+            game.board.get("-1,-3"),
+            Some(&Piece::new(PieceType::Dabbaba, Color::Black))
+        );
+        assert_eq!(game.status, GameStatus::Ongoing);
+        assert!(get_legal_moves(&game)
+            .iter()
+            .any(|m| m.piece.piece_type == PieceType::Dabbaba && m.to == HexCoord::new(1, 1)));
+
+        assert!(create_army_game(&army[1..]).is_none());
+    }
+
+    #[test]
+    fn test_last_move_events() {
+        let game = create_new_game();
+        assert_eq!(get_last_move_events(&game), None);
+
+        let game = play_from(&game, &[[0, 2, 0, 1], [0, -2, 0, -1], [0, 1, 0, 0]]);
+        assert_eq!(get_last_move_events(&game), Some(MoveEvents::default()));
+        let captured = make_move(&game, HexCoord::new(0, -1), HexCoord::new(0, 0)).unwrap();
+        let events = get_last_move_events(&captured).unwrap();
+        assert!(events.capture && !events.check && !events.game_end);
+
+        let resigned = resign(&captured, Color::White);
+        assert!(get_last_move_events(&resigned).unwrap().game_end);
+    }
+
     #[test]
     fn test_is_player_turn() {
         let game = create_new_game();
         assert!(is_player_turn(&game, Color::White));
         assert!(!is_player_turn(&game, Color::Black));
     }
+
+    #[test]
+    fn test_validate_many() {
+        let game = play_from(
+            &create_new_game(),
+            &[[0, 2, 0, 1], [0, -2, 0, -1], [0, 1, 0, 0]],
+        );
+        let cell = |q, r| HexCoord::new(q, r);
+        let candidates = [
+            (cell(0, -1), cell(0, 0), None),
+            (cell(0, 0), cell(0, -1), None),
+            (cell(0, 1), cell(0, 0), None),
+        ];
+        let validations = validate_many(&game, &candidates);
+        assert!(validations[0].legal && validations[0].capture);
+        assert_eq!(validations[1].reason.as_deref(), Some("notYourPiece"));
+        assert_eq!(validations[2].reason.as_deref(), Some("noPieceAtSource"));
+        for (validation, &(from, to, promotion)) in validations.iter().zip(&candidates) {
+            assert_eq!(
+                validation.reason,
+                try_make_move(&game, from, to, promotion).err()
+            );
+        }
+
+        let over = resign(&game, Color::Black);
+        let validations = validate_many(&over, &candidates[..1]);
+        assert_eq!(validations[0].reason.as_deref(), Some("gameOver"));
+    }
 }
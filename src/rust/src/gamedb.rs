@@ -0,0 +1,819 @@
+//! Underchex Game Database (native only, `gamedb` feature)
+//!
+//! Stores games in a compact append-only file and keeps in-memory indexes by
+//! player, opening, result and position, so large training corpora can be
+//! queried without holding every game in memory.
+//!
+//! File layout (little-endian):
+//! - Magic `UCXDB1`
+//! - Records, each prefixed by its length (u32):
+//!   white name, black name, opening (u16 length + UTF-8 bytes each),
+//!   result (u8), move count (u16), then 3 bytes per move
+//!   (from cell, to cell, promotion).
+//!
+//! Indexes are rebuilt when the file is opened.
+//!
+//! Corpora can be imported from NDJSON streams (one game per line) without
+//! reading whole files into memory; see [`import_stream`].
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::explorer::get_game_winner;
+pub use crate::explorer::{classify_opening, OPENING_SIGNATURE_PLIES};
+use crate::game::{create_new_game, get_position_key, make_move_with_promotion};
+use crate::moves::apply_move;
+use crate::types::{BoardState, Color, GameStatus, HexCoord, Move, PieceType, BOARD_RADIUS};
+
+const MAGIC: &[u8; 6] = b"UCXDB1";
+
+// ============================================================================
+// Records
+// ============================================================================
+
+/// Result of a stored game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GameResult {
+    WhiteWin,
+    BlackWin,
+    Draw,
+    Unfinished,
+}
+
+impl GameResult {
+    /// Derive the result from a game status.
+    pub fn from_status(status: &GameStatus) -> Self {
+        match get_game_winner(status) {
+            None => GameResult::Unfinished,
+            Some(Some(Color::White)) => GameResult::WhiteWin,
+            Some(Some(Color::Black)) => GameResult::BlackWin,
+            Some(None) => GameResult::Draw,
+        }
+    }
+
+    fn to_byte(self) -> u8 {
+        match self {
+            GameResult::Unfinished => 0,
+            GameResult::WhiteWin => 1,
+            GameResult::BlackWin => 2,
+            GameResult::Draw => 3,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(GameResult::Unfinished),
+            1 => Some(GameResult::WhiteWin),
+            2 => Some(GameResult::BlackWin),
+            3 => Some(GameResult::Draw),
+            _ => None,
+        }
+    }
+}
+
+/// A game stored in the database.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameRecord {
+    pub white: String,
+    pub black: String,
+    /// Opening classification (see `classify_opening`)
+    pub opening: String,
+    pub result: GameResult,
+    /// Moves played from the standard starting position
+    pub moves: Vec<Move>,
+}
+
+impl GameRecord {
+    /// Create a record, classifying the opening from the moves.
+    pub fn new(white: &str, black: &str, moves: Vec<Move>, result: GameResult) -> Self {
+        Self {
+            white: white.to_string(),
+            black: black.to_string(),
+            opening: classify_opening(&moves),
+            result,
+            moves,
+        }
+    }
+}
+
+/// Compact 64-bit hash of a position for the position index.
+pub fn position_index_hash(board: &BoardState, side_to_move: Color) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    get_position_key(board, side_to_move).hash(&mut hasher);
+    hasher.finish()
+}
+
+// ============================================================================
+// Binary Encoding
+// ============================================================================
+
+const GRID_SIZE: i32 = 2 * BOARD_RADIUS + 1;
+
+fn cell_to_byte(coord: HexCoord) -> u8 {
+    ((coord.q + BOARD_RADIUS) * GRID_SIZE + (coord.r + BOARD_RADIUS)) as u8
+}
+
+fn byte_to_cell(byte: u8) -> HexCoord {
+    let index = byte as i32;
+    HexCoord::new(
+        index / GRID_SIZE - BOARD_RADIUS,
+        index % GRID_SIZE - BOARD_RADIUS,
+    )
+}
+
+fn promotion_to_byte(promotion: Option<PieceType>) -> u8 {
+    match promotion {
+        None => 0,
+        Some(PieceType::Queen) => 1,
+        Some(PieceType::Chariot) => 2,
+        Some(PieceType::Lance) => 3,
+        Some(PieceType::Knight) => 4,
+        Some(PieceType::Pawn) => 5,
+        Some(PieceType::King) => 6,
+        Some(PieceType::Boulder) => 7,
+        Some(PieceType::Wazir) => 8,
+        Some(PieceType::Ferz) => 9,
+        Some(PieceType::Dabbaba) => 10,
+        Some(PieceType::Cannon) => 11,
+        Some(PieceType::PromotedKnight) => 12,
+        Some(PieceType::PromotedLance) => 13,
+    }
+}
+
+fn byte_to_promotion(byte: u8) -> Option<PieceType> {
+    match byte {
+        1 => Some(PieceType::Queen),
+        2 => Some(PieceType::Chariot),
+        3 => Some(PieceType::Lance),
+        4 => Some(PieceType::Knight),
+        5 => Some(PieceType::Pawn),
+        6 => Some(PieceType::King),
+        7 => Some(PieceType::Boulder),
+        8 => Some(PieceType::Wazir),
+        9 => Some(PieceType::Ferz),
+        10 => Some(PieceType::Dabbaba),
+        11 => Some(PieceType::Cannon),
+        12 => Some(PieceType::PromotedKnight),
+        13 => Some(PieceType::PromotedLance),
+        _ => None,
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn write_string(buf: &mut Vec<u8>, value: &str) -> io::Result<()> {
+    let len = u16::try_from(value.len()).map_err(|_| invalid_data("string too long"))?;
+    buf.extend_from_slice(&len.to_le_bytes());
+    buf.extend_from_slice(value.as_bytes());
+    Ok(())
+}
+
+fn read_string(data: &[u8], pos: &mut usize) -> io::Result<String> {
+    let len = u16::from_le_bytes(read_bytes::<2>(data, pos)?) as usize;
+    let bytes = data
+        .get(*pos..*pos + len)
+        .ok_or_else(|| invalid_data("truncated string"))?;
+    *pos += len;
+    String::from_utf8(bytes.to_vec()).map_err(|_| invalid_data("invalid UTF-8"))
+}
+
+fn read_bytes<const N: usize>(data: &[u8], pos: &mut usize) -> io::Result<[u8; N]> {
+    let bytes = data
+        .get(*pos..*pos + N)
+        .ok_or_else(|| invalid_data("truncated record"))?;
+    *pos += N;
+    let mut out = [0u8; N];
+    out.copy_from_slice(bytes);
+    Ok(out)
+}
+
+/// Encode a record into its compact binary form (without the length prefix).
+/// Fails if a name or the move list is too long for its length field.
+pub fn encode_record(record: &GameRecord) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::with_capacity(16 + record.moves.len() * 3);
+    write_string(&mut buf, &record.white)?;
+    write_string(&mut buf, &record.black)?;
+    write_string(&mut buf, &record.opening)?;
+    buf.push(record.result.to_byte());
+    let move_count =
+        u16::try_from(record.moves.len()).map_err(|_| invalid_data("too many moves"))?;
+    buf.extend_from_slice(&move_count.to_le_bytes());
+    for mv in &record.moves {
+        buf.push(cell_to_byte(mv.from));
+        buf.push(cell_to_byte(mv.to));
+        buf.push(promotion_to_byte(mv.promotion));
+    }
+    Ok(buf)
+}
+
+/// Decode a record, replaying its moves from the starting position to
+/// restore piece and capture information. Fails on an illegal move.
+pub fn decode_record(data: &[u8]) -> io::Result<GameRecord> {
+    let mut pos = 0;
+    let white = read_string(data, &mut pos)?;
+    let black = read_string(data, &mut pos)?;
+    let opening = read_string(data, &mut pos)?;
+    let [result_byte] = read_bytes::<1>(data, &mut pos)?;
+    let result = GameResult::from_byte(result_byte).ok_or_else(|| invalid_data("bad result"))?;
+    let move_count = u16::from_le_bytes(read_bytes::<2>(data, &mut pos)?) as usize;
+
+    let mut game = create_new_game();
+    let mut moves = Vec::with_capacity(move_count);
+    for _ in 0..move_count {
+        let [from, to, promotion] = read_bytes::<3>(data, &mut pos)?;
+        game = make_move_with_promotion(
+            &game,
+            byte_to_cell(from),
+            byte_to_cell(to),
+            byte_to_promotion(promotion),
+        )
+        .ok_or_else(|| invalid_data("illegal move"))?;
+        let mv = game
+            .history
+            .last()
+            .cloned()
+            .ok_or_else(|| invalid_data("illegal move"))?;
+        moves.push(mv);
+    }
+
+    Ok(GameRecord {
+        white,
+        black,
+        opening,
+        result,
+        moves,
+    })
+}
+
+// ============================================================================
+// Queries
+// ============================================================================
+
+/// Filter for database queries. All set fields must match.
+#[derive(Debug, Clone, Default)]
+pub struct GameQuery {
+    /// Games where this player had either color
+    pub player: Option<String>,
+    pub opening: Option<String>,
+    pub result: Option<GameResult>,
+    /// Games reaching this position (see `position_index_hash`)
+    pub position: Option<u64>,
+}
+
+impl GameQuery {
+    /// Query for games reaching a position.
+    pub fn reaching(board: &BoardState, side_to_move: Color) -> Self {
+        Self {
+            position: Some(position_index_hash(board, side_to_move)),
+            ..Self::default()
+        }
+    }
+}
+
+// ============================================================================
+// Database
+// ============================================================================
+
+/// File-backed game database with in-memory indexes.
+pub struct GameDatabase {
+    path: PathBuf,
+    /// Byte offset of each record's length prefix, by game id
+    offsets: Vec<u64>,
+    by_player: HashMap<String, Vec<u32>>,
+    by_opening: HashMap<String, Vec<u32>>,
+    by_result: HashMap<GameResult, Vec<u32>>,
+    by_position: HashMap<u64, Vec<u32>>,
+}
+
+impl GameDatabase {
+    /// Create a new, empty database file (truncating any existing file).
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut file = File::create(path.as_ref())?;
+        file.write_all(MAGIC)?;
+        Ok(Self::empty(path.as_ref()))
+    }
+
+    /// Open an existing database file and rebuild its indexes.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut db = Self::empty(path.as_ref());
+        let file = File::open(path.as_ref())?;
+        let file_len = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+
+        let mut magic = [0u8; 6];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not an Underchex game database"));
+        }
+
+        let mut offset = MAGIC.len() as u64;
+        loop {
+            let mut len_bytes = [0u8; 4];
+            match reader.read_exact(&mut len_bytes) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e),
+            }
+            let len = u32::from_le_bytes(len_bytes) as usize;
+            if offset + 4 + len as u64 > file_len {
+                return Err(invalid_data("truncated record"));
+            }
+            let mut data = vec![0u8; len];
+            reader.read_exact(&mut data)?;
+
+            let record = decode_record(&data)?;
+            db.index_record(offset, &record);
+            offset += 4 + len as u64;
+        }
+
+        Ok(db)
+    }
+
+    fn empty(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            offsets: Vec::new(),
+            by_player: HashMap::new(),
+            by_opening: HashMap::new(),
+            by_result: HashMap::new(),
+            by_position: HashMap::new(),
+        }
+    }
+
+    fn index_record(&mut self, offset: u64, record: &GameRecord) -> u32 {
+        let id = self.offsets.len() as u32;
+        self.offsets.push(offset);
+
+        for player in [&record.white, &record.black] {
+            let ids = self.by_player.entry(player.clone()).or_default();
+            if ids.last() != Some(&id) {
+                ids.push(id);
+            }
+        }
+        self.by_opening
+            .entry(record.opening.clone())
+            .or_default()
+            .push(id);
+        self.by_result.entry(record.result).or_default().push(id);
+
+        let mut board = create_new_game().board;
+        let mut turn = Color::White;
+        let mut seen = HashSet::new();
+        for mv in record.moves.iter().map(Some).chain(std::iter::once(None)) {
+            let hash = position_index_hash(&board, turn);
+            if seen.insert(hash) {
+                self.by_position.entry(hash).or_default().push(id);
+            }
+            if let Some(mv) = mv {
+                board = apply_move(&board, mv);
+                turn = turn.opposite();
+            }
+        }
+
+        id
+    }
+
+    /// Append a game. Returns its id.
+    pub fn append(&mut self, record: &GameRecord) -> io::Result<u32> {
+        let data = encode_record(record)?;
+        let len = u32::try_from(data.len()).map_err(|_| invalid_data("record too long"))?;
+        let mut file = OpenOptions::new().append(true).open(&self.path)?;
+        let offset = file.seek(SeekFrom::End(0))?;
+        file.write_all(&len.to_le_bytes())?;
+        file.write_all(&data)?;
+        Ok(self.index_record(offset, record))
+    }
+
+    /// Number of stored games.
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Whether the database has no games.
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// Read a single game by id.
+    pub fn get(&self, id: u32) -> io::Result<GameRecord> {
+        let offset = *self
+            .offsets
+            .get(id as usize)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such game"))?;
+        let mut file = File::open(&self.path)?;
+        let file_len = file.metadata()?.len();
+        file.seek(SeekFrom::Start(offset))?;
+        let mut len_bytes = [0u8; 4];
+        file.read_exact(&mut len_bytes)?;
+        let len = u32::from_le_bytes(len_bytes) as usize;
+        if offset + 4 + len as u64 > file_len {
+            return Err(invalid_data("truncated record"));
+        }
+        let mut data = vec![0u8; len];
+        file.read_exact(&mut data)?;
+        decode_record(&data)
+    }
+
+    /// Ids of games matching a query, in insertion order.
+    pub fn query(&self, query: &GameQuery) -> Vec<u32> {
+        let empty = Vec::new();
+        let mut filters: Vec<&Vec<u32>> = Vec::new();
+        if let Some(player) = &query.player {
+            filters.push(self.by_player.get(player).unwrap_or(&empty));
+        }
+        if let Some(opening) = &query.opening {
+            filters.push(self.by_opening.get(opening).unwrap_or(&empty));
+        }
+        if let Some(result) = &query.result {
+            filters.push(self.by_result.get(result).unwrap_or(&empty));
+        }
+        if let Some(position) = &query.position {
+            filters.push(self.by_position.get(position).unwrap_or(&empty));
+        }
+
+        filters.sort_by_key(|ids| ids.len());
+        match filters.split_first() {
+            None => (0..self.len() as u32).collect(),
+            Some((smallest, rest)) => {
+                let rest: Vec<HashSet<u32>> = rest
+                    .iter()
+                    .map(|ids| ids.iter().copied().collect())
+                    .collect();
+                smallest
+                    .iter()
+                    .copied()
+                    .filter(|id| rest.iter().all(|set| set.contains(id)))
+                    .collect()
+            }
+        }
+    }
+
+    /// Stream games matching a query, reading each from disk on demand.
+    pub fn stream<'a>(
+        &'a self,
+        query: &GameQuery,
+    ) -> impl Iterator<Item = io::Result<GameRecord>> + 'a {
+        self.query(query).into_iter().map(move |id| self.get(id))
+    }
+
+    /// Distinct players in the database.
+    pub fn players(&self) -> Vec<&str> {
+        self.by_player.keys().map(|s| s.as_str()).collect()
+    }
+}
+
+// ============================================================================
+// Streamed Import
+// ============================================================================
+
+/// Longest accepted line of an import stream; longer lines are skipped.
+pub const MAX_IMPORT_LINE_BYTES: usize = 1 << 20;
+
+/// Most import errors kept in an [`ImportReport`]; later ones are only counted.
+pub const MAX_REPORTED_IMPORT_ERRORS: usize = 100;
+
+/// One line of an NDJSON corpus: a game played from the standard starting
+/// position. Without `result`, it is taken from `status`, or else from the
+/// position the moves reach.
+#[derive(Debug, Clone, Deserialize)]
+struct ImportedGame {
+    #[serde(default)]
+    white: String,
+    #[serde(default)]
+    black: String,
+    moves: Vec<ImportedMove>,
+    #[serde(default)]
+    result: Option<GameResult>,
+    #[serde(default)]
+    status: Option<GameStatus>,
+}
+
+/// A move as recorded by `WasmGame.get_history()`, or compactly as
+/// [from_q, from_r, to_q, to_r].
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum ImportedMove {
+    Cells([i32; 4]),
+    Full(Move),
+}
+
+/// A game of an import stream that was skipped.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportError {
+    /// 1-based line number
+    pub line: usize,
+    pub message: String,
+}
+
+/// Outcome of importing a stream into a database.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub imported: usize,
+    pub skipped: usize,
+    /// The first [`MAX_REPORTED_IMPORT_ERRORS`] errors
+    pub errors: Vec<ImportError>,
+}
+
+/// Parse a game, replaying its moves to check them and to fill in piece and
+/// capture information.
+fn parse_imported_game(line: &str) -> Result<GameRecord, String> {
+    let game: ImportedGame = serde_json::from_str(line).map_err(|e| e.to_string())?;
+
+    let mut state = create_new_game();
+    for (ply, mv) in game.moves.iter().enumerate() {
+        let (from, to, promotion) = match mv {
+            ImportedMove::Cells([fq, fr, tq, tr]) => {
+                (HexCoord::new(*fq, *fr), HexCoord::new(*tq, *tr), None)
+            }
+            ImportedMove::Full(mv) => (mv.from, mv.to, mv.promotion),
+        };
+        state = make_move_with_promotion(&state, from, to, promotion)
+            .ok_or_else(|| format!("illegal move {} {}-{}", ply + 1, from.to_key(), to.to_key()))?;
+    }
+
+    let result = game
+        .result
+        .or_else(|| game.status.as_ref().map(GameResult::from_status))
+        .unwrap_or_else(|| GameResult::from_status(&state.status));
+    Ok(GameRecord::new(
+        &game.white,
+        &game.black,
+        state.history,
+        result,
+    ))
+}
+
+/// Games of an NDJSON stream (see [`import_stream`]).
+pub struct ImportStream<R> {
+    reader: R,
+    line: usize,
+    buffer: Vec<u8>,
+    failed: bool,
+}
+
+impl<R: BufRead> ImportStream<R> {
+    /// Read the next line into the buffer. Returns false at the end of the
+    /// stream; a line longer than the limit is consumed but left truncated.
+    fn read_line(&mut self) -> io::Result<bool> {
+        self.buffer.clear();
+        let limit = MAX_IMPORT_LINE_BYTES as u64 + 1;
+        let read = self
+            .reader
+            .by_ref()
+            .take(limit)
+            .read_until(b'\n', &mut self.buffer)?;
+        if read == 0 {
+            return Ok(false);
+        }
+        self.line += 1;
+        if self.buffer.last() != Some(&b'\n') && read as u64 == limit {
+            // Discard the rest of the line
+            loop {
+                let available = self.reader.fill_buf()?;
+                if available.is_empty() {
+                    break;
+                }
+                match available.iter().position(|&b| b == b'\n') {
+                    Some(end) => {
+                        self.reader.consume(end + 1);
+                        break;
+                    }
+                    None => {
+                        let len = available.len();
+                        self.reader.consume(len);
+                    }
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    fn error(&self, message: String) -> ImportError {
+        ImportError {
+            line: self.line,
+            message,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for ImportStream<R> {
+    type Item = Result<GameRecord, ImportError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.failed {
+            match self.read_line() {
+                Ok(false) => return None,
+                Ok(true) => {}
+                Err(e) => {
+                    // A read error ends the stream
+                    self.failed = true;
+                    return Some(Err(self.error(e.to_string())));
+                }
+            }
+            if self.buffer.len() > MAX_IMPORT_LINE_BYTES {
+                return Some(Err(self.error("line too long".to_string())));
+            }
+            let Ok(line) = std::str::from_utf8(&self.buffer) else {
+                return Some(Err(self.error("invalid UTF-8".to_string())));
+            };
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            return Some(parse_imported_game(line).map_err(|message| self.error(message)));
+        }
+        None
+    }
+}
+
+/// Parse an NDJSON stream of games one line at a time. Each line is a JSON
+/// object { white?, black?, moves, result?, status? } whose moves are as
+/// recorded by `WasmGame.get_history()` or [from_q, from_r, to_q, to_r].
+/// Malformed games are reported with their line number and skipped; only one
+/// line is held in memory.
+pub fn import_stream<R: BufRead>(reader: R) -> ImportStream<R> {
+    ImportStream {
+        reader,
+        line: 0,
+        buffer: Vec::new(),
+        failed: false,
+    }
+}
+
+impl GameDatabase {
+    /// Append every valid game of an NDJSON stream (see [`import_stream`]).
+    /// Fails only if writing to the database fails.
+    pub fn import<R: BufRead>(&mut self, reader: R) -> io::Result<ImportReport> {
+        let mut report = ImportReport::default();
+        for game in import_stream(reader) {
+            match game {
+                Ok(record) => {
+                    self.append(&record)?;
+                    report.imported += 1;
+                }
+                Err(error) => {
+                    report.skipped += 1;
+                    if report.errors.len() < MAX_REPORTED_IMPORT_ERRORS {
+                        report.errors.push(error);
+                    }
+                }
+            }
+        }
+        Ok(report)
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::make_move;
+    use crate::test_support::play;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("underchex-{}-{}.db", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_record_roundtrip() {
+        let record = GameRecord::new(
+            "alice",
+            "bob",
+            play(&[
+                [0, 2, 0, 1],
+                [0, -2, 0, -1],
+                [0, 1, 0, 0],
+                [1, -2, 1, -1],
+                [0, 0, 0, -1],
+            ])
+            .history,
+            GameResult::Draw,
+        );
+        let encoded = encode_record(&record).unwrap();
+        assert_eq!(decode_record(&encoded).unwrap(), record);
+
+        // A move that is illegal in the replayed game is rejected
+        let mut illegal = encoded.clone();
+        let last = illegal.len() - 3;
+        illegal[last..].copy_from_slice(&encoded[last - 3..last]);
+        assert!(decode_record(&illegal).is_err());
+
+        let long_name = GameRecord {
+            white: "x".repeat(usize::from(u16::MAX) + 1),
+            ..record
+        };
+        assert!(encode_record(&long_name).is_err());
+    }
+
+    #[test]
+    fn test_database_indexes_and_reopen() {
+        let path = temp_path("indexes");
+        let mut db = GameDatabase::create(&path).unwrap();
+
+        let e4 = play(&[[0, 2, 0, 1], [0, -2, 0, -1]]).history;
+        let other = play(&[[1, 2, 1, 1], [0, -2, 0, -1]]).history;
+        db.append(&GameRecord::new(
+            "alice",
+            "bob",
+            e4.clone(),
+            GameResult::WhiteWin,
+        ))
+        .unwrap();
+        db.append(&GameRecord::new("bob", "carol", other, GameResult::Draw))
+            .unwrap();
+        db.append(&GameRecord::new("carol", "alice", e4, GameResult::BlackWin))
+            .unwrap();
+
+        let reopened = GameDatabase::open(&path).unwrap();
+        assert_eq!(reopened.len(), 3);
+
+        let alice = GameQuery {
+            player: Some("alice".to_string()),
+            ..GameQuery::default()
+        };
+        assert_eq!(reopened.query(&alice), vec![0, 2]);
+
+        let alice_draws = GameQuery {
+            result: Some(GameResult::Draw),
+            ..alice.clone()
+        };
+        assert!(reopened.query(&alice_draws).is_empty());
+
+        let start = create_new_game();
+        let after = make_move(&start, HexCoord::new(0, 2), HexCoord::new(0, 1)).unwrap();
+        let reaching = GameQuery::reaching(&after.board, after.turn);
+        let games: Vec<GameRecord> = reopened.stream(&reaching).map(|g| g.unwrap()).collect();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[1].white, "carol");
+
+        let by_opening = GameQuery {
+            opening: Some(games[0].opening.clone()),
+            ..GameQuery::default()
+        };
+        assert_eq!(reopened.query(&by_opening), vec![0, 2]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_open_rejects_foreign_file() {
+        let path = temp_path("foreign");
+        std::fs::write(&path, b"not a database").unwrap();
+        assert!(GameDatabase::open(&path).is_err());
+
+        // A length prefix running past the end of the file
+        let mut data = MAGIC.to_vec();
+        data.extend_from_slice(&u32::MAX.to_le_bytes());
+        data.extend_from_slice(b"short");
+        std::fs::write(&path, data).unwrap();
+        assert!(GameDatabase::open(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_import_stream_skips_malformed_games() {
+        let history =
+            serde_json::to_string(&play(&[[0, 2, 0, 1], [0, -2, 0, -1]]).history).unwrap();
+        let corpus = format!(
+            "{}\n\n{}\nnot json\n{}\n{}\n",
+            r#"{"white":"a","black":"b","moves":[[0,2,0,1],[0,-2,0,-1]],"result":"WhiteWin"}"#,
+            r#"{"moves":[[0,2,0,1],[0,2,0,1]]}"#,
+            format_args!(
+                r#"{{"white":"c","moves":{},"status":"Stalemate"}}"#,
+                history
+            ),
+            "x".repeat(MAX_IMPORT_LINE_BYTES + 10),
+        );
+        let games: Vec<_> = import_stream(corpus.as_bytes()).collect();
+        assert_eq!(games.len(), 5);
+        assert_eq!(games[0].as_ref().unwrap().result, GameResult::WhiteWin);
+        assert_eq!(games[1].as_ref().unwrap_err().line, 3);
+        assert_eq!(games[2].as_ref().unwrap_err().line, 4);
+        let third = games[3].as_ref().unwrap();
+        assert_eq!(
+            (third.white.as_str(), third.result),
+            ("c", GameResult::Draw)
+        );
+        assert_eq!(third.moves, games[0].as_ref().unwrap().moves);
+        assert_eq!(games[4].as_ref().unwrap_err().message, "line too long");
+
+        let path = temp_path("import");
+        let mut db = GameDatabase::create(&path).unwrap();
+        let report = db.import(corpus.as_bytes()).unwrap();
+        assert_eq!((report.imported, report.skipped), (2, 3));
+        assert_eq!(report.errors.len(), 3);
+        assert_eq!(GameDatabase::open(&path).unwrap().len(), 2);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,342 @@
+//! Underchex Human-like Move Selection
+//!
+//! Plays like a club player rather than a depth-capped engine: every legal
+//! move gets a shallow search score plus a heuristic "instinct" bonus for the
+//! moves people are drawn to (captures, checks, developing pieces), and the
+//! move is drawn at random with probabilities that favor good, natural moves.
+//! Now and then the choice goes by instinct alone, which produces the typical
+//! mistakes of weaker players: grabbing material, checking for its own sake,
+//! missing a quiet reply.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ai::{
+    alpha_beta, get_centrality_bonus, get_piece_value, gives_check, prepare_variant_moves,
+    search_moves, AIDifficulty, SearchStats, TranspositionTable, BOULDER_PLACEMENT_CANDIDATES,
+    CHECKMATE_VALUE,
+};
+use crate::game::{create_position_record, RepetitionStack};
+use crate::moves::{apply_move, is_in_check};
+use crate::rng::SeededRng;
+use crate::stats::OPENING_PLIES;
+use crate::types::{BoardState, Color, Move, PieceType, PositionRecord, Variant};
+
+/// Instinct bonus for any capture, on top of a share of the captured value.
+const CAPTURE_BONUS: i32 = 40;
+
+/// Instinct bonus for giving check.
+const CHECK_BONUS: i32 = 50;
+
+/// Instinct bonus for bringing a piece towards the center in the opening.
+const DEVELOPMENT_BONUS: i32 = 25;
+
+/// Instinct penalty for walking the king when not in check.
+const KING_WALK_PENALTY: i32 = 40;
+
+/// Search scores are clamped to this far below the best move, so that a
+/// hopeless move is unlikely but still has a defined probability.
+const SCORE_FLOOR: i32 = 1000;
+
+// ============================================================================
+// Settings
+// ============================================================================
+
+/// How a human-like player chooses.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct HumanlikeSettings {
+    /// Search depth used to score each move (counting the move itself)
+    pub depth: i32,
+    /// Spread of the choice in centipawns: the higher, the more often moves
+    /// scored below the best are played
+    pub temperature: f64,
+    /// Chance of choosing by instinct alone
+    pub mistake_rate: f64,
+    /// Most an instinctive move may lose against the best move, in centipawns
+    pub mistake_limit: i32,
+}
+
+impl HumanlikeSettings {
+    /// Settings for a difficulty level: the lower, the shallower and more
+    /// error-prone.
+    pub fn for_difficulty(difficulty: AIDifficulty) -> Self {
+        match difficulty {
+            AIDifficulty::Easy => Self {
+                depth: 1,
+                temperature: 120.0,
+                mistake_rate: 0.25,
+                mistake_limit: 600,
+            },
+            AIDifficulty::Medium => Self {
+                depth: 2,
+                temperature: 60.0,
+                mistake_rate: 0.1,
+                mistake_limit: 300,
+            },
+            AIDifficulty::Hard => Self {
+                depth: 3,
+                temperature: 25.0,
+                mistake_rate: 0.03,
+                mistake_limit: 150,
+            },
+        }
+    }
+}
+
+// ============================================================================
+// Move Model
+// ============================================================================
+
+/// A legal move with its model scores.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HumanlikeMove {
+    pub mv: Move,
+    /// Shallow search score, from the mover's side
+    pub score: i32,
+    /// Heuristic instinct bonus
+    pub instinct: i32,
+    /// Probability of playing the move
+    pub probability: f64,
+}
+
+/// How natural a move looks to a human, in centipawn-like units.
+pub fn instinct_bonus(board: &BoardState, mv: &Move, ply: usize) -> i32 {
+    let mut bonus = 0;
+    if let Some(captured) = mv.captured {
+        bonus += CAPTURE_BONUS + get_piece_value(captured.piece_type) / 10;
+    }
+    if gives_check(board, mv) {
+        bonus += CHECK_BONUS;
+    }
+    match mv.piece.piece_type {
+        PieceType::King => {
+            if !is_in_check(board, mv.piece.color) {
+                bonus -= KING_WALK_PENALTY;
+            }
+        }
+        PieceType::Pawn => {}
+        _ => {
+            if ply < OPENING_PLIES && get_centrality_bonus(mv.to) > get_centrality_bonus(mv.from) {
+                bonus += DEVELOPMENT_BONUS;
+            }
+        }
+    }
+    bonus
+}
+
+/// Softmax weights of values (higher is likelier) at a temperature.
+fn softmax(values: &[f64], temperature: f64) -> Vec<f64> {
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let weights: Vec<f64> = values
+        .iter()
+        .map(|value| ((value - max) / temperature.max(1.0)).exp())
+        .collect();
+    let total: f64 = weights.iter().sum();
+    weights.iter().map(|weight| weight / total).collect()
+}
+
+/// Score every legal move and the probability of playing it. `history` holds
+/// the game's positions, for repetition detection and the game phase.
+pub fn humanlike_moves(
+    board: &BoardState,
+    color: Color,
+    settings: &HumanlikeSettings,
+    tt: &mut TranspositionTable,
+    history: &[PositionRecord],
+    variant: Variant,
+) -> Vec<HumanlikeMove> {
+    let mut moves = prepare_variant_moves(
+        board,
+        search_moves(board, color, variant, tt),
+        variant,
+        BOULDER_PLACEMENT_CANDIDATES,
+    );
+    // A fixed order makes seeded choices repeatable
+    moves.sort_by_key(|mv| (mv.from.q, mv.from.r, mv.to.q, mv.to.r));
+
+    let ply = history.len().saturating_sub(1);
+    let sign = if color == Color::White { 1 } else { -1 };
+    let mut path = RepetitionStack::from_root(history, create_position_record(board, color));
+
+    let mut stats = SearchStats::default();
+    let mut scored: Vec<HumanlikeMove> = moves
+        .into_iter()
+        .map(|mv| {
+            let saved = tt.enter_hands(&mv, variant);
+            let score = sign
+                * alpha_beta(
+                    &apply_move(board, &mv),
+                    (settings.depth - 1).max(0),
+                    -CHECKMATE_VALUE - 1,
+                    CHECKMATE_VALUE + 1,
+                    color != Color::White,
+                    &mut stats,
+                    tt,
+                    true,
+                    &mut path,
+                    variant,
+                    Some(&mv),
+                );
+            tt.leave_hands(saved);
+            HumanlikeMove {
+                instinct: instinct_bonus(board, &mv, ply),
+                mv,
+                score,
+                probability: 0.0,
+            }
+        })
+        .collect();
+
+    let Some(best) = scored.iter().map(|m| m.score).max() else {
+        return scored;
+    };
+    let values: Vec<f64> = scored
+        .iter()
+        .map(|m| f64::from(m.score.max(best - SCORE_FLOOR) - best + m.instinct))
+        .collect();
+    for (m, probability) in scored
+        .iter_mut()
+        .zip(softmax(&values, settings.temperature))
+    {
+        m.probability = probability;
+    }
+    scored
+}
+
+/// Choose a move the human-like way. With probability `mistake_rate` the
+/// choice goes by instinct among the moves within `mistake_limit` of the best;
+/// otherwise it is drawn by the model probabilities.
+pub fn choose_humanlike_move(
+    board: &BoardState,
+    color: Color,
+    settings: &HumanlikeSettings,
+    rng: &mut SeededRng,
+    tt: &mut TranspositionTable,
+    history: &[PositionRecord],
+    variant: Variant,
+) -> Option<HumanlikeMove> {
+    let moves = humanlike_moves(board, color, settings, tt, history, variant);
+    let best = moves.iter().map(|m| m.score).max()?;
+
+    let (pool, weights): (Vec<&HumanlikeMove>, Vec<f64>) = if rng.next_f64() < settings.mistake_rate
+    {
+        let pool: Vec<&HumanlikeMove> = moves
+            .iter()
+            .filter(|m| m.score >= best - settings.mistake_limit)
+            .collect();
+        let instincts: Vec<f64> = pool.iter().map(|m| f64::from(m.instinct)).collect();
+        let weights = softmax(&instincts, settings.temperature);
+        (pool, weights)
+    } else {
+        (
+            moves.iter().collect(),
+            moves.iter().map(|m| m.probability).collect(),
+        )
+    };
+
+    let mut roll = rng.next_f64();
+    for (m, weight) in pool.iter().zip(&weights) {
+        if roll < *weight {
+            return Some((*m).clone());
+        }
+        roll -= weight;
+    }
+    pool.last().map(|m| (*m).clone())
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::create_new_game;
+    use crate::moves::generate_all_legal_moves;
+    use crate::types::{HexCoord, Piece};
+
+    #[test]
+    fn test_probabilities_favor_good_natural_moves() {
+        // White can take a loose queen
+        let mut board = BoardState::new();
+        board.insert("0,4".to_string(), Piece::new(PieceType::King, Color::White));
+        board.insert(
+            "0,1".to_string(),
+            Piece::new(PieceType::Queen, Color::White),
+        );
+        board.insert(
+            "0,-4".to_string(),
+            Piece::new(PieceType::King, Color::Black),
+        );
+        board.insert(
+            "0,-2".to_string(),
+            Piece::new(PieceType::Queen, Color::Black),
+        );
+        board.insert(
+            "3,-4".to_string(),
+            Piece::new(PieceType::Pawn, Color::Black),
+        );
+
+        let settings = HumanlikeSettings::for_difficulty(AIDifficulty::Medium);
+        let mut tt = TranspositionTable::new(1000);
+        let moves = humanlike_moves(
+            &board,
+            Color::White,
+            &settings,
+            &mut tt,
+            &[],
+            Variant::Standard,
+        );
+        let total: f64 = moves.iter().map(|m| m.probability).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+
+        let likeliest = moves
+            .iter()
+            .max_by(|a, b| a.probability.total_cmp(&b.probability))
+            .unwrap();
+        assert_eq!(likeliest.mv.to, HexCoord::new(0, -2));
+        assert!(likeliest.instinct >= CAPTURE_BONUS);
+    }
+
+    #[test]
+    fn test_choice_is_seeded_and_varied() {
+        let game = create_new_game();
+        let settings = HumanlikeSettings::for_difficulty(AIDifficulty::Easy);
+        let mut tt = TranspositionTable::new(1000);
+        let mut choose = |seed: u64| {
+            choose_humanlike_move(
+                &game.board,
+                Color::White,
+                &settings,
+                &mut SeededRng::new(seed),
+                &mut tt,
+                &game.position_history,
+                Variant::Standard,
+            )
+            .unwrap()
+            .mv
+        };
+        assert_eq!(choose(7), choose(7));
+        let distinct: std::collections::HashSet<_> = (0..20)
+            .map(|seed| (choose(seed).from, choose(seed).to))
+            .collect();
+        assert!(distinct.len() > 1);
+    }
+
+    #[test]
+    fn test_development_instinct() {
+        let game = create_new_game();
+        let moves = generate_all_legal_moves(&game.board, Color::White);
+        let developing = moves
+            .iter()
+            .find(|mv| {
+                mv.piece.piece_type == PieceType::Knight
+                    && get_centrality_bonus(mv.to) > get_centrality_bonus(mv.from)
+            })
+            .unwrap();
+        assert_eq!(
+            instinct_bonus(&game.board, developing, 0),
+            DEVELOPMENT_BONUS
+        );
+        assert_eq!(instinct_bonus(&game.board, developing, OPENING_PLIES), 0);
+    }
+}
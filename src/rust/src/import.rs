@@ -0,0 +1,255 @@
+//! Underchex Move List Import
+//!
+//! Reads games recorded by other Underchex clients as plain coordinate move
+//! lists, in either of the community formats:
+//! - a JSON array of moves `{from: {q, r}, to: {q, r}, promotion?}`, as the
+//!   TypeScript implementation writes them (other fields are ignored), or
+//!   wrapped in an object `{moves: [...]}`. Cells may also be `[q, r]` pairs
+//!   or `"q,r"` strings, and promotions piece names ("queen") or letters.
+//! - whitespace-separated text moves `q,r>q,r`, with an optional `=Q`
+//!   promotion. Move numbers such as `12.` are skipped.
+//!
+//! Moves are replayed from the standard starting position. An entry that
+//! cannot be read is reported and skipped; the first illegal move is
+//! reported with the reason and ends the replay, keeping the game up to it.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::game::{create_new_game, try_make_move};
+use crate::messages::{move_message, piece_type_key, Message};
+use crate::pieces::piece_type_from_symbol;
+use crate::types::{GameState, HexCoord, PieceType, PROMOTION_TARGETS};
+
+// ============================================================================
+// Import Results
+// ============================================================================
+
+/// An entry of a move list that was not imported.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MoveImportError {
+    /// 0-based position of the entry in the list
+    pub index: usize,
+    /// The entry as given
+    pub entry: String,
+    /// "malformedMove", or why the move is illegal (a move rejection key)
+    pub reason: Message,
+}
+
+/// A game read from a move list.
+#[derive(Debug, Clone)]
+pub struct MoveListImport {
+    /// The game, replayed up to the first illegal move
+    pub state: GameState,
+    /// Moves replayed
+    pub imported: usize,
+    pub errors: Vec<MoveImportError>,
+}
+
+/// A move of a list, before replay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ListedMove {
+    from: HexCoord,
+    to: HexCoord,
+    promotion: Option<PieceType>,
+}
+
+/// A promotion by piece name or HexFEN letter, in any case.
+fn parse_promotion(text: &str) -> Option<PieceType> {
+    let mut chars = text.chars();
+    if let (Some(letter), None) = (chars.next(), chars.next()) {
+        return piece_type_from_symbol(letter);
+    }
+    PROMOTION_TARGETS
+        .iter()
+        .copied()
+        .find(|&piece_type| piece_type_key(piece_type).eq_ignore_ascii_case(text))
+}
+
+// ============================================================================
+// Formats
+// ============================================================================
+
+/// A cell as `{q, r}`, `[q, r]` or `"q,r"`.
+fn json_cell(value: &Value) -> Option<HexCoord> {
+    let number = |value: &Value| value.as_i64().and_then(|n| i32::try_from(n).ok());
+    match value {
+        Value::Object(fields) => Some(HexCoord::new(
+            number(fields.get("q")?)?,
+            number(fields.get("r")?)?,
+        )),
+        Value::Array(pair) => match pair.as_slice() {
+            [q, r] => Some(HexCoord::new(number(q)?, number(r)?)),
+            _ => None,
+        },
+        Value::String(key) => text_cell(key),
+        _ => None,
+    }
+}
+
+fn json_move(value: &Value) -> Option<ListedMove> {
+    let promotion = match value.get("promotion") {
+        None | Some(Value::Null) => None,
+        Some(promotion) => Some(parse_promotion(promotion.as_str()?)?),
+    };
+    Some(ListedMove {
+        from: json_cell(value.get("from")?)?,
+        to: json_cell(value.get("to")?)?,
+        promotion,
+    })
+}
+
+fn text_cell(text: &str) -> Option<HexCoord> {
+    let (q, r) = text.split_once(',')?;
+    Some(HexCoord::new(
+        q.trim().parse().ok()?,
+        r.trim().parse().ok()?,
+    ))
+}
+
+fn text_move(token: &str) -> Option<ListedMove> {
+    let (cells, promotion) = match token.split_once('=') {
+        Some((cells, promotion)) => (cells, Some(parse_promotion(promotion)?)),
+        None => (token, None),
+    };
+    let (from, to) = cells.split_once('>')?;
+    Some(ListedMove {
+        from: text_cell(from)?,
+        to: text_cell(to)?,
+        promotion,
+    })
+}
+
+/// Entries of a JSON move list with their source text, or None if the text
+/// is not a JSON array (or an object with a `moves` array).
+fn json_entries(text: &str) -> Option<Vec<(String, Option<ListedMove>)>> {
+    let value: Value = serde_json::from_str(text).ok()?;
+    let list = match &value {
+        Value::Array(list) => list,
+        Value::Object(fields) => fields.get("moves")?.as_array()?,
+        _ => return None,
+    };
+    Some(
+        list.iter()
+            .map(|entry| (entry.to_string(), json_move(entry)))
+            .collect(),
+    )
+}
+
+/// Entries of a text move list, without move numbers.
+fn text_entries(text: &str) -> Vec<(String, Option<ListedMove>)> {
+    text.split_whitespace()
+        .filter(|token| {
+            !token
+                .strip_suffix('.')
+                .is_some_and(|number| number.chars().all(|c| c.is_ascii_digit()))
+        })
+        .map(|token| (token.to_string(), text_move(token)))
+        .collect()
+}
+
+// ============================================================================
+// Import
+// ============================================================================
+
+/// Import a move list in either format, telling them apart by whether the
+/// text is JSON. Returns None for JSON that is not a move list.
+pub fn import_move_list(text: &str) -> Option<MoveListImport> {
+    let trimmed = text.trim_start();
+    let entries = if trimmed.starts_with('[') || trimmed.starts_with('{') {
+        json_entries(trimmed)?
+    } else {
+        text_entries(text)
+    };
+
+    let mut state = create_new_game();
+    let mut imported = 0;
+    let mut errors = Vec::new();
+    for (index, (entry, listed)) in entries.into_iter().enumerate() {
+        let Some(mv) = listed else {
+            errors.push(MoveImportError {
+                index,
+                entry,
+                reason: Message::new("malformedMove"),
+            });
+            continue;
+        };
+        match try_make_move(&state, mv.from, mv.to, mv.promotion) {
+            Ok(next) => {
+                state = next;
+                imported += 1;
+            }
+            Err(reason) => {
+                errors.push(MoveImportError {
+                    index,
+                    entry,
+                    reason: move_message(&reason, &state.board, mv.from, mv.to),
+                });
+                break;
+            }
+        }
+    }
+    Some(MoveListImport {
+        state,
+        imported,
+        errors,
+    })
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_typescript_json() {
+        // Moves as the TypeScript client writes them, with a bad entry
+        let json = r#"[
+            {"from": {"q": 0, "r": 2}, "to": {"q": 0, "r": 1},
+             "piece": {"type": "pawn", "color": "white"}},
+            {"from": [2, -3], "to": "3,-2"},
+            {"from": {"q": 0}, "to": {"q": 0, "r": 0}},
+            {"from": {"q": -2, "r": 4}, "to": {"q": 2, "r": 0}, "promotion": null}
+        ]"#;
+        let import = import_move_list(json).unwrap();
+        assert_eq!(import.imported, 3);
+        assert_eq!(import.state.history.len(), 3);
+        assert_eq!(import.errors.len(), 1);
+        assert_eq!(import.errors[0].index, 2);
+        assert_eq!(import.errors[0].reason.key, "malformedMove");
+
+        let wrapped = format!(r#"{{"moves": {}}}"#, json);
+        assert_eq!(import_move_list(&wrapped).unwrap().imported, 3);
+        assert!(import_move_list(r#"{"white": "A"}"#).is_none());
+    }
+
+    #[test]
+    fn test_import_text() {
+        let import = import_move_list("1. 0,2>0,1 2,-3>3,-2\n2. -2,4>2,0 3,-2>2,0").unwrap();
+        assert_eq!(import.imported, 4);
+        assert!(import.errors.is_empty());
+        assert!(import.state.history[3].captured.is_some());
+
+        // The replay stops at an illegal move, saying why
+        let import = import_move_list("0,2>0,1 0,1>0,0 2,-3>3,-2").unwrap();
+        assert_eq!(import.imported, 1);
+        assert_eq!(import.errors.len(), 1);
+        assert_eq!(import.errors[0].entry, "0,1>0,0");
+        assert_eq!(import.errors[0].reason.key, "notYourPiece");
+    }
+
+    #[test]
+    fn test_parse_promotion() {
+        assert_eq!(parse_promotion("queen"), Some(PieceType::Queen));
+        assert_eq!(parse_promotion("Q"), Some(PieceType::Queen));
+        assert_eq!(parse_promotion("Chariot"), Some(PieceType::Chariot));
+        assert_eq!(parse_promotion("wizard"), None);
+        assert_eq!(
+            text_move("0,-3>0,-4=q").map(|mv| mv.promotion),
+            Some(Some(PieceType::Queen))
+        );
+    }
+}
@@ -3,47 +3,208 @@
 //! This crate provides the core game logic for Underchex, a hexagonal chess variant
 //! designed as a "downgrade" from 8-way to 6-way movement.
 //!
+//! The stable API is [`prelude`], whose names are also re-exported at the
+//! crate root. Everything else is reached through its module and may change
+//! between versions. The internal `trace` and `tuning` modules are hidden
+//! from the docs and export only what the WASM bindings and tools use.
+//!
 //! Signed-by: agent #21 claude-sonnet-4 via opencode 20260122T06:31:01
 //! Edited-by: agent #22 claude-sonnet-4 via opencode 20260122T06:43:39 (added AI module)
 
+// A panic aborts the whole WASM instance, so library code reports failures
+// through Option, Result or JSON instead.
+#![cfg_attr(
+    not(test),
+    deny(
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::indexing_slicing,
+        clippy::panic
+    )
+)]
+
 pub mod ai;
+pub mod analysis;
+pub mod annotations;
+pub mod attacks;
 pub mod board;
+pub mod bots;
+pub mod clock;
+pub mod endgames;
+pub mod engine;
+pub mod eval;
+pub mod explorer;
+pub mod export;
 pub mod game;
+#[cfg(all(feature = "gamedb", not(target_arch = "wasm32")))]
+pub mod gamedb;
+pub mod humanlike;
+pub mod import;
+pub mod mates;
+pub mod memory;
+pub mod messages;
 pub mod moves;
+pub mod notation;
+pub mod options;
+pub mod packed;
+pub mod pieces;
+pub mod positions;
+pub mod prelude;
+pub mod profile;
+pub mod puzzles;
+pub mod repertoire;
+pub mod rng;
+pub mod save;
+pub mod scenarios;
+pub mod scoring;
+pub mod selfplay;
+pub mod session;
+pub mod share;
+pub mod simulation;
+pub mod stats;
 pub mod tablebase;
+pub mod tactics;
+#[cfg(test)]
+pub(crate) mod test_support;
+#[doc(hidden)]
+pub mod trace;
+#[doc(hidden)]
+pub mod tuning;
 pub mod types;
+pub mod variants;
+pub mod vision;
+pub mod zobrist;
 
-use std::sync::Mutex;
+use std::sync::{Mutex, MutexGuard, PoisonError};
 use wasm_bindgen::prelude::*;
 
-// Re-export main types for convenience
-pub use ai::*;
-pub use board::*;
-pub use game::*;
-pub use moves::*;
-pub use tablebase::*;
-pub use types::*;
+// The stable names (see `prelude`), and the internal items the tools use
+pub use ai::{
+    evaluate_position, get_ai_move, AIDifficulty, CancelToken, ScoreKind, SearchResult,
+    TranspositionTable,
+};
+pub use engine::{Engine, EngineSearch};
+pub use game::{
+    create_game_from_position, create_new_game, create_variant_game, game_result, get_legal_moves,
+    make_move, make_move_with_promotion, try_make_move, GameResult,
+};
+pub use messages::{english, Message};
+pub use notation::{board_to_hexfen, game_to_hexfen, move_to_san, parse_game_hexfen, parse_hexfen};
+#[doc(hidden)]
+pub use trace::init_tracing;
+#[doc(hidden)]
+pub use tuning::{run_bench, tune_move_ordering, BenchReport, TuningResult};
+pub use types::{
+    BoardState, Color, GameState, GameStatus, HexCoord, Move, Piece, PieceType, StalemateRule,
+    Variant, WinConditions,
+};
+pub use zobrist::{boards_equal, hash_board, hash_position, positions_equal};
+
+use ai::{evaluate_batch, AnalysisCache};
+use analysis::{
+    coach_report, critical_positions, critical_positions_from_json, difficulty, is_resignable,
+    principal_variation, pv_to_arrows, summarize, summarize_from_json, AnalysisSession,
+    CoachReport, PlayedGame, VariationTree,
+};
+use annotations::{AnnotationColor, Annotations};
+use attacks::{annotate_moves, build_attack_map};
+use board::{
+    board_from_offset, board_to_offset, cell_class, diff, get_all_cells, get_line, hex_distance,
+    is_valid_cell, moves_to_offset, CellClass, OffsetPiece,
+};
+use bots::{create_bot, Bot, ConsultationBot, SearchLimits};
+use endgames::{
+    grade_defense, hex_opposition, king_confinement, random_position,
+    random_position_with_difficulty, EndgameDifficulty,
+};
+use engine::engine_info;
+use explorer::{clear_explorer, import_explorer_games_from_json, query_explorer};
+use export::{export_history_table, HistoryRecord, TableFormat};
+use game::{
+    claim_draw, claimable_draw, get_boulder_placements, get_last_move_events,
+    is_current_player_in_check, make_boulder_placement, make_drop, position_occurrences, resign,
+    validate_many, with_stalemate_rule, with_win_conditions,
+};
+use import::import_move_list;
+use memory::{memory_usage, set_memory_budget};
+use messages::{describe_last_move, describe_move, move_message};
+use moves::{generate_legal_moves, validate_move};
+use notation::parse_piece_token;
+use options::{list_options, load_options_config, set_option, EngineOption};
+use packed::{unpack_boards, PackedBoard};
+use pieces::piece_type_from_symbol;
+use positions::{generate_random_position, PositionConstraints};
+use profile::build_profile_from_json;
+use puzzles::{daily, Puzzle, PuzzleSet};
+use repertoire::Repertoire;
+use save::{autosave_snapshot, load_game, restore_autosave, save_game, save_game_compressed};
+use scenarios::ScenarioRun;
+use scoring::score_run_from_json;
+use session::{
+    apply_sync_response, create_move_proof, create_sync_request, replay_move, respond_to_sync,
+    state_hash, Exhibition, MoveProof, SyncRequest, SyncResponse, Transcript,
+};
+use share::{
+    decode_game_compact, decode_share_code, encode_game_compact, encode_share_code, SharedPosition,
+};
+use simulation::simulate_game;
+use tablebase::{
+    explain, load_embedded_tablebases, load_tablebase_build, save_tablebase_build,
+    tablebase_build_step,
+};
+use types::{OffsetCoord, OffsetLayout};
+use vision::{attack_quiz, knight_path_puzzle, verify_piece_list, AttackQuiz, KnightPathPuzzle};
 
 // Global transposition table for WASM (wrapped in Mutex for thread safety)
 lazy_static::lazy_static! {
-    static ref GLOBAL_TT: Mutex<ai::TranspositionTable> = Mutex::new(ai::TranspositionTable::new(50000));
+    static ref GLOBAL_TT: Mutex<ai::TranspositionTable> =
+        Mutex::new(ai::TranspositionTable::new(EngineOption::TTSize.get() as usize));
+}
+
+/// Lock the global transposition table. A panic while it was held cannot
+/// leave it inconsistent in a way that matters (entries are only hints), so a
+/// poisoned lock is recovered rather than propagated.
+fn global_tt() -> MutexGuard<'static, ai::TranspositionTable> {
+    GLOBAL_TT.lock().unwrap_or_else(PoisonError::into_inner)
 }
 
 // ============================================================================
 // WASM Bindings
 // ============================================================================
 
-/// Initialize panic hook for better error messages in browser console
+/// Initialize panic hook for better error messages in browser console, and
+/// load any tablebases embedded in the build
 #[wasm_bindgen(start)]
 pub fn init() {
     #[cfg(feature = "console_error_panic_hook")]
     console_error_panic_hook::set_once();
+    load_embedded_tablebases();
+}
+
+/// Forward engine tracing to `console.log`, showing events up to `level`
+/// ("error", "warn", "info", "debug" or "trace"). Returns false if the build
+/// lacks the `trace` feature, the level is unknown or tracing is already on.
+#[wasm_bindgen]
+pub fn wasm_init_tracing(level: &str) -> bool {
+    init_tracing(level)
 }
 
 /// WASM wrapper for the game state
 #[wasm_bindgen]
 pub struct WasmGame {
     state: GameState,
+    /// Analysis of the current position, cleared whenever the game moves
+    analysis: AnalysisCache,
+    /// Swindle mode for AI moves (see `set_swindle_mode`)
+    swindle: bool,
+    /// Device calibration limiting the AI's searches (see `calibrate`)
+    calibration: Option<ai::Calibration>,
+    /// Sequence number of the last autosave snapshot
+    autosave_seq: u32,
+    /// Search depth of the coach's reports, if enabled (see `set_coach`)
+    coach_depth: Option<i32>,
+    /// The coach's report on the last move, if the user played it
+    coach_report: Option<CoachReport>,
 }
 
 #[wasm_bindgen]
@@ -51,11 +212,143 @@ impl WasmGame {
     /// Create a new game with standard starting position
     #[wasm_bindgen(constructor)]
     pub fn new() -> Self {
-        Self {
-            state: create_new_game(),
+        Self::from_state(create_new_game())
+    }
+
+    /// Create a game from a HexFEN position, with the half-move clock, move
+    /// number and repetition history if given (see `get_hexfen`).
+    /// Returns undefined if the HexFEN is invalid.
+    pub fn from_hexfen(fen: &str) -> Option<WasmGame> {
+        parse_game_hexfen(fen).map(Self::from_state)
+    }
+
+    /// Restore a game saved with `save`. Returns undefined if the JSON is
+    /// invalid.
+    pub fn load(json: &str) -> Option<WasmGame> {
+        load_game(json).map(Self::from_state)
+    }
+
+    /// Create a game from a share code (see `get_share_code`), with the
+    /// shared position and side to move but no history. Returns undefined if
+    /// the code is invalid.
+    pub fn from_share_code(code: &str) -> Option<WasmGame> {
+        decode_share_code(code)
+            .map(|shared| Self::from_state(create_game_from_position(shared.board, shared.turn)))
+    }
+
+    /// Get a short URL-safe code of the position, side to move and last move
+    /// for sharing in a link (see `wasm_decode_share_code`). Returns undefined
+    /// if the board cannot be encoded.
+    pub fn get_share_code(&self) -> Option<String> {
+        encode_share_code(&SharedPosition::from_game(&self.state))
+    }
+
+    /// Replay a game from a compact record (see `get_compact_game`).
+    /// Resignations and other endings without a move are not recorded.
+    /// Returns undefined for malformed data.
+    pub fn from_compact_game(bytes: &[u8]) -> Option<WasmGame> {
+        decode_game_compact(bytes).map(Self::from_state)
+    }
+
+    /// Save the full game state (moves, clocks and repetition history) as JSON
+    pub fn save(&self) -> String {
+        save_game(&self.state)
+    }
+
+    /// Save the game like `save`, with its moves compressed to a fraction of
+    /// the size for long games and archives. `load` reads either form
+    pub fn save_compressed(&self) -> String {
+        save_game_compressed(&self.state)
+    }
+
+    /// Get a small binary snapshot of the game (position, counters, recent
+    /// repetition history and last moves) to write after every move. Each
+    /// snapshot has a higher sequence number than the last, so the newest
+    /// valid one can be restored with `restore_autosave`. Returns undefined
+    /// if the board cannot be packed
+    pub fn autosave_blob(&mut self) -> Option<Vec<u8>> {
+        let seq = self.autosave_seq.checked_add(1)?;
+        let blob = autosave_snapshot(&self.state, seq)?;
+        self.autosave_seq = seq;
+        Some(blob)
+    }
+
+    /// Restore a game from an autosave snapshot, with only its last moves
+    /// for undo. Later snapshots continue its sequence. Returns undefined for
+    /// a torn, corrupt or unknown snapshot.
+    pub fn restore_autosave(bytes: &[u8]) -> Option<WasmGame> {
+        let snapshot = restore_autosave(bytes)?;
+        let mut game = Self::from_state(snapshot.state);
+        game.autosave_seq = snapshot.seq;
+        Some(game)
+    }
+
+    /// Save the game signed with `key`, so that `load_signed` detects any
+    /// edits (requires the `signed-saves` feature)
+    #[cfg(feature = "signed-saves")]
+    pub fn save_signed(&self, key: &str) -> String {
+        save::save_signed(&self.state, key.as_bytes())
+    }
+
+    /// Restore a game saved with `save_signed` under the same key. Returns
+    /// undefined if the save was tampered with, signed with another key or is
+    /// invalid (requires the `signed-saves` feature).
+    #[cfg(feature = "signed-saves")]
+    pub fn load_signed(json: &str, key: &str) -> Option<WasmGame> {
+        save::load_signed(json, key.as_bytes())
+            .ok()
+            .map(Self::from_state)
+    }
+
+    /// Create a new game under a named variant ("standard", "crazyhouse",
+    /// "kingOfTheHill", "racingKings", "boulder").
+    /// Returns undefined for an unknown variant.
+    pub fn with_variant(name: &str) -> Option<WasmGame> {
+        Variant::from_name(name).map(|variant| Self::from_state(create_variant_game(variant)))
+    }
+
+    /// Get the variant as JSON
+    pub fn get_variant(&self) -> String {
+        serde_json::to_string(&self.state.variant).unwrap_or_else(|_| "null".to_string())
+    }
+
+    /// Set how stalemates are scored: "draw", "stalematerWins",
+    /// "stalematerLoses", or the stalemating side's share of the point as
+    /// "1/4" or "3/4". A game that ended in stalemate is scored again.
+    /// Returns false for an unknown rule.
+    pub fn set_stalemate_rule(&mut self, name: &str) -> bool {
+        match StalemateRule::from_name(name) {
+            Some(rule) => {
+                self.set_state(with_stalemate_rule(&self.state, rule));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Get the stalemate rule as JSON
+    pub fn get_stalemate_rule(&self) -> String {
+        serde_json::to_string(&self.state.stalemate_rule).unwrap_or_else(|_| "null".to_string())
+    }
+
+    /// Set optional win conditions from JSON, e.g.
+    /// `{"bare_king":true,"capture_all":"Knight"}`; `{}` removes them. A game
+    /// the new conditions decide ends. Returns false for invalid conditions.
+    pub fn set_win_conditions(&mut self, json: &str) -> bool {
+        match serde_json::from_str::<WinConditions>(json) {
+            Ok(conditions) if conditions.is_valid() => {
+                self.set_state(with_win_conditions(&self.state, conditions));
+                true
+            }
+            _ => false,
         }
     }
 
+    /// Get the optional win conditions as JSON
+    pub fn get_win_conditions(&self) -> String {
+        serde_json::to_string(&self.state.win_conditions).unwrap_or_else(|_| "null".to_string())
+    }
+
     /// Get the current turn as a string ("white" or "black")
     pub fn get_turn(&self) -> String {
         match self.state.turn {
@@ -69,11 +362,33 @@ impl WasmGame {
         serde_json::to_string(&self.state.status).unwrap_or_else(|_| "\"ongoing\"".to_string())
     }
 
+    /// Get the result of a finished game as JSON { winner, termination,
+    /// reason, score }, or null while the game is ongoing. `winner` is
+    /// "White", "Black" or null for a draw; `termination` is "checkmate",
+    /// "stalemate", "drawRule", "resignation", "timeout", "variant" or
+    /// "adjudication"; `score` is "1-0", "0-1" or "1/2-1/2".
+    pub fn get_result(&self) -> String {
+        game_result(&self.state)
+            .and_then(|result| serde_json::to_string(&result).ok())
+            .unwrap_or_else(|| "null".to_string())
+    }
+
     /// Get the board state as JSON (map of "q,r" -> piece)
     pub fn get_board(&self) -> String {
         serde_json::to_string(&self.state.board).unwrap_or_else(|_| "{}".to_string())
     }
 
+    /// Get the board's pieces at offset coordinates as JSON
+    /// [{ col, row, piece }] for a layout ("oddQ", "evenQ", "oddR" or
+    /// "evenR"), or null for an unknown layout
+    pub fn get_board_offset(&self, layout: &str) -> String {
+        OffsetLayout::from_name(layout)
+            .and_then(|layout| {
+                serde_json::to_string(&board_to_offset(&self.state.board, layout)).ok()
+            })
+            .unwrap_or_else(|| "null".to_string())
+    }
+
     /// Get all legal moves as JSON array
     pub fn get_legal_moves(&self) -> String {
         let moves = get_legal_moves(&self.state);
@@ -92,7 +407,270 @@ impl WasmGame {
         let to = HexCoord::new(to_q, to_r);
 
         if let Some(new_state) = make_move(&self.state, from, to) {
-            self.state = new_state;
+            self.play_human_move(new_state);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Make a move that promotes the moving piece. `promotion` is the HexFEN
+    /// letter of the piece to promote to (e.g. "Q", or "E" for a promoted
+    /// knight in the shogi variant); its case is ignored.
+    /// Returns true if the move was successful
+    pub fn make_move_with_promotion(
+        &mut self,
+        from_q: i32,
+        from_r: i32,
+        to_q: i32,
+        to_r: i32,
+        promotion: &str,
+    ) -> bool {
+        let mut chars = promotion.chars();
+        let promotion = match (chars.next().and_then(piece_type_from_symbol), chars.next()) {
+            (Some(piece_type), None) => piece_type,
+            _ => return false,
+        };
+        let from = HexCoord::new(from_q, from_r);
+        let to = HexCoord::new(to_q, to_r);
+
+        if let Some(new_state) = make_move_with_promotion(&self.state, from, to, Some(promotion)) {
+            self.play_human_move(new_state);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Make a move, reporting why it failed. `promotion` is a HexFEN letter
+    /// as for `make_move_with_promotion`, or empty for none.
+    /// Returns JSON { ok: true } or { ok: false, error, message } where error
+    /// is a reason code such as "notYourPiece", "movesIntoCheck" or
+    /// "gameOver", and message that key with parameters { from, to, piece }
+    /// (see `wasm_format_message`).
+    pub fn try_move(
+        &mut self,
+        from_q: i32,
+        from_r: i32,
+        to_q: i32,
+        to_r: i32,
+        promotion: &str,
+    ) -> String {
+        let Some(promotion) = promotion_arg(promotion) else {
+            return move_error(Message::new("unknownPromotion"));
+        };
+        let from = HexCoord::new(from_q, from_r);
+        let to = HexCoord::new(to_q, to_r);
+
+        match try_make_move(&self.state, from, to, promotion) {
+            Ok(new_state) => {
+                self.play_human_move(new_state);
+                serde_json::json!({ "ok": true }).to_string()
+            }
+            Err(reason) => move_error(move_message(&reason, &self.state.board, from, to)),
+        }
+    }
+
+    /// Describe a move for a screen reader before it is played. `promotion`
+    /// is as for `try_move`. Returns JSON { color, piece, from, to, drop,
+    /// captured, promotion, boulder, check, checkmate } with pieces and
+    /// colors as message keys and cells by name, or null if the move is not
+    /// legal.
+    pub fn describe_move(
+        &self,
+        from_q: i32,
+        from_r: i32,
+        to_q: i32,
+        to_r: i32,
+        promotion: &str,
+    ) -> String {
+        let from = HexCoord::new(from_q, from_r);
+        let to = HexCoord::new(to_q, to_r);
+        promotion_arg(promotion)
+            .zip(self.state.board.get(&from.to_key()))
+            .and_then(|(promotion, &piece)| {
+                let mut mv = Move::new(piece, from, to);
+                mv.promotion = promotion;
+                describe_move(&self.state, &mv)
+            })
+            .and_then(|description| serde_json::to_string(&description).ok())
+            .unwrap_or_else(|| "null".to_string())
+    }
+
+    /// Describe the last move played (see `describe_move`), or null before
+    /// the first move
+    pub fn describe_last_move(&self) -> String {
+        describe_last_move(&self.state)
+            .and_then(|description| serde_json::to_string(&description).ok())
+            .unwrap_or_else(|| "null".to_string())
+    }
+
+    /// Check the game for internal contradictions (debug builds only).
+    /// Returns JSON array of violation messages (see `wasm_format_message`),
+    /// empty when the state is sound.
+    #[cfg(debug_assertions)]
+    pub fn check_invariants(&self) -> String {
+        serde_json::to_string(&self.state.check_invariants()).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Get the events of the last move (after `make_move`, `make_ai_move`,
+    /// `drop_piece` or `place_boulder`) as JSON
+    /// { capture, check, promotion, drop, boulder, game_end }, or null before
+    /// the first move. With the coach on, a move the user made also has
+    /// `coach`: { ply, mv, evalBefore, evalAfter, evalChange, better,
+    /// threatCreated, threatMissed, advice } (see `set_coach`).
+    pub fn get_last_move_events(&self) -> String {
+        let Some(events) = get_last_move_events(&self.state) else {
+            return "null".to_string();
+        };
+        let mut json = serde_json::to_value(events).unwrap_or_default();
+        if let (Some(report), Some(fields)) = (&self.coach_report, json.as_object_mut()) {
+            fields.insert(
+                "coach".to_string(),
+                serde_json::to_value(report).unwrap_or_default(),
+            );
+        }
+        json.to_string()
+    }
+
+    /// Turn on the coach, reporting on each move the user makes with a
+    /// search to `depth` (see `get_last_move_events`); 0 turns it off.
+    pub fn set_coach(&mut self, depth: u32) {
+        self.coach_depth = (depth > 0).then_some(depth as i32);
+        self.coach_report = None;
+    }
+
+    /// Get the changes from this game's board to another game's board as JSON
+    /// array of deltas ({"Removed": {cell, piece}}, {"Moved": {from, to, piece}}
+    /// or {"Added": {cell, piece}}), for incremental rendering and syncing.
+    pub fn diff_with(&self, other: &WasmGame) -> String {
+        serde_json::to_string(&diff(&self.state.board, &other.state.board))
+            .unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Get the hash identifying the current position for session syncing
+    pub fn get_sync_hash(&self) -> String {
+        state_hash(&self.state)
+    }
+
+    /// Get the sync request to send to the server when (re)connecting as JSON
+    pub fn sync_request(&self) -> String {
+        serde_json::to_string(&create_sync_request(&self.state))
+            .unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// Answer a client's sync request (JSON) from this (server) game.
+    /// Returns the response as JSON, or "null" if the request is malformed
+    pub fn sync_response(&self, request_json: &str) -> String {
+        match serde_json::from_str::<SyncRequest>(request_json) {
+            Ok(request) => serde_json::to_string(&respond_to_sync(&self.state, &request))
+                .unwrap_or_else(|_| "null".to_string()),
+            Err(_) => "null".to_string(),
+        }
+    }
+
+    /// Apply a sync response (JSON) from the server.
+    /// Returns false if it does not apply or verify; request a full sync then
+    pub fn apply_sync(&mut self, response_json: &str) -> bool {
+        let Ok(response) = serde_json::from_str::<SyncResponse>(response_json) else {
+            return false;
+        };
+        match apply_sync_response(&self.state, &response) {
+            Some(state) => {
+                self.set_state(state);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Build the proof to submit for a move from the current position,
+    /// without making it. `promotion` is a HexFEN letter, or empty for none.
+    /// Returns JSON { seq, mv, position_hash }, or null if the move is illegal.
+    pub fn prove_move(
+        &self,
+        from_q: i32,
+        from_r: i32,
+        to_q: i32,
+        to_r: i32,
+        promotion: &str,
+    ) -> String {
+        promotion_arg(promotion)
+            .and_then(|promotion| {
+                let from = HexCoord::new(from_q, from_r);
+                let to = HexCoord::new(to_q, to_r);
+                try_make_move(&self.state, from, to, promotion).ok()
+            })
+            .and_then(|after| after.history.last().cloned())
+            .and_then(|mv| create_move_proof(&self.state, &mv))
+            .and_then(|proof| serde_json::to_string(&proof).ok())
+            .unwrap_or_else(|| "null".to_string())
+    }
+
+    /// Apply an opponent's move proof (JSON). Returns false, leaving the game
+    /// unchanged, if it is for another move number, illegal, or commits to a
+    /// different position than the one it produces here.
+    pub fn apply_move_proof(&mut self, proof_json: &str) -> bool {
+        let Ok(proof) = serde_json::from_str::<MoveProof>(proof_json) else {
+            return false;
+        };
+        if proof.seq != self.state.history.len() {
+            return false;
+        }
+        match replay_move(&self.state, &proof.mv) {
+            Some(state) if state_hash(&state) == proof.position_hash => {
+                self.set_state(state);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Get the pieces in hand as JSON ({ white: {type: count}, black: {...} })
+    pub fn get_hands(&self) -> String {
+        serde_json::to_string(&self.state.hands).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// Drop a piece from the current player's hand (drop variants only).
+    /// `token` is a HexFEN piece token such as "N" or "La"; its case is ignored.
+    /// Returns true if the drop was successful
+    pub fn drop_piece(&mut self, token: &str, q: i32, r: i32) -> bool {
+        let piece = match parse_piece_token(token) {
+            Some(piece) => Piece {
+                color: self.state.turn,
+                ..piece
+            },
+            None => return false,
+        };
+
+        if let Some(new_state) = make_drop(&self.state, piece, HexCoord::new(q, r)) {
+            self.play_human_move(new_state);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Get the turn phase as JSON ("Move" or "PlaceBoulder")
+    pub fn get_phase(&self) -> String {
+        serde_json::to_string(&self.state.phase).unwrap_or_else(|_| "\"Move\"".to_string())
+    }
+
+    /// Get the cells the boulder can be placed on as JSON array of [q, r] pairs
+    /// (empty unless a placement is pending)
+    pub fn get_boulder_placements(&self) -> String {
+        let cells: Vec<[i32; 2]> = get_boulder_placements(&self.state)
+            .iter()
+            .map(|c| [c.q, c.r])
+            .collect();
+        serde_json::to_string(&cells).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Place the boulder after a move (boulder variant), ending the turn.
+    /// Returns true if the placement was successful
+    pub fn place_boulder(&mut self, q: i32, r: i32) -> bool {
+        if let Some(new_state) = make_boulder_placement(&self.state, HexCoord::new(q, r)) {
+            self.set_state(new_state);
             true
         } else {
             false
@@ -101,7 +679,29 @@ impl WasmGame {
 
     /// Resign the game for the current player
     pub fn resign(&mut self) {
-        self.state = resign(&self.state, self.state.turn);
+        self.set_state(resign(&self.state, self.state.turn));
+    }
+
+    /// Get how many times the current position has occurred in the game,
+    /// itself included (3 draws by repetition)
+    pub fn get_repetition_count(&self) -> u32 {
+        position_occurrences(&self.state) as u32
+    }
+
+    /// Check if the current player may claim a draw (fifty-move rule)
+    pub fn can_claim_draw(&self) -> bool {
+        claimable_draw(&self.state).is_some()
+    }
+
+    /// Claim a draw. Returns false if no draw is claimable.
+    pub fn claim_draw(&mut self) -> bool {
+        match claim_draw(&self.state) {
+            Some(state) => {
+                self.set_state(state);
+                true
+            }
+            None => false,
+        }
     }
 
     /// Get move history as JSON
@@ -109,11 +709,67 @@ impl WasmGame {
         serde_json::to_string(&self.state.history).unwrap_or_else(|_| "[]".to_string())
     }
 
+    /// Get the move history's cells at offset coordinates as JSON
+    /// [{ from, to, boulder }] for a layout ("oddQ", "evenQ", "oddR" or
+    /// "evenR"), or null for an unknown layout
+    pub fn get_history_offset(&self, layout: &str) -> String {
+        OffsetLayout::from_name(layout)
+            .and_then(|layout| {
+                serde_json::to_string(&moves_to_offset(&self.state.history, layout)).ok()
+            })
+            .unwrap_or_else(|| "null".to_string())
+    }
+
+    /// Get the post-game summary (see `wasm_game_summary`) of this game
+    pub fn get_summary(&self) -> String {
+        summarize(&PlayedGame::from_state(&self.state)).to_json()
+    }
+
+    /// Get the `k` most critical positions (see `wasm_critical_positions`)
+    /// of this game, searched to `depth`
+    pub fn get_critical_positions(&self, depth: i32, k: u32) -> String {
+        let positions = critical_positions(&PlayedGame::from_state(&self.state), depth, k as usize);
+        serde_json::to_string(&positions).unwrap_or_else(|_| "[]".to_string())
+    }
+
     /// Get current move number
     pub fn get_move_number(&self) -> u32 {
         self.state.move_number
     }
 
+    /// Export the moves as a table for spreadsheets, one row per half-move
+    /// with ply, moveNumber, color, san, from, to, capture, check, clockMs,
+    /// eval (these two empty here), phase ("opening", "middlegame" or
+    /// "endgame") and phaseChange (the move entered a new phase): `format`
+    /// "csv" gives CSV with a header row, "json" a JSON array of rows.
+    /// Returns undefined for an unknown format
+    pub fn export_history(&self, format: &str) -> Option<String> {
+        let format = TableFormat::parse(format)?;
+        Some(export_history_table(
+            &HistoryRecord::new(&self.state),
+            format,
+        ))
+    }
+
+    /// Get the current position as a HexFEN string with the half-move clock,
+    /// move number and repetition history
+    pub fn get_hexfen(&self) -> String {
+        game_to_hexfen(&self.state)
+    }
+
+    /// Get the board as packed bytes (see `wasm_unpack_board`), a compact
+    /// alternative to `get_board` for network transfer
+    pub fn get_packed_board(&self) -> Vec<u8> {
+        self.state.board.to_packed_bytes().unwrap_or_default()
+    }
+
+    /// Get the game's moves as a compact record small enough for a QR code
+    /// (see `from_compact_game`). Returns an empty array for a game that did
+    /// not start from its variant's starting position.
+    pub fn get_compact_game(&self) -> Vec<u8> {
+        encode_game_compact(&self.state).unwrap_or_default()
+    }
+
     /// Check if a specific move is legal
     pub fn is_move_legal(&self, from_q: i32, from_r: i32, to_q: i32, to_r: i32) -> bool {
         let from = HexCoord::new(from_q, from_r);
@@ -122,6 +778,14 @@ impl WasmGame {
         validation.legal
     }
 
+    /// Validate a batch of moves, given as a JSON array of
+    /// [from_q, from_r, to_q, to_r], without playing them. Returns a JSON
+    /// array of { legal, reason, capture } in the same order (reasons as for
+    /// `try_move`), or null if the JSON is invalid.
+    pub fn validate_many(&self, moves_json: &str) -> String {
+        validate_many_json(&self.state, moves_json)
+    }
+
     /// Get legal moves for a specific piece as JSON
     pub fn get_legal_moves_for_piece(&self, q: i32, r: i32) -> String {
         let coord = HexCoord::new(q, r);
@@ -134,24 +798,107 @@ impl WasmGame {
         "[]".to_string()
     }
 
-    /// Get AI move for the current player.
-    /// Difficulty: "easy", "medium", or "hard"
-    /// Returns JSON with { from: [q, r], to: [q, r], score: number } or null if no move.
-    pub fn get_ai_move(&self, difficulty: &str) -> String {
-        let diff = match difficulty {
-            "easy" => ai::AIDifficulty::Easy,
-            "hard" => ai::AIDifficulty::Hard,
-            _ => ai::AIDifficulty::Medium,
+    /// Get legal moves for a specific piece as JSON, each annotated with
+    /// { capture, defenders, defended, gives_check } alongside the move fields
+    /// so UIs can color-code risky destinations.
+    pub fn get_move_targets_annotated(&self, q: i32, r: i32) -> String {
+        let coord = HexCoord::new(q, r);
+        let moves: Vec<Move> = get_legal_moves(&self.state)
+            .into_iter()
+            .filter(|mv| !mv.is_drop && mv.from == coord)
+            .collect();
+        let annotated = annotate_moves(&self.state.board, moves);
+        serde_json::to_string(&annotated).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Get the cells attacked by a color ("white" or "black") as JSON map of
+    /// "q,r" -> number of attackers.
+    pub fn get_attack_map(&self, color: &str) -> String {
+        let color = match color {
+            "black" => Color::Black,
+            _ => Color::White,
+        };
+        serde_json::to_string(&build_attack_map(&self.state.board, color))
+            .unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// Check a blindfold player's claimed piece locations, given as JSON in
+    /// the form of `get_board`. With color "white" or "black" only that
+    /// side's pieces count as missed (any other value counts both). Returns
+    /// JSON { correct, wrong, missed, score } with cells as { q, r }, or null
+    /// if the JSON is invalid.
+    pub fn verify_piece_list(&self, claimed_json: &str, color: &str) -> String {
+        let color = match color {
+            "white" => Some(Color::White),
+            "black" => Some(Color::Black),
+            _ => None,
         };
+        serde_json::from_str::<BoardState>(claimed_json)
+            .ok()
+            .and_then(|claimed| {
+                serde_json::to_string(&verify_piece_list(&self.state.board, &claimed, color)).ok()
+            })
+            .unwrap_or_else(|| "null".to_string())
+    }
+
+    /// Get a "which cells does this piece attack?" quiz on a piece picked
+    /// from `seed`, as JSON { cell, piece, attacked } (the answer included,
+    /// for revealing), or null if no piece qualifies
+    pub fn get_attack_quiz(&self, seed: u32) -> String {
+        attack_quiz(&self.state.board, u64::from(seed))
+            .and_then(|quiz| serde_json::to_string(&quiz).ok())
+            .unwrap_or_else(|| "null".to_string())
+    }
+
+    /// Grade an answer to the attack quiz on the piece at (q, r), given as a
+    /// JSON array of [q, r] pairs. Returns JSON { correct, wrong, missed,
+    /// score }, or null if the cell has no piece or the JSON is invalid.
+    pub fn grade_attack_quiz(&self, q: i32, r: i32, answer_json: &str) -> String {
+        let quiz = AttackQuiz::for_piece(&self.state.board, HexCoord::new(q, r));
+        let answer = serde_json::from_str::<Vec<[i32; 2]>>(answer_json).ok();
+        quiz.zip(answer)
+            .and_then(|(quiz, answer)| {
+                let cells: Vec<HexCoord> =
+                    answer.iter().map(|&[q, r]| HexCoord::new(q, r)).collect();
+                serde_json::to_string(&quiz.grade(&cells)).ok()
+            })
+            .unwrap_or_else(|| "null".to_string())
+    }
 
-        let mut tt = GLOBAL_TT.lock().unwrap();
-        let result = ai::get_ai_move(&self.state.board, self.state.turn, diff, &mut tt);
+    /// Get AI move for the current player.
+    /// Difficulty: "easy", "medium" (human-like play), or "hard"
+    /// Returns JSON with { from: [q, r], to: [q, r], boulder: [q, r] | null, score: number,
+    /// scoreKind: "centipawns" | "tablebase" } or null if no move. A "tablebase"
+    /// score is exact: 0 for a draw, or a win or loss. Repeated requests for the position are answered
+    /// from its deepest analysis with the same options.
+    pub fn get_ai_move(&mut self, difficulty: &str) -> String {
+        let options = self.ai_options(difficulty);
+        let result = match self.analysis.search(&self.state, &options) {
+            Some(result) => result.clone(),
+            None => {
+                let mut tt = global_tt();
+                tt.set_game_rules(&self.state);
+                let result = ai::get_ai_move_with_options(
+                    &self.state.board,
+                    self.state.turn,
+                    &options,
+                    &mut tt,
+                    &self.state.position_history,
+                    self.state.variant,
+                );
+                self.analysis
+                    .store_search(&self.state, &options, result.clone());
+                result
+            }
+        };
 
         if let Some(mv) = result.best_move {
             serde_json::json!({
                 "from": [mv.from.q, mv.from.r],
                 "to": [mv.to.q, mv.to.r],
+                "boulder": mv.boulder.map(|b| [b.q, b.r]),
                 "score": result.score,
+                "scoreKind": result.score_kind,
                 "nodes": result.stats.nodes_searched,
             })
             .to_string()
@@ -160,27 +907,268 @@ impl WasmGame {
         }
     }
 
+    /// Start a search of the current position to `max_depth` that runs a
+    /// step at a time and can be cancelled (see `WasmSearch`)
+    pub fn start_search(&self, max_depth: i32) -> WasmSearch {
+        WasmSearch::new(&self.state, max_depth)
+    }
+
+    /// Search for the best move other than the listed ones, given as a JSON
+    /// array of [from_q, from_r, to_q, to_r] (excluding every promotion and
+    /// boulder placement of each). Returns JSON { from, to, boulder, score,
+    /// scoreKind, nodes }, or null if the JSON is invalid or every move is excluded.
+    pub fn analyze_excluding(&self, excluded_json: &str, depth: i32) -> String {
+        let Ok(listed) = serde_json::from_str::<Vec<[i32; 4]>>(excluded_json) else {
+            return "null".to_string();
+        };
+        let excluded: Vec<Move> = get_legal_moves(&self.state)
+            .into_iter()
+            .filter(|mv| {
+                listed.iter().any(|&[fq, fr, tq, tr]| {
+                    mv.from == HexCoord::new(fq, fr) && mv.to == HexCoord::new(tq, tr)
+                })
+            })
+            .map(|mv| Move {
+                boulder: None,
+                ..mv
+            })
+            .collect();
+        let result = ai::analyze_excluding(&self.state, &excluded, depth);
+        match result.best_move {
+            Some(mv) => serde_json::json!({
+                "from": [mv.from.q, mv.from.r],
+                "to": [mv.to.q, mv.to.r],
+                "boulder": mv.boulder.map(|b| [b.q, b.r]),
+                "score": result.score,
+                "scoreKind": result.score_kind,
+                "nodes": result.stats.nodes_searched,
+            })
+            .to_string(),
+            None => "null".to_string(),
+        }
+    }
+
+    /// Get the engine's plan from a search to `depth` as annotations JSON
+    /// { arrows, cells } for a "show engine plan" toggle: at most
+    /// `max_arrows`, the best move solid and the expected continuation
+    /// dashed (drops as highlighted cells)
+    pub fn get_engine_plan(&self, depth: i32, max_arrows: u32) -> String {
+        let pv = principal_variation(&self.state, depth, max_arrows as usize);
+        serde_json::to_string(&pv_to_arrows(&pv, max_arrows as usize))
+            .unwrap_or_else(|_| "null".to_string())
+    }
+
+    /// Estimate how hard the position is for the side to move, searching
+    /// every legal move to `depth`, for grading puzzles and picking
+    /// instructive moments. Returns JSON { bestMove, bestMoveGap,
+    /// nearBestMoves, legalMoves, tacticalDensity, score, grade } with score
+    /// 0-100 and grade "easy", "medium" or "hard"
+    pub fn get_difficulty(&self, depth: i32) -> String {
+        serde_json::to_string(&difficulty(&self.state.board, self.state.turn, depth))
+            .unwrap_or_else(|_| "null".to_string())
+    }
+
+    /// Get the opposition between the kings, for endgame lessons. Returns
+    /// JSON { kind, holder, distance } with kind "direct" or "distant" and
+    /// holder the side not to move, or null if the kings are not in
+    /// opposition
+    pub fn get_opposition(&self) -> String {
+        hex_opposition(&self.state.board, self.state.turn)
+            .and_then(|opposition| serde_json::to_string(&opposition).ok())
+            .unwrap_or_else(|| "null".to_string())
+    }
+
+    /// Get how confined a color's ("white" or "black") king is. Returns JSON
+    /// { king, ring, region } with ring 0 (center) to 4 (edge) and region the
+    /// cells the king can walk to unattacked, or null without a king
+    pub fn get_king_confinement(&self, color: &str) -> String {
+        let color = match color {
+            "black" => Color::Black,
+            _ => Color::White,
+        };
+        king_confinement(&self.state.board, color)
+            .and_then(|confinement| serde_json::to_string(&confinement).ok())
+            .unwrap_or_else(|| "null".to_string())
+    }
+
+    /// Grade a defensive move (not played) by how well it keeps the king
+    /// active, for the endgame trainer. Returns JSON { score, best, worst,
+    /// accuracy } with accuracy 0-100, or null for an illegal move
+    pub fn grade_defense(&self, from_q: i32, from_r: i32, to_q: i32, to_r: i32) -> String {
+        grade_defense(
+            &self.state.board,
+            HexCoord::new(from_q, from_r),
+            HexCoord::new(to_q, to_r),
+        )
+        .and_then(|grade| serde_json::to_string(&grade).ok())
+        .unwrap_or_else(|| "null".to_string())
+    }
+
+    /// Whether the position is lost for a color ("white" or "black"),
+    /// searched to `depth`, for "this position is lost" prompts. Returns JSON
+    /// { reason, eval, mateIn, message } with reason "tablebaseLoss",
+    /// "mateIncoming" or "lostEval" and eval from that color's side, or null
+    /// if the position is not lost
+    pub fn get_resign_advisory(&self, color: &str, depth: i32) -> String {
+        let color = match color {
+            "black" => Color::Black,
+            _ => Color::White,
+        };
+        is_resignable(&self.state, color, depth)
+            .and_then(|advisory| serde_json::to_string(&advisory).ok())
+            .unwrap_or_else(|| "null".to_string())
+    }
+
+    /// Explain how this tablebase-won endgame is won, for endgame lessons.
+    /// Returns JSON { winner, dtm, plan, line } with the plan as messages,
+    /// or null for a draw or a position with no loaded tablebase
+    pub fn get_endgame_explanation(&self) -> String {
+        explain(&self.state.board, self.state.turn)
+            .and_then(|explanation| serde_json::to_string(&explanation).ok())
+            .unwrap_or_else(|| "null".to_string())
+    }
+
+    /// Measure this device's search speed for about `ms_budget` milliseconds
+    /// and limit each difficulty to the depth it can search in its target
+    /// time (easy 0.25s, medium 1s, hard 3s), never deeper than usual; 0
+    /// removes the limits. Returns JSON { nodesPerSecond, baseNodes,
+    /// branchingFactor, easy, medium, hard } with each level's { depth,
+    /// timeLimitMs }, or null after removing them.
+    pub fn calibrate(&mut self, ms_budget: u32) -> String {
+        self.analysis.clear();
+        if ms_budget == 0 {
+            self.calibration = None;
+            return "null".to_string();
+        }
+        let calibration = ai::calibrate(ms_budget as u64);
+        let json = serde_json::to_string(&calibration).unwrap_or_else(|_| "null".to_string());
+        self.calibration = Some(calibration);
+        json
+    }
+
+    /// Turn swindle mode on or off. In a position the AI considers lost, it
+    /// then plays for traps that give the opponent the most chances to go
+    /// wrong, instead of the longest defense.
+    pub fn set_swindle_mode(&mut self, enabled: bool) {
+        if self.swindle != enabled {
+            self.swindle = enabled;
+            self.analysis.clear();
+        }
+    }
+
     /// Make the AI move for the current player.
     /// Returns true if a move was made, false if no legal moves.
     pub fn make_ai_move(&mut self, difficulty: &str) -> bool {
-        let diff = match difficulty {
-            "easy" => ai::AIDifficulty::Easy,
-            "hard" => ai::AIDifficulty::Hard,
-            _ => ai::AIDifficulty::Medium,
-        };
-
-        let mut tt = GLOBAL_TT.lock().unwrap();
-        let result = ai::get_ai_move(&self.state.board, self.state.turn, diff, &mut tt);
+        let options = self.ai_options(difficulty);
+        let mut tt = global_tt();
+        tt.set_game_rules(&self.state);
+        let result = ai::get_ai_move_with_options(
+            &self.state.board,
+            self.state.turn,
+            &options,
+            &mut tt,
+            &self.state.position_history,
+            self.state.variant,
+        );
 
         if let Some(mv) = result.best_move {
-            if let Some(new_state) = make_move(&self.state, mv.from, mv.to) {
-                self.state = new_state;
+            if let Some(new_state) = replay_move(&self.state, &mv) {
+                self.set_state(new_state);
                 return true;
             }
         }
         false
     }
 
+    /// Make a move with a named bot ("random", "capture", "greedy",
+    /// "knightBlind", "humanlike", "mcts", "alphabeta" or "consult"); the
+    /// weak bots suit beginners. Their random choices are seeded by the
+    /// position, so replays are repeatable. Returns true if the bot moved
+    pub fn make_bot_move(&mut self, name: &str) -> bool {
+        let seed = u64::from_str_radix(&state_hash(&self.state), 16).unwrap_or(0);
+        let Some(mut bot) = create_bot(name, seed) else {
+            return false;
+        };
+        let next = bot
+            .choose_move(&self.state, &SearchLimits::default())
+            .and_then(|mv| replay_move(&self.state, &mv));
+        match next {
+            Some(state) => {
+                self.set_state(state);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Ask a weighted panel of bots (JSON `[{bot, weight}]`, any names
+    /// accepted by `make_bot_move`) for a move without playing it. Returns
+    /// JSON `{move: {from, to, boulder} | null, votes: [{bot, weight, from,
+    /// to}]}`, or null if the panel is invalid (an unknown bot, or a weight
+    /// that is not positive).
+    pub fn consult(&self, panel_json: &str) -> String {
+        #[derive(serde::Deserialize)]
+        struct Member {
+            bot: String,
+            weight: f64,
+        }
+        let Ok(members) = serde_json::from_str::<Vec<Member>>(panel_json) else {
+            return "null".to_string();
+        };
+        let seed = u64::from_str_radix(&state_hash(&self.state), 16).unwrap_or(0);
+        let mut panel = ConsultationBot::new();
+        for member in members {
+            let Some(bot) = create_bot(&member.bot, seed) else {
+                return "null".to_string();
+            };
+            let Some(joined) = panel.with_member(bot, member.weight) else {
+                return "null".to_string();
+            };
+            panel = joined;
+        }
+
+        let chosen = panel.choose_move(&self.state, &SearchLimits::default());
+        let votes: Vec<serde_json::Value> = panel
+            .last_votes()
+            .iter()
+            .map(|vote| {
+                serde_json::json!({
+                    "bot": vote.bot,
+                    "weight": vote.weight,
+                    "from": vote.mv.as_ref().map(|mv| [mv.from.q, mv.from.r]),
+                    "to": vote.mv.as_ref().map(|mv| [mv.to.q, mv.to.r]),
+                })
+            })
+            .collect();
+        let chosen = chosen.map(|mv| {
+            serde_json::json!({
+                "from": [mv.from.q, mv.from.r],
+                "to": [mv.to.q, mv.to.r],
+                "boulder": mv.boulder.map(|b| [b.q, b.r]),
+            })
+        });
+        serde_json::json!({ "move": chosen, "votes": votes }).to_string()
+    }
+
+    /// Size the AI's tables to fit `megabytes` (at least 4): the shared
+    /// transposition table and evaluation caches are recreated smaller or
+    /// larger, and loaded tablebases evicted to fit their share. Returns false
+    /// if the budget is too small.
+    pub fn set_memory_budget(&mut self, megabytes: u32) -> bool {
+        if set_memory_budget(megabytes).is_err() {
+            return false;
+        }
+        *global_tt() = ai::TranspositionTable::new(EngineOption::TTSize.get() as usize);
+        self.analysis.clear();
+        true
+    }
+
+    /// Get estimates of the AI's memory use as JSON { ttEntries, ttBytes,
+    /// evalCacheBytes, tablebaseBytes, totalBytes, budgetBytes | null }.
+    pub fn get_memory_usage(&self) -> String {
+        serde_json::to_string(&memory_usage(&global_tt())).unwrap_or_else(|_| "null".to_string())
+    }
+
     /// Clear the AI transposition table (useful when starting a new game).
     pub fn clear_ai_cache(&self) {
         if let Ok(mut tt) = GLOBAL_TT.lock() {
@@ -190,8 +1178,56 @@ impl WasmGame {
 
     /// Get the static evaluation of the current position.
     /// Returns score from white's perspective in centipawns.
-    pub fn evaluate(&self) -> i32 {
-        ai::evaluate_position(&self.state.board)
+    pub fn evaluate(&mut self) -> i32 {
+        self.analysis.evaluation(&self.state)
+    }
+
+    /// Drop cached analysis of the current position (e.g. after changing the
+    /// loaded tablebases). Moves clear it automatically.
+    pub fn clear_analysis_cache(&mut self) {
+        self.analysis.clear();
+    }
+}
+
+impl WasmGame {
+    fn from_state(state: GameState) -> Self {
+        Self {
+            state,
+            analysis: AnalysisCache::new(),
+            swindle: false,
+            calibration: None,
+            autosave_seq: 0,
+            coach_depth: None,
+            coach_report: None,
+        }
+    }
+
+    /// Search options for a difficulty name ("easy", "hard", otherwise medium).
+    fn ai_options(&self, difficulty: &str) -> ai::AIOptions {
+        let difficulty = difficulty_arg(difficulty);
+        ai::AIOptions::new(difficulty)
+            .with_swindle(self.swindle)
+            .with_budget(
+                self.calibration
+                    .as_ref()
+                    .map(|calibration| calibration.budget(difficulty)),
+            )
+    }
+
+    /// Replace the game state, invalidating the analysis of the old position.
+    fn set_state(&mut self, state: GameState) {
+        self.state = state;
+        self.analysis.clear();
+        self.coach_report = None;
+    }
+
+    /// Play a move the user made, with the coach's report on it if enabled.
+    fn play_human_move(&mut self, state: GameState) {
+        let report = self
+            .coach_depth
+            .and_then(|depth| coach_report(&self.state, &state, depth));
+        self.set_state(state);
+        self.coach_report = report;
     }
 }
 
@@ -201,27 +1237,1177 @@ impl Default for WasmGame {
     }
 }
 
-// ============================================================================
-// Standalone WASM Functions
-// ============================================================================
-
-/// Check if a coordinate is valid on the board
+/// WASM wrapper for an analysis board: a variation tree with free editing,
+/// unlimited undo/redo and incremental engine analysis. Unlike [`WasmGame`]
+/// it never refuses a move because the game is over.
 #[wasm_bindgen]
-pub fn wasm_is_valid_cell(q: i32, r: i32) -> bool {
-    is_valid_cell(HexCoord::new(q, r))
+pub struct WasmAnalysisBoard {
+    tree: VariationTree,
+    session: AnalysisSession,
 }
 
-/// Get all valid cells as JSON array of [q, r] pairs
 #[wasm_bindgen]
-pub fn wasm_get_all_cells() -> String {
-    let cells: Vec<[i32; 2]> = get_all_cells().iter().map(|c| [c.q, c.r]).collect();
-    serde_json::to_string(&cells).unwrap_or_else(|_| "[]".to_string())
-}
+impl WasmAnalysisBoard {
+    /// Create an analysis board at the standard starting position
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self {
+            tree: VariationTree::default(),
+            session: AnalysisSession::new(),
+        }
+    }
+
+    /// Create an analysis board from a HexFEN position.
+    /// Returns undefined if the HexFEN is invalid.
+    pub fn from_hexfen(fen: &str) -> Option<WasmAnalysisBoard> {
+        let (board, turn) = parse_hexfen(fen)?;
+        Some(Self {
+            tree: VariationTree::new(board, turn, Variant::Standard),
+            session: AnalysisSession::new(),
+        })
+    }
+
+    /// Get the board state of the current node as JSON
+    pub fn get_board(&self) -> String {
+        serde_json::to_string(self.tree.board()).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// Get the side to move ("white" or "black")
+    pub fn get_turn(&self) -> String {
+        match self.tree.turn() {
+            Color::White => "white".to_string(),
+            Color::Black => "black".to_string(),
+        }
+    }
+
+    /// Get the current position as HexFEN
+    pub fn get_hexfen(&self) -> String {
+        self.tree.hexfen()
+    }
+
+    /// Check if the side to move is in check
+    pub fn is_in_check(&self) -> bool {
+        self.tree.is_in_check()
+    }
+
+    /// Get all legal moves in the current position as JSON array
+    pub fn get_legal_moves(&self) -> String {
+        serde_json::to_string(&self.tree.legal_moves()).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Play a move from the current node (adding a variation if needed).
+    /// Returns the new node id, or -1 if the move is illegal.
+    pub fn play_move(&mut self, from_q: i32, from_r: i32, to_q: i32, to_r: i32) -> i32 {
+        self.tree
+            .play(
+                HexCoord::new(from_q, from_r),
+                HexCoord::new(to_q, to_r),
+                None,
+            )
+            .map_or(-1, |id| id as i32)
+    }
+
+    /// Play a promoting move; `promotion` is a HexFEN piece letter.
+    /// Returns the new node id, or -1 if the move is illegal.
+    pub fn play_move_with_promotion(
+        &mut self,
+        from_q: i32,
+        from_r: i32,
+        to_q: i32,
+        to_r: i32,
+        promotion: &str,
+    ) -> i32 {
+        let mut chars = promotion.chars();
+        let promotion = match (chars.next().and_then(piece_type_from_symbol), chars.next()) {
+            (Some(piece_type), None) => piece_type,
+            _ => return -1,
+        };
+        self.tree
+            .play(
+                HexCoord::new(from_q, from_r),
+                HexCoord::new(to_q, to_r),
+                Some(promotion),
+            )
+            .map_or(-1, |id| id as i32)
+    }
+
+    /// Step back to the parent node. Returns false at the root.
+    pub fn undo(&mut self) -> bool {
+        self.tree.back()
+    }
+
+    /// Step forward along the main line. Returns false at the end of a line.
+    pub fn redo(&mut self) -> bool {
+        self.tree.forward()
+    }
+
+    /// Jump to a node by id. Returns false if the node does not exist.
+    pub fn go_to(&mut self, node: usize) -> bool {
+        self.tree.go_to(node)
+    }
+
+    /// Get the current node id
+    pub fn get_current_node(&self) -> usize {
+        self.tree.current
+    }
+
+    /// Delete a node and its subtree. Returns false for the root.
+    pub fn delete_variation(&mut self, node: usize) -> bool {
+        self.tree.delete_variation(node)
+    }
+
+    /// Make a node's line the main line.
+    pub fn promote_variation(&mut self, node: usize) -> bool {
+        self.tree.promote_variation(node)
+    }
+
+    /// Get the whole variation tree as JSON ({ variant, nodes, current }),
+    /// including each node's annotations. Suitable for saving.
+    pub fn get_tree(&self) -> String {
+        self.tree.to_json()
+    }
+
+    /// Restore a tree saved with `get_tree`. Returns false if the JSON is invalid.
+    pub fn load_tree(&mut self, json: &str) -> bool {
+        match VariationTree::from_json(json) {
+            Some(tree) => {
+                self.tree = tree;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Get the current node's annotations as JSON ({ arrows, cells })
+    pub fn get_annotations(&self) -> String {
+        serde_json::to_string(self.tree.annotations()).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// Replace the current node's annotations from JSON ({ arrows, cells }).
+    /// Returns false if the JSON is invalid.
+    pub fn set_annotations(&mut self, json: &str) -> bool {
+        match serde_json::from_str::<Annotations>(json) {
+            Ok(annotations) => match self.tree.annotations_mut() {
+                Some(current) => {
+                    *current = annotations;
+                    true
+                }
+                None => false,
+            },
+            Err(_) => false,
+        }
+    }
+
+    /// Toggle an arrow on the current node. `color` is "G", "R", "Y" or "B".
+    /// Returns false for an unknown color.
+    pub fn toggle_arrow(
+        &mut self,
+        from_q: i32,
+        from_r: i32,
+        to_q: i32,
+        to_r: i32,
+        color: &str,
+    ) -> bool {
+        let Some(color) = parse_annotation_color(color) else {
+            return false;
+        };
+        let Some(annotations) = self.tree.annotations_mut() else {
+            return false;
+        };
+        annotations.toggle_arrow(
+            HexCoord::new(from_q, from_r),
+            HexCoord::new(to_q, to_r),
+            color,
+        );
+        true
+    }
+
+    /// Toggle a highlighted cell on the current node. `color` is "G", "R", "Y" or "B".
+    /// Returns false for an unknown color.
+    pub fn toggle_cell(&mut self, q: i32, r: i32, color: &str) -> bool {
+        let Some(color) = parse_annotation_color(color) else {
+            return false;
+        };
+        let Some(annotations) = self.tree.annotations_mut() else {
+            return false;
+        };
+        annotations.toggle_cell(HexCoord::new(q, r), color);
+        true
+    }
+
+    /// Get the current node's annotations as comment commands
+    /// (e.g. "[%csl G0:2][%cal R0:2>0:0]")
+    pub fn get_annotation_comment(&self) -> String {
+        self.tree.annotations().to_comment()
+    }
+
+    /// Replace the current node's annotations with those found in a comment.
+    /// Returns false if a command is malformed.
+    pub fn set_annotations_from_comment(&mut self, comment: &str) -> bool {
+        match Annotations::from_comment(comment) {
+            Some(annotations) => match self.tree.annotations_mut() {
+                Some(current) => {
+                    *current = annotations;
+                    true
+                }
+                None => false,
+            },
+            None => false,
+        }
+    }
+
+    /// Get the moves from the root to the current node as JSON array
+    pub fn get_line(&self) -> String {
+        serde_json::to_string(&self.tree.current_line()).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Put a piece (HexFEN token such as "Q" or "lb") on a cell.
+    /// Editing starts a fresh tree at the edited position.
+    /// Returns false if the token or cell is invalid.
+    pub fn set_piece(&mut self, q: i32, r: i32, token: &str) -> bool {
+        let coord = HexCoord::new(q, r);
+        match parse_piece_token(token) {
+            Some(piece) if is_valid_cell(coord) => {
+                self.tree.set_piece(coord, Some(piece));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Remove the piece on a cell (starts a fresh tree). Returns false,
+    /// keeping the tree, if the cell is empty.
+    pub fn clear_piece(&mut self, q: i32, r: i32) -> bool {
+        let coord = HexCoord::new(q, r);
+        if !self.tree.board().contains_key(&coord.to_key()) {
+            return false;
+        }
+        self.tree.set_piece(coord, None);
+        true
+    }
+
+    /// Set the side to move ("white" or "black"; starts a fresh tree).
+    /// Returns false for any other value.
+    pub fn set_turn(&mut self, turn: &str) -> bool {
+        let color = match turn {
+            "white" => Color::White,
+            "black" => Color::Black,
+            _ => return false,
+        };
+        self.tree.set_turn(color);
+        true
+    }
+
+    /// Search the current node one ply deeper than the previous call.
+    /// Call repeatedly to stream deeper results; restarts at depth 1 after
+    /// moving to another node.
+    /// Returns JSON { node, depth, score, best_move, nodes_searched }, or null
+    /// once `max_depth` is reached.
+    pub fn analyze_next(&mut self, max_depth: i32) -> String {
+        let mut tt = global_tt();
+        match self.session.step(&self.tree, &mut tt, max_depth) {
+            Some(info) => serde_json::to_string(&info).unwrap_or_else(|_| "null".to_string()),
+            None => "null".to_string(),
+        }
+    }
+
+    /// Get the static evaluation of the current position.
+    /// Returns score from white's perspective in centipawns.
+    pub fn evaluate(&self) -> i32 {
+        ai::evaluate_position(self.tree.board())
+    }
+}
+
+/// Validations of a JSON array of [from_q, from_r, to_q, to_r] as JSON, or
+/// "null" if the JSON is invalid.
+fn validate_many_json(state: &GameState, moves_json: &str) -> String {
+    let Ok(moves) = serde_json::from_str::<Vec<[i32; 4]>>(moves_json) else {
+        return "null".to_string();
+    };
+    let moves: Vec<_> = moves
+        .iter()
+        .map(|&[fq, fr, tq, tr]| (HexCoord::new(fq, fr), HexCoord::new(tq, tr), None))
+        .collect();
+    serde_json::to_string(&validate_many(state, &moves)).unwrap_or_else(|_| "null".to_string())
+}
+
+/// JSON result of a rejected `WasmGame.try_move`: the message key as the
+/// error, and the message.
+fn move_error(message: Message) -> String {
+    serde_json::json!({ "ok": false, "error": message.key, "message": message }).to_string()
+}
+
+/// Parse a difficulty name ("easy", "hard", otherwise medium).
+fn difficulty_arg(difficulty: &str) -> ai::AIDifficulty {
+    match difficulty {
+        "easy" => ai::AIDifficulty::Easy,
+        "hard" => ai::AIDifficulty::Hard,
+        _ => ai::AIDifficulty::Medium,
+    }
+}
+
+/// Parse a promotion argument: a HexFEN letter, or empty for none.
+/// Returns None for anything else.
+fn promotion_arg(promotion: &str) -> Option<Option<PieceType>> {
+    let mut chars = promotion.chars();
+    match (chars.next(), chars.next()) {
+        (None, _) => Some(None),
+        (Some(symbol), None) => piece_type_from_symbol(symbol).map(Some),
+        _ => None,
+    }
+}
+
+// ============================================================================
+// Scenarios
+// ============================================================================
+
+/// WASM wrapper for a challenge being played (see `ScenarioRun`). The AI
+/// answers each of the player's moves.
+#[wasm_bindgen]
+pub struct WasmScenario {
+    run: ScenarioRun,
+}
+
+#[wasm_bindgen]
+impl WasmScenario {
+    /// Start a challenge from its JSON definition { id, fen, player, goal,
+    /// constraints, opponent }, playing the opponent's first move if it is to
+    /// move. Returns undefined for an invalid definition.
+    pub fn start(json: &str) -> Option<WasmScenario> {
+        let scenario = serde_json::from_str(json).ok()?;
+        ScenarioRun::start(scenario).map(|run| WasmScenario { run })
+    }
+
+    /// Play a move and the opponent's reply. `promotion` is as for
+    /// `WasmGame.try_move`. Returns JSON { ok: true }, or { ok: false, error,
+    /// message } with error e.g. "constraintNeverMove" or "gameOver".
+    pub fn play_move(
+        &mut self,
+        from_q: i32,
+        from_r: i32,
+        to_q: i32,
+        to_r: i32,
+        promotion: &str,
+    ) -> String {
+        let Some(promotion) = promotion_arg(promotion) else {
+            return move_error(Message::new("unknownPromotion"));
+        };
+        match self.run.play_move(
+            HexCoord::new(from_q, from_r),
+            HexCoord::new(to_q, to_r),
+            promotion,
+        ) {
+            Ok(()) => serde_json::json!({ "ok": true }).to_string(),
+            Err(message) => move_error(message),
+        }
+    }
+
+    /// Get the challenge's status as JSON { status } with status
+    /// "inProgress", "succeeded" or "failed" (then with a reason message).
+    pub fn get_status(&self) -> String {
+        serde_json::to_string(&self.run.status).unwrap_or_else(|_| "null".to_string())
+    }
+
+    /// Number of moves the player has made.
+    pub fn get_moves_played(&self) -> u32 {
+        self.run.moves
+    }
+
+    /// Get a copy of the challenge's game, e.g. to draw the board.
+    pub fn get_game(&self) -> WasmGame {
+        WasmGame::from_state(self.run.state.clone())
+    }
+}
+
+// ============================================================================
+// Repertoire Trainer
+// ============================================================================
+
+/// WASM wrapper for an opening repertoire with spaced-repetition quizzes.
+/// Scheduling uses the current time.
+#[wasm_bindgen]
+pub struct WasmRepertoire {
+    repertoire: Repertoire,
+}
+
+impl Default for WasmRepertoire {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen]
+impl WasmRepertoire {
+    /// Create an empty repertoire for white
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self {
+            repertoire: Repertoire::new(Color::White),
+        }
+    }
+
+    /// Create an empty repertoire for "white" or "black".
+    /// Returns undefined for an unknown color.
+    pub fn for_color(color: &str) -> Option<WasmRepertoire> {
+        let color = match color {
+            "white" => Color::White,
+            "black" => Color::Black,
+            _ => return None,
+        };
+        Some(Self {
+            repertoire: Repertoire::new(color),
+        })
+    }
+
+    /// Add a move from a node (0 is the starting position).
+    /// Returns the new node's id, or -1 if the node or move is invalid.
+    pub fn add_move(&mut self, node: usize, from_q: i32, from_r: i32, to_q: i32, to_r: i32) -> i32 {
+        self.repertoire
+            .add_move(
+                node,
+                HexCoord::new(from_q, from_r),
+                HexCoord::new(to_q, to_r),
+                None,
+            )
+            .map_or(-1, |id| id as i32)
+    }
+
+    /// Add a line from the starting position, given as a JSON array of
+    /// [from_q, from_r, to_q, to_r]. Returns the last node's id, or -1 if the
+    /// JSON is invalid or a move is illegal.
+    pub fn add_line(&mut self, json: &str) -> i32 {
+        let Ok(moves) = serde_json::from_str::<Vec<[i32; 4]>>(json) else {
+            return -1;
+        };
+        let moves: Vec<_> = moves
+            .iter()
+            .map(|&[fq, fr, tq, tr]| (HexCoord::new(fq, fr), HexCoord::new(tq, tr), None))
+            .collect();
+        self.repertoire.add_line(&moves).map_or(-1, |id| id as i32)
+    }
+
+    /// Remove a node and the lines through it. Returns false for the root.
+    pub fn remove(&mut self, node: usize) -> bool {
+        self.repertoire.remove(node)
+    }
+
+    /// Number of positions due for review
+    pub fn get_due_count(&self) -> usize {
+        self.repertoire.due_positions(now_ms()).len()
+    }
+
+    /// Get the next quiz as JSON { node, hexfen, line }, or null if nothing
+    /// is due
+    pub fn next_quiz(&self) -> String {
+        self.repertoire
+            .next_quiz(now_ms())
+            .and_then(|quiz| serde_json::to_string(&quiz).ok())
+            .unwrap_or_else(|| "null".to_string())
+    }
+
+    /// Answer the quiz at a node and reschedule it.
+    /// Returns JSON { correct, expected, next_node, due_ms }, or null if the
+    /// node is not a quiz position.
+    pub fn answer(
+        &mut self,
+        node: usize,
+        from_q: i32,
+        from_r: i32,
+        to_q: i32,
+        to_r: i32,
+    ) -> String {
+        self.repertoire
+            .answer(
+                node,
+                HexCoord::new(from_q, from_r),
+                HexCoord::new(to_q, to_r),
+                None,
+                now_ms(),
+            )
+            .and_then(|result| serde_json::to_string(&result).ok())
+            .unwrap_or_else(|| "null".to_string())
+    }
+
+    /// Reschedule a quiz position with a self-assessed quality (0-5, 3 or
+    /// more is a pass). Returns false if the node is not a quiz position.
+    pub fn review(&mut self, node: usize, quality: u8) -> bool {
+        self.repertoire.review(node, quality, now_ms())
+    }
+
+    /// Get the repertoire with its scheduling as JSON. Suitable for saving.
+    pub fn get_repertoire(&self) -> String {
+        self.repertoire.to_json()
+    }
+
+    /// Restore a repertoire saved with `get_repertoire`. Returns false if the
+    /// JSON is invalid.
+    pub fn load_repertoire(&mut self, json: &str) -> bool {
+        match Repertoire::from_json(json) {
+            Some(repertoire) => {
+                self.repertoire = repertoire;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+fn now_ms() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+/// Parse a single-letter annotation color.
+fn parse_annotation_color(color: &str) -> Option<AnnotationColor> {
+    let mut chars = color.chars();
+    match (chars.next(), chars.next()) {
+        (Some(letter), None) => AnnotationColor::from_letter(letter),
+        _ => None,
+    }
+}
+
+impl Default for WasmAnalysisBoard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ============================================================================
+// Simultaneous Exhibitions
+// ============================================================================
+
+/// WASM wrapper for a simultaneous exhibition: a named bot against a human on
+/// several boards at once, with one shared thinking-time budget for the bot.
+/// Timing uses the current time.
+#[wasm_bindgen]
+pub struct WasmExhibition {
+    exhibition: Exhibition,
+    bot: String,
+}
+
+#[wasm_bindgen]
+impl WasmExhibition {
+    /// Create an exhibition of `boards` standard games where a named bot (see
+    /// `WasmGame.make_bot_move`) plays "white" or "black" on every board,
+    /// with a shared budget in milliseconds (0 for untimed).
+    /// Returns undefined for an unknown bot or color.
+    pub fn with_bot(
+        bot: &str,
+        color: &str,
+        boards: usize,
+        budget_ms: u32,
+    ) -> Option<WasmExhibition> {
+        let color = match color {
+            "white" => Color::White,
+            "black" => Color::Black,
+            _ => return None,
+        };
+        create_bot(bot, 0)?;
+        let now = now_ms() as u64;
+        let mut exhibition = Exhibition::new((budget_ms > 0).then_some(u64::from(budget_ms)));
+        for _ in 0..boards {
+            exhibition.add_board(create_new_game(), color, now);
+        }
+        Some(Self {
+            exhibition,
+            bot: bot.to_string(),
+        })
+    }
+
+    /// Number of boards
+    pub fn get_board_count(&self) -> usize {
+        self.exhibition.boards.len()
+    }
+
+    /// Get a board's game state as JSON, or null for an unknown board
+    pub fn get_state(&self, board: usize) -> String {
+        self.exhibition
+            .boards
+            .get(board)
+            .and_then(|entry| serde_json::to_string(&entry.session.state).ok())
+            .unwrap_or_else(|| "null".to_string())
+    }
+
+    /// Get the boards awaiting the bot's move as a JSON array
+    pub fn get_awaiting(&self) -> String {
+        serde_json::to_string(&self.exhibition.awaiting_exhibitor())
+            .unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// The bot's thinking time left in milliseconds, undefined if untimed
+    pub fn get_remaining_ms(&self) -> Option<u32> {
+        self.exhibition
+            .remaining(now_ms() as u64)
+            .map(|ms| ms.min(u64::from(u32::MAX)) as u32)
+    }
+
+    /// Make the human's move on a board. Returns false if the move is
+    /// illegal, the bot is to move there, or the bot's time has run out.
+    pub fn make_move(
+        &mut self,
+        board: usize,
+        from_q: i32,
+        from_r: i32,
+        to_q: i32,
+        to_r: i32,
+    ) -> bool {
+        let Some(entry) = self.exhibition.boards.get(board) else {
+            return false;
+        };
+        let state = &entry.session.state;
+        let Some(mv) = make_move(
+            state,
+            HexCoord::new(from_q, from_r),
+            HexCoord::new(to_q, to_r),
+        )
+        .and_then(|next| next.history.last().cloned()) else {
+            return false;
+        };
+        let seq = entry.session.seq();
+        self.exhibition
+            .submit_opponent_move(board, seq, &mv, now_ms() as u64)
+            .accepted
+    }
+
+    /// Let the bot move on the next board awaiting it, going round the
+    /// boards in order. Returns the board it moved on, or -1 if none awaits
+    /// it.
+    pub fn play_bot_move(&mut self) -> i32 {
+        let now = now_ms() as u64;
+        let Some(board) = self.exhibition.next_board() else {
+            return -1;
+        };
+        let Some(entry) = self.exhibition.boards.get(board) else {
+            return -1;
+        };
+        let state = &entry.session.state;
+        let seed = u64::from_str_radix(&state_hash(state), 16).unwrap_or(0);
+        let Some(mut bot) = create_bot(&self.bot, seed) else {
+            return -1;
+        };
+        let limits = self
+            .exhibition
+            .bot_limits(SearchLimits::default().max_depth, now);
+        match self.exhibition.play_bot_move(bot.as_mut(), &limits, now) {
+            Some((board, result)) if result.accepted => board as i32,
+            _ => -1,
+        }
+    }
+
+    /// Get the bot's results as JSON { wins, draws, losses, ongoing }, after
+    /// ending every board on time if its budget has run out
+    pub fn get_score(&mut self) -> String {
+        self.exhibition.check_timeout(now_ms() as u64);
+        serde_json::to_string(&self.exhibition.score()).unwrap_or_else(|_| "null".to_string())
+    }
+}
+
+/// A search run a step at a time (see `WasmGame.start_search`), so that a
+/// page can stop it, e.g. from an AbortController's abort event, without
+/// terminating the worker it runs in. Call `step` until it returns true;
+/// after `cancel` the next step finishes with the deepest iteration done.
+#[wasm_bindgen]
+pub struct WasmSearch {
+    search: ai::IterativeSearch,
+}
+
+impl WasmSearch {
+    fn new(state: &GameState, max_depth: i32) -> Self {
+        Self {
+            search: ai::IterativeSearch::new(state, max_depth, EngineOption::TTSize.get() as usize),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl WasmSearch {
+    /// Search iterations for up to about `budget_ms` milliseconds (at least
+    /// one). Returns true once the search is done.
+    pub fn step(&mut self, budget_ms: u32) -> bool {
+        let deadline = now_ms() + i64::from(budget_ms);
+        while !self.search.step() {
+            if now_ms() >= deadline {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Stop the search: it keeps the deepest iteration finished (depth 1 is
+    /// always searched).
+    pub fn cancel(&self) {
+        self.search.cancel_token().cancel();
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.search.is_done()
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.search.is_cancelled()
+    }
+
+    /// Get the best move so far as JSON { from, to, boulder, score,
+    /// scoreKind, depth, nodes }, or null before the first step or without legal moves
+    pub fn get_result(&self) -> String {
+        match self.search.result() {
+            Some(SearchResult {
+                best_move: Some(mv),
+                score,
+                score_kind,
+                stats,
+            }) => serde_json::json!({
+                "from": [mv.from.q, mv.from.r],
+                "to": [mv.to.q, mv.to.r],
+                "boulder": mv.boulder.map(|b| [b.q, b.r]),
+                "score": score,
+                "scoreKind": score_kind,
+                "depth": stats.max_depth_reached,
+                "nodes": stats.nodes_searched,
+            })
+            .to_string(),
+            _ => "null".to_string(),
+        }
+    }
+}
+
+// ============================================================================
+// Standalone WASM Functions
+// ============================================================================
+
+/// Check if a coordinate is valid on the board
+#[wasm_bindgen]
+pub fn wasm_is_valid_cell(q: i32, r: i32) -> bool {
+    is_valid_cell(HexCoord::new(q, r))
+}
+
+/// Get all valid cells as JSON array of [q, r] pairs
+#[wasm_bindgen]
+pub fn wasm_get_all_cells() -> String {
+    let cells: Vec<[i32; 2]> = get_all_cells().iter().map(|c| [c.q, c.r]).collect();
+    serde_json::to_string(&cells).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// Get how to render each valid cell as JSON array of
+/// { q, r, shade, region, ring, name }: `shade` (0-2) is the traditional
+/// three-coloring, `region` is "Center", "Interior", "Edge" or "Corner" and
+/// `name` the cell's name (e.g. "e5" for the center)
+#[wasm_bindgen]
+pub fn wasm_get_cell_classes() -> String {
+    let classes: Vec<CellClass> = get_all_cells().into_iter().filter_map(cell_class).collect();
+    serde_json::to_string(&classes).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// Convert axial coordinates to offset coordinates in a layout ("oddQ",
+/// "evenQ", "oddR" or "evenR"). Returns JSON { col, row }, or null for an
+/// unknown layout. The board center is at (0, 0) in every layout.
+#[wasm_bindgen]
+pub fn wasm_axial_to_offset(q: i32, r: i32, layout: &str) -> String {
+    OffsetLayout::from_name(layout)
+        .and_then(|layout| serde_json::to_string(&HexCoord::new(q, r).to_offset(layout)).ok())
+        .unwrap_or_else(|| "null".to_string())
+}
+
+/// Convert offset coordinates in a layout to axial coordinates. Returns JSON
+/// { q, r }, or null for an unknown layout.
+#[wasm_bindgen]
+pub fn wasm_offset_to_axial(col: i32, row: i32, layout: &str) -> String {
+    OffsetLayout::from_name(layout)
+        .and_then(|layout| {
+            serde_json::to_string(&HexCoord::from_offset(OffsetCoord { col, row }, layout)).ok()
+        })
+        .unwrap_or_else(|| "null".to_string())
+}
+
+/// Convert a board given as JSON [{ col, row, piece }] in an offset layout
+/// to a board (map of "q,r" -> piece). Returns null for invalid JSON or an
+/// unknown layout.
+#[wasm_bindgen]
+pub fn wasm_board_from_offset(json: &str, layout: &str) -> String {
+    let pieces: Option<Vec<OffsetPiece>> = serde_json::from_str(json).ok();
+    pieces
+        .zip(OffsetLayout::from_name(layout))
+        .and_then(|(pieces, layout)| {
+            serde_json::to_string(&board_from_offset(&pieces, layout)).ok()
+        })
+        .unwrap_or_else(|| "null".to_string())
+}
+
+/// Calculate hex distance between two cells
+#[wasm_bindgen]
+pub fn wasm_hex_distance(q1: i32, r1: i32, q2: i32, r2: i32) -> i32 {
+    hex_distance(HexCoord::new(q1, r1), HexCoord::new(q2, r2))
+}
+
+/// Get the line between two cells, e.g. to snap a drawn arrow. Returns JSON
+/// { direction, steps } (direction "N", "S", "NE", "SW", "NW" or "SE"), or
+/// null if the cells are equal or not aligned.
+#[wasm_bindgen]
+pub fn wasm_get_line(q1: i32, r1: i32, q2: i32, r2: i32) -> String {
+    get_line(HexCoord::new(q1, r1), HexCoord::new(q2, r2))
+        .map(|(direction, steps)| {
+            serde_json::json!({ "direction": direction, "steps": steps }).to_string()
+        })
+        .unwrap_or_else(|| "null".to_string())
+}
+
+/// Generate a practice position for an endgame class such as "KQvK", "KLvK"
+/// or "KPvK". White has the extra material and is to move.
+/// Returns undefined for an unknown class.
+#[wasm_bindgen]
+pub fn wasm_generate_endgame(class: &str, seed: u32) -> Option<WasmGame> {
+    random_position(class, seed as u64).map(WasmGame::from_state)
+}
+
+/// Generate a random legal, quiet position matching constraints given as JSON
+/// (`{"minPieces":2,"maxPieces":6,"phase":"Endgame","minBalance":-100,
+/// "maxBalance":100,"sideToMove":"White"}`, every field optional).
+/// Returns undefined for invalid or unsatisfiable constraints.
+#[wasm_bindgen]
+pub fn wasm_generate_random_position(seed: u32, constraints_json: &str) -> Option<WasmGame> {
+    let constraints: PositionConstraints = serde_json::from_str(constraints_json).ok()?;
+    generate_random_position(seed as u64, &constraints).map(WasmGame::from_state)
+}
+
+/// Generate a winning practice position whose distance to mate (in plies) lies
+/// in [min_dtm, max_dtm]. Requires (and if needed generates) the class tablebase.
+/// Returns undefined if no such position exists.
+#[wasm_bindgen]
+pub fn wasm_generate_endgame_with_difficulty(
+    class: &str,
+    seed: u32,
+    min_dtm: i32,
+    max_dtm: i32,
+) -> Option<WasmGame> {
+    let difficulty = EndgameDifficulty::new(min_dtm, max_dtm);
+    random_position_with_difficulty(class, seed as u64, Some(difficulty)).map(WasmGame::from_state)
+}
+
+/// Build the common endgame tablebases for up to `budget_ms` milliseconds.
+/// Call repeatedly (e.g. in idle frames) until the returned JSON status has
+/// `done: true`; the AI uses each tablebase as soon as it is completed.
+#[wasm_bindgen]
+pub fn wasm_tablebase_build_step(budget_ms: u32) -> String {
+    tablebase_build_step(budget_ms as u64)
+        .and_then(|status| serde_json::to_string(&status).ok())
+        .unwrap_or_else(|| "null".to_string())
+}
+
+/// Save the progress of the tablebase build, including completed tablebases,
+/// as JSON. Returns null on failure.
+#[wasm_bindgen]
+pub fn wasm_tablebase_build_save() -> String {
+    save_tablebase_build().unwrap_or_else(|| "null".to_string())
+}
+
+/// Resume a tablebase build saved with `wasm_tablebase_build_save`.
+/// Returns false if the JSON is invalid.
+#[wasm_bindgen]
+pub fn wasm_tablebase_build_load(json: &str) -> bool {
+    load_tablebase_build(json)
+}
+
+/// Decode a packed board (as from `WasmGame.get_packed_board()`) to the JSON
+/// board format of `get_board`. Returns null for malformed data.
+#[wasm_bindgen]
+pub fn wasm_unpack_board(bytes: &[u8]) -> String {
+    BoardState::from_packed_bytes(bytes)
+        .and_then(|board| serde_json::to_string(&board).ok())
+        .unwrap_or_else(|| "null".to_string())
+}
+
+/// Evaluate many packed boards (as from `WasmGame.get_packed_board()`, laid
+/// end to end) in one call, each in centipawns from White's perspective.
+/// Returns undefined for malformed data.
+#[wasm_bindgen]
+pub fn wasm_evaluate_batch(bytes: &[u8]) -> Option<Vec<i32>> {
+    unpack_boards(bytes).map(|boards| evaluate_batch(&boards))
+}
+
+/// Sequence number of an autosave snapshot (from `WasmGame.autosave_blob()`),
+/// for choosing the newest of several. Returns undefined for a torn, corrupt
+/// or unknown snapshot.
+#[wasm_bindgen]
+pub fn wasm_autosave_seq(bytes: &[u8]) -> Option<u32> {
+    restore_autosave(bytes).map(|snapshot| snapshot.seq)
+}
+
+/// Import games into the opening explorer.
+/// Takes a JSON array of { moves, status, white_rating?, black_rating? } where
+/// `moves` is a game history as returned by `WasmGame.get_history()`.
+/// Returns the number of games added, or -1 if the JSON is invalid.
+#[wasm_bindgen]
+pub fn wasm_explorer_import(json: &str) -> i32 {
+    import_explorer_games_from_json(json)
+        .map(|count| count as i32)
+        .unwrap_or(-1)
+}
+
+/// Query the opening explorer for a HexFEN position.
+/// Returns JSON statistics for the position, or null if it was never reached.
+#[wasm_bindgen]
+pub fn wasm_explorer_query(fen: &str) -> String {
+    parse_hexfen(fen)
+        .and_then(|(board, turn)| query_explorer(&board, turn))
+        .and_then(|entry| serde_json::to_string(&entry).ok())
+        .unwrap_or_else(|| "null".to_string())
+}
+
+/// Remove all games from the opening explorer.
+#[wasm_bindgen]
+pub fn wasm_explorer_clear() {
+    clear_explorer();
+}
+
+/// Verify a game transcript (JSON { start, entries } as recorded by a server
+/// session) by replaying every move and checking its hashes.
+/// Returns JSON { ok: true, head, moves } or { ok: false, index, reason }, or
+/// null if the JSON is invalid.
+#[wasm_bindgen]
+pub fn wasm_verify_transcript(json: &str) -> String {
+    let Ok(transcript) = serde_json::from_str::<Transcript>(json) else {
+        return "null".to_string();
+    };
+    match transcript.verify() {
+        Ok(state) => serde_json::json!({
+            "ok": true,
+            "head": transcript.head(),
+            "moves": state.history.len(),
+        }),
+        Err(error) => serde_json::json!({
+            "ok": false,
+            "index": error.index,
+            "reason": error.reason,
+        }),
+    }
+    .to_string()
+}
+
+/// Validate a batch of moves against a game saved with `WasmGame.save`,
+/// for servers checking queued premoves. Returns a JSON array as
+/// `WasmGame.validate_many` does, or null if either JSON is invalid.
+#[wasm_bindgen]
+pub fn wasm_validate_many(state_json: &str, moves_json: &str) -> String {
+    match load_game(state_json) {
+        Some(state) => validate_many_json(&state, moves_json),
+        None => "null".to_string(),
+    }
+}
+
+/// Select the daily puzzle for a date ("YYYY-MM-DD") from a JSON array of
+/// { id, fen, stipulation }. Every client gets the same valid puzzle for the
+/// same date and pool. Returns the puzzle as JSON, or null if the pool JSON
+/// is invalid.
+#[wasm_bindgen]
+pub fn wasm_daily_puzzle(date: &str, pool_json: &str) -> String {
+    PuzzleSet::from_json(pool_json)
+        .and_then(|pool| serde_json::to_string(&daily(date, &pool)).ok())
+        .unwrap_or_else(|| "null".to_string())
+}
+
+/// Check a first move for a puzzle (JSON as from `wasm_daily_puzzle`).
+/// `promotion` is a HexFEN letter, or empty for none. Any move proven to
+/// still mate in time is accepted.
+/// Returns JSON { result: "correct", mate_in } or { result: "incorrect" |
+/// "illegal" }, or null if the puzzle JSON or promotion is invalid.
+#[wasm_bindgen]
+pub fn wasm_check_puzzle_answer(
+    puzzle_json: &str,
+    from_q: i32,
+    from_r: i32,
+    to_q: i32,
+    to_r: i32,
+    promotion: &str,
+) -> String {
+    let answer = serde_json::from_str::<Puzzle>(puzzle_json)
+        .ok()
+        .zip(promotion_arg(promotion))
+        .map(|(puzzle, promotion)| {
+            puzzle.check_answer(
+                HexCoord::new(from_q, from_r),
+                HexCoord::new(to_q, to_r),
+                promotion,
+            )
+        });
+    answer
+        .and_then(|answer| serde_json::to_string(&answer).ok())
+        .unwrap_or_else(|| "null".to_string())
+}
+
+/// Generate a knight-path puzzle from `seed`: take a knight from one cell to
+/// another in exactly `moves` leaps, avoiding `blocked` cells. Returns JSON
+/// { from, to, blocked, moves } with cells as { q, r }, or null if no such
+/// puzzle turns up.
+#[wasm_bindgen]
+pub fn wasm_knight_path_puzzle(seed: u32, moves: u32, blocked: u32) -> String {
+    knight_path_puzzle(u64::from(seed), moves, blocked as usize)
+        .and_then(|puzzle| serde_json::to_string(&puzzle).ok())
+        .unwrap_or_else(|| "null".to_string())
+}
+
+/// Check a route for a knight-path puzzle (as returned by
+/// `wasm_knight_path_puzzle`), given as a JSON array of the [q, r] cells
+/// leapt to. Returns JSON { result: "optimal" | "longer" | "illegal" |
+/// "incomplete" } (with `moves` when longer, `step` when illegal), or null if
+/// either JSON is invalid.
+#[wasm_bindgen]
+pub fn wasm_check_knight_path(puzzle_json: &str, route_json: &str) -> String {
+    let puzzle = serde_json::from_str::<KnightPathPuzzle>(puzzle_json).ok();
+    let route = serde_json::from_str::<Vec<[i32; 2]>>(route_json).ok();
+    puzzle
+        .zip(route)
+        .and_then(|(puzzle, route)| {
+            let cells: Vec<HexCoord> = route.iter().map(|&[q, r]| HexCoord::new(q, r)).collect();
+            serde_json::to_string(&puzzle.check(&cells)).ok()
+        })
+        .unwrap_or_else(|| "null".to_string())
+}
+
+/// Import a game recorded by another client as a coordinate move list: a
+/// JSON array of {from: {q, r}, to: {q, r}, promotion?} or text moves
+/// "q,r>q,r". Returns JSON { game, imported, errors } with `game` a save
+/// for `WasmGame.load`, replayed up to the first illegal move, and errors
+/// [{ index, entry, reason }] with reason a message (see
+/// `wasm_format_message`), or null if the JSON is not a move list.
+#[wasm_bindgen]
+pub fn wasm_import_move_list(text: &str) -> String {
+    import_move_list(text)
+        .map(|import| {
+            serde_json::json!({
+                "game": save_game(&import.state),
+                "imported": import.imported,
+                "errors": import.errors,
+            })
+            .to_string()
+        })
+        .unwrap_or_else(|| "null".to_string())
+}
+
+/// Decode a share code (see `WasmGame.get_share_code`). Returns JSON
+/// { board, turn, lastMove } with the board as in `WasmGame.get_board` and
+/// the last move as [from, to] cells or null, or null if the code is invalid.
+#[wasm_bindgen]
+pub fn wasm_decode_share_code(code: &str) -> String {
+    decode_share_code(code)
+        .and_then(|shared| serde_json::to_string(&shared).ok())
+        .unwrap_or_else(|| "null".to_string())
+}
+
+/// Render an engine message (JSON { key, params } as in move rejections,
+/// game results and key moments) in English. Returns undefined if the JSON
+/// is invalid.
+#[wasm_bindgen]
+pub fn wasm_format_message(json: &str) -> Option<String> {
+    serde_json::from_str::<Message>(json)
+        .ok()
+        .map(|message| english(&message))
+}
+
+/// Build a player profile from their games.
+/// Takes a JSON array of { moves, status, color } where `moves` is a game
+/// history as returned by `WasmGame.get_history()` and `color` the player's.
+/// Returns the profile as JSON, or null if the JSON is invalid.
+#[wasm_bindgen]
+pub fn wasm_player_profile(json: &str) -> String {
+    build_profile_from_json(json)
+        .map(|profile| profile.to_json())
+        .unwrap_or_else(|| "null".to_string())
+}
 
-/// Calculate hex distance between two cells
+/// Summarize a game for the post-game report. Takes JSON
+/// { moves, status, times_ms } where `moves` is a game history as returned by
+/// `WasmGame.get_history()` and `times_ms`, optional, the time spent on each
+/// move. Returns JSON { result, plies, evaluations, moments, white, black }
+/// (see `GameSummary`), or null if the JSON is invalid.
 #[wasm_bindgen]
-pub fn wasm_hex_distance(q1: i32, r1: i32, q2: i32, r2: i32) -> i32 {
-    hex_distance(HexCoord::new(q1, r1), HexCoord::new(q2, r2))
+pub fn wasm_game_summary(json: &str) -> String {
+    summarize_from_json(json)
+        .map(|summary| summary.to_json())
+        .unwrap_or_else(|| "null".to_string())
+}
+
+/// Pick a game's `k` most critical positions, searched to `depth`, as
+/// practice positions for the "learn from your mistakes" trainer. Takes a
+/// game as for `wasm_game_summary`. Returns a JSON array, most critical
+/// first, of { ply, hexfen, turn, played, bestLine, bestScore, evalDrop,
+/// bestMoveGap, onlyMove } (see `CriticalPosition`), or null if the JSON is
+/// invalid.
+#[wasm_bindgen]
+pub fn wasm_critical_positions(json: &str, depth: i32, k: u32) -> String {
+    critical_positions_from_json(json, depth, k as usize)
+        .and_then(|positions| serde_json::to_string(&positions).ok())
+        .unwrap_or_else(|| "null".to_string())
+}
+
+/// Score a score attack run (beat the bot fast). Takes JSON
+/// { moves, player, time_ms } where `moves` is a game history as returned by
+/// `WasmGame.get_history()`. Returns JSON { won, player_moves, accuracy,
+/// material_kept, win_points, move_bonus, time_bonus, accuracy_bonus,
+/// material_bonus, total }, or null if the JSON is invalid or the moves are
+/// not legal.
+#[wasm_bindgen]
+pub fn wasm_score_run(json: &str) -> String {
+    score_run_from_json(json)
+        .and_then(|score| serde_json::to_string(&score).ok())
+        .unwrap_or_else(|| "null".to_string())
+}
+
+/// Play an AI-vs-AI game from the starting position for an attract-mode demo
+/// board. `white` and `black` are difficulty names ("easy", "medium",
+/// "hard"); games longer than `max_plies` moves are drawn. Returns JSON
+/// { moves, status, adjudication, scores } (see `SimulatedGame`).
+#[wasm_bindgen]
+pub fn wasm_simulate_game(white: &str, black: &str, max_plies: u32) -> String {
+    let game = simulate_game(
+        &ai::AIOptions::new(difficulty_arg(white)),
+        &ai::AIOptions::new(difficulty_arg(black)),
+        max_plies as usize,
+    );
+    serde_json::to_string(&game).unwrap_or_else(|_| "null".to_string())
+}
+
+/// Describe this build of the engine as JSON { name, version, variants,
+/// ruleOptions: [{ name, values }], tablebases: { embedded, loaded },
+/// features, options } (options as in `wasm_list_options`).
+#[wasm_bindgen]
+pub fn wasm_engine_info() -> String {
+    serde_json::to_string(&engine_info()).unwrap_or_else(|_| "null".to_string())
+}
+
+/// List the engine's tunable options as JSON array of
+/// { name, type: "spin" | "check", value, default, min, max, description }.
+#[wasm_bindgen]
+pub fn wasm_list_options() -> String {
+    serde_json::to_string(&list_options()).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// Set an engine option; `value` is an integer, or "true"/"false" for a check.
+/// Clears the AI cache, whose entries may no longer hold. Returns JSON
+/// { ok: true } or { ok: false, error } where error is "unknownOption",
+/// "invalidValue" or "outOfRange".
+#[wasm_bindgen]
+pub fn wasm_set_option(name: &str, value: &str) -> String {
+    match set_option(name, value) {
+        Ok(()) => {
+            *global_tt() = ai::TranspositionTable::new(EngineOption::TTSize.get() as usize);
+            serde_json::json!({ "ok": true }).to_string()
+        }
+        Err(reason) => move_error(Message::new(&reason)),
+    }
+}
+
+/// Load an options config, a JSON object { name: value } such as one written
+/// by the ordering tuner. Clears the AI cache. Returns JSON { ok: true } or
+/// { ok: false, error } where error is "malformed" or a `wasm_set_option`
+/// error; on error no option changes.
+#[wasm_bindgen]
+pub fn wasm_load_options_config(json: &str) -> String {
+    match load_options_config(json) {
+        Ok(()) => {
+            *global_tt() = ai::TranspositionTable::new(EngineOption::TTSize.get() as usize);
+            serde_json::json!({ "ok": true }).to_string()
+        }
+        Err(reason) => move_error(Message::new(&reason)),
+    }
 }
 
 // ============================================================================
@@ -231,6 +2417,7 @@ pub fn wasm_hex_distance(q1: i32, r1: i32, q2: i32, r2: i32) -> i32 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::annotations::ArrowStyle;
 
     #[test]
     fn test_wasm_game_new() {
@@ -244,9 +2431,225 @@ mod tests {
         let mut game = WasmGame::new();
 
         // Move a pawn
+        assert_eq!(game.get_last_move_events(), "null");
         let success = game.make_move(0, 2, 0, 1);
         assert!(success);
         assert_eq!(game.get_turn(), "black");
+
+        let events: serde_json::Value = serde_json::from_str(&game.get_last_move_events()).unwrap();
+        assert_eq!(events["capture"], false);
+        assert_eq!(events["game_end"], false);
+    }
+
+    #[test]
+    fn test_wasm_difficulty() {
+        let game = WasmGame::new();
+        let score: serde_json::Value = serde_json::from_str(&game.get_difficulty(1)).unwrap();
+        assert!(score["nearBestMoves"].as_u64().unwrap() > 0);
+        assert!(["easy", "medium", "hard"].contains(&score["grade"].as_str().unwrap()));
+    }
+
+    #[test]
+    fn test_wasm_repetition_count() {
+        let mut game = WasmGame::new();
+        assert_eq!(game.get_repetition_count(), 1);
+        for [fq, fr, tq, tr] in [
+            [-2, 3, -1, 1],
+            [2, -3, 1, -1],
+            [-1, 1, -2, 3],
+            [1, -1, 2, -3],
+        ] {
+            assert!(game.make_move(fq, fr, tq, tr));
+        }
+        assert_eq!(game.get_repetition_count(), 2);
+    }
+
+    #[test]
+    fn test_wasm_export_history() {
+        let mut game = WasmGame::new();
+        assert!(game.make_move(0, 2, 0, 1));
+        let csv = game.export_history("csv").unwrap();
+        assert_eq!(csv.lines().count(), 2);
+        let rows: serde_json::Value =
+            serde_json::from_str(&game.export_history("json").unwrap()).unwrap();
+        assert_eq!(rows[0]["color"], "white");
+        assert!(game.export_history("xml").is_none());
+    }
+
+    #[test]
+    fn test_wasm_import_move_list() {
+        let import: serde_json::Value =
+            serde_json::from_str(&wasm_import_move_list("0,2>0,1 nonsense")).unwrap();
+        assert_eq!(import["imported"], 1);
+        assert_eq!(import["errors"][0]["reason"]["key"], "malformedMove");
+        let game = WasmGame::load(import["game"].as_str().unwrap()).unwrap();
+        assert_eq!(game.get_turn(), "black");
+        assert_eq!(wasm_import_move_list("{}"), "null");
+    }
+
+    #[test]
+    fn test_wasm_coach_events() {
+        let mut game = WasmGame::new();
+        assert!(game.make_move(0, 2, 0, 1));
+        let events: serde_json::Value = serde_json::from_str(&game.get_last_move_events()).unwrap();
+        assert!(events.get("coach").is_none());
+
+        game.set_coach(1);
+        assert!(game.make_move(2, -3, 3, -2));
+        let events: serde_json::Value = serde_json::from_str(&game.get_last_move_events()).unwrap();
+        assert_eq!(events["coach"]["ply"], 1);
+        assert_eq!(events["coach"]["advice"]["key"], "adviceOpening");
+
+        // The AI's reply carries no report
+        assert!(game.make_ai_move("easy"));
+        let events: serde_json::Value = serde_json::from_str(&game.get_last_move_events()).unwrap();
+        assert!(events.get("coach").is_none());
+    }
+
+    #[test]
+    fn test_wasm_analysis_board() {
+        let mut board = WasmAnalysisBoard::new();
+        assert_eq!(board.play_move(0, 2, 0, 1), 1);
+        assert_eq!(board.play_move(0, 1, 0, 0), -1);
+        assert!(board.undo());
+        assert!(!board.undo());
+        assert!(board.redo());
+        assert_eq!(board.get_current_node(), 1);
+
+        assert!(board.set_piece(0, 0, "q"));
+        assert!(!board.set_piece(9, 9, "q"));
+        assert!(board.set_turn("black"));
+        assert_eq!(board.get_current_node(), 0);
+        assert!(board.get_hexfen().ends_with(" b"));
+
+        let info: serde_json::Value = serde_json::from_str(&board.analyze_next(1)).unwrap();
+        assert_eq!(info["depth"], 1);
+        assert_eq!(board.analyze_next(1), "null");
+
+        // Clearing an empty cell keeps the tree and its analysis
+        assert!(!board.clear_piece(1, 0));
+        assert_eq!(board.analyze_next(1), "null");
+        assert!(board.clear_piece(0, 0));
+        assert_ne!(board.analyze_next(1), "null");
+    }
+
+    #[test]
+    fn test_wasm_analysis_annotations() {
+        let mut board = WasmAnalysisBoard::new();
+        assert!(board.toggle_arrow(0, 2, 0, 0, "G"));
+        assert!(board.toggle_cell(0, 0, "r"));
+        assert!(!board.toggle_cell(0, 0, "purple"));
+        assert_eq!(board.get_annotation_comment(), "[%csl R0:0][%cal G0:2>0:0]");
+
+        let saved = board.get_tree();
+        let mut restored = WasmAnalysisBoard::new();
+        assert!(restored.load_tree(&saved));
+        assert_eq!(restored.get_annotations(), board.get_annotations());
+
+        assert!(restored.set_annotations_from_comment("[%csl B1:1]"));
+        assert_eq!(restored.get_annotation_comment(), "[%csl B1:1]");
+        assert!(!restored.load_tree("not json"));
+    }
+
+    #[test]
+    fn test_wasm_move_targets_annotated() {
+        let game = WasmGame::new();
+        let targets: serde_json::Value =
+            serde_json::from_str(&game.get_move_targets_annotated(0, 2)).unwrap();
+        let targets = targets.as_array().unwrap();
+        assert!(!targets.is_empty());
+        assert!(targets
+            .iter()
+            .all(|t| t["capture"] == false && t["gives_check"] == false));
+        assert_eq!(game.get_move_targets_annotated(0, -2), "[]");
+
+        let map: serde_json::Value = serde_json::from_str(&game.get_attack_map("black")).unwrap();
+        assert!(map["0,-1"].as_u64().unwrap() > 0);
+    }
+
+    #[test]
+    fn test_wasm_diff_with() {
+        let before = WasmGame::new();
+        let mut after = WasmGame::new();
+        assert_eq!(before.diff_with(&after), "[]");
+        assert!(after.make_move(0, 2, 0, 1));
+
+        let deltas: serde_json::Value = serde_json::from_str(&before.diff_with(&after)).unwrap();
+        assert_eq!(deltas.as_array().unwrap().len(), 1);
+        assert_eq!(deltas[0]["Moved"]["to"]["r"], 1);
+    }
+
+    #[test]
+    fn test_wasm_crazyhouse_drop() {
+        let mut game = WasmGame::with_variant("crazyhouse").unwrap();
+        assert!(WasmGame::with_variant("chess960").is_none());
+
+        for [fq, fr, tq, tr] in [[0, 2, 0, 1], [0, -2, 0, -1], [0, 1, 0, 0], [0, -1, 0, 0]] {
+            assert!(game.make_move(fq, fr, tq, tr));
+        }
+        assert!(game.make_move(1, 2, 1, 1));
+
+        let hands: serde_json::Value = serde_json::from_str(&game.get_hands()).unwrap();
+        assert_eq!(hands["black"]["Pawn"], 1);
+        assert!(!game.drop_piece("N", -3, 0));
+        assert!(game.drop_piece("p", -3, 0));
+        assert_eq!(game.get_turn(), "white");
+    }
+
+    #[test]
+    fn test_wasm_boulder_turn() {
+        let mut game = WasmGame::with_variant("boulder").unwrap();
+        assert!(game.make_move(0, 2, 0, 1));
+        assert_eq!(game.get_turn(), "white");
+        assert_eq!(game.get_phase(), "\"PlaceBoulder\"");
+        assert!(!game.make_move(0, -2, 0, -1));
+        assert!(!game.place_boulder(0, 1));
+        assert!(game.place_boulder(0, 0));
+        assert_eq!(game.get_turn(), "black");
+
+        assert!(game.make_ai_move("easy"));
+        assert_eq!(game.get_turn(), "white");
+        assert_eq!(game.get_phase(), "\"Move\"");
+    }
+
+    #[test]
+    fn test_wasm_explorer_query() {
+        let mut game = WasmGame::new();
+        let start_fen = game.get_hexfen();
+        game.make_move(0, 2, 0, 1);
+
+        let json = format!(
+            r#"[{{"moves": {}, "status": "Stalemate", "white_rating": 1400, "black_rating": 1600}}]"#,
+            game.get_history()
+        );
+        assert_eq!(wasm_explorer_import(&json), 1);
+        assert_eq!(wasm_explorer_import("not json"), -1);
+
+        let stats: serde_json::Value =
+            serde_json::from_str(&wasm_explorer_query(&start_fen)).unwrap();
+        assert!(stats["games"].as_u64().unwrap() >= 1);
+        assert!(stats["moves"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|m| m["to"]["r"] == 1));
+        assert_eq!(wasm_explorer_query("garbage"), "null");
+    }
+
+    #[test]
+    fn test_wasm_generate_endgame() {
+        let game = wasm_generate_endgame("KPvK", 11).unwrap();
+        assert_eq!(game.get_turn(), "white");
+        assert!(wasm_generate_endgame("nonsense", 11).is_none());
+    }
+
+    #[test]
+    fn test_wasm_generate_random_position() {
+        let json = r#"{"maxPieces":3,"phase":"Endgame","sideToMove":"Black"}"#;
+        let game = wasm_generate_random_position(5, json).unwrap();
+        assert_eq!(game.get_turn(), "black");
+        assert!(wasm_generate_random_position(5, r#"{"phase":"Opening"}"#).is_none());
+        assert!(wasm_generate_random_position(5, "not json").is_none());
     }
 
     #[test]
@@ -255,4 +2658,614 @@ mod tests {
         assert!(wasm_is_valid_cell(4, 0));
         assert!(!wasm_is_valid_cell(5, 0));
     }
+
+    #[test]
+    fn test_analysis_cached_until_move() {
+        let mut game = WasmGame::new();
+        let first = game.get_ai_move("easy");
+        assert_eq!(game.analysis.len(), 1);
+        assert_eq!(game.get_ai_move("easy"), first);
+        game.evaluate();
+        assert_eq!(game.analysis.len(), 1);
+
+        assert!(game.make_move(0, 2, 0, 1));
+        assert!(game.analysis.is_empty());
+        game.get_ai_move("easy");
+        game.clear_analysis_cache();
+        assert!(game.analysis.is_empty());
+    }
+
+    #[test]
+    fn test_wasm_move_proofs() {
+        let mut sender = WasmGame::new();
+        let mut receiver = WasmGame::new();
+        let proof = sender.prove_move(0, 2, 0, 1, "");
+        assert_eq!(sender.get_turn(), "white");
+        assert_eq!(sender.prove_move(0, 2, 0, -2, ""), "null");
+
+        assert!(sender.make_move(0, 2, 0, 1));
+        assert!(receiver.apply_move_proof(&proof));
+        assert_eq!(receiver.get_sync_hash(), sender.get_sync_hash());
+        // The same proof cannot be applied twice
+        assert!(!receiver.apply_move_proof(&proof));
+    }
+
+    #[test]
+    fn test_wasm_consult() {
+        let game = WasmGame::new();
+        let result: serde_json::Value = serde_json::from_str(
+            &game.consult(r#"[{"bot":"greedy","weight":1},{"bot":"random","weight":0.5}]"#),
+        )
+        .unwrap();
+        assert_eq!(result["votes"].as_array().unwrap().len(), 2);
+        assert_eq!(result["votes"][0]["bot"], "greedy");
+        // The heavier greedy vote wins a split panel
+        assert_eq!(result["move"]["from"], result["votes"][0]["from"]);
+        assert_eq!(result["move"]["to"], result["votes"][0]["to"]);
+        assert_eq!(game.get_turn(), "white");
+        assert_eq!(game.consult(r#"[{"bot":"nobody","weight":1}]"#), "null");
+        assert_eq!(game.consult(r#"[{"bot":"greedy","weight":0}]"#), "null");
+        assert_eq!(game.consult("nope"), "null");
+    }
+
+    #[test]
+    fn test_wasm_get_result() {
+        let mut game = WasmGame::new();
+        assert_eq!(game.get_result(), "null");
+        game.resign();
+        assert_eq!(
+            game.get_result(),
+            concat!(
+                r#"{"winner":"Black","termination":"resignation","reason":"resignation","#,
+                r#""score":"0-1","message":{"key":"resignation","params":{"winner":"black"}}}"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_wasm_ai_under_promotion() {
+        // Only promoting to a knight mates the smothered king
+        let (board, turn) = crate::notation::parse_hexfen("4n4/3knP3/2nn5/9/K8/9/9/9/9 w").unwrap();
+        let mut game = WasmGame::from_state(create_game_from_position(board, turn));
+        assert!(game.make_ai_move("easy"));
+        let last = game.state.history.last().unwrap();
+        assert_eq!(last.promotion, Some(PieceType::Knight));
+        assert!(matches!(game.state.status, GameStatus::Checkmate { .. }));
+    }
+
+    #[test]
+    fn test_wasm_stalemate_rule() {
+        let mut board = BoardState::new();
+        board.insert("0,4".to_string(), Piece::new(PieceType::King, Color::White));
+        board.insert(
+            "0,-4".to_string(),
+            Piece::new(PieceType::King, Color::Black),
+        );
+        board.insert(
+            "-4,1".to_string(),
+            Piece::new(PieceType::Queen, Color::White),
+        );
+        let mut game = WasmGame::from_state(create_game_from_position(board, Color::White));
+        game.make_move(-4, 1, -1, -2);
+        assert_eq!(game.state.status, GameStatus::Stalemate);
+
+        assert!(game.set_stalemate_rule("3/4"));
+        let result: serde_json::Value = serde_json::from_str(&game.get_result()).unwrap();
+        assert_eq!(result["score"], "3/4-1/4");
+        assert_eq!(result["winner"], "White");
+        assert!(game.set_stalemate_rule("stalematerLoses"));
+        let result: serde_json::Value = serde_json::from_str(&game.get_result()).unwrap();
+        assert_eq!(result["score"], "0-1");
+        assert_eq!(result["termination"], "stalemate");
+        assert!(game.set_stalemate_rule("draw"));
+        assert_eq!(game.state.status, GameStatus::Stalemate);
+        assert!(!game.set_stalemate_rule("half"));
+        assert_eq!(game.get_stalemate_rule(), r#""Draw""#);
+    }
+
+    #[test]
+    fn test_wasm_win_conditions() {
+        let mut game = WasmGame::new();
+        assert_eq!(
+            game.get_win_conditions(),
+            r#"{"bare_king":false,"capture_all":null}"#
+        );
+        assert!(game.set_win_conditions(r#"{"capture_all":"Knight"}"#));
+        assert_eq!(game.state.status, GameStatus::Ongoing);
+        assert_eq!(
+            game.state.win_conditions.capture_all,
+            Some(PieceType::Knight)
+        );
+        assert!(!game.set_win_conditions(r#"{"capture_all":"King"}"#));
+        assert!(!game.set_win_conditions("bareKing"));
+        assert!(game.set_win_conditions("{}"));
+        assert_eq!(game.state.win_conditions, WinConditions::default());
+    }
+
+    #[test]
+    fn test_wasm_exhibition() {
+        assert!(WasmExhibition::with_bot("random", "red", 2, 0).is_none());
+        assert!(WasmExhibition::with_bot("nobody", "white", 2, 0).is_none());
+        let mut exhibition = WasmExhibition::with_bot("random", "black", 2, 0).unwrap();
+        assert_eq!(exhibition.get_board_count(), 2);
+        assert_eq!(exhibition.get_awaiting(), "[]");
+        assert_eq!(exhibition.get_remaining_ms(), None);
+        assert_eq!(exhibition.play_bot_move(), -1);
+
+        assert!(exhibition.make_move(1, 0, 2, 0, 1));
+        assert!(!exhibition.make_move(1, 0, 1, 0, 0));
+        assert!(!exhibition.make_move(5, 0, 2, 0, 1));
+        assert_eq!(exhibition.get_awaiting(), "[1]");
+        assert_eq!(exhibition.play_bot_move(), 1);
+        assert_eq!(
+            exhibition.get_score(),
+            r#"{"wins":0,"draws":0,"losses":0,"ongoing":2}"#
+        );
+        assert_eq!(exhibition.get_state(7), "null");
+    }
+
+    #[test]
+    fn test_wasm_autosave() {
+        let mut game = WasmGame::new();
+        assert!(game.make_move(0, 2, 0, 1));
+        let first = game.autosave_blob().unwrap();
+        assert!(game.make_move(0, -2, 0, -1));
+        let second = game.autosave_blob().unwrap();
+        assert_eq!(wasm_autosave_seq(&first), Some(1));
+        assert_eq!(wasm_autosave_seq(&second), Some(2));
+        assert_eq!(wasm_autosave_seq(&second[..second.len() - 1]), None);
+
+        let mut restored = WasmGame::restore_autosave(&second).unwrap();
+        assert_eq!(restored.get_hexfen(), game.get_hexfen());
+        assert_eq!(restored.state.history, game.state.history);
+        let third = restored.autosave_blob().unwrap();
+        assert_eq!(wasm_autosave_seq(&third), Some(3));
+    }
+
+    #[test]
+    fn test_wasm_engine_plan() {
+        let game = WasmGame::new();
+        let plan: Annotations = serde_json::from_str(&game.get_engine_plan(2, 2)).unwrap();
+        assert!(!plan.arrows.is_empty() && plan.arrows.len() <= 2);
+        assert_eq!(plan.arrows[0].style, ArrowStyle::Solid);
+        assert_eq!(game.get_engine_plan(2, 0), r#"{"arrows":[],"cells":[]}"#);
+    }
+
+    #[test]
+    fn test_wasm_endgame_explanation_needs_tablebase() {
+        // The opening is no endgame
+        assert_eq!(WasmGame::new().get_endgame_explanation(), "null");
+    }
+
+    #[test]
+    fn test_wasm_get_line() {
+        assert_eq!(wasm_get_line(0, 0, 0, -3), r#"{"direction":"N","steps":3}"#);
+        assert_eq!(wasm_get_line(0, 0, 1, 1), "null");
+    }
+
+    #[test]
+    fn test_wasm_offset_coordinates() {
+        assert_eq!(
+            wasm_axial_to_offset(-1, 0, "oddQ"),
+            r#"{"col":-1,"row":-1}"#
+        );
+        assert_eq!(wasm_offset_to_axial(-1, -1, "oddQ"), r#"{"q":-1,"r":0}"#);
+        assert_eq!(wasm_axial_to_offset(0, 0, "hex"), "null");
+
+        let game = WasmGame::new();
+        let board = game.get_board_offset("evenR");
+        let original: BoardState = serde_json::from_str(&game.get_board()).unwrap();
+        let converted: BoardState =
+            serde_json::from_str(&wasm_board_from_offset(&board, "evenR")).unwrap();
+        assert_eq!(converted, original);
+        assert_eq!(wasm_board_from_offset("nope", "evenR"), "null");
+        assert_eq!(game.get_history_offset("oddQ"), "[]");
+    }
+
+    #[test]
+    fn test_wasm_cell_classes() {
+        let classes: serde_json::Value = serde_json::from_str(&wasm_get_cell_classes()).unwrap();
+        let classes = classes.as_array().unwrap();
+        assert_eq!(classes.len(), 61);
+        let center = classes.iter().find(|c| c["name"] == "e5").unwrap();
+        assert_eq!(
+            (center["q"].as_i64(), center["r"].as_i64()),
+            (Some(0), Some(0))
+        );
+        assert_eq!(center["region"], "Center");
+    }
+
+    #[test]
+    fn test_wasm_score_run() {
+        let mut game = WasmGame::new();
+        game.make_move(0, 2, 0, 1);
+        let json = format!(
+            r#"{{"moves": {}, "player": "White", "time_ms": 1000}}"#,
+            game.get_history()
+        );
+        let score: serde_json::Value = serde_json::from_str(&wasm_score_run(&json)).unwrap();
+        assert_eq!(score["player_moves"], 1);
+        assert_eq!(score["total"], 0);
+        assert_eq!(wasm_score_run("nope"), "null");
+    }
+
+    #[test]
+    fn test_wasm_game_summary() {
+        let mut game = WasmGame::new();
+        game.make_move(0, 2, 0, 1);
+        let summary: serde_json::Value = serde_json::from_str(&game.get_summary()).unwrap();
+        assert_eq!(summary["plies"], 1);
+        assert_eq!(summary["white"]["moves"], 1);
+
+        let json = format!(
+            r#"{{"moves": {}, "status": "Ongoing", "times_ms": [4000]}}"#,
+            game.get_history()
+        );
+        let summary: serde_json::Value = serde_json::from_str(&wasm_game_summary(&json)).unwrap();
+        assert!(summary["result"].is_null());
+        assert_eq!(summary["moments"][0]["kind"], "longestThink");
+        assert_eq!(summary["moments"][0]["spentMs"], 4000);
+        assert_eq!(wasm_game_summary("nope"), "null");
+    }
+
+    #[test]
+    fn test_wasm_critical_positions() {
+        let mut game = WasmGame::new();
+        for [fq, fr, tq, tr] in [[0, 2, 0, 1], [2, -3, 3, -2], [-2, 4, 2, 0], [3, -2, 2, 0]] {
+            assert!(game.make_move(fq, fr, tq, tr));
+        }
+        let positions: serde_json::Value =
+            serde_json::from_str(&game.get_critical_positions(1, 1)).unwrap();
+        assert_eq!(positions.as_array().unwrap().len(), 1);
+        assert!(positions[0]["hexfen"].is_string());
+        assert!(positions[0]["bestLine"]
+            .as_array()
+            .is_some_and(|line| !line.is_empty()));
+
+        let json = format!(
+            r#"{{"moves": {}, "status": "Ongoing"}}"#,
+            game.get_history()
+        );
+        let positions: serde_json::Value =
+            serde_json::from_str(&wasm_critical_positions(&json, 1, 5)).unwrap();
+        assert!(positions.as_array().is_some_and(|p| p.len() >= 2));
+        assert_eq!(wasm_critical_positions("nope", 1, 5), "null");
+    }
+
+    #[test]
+    fn test_wasm_board_vision() {
+        let game = WasmGame::new();
+        let check: serde_json::Value =
+            serde_json::from_str(&game.verify_piece_list(&game.get_board(), "")).unwrap();
+        assert_eq!(check["score"], 100);
+        let check: serde_json::Value =
+            serde_json::from_str(&game.verify_piece_list("{}", "black")).unwrap();
+        assert_eq!(check["score"], 0);
+        assert_eq!(game.verify_piece_list("nope", ""), "null");
+
+        let quiz: serde_json::Value = serde_json::from_str(&game.get_attack_quiz(1)).unwrap();
+        let answer: Vec<[i64; 2]> = quiz["attacked"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|cell| [cell["q"].as_i64().unwrap(), cell["r"].as_i64().unwrap()])
+            .collect();
+        let (q, r) = (
+            quiz["cell"]["q"].as_i64().unwrap(),
+            quiz["cell"]["r"].as_i64().unwrap(),
+        );
+        let grade: serde_json::Value = serde_json::from_str(&game.grade_attack_quiz(
+            q as i32,
+            r as i32,
+            &serde_json::to_string(&answer).unwrap(),
+        ))
+        .unwrap();
+        assert_eq!(grade["score"], 100);
+        assert_eq!(game.grade_attack_quiz(0, 0, "[]"), "null");
+
+        let puzzle = wasm_knight_path_puzzle(5, 2, 0);
+        let parsed: serde_json::Value = serde_json::from_str(&puzzle).unwrap();
+        assert_eq!(parsed["moves"], 2);
+        let verdict: serde_json::Value =
+            serde_json::from_str(&wasm_check_knight_path(&puzzle, "[]")).unwrap();
+        assert_eq!(verdict["result"], "incomplete");
+        assert_eq!(wasm_check_knight_path(&puzzle, "nope"), "null");
+    }
+
+    #[test]
+    fn test_wasm_share_code() {
+        let mut game = WasmGame::new();
+        game.make_move(0, 2, 0, 1);
+        let code = game.get_share_code().unwrap();
+        let shared = WasmGame::from_share_code(&code).unwrap();
+        let board = |game: &WasmGame| -> serde_json::Value {
+            serde_json::from_str(&game.get_board()).unwrap()
+        };
+        assert_eq!(board(&shared), board(&game));
+        assert_eq!(shared.get_turn(), "black");
+        assert!(WasmGame::from_share_code("???").is_none());
+
+        let decoded: serde_json::Value =
+            serde_json::from_str(&wasm_decode_share_code(&code)).unwrap();
+        assert_eq!(decoded["turn"], "Black");
+        assert_eq!(decoded["lastMove"][1]["r"], 1);
+        assert_eq!(wasm_decode_share_code("???"), "null");
+    }
+
+    #[test]
+    fn test_wasm_compact_game() {
+        let mut game = WasmGame::new();
+        game.make_move(0, 2, 0, 1);
+        game.make_move(0, -2, 0, -1);
+        let bytes = game.get_compact_game();
+        assert!(bytes.len() < 8);
+        let copy = WasmGame::from_compact_game(&bytes).unwrap();
+        assert_eq!(copy.get_history(), game.get_history());
+        assert!(WasmGame::from_compact_game(&[]).is_none());
+    }
+
+    #[test]
+    fn test_wasm_messages() {
+        let mut game = WasmGame::new();
+        let error: serde_json::Value =
+            serde_json::from_str(&game.try_move(0, -2, 0, -1, "")).unwrap();
+        assert_eq!(error["error"], "notYourPiece");
+        assert_eq!(error["message"]["params"]["from"], "e7");
+        assert_eq!(
+            wasm_format_message(&error["message"].to_string()).as_deref(),
+            Some("The pawn on e7 is not yours.")
+        );
+        assert!(wasm_format_message("nope").is_none());
+    }
+
+    #[test]
+    fn test_wasm_describe_move() {
+        let mut game = WasmGame::new();
+        let description: serde_json::Value =
+            serde_json::from_str(&game.describe_move(0, 2, 0, 1, "")).unwrap();
+        assert_eq!(description["piece"], "pawn");
+        assert_eq!(description["to"], "e4");
+        assert_eq!(game.describe_move(0, -2, 0, -1, ""), "null");
+        assert_eq!(game.describe_move(0, 0, 0, -1, ""), "null");
+        assert_eq!(game.describe_last_move(), "null");
+
+        game.make_move(0, 2, 0, 1);
+        let last: serde_json::Value = serde_json::from_str(&game.describe_last_move()).unwrap();
+        assert_eq!(last, description);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn test_wasm_check_invariants() {
+        let mut game = WasmGame::new();
+        game.make_move(0, 2, 0, 1);
+        assert_eq!(game.check_invariants(), "[]");
+        game.state.board.remove("0,-4");
+        let violations: serde_json::Value = serde_json::from_str(&game.check_invariants()).unwrap();
+        assert_eq!(violations[0]["key"], "missingKing");
+        assert_eq!(violations[0]["params"]["color"], "black");
+    }
+
+    #[test]
+    fn test_wasm_simulate_game() {
+        let game: serde_json::Value =
+            serde_json::from_str(&wasm_simulate_game("easy", "easy", 2)).unwrap();
+        assert_eq!(game["moves"].as_array().unwrap().len(), 2);
+        assert_eq!(game["adjudication"], "moveLimit");
+    }
+
+    #[test]
+    fn test_wasm_engine_info() {
+        let info: serde_json::Value = serde_json::from_str(&wasm_engine_info()).unwrap();
+        assert_eq!(info["name"], "Underchex");
+        assert!(info["variants"]
+            .as_array()
+            .unwrap()
+            .contains(&serde_json::json!("crazyhouse")));
+        assert_eq!(info["ruleOptions"][0]["name"], "stalemateRule");
+        assert!(info["tablebases"]["loaded"].is_array());
+    }
+
+    #[test]
+    fn test_wasm_options() {
+        let options: serde_json::Value = serde_json::from_str(&wasm_list_options()).unwrap();
+        let mobility = options
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|option| option["name"] == "mobilityWeight")
+            .unwrap();
+        assert_eq!(mobility["type"], "spin");
+
+        let result = |json: String| -> serde_json::Value { serde_json::from_str(&json).unwrap() };
+        // Setting the current value leaves the engine as it was
+        let value = mobility["value"].to_string();
+        assert_eq!(
+            result(wasm_set_option("mobilityWeight", &value))["ok"],
+            true
+        );
+        assert_eq!(
+            result(wasm_set_option("mobilityWeight", "1000"))["error"],
+            "outOfRange"
+        );
+        assert_eq!(
+            result(wasm_set_option("nope", "1"))["error"],
+            "unknownOption"
+        );
+        assert_eq!(
+            result(wasm_load_options_config("[1]"))["error"],
+            "malformed"
+        );
+    }
+
+    #[test]
+    fn test_wasm_save_and_hexfen_keep_draw_state() {
+        let mut game = WasmGame::new();
+        // Knights shuffle out and back: the start position occurs twice
+        for [fq, fr, tq, tr] in [
+            [-2, 3, -1, 1],
+            [2, -3, 1, -1],
+            [-1, 1, -2, 3],
+            [1, -1, 2, -3],
+        ] {
+            assert!(game.make_move(fq, fr, tq, tr));
+        }
+        let compressed = WasmGame::load(&game.save_compressed()).unwrap();
+        assert_eq!(
+            compressed.state.position_history,
+            game.state.position_history
+        );
+        let mut loaded = WasmGame::load(&game.save()).unwrap();
+        let mut from_fen = WasmGame::from_hexfen(&game.get_hexfen()).unwrap();
+        assert_eq!(from_fen.get_hexfen(), game.get_hexfen());
+        for copy in [&mut game, &mut loaded, &mut from_fen] {
+            for [fq, fr, tq, tr] in [
+                [-2, 3, -1, 1],
+                [2, -3, 1, -1],
+                [-1, 1, -2, 3],
+                [1, -1, 2, -3],
+            ] {
+                assert!(copy.make_move(fq, fr, tq, tr));
+            }
+            assert!(copy.get_status().contains("threefoldRepetition"));
+        }
+
+        assert!(!WasmGame::new().can_claim_draw());
+        assert!(!WasmGame::new().claim_draw());
+        assert!(WasmGame::load("nope").is_none());
+        #[cfg(feature = "signed-saves")]
+        {
+            let signed = game.save_signed("key");
+            assert!(WasmGame::load_signed(&signed, "key").is_some());
+            assert!(WasmGame::load_signed(&signed, "other").is_none());
+        }
+        assert!(WasmGame::from_hexfen("nope").is_none());
+    }
+
+    #[test]
+    fn test_try_move_reports_errors() {
+        let mut game = WasmGame::new();
+        let error = |json: String| -> Option<String> {
+            let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+            value["error"].as_str().map(str::to_string)
+        };
+        assert_eq!(
+            error(game.try_move(0, -2, 0, -1, "")).as_deref(),
+            Some("notYourPiece")
+        );
+        assert_eq!(
+            error(game.try_move(3, 3, 3, 2, "")).as_deref(),
+            Some("noPieceAtSource")
+        );
+        assert_eq!(
+            error(game.try_move(0, 2, 0, 1, "XY")).as_deref(),
+            Some("unknownPromotion")
+        );
+        assert_eq!(error(game.try_move(0, 2, 0, 1, "")), None);
+        assert_eq!(game.get_turn(), "black");
+
+        game.resign();
+        assert_eq!(
+            error(game.try_move(0, -2, 0, -1, "")).as_deref(),
+            Some("gameOver")
+        );
+    }
+
+    #[test]
+    fn test_analyze_excluding() {
+        let game = WasmGame::new();
+        assert_eq!(game.analyze_excluding("{}", 1), "null");
+        let best: serde_json::Value =
+            serde_json::from_str(&game.analyze_excluding("[]", 1)).unwrap();
+        let excluded = serde_json::json!([[
+            best["from"][0],
+            best["from"][1],
+            best["to"][0],
+            best["to"][1]
+        ]]);
+        let other: serde_json::Value =
+            serde_json::from_str(&game.analyze_excluding(&excluded.to_string(), 1)).unwrap();
+        assert!(other["from"] != best["from"] || other["to"] != best["to"]);
+    }
+
+    #[test]
+    fn test_calibrate() {
+        let mut game = WasmGame::new();
+        let calibration: serde_json::Value = serde_json::from_str(&game.calibrate(50)).unwrap();
+        assert!(calibration["nodesPerSecond"].as_u64().unwrap() > 0);
+        let hard = calibration["hard"]["depth"].as_i64().unwrap();
+        assert!((1..=6).contains(&hard));
+        assert_eq!(calibration["hard"]["timeLimitMs"], 3000);
+        assert!(game.ai_options("hard").budget.is_some());
+        assert_eq!(game.ai_options("hard").search_depth(), hard as i32);
+
+        assert_eq!(game.calibrate(0), "null");
+        assert_eq!(game.ai_options("hard").budget, None);
+    }
+
+    #[test]
+    fn test_validate_many() {
+        let game = WasmGame::new();
+        let validations: serde_json::Value =
+            serde_json::from_str(&game.validate_many("[[0, 2, 0, 1], [0, -2, 0, -1]]")).unwrap();
+        assert_eq!(validations[0]["legal"], true);
+        assert_eq!(validations[1]["reason"], "notYourPiece");
+        assert_eq!(game.validate_many("[[0, 2]]"), "null");
+
+        let saved = wasm_validate_many(&game.save(), "[[0, 2, 0, 1]]");
+        assert_eq!(saved, game.validate_many("[[0, 2, 0, 1]]"));
+        assert_eq!(wasm_validate_many("{}", "[]"), "null");
+    }
+
+    #[test]
+    fn test_packed_board_roundtrip() {
+        let game = WasmGame::new();
+        let packed = game.get_packed_board();
+        let board: BoardState = serde_json::from_str(&wasm_unpack_board(&packed)).unwrap();
+        assert_eq!(board, game.state.board);
+        assert_eq!(wasm_unpack_board(&[1, 2, 3]), "null");
+
+        let batch = [packed.clone(), packed].concat();
+        let score = evaluate_position(&game.state.board);
+        assert_eq!(wasm_evaluate_batch(&batch), Some(vec![score, score]));
+        assert_eq!(wasm_evaluate_batch(&[1, 2, 3]), None);
+    }
+
+    #[test]
+    fn test_wasm_scenario() {
+        assert!(WasmScenario::start("{}").is_none());
+        let json = r#"{"id":"mate","fen":"4kpQ2/3p5/9/9/9/9/9/9/4K4 w","player":"White",
+            "goal":{"type":"win","moves":1},"opponent":"easy"}"#;
+        let mut scenario = WasmScenario::start(json).unwrap();
+        let result: serde_json::Value =
+            serde_json::from_str(&scenario.play_move(2, -4, 0, -2, "")).unwrap();
+        assert_eq!(result["ok"], true);
+        let status: serde_json::Value = serde_json::from_str(&scenario.get_status()).unwrap();
+        assert_eq!(status["status"], "succeeded");
+        assert_eq!(scenario.get_moves_played(), 1);
+    }
+
+    #[test]
+    fn test_wasm_repertoire() {
+        let mut repertoire = WasmRepertoire::for_color("white").unwrap();
+        assert!(WasmRepertoire::for_color("green").is_none());
+        assert_eq!(repertoire.add_line("[[0,2,0,1],[0,-2,0,-1]]"), 2);
+        assert_eq!(repertoire.add_move(2, 1, 2, 1, 1), 3);
+        assert_eq!(repertoire.add_line("[[0,2,0,-3]]"), -1);
+        assert_eq!(repertoire.get_due_count(), 2);
+
+        let quiz: serde_json::Value = serde_json::from_str(&repertoire.next_quiz()).unwrap();
+        assert_eq!(quiz["node"], 0);
+        let result: serde_json::Value =
+            serde_json::from_str(&repertoire.answer(0, 0, 2, 0, 1)).unwrap();
+        assert_eq!(result["correct"], true);
+        assert_eq!(repertoire.answer(1, 0, -2, 0, -1), "null");
+        assert_eq!(repertoire.get_due_count(), 1);
+
+        let saved = repertoire.get_repertoire();
+        let mut restored = WasmRepertoire::new();
+        assert!(restored.load_repertoire(&saved));
+        assert_eq!(restored.get_due_count(), 1);
+        assert!(!restored.load_repertoire("{}"));
+    }
 }
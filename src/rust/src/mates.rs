@@ -0,0 +1,299 @@
+//! Underchex Mate Patterns
+//!
+//! The fundamental mates of the hex board against a bare king:
+//! - King and queen against a king on an edge, or in a corner
+//! - King and lance against a king in a corner (a lance alone cannot force a
+//!   king to the edge, but does mate it in the corner)
+//!
+//! Detectors recognize a finished pattern ([`classify_mate`]) and a king
+//! being driven into one ([`mating_net`]); the evaluation rewards the latter
+//! (the `matingNetWeight` option), and [`mate_drills`] composes positions for
+//! pattern-recognition drills.
+
+use serde::{Deserialize, Serialize};
+
+use crate::board::{cell_class, get_all_cells, hex_distance, CellRegion};
+use crate::messages::Message;
+use crate::moves::{apply_move, find_king, generate_all_legal_moves, is_in_check};
+use crate::notation::board_to_hexfen;
+use crate::options::EngineOption;
+use crate::puzzles::{find_mating_moves, is_checkmated, Puzzle};
+use crate::rng::SeededRng;
+use crate::types::{BoardState, Color, HexCoord, LanceVariant, Piece, PieceType};
+use crate::variants::has_bare_king;
+
+/// Cells around a king away from the edge.
+const KING_NEIGHBORS: i32 = 6;
+
+/// Placements tried per drill before giving up on a seed.
+const DRILL_ATTEMPTS: usize = 2000;
+
+// ============================================================================
+// Patterns
+// ============================================================================
+
+/// A fundamental mating pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MatePattern {
+    /// King and queen mate a king on an edge
+    QueenEdge,
+    /// King and queen mate a king in a corner
+    QueenCorner,
+    /// King and lance mate a king in a corner
+    LanceCorner,
+}
+
+/// Every pattern, in teaching order.
+pub const MATE_PATTERNS: [MatePattern; 3] = [
+    MatePattern::QueenEdge,
+    MatePattern::QueenCorner,
+    MatePattern::LanceCorner,
+];
+
+impl MatePattern {
+    /// The attacker's piece besides its king.
+    pub fn piece(self) -> PieceType {
+        match self {
+            MatePattern::QueenEdge | MatePattern::QueenCorner => PieceType::Queen,
+            MatePattern::LanceCorner => PieceType::Lance,
+        }
+    }
+
+    /// Where the mated king stands.
+    pub fn region(self) -> CellRegion {
+        match self {
+            MatePattern::QueenEdge => CellRegion::Edge,
+            MatePattern::QueenCorner | MatePattern::LanceCorner => CellRegion::Corner,
+        }
+    }
+
+    /// Name as serialized ("queenEdge", ...).
+    pub fn name(self) -> &'static str {
+        match self {
+            MatePattern::QueenEdge => "queenEdge",
+            MatePattern::QueenCorner => "queenCorner",
+            MatePattern::LanceCorner => "lanceCorner",
+        }
+    }
+
+    /// How the pattern is played, for lessons.
+    pub fn message(self) -> Message {
+        Message::new(match self {
+            MatePattern::QueenEdge => "mateQueenEdge",
+            MatePattern::QueenCorner => "mateQueenCorner",
+            MatePattern::LanceCorner => "mateLanceCorner",
+        })
+    }
+
+    /// Pattern of an attacker's piece with the defending king in a region.
+    fn of(piece_type: PieceType, region: CellRegion) -> Option<Self> {
+        MATE_PATTERNS
+            .into_iter()
+            .find(|pattern| pattern.piece() == piece_type && pattern.region() == region)
+    }
+}
+
+// ============================================================================
+// Detectors
+// ============================================================================
+
+/// A bare king driven towards a mating pattern.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatingNet {
+    pub pattern: MatePattern,
+    pub king: HexCoord,
+    /// Cells the king may move to
+    pub flights: Vec<HexCoord>,
+}
+
+/// The attacker's only piece besides its king, if it has exactly one.
+fn lone_piece(board: &BoardState, attacker: Color) -> Option<PieceType> {
+    let mut pieces = board
+        .values()
+        .filter(|piece| piece.color == attacker && piece.piece_type != PieceType::King)
+        .filter(|piece| !piece.is_boulder());
+    match (pieces.next(), pieces.next()) {
+        (Some(piece), None) => Some(piece.piece_type),
+        _ => None,
+    }
+}
+
+/// The net around `defender`'s bare king, if its opponent has a pattern's
+/// material (king and one queen or lance) and the king stands where the
+/// pattern mates.
+pub fn mating_net(board: &BoardState, defender: Color) -> Option<MatingNet> {
+    if !has_bare_king(board, defender) {
+        return None;
+    }
+    let piece_type = lone_piece(board, defender.opposite())?;
+    let king = find_king(board, defender)?;
+    let pattern = MatePattern::of(piece_type, cell_class(king)?.region)?;
+    let flights = generate_all_legal_moves(board, defender)
+        .into_iter()
+        .map(|mv| mv.to)
+        .collect();
+    Some(MatingNet {
+        pattern,
+        king,
+        flights,
+    })
+}
+
+/// The pattern `defender` is checkmated in, if any.
+pub fn classify_mate(board: &BoardState, defender: Color) -> Option<MatePattern> {
+    if !is_checkmated(board, defender) {
+        return None;
+    }
+    mating_net(board, defender).map(|net| net.pattern)
+}
+
+/// Evaluation bonus for confining a bare king in a mating net: the
+/// `matingNetWeight` option per neighboring cell it cannot flee to.
+/// Returns value from white's perspective in centipawns.
+pub(crate) fn mating_net_bonus(board: &BoardState) -> i32 {
+    let weight = EngineOption::MatingNetWeight.get();
+    if weight == 0 {
+        return 0;
+    }
+    let confinement = |defender: Color| {
+        mating_net(board, defender).map_or(0, |net| {
+            (KING_NEIGHBORS - net.flights.len() as i32) * weight
+        })
+    };
+    confinement(Color::Black) - confinement(Color::White)
+}
+
+// ============================================================================
+// Drills
+// ============================================================================
+
+/// A random position of the pattern's material with White to mate in one,
+/// every mate being the pattern.
+fn compose_drill(pattern: MatePattern, rng: &mut SeededRng) -> Option<BoardState> {
+    let cells = get_all_cells();
+    let king_cells: Vec<HexCoord> = cells
+        .iter()
+        .copied()
+        .filter(|&cell| cell_class(cell).is_some_and(|class| class.region == pattern.region()))
+        .collect();
+    let black_king = *rng.choose(&king_cells)?;
+    let white_king = *rng.choose(&cells)?;
+    let piece_cell = *rng.choose(&cells)?;
+    if hex_distance(white_king, black_king) < 2
+        || piece_cell == white_king
+        || piece_cell == black_king
+    {
+        return None;
+    }
+    let piece = match pattern.piece() {
+        PieceType::Lance if rng.next_below(2) == 0 => Piece::lance(Color::White, LanceVariant::A),
+        PieceType::Lance => Piece::lance(Color::White, LanceVariant::B),
+        piece_type => Piece::new(piece_type, Color::White),
+    };
+
+    let mut board = BoardState::new();
+    board.insert(
+        white_king.to_key(),
+        Piece::new(PieceType::King, Color::White),
+    );
+    board.insert(
+        black_king.to_key(),
+        Piece::new(PieceType::King, Color::Black),
+    );
+    board.insert(piece_cell.to_key(), piece);
+    if is_in_check(&board, Color::Black) {
+        return None;
+    }
+    let mates = find_mating_moves(&board, Color::White, 1);
+    let all_pattern = mates.iter().all(|mv| {
+        let after = apply_move(&board, mv);
+        classify_mate(&after, Color::Black) == Some(pattern)
+    });
+    (!mates.is_empty() && all_pattern).then_some(board)
+}
+
+/// Mate-in-one drills of a pattern, as puzzles. A seed always gives the same
+/// drills; fewer than `count` are returned if placements run out.
+pub fn mate_drills(pattern: MatePattern, count: usize, seed: u64) -> Vec<Puzzle> {
+    let mut rng = SeededRng::new(seed);
+    let mut drills: Vec<Puzzle> = Vec::new();
+    for _ in 0..count * DRILL_ATTEMPTS {
+        if drills.len() >= count {
+            break;
+        }
+        let Some(board) = compose_drill(pattern, &mut rng) else {
+            continue;
+        };
+        let fen = board_to_hexfen(&board, Color::White);
+        if drills.iter().any(|drill| drill.fen == fen) {
+            continue;
+        }
+        drills.push(Puzzle {
+            id: format!("{}-{}-{}", pattern.name(), seed, drills.len() + 1),
+            fen,
+            stipulation: "#1".to_string(),
+        });
+    }
+    drills
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::evaluate_position;
+    use crate::notation::parse_hexfen;
+
+    #[test]
+    fn test_classify_queen_mates() {
+        // Q(0,-2) mates the king at (0,-4) with its own pawns blocking, so
+        // it is not a pattern
+        let (board, _) = parse_hexfen("4kpQ2/3p5/9/9/9/9/9/9/4K4 w").unwrap();
+        let mv = find_mating_moves(&board, Color::White, 1).remove(0);
+        let after = apply_move(&board, &mv);
+        assert!(is_checkmated(&after, Color::Black));
+        assert_eq!(classify_mate(&after, Color::Black), None);
+
+        for pattern in MATE_PATTERNS {
+            let drills = mate_drills(pattern, 2, 11);
+            assert_eq!(drills.len(), 2, "{:?}", pattern);
+            for drill in &drills {
+                assert!(drill.is_valid());
+                for mv in drill.solutions() {
+                    let (board, _) = parse_hexfen(&drill.fen).unwrap();
+                    let after = apply_move(&board, &mv);
+                    assert_eq!(classify_mate(&after, Color::Black), Some(pattern));
+                }
+            }
+            assert_eq!(mate_drills(pattern, 2, 11), drills);
+        }
+    }
+
+    #[test]
+    fn test_mating_net() {
+        let (board, _) = parse_hexfen(&mate_drills(MatePattern::QueenEdge, 1, 3)[0].fen).unwrap();
+        let net = mating_net(&board, Color::Black).unwrap();
+        assert_eq!(net.pattern, MatePattern::QueenEdge);
+        assert!(net.flights.len() < KING_NEIGHBORS as usize);
+        assert!(mating_net(&board, Color::White).is_none());
+        assert!(mating_net_bonus(&board) > 0);
+
+        // The bonus is part of the evaluation, and mirrors for Black
+        let flipped = crate::eval::flip_colors(&board);
+        assert_eq!(mating_net_bonus(&flipped), -mating_net_bonus(&board));
+        assert_eq!(evaluate_position(&flipped), -evaluate_position(&board));
+
+        // No net with more material on the board
+        let mut busy = board.clone();
+        busy.insert(
+            HexCoord::new(0, 0).to_key(),
+            Piece::new(PieceType::Pawn, Color::Black),
+        );
+        assert!(mating_net(&busy, Color::Black).is_none());
+    }
+}
@@ -0,0 +1,189 @@
+//! Underchex Memory Budget
+//!
+//! Sizing of the engine's tables to fit a memory budget, and estimates of
+//! what they use. Without a budget the transposition table has `ttSize`
+//! entries, the evaluation caches their default sizes, and tablebases grow
+//! without limit, which is too much for some mobile browsers.
+//!
+//! Like the engine options, the budget is process-wide: tables created after
+//! it is set take their sizes from it, and loaded tablebases are evicted to
+//! fit it.
+
+use std::mem::size_of;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ai::{TranspositionTable, EVAL_CACHE_SIZE, PAWN_CACHE_SIZE};
+use crate::options::set_option;
+use crate::tablebase::{enforce_tablebase_budget, tablebase_memory_usage};
+use crate::types::{BoardState, Piece};
+
+/// Estimated bytes of a transposition table entry with a middlegame board,
+/// for sizing the table.
+pub const TT_ENTRY_BYTES: usize = 2_048;
+
+/// Estimated bytes of an evaluation cache entry (key, score and hash table
+/// overhead).
+pub const EVAL_ENTRY_BYTES: usize = 32;
+
+/// Smallest budget accepted, in megabytes.
+pub const MIN_MEMORY_BUDGET_MB: u32 = 4;
+
+/// Shares of a budget, in percent, given to the transposition table, the
+/// evaluation caches and tablebases.
+const TT_SHARE: usize = 50;
+const EVAL_CACHE_SHARE: usize = 10;
+const TABLEBASE_SHARE: usize = 40;
+
+/// Megabytes of the budget, 0 when unset.
+static BUDGET_MB: AtomicUsize = AtomicUsize::new(0);
+static PAWN_CACHE_ENTRIES: AtomicUsize = AtomicUsize::new(PAWN_CACHE_SIZE);
+static EVAL_CACHE_ENTRIES: AtomicUsize = AtomicUsize::new(EVAL_CACHE_SIZE);
+/// Bytes tablebases may use, 0 when unlimited.
+static TABLEBASE_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+// ============================================================================
+// Budget
+// ============================================================================
+
+/// Table sizes chosen for a budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryBudget {
+    pub megabytes: u32,
+    pub tt_entries: usize,
+    pub pawn_cache_entries: usize,
+    pub eval_cache_entries: usize,
+    pub tablebase_bytes: usize,
+}
+
+impl MemoryBudget {
+    /// Split a budget of `megabytes` between the tables. A fifth of the
+    /// evaluation caches' share goes to the pawn hash table.
+    pub fn from_megabytes(megabytes: u32) -> Self {
+        let bytes = megabytes as usize * 1024 * 1024;
+        let share = |percent: usize| bytes / 100 * percent;
+        let eval_entries = share(EVAL_CACHE_SHARE) / EVAL_ENTRY_BYTES;
+        Self {
+            megabytes,
+            tt_entries: (share(TT_SHARE) / TT_ENTRY_BYTES).max(1),
+            pawn_cache_entries: (eval_entries / 5).max(1),
+            eval_cache_entries: (eval_entries - eval_entries / 5).max(1),
+            tablebase_bytes: share(TABLEBASE_SHARE),
+        }
+    }
+}
+
+/// Size the tables to fit `megabytes`: sets the `ttSize` option and the
+/// evaluation cache sizes for tables created from now on, and evicts loaded
+/// tablebases until they fit their share. Fails with "outOfRange" below
+/// [`MIN_MEMORY_BUDGET_MB`] or if the table would exceed `ttSize`'s range.
+pub fn set_memory_budget(megabytes: u32) -> Result<MemoryBudget, String> {
+    if megabytes < MIN_MEMORY_BUDGET_MB {
+        return Err("outOfRange".to_string());
+    }
+    let budget = MemoryBudget::from_megabytes(megabytes);
+    set_option("ttSize", &budget.tt_entries.to_string())?;
+    BUDGET_MB.store(megabytes as usize, Ordering::Relaxed);
+    PAWN_CACHE_ENTRIES.store(budget.pawn_cache_entries, Ordering::Relaxed);
+    EVAL_CACHE_ENTRIES.store(budget.eval_cache_entries, Ordering::Relaxed);
+    TABLEBASE_BYTES.store(budget.tablebase_bytes, Ordering::Relaxed);
+    enforce_tablebase_budget();
+    Ok(budget)
+}
+
+/// The budget set, if any.
+pub fn memory_budget() -> Option<MemoryBudget> {
+    match BUDGET_MB.load(Ordering::Relaxed) {
+        0 => None,
+        megabytes => Some(MemoryBudget::from_megabytes(megabytes as u32)),
+    }
+}
+
+/// Entries of the pawn hash table and evaluation cache of new caches.
+pub fn eval_cache_capacity() -> (usize, usize) {
+    (
+        PAWN_CACHE_ENTRIES.load(Ordering::Relaxed),
+        EVAL_CACHE_ENTRIES.load(Ordering::Relaxed),
+    )
+}
+
+/// Bytes loaded tablebases may use, or None without a budget.
+pub(crate) fn tablebase_budget() -> Option<usize> {
+    match TABLEBASE_BYTES.load(Ordering::Relaxed) {
+        0 => None,
+        bytes => Some(bytes),
+    }
+}
+
+// ============================================================================
+// Usage Estimates
+// ============================================================================
+
+/// Estimated bytes of a board held in a table.
+pub(crate) fn board_bytes(board: &BoardState) -> usize {
+    // Hash map slots plus a control byte each, and the keys' text
+    board.capacity() * (size_of::<(String, Piece)>() + 1)
+        + board.keys().map(String::capacity).sum::<usize>()
+}
+
+/// Estimated memory use of the engine's tables, in bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryUsage {
+    pub tt_entries: usize,
+    pub tt_bytes: usize,
+    pub eval_cache_bytes: usize,
+    pub tablebase_bytes: usize,
+    pub total_bytes: usize,
+    /// The budget, if set, in bytes
+    pub budget_bytes: Option<usize>,
+}
+
+/// Estimate the memory use of a transposition table (with its evaluation
+/// cache) and of the loaded tablebases.
+pub fn memory_usage(tt: &TranspositionTable) -> MemoryUsage {
+    let tt_bytes = tt.memory_usage();
+    let eval_cache_bytes = tt.eval_cache().memory_usage();
+    let tablebase_bytes = tablebase_memory_usage();
+    MemoryUsage {
+        tt_entries: tt.size(),
+        tt_bytes,
+        eval_cache_bytes,
+        tablebase_bytes,
+        total_bytes: tt_bytes + eval_cache_bytes + tablebase_bytes,
+        budget_bytes: memory_budget().map(|budget| budget.megabytes as usize * 1024 * 1024),
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::create_new_game;
+
+    #[test]
+    fn test_budget_split() {
+        let budget = MemoryBudget::from_megabytes(64);
+        let total = budget.tt_entries * TT_ENTRY_BYTES
+            + (budget.pawn_cache_entries + budget.eval_cache_entries) * EVAL_ENTRY_BYTES
+            + budget.tablebase_bytes;
+        assert!(total <= 64 * 1024 * 1024);
+        assert!(budget.tt_entries > 10_000);
+        assert!(budget.pawn_cache_entries < budget.eval_cache_entries);
+        assert!(MemoryBudget::from_megabytes(8).tt_entries < budget.tt_entries);
+    }
+
+    #[test]
+    fn test_board_bytes() {
+        let board = create_new_game().board;
+        let bytes = board_bytes(&board);
+        assert!(bytes >= board.len() * size_of::<(String, Piece)>());
+        assert!(bytes < TT_ENTRY_BYTES * 2);
+        assert_eq!(board_bytes(&BoardState::new()), 0);
+    }
+}
@@ -0,0 +1,457 @@
+//! Underchex Message Catalog
+//!
+//! User-facing text the engine reports (why a move is rejected, how a game
+//! ended, key moments of a game) travels as a [`Message`]: a stable
+//! camelCase key, the same as the reason codes, with named parameters such
+//! as the cells and piece involved. Frontends localize messages by key;
+//! [`english`] renders them with the built-in English catalog.
+//!
+//! Moves are described the same way for screen readers: a
+//! [`MoveDescription`] holds the piece, cell names and flags an announcement
+//! is made of.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::board::cell_name;
+use crate::moves::{get_piece_at, is_in_check};
+use crate::session::replay_move;
+use crate::types::{BoardState, Color, GameState, GameStatus, HexCoord, Move, PieceType};
+
+/// A message key with its parameters.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Message {
+    pub key: String,
+    /// Parameter values are cell names, numbers or themselves keys (colors
+    /// and pieces)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub params: BTreeMap<String, String>,
+}
+
+impl Message {
+    pub fn new(key: &str) -> Self {
+        Self {
+            key: key.to_string(),
+            params: BTreeMap::new(),
+        }
+    }
+
+    /// Add a parameter.
+    pub fn with(mut self, name: &str, value: impl ToString) -> Self {
+        self.params.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Add a cell parameter, by name (or key if off the board).
+    pub fn with_cell(self, name: &str, cell: HexCoord) -> Self {
+        let value = cell_name(cell).unwrap_or_else(|| cell.to_key());
+        self.with(name, value)
+    }
+}
+
+/// Key of a color: "white" or "black".
+pub fn color_key(color: Color) -> &'static str {
+    match color {
+        Color::White => "white",
+        Color::Black => "black",
+    }
+}
+
+/// Key of a piece type: its name in camelCase, e.g. "promotedLance".
+pub fn piece_type_key(piece_type: PieceType) -> String {
+    let name = format!("{:?}", piece_type);
+    let mut chars = name.chars();
+    chars
+        .next()
+        .map(|first| first.to_ascii_lowercase().to_string() + chars.as_str())
+        .unwrap_or_default()
+}
+
+/// Message of a rejected move from `from` to `to`: the reason with the
+/// cells and, if there is one, the piece on `from`.
+pub fn move_message(reason: &str, board: &BoardState, from: HexCoord, to: HexCoord) -> Message {
+    let message = Message::new(reason)
+        .with_cell("from", from)
+        .with_cell("to", to);
+    match get_piece_at(board, from) {
+        Some(piece) => message.with("piece", piece_type_key(piece.piece_type)),
+        None => message,
+    }
+}
+
+// ============================================================================
+// Move Descriptions
+// ============================================================================
+
+/// What a move did, for spoken announcements. Pieces and colors are keys as
+/// in messages, cells are names such as "e5".
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MoveDescription {
+    pub color: String,
+    pub piece: String,
+    /// None for a drop
+    pub from: Option<String>,
+    pub to: String,
+    pub drop: bool,
+    /// The piece captured on `to`
+    pub captured: Option<String>,
+    /// The piece promoted to
+    pub promotion: Option<String>,
+    /// Where the boulder was placed after the move (boulder variant)
+    pub boulder: Option<String>,
+    /// Whether the move gives check (including checkmate)
+    pub check: bool,
+    pub checkmate: bool,
+}
+
+fn cell_text(cell: HexCoord) -> String {
+    cell_name(cell).unwrap_or_else(|| cell.to_key())
+}
+
+/// Describe the move `mv` as recorded, given the game after it.
+fn describe(mv: &Move, after: &GameState) -> MoveDescription {
+    MoveDescription {
+        color: color_key(mv.piece.color).to_string(),
+        piece: piece_type_key(mv.piece.piece_type),
+        from: (!mv.is_drop).then(|| cell_text(mv.from)),
+        to: cell_text(mv.to),
+        drop: mv.is_drop,
+        captured: mv.captured.map(|piece| piece_type_key(piece.piece_type)),
+        promotion: mv.promotion.map(piece_type_key),
+        boulder: mv.boulder.map(cell_text),
+        check: is_in_check(&after.board, mv.piece.color.opposite()),
+        checkmate: matches!(after.status, GameStatus::Checkmate { .. }),
+    }
+}
+
+/// Describe a move about to be played in a game. Returns None if the move
+/// is not legal.
+pub fn describe_move(state: &GameState, mv: &Move) -> Option<MoveDescription> {
+    let after = replay_move(state, mv)?;
+    let played = after.history.last()?;
+    Some(describe(played, &after))
+}
+
+/// Describe the last move played in a game, or None before the first move.
+pub fn describe_last_move(state: &GameState) -> Option<MoveDescription> {
+    state.history.last().map(|mv| describe(mv, state))
+}
+
+// ============================================================================
+// English
+// ============================================================================
+
+/// English text of every key, with `{name}` for parameters.
+pub const ENGLISH: &[(&str, &str)] = &[
+    // Rejected moves
+    ("noPieceAtSource", "There is no piece on {from}."),
+    ("notYourPiece", "The {piece} on {from} is not yours."),
+    ("invalidDestination", "That piece cannot go to {to}."),
+    (
+        "movesIntoCheck",
+        "That move would leave your king in check.",
+    ),
+    ("illegalMove", "The {piece} on {from} cannot move to {to}."),
+    ("gameOver", "The game is over."),
+    ("boulderPending", "Place the boulder first."),
+    (
+        "forbiddenByVariant",
+        "That move is not allowed in this variant.",
+    ),
+    ("invalidPromotion", "That promotion is not allowed."),
+    ("unknownPromotion", "Unknown promotion piece."),
+    ("notInHand", "You have no {piece} in hand."),
+    ("destinationOccupied", "The hex {to} is occupied."),
+    (
+        "pawnDropOnPromotionHex",
+        "A pawn cannot be dropped on a promotion hex.",
+    ),
+    (
+        "dropMate",
+        "A drop may not deliver checkmate in this variant.",
+    ),
+    ("malformedMove", "That move could not be read."),
+    (
+        "constraintNeverMove",
+        "Your {piece} may not move in this challenge.",
+    ),
+    (
+        "constraintNoCaptures",
+        "Captures are not allowed in this challenge.",
+    ),
+    // Game endings
+    ("checkmate", "{winner} wins by checkmate."),
+    ("stalemate", "Draw by stalemate."),
+    ("stalemateWin", "{winner} wins by stalemate."),
+    (
+        "stalemateShare",
+        "{winner} takes {points} of the point by stalemate.",
+    ),
+    ("armageddonDraw", "Drawn armageddon: {winner} wins."),
+    ("resignation", "{winner} wins by resignation."),
+    ("timeout", "{winner} wins on time."),
+    ("threefoldRepetition", "Draw by threefold repetition."),
+    ("fiftyMoveRule", "Draw by the fifty-move rule."),
+    ("perpetualCheck", "Draw by perpetual check."),
+    ("moveLimit", "Draw by the move limit."),
+    ("kingOfTheHill", "{winner} wins by reaching the hill."),
+    ("kingReachedGoal", "{winner} wins by reaching the far edge."),
+    (
+        "bothKingsReachedGoal",
+        "Draw: both kings reached the far edge.",
+    ),
+    ("bareKing", "{winner} wins by baring the king."),
+    (
+        "allCaptured",
+        "{winner} wins by capturing every piece of a kind.",
+    ),
+    (
+        "bothArmiesReduced",
+        "Draw: both armies were reduced at once.",
+    ),
+    // Key moments
+    (
+        "firstBlunder",
+        "Move {moveNumber}: {color}'s first blunder.",
+    ),
+    ("turningPoint", "Move {moveNumber}: the turning point."),
+    (
+        "materialSwing",
+        "Move {moveNumber}: material changed hands.",
+    ),
+    (
+        "longestThink",
+        "Move {moveNumber}: {color}'s longest think.",
+    ),
+    // Coach advice
+    (
+        "adviceOpening",
+        "Develop your pieces and contest the center.",
+    ),
+    (
+        "adviceMiddlegame",
+        "Look for tactics and keep your king safe.",
+    ),
+    ("adviceEndgame", "Activate your king and push your pawns."),
+    // Challenges
+    ("scenarioLost", "Challenge failed: the game was lost."),
+    ("scenarioDrawn", "Challenge failed: the game was drawn."),
+    ("scenarioOutOfMoves", "Challenge failed: out of moves."),
+    (
+        "scenarioPieceLost",
+        "Challenge failed: your {piece} was lost.",
+    ),
+    // Resignation advisories
+    (
+        "resignTablebaseLoss",
+        "This endgame is lost with best play.",
+    ),
+    (
+        "resignMateIncoming",
+        "Your opponent can force mate in {moves}.",
+    ),
+    (
+        "resignLostEval",
+        "This position is lost, with no counterplay left.",
+    ),
+    // Endgame plans
+    ("planConfineKing", "Drive the {color} king to the edge."),
+    ("planApproachKing", "Bring the {color} king up."),
+    ("planPromote", "Promote the pawn to a {piece}."),
+    ("planMateOnCorner", "Deliver mate on the corner hex {cell}."),
+    ("planMateOnEdge", "Deliver mate on the edge hex {cell}."),
+    ("planMate", "Deliver mate on {cell}."),
+    // Mate patterns
+    (
+        "mateQueenEdge",
+        "Pin the king to the edge with the queen one row in, and guard the queen with your king.",
+    ),
+    (
+        "mateQueenCorner",
+        "Take the corner king's flight hexes with the queen next to it, guarded by your king.",
+    ),
+    (
+        "mateLanceCorner",
+        "Check the corner king along a lance line while your king covers its flight hexes.",
+    ),
+    // Invariant violations
+    ("missingKing", "{color} has no king."),
+    ("extraKing", "{color} has more than one king."),
+    (
+        "kingInCheckOffTurn",
+        "{color}'s king is in check with {color} not to move.",
+    ),
+    (
+        "illegalHistoryMove",
+        "Move {ply} of the history is not legal.",
+    ),
+    (
+        "historyMismatch",
+        "The history does not replay to this position.",
+    ),
+    ("moveNumberTooLow", "The move number is below {expected}."),
+    (
+        "halfMoveClockMismatch",
+        "The half-move clock should be {expected}.",
+    ),
+    (
+        "statusMismatch",
+        "The status does not match the legal moves.",
+    ),
+    // Parameter values
+    ("white", "White"),
+    ("black", "Black"),
+    ("pawn", "pawn"),
+    ("king", "king"),
+    ("queen", "queen"),
+    ("knight", "knight"),
+    ("lance", "lance"),
+    ("chariot", "chariot"),
+    ("boulder", "boulder"),
+    ("wazir", "wazir"),
+    ("ferz", "ferz"),
+    ("dabbaba", "dabbaba"),
+    ("cannon", "cannon"),
+    ("promotedKnight", "promoted knight"),
+    ("promotedLance", "promoted lance"),
+];
+
+fn english_text(key: &str) -> Option<&'static str> {
+    ENGLISH
+        .iter()
+        .find(|(entry, _)| *entry == key)
+        .map(|&(_, text)| text)
+}
+
+/// Render a message in English. Parameter values that are keys are
+/// rendered too; an unknown key renders as itself.
+pub fn english(message: &Message) -> String {
+    let Some(text) = english_text(&message.key) else {
+        return message.key.clone();
+    };
+    let mut text = text.to_string();
+    for (name, value) in &message.params {
+        let value = english_text(value).unwrap_or(value);
+        text = text.replace(&format!("{{{}}}", name), value);
+    }
+    // Sentences may open with a parameter
+    let mut chars = text.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().collect::<String>() + chars.as_str())
+        .unwrap_or_default()
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::create_new_game;
+
+    #[test]
+    fn test_piece_keys_have_english() {
+        for piece_type in [
+            PieceType::Pawn,
+            PieceType::King,
+            PieceType::Queen,
+            PieceType::Knight,
+            PieceType::Lance,
+            PieceType::Chariot,
+            PieceType::Boulder,
+            PieceType::Wazir,
+            PieceType::Ferz,
+            PieceType::Dabbaba,
+            PieceType::Cannon,
+            PieceType::PromotedKnight,
+            PieceType::PromotedLance,
+        ] {
+            assert!(
+                english_text(&piece_type_key(piece_type)).is_some(),
+                "{:?}",
+                piece_type
+            );
+        }
+        assert_eq!(piece_type_key(PieceType::PromotedLance), "promotedLance");
+    }
+
+    #[test]
+    fn test_describe_move() {
+        use crate::game::make_move;
+
+        let start = create_new_game();
+        let pawn = Move::new(
+            *get_piece_at(&start.board, HexCoord::new(0, 2)).unwrap(),
+            HexCoord::new(0, 2),
+            HexCoord::new(0, 1),
+        );
+        let description = describe_move(&start, &pawn).unwrap();
+        assert_eq!(description.piece, "pawn");
+        assert_eq!(description.color, "white");
+        assert_eq!(
+            (description.from.as_deref(), description.to.as_str()),
+            (Some("e3"), "e4")
+        );
+        assert!(!description.check && description.captured.is_none());
+        let after = make_move(&start, HexCoord::new(0, 2), HexCoord::new(0, 1)).unwrap();
+        assert_eq!(describe_last_move(&after), Some(description));
+        assert!(describe_last_move(&start).is_none());
+
+        // Illegal: a black pawn on White's turn
+        let black = Move::new(
+            *get_piece_at(&start.board, HexCoord::new(0, -2)).unwrap(),
+            HexCoord::new(0, -2),
+            HexCoord::new(0, -1),
+        );
+        assert!(describe_move(&start, &black).is_none());
+
+        // The queen takes on (0, -2) and mates the boxed-in king
+        let mut board = BoardState::new();
+        let mut place = |q, r, piece_type, color| {
+            board.insert(
+                HexCoord::new(q, r).to_key(),
+                crate::types::Piece::new(piece_type, color),
+            );
+        };
+        place(0, 4, PieceType::King, Color::White);
+        place(2, -4, PieceType::Queen, Color::White);
+        place(0, -4, PieceType::King, Color::Black);
+        place(-1, -3, PieceType::Pawn, Color::Black);
+        place(1, -4, PieceType::Pawn, Color::Black);
+        place(0, -2, PieceType::Pawn, Color::Black);
+        let state = crate::game::create_game_from_position(board, Color::White);
+        let queen = Move::new(
+            *get_piece_at(&state.board, HexCoord::new(2, -4)).unwrap(),
+            HexCoord::new(2, -4),
+            HexCoord::new(0, -2),
+        );
+        let description = describe_move(&state, &queen).unwrap();
+        assert_eq!(description.captured.as_deref(), Some("pawn"));
+        assert!(description.check && description.checkmate);
+    }
+
+    #[test]
+    fn test_english() {
+        let board = create_new_game().board;
+        let message = move_message(
+            "notYourPiece",
+            &board,
+            HexCoord::new(0, -2),
+            HexCoord::new(0, -1),
+        );
+        assert_eq!(message.params["piece"], "pawn");
+        assert_eq!(english(&message), "The pawn on e7 is not yours.");
+
+        let won = Message::new("checkmate").with("winner", color_key(Color::Black));
+        assert_eq!(english(&won), "Black wins by checkmate.");
+        let occupied = Message::new("destinationOccupied").with("to", "e5");
+        assert_eq!(english(&occupied), "The hex e5 is occupied.");
+        assert_eq!(english(&Message::new("somethingNew")), "somethingNew");
+
+        let json = serde_json::to_string(&Message::new("gameOver")).unwrap();
+        assert_eq!(json, r#"{"key":"gameOver"}"#);
+    }
+}
@@ -2,10 +2,17 @@
 //!
 //! Signed-by: agent #21 claude-sonnet-4 via opencode 20260122T06:31:01
 
-use crate::board::{get_knight_targets, get_neighbor, get_ray, is_valid_cell};
+use serde::{Deserialize, Serialize};
+
+use crate::board::{
+    get_all_cells, get_neighbor, get_ray, is_valid_cell, occupied_cells, pieces_of, pieces_of_type,
+};
+use crate::messages::{move_message, piece_type_key, Message};
+use crate::pieces::{all_leap_offsets, get_piece_definition};
+use crate::trace::{trace_event, trace_span};
 use crate::types::{
-    is_promotion_zone, BoardState, Color, Direction, HexCoord, Move, Piece, PieceType,
-    PROMOTION_TARGETS,
+    is_promotion_zone, BoardState, Color, Direction, Hand, Hands, HexCoord, LanceVariant, Move,
+    Piece, PieceType, PROMOTION_TARGETS,
 };
 
 // ============================================================================
@@ -21,7 +28,7 @@ pub fn get_forward_direction(color: Color) -> Direction {
 }
 
 /// Get pawn capture directions for a color.
-pub fn get_pawn_capture_directions(color: Color) -> &'static [Direction] {
+pub(crate) fn get_pawn_capture_directions(color: Color) -> &'static [Direction] {
     match color {
         Color::White => &[Direction::N, Direction::NE, Direction::NW],
         Color::Black => &[Direction::S, Direction::SE, Direction::SW],
@@ -42,14 +49,27 @@ pub fn is_occupied(board: &BoardState, coord: HexCoord) -> bool {
     board.contains_key(&coord.to_key())
 }
 
-/// Check if a cell has an enemy piece.
+/// Check if a cell has an enemy piece (the neutral boulder is nobody's enemy).
 pub fn has_enemy(board: &BoardState, coord: HexCoord, color: Color) -> bool {
-    get_piece_at(board, coord).is_some_and(|p| p.color != color)
+    enemy_at(board, coord, color).is_some()
 }
 
-/// Check if a cell has a friendly piece.
+/// The enemy piece on a cell, if any (never the boulder).
+pub fn enemy_at(board: &BoardState, coord: HexCoord, color: Color) -> Option<&Piece> {
+    get_piece_at(board, coord).filter(|p| p.color != color && !p.is_boulder())
+}
+
+/// Check if a cell has a friendly piece. The neutral boulder counts as
+/// friendly to both sides: it blocks movement and cannot be captured.
 pub fn has_friendly(board: &BoardState, coord: HexCoord, color: Color) -> bool {
-    get_piece_at(board, coord).is_some_and(|p| p.color == color)
+    get_piece_at(board, coord).is_some_and(|p| p.color == color || p.is_boulder())
+}
+
+/// Find the boulder, if one is on the board.
+pub fn find_boulder(board: &BoardState) -> Option<HexCoord> {
+    occupied_cells(board)
+        .find(|(_, piece)| piece.is_boulder())
+        .map(|(cell, _)| cell)
 }
 
 // ============================================================================
@@ -62,11 +82,7 @@ pub fn generate_pseudo_legal_moves(board: &BoardState, piece: &Piece, from: HexC
 
     match piece.piece_type {
         PieceType::Pawn => generate_pawn_moves(board, piece, from, &mut moves),
-        PieceType::King => generate_king_moves(board, piece, from, &mut moves),
-        PieceType::Knight => generate_knight_moves(board, piece, from, &mut moves),
-        PieceType::Queen | PieceType::Lance | PieceType::Chariot => {
-            generate_slider_moves(board, piece, from, &mut moves)
-        }
+        _ => generate_defined_moves(board, piece, from, &mut moves),
     }
 
     moves
@@ -93,8 +109,7 @@ fn generate_pawn_moves(board: &BoardState, piece: &Piece, from: HexCoord, moves:
     // Captures (including forward capture)
     for &dir in capture_directions {
         if let Some(target) = get_neighbor(from, dir) {
-            if has_enemy(board, target, piece.color) {
-                let captured = *get_piece_at(board, target).unwrap();
+            if let Some(&captured) = enemy_at(board, target, piece.color) {
                 if is_promotion_zone(target, piece.color) {
                     // Generate promotion captures for each target piece type
                     for &promo_type in PROMOTION_TARGETS {
@@ -112,22 +127,28 @@ fn generate_pawn_moves(board: &BoardState, piece: &Piece, from: HexCoord, moves:
     }
 }
 
-fn generate_king_moves(board: &BoardState, piece: &Piece, from: HexCoord, moves: &mut Vec<Move>) {
-    for &dir in Direction::all() {
-        if let Some(target) = get_neighbor(from, dir) {
-            if !has_friendly(board, target, piece.color) {
-                let mut mv = Move::new(*piece, from, target);
-                if let Some(&captured) = get_piece_at(board, target) {
-                    mv = mv.with_capture(captured);
-                }
-                moves.push(mv);
-            }
-        }
-    }
-}
-
-fn generate_knight_moves(board: &BoardState, piece: &Piece, from: HexCoord, moves: &mut Vec<Move>) {
-    for target in get_knight_targets(from) {
+/// Generate moves for any piece described by its [`PieceDefinition`]: single
+/// steps, leaps (which ignore intervening pieces), slides and cannon hops.
+///
+/// [`PieceDefinition`]: crate::pieces::PieceDefinition
+fn generate_defined_moves(
+    board: &BoardState,
+    piece: &Piece,
+    from: HexCoord,
+    moves: &mut Vec<Move>,
+) {
+    let definition = get_piece_definition(piece);
+
+    let steps = definition
+        .steps
+        .iter()
+        .filter_map(|&dir| get_neighbor(from, dir));
+    let leaps = definition
+        .leaps
+        .iter()
+        .map(|&(dq, dr)| HexCoord::new(from.q + dq, from.r + dr))
+        .filter(|&target| is_valid_cell(target));
+    for target in steps.chain(leaps) {
         if !has_friendly(board, target, piece.color) {
             let mut mv = Move::new(*piece, from, target);
             if let Some(&captured) = get_piece_at(board, target) {
@@ -136,12 +157,8 @@ fn generate_knight_moves(board: &BoardState, piece: &Piece, from: HexCoord, move
             moves.push(mv);
         }
     }
-}
 
-fn generate_slider_moves(board: &BoardState, piece: &Piece, from: HexCoord, moves: &mut Vec<Move>) {
-    let directions = piece.directions();
-
-    for &dir in directions {
+    for &dir in definition.slides {
         let ray = get_ray(from, dir);
         for target in ray {
             if has_friendly(board, target, piece.color) {
@@ -156,6 +173,24 @@ fn generate_slider_moves(board: &BoardState, piece: &Piece, from: HexCoord, move
             moves.push(mv);
         }
     }
+
+    for &dir in definition.hops {
+        let mut screened = false;
+        for target in get_ray(from, dir) {
+            if !is_occupied(board, target) {
+                if !screened {
+                    moves.push(Move::new(*piece, from, target));
+                }
+            } else if !screened {
+                screened = true; // Jump over the screen
+            } else {
+                if let Some(&captured) = enemy_at(board, target, piece.color) {
+                    moves.push(Move::new(*piece, from, target).with_capture(captured));
+                }
+                break;
+            }
+        }
+    }
 }
 
 // ============================================================================
@@ -164,12 +199,9 @@ fn generate_slider_moves(board: &BoardState, piece: &Piece, from: HexCoord, move
 
 /// Find the king of a given color.
 pub fn find_king(board: &BoardState, color: Color) -> Option<HexCoord> {
-    for (pos_str, piece) in board.iter() {
-        if piece.piece_type == PieceType::King && piece.color == color {
-            return HexCoord::from_key(pos_str);
-        }
-    }
-    None
+    pieces_of_type(board, color, PieceType::King)
+        .next()
+        .map(|(cell, _)| cell)
 }
 
 /// Check if a square is attacked by any piece of the given color.
@@ -188,21 +220,24 @@ pub fn is_attacked(board: &BoardState, target: HexCoord, by_color: Color) -> boo
         }
     }
 
-    // Check for king attacks
+    // Check for stepping pieces (king, wazir, ferz)
     for &dir in Direction::all() {
         if let Some(attacker) = get_neighbor(target, dir) {
             if let Some(piece) = get_piece_at(board, attacker) {
-                if piece.piece_type == PieceType::King && piece.color == by_color {
+                if piece.color == by_color
+                    && get_piece_definition(piece).steps.contains(&dir.opposite())
+                {
                     return true;
                 }
             }
         }
     }
 
-    // Check for knight attacks
-    for attacker_pos in get_knight_targets(target) {
-        if let Some(piece) = get_piece_at(board, attacker_pos) {
-            if piece.piece_type == PieceType::Knight && piece.color == by_color {
+    // Check for leaping pieces (knight, dabbaba)
+    for (dq, dr) in all_leap_offsets() {
+        let attacker = HexCoord::new(target.q - dq, target.r - dr);
+        if let Some(piece) = get_piece_at(board, attacker) {
+            if piece.color == by_color && get_piece_definition(piece).leaps.contains(&(dq, dr)) {
                 return true;
             }
         }
@@ -219,7 +254,7 @@ pub fn is_attacked(board: &BoardState, target: HexCoord, by_color: Color) -> boo
 
                 // Check if this piece can attack along this direction
                 let reverse_dir = dir.opposite();
-                if piece.is_slider() && piece.directions().contains(&reverse_dir) {
+                if get_piece_definition(piece).slides.contains(&reverse_dir) {
                     return true;
                 }
                 break; // Blocked by this piece either way
@@ -227,6 +262,19 @@ pub fn is_attacked(board: &BoardState, target: HexCoord, by_color: Color) -> boo
         }
     }
 
+    // Check for hopping attacks (cannon): the second piece along a ray
+    for &dir in Direction::all() {
+        let mut pieces = get_ray(target, dir)
+            .into_iter()
+            .filter_map(|pos| get_piece_at(board, pos));
+        if let (Some(_screen), Some(piece)) = (pieces.next(), pieces.next()) {
+            if piece.color == by_color && get_piece_definition(piece).hops.contains(&dir.opposite())
+            {
+                return true;
+            }
+        }
+    }
+
     false
 }
 
@@ -244,22 +292,38 @@ pub fn is_in_check(board: &BoardState, color: Color) -> bool {
 // ============================================================================
 
 /// Apply a move to a board state (returns new board state).
-/// Handles pawn promotion by replacing the piece.
+/// Handles pawn promotion by replacing the piece, and moves the boulder if
+/// the move carries a boulder placement.
 pub fn apply_move(board: &BoardState, mv: &Move) -> BoardState {
     let mut new_board = board.clone();
     new_board.remove(&mv.from.to_key());
 
-    // Handle promotion
-    let piece_to_place = if let Some(promo_type) = mv.promotion {
-        Piece::new(promo_type, mv.piece.color)
-    } else {
-        mv.piece
+    // Handle promotion (a promoted lance keeps its variant)
+    let piece_to_place = match mv.promotion {
+        Some(PieceType::PromotedLance) => Piece {
+            piece_type: PieceType::PromotedLance,
+            ..mv.piece
+        },
+        Some(promo_type) => Piece::new(promo_type, mv.piece.color),
+        None => mv.piece,
     };
 
     new_board.insert(mv.to.to_key(), piece_to_place);
+
+    if let Some(boulder) = mv.boulder {
+        place_boulder(&mut new_board, boulder);
+    }
     new_board
 }
 
+/// Move the boulder (or put it on the board for the first time) onto a cell.
+pub fn place_boulder(board: &mut BoardState, to: HexCoord) {
+    if let Some(old) = find_boulder(board) {
+        board.remove(&old.to_key());
+    }
+    board.insert(to.to_key(), Piece::boulder());
+}
+
 /// Generate all legal moves for a piece.
 pub fn generate_legal_moves(board: &BoardState, piece: &Piece, from: HexCoord) -> Vec<Move> {
     let pseudo_legal = generate_pseudo_legal_moves(board, piece, from);
@@ -277,15 +341,137 @@ pub fn generate_legal_moves(board: &BoardState, piece: &Piece, from: HexCoord) -
 pub fn generate_all_legal_moves(board: &BoardState, color: Color) -> Vec<Move> {
     let mut moves = Vec::new();
 
-    for (pos_str, piece) in board.iter() {
-        if piece.color != color {
-            continue;
-        }
-        if let Some(from) = HexCoord::from_key(pos_str) {
-            moves.extend(generate_legal_moves(board, piece, from));
+    for (from, piece) in pieces_of(board, color) {
+        moves.extend(generate_legal_moves(board, &piece, from));
+    }
+
+    moves
+}
+
+// ============================================================================
+// Perft
+// ============================================================================
+
+/// Count the leaf nodes of the legal move tree `depth` plies deep (standard
+/// rules, no drops or boulders). Used to check move generation against known
+/// counts.
+pub fn perft(board: &BoardState, color: Color, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let moves = generate_all_legal_moves(board, color);
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+    moves
+        .iter()
+        .map(|mv| perft(&apply_move(board, mv), color.opposite(), depth - 1))
+        .sum()
+}
+
+/// [`perft`] split by first move, sorted by origin, destination and promotion
+/// so that runs can be diffed.
+pub fn perft_divide(board: &BoardState, color: Color, depth: u32) -> Vec<(Move, u64)> {
+    let mut moves = generate_all_legal_moves(board, color);
+    moves.sort_by_key(|mv| {
+        (
+            mv.from.q,
+            mv.from.r,
+            mv.to.q,
+            mv.to.r,
+            mv.promotion.map(|p| p as u8),
+        )
+    });
+    moves
+        .into_iter()
+        .map(|mv| {
+            let count = perft(
+                &apply_move(board, &mv),
+                color.opposite(),
+                depth.saturating_sub(1),
+            );
+            (mv, count)
+        })
+        .collect()
+}
+
+// ============================================================================
+// Drops
+// ============================================================================
+
+/// Check if a piece type may be dropped on a cell by a color, ignoring king safety.
+/// The cell must be empty and pawns may not be dropped on their promotion hexes.
+pub fn can_drop_on(board: &BoardState, piece_type: PieceType, color: Color, to: HexCoord) -> bool {
+    is_valid_cell(to)
+        && !is_occupied(board, to)
+        && !(piece_type == PieceType::Pawn && is_promotion_zone(to, color))
+}
+
+/// Pieces that a piece type in hand can be dropped as (lances choose their variant).
+fn drop_pieces(piece_type: PieceType, color: Color) -> Vec<Piece> {
+    match piece_type {
+        PieceType::Lance => vec![
+            Piece::lance(color, LanceVariant::A),
+            Piece::lance(color, LanceVariant::B),
+        ],
+        _ => vec![Piece::new(piece_type, color)],
+    }
+}
+
+/// Generate pseudo-legal drops from a hand (doesn't check for leaving king in check).
+pub fn generate_pseudo_legal_drops(board: &BoardState, hand: &Hand, color: Color) -> Vec<Move> {
+    let mut piece_types: Vec<PieceType> = hand
+        .iter()
+        .filter(|(_, &count)| count > 0)
+        .map(|(&piece_type, _)| piece_type)
+        .collect();
+    piece_types.sort_by_key(|&p| p as u8);
+
+    let cells = get_all_cells();
+    let mut moves = Vec::new();
+    for piece_type in piece_types {
+        for &to in &cells {
+            if can_drop_on(board, piece_type, color, to) {
+                for piece in drop_pieces(piece_type, color) {
+                    moves.push(Move::new_drop(piece, to));
+                }
+            }
         }
     }
+    moves
+}
 
+/// Generate legal drops from a hand.
+pub fn generate_legal_drops(board: &BoardState, hand: &Hand, color: Color) -> Vec<Move> {
+    generate_pseudo_legal_drops(board, hand, color)
+        .into_iter()
+        .filter(|mv| !is_in_check(&apply_move(board, mv), color))
+        .collect()
+}
+
+/// Check if a drop checkmates the opponent, who may answer with drops from `defender_hand`.
+pub fn is_drop_mate(board: &BoardState, mv: &Move, defender_hand: &Hand) -> bool {
+    let defender = mv.piece.color.opposite();
+    let after = apply_move(board, mv);
+    is_in_check(&after, defender)
+        && generate_all_legal_moves(&after, defender).is_empty()
+        && generate_legal_drops(&after, defender_hand, defender).is_empty()
+}
+
+/// Generate all legal moves for a player including drops from their hand.
+/// When `allow_drop_mate` is false, drops that deliver checkmate are excluded.
+pub fn generate_all_legal_moves_with_drops(
+    board: &BoardState,
+    color: Color,
+    hands: &Hands,
+    allow_drop_mate: bool,
+) -> Vec<Move> {
+    let mut moves = generate_all_legal_moves(board, color);
+    moves.extend(
+        generate_legal_drops(board, hands.get(color), color)
+            .into_iter()
+            .filter(|mv| allow_drop_mate || !is_drop_mate(board, mv, hands.get(color.opposite()))),
+    );
     moves
 }
 
@@ -293,11 +479,35 @@ pub fn generate_all_legal_moves(board: &BoardState, color: Color) -> Vec<Move> {
 // Move Validation
 // ============================================================================
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MoveValidation {
     pub legal: bool,
     pub reason: Option<String>,
     pub capture: bool,
+    /// The reason with its parameters, for display
+    #[serde(default)]
+    pub message: Option<Message>,
+}
+
+impl MoveValidation {
+    pub fn legal(capture: bool) -> Self {
+        Self {
+            legal: true,
+            reason: None,
+            capture,
+            message: None,
+        }
+    }
+
+    /// A rejected move; the reason is the message's key.
+    pub fn rejected(message: Message) -> Self {
+        Self {
+            legal: false,
+            reason: Some(message.key.clone()),
+            capture: false,
+            message: Some(message),
+        }
+    }
 }
 
 /// Validate a specific move.
@@ -307,56 +517,80 @@ pub fn validate_move(
     to: HexCoord,
     turn: Color,
 ) -> MoveValidation {
+    trace_span!("validate_move", from = %from.to_key(), to = %to.to_key(), ?turn);
+    let validation = check_move(board, from, to, turn);
+    trace_event!(legal = validation.legal, reason = ?validation.reason);
+    validation
+}
+
+fn check_move(board: &BoardState, from: HexCoord, to: HexCoord, turn: Color) -> MoveValidation {
+    let reject = |reason| MoveValidation::rejected(move_message(reason, board, from, to));
     let piece = match get_piece_at(board, from) {
         Some(p) => p,
-        None => {
-            return MoveValidation {
-                legal: false,
-                reason: Some("noPieceAtSource".to_string()),
-                capture: false,
-            }
-        }
+        None => return reject("noPieceAtSource"),
     };
 
     if piece.color != turn {
-        return MoveValidation {
-            legal: false,
-            reason: Some("notYourPiece".to_string()),
-            capture: false,
-        };
+        return reject("notYourPiece");
     }
 
     if !is_valid_cell(to) {
-        return MoveValidation {
-            legal: false,
-            reason: Some("invalidDestination".to_string()),
-            capture: false,
-        };
+        return reject("invalidDestination");
     }
 
     let legal_moves = generate_legal_moves(board, piece, from);
     if let Some(matching_move) = legal_moves.iter().find(|m| m.to == to) {
-        return MoveValidation {
-            legal: true,
-            reason: None,
-            capture: matching_move.captured.is_some(),
-        };
+        return MoveValidation::legal(matching_move.captured.is_some());
     }
 
     // Check if it would leave king in check
     let pseudo_legal = generate_pseudo_legal_moves(board, piece, from);
     if pseudo_legal.iter().any(|m| m.to == to) {
-        return MoveValidation {
-            legal: false,
-            reason: Some("movesIntoCheck".to_string()),
-            capture: false,
-        };
+        return reject("movesIntoCheck");
     }
 
-    MoveValidation {
-        legal: false,
-        reason: Some("illegalMove".to_string()),
-        capture: false,
+    reject("illegalMove")
+}
+
+/// Validate a drop of a piece from the hand.
+pub fn validate_drop(
+    board: &BoardState,
+    hands: &Hands,
+    piece_type: PieceType,
+    to: HexCoord,
+    turn: Color,
+    allow_drop_mate: bool,
+) -> MoveValidation {
+    let reason = if hands.get(turn).get(&piece_type).copied().unwrap_or(0) == 0 {
+        Some("notInHand")
+    } else if !is_valid_cell(to) {
+        Some("invalidDestination")
+    } else if is_occupied(board, to) {
+        Some("destinationOccupied")
+    } else if !can_drop_on(board, piece_type, turn, to) {
+        Some("pawnDropOnPromotionHex")
+    } else {
+        let piece = drop_pieces(piece_type, turn)
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| Piece::new(piece_type, turn));
+        let mv = Move::new_drop(piece, to);
+        if is_in_check(&apply_move(board, &mv), turn) {
+            Some("movesIntoCheck")
+        } else if !allow_drop_mate && is_drop_mate(board, &mv, hands.get(turn.opposite())) {
+            Some("dropMate")
+        } else {
+            None
+        }
+    };
+
+    match reason {
+        Some(reason) => MoveValidation::rejected(
+            Message::new(reason)
+                .with("piece", piece_type_key(piece_type))
+                .with_cell("to", to),
+        ),
+        None => MoveValidation::legal(false),
     }
 }
 
@@ -486,4 +720,157 @@ mod tests {
         // But can move NE, NW, S, SE, SW
         assert!(legal_moves.len() < 6);
     }
+
+    #[test]
+    fn test_fairy_pieces() {
+        let mut board = create_empty_board();
+        let from = HexCoord::new(0, 0);
+        let count = |piece_type| {
+            let piece = Piece::new(piece_type, Color::White);
+            generate_pseudo_legal_moves(&board, &piece, from).len()
+        };
+        assert_eq!(count(PieceType::Wazir), 6);
+        assert_eq!(count(PieceType::Ferz), 4);
+        assert_eq!(count(PieceType::Dabbaba), 6);
+
+        // The dabbaba leaps over a blocker and attacks two cells away
+        board.insert(
+            HexCoord::new(0, 1).to_key(),
+            Piece::new(PieceType::Pawn, Color::White),
+        );
+        board.insert(
+            HexCoord::new(0, 2).to_key(),
+            Piece::new(PieceType::Dabbaba, Color::Black),
+        );
+        assert!(is_attacked(&board, from, Color::Black));
+        assert!(!is_attacked(&board, HexCoord::new(0, -1), Color::Black));
+
+        // The ferz does not attack along N/S
+        board.clear();
+        board.insert(
+            HexCoord::new(0, 1).to_key(),
+            Piece::new(PieceType::Ferz, Color::Black),
+        );
+        assert!(!is_attacked(&board, from, Color::Black));
+        assert!(is_attacked(&board, HexCoord::new(1, 0), Color::Black));
+    }
+
+    #[test]
+    fn test_cannon_moves_and_attacks() {
+        let mut board = create_empty_board();
+        let cannon = Piece::new(PieceType::Cannon, Color::White);
+        let from = HexCoord::new(0, 2);
+        board.insert(from.to_key(), cannon);
+        board.insert(
+            HexCoord::new(0, 0).to_key(),
+            Piece::new(PieceType::Pawn, Color::Black),
+        );
+        board.insert(
+            HexCoord::new(0, -2).to_key(),
+            Piece::new(PieceType::King, Color::Black),
+        );
+
+        let moves = generate_pseudo_legal_moves(&board, &cannon, from);
+        // Cannot capture the adjacent screen, but jumps it to capture the king
+        assert!(moves
+            .iter()
+            .any(|m| m.to == HexCoord::new(0, 1) && m.captured.is_none()));
+        assert!(!moves.iter().any(|m| m.to == HexCoord::new(0, 0)));
+        assert!(!moves.iter().any(|m| m.to == HexCoord::new(0, -1)));
+        assert!(moves
+            .iter()
+            .any(|m| m.to == HexCoord::new(0, -2) && m.captured.is_some()));
+        assert!(is_in_check(&board, Color::Black));
+
+        // Without a screen there is no attack
+        board.remove("0,0");
+        assert!(!is_in_check(&board, Color::Black));
+    }
+
+    /// Black king boxed in by its own pawns; a white queen on (0,-2) mates.
+    fn create_drop_mate_position() -> BoardState {
+        let mut board = create_empty_board();
+        board.insert("0,4".to_string(), Piece::new(PieceType::King, Color::White));
+        board.insert(
+            "0,-4".to_string(),
+            Piece::new(PieceType::King, Color::Black),
+        );
+        board.insert(
+            "-1,-3".to_string(),
+            Piece::new(PieceType::Pawn, Color::Black),
+        );
+        board.insert(
+            "1,-4".to_string(),
+            Piece::new(PieceType::Pawn, Color::Black),
+        );
+        board
+    }
+
+    #[test]
+    fn test_drop_generation() {
+        let board = create_drop_mate_position();
+        let mut hand = Hand::new();
+        hand.insert(PieceType::Pawn, 1);
+        hand.insert(PieceType::Lance, 1);
+
+        let drops = generate_legal_drops(&board, &hand, Color::White);
+        let empty_cells = 61 - board.len();
+        let pawn_drops = drops
+            .iter()
+            .filter(|m| m.piece.piece_type == PieceType::Pawn)
+            .count();
+        // No pawn drops on the 5 cells of white's promotion row (2 are occupied)
+        assert_eq!(pawn_drops, empty_cells - 3);
+        // Lances can be dropped as either variant
+        let lance_drops = drops
+            .iter()
+            .filter(|m| m.piece.piece_type == PieceType::Lance)
+            .count();
+        assert_eq!(lance_drops, empty_cells * 2);
+        assert!(drops.iter().all(|m| m.is_drop && m.from == m.to));
+    }
+
+    #[test]
+    fn test_drop_mate_rule() {
+        let board = create_drop_mate_position();
+        let mut hands = Hands::default();
+        hands.add(Color::White, PieceType::Queen);
+        let mate = Move::new_drop(
+            Piece::new(PieceType::Queen, Color::White),
+            HexCoord::new(0, -2),
+        );
+
+        assert!(is_drop_mate(&board, &mate, hands.get(Color::Black)));
+        let allowed = generate_all_legal_moves_with_drops(&board, Color::White, &hands, true);
+        assert!(allowed.contains(&mate));
+        let forbidden = generate_all_legal_moves_with_drops(&board, Color::White, &hands, false);
+        assert!(!forbidden.contains(&mate));
+
+        let validation = validate_drop(
+            &board,
+            &hands,
+            PieceType::Queen,
+            HexCoord::new(0, -2),
+            Color::White,
+            false,
+        );
+        assert_eq!(validation.reason.as_deref(), Some("dropMate"));
+
+        // A pawn in black's hand can block on (0,-3)
+        hands.add(Color::Black, PieceType::Pawn);
+        assert!(!is_drop_mate(&board, &mate, hands.get(Color::Black)));
+    }
+
+    #[test]
+    fn test_perft() {
+        let board = crate::game::create_new_game().board;
+        assert_eq!(perft(&board, Color::White, 0), 1);
+        assert_eq!(perft(&board, Color::White, 1), 16);
+        assert_eq!(perft(&board, Color::White, 2), 256);
+        assert_eq!(perft(&board, Color::White, 3), 4432);
+
+        let divide = perft_divide(&board, Color::White, 2);
+        assert_eq!(divide.len(), 16);
+        assert_eq!(divide.iter().map(|(_, count)| count).sum::<u64>(), 256);
+    }
 }
@@ -0,0 +1,494 @@
+//! Underchex Position Notation (HexFEN)
+//!
+//! HexFEN describes a position as `<board> <side to move>`:
+//! - The board lists rows from r = -4 (black's back rank) to r = 4, separated by `/`.
+//! - Each row lists the cells q = -4..=4 in increasing order. Rows span the full
+//!   axial range rather than just the hexagon so that pieces placed outside it
+//!   (as in the current preliminary starting setup) survive a round trip.
+//! - Pieces use `K Q C N P` (white uppercase, black lowercase); lances are written
+//!   with their variant, e.g. `La` / `lb`. Variant and fairy pieces use the
+//!   letters from their [`PieceDefinition`] (`X W F D H`, and `E` / `Ra`
+//!   for promoted knights and lances). Runs of empty cells
+//!   are digits.
+//!
+//! [`PieceDefinition`]: crate::pieces::PieceDefinition
+//! - The side to move is `w` or `b`.
+//!
+//! Example (starting position):
+//! `2nlbklac2/2cq2np1/2ppppp2/9/9/9/2PPPPP2/1PN2QC2/2CLaKLbN2 w`
+//!
+//! Moves are written in short algebraic notation ([`move_to_san`]): the
+//! piece letter (none for a pawn), the source cell for a pawn capture or
+//! when another piece of the kind could also go there, `x` for a capture, the destination,
+//! `=` and the new piece for a promotion, `/@` and the cell of a boulder
+//! placement, and `+` for check or `#` for mate. Drops are written as the
+//! piece letter, `@` and the cell, e.g. `N@e5`.
+//!
+//! A game position ([`game_to_hexfen`]) adds the fields draw rules need, so
+//! that they behave the same after a save and load:
+//! `<board> <side to move> <half-move clock> <move number> <history>`.
+//! The history lists the positions since the last pawn move or capture (the
+//! only ones the current position can repeat), oldest first, as 16-digit hex
+//! Zobrist hashes joined by `,`, each with a `+` if its side to move was in
+//! check; `-` if there are none.
+
+use crate::board::cell_name;
+use crate::game::{create_game_from_position, determine_repetition_status};
+use crate::moves::{apply_move, generate_all_legal_moves, is_in_check};
+use crate::pieces::{get_type_definition, piece_type_from_symbol};
+use crate::types::{
+    BoardState, Color, GameState, GameStatus, HexCoord, LanceVariant, Move, Piece, PieceType,
+    PositionRecord, BOARD_RADIUS,
+};
+
+// ============================================================================
+// Piece Characters
+// ============================================================================
+
+fn piece_char(piece_type: PieceType) -> char {
+    get_type_definition(piece_type).symbol.to_ascii_lowercase()
+}
+
+fn piece_type_from_char(c: char) -> Option<PieceType> {
+    piece_type_from_symbol(c)
+}
+
+/// Encode a piece as its HexFEN token (e.g. "Q", "n", "La").
+pub fn piece_to_token(piece: &Piece) -> String {
+    let c = piece_char(piece.piece_type);
+    let mut token = match piece.color {
+        Color::White => c.to_ascii_uppercase().to_string(),
+        Color::Black => c.to_string(),
+    };
+    if matches!(
+        piece.piece_type,
+        PieceType::Lance | PieceType::PromotedLance
+    ) {
+        token.push(match piece.variant {
+            Some(LanceVariant::A) => 'a',
+            Some(LanceVariant::B) | None => 'b',
+        });
+    }
+    token
+}
+
+/// Parse a single HexFEN piece token (e.g. "Q", "n", "La").
+pub fn parse_piece_token(token: &str) -> Option<Piece> {
+    let mut chars = token.chars();
+    let c = chars.next()?;
+    let piece_type = piece_type_from_char(c)?;
+    let color = if c.is_ascii_uppercase() {
+        Color::White
+    } else {
+        Color::Black
+    };
+    let lance_variant = |variant| Piece {
+        piece_type,
+        color,
+        variant: Some(variant),
+    };
+    let piece = match (piece_type, chars.next()) {
+        (PieceType::Lance | PieceType::PromotedLance, Some('a')) => lance_variant(LanceVariant::A),
+        (PieceType::Lance | PieceType::PromotedLance, Some('b')) => lance_variant(LanceVariant::B),
+        (PieceType::Lance | PieceType::PromotedLance, _) => return None,
+        (_, None) => Piece::new(piece_type, color),
+        (_, Some(_)) => return None,
+    };
+    chars.next().is_none().then_some(piece)
+}
+
+/// Cells of a HexFEN row (fixed r) in increasing q order.
+fn row_cells(r: i32) -> Vec<HexCoord> {
+    (-BOARD_RADIUS..=BOARD_RADIUS)
+        .map(|q| HexCoord::new(q, r))
+        .collect()
+}
+
+// ============================================================================
+// Encoding / Decoding
+// ============================================================================
+
+/// Encode a board and side to move as a HexFEN string.
+pub fn board_to_hexfen(board: &BoardState, side_to_move: Color) -> String {
+    let rows: Vec<String> = (-BOARD_RADIUS..=BOARD_RADIUS)
+        .map(|r| {
+            let mut row = String::new();
+            let mut empty = 0;
+            for cell in row_cells(r) {
+                match board.get(&cell.to_key()) {
+                    Some(piece) => {
+                        if empty > 0 {
+                            row.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        row.push_str(&piece_to_token(piece));
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                row.push_str(&empty.to_string());
+            }
+            row
+        })
+        .collect();
+
+    let side = match side_to_move {
+        Color::White => "w",
+        Color::Black => "b",
+    };
+    format!("{} {}", rows.join("/"), side)
+}
+
+/// Parse a HexFEN string into a board and side to move.
+/// Returns None if the string is malformed.
+pub fn parse_hexfen(fen: &str) -> Option<(BoardState, Color)> {
+    let mut fields = fen.split_whitespace();
+    let board_field = fields.next()?;
+    let side_to_move = match fields.next().unwrap_or("w") {
+        "w" => Color::White,
+        "b" => Color::Black,
+        _ => return None,
+    };
+
+    let rows: Vec<&str> = board_field.split('/').collect();
+    if rows.len() != (2 * BOARD_RADIUS + 1) as usize {
+        return None;
+    }
+
+    let mut board = BoardState::new();
+    for (row, r) in rows.iter().zip(-BOARD_RADIUS..=BOARD_RADIUS) {
+        let cells = row_cells(r);
+        let mut index: usize = 0;
+        let mut chars = row.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if let Some(digit) = c.to_digit(10) {
+                let mut count = digit as usize;
+                while let Some(d) = chars.peek().and_then(|d| d.to_digit(10)) {
+                    count = count.checked_mul(10)?.checked_add(d as usize)?;
+                    if count > cells.len() {
+                        return None;
+                    }
+                    chars.next();
+                }
+                index = index.checked_add(count)?;
+                if index > cells.len() {
+                    return None;
+                }
+                continue;
+            }
+
+            let piece_type = piece_type_from_char(c)?;
+            let color = if c.is_ascii_uppercase() {
+                Color::White
+            } else {
+                Color::Black
+            };
+            let piece = if piece_type == PieceType::Lance {
+                let variant = match chars.next()? {
+                    'a' => LanceVariant::A,
+                    'b' => LanceVariant::B,
+                    _ => return None,
+                };
+                Piece::lance(color, variant)
+            } else {
+                Piece::new(piece_type, color)
+            };
+
+            board.insert(cells.get(index)?.to_key(), piece);
+            index += 1;
+        }
+
+        if index != cells.len() {
+            return None;
+        }
+    }
+
+    Some((board, side_to_move))
+}
+
+// ============================================================================
+// Game Positions
+// ============================================================================
+
+/// Encode a game's position with its half-move clock, move number and
+/// repetition history.
+pub fn game_to_hexfen(state: &GameState) -> String {
+    let earlier = state
+        .position_history
+        .split_last()
+        .map_or(&[][..], |(_, earlier)| earlier);
+    let since_reset = earlier.len().min(state.half_move_clock as usize);
+    let history: Vec<String> = earlier
+        .iter()
+        .skip(earlier.len() - since_reset)
+        .map(|record| {
+            let check = if record.in_check { "+" } else { "" };
+            format!("{:016x}{}", record.hash, check)
+        })
+        .collect();
+    let history = if history.is_empty() {
+        "-".to_string()
+    } else {
+        history.join(",")
+    };
+    format!(
+        "{} {} {} {}",
+        board_to_hexfen(&state.board, state.turn),
+        state.half_move_clock,
+        state.move_number,
+        history
+    )
+}
+
+/// Parse a game position written by [`game_to_hexfen`] into a standard game.
+/// Missing fields default to a fresh clock, move 1 and no history. Returns
+/// None if the string is malformed or lists more history than the clock
+/// allows.
+pub fn parse_game_hexfen(fen: &str) -> Option<GameState> {
+    let (board, turn) = parse_hexfen(fen)?;
+    let fields: Vec<&str> = fen.split_whitespace().skip(2).collect();
+    if fields.len() > 3 {
+        return None;
+    }
+    let half_move_clock: u32 = fields.first().map_or(Some(0), |f| f.parse().ok())?;
+    let move_number: u32 = fields.get(1).map_or(Some(1), |f| f.parse().ok())?;
+    let hashes: Vec<&str> = match fields.get(2) {
+        None | Some(&"-") => Vec::new(),
+        Some(field) => field.split(',').collect(),
+    };
+    if move_number == 0 || hashes.len() > half_move_clock as usize {
+        return None;
+    }
+
+    let mut state = create_game_from_position(board, turn);
+    state.half_move_clock = half_move_clock;
+    state.move_number = move_number;
+
+    // Sides to move alternate back from the current position
+    let mut side = turn;
+    let mut earlier = Vec::with_capacity(hashes.len());
+    for hash in hashes.iter().rev() {
+        side = side.opposite();
+        let (hash, in_check) = match hash.strip_suffix('+') {
+            Some(hash) => (hash, true),
+            None => (*hash, false),
+        };
+        if hash.len() != 16 {
+            return None;
+        }
+        earlier.push(PositionRecord {
+            key: String::new(),
+            hash: u64::from_str_radix(hash, 16).ok()?,
+            side_to_move: side,
+            in_check,
+        });
+    }
+    earlier.reverse();
+    earlier.append(&mut state.position_history);
+    state.position_history = earlier;
+
+    if state.status == GameStatus::Ongoing {
+        if let Some(status) = determine_repetition_status(&state.position_history) {
+            state.status = status;
+        }
+    }
+    Some(state)
+}
+
+// ============================================================================
+// Move Notation
+// ============================================================================
+
+/// Write a move played from `board` in short algebraic notation, e.g.
+/// "Qxe5+" (see the module documentation). Cells off the board are
+/// written as "q,r" keys.
+pub fn move_to_san(board: &BoardState, mv: &Move) -> String {
+    let cell = |coord: HexCoord| cell_name(coord).unwrap_or_else(|| coord.to_key());
+    let letter =
+        |piece_type: PieceType| get_type_definition(piece_type).symbol.to_ascii_uppercase();
+    let color = mv.piece.color;
+
+    let mut san = String::new();
+    if mv.is_drop {
+        san.push(letter(mv.piece.piece_type));
+        san.push('@');
+        san.push_str(&cell(mv.to));
+    } else {
+        if mv.piece.piece_type != PieceType::Pawn {
+            san.push(letter(mv.piece.piece_type));
+        }
+        let ambiguous = generate_all_legal_moves(board, color).iter().any(|other| {
+            other.to == mv.to
+                && other.from != mv.from
+                && other.piece.piece_type == mv.piece.piece_type
+        });
+        if ambiguous || (mv.piece.piece_type == PieceType::Pawn && mv.captured.is_some()) {
+            san.push_str(&cell(mv.from));
+        }
+        if mv.captured.is_some() {
+            san.push('x');
+        }
+        san.push_str(&cell(mv.to));
+        if let Some(promotion) = mv.promotion {
+            san.push('=');
+            san.push(letter(promotion));
+        }
+    }
+    if let Some(boulder) = mv.boulder {
+        san.push_str("/@");
+        san.push_str(&cell(boulder));
+    }
+
+    let after = apply_move(board, mv);
+    let opponent = color.opposite();
+    if is_in_check(&after, opponent) {
+        let mated = generate_all_legal_moves(&after, opponent).is_empty();
+        san.push(if mated { '#' } else { '+' });
+    }
+    san
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{claimable_draw, create_new_game};
+    use crate::test_support::play_from;
+
+    #[test]
+    fn test_move_to_san() {
+        let game = create_new_game();
+        let pawn = generate_all_legal_moves(&game.board, Color::White)
+            .into_iter()
+            .find(|mv| mv.from == HexCoord::new(0, 2) && mv.to == HexCoord::new(0, 1))
+            .unwrap();
+        assert_eq!(move_to_san(&game.board, &pawn), "e4");
+
+        // The queen mates next to the black king
+        let (board, side) = parse_hexfen("4kpQ2/3p5/9/9/9/9/9/9/4K4 w").unwrap();
+        let mate = generate_all_legal_moves(&board, side)
+            .into_iter()
+            .find(|mv| mv.to == HexCoord::new(0, -2))
+            .unwrap();
+        assert_eq!(move_to_san(&board, &mate), "Qe7#");
+
+        let mut drop = Move::new(
+            Piece::new(PieceType::Knight, Color::White),
+            HexCoord::new(0, 0),
+            HexCoord::new(0, 0),
+        );
+        drop.is_drop = true;
+        assert_eq!(move_to_san(&board, &drop), "N@e5");
+    }
+
+    #[test]
+    fn test_parse_piece_token() {
+        assert_eq!(
+            parse_piece_token("N"),
+            Some(Piece::new(PieceType::Knight, Color::White))
+        );
+        assert_eq!(
+            parse_piece_token("lb"),
+            Some(Piece::lance(Color::Black, LanceVariant::B))
+        );
+        assert_eq!(parse_piece_token("L"), None);
+        assert_eq!(parse_piece_token("Qx"), None);
+    }
+
+    #[test]
+    fn test_hexfen_roundtrip_starting_position() {
+        let game = create_new_game();
+        let fen = board_to_hexfen(&game.board, game.turn);
+
+        let (board, turn) = parse_hexfen(&fen).unwrap();
+        assert_eq!(board, game.board);
+        assert_eq!(turn, Color::White);
+    }
+
+    #[test]
+    fn test_hexfen_empty_board() {
+        let fen = board_to_hexfen(&BoardState::new(), Color::Black);
+        assert_eq!(fen, "9/9/9/9/9/9/9/9/9 b");
+    }
+
+    #[test]
+    fn test_hexfen_rejects_malformed() {
+        assert!(parse_hexfen("9/9/9 w").is_none());
+        assert!(parse_hexfen("9/9/9/9/9/9/9/9/10 w").is_none());
+        assert!(parse_hexfen("9/9/9/9/9/9/9/9/9 x").is_none());
+        assert!(parse_hexfen("Lx8/9/9/9/9/9/9/9/9 w").is_none());
+        // Digit runs too long for the row, even past usize, are rejected
+        let huge = format!("{}/9/9/9/9/9/9/9/9 w", "9".repeat(40));
+        assert!(parse_hexfen(&huge).is_none());
+        assert!(parse_hexfen("5555555555555555555k/9/9/9/9/9/9/9/9 w").is_none());
+    }
+
+    /// Knights shuffle back and forth, repeating the position.
+    const SHUFFLE: [[i32; 4]; 4] = [
+        [-2, 3, -1, 1],
+        [2, -3, 1, -1],
+        [-1, 1, -2, 3],
+        [1, -1, 2, -3],
+    ];
+
+    #[test]
+    fn test_game_hexfen_keeps_repetitions() {
+        // Pawn moves reset the clock; the shuffle then repeats once
+        let opening = play_from(&create_new_game(), &[[0, 2, 0, 1], [0, -2, 0, -1]]);
+        let game = play_from(&opening, &SHUFFLE);
+        let fen = game_to_hexfen(&game);
+        assert!(fen.starts_with(&board_to_hexfen(&game.board, game.turn)));
+        assert_eq!(fen.split_whitespace().nth(2), Some("4"));
+        assert_eq!(fen.split(',').count(), 4);
+
+        let restored = parse_game_hexfen(&fen).unwrap();
+        assert_eq!(restored.half_move_clock, 4);
+        assert_eq!(restored.move_number, game.move_number);
+        assert_eq!(game_to_hexfen(&restored), fen);
+
+        // The next repetition draws, whether or not the game was restored
+        let expected = play_from(&game, &SHUFFLE).status;
+        assert_eq!(
+            expected,
+            GameStatus::Draw {
+                reason: "threefoldRepetition".to_string()
+            }
+        );
+        assert_eq!(play_from(&restored, &SHUFFLE).status, expected);
+    }
+
+    #[test]
+    fn test_game_hexfen_keeps_fifty_move_claim() {
+        let mut game = create_new_game();
+        game.half_move_clock = 99;
+        let game = play_from(&game, &SHUFFLE[..1]);
+        assert_eq!(claimable_draw(&game), Some("fiftyMoveRule"));
+
+        let restored = parse_game_hexfen(&game_to_hexfen(&game)).unwrap();
+        assert_eq!(restored.half_move_clock, 100);
+        assert_eq!(claimable_draw(&restored), Some("fiftyMoveRule"));
+    }
+
+    #[test]
+    fn test_game_hexfen_fields() {
+        // A bare position is a fresh game
+        let fen = board_to_hexfen(&create_new_game().board, Color::Black);
+        let game = parse_game_hexfen(&fen).unwrap();
+        assert_eq!((game.half_move_clock, game.move_number), (0, 1));
+        assert_eq!(game.position_history.len(), 1);
+        assert!(game_to_hexfen(&game).ends_with(" b 0 1 -"));
+
+        let hash = "0123456789abcdef";
+        assert!(parse_game_hexfen(&format!("{} 1 3 {}+", fen, hash)).is_some());
+        // More history than the clock allows, bad hashes, extra fields
+        assert!(parse_game_hexfen(&format!("{} 0 3 {}", fen, hash)).is_none());
+        assert!(parse_game_hexfen(&format!("{} 1 3 xyz", fen)).is_none());
+        assert!(parse_game_hexfen(&format!("{} 0 0", fen)).is_none());
+        assert!(parse_game_hexfen(&format!("{} 0 1 - x", fen)).is_none());
+    }
+}
@@ -0,0 +1,554 @@
+//! Underchex Engine Options
+//!
+//! The tunable search and evaluation parameters as a table of named options,
+//! in the manner of UCI engine options: each is either a "spin" (an integer
+//! within bounds) or a "check" (on or off). Tuning tools and GUIs list them
+//! with [`list_options`] and change them with [`set_option`], without
+//! recompiling.
+//!
+//! Values are process-wide and read on every use, so a change applies to the
+//! next search. Results computed before it (transposition table entries,
+//! cached analyses) may be stale; [`options_generation`] changes with every
+//! change so that caches can tell. [`with_options`] instead sets values for
+//! one thread's work only, as tuning tools do to try values out.
+
+// The value arrays are indexed by the option itself, which is always in
+// range: `OPTIONS` lists every option in declaration order (see the tests).
+#![allow(clippy::indexing_slicing)]
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicI32, AtomicU64, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ai::{KING_OF_THE_HILL_STEP_BONUS, QUIESCENCE_CHECK_PLIES, RACING_KINGS_STEP_BONUS};
+
+// ============================================================================
+// Option Table
+// ============================================================================
+
+/// Kind of an option, as in UCI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OptionKind {
+    /// An integer between `min` and `max`
+    Spin,
+    /// A boolean, stored as 0 or 1
+    Check,
+}
+
+/// An engine option.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EngineOption {
+    /// Entries of the shared transposition table
+    TTSize,
+    /// Whether leaf nodes are resolved with a quiescence search
+    Quiescence,
+    /// Deepest quiescence search, in plies from the horizon
+    QuiescenceDepth,
+    /// Quiescence plies in which quiet checks are searched
+    QuiescenceCheckPlies,
+    /// Whether transposition table bounds may cut off the search
+    TTCutoffs,
+    /// Centipawns per ring closer to the center
+    CentralityWeight,
+    /// Bonus of a pawn about to promote (scaled down quadratically before)
+    PawnAdvancementWeight,
+    /// Centipawns per legal move
+    MobilityWeight,
+    /// Penalty for being in check
+    CheckPenalty,
+    /// King of the hill bonus per ring closer to the hill
+    KingOfTheHillStep,
+    /// Racing kings bonus per row advanced
+    RacingKingsStep,
+    /// Multiplier of the victim's value when ordering captures (MVV-LVA)
+    MvvLvaScale,
+    /// Ordering bonus of a killer move
+    KillerBonus,
+    /// Ordering bonus of the countermove to the opponent's previous move
+    CountermoveBonus,
+    /// Percent of a quiet move's history score added to its ordering value
+    HistoryScale,
+    /// Whether a singular TT move is searched one ply deeper
+    SingularExtensions,
+    /// How far below the TT move's score every alternative must stay for it
+    /// to count as singular
+    SingularMargin,
+    /// Bonus per flight cell a bare king in a mating net has lost
+    MatingNetWeight,
+    /// Percent of the losing side's defense score added in pawnless endings
+    DefenseWeight,
+}
+
+/// How an option is described and bounded.
+struct OptionSpec {
+    option: EngineOption,
+    name: &'static str,
+    kind: OptionKind,
+    default: i32,
+    min: i32,
+    max: i32,
+    description: &'static str,
+}
+
+const fn spin(
+    option: EngineOption,
+    name: &'static str,
+    default: i32,
+    min: i32,
+    max: i32,
+    description: &'static str,
+) -> OptionSpec {
+    OptionSpec {
+        option,
+        name,
+        kind: OptionKind::Spin,
+        default,
+        min,
+        max,
+        description,
+    }
+}
+
+const fn check(
+    option: EngineOption,
+    name: &'static str,
+    default: bool,
+    description: &'static str,
+) -> OptionSpec {
+    OptionSpec {
+        option,
+        name,
+        kind: OptionKind::Check,
+        default: default as i32,
+        min: 0,
+        max: 1,
+        description,
+    }
+}
+
+/// Every option, in [`EngineOption`] order.
+const OPTIONS: [OptionSpec; 19] = [
+    spin(
+        EngineOption::TTSize,
+        "ttSize",
+        50_000,
+        1,
+        10_000_000,
+        "Entries of the shared transposition table",
+    ),
+    check(
+        EngineOption::Quiescence,
+        "quiescence",
+        true,
+        "Resolve captures and checks at the search horizon",
+    ),
+    spin(
+        EngineOption::QuiescenceDepth,
+        "quiescenceDepth",
+        8,
+        0,
+        32,
+        "Deepest quiescence search in plies",
+    ),
+    spin(
+        EngineOption::QuiescenceCheckPlies,
+        "quiescenceCheckPlies",
+        QUIESCENCE_CHECK_PLIES,
+        0,
+        8,
+        "Quiescence plies that also search quiet checks",
+    ),
+    check(
+        EngineOption::TTCutoffs,
+        "ttCutoffs",
+        true,
+        "Cut off the search on transposition table bounds",
+    ),
+    spin(
+        EngineOption::CentralityWeight,
+        "centralityWeight",
+        5,
+        0,
+        100,
+        "Centipawns per ring closer to the center",
+    ),
+    spin(
+        EngineOption::PawnAdvancementWeight,
+        "pawnAdvancementWeight",
+        50,
+        0,
+        500,
+        "Bonus of a pawn about to promote",
+    ),
+    spin(
+        EngineOption::MobilityWeight,
+        "mobilityWeight",
+        2,
+        0,
+        50,
+        "Centipawns per legal move",
+    ),
+    spin(
+        EngineOption::CheckPenalty,
+        "checkPenalty",
+        50,
+        0,
+        500,
+        "Penalty for being in check",
+    ),
+    spin(
+        EngineOption::KingOfTheHillStep,
+        "kingOfTheHillStep",
+        KING_OF_THE_HILL_STEP_BONUS,
+        0,
+        500,
+        "King of the hill bonus per ring closer to the hill",
+    ),
+    spin(
+        EngineOption::RacingKingsStep,
+        "racingKingsStep",
+        RACING_KINGS_STEP_BONUS,
+        0,
+        500,
+        "Racing kings bonus per row advanced",
+    ),
+    spin(
+        EngineOption::MvvLvaScale,
+        "mvvLvaScale",
+        10,
+        1,
+        100,
+        "Multiplier of the victim's value when ordering captures",
+    ),
+    spin(
+        EngineOption::KillerBonus,
+        "killerBonus",
+        0,
+        0,
+        8999,
+        "Ordering bonus of a quiet move that caused a cutoff at the same depth",
+    ),
+    spin(
+        EngineOption::CountermoveBonus,
+        "countermoveBonus",
+        0,
+        0,
+        8999,
+        "Ordering bonus of the quiet move that last refuted the opponent's previous move",
+    ),
+    spin(
+        EngineOption::HistoryScale,
+        "historyScale",
+        0,
+        0,
+        1000,
+        "Percent of a quiet move's history score added to its ordering value",
+    ),
+    check(
+        EngineOption::SingularExtensions,
+        "singularExtensions",
+        false,
+        "Search a transposition table move far better than every alternative one ply deeper",
+    ),
+    spin(
+        EngineOption::SingularMargin,
+        "singularMargin",
+        100,
+        1,
+        2000,
+        "Centipawns every alternative must fall short of a singular move by",
+    ),
+    spin(
+        EngineOption::MatingNetWeight,
+        "matingNetWeight",
+        10,
+        0,
+        200,
+        "Bonus per flight cell a bare king driven into a mating pattern has lost",
+    ),
+    spin(
+        EngineOption::DefenseWeight,
+        "defenseWeight",
+        100,
+        0,
+        400,
+        "Percent of the losing side's king activity counted in pawnless endings",
+    ),
+];
+
+const fn initial_values() -> [AtomicI32; OPTIONS.len()] {
+    let mut values = [const { AtomicI32::new(0) }; OPTIONS.len()];
+    let mut i = 0;
+    while i < OPTIONS.len() {
+        values[i] = AtomicI32::new(OPTIONS[i].default);
+        i += 1;
+    }
+    values
+}
+
+static VALUES: [AtomicI32; OPTIONS.len()] = initial_values();
+
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    /// Values that replace the process-wide ones on this thread while
+    /// [`with_options`] runs.
+    static LOCAL_VALUES: RefCell<Option<[i32; OPTIONS.len()]>> = const { RefCell::new(None) };
+}
+
+impl EngineOption {
+    fn spec(self) -> &'static OptionSpec {
+        &OPTIONS[self as usize]
+    }
+
+    /// Option by name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        OPTIONS
+            .iter()
+            .find(|spec| spec.name == name)
+            .map(|spec| spec.option)
+    }
+
+    pub fn name(self) -> &'static str {
+        self.spec().name
+    }
+
+    /// Current value (0 or 1 for a check).
+    pub fn get(self) -> i32 {
+        LOCAL_VALUES.with_borrow(|local| match local {
+            Some(values) => values[self as usize],
+            None => VALUES[self as usize].load(Ordering::Relaxed),
+        })
+    }
+
+    /// Current value of a check.
+    pub fn enabled(self) -> bool {
+        self.get() != 0
+    }
+}
+
+// ============================================================================
+// Listing and Setting
+// ============================================================================
+
+/// An option with its current value, for listing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OptionInfo {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: OptionKind,
+    pub value: i32,
+    pub default: i32,
+    pub min: i32,
+    pub max: i32,
+    pub description: String,
+}
+
+/// Every option with its current value.
+pub fn list_options() -> Vec<OptionInfo> {
+    OPTIONS
+        .iter()
+        .map(|spec| OptionInfo {
+            name: spec.name.to_string(),
+            kind: spec.kind,
+            value: spec.option.get(),
+            default: spec.default,
+            min: spec.min,
+            max: spec.max,
+            description: spec.description.to_string(),
+        })
+        .collect()
+}
+
+/// Parse an option value: an integer for a spin, "true" or "false" (or 1 or
+/// 0) for a check.
+fn parse_value(spec: &OptionSpec, value: &str) -> Option<i32> {
+    match (spec.kind, value.trim()) {
+        (OptionKind::Check, "true") => Some(1),
+        (OptionKind::Check, "false") => Some(0),
+        (_, value) => value.parse().ok(),
+    }
+}
+
+/// Set an option by name. Fails with "unknownOption", "invalidValue" or
+/// "outOfRange".
+pub fn set_option(name: &str, value: &str) -> Result<(), String> {
+    let option = EngineOption::from_name(name).ok_or("unknownOption")?;
+    let spec = option.spec();
+    let value = parse_value(spec, value).ok_or("invalidValue")?;
+    if !(spec.min..=spec.max).contains(&value) {
+        return Err("outOfRange".to_string());
+    }
+    if VALUES[option as usize].swap(value, Ordering::Relaxed) != value {
+        GENERATION.fetch_add(1, Ordering::Relaxed);
+    }
+    Ok(())
+}
+
+/// Restore every option to its default.
+pub fn reset_options() {
+    for spec in &OPTIONS {
+        if VALUES[spec.option as usize].swap(spec.default, Ordering::Relaxed) != spec.default {
+            GENERATION.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Restores the thread's previous local values when dropped, so that they
+/// are restored even if the work unwinds.
+struct LocalValuesGuard(Option<[i32; OPTIONS.len()]>);
+
+impl Drop for LocalValuesGuard {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        LOCAL_VALUES.with_borrow_mut(|local| *local = previous);
+    }
+}
+
+/// Run `work` with some options set to other values (clamped to their
+/// bounds) on this thread only. The process-wide values, and searches on
+/// other threads, are untouched, and [`options_generation`] does not change,
+/// so caches kept across the call must not be shared with it.
+pub fn with_options<R>(values: &[(EngineOption, i32)], work: impl FnOnce() -> R) -> R {
+    let mut local = OPTIONS.map(|spec| spec.option.get());
+    for &(option, value) in values {
+        let spec = option.spec();
+        local[option as usize] = value.clamp(spec.min, spec.max);
+    }
+    let _guard = LocalValuesGuard(LOCAL_VALUES.with_borrow_mut(|current| current.replace(local)));
+    work()
+}
+
+/// A counter that changes whenever an option value does.
+pub(crate) fn options_generation() -> u64 {
+    GENERATION.load(Ordering::Relaxed)
+}
+
+// ============================================================================
+// Configs
+// ============================================================================
+
+/// Every option's current value as a JSON object { name: value }, to be
+/// saved as a config and loaded with [`load_options_config`].
+pub fn options_config() -> String {
+    let config: BTreeMap<&str, i32> = OPTIONS
+        .iter()
+        .map(|spec| (spec.name, spec.option.get()))
+        .collect();
+    serde_json::to_string_pretty(&config).unwrap_or_else(|_| "{}".to_string())
+}
+
+/// Set the options of a config { name: value }; options it does not name
+/// keep their values. Fails with "malformed" or the first failing option's
+/// [`set_option`] error, in which case no option changes.
+pub fn load_options_config(json: &str) -> Result<(), String> {
+    let config: BTreeMap<String, i32> = serde_json::from_str(json).map_err(|_| "malformed")?;
+    let previous: Vec<(EngineOption, i32)> = OPTIONS
+        .iter()
+        .map(|spec| (spec.option, spec.option.get()))
+        .collect();
+    for (name, value) in &config {
+        if let Err(reason) = set_option(name, &value.to_string()) {
+            for (option, value) in previous {
+                set_option(option.name(), &value.to_string())?;
+            }
+            return Err(reason);
+        }
+    }
+    Ok(())
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_option_table_is_consistent() {
+        for (i, spec) in OPTIONS.iter().enumerate() {
+            assert_eq!(spec.option as usize, i, "{}", spec.name);
+            assert!(
+                (spec.min..=spec.max).contains(&spec.default),
+                "{}",
+                spec.name
+            );
+            assert_eq!(EngineOption::from_name(spec.name), Some(spec.option));
+        }
+        let listed = list_options();
+        assert_eq!(listed.len(), OPTIONS.len());
+        assert_eq!(listed[0].name, "ttSize");
+        let json = serde_json::to_value(&listed[1]).unwrap();
+        assert_eq!(json["type"], "check");
+    }
+
+    #[test]
+    fn test_set_option_validates() {
+        let error = |name: &str, value: &str| set_option(name, value).err();
+        assert_eq!(error("nope", "1").as_deref(), Some("unknownOption"));
+        assert_eq!(
+            error("mobilityWeight", "x").as_deref(),
+            Some("invalidValue")
+        );
+        assert_eq!(error("mobilityWeight", "-1").as_deref(), Some("outOfRange"));
+        assert_eq!(error("quiescence", "2").as_deref(), Some("outOfRange"));
+
+        // Setting the current value is accepted and changes nothing
+        let generation = options_generation();
+        assert_eq!(set_option("quiescence", "true"), Ok(()));
+        assert_eq!(set_option("mobilityWeight", " 2 "), Ok(()));
+        assert_eq!(options_generation(), generation);
+        assert!(EngineOption::Quiescence.enabled());
+    }
+
+    #[test]
+    fn test_with_options_is_local() {
+        let weight = EngineOption::MvvLvaScale.get();
+        let inside = with_options(
+            &[
+                (EngineOption::MvvLvaScale, weight + 1),
+                (EngineOption::HistoryScale, 100_000),
+            ],
+            || {
+                let other_thread = std::thread::spawn(|| EngineOption::MvvLvaScale.get());
+                (
+                    EngineOption::MvvLvaScale.get(),
+                    EngineOption::HistoryScale.get(),
+                    other_thread.join().unwrap(),
+                )
+            },
+        );
+        assert_eq!(inside, (weight + 1, 1000, weight));
+        assert_eq!(EngineOption::MvvLvaScale.get(), weight);
+    }
+
+    #[test]
+    fn test_options_config_roundtrip() {
+        // Loading the current values changes nothing
+        let config = options_config();
+        let generation = options_generation();
+        assert_eq!(load_options_config(&config), Ok(()));
+        assert_eq!(options_generation(), generation);
+
+        let parsed: BTreeMap<String, i32> = serde_json::from_str(&config).unwrap();
+        assert_eq!(parsed.len(), OPTIONS.len());
+        assert_eq!(parsed["mvvLvaScale"], EngineOption::MvvLvaScale.get());
+
+        assert_eq!(
+            load_options_config("nope").err().as_deref(),
+            Some("malformed")
+        );
+        assert_eq!(
+            load_options_config(r#"{"killerBonus": -5}"#)
+                .err()
+                .as_deref(),
+            Some("outOfRange")
+        );
+        assert_eq!(options_generation(), generation);
+    }
+}
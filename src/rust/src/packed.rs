@@ -0,0 +1,294 @@
+//! Underchex Packed Boards
+//!
+//! A compact binary encoding of a board for bandwidth-sensitive transfer:
+//! - 11 bytes: occupancy bitmap, bit i (little-endian) set if slot i holds a
+//!   piece. Slots are the cells of the axial square |q|, |r| <= 4 ordered by
+//!   q then r: the 61 board cells plus the corners where the standard setup
+//!   keeps some pieces.
+//! - 5 bits per occupied slot, in the same order and packed least significant
+//!   bit first: the piece's code (see [`piece_code`])
+//!
+//! The starting position takes 29 bytes instead of a JSON map of about 1700.
+//! The bitmap gives a packed board's length, so boards can be laid end to
+//! end for batches (see [`unpack_boards`]).
+
+use crate::types::{BoardState, Color, HexCoord, LanceVariant, Piece, PieceType, BOARD_RADIUS};
+
+/// Cells per row of the slot square.
+const SIDE: i32 = 2 * BOARD_RADIUS + 1;
+
+/// Number of slots.
+pub(crate) const SLOTS: usize = (SIDE * SIDE) as usize;
+
+/// Bytes of the occupancy bitmap.
+const BITMAP_BYTES: usize = SLOTS.div_ceil(8);
+
+/// Bits per occupied cell.
+const CODE_BITS: usize = 5;
+
+/// Piece kinds in code order, shared by both colors.
+const KINDS: [(PieceType, Option<LanceVariant>); 15] = [
+    (PieceType::Pawn, None),
+    (PieceType::King, None),
+    (PieceType::Queen, None),
+    (PieceType::Knight, None),
+    (PieceType::Lance, Some(LanceVariant::A)),
+    (PieceType::Lance, Some(LanceVariant::B)),
+    (PieceType::Lance, None),
+    (PieceType::Chariot, None),
+    (PieceType::Wazir, None),
+    (PieceType::Ferz, None),
+    (PieceType::Dabbaba, None),
+    (PieceType::Cannon, None),
+    (PieceType::PromotedKnight, None),
+    (PieceType::PromotedLance, Some(LanceVariant::A)),
+    (PieceType::PromotedLance, Some(LanceVariant::B)),
+];
+
+/// Code of the neutral boulder; codes below it are a kind, plus 15 for black.
+const BOULDER_CODE: u8 = 30;
+
+// ============================================================================
+// Piece Codes
+// ============================================================================
+
+/// 5-bit code of a piece. None for a promoted lance without a variant, which
+/// no position can contain.
+pub fn piece_code(piece: &Piece) -> Option<u8> {
+    if piece.is_boulder() {
+        return Some(BOULDER_CODE);
+    }
+    // Only lances and promoted lances have a variant
+    let variant = match piece.piece_type {
+        PieceType::Lance | PieceType::PromotedLance => piece.variant,
+        _ => None,
+    };
+    let kind = KINDS
+        .iter()
+        .position(|&kind| kind == (piece.piece_type, variant))?;
+    let offset = match piece.color {
+        Color::White => 0,
+        Color::Black => KINDS.len(),
+    };
+    Some((kind + offset) as u8)
+}
+
+/// Piece for a 5-bit code.
+pub fn piece_from_code(code: u8) -> Option<Piece> {
+    if code == BOULDER_CODE {
+        return Some(Piece::boulder());
+    }
+    let code = usize::from(code);
+    let (color, kind) = if code < KINDS.len() {
+        (Color::White, code)
+    } else {
+        (Color::Black, code.checked_sub(KINDS.len())?)
+    };
+    let &(piece_type, variant) = KINDS.get(kind)?;
+    Some(Piece {
+        piece_type,
+        color,
+        variant,
+    })
+}
+
+// ============================================================================
+// Packing
+// ============================================================================
+
+/// Slot of a cell, or None outside the slot square.
+pub(crate) fn slot_of(cell: HexCoord) -> Option<usize> {
+    let q = cell.q + BOARD_RADIUS;
+    let r = cell.r + BOARD_RADIUS;
+    ((0..SIDE).contains(&q) && (0..SIDE).contains(&r)).then(|| (q * SIDE + r) as usize)
+}
+
+/// Cell of a slot (which must be below [`SLOTS`]).
+pub(crate) fn cell_of(slot: usize) -> HexCoord {
+    let slot = slot as i32;
+    HexCoord::new(slot / SIDE - BOARD_RADIUS, slot % SIDE - BOARD_RADIUS)
+}
+
+/// Packed binary encoding of a board.
+pub trait PackedBoard: Sized {
+    /// Encode the board. Returns None if a key is not a cell of the slot
+    /// square or a piece has no code.
+    fn to_packed_bytes(&self) -> Option<Vec<u8>>;
+
+    /// Decode a board. Returns None for malformed data.
+    fn from_packed_bytes(bytes: &[u8]) -> Option<Self>;
+}
+
+impl PackedBoard for BoardState {
+    fn to_packed_bytes(&self) -> Option<Vec<u8>> {
+        let mut pieces = Vec::with_capacity(self.len());
+        for (key, piece) in self {
+            let slot = slot_of(HexCoord::from_key(key)?)?;
+            pieces.push((slot, piece_code(piece)?));
+        }
+        pieces.sort_unstable();
+
+        let mut bitmap = 0u128;
+        let mut codes = BitWriter::default();
+        for &(slot, code) in &pieces {
+            bitmap |= 1 << slot;
+            codes.write(code);
+        }
+
+        let mut bytes = bitmap.to_le_bytes()[..BITMAP_BYTES].to_vec();
+        bytes.extend(codes.bytes);
+        Some(bytes)
+    }
+
+    fn from_packed_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut bitmap_bytes = [0u8; 16];
+        let (bitmap_part, codes) = bytes.split_at_checked(BITMAP_BYTES)?;
+        bitmap_bytes[..BITMAP_BYTES].copy_from_slice(bitmap_part);
+        let bitmap = u128::from_le_bytes(bitmap_bytes);
+        if bitmap >> SLOTS != 0 {
+            return None;
+        }
+        let count = bitmap.count_ones() as usize;
+        if codes.len() != (count * CODE_BITS).div_ceil(8) {
+            return None;
+        }
+
+        let mut board = BoardState::with_capacity(count);
+        let occupied = (0..SLOTS).filter(|&slot| bitmap & (1 << slot) != 0);
+        for (n, slot) in occupied.enumerate() {
+            board.insert(
+                cell_of(slot).to_key(),
+                piece_from_code(read_code(codes, n))?,
+            );
+        }
+        Some(board)
+    }
+}
+
+/// Length of the packed board at the start of `bytes`, read from its
+/// bitmap. None if `bytes` is too short to hold it.
+pub fn packed_len(bytes: &[u8]) -> Option<usize> {
+    let count: u32 = bytes
+        .get(..BITMAP_BYTES)?
+        .iter()
+        .map(|byte| byte.count_ones())
+        .sum();
+    let len = BITMAP_BYTES + (count as usize * CODE_BITS).div_ceil(8);
+    (len <= bytes.len()).then_some(len)
+}
+
+/// Decode packed boards laid end to end. Returns None if any is malformed.
+pub fn unpack_boards(mut bytes: &[u8]) -> Option<Vec<BoardState>> {
+    let mut boards = Vec::new();
+    while !bytes.is_empty() {
+        let len = packed_len(bytes)?;
+        let (board, rest) = bytes.split_at_checked(len)?;
+        boards.push(BoardState::from_packed_bytes(board)?);
+        bytes = rest;
+    }
+    Some(boards)
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    bits: usize,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u8) {
+        for bit in 0..CODE_BITS {
+            if self.bits.is_multiple_of(8) {
+                self.bytes.push(0);
+            }
+            if code & (1 << bit) != 0 {
+                if let Some(last) = self.bytes.last_mut() {
+                    *last |= 1 << (self.bits % 8);
+                }
+            }
+            self.bits += 1;
+        }
+    }
+}
+
+/// The n-th code of the packed codes (which must be long enough).
+fn read_code(codes: &[u8], n: usize) -> u8 {
+    (0..CODE_BITS).fold(0, |code, bit| {
+        let position = n * CODE_BITS + bit;
+        let set = codes
+            .get(position / 8)
+            .is_some_and(|byte| byte & (1 << (position % 8)) != 0);
+        code | (u8::from(set) << bit)
+    })
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::create_new_game;
+
+    #[test]
+    fn test_piece_codes_roundtrip() {
+        for code in 0..32 {
+            if let Some(piece) = piece_from_code(code) {
+                assert_eq!(piece_code(&piece), Some(code));
+            }
+        }
+        assert_eq!(piece_from_code(31), None);
+        let unnamed = Piece::new(PieceType::PromotedLance, Color::White);
+        assert_eq!(piece_code(&unnamed), None);
+    }
+
+    #[test]
+    fn test_board_roundtrip() {
+        let mut board = create_new_game().board;
+        board.insert("0,0".to_string(), Piece::boulder());
+        board.insert(
+            "1,-1".to_string(),
+            Piece::new(PieceType::Lance, Color::Black),
+        );
+        let bytes = board.to_packed_bytes().unwrap();
+        assert_eq!(bytes.len(), 11 + (board.len() * 5).div_ceil(8));
+        assert!(bytes.len() < 40);
+        assert_eq!(BoardState::from_packed_bytes(&bytes), Some(board));
+
+        let empty = BoardState::new();
+        assert_eq!(empty.to_packed_bytes().unwrap(), vec![0; 11]);
+    }
+
+    #[test]
+    fn test_rejects_malformed() {
+        let bytes = create_new_game().board.to_packed_bytes().unwrap();
+        assert!(BoardState::from_packed_bytes(&bytes[..bytes.len() - 1]).is_none());
+        assert!(BoardState::from_packed_bytes(&[bytes.clone(), vec![0]].concat()).is_none());
+        assert!(BoardState::from_packed_bytes(&[0xFF; 11]).is_none());
+
+        let mut off_board = BoardState::new();
+        off_board.insert("9,9".to_string(), Piece::boulder());
+        assert!(off_board.to_packed_bytes().is_none());
+    }
+
+    #[test]
+    fn test_unpack_boards() {
+        let start = create_new_game().board;
+        let mut bare = BoardState::new();
+        bare.insert("0,4".to_string(), Piece::new(PieceType::King, Color::White));
+        let bytes = [
+            start.to_packed_bytes().unwrap(),
+            bare.to_packed_bytes().unwrap(),
+        ]
+        .concat();
+
+        assert_eq!(
+            packed_len(&bytes),
+            Some(start.to_packed_bytes().unwrap().len())
+        );
+        assert_eq!(unpack_boards(&bytes), Some(vec![start, bare]));
+        assert_eq!(unpack_boards(&[]), Some(Vec::new()));
+        assert_eq!(unpack_boards(&bytes[..bytes.len() - 1]), None);
+    }
+}
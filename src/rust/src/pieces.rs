@@ -0,0 +1,351 @@
+//! Underchex Piece Definitions
+//!
+//! Movement of every piece except the pawn is described by data: single
+//! steps, slide directions, leap offsets and cannon-style hops. Move generation and attack
+//! detection in `moves.rs` read these definitions, so a new fairy piece only
+//! needs a [`PieceType`] and an entry here.
+//!
+//! Also provides custom armies: a white setup that is mirrored for black.
+
+use crate::board::{is_valid_cell, KNIGHT_OFFSETS};
+use crate::types::{Color, Direction, HexCoord, LanceVariant, Piece, PieceType};
+
+// ============================================================================
+// Definitions
+// ============================================================================
+
+/// Data-driven description of how a piece moves and captures.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PieceDefinition {
+    pub piece_type: PieceType,
+    /// Lance variant this definition applies to (lances only)
+    pub variant: Option<LanceVariant>,
+    pub name: &'static str,
+    /// Notation letter (uppercase)
+    pub symbol: char,
+    /// Material value in centipawns
+    pub value: i32,
+    /// Moves exactly one cell in these directions
+    pub steps: &'static [Direction],
+    /// Slides any number of empty cells in these directions
+    pub slides: &'static [Direction],
+    /// Jumps to these (dq, dr) offsets regardless of intervening pieces
+    pub leaps: &'static [(i32, i32)],
+    /// Slides through empty cells in these directions without capturing, and
+    /// captures the first piece beyond exactly one intervening piece (screen)
+    pub hops: &'static [Direction],
+    /// Type this piece may promote to in the far zone (shogi variant)
+    pub promotes_to: Option<PieceType>,
+    /// Type this piece reverts to when captured into a hand
+    pub demotes_to: Option<PieceType>,
+}
+
+const ALL_DIRECTIONS: &[Direction] = &[
+    Direction::N,
+    Direction::S,
+    Direction::NE,
+    Direction::SW,
+    Direction::NW,
+    Direction::SE,
+];
+const DIAGONALS: &[Direction] = &[Direction::NE, Direction::NW, Direction::SE, Direction::SW];
+const LANCE_A: &[Direction] = &[Direction::N, Direction::S, Direction::NW, Direction::SE];
+const LANCE_B: &[Direction] = &[Direction::N, Direction::S, Direction::NE, Direction::SW];
+const DOUBLE_STEPS: &[(i32, i32)] = &[(0, -2), (0, 2), (2, -2), (-2, 2), (-2, 0), (2, 0)];
+
+const fn define(
+    piece_type: PieceType,
+    name: &'static str,
+    symbol: char,
+    value: i32,
+) -> PieceDefinition {
+    PieceDefinition {
+        piece_type,
+        variant: None,
+        name,
+        symbol,
+        value,
+        steps: &[],
+        slides: &[],
+        leaps: &[],
+        hops: &[],
+        promotes_to: None,
+        demotes_to: None,
+    }
+}
+
+pub static KING_DEFINITION: PieceDefinition = PieceDefinition {
+    steps: ALL_DIRECTIONS,
+    ..define(PieceType::King, "king", 'K', 0)
+};
+pub static QUEEN_DEFINITION: PieceDefinition = PieceDefinition {
+    slides: ALL_DIRECTIONS,
+    ..define(PieceType::Queen, "queen", 'Q', 900)
+};
+pub static KNIGHT_DEFINITION: PieceDefinition = PieceDefinition {
+    leaps: &KNIGHT_OFFSETS,
+    promotes_to: Some(PieceType::PromotedKnight),
+    ..define(PieceType::Knight, "knight", 'N', 300)
+};
+pub static LANCE_A_DEFINITION: PieceDefinition = PieceDefinition {
+    variant: Some(LanceVariant::A),
+    slides: LANCE_A,
+    promotes_to: Some(PieceType::PromotedLance),
+    ..define(PieceType::Lance, "lance", 'L', 450)
+};
+pub static LANCE_B_DEFINITION: PieceDefinition = PieceDefinition {
+    variant: Some(LanceVariant::B),
+    slides: LANCE_B,
+    promotes_to: Some(PieceType::PromotedLance),
+    ..define(PieceType::Lance, "lance", 'L', 450)
+};
+pub static CHARIOT_DEFINITION: PieceDefinition = PieceDefinition {
+    slides: DIAGONALS,
+    ..define(PieceType::Chariot, "chariot", 'C', 450)
+};
+/// Pawn movement is asymmetric and handled by dedicated code in `moves.rs`.
+pub static PAWN_DEFINITION: PieceDefinition = define(PieceType::Pawn, "pawn", 'P', 100);
+pub static BOULDER_DEFINITION: PieceDefinition = define(PieceType::Boulder, "boulder", 'X', 0);
+/// One step to any adjacent cell, without being royal.
+pub static WAZIR_DEFINITION: PieceDefinition = PieceDefinition {
+    steps: ALL_DIRECTIONS,
+    ..define(PieceType::Wazir, "wazir", 'W', 200)
+};
+/// One step along the chariot's diagonals.
+pub static FERZ_DEFINITION: PieceDefinition = PieceDefinition {
+    steps: DIAGONALS,
+    ..define(PieceType::Ferz, "ferz", 'F', 150)
+};
+/// Jumps exactly two cells in any direction.
+pub static DABBABA_DEFINITION: PieceDefinition = PieceDefinition {
+    leaps: DOUBLE_STEPS,
+    ..define(PieceType::Dabbaba, "dabbaba", 'D', 200)
+};
+
+/// Moves along the lance lines (N, S, NE, SW) and captures by jumping a screen.
+pub static CANNON_DEFINITION: PieceDefinition = PieceDefinition {
+    hops: LANCE_B,
+    ..define(PieceType::Cannon, "cannon", 'H', 400)
+};
+
+/// Promoted knight: knight leaps plus single steps in every direction.
+pub static PROMOTED_KNIGHT_DEFINITION: PieceDefinition = PieceDefinition {
+    steps: ALL_DIRECTIONS,
+    leaps: &KNIGHT_OFFSETS,
+    demotes_to: Some(PieceType::Knight),
+    ..define(PieceType::PromotedKnight, "promoted knight", 'E', 500)
+};
+pub static PROMOTED_LANCE_A_DEFINITION: PieceDefinition = PieceDefinition {
+    variant: Some(LanceVariant::A),
+    steps: ALL_DIRECTIONS,
+    slides: LANCE_A,
+    demotes_to: Some(PieceType::Lance),
+    ..define(PieceType::PromotedLance, "promoted lance", 'R', 650)
+};
+pub static PROMOTED_LANCE_B_DEFINITION: PieceDefinition = PieceDefinition {
+    variant: Some(LanceVariant::B),
+    steps: ALL_DIRECTIONS,
+    slides: LANCE_B,
+    demotes_to: Some(PieceType::Lance),
+    ..define(PieceType::PromotedLance, "promoted lance", 'R', 650)
+};
+
+/// Registry of all piece definitions.
+pub static PIECE_DEFINITIONS: [&PieceDefinition; 15] = [
+    &KING_DEFINITION,
+    &QUEEN_DEFINITION,
+    &KNIGHT_DEFINITION,
+    &LANCE_A_DEFINITION,
+    &LANCE_B_DEFINITION,
+    &CHARIOT_DEFINITION,
+    &PAWN_DEFINITION,
+    &BOULDER_DEFINITION,
+    &WAZIR_DEFINITION,
+    &FERZ_DEFINITION,
+    &DABBABA_DEFINITION,
+    &CANNON_DEFINITION,
+    &PROMOTED_KNIGHT_DEFINITION,
+    &PROMOTED_LANCE_A_DEFINITION,
+    &PROMOTED_LANCE_B_DEFINITION,
+];
+
+/// Get the definition for a piece (lances by variant; B when unspecified).
+pub fn get_piece_definition(piece: &Piece) -> &'static PieceDefinition {
+    match piece.piece_type {
+        PieceType::Lance if piece.variant == Some(LanceVariant::A) => &LANCE_A_DEFINITION,
+        PieceType::PromotedLance if piece.variant == Some(LanceVariant::A) => {
+            &PROMOTED_LANCE_A_DEFINITION
+        }
+        piece_type => get_type_definition(piece_type),
+    }
+}
+
+/// Get the definition for a piece type (lances and promoted lances as variant B).
+pub fn get_type_definition(piece_type: PieceType) -> &'static PieceDefinition {
+    match piece_type {
+        PieceType::King => &KING_DEFINITION,
+        PieceType::Queen => &QUEEN_DEFINITION,
+        PieceType::Knight => &KNIGHT_DEFINITION,
+        PieceType::Lance => &LANCE_B_DEFINITION,
+        PieceType::Chariot => &CHARIOT_DEFINITION,
+        PieceType::Pawn => &PAWN_DEFINITION,
+        PieceType::Boulder => &BOULDER_DEFINITION,
+        PieceType::Wazir => &WAZIR_DEFINITION,
+        PieceType::Ferz => &FERZ_DEFINITION,
+        PieceType::Dabbaba => &DABBABA_DEFINITION,
+        PieceType::Cannon => &CANNON_DEFINITION,
+        PieceType::PromotedKnight => &PROMOTED_KNIGHT_DEFINITION,
+        PieceType::PromotedLance => &PROMOTED_LANCE_B_DEFINITION,
+    }
+}
+
+/// Look up a piece type by its notation letter (either case).
+pub fn piece_type_from_symbol(symbol: char) -> Option<PieceType> {
+    let symbol = symbol.to_ascii_uppercase();
+    PIECE_DEFINITIONS
+        .iter()
+        .find(|d| d.symbol == symbol)
+        .map(|d| d.piece_type)
+}
+
+/// Type a captured piece goes into the hand as (promoted pieces revert).
+pub fn demoted_type(piece_type: PieceType) -> PieceType {
+    get_type_definition(piece_type)
+        .demotes_to
+        .unwrap_or(piece_type)
+}
+
+/// All leap offsets used by any piece (for reverse attack lookups).
+pub(crate) fn all_leap_offsets() -> Vec<(i32, i32)> {
+    let mut offsets: Vec<(i32, i32)> = PIECE_DEFINITIONS
+        .iter()
+        .flat_map(|d| d.leaps.iter().copied())
+        .collect();
+    offsets.sort_unstable();
+    offsets.dedup();
+    offsets
+}
+
+// ============================================================================
+// Custom Armies
+// ============================================================================
+
+/// A piece placed at a cell in an army setup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArmyPlacement {
+    pub piece_type: PieceType,
+    pub variant: Option<LanceVariant>,
+    pub position: HexCoord,
+}
+
+impl ArmyPlacement {
+    pub fn new(piece_type: PieceType, position: HexCoord) -> Self {
+        Self {
+            piece_type,
+            variant: (piece_type == PieceType::Lance).then_some(LanceVariant::B),
+            position,
+        }
+    }
+
+    pub fn lance(variant: LanceVariant, position: HexCoord) -> Self {
+        Self {
+            piece_type: PieceType::Lance,
+            variant: Some(variant),
+            position,
+        }
+    }
+
+    fn piece(&self, color: Color) -> Piece {
+        Piece {
+            piece_type: self.piece_type,
+            color,
+            variant: self.variant,
+        }
+    }
+}
+
+/// Expand a white army into placements for both sides. Black's army is the
+/// point reflection (q, r) -> (-q, -r), which maps each lance onto itself.
+/// Returns None unless the army has exactly one king, every cell is on the
+/// board and no two pieces (of either side) share a cell.
+pub fn mirror_army(white: &[ArmyPlacement]) -> Option<Vec<(Piece, HexCoord)>> {
+    let kings = white
+        .iter()
+        .filter(|p| p.piece_type == PieceType::King)
+        .count();
+    if kings != 1 || white.iter().any(|p| p.piece_type == PieceType::Boulder) {
+        return None;
+    }
+
+    let placements: Vec<(Piece, HexCoord)> = white
+        .iter()
+        .flat_map(|p| {
+            [
+                (p.piece(Color::White), p.position),
+                (
+                    p.piece(Color::Black),
+                    HexCoord::new(-p.position.q, -p.position.r),
+                ),
+            ]
+        })
+        .collect();
+
+    let mut cells: Vec<HexCoord> = placements.iter().map(|&(_, cell)| cell).collect();
+    cells.sort_by_key(|c| (c.q, c.r));
+    cells.dedup();
+    let valid = cells.len() == placements.len() && cells.iter().all(|&c| is_valid_cell(c));
+    valid.then_some(placements)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_lookup() {
+        assert_eq!(get_type_definition(PieceType::Queen).slides.len(), 6);
+        assert_eq!(
+            get_piece_definition(&Piece::lance(Color::White, LanceVariant::A)).slides,
+            LANCE_A
+        );
+        assert_eq!(piece_type_from_symbol('d'), Some(PieceType::Dabbaba));
+        assert_eq!(piece_type_from_symbol('z'), None);
+        for definition in PIECE_DEFINITIONS {
+            assert_eq!(
+                get_type_definition(definition.piece_type).symbol,
+                definition.symbol
+            );
+        }
+    }
+
+    #[test]
+    fn test_mirror_army() {
+        let army = [
+            ArmyPlacement::new(PieceType::King, HexCoord::new(0, 4)),
+            ArmyPlacement::new(PieceType::Wazir, HexCoord::new(1, 3)),
+            ArmyPlacement::lance(LanceVariant::A, HexCoord::new(-1, 4)),
+        ];
+        let placements = mirror_army(&army).unwrap();
+        assert_eq!(placements.len(), 6);
+        assert!(placements.contains(&(
+            Piece::new(PieceType::Wazir, Color::Black),
+            HexCoord::new(-1, -3)
+        )));
+        assert!(placements.contains(&(
+            Piece::lance(Color::Black, LanceVariant::A),
+            HexCoord::new(1, -4)
+        )));
+
+        // No king, or a piece on the center (clashes with its mirror image)
+        assert!(mirror_army(&army[1..]).is_none());
+        let clash = [
+            ArmyPlacement::new(PieceType::King, HexCoord::new(0, 4)),
+            ArmyPlacement::new(PieceType::Ferz, HexCoord::new(0, 0)),
+        ];
+        assert!(mirror_army(&clash).is_none());
+    }
+}
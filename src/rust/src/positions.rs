@@ -0,0 +1,281 @@
+//! Underchex Random Positions
+//!
+//! Seeded generator of random legal positions matching constraints: how many
+//! pieces each side has, the game phase, a window on the material balance and
+//! the side to move. Generated positions are quiet: the side to move is not in
+//! check, has a legal move, and cannot win material with captures or checks
+//! beyond a small margin, so their static evaluation can be trusted.
+//!
+//! The evaluation self-test and the move generation differential test draw
+//! their positions from here, and so can puzzle mining and practice modes; the
+//! same seed and constraints always give the same position.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ai::{evaluate_position, evaluate_quiet, get_piece_value};
+use crate::board::get_all_cells;
+use crate::game::create_game_from_position;
+use crate::moves::{generate_all_legal_moves, is_in_check};
+use crate::rng::SeededRng;
+use crate::stats::{game_phase, GamePhase, OPENING_PLIES};
+use crate::types::{
+    is_promotion_zone, BoardState, Color, GameState, HexCoord, LanceVariant, Piece, PieceType,
+    Variant,
+};
+
+/// Placement attempts before giving up on the constraints.
+pub const MAX_POSITION_ATTEMPTS: usize = 5000;
+
+/// Centipawns the quiescence search may gain over the static evaluation for a
+/// position to still count as quiet.
+pub const QUIET_MARGIN: i32 = 50;
+
+/// Piece types placed by default.
+const DEFAULT_PIECE_TYPES: [PieceType; 5] = [
+    PieceType::Pawn,
+    PieceType::Knight,
+    PieceType::Lance,
+    PieceType::Chariot,
+    PieceType::Queen,
+];
+
+// ============================================================================
+// Constraints
+// ============================================================================
+
+/// What a generated position must satisfy.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PositionConstraints {
+    /// Fewest non-king pieces of each side
+    pub min_pieces: usize,
+    /// Most non-king pieces of each side
+    pub max_pieces: usize,
+    /// Piece types to draw from (kings are always placed)
+    pub piece_types: Vec<PieceType>,
+    /// Middlegame or endgame, judged by material; an opening never matches
+    pub phase: Option<GamePhase>,
+    /// Least White material minus Black material, in centipawns
+    pub min_balance: i32,
+    /// Most White material minus Black material, in centipawns
+    pub max_balance: i32,
+    /// Side to move; random if not given
+    pub side_to_move: Option<Color>,
+}
+
+impl Default for PositionConstraints {
+    fn default() -> Self {
+        Self {
+            min_pieces: 0,
+            max_pieces: 5,
+            piece_types: DEFAULT_PIECE_TYPES.to_vec(),
+            phase: None,
+            min_balance: i32::MIN,
+            max_balance: i32::MAX,
+            side_to_move: None,
+        }
+    }
+}
+
+impl PositionConstraints {
+    /// Whether a board has the phase and material balance asked for.
+    fn matches_material(&self, board: &BoardState) -> bool {
+        let balance: i32 = board
+            .values()
+            .map(|piece| match piece.color {
+                Color::White => get_piece_value(piece.piece_type),
+                Color::Black => -get_piece_value(piece.piece_type),
+            })
+            .sum();
+        let phase_matches = self
+            .phase
+            .is_none_or(|phase| game_phase(board, OPENING_PLIES) == phase);
+        phase_matches && balance >= self.min_balance && balance <= self.max_balance
+    }
+}
+
+// ============================================================================
+// Generation
+// ============================================================================
+
+/// Generate a legal, quiet position matching `constraints`. None if the
+/// constraints cannot be met (or no match was found within
+/// [`MAX_POSITION_ATTEMPTS`] placements).
+pub fn generate_random_position(seed: u64, constraints: &PositionConstraints) -> Option<GameState> {
+    if constraints.min_pieces > constraints.max_pieces
+        || constraints.min_balance > constraints.max_balance
+        || constraints.phase == Some(GamePhase::Opening)
+        || (constraints.max_pieces > 0 && constraints.piece_types.is_empty())
+    {
+        return None;
+    }
+    let mut rng = SeededRng::new(seed);
+    for _ in 0..MAX_POSITION_ATTEMPTS {
+        let turn = constraints
+            .side_to_move
+            .unwrap_or(if rng.next_below(2) == 0 {
+                Color::White
+            } else {
+                Color::Black
+            });
+        let Some(board) = place_pieces(constraints, &mut rng) else {
+            continue;
+        };
+        if constraints.matches_material(&board) && is_quiet_position(&board, turn) {
+            return Some(create_game_from_position(board, turn));
+        }
+    }
+    None
+}
+
+/// Both kings and a random number of pieces for each side on random cells,
+/// with no pawn on its promotion row.
+fn place_pieces(constraints: &PositionConstraints, rng: &mut SeededRng) -> Option<BoardState> {
+    let mut cells = get_all_cells();
+    let mut board = BoardState::new();
+    let span = constraints.max_pieces - constraints.min_pieces + 1;
+    for color in [Color::White, Color::Black] {
+        let mut pieces = vec![Piece::new(PieceType::King, color)];
+        for _ in 0..constraints.min_pieces + rng.next_below(span) {
+            let piece_type = *rng.choose(&constraints.piece_types)?;
+            pieces.push(match piece_type {
+                PieceType::Lance | PieceType::PromotedLance => Piece {
+                    variant: Some(if rng.next_below(2) == 0 {
+                        LanceVariant::A
+                    } else {
+                        LanceVariant::B
+                    }),
+                    ..Piece::new(piece_type, color)
+                },
+                _ => Piece::new(piece_type, color),
+            });
+        }
+        for piece in pieces {
+            let allowed: Vec<usize> = cells
+                .iter()
+                .enumerate()
+                .filter(|&(_, &cell)| {
+                    piece.piece_type != PieceType::Pawn || !is_promotion_zone(cell, color)
+                })
+                .map(|(i, _)| i)
+                .collect();
+            let index = *rng.choose(&allowed)?;
+            let cell: HexCoord = cells.swap_remove(index);
+            board.insert(cell.to_key(), piece);
+        }
+    }
+    Some(board)
+}
+
+/// Whether `turn` can move, neither king is in check and the quiescence
+/// search finds nothing worth more than [`QUIET_MARGIN`] for the side to move.
+pub fn is_quiet_position(board: &BoardState, turn: Color) -> bool {
+    if is_in_check(board, turn)
+        || is_in_check(board, turn.opposite())
+        || generate_all_legal_moves(board, turn).is_empty()
+    {
+        return false;
+    }
+    let sign = match turn {
+        Color::White => 1,
+        Color::Black => -1,
+    };
+    let gain = (evaluate_quiet(board, turn, Variant::Standard) - evaluate_position(board)) * sign;
+    gain <= QUIET_MARGIN
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts(board: &BoardState, color: Color) -> usize {
+        board
+            .values()
+            .filter(|piece| piece.color == color && piece.piece_type != PieceType::King)
+            .count()
+    }
+
+    #[test]
+    fn test_positions_are_legal_and_quiet() {
+        let constraints = PositionConstraints::default();
+        for seed in 0..20 {
+            let game = generate_random_position(seed, &constraints).unwrap();
+            let board = &game.board;
+            assert!(is_quiet_position(board, game.turn));
+            for color in [Color::White, Color::Black] {
+                assert!(counts(board, color) <= constraints.max_pieces);
+                let kings = board
+                    .values()
+                    .filter(|piece| piece.color == color && piece.piece_type == PieceType::King)
+                    .count();
+                assert_eq!(kings, 1);
+            }
+            assert!(board.iter().all(|(key, piece)| {
+                piece.piece_type != PieceType::Pawn
+                    || HexCoord::from_key(key).is_some_and(|c| !is_promotion_zone(c, piece.color))
+            }));
+            let again = generate_random_position(seed, &constraints).unwrap();
+            assert_eq!(again.board, game.board);
+            assert_eq!(again.turn, game.turn);
+        }
+    }
+
+    #[test]
+    fn test_constraints_are_met() {
+        let constraints = PositionConstraints {
+            min_pieces: 2,
+            max_pieces: 3,
+            piece_types: vec![PieceType::Knight, PieceType::Chariot],
+            phase: Some(GamePhase::Endgame),
+            min_balance: 0,
+            max_balance: 100,
+            side_to_move: Some(Color::Black),
+        };
+        for seed in 0..10 {
+            let game = generate_random_position(seed, &constraints).unwrap();
+            assert_eq!(game.turn, Color::Black);
+            assert!(constraints.matches_material(&game.board));
+            for color in [Color::White, Color::Black] {
+                assert!((2..=3).contains(&counts(&game.board, color)));
+            }
+        }
+
+        let middlegame = PositionConstraints {
+            min_pieces: 6,
+            max_pieces: 8,
+            phase: Some(GamePhase::Middlegame),
+            ..Default::default()
+        };
+        let game = generate_random_position(1, &middlegame).unwrap();
+        assert_eq!(
+            game_phase(&game.board, OPENING_PLIES),
+            GamePhase::Middlegame
+        );
+    }
+
+    #[test]
+    fn test_unsatisfiable_constraints() {
+        let opening = PositionConstraints {
+            phase: Some(GamePhase::Opening),
+            ..Default::default()
+        };
+        assert!(generate_random_position(1, &opening).is_none());
+        let inverted = PositionConstraints {
+            min_balance: 100,
+            max_balance: -100,
+            ..Default::default()
+        };
+        assert!(generate_random_position(1, &inverted).is_none());
+        // Kings alone cannot leave White a queen up
+        let bare = PositionConstraints {
+            max_pieces: 0,
+            min_balance: 900,
+            ..Default::default()
+        };
+        assert!(generate_random_position(1, &bare).is_none());
+    }
+}
@@ -0,0 +1,35 @@
+//! Underchex Prelude
+//!
+//! The stable, user-facing part of the crate: the game types, playing moves,
+//! HexFEN and move notation, and the engine's entry points. Everything else
+//! the crate exports may change between versions; code that should keep
+//! building across them imports from here:
+//!
+//! ```
+//! use underchex_wasm::prelude::*;
+//!
+//! let game = create_new_game();
+//! let after = make_move(&game, HexCoord::new(0, 2), HexCoord::new(0, 1));
+//! assert_eq!(after.map(|game| game.turn), Some(Color::Black));
+//! ```
+//!
+//! The `api_stability` test snapshots this list and the crate root's exports.
+
+pub use crate::ai::{
+    evaluate_position, get_ai_move, AIDifficulty, CancelToken, ScoreKind, SearchResult,
+    TranspositionTable,
+};
+pub use crate::engine::{Engine, EngineSearch};
+pub use crate::game::{
+    create_game_from_position, create_new_game, create_variant_game, game_result, get_legal_moves,
+    make_move, make_move_with_promotion, try_make_move, GameResult,
+};
+pub use crate::messages::{english, Message};
+pub use crate::notation::{
+    board_to_hexfen, game_to_hexfen, move_to_san, parse_game_hexfen, parse_hexfen,
+};
+pub use crate::types::{
+    BoardState, Color, GameState, GameStatus, HexCoord, Move, Piece, PieceType, StalemateRule,
+    Variant, WinConditions,
+};
+pub use crate::zobrist::{hash_position, positions_equal};
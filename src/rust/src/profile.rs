@@ -0,0 +1,333 @@
+//! Underchex Player Profiles
+//!
+//! Aggregates a player's games into the statistics of a profile page: results
+//! by color, move accuracy, blunder patterns and favorite openings.
+//!
+//! Every move of the player is scored by how much evaluation it gave away
+//! (each side of the move evaluated with a quiescence search). Accuracy turns
+//! that loss into 0-100 through winning chances, so a loss matters less in a
+//! position that was already decided. A move losing at least
+//! [`DEFAULT_BLUNDER_DROP`] centipawns is a blunder; the piece captured by the
+//! opponent's reply, if any, is the piece it hung.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ai::evaluate_quiet;
+use crate::explorer::{classify_opening, get_game_winner};
+use crate::game::create_new_game;
+use crate::moves::apply_move;
+use crate::stats::{game_phase, GamePhase, DEFAULT_BLUNDER_DROP};
+use crate::types::{Color, GameState, GameStatus, Move, PieceType, Variant};
+
+/// Openings listed in a profile.
+pub const FAVORITE_OPENINGS: usize = 5;
+
+// ============================================================================
+// Input
+// ============================================================================
+
+/// A game from the point of view of the profile's player.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerGame {
+    /// Moves played from the standard starting position
+    pub moves: Vec<Move>,
+    pub status: GameStatus,
+    /// The color the player had
+    pub color: Color,
+}
+
+impl PlayerGame {
+    pub fn from_state(state: &GameState, color: Color) -> Self {
+        Self {
+            moves: state.history.clone(),
+            status: state.status.clone(),
+            color,
+        }
+    }
+}
+
+// ============================================================================
+// Profile
+// ============================================================================
+
+/// Results with one color.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ColorRecord {
+    pub games: u32,
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+    /// Wins over finished games (0 without any)
+    pub win_rate: f64,
+}
+
+impl ColorRecord {
+    fn record(&mut self, outcome: Option<Option<bool>>) {
+        self.games += 1;
+        match outcome {
+            Some(Some(true)) => self.wins += 1,
+            Some(Some(false)) => self.losses += 1,
+            Some(None) => self.draws += 1,
+            None => {}
+        }
+        let finished = self.wins + self.losses + self.draws;
+        if finished > 0 {
+            self.win_rate = f64::from(self.wins) / f64::from(finished);
+        }
+    }
+}
+
+/// Blunders per game phase.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhaseErrors {
+    pub opening: u32,
+    pub middlegame: u32,
+    pub endgame: u32,
+}
+
+impl PhaseErrors {
+    fn add(&mut self, phase: GamePhase) {
+        match phase {
+            GamePhase::Opening => self.opening += 1,
+            GamePhase::Middlegame => self.middlegame += 1,
+            GamePhase::Endgame => self.endgame += 1,
+        }
+    }
+
+    /// The phase with the most blunders (the earliest on ties), if any.
+    pub fn worst(&self) -> Option<GamePhase> {
+        [
+            (GamePhase::Opening, self.opening),
+            (GamePhase::Middlegame, self.middlegame),
+            (GamePhase::Endgame, self.endgame),
+        ]
+        .into_iter()
+        .filter(|&(_, count)| count > 0)
+        .max_by_key(|&(phase, count)| (count, std::cmp::Reverse(phase as u8)))
+        .map(|(phase, _)| phase)
+    }
+}
+
+/// How often a piece type was hung.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HungPiece {
+    pub piece_type: PieceType,
+    pub count: u32,
+}
+
+/// An opening the player reached, with their score in it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpeningRecord {
+    /// Opening classification (see [`classify_opening`])
+    pub opening: String,
+    pub color: Color,
+    pub games: u32,
+    pub wins: u32,
+}
+
+/// A player's profile statistics.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PlayerProfile {
+    pub games: u32,
+    pub as_white: ColorRecord,
+    pub as_black: ColorRecord,
+    /// Moves of the player that were scored
+    pub moves: u32,
+    /// Mean accuracy of those moves, 0-100
+    pub average_accuracy: f64,
+    /// Mean evaluation lost per move, in centipawns
+    pub average_centipawn_loss: f64,
+    pub blunders: u32,
+    pub blunders_by_phase: PhaseErrors,
+    /// Phase with the most blunders
+    pub weakest_phase: Option<GamePhase>,
+    /// Pieces lost to blunders, most often first
+    pub hung_pieces: Vec<HungPiece>,
+    /// Most played openings, most often first
+    pub favorite_openings: Vec<OpeningRecord>,
+}
+
+impl PlayerProfile {
+    /// Serialize to JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|_| "{}".to_string())
+    }
+}
+
+/// Chance of winning (0-100) for an evaluation in centipawns.
+pub fn winning_chances(centipawns: i32) -> f64 {
+    let centipawns = f64::from(centipawns.clamp(-2000, 2000));
+    50.0 + 50.0 * (2.0 / (1.0 + (-0.003_682_08 * centipawns).exp()) - 1.0)
+}
+
+/// Accuracy (0-100) of a move going from `before` to `after` centipawns,
+/// both from the mover's side.
+pub fn move_accuracy(before: i32, after: i32) -> f64 {
+    let lost = (winning_chances(before) - winning_chances(after)).max(0.0);
+    (103.1668 * (-0.04354 * lost).exp() - 3.1669).clamp(0.0, 100.0)
+}
+
+/// Build a profile from a player's games.
+pub fn build_profile(games: &[PlayerGame]) -> PlayerProfile {
+    let mut profile = PlayerProfile::default();
+    let mut accuracy_sum = 0.0;
+    let mut loss_sum = 0i64;
+    let mut hung: HashMap<PieceType, u32> = HashMap::new();
+    let mut openings: HashMap<(String, Color), OpeningRecord> = HashMap::new();
+
+    for game in games {
+        profile.games += 1;
+        let outcome = get_game_winner(&game.status).map(|winner| winner.map(|w| w == game.color));
+        match game.color {
+            Color::White => profile.as_white.record(outcome),
+            Color::Black => profile.as_black.record(outcome),
+        }
+
+        let opening = classify_opening(&game.moves);
+        let record = openings
+            .entry((opening.clone(), game.color))
+            .or_insert_with(|| OpeningRecord {
+                opening,
+                color: game.color,
+                games: 0,
+                wins: 0,
+            });
+        record.games += 1;
+        if outcome == Some(Some(true)) {
+            record.wins += 1;
+        }
+
+        let mut board = create_new_game().board;
+        for (ply, mv) in game.moves.iter().enumerate() {
+            let next = apply_move(&board, mv);
+            if mv.piece.color == game.color {
+                let sign = if game.color == Color::White { 1 } else { -1 };
+                let before = sign * evaluate_quiet(&board, game.color, Variant::Standard);
+                let after = sign * evaluate_quiet(&next, game.color.opposite(), Variant::Standard);
+                let lost = (before - after).max(0);
+
+                profile.moves += 1;
+                accuracy_sum += move_accuracy(before, after);
+                loss_sum += i64::from(lost);
+                if lost >= DEFAULT_BLUNDER_DROP {
+                    profile.blunders += 1;
+                    profile.blunders_by_phase.add(game_phase(&board, ply));
+                    if let Some(captured) = game.moves.get(ply + 1).and_then(|reply| reply.captured)
+                    {
+                        *hung.entry(captured.piece_type).or_default() += 1;
+                    }
+                }
+            }
+            board = next;
+        }
+    }
+
+    if profile.moves > 0 {
+        profile.average_accuracy = accuracy_sum / f64::from(profile.moves);
+        profile.average_centipawn_loss = loss_sum as f64 / f64::from(profile.moves);
+    }
+    profile.weakest_phase = profile.blunders_by_phase.worst();
+
+    profile.hung_pieces = hung
+        .into_iter()
+        .map(|(piece_type, count)| HungPiece { piece_type, count })
+        .collect();
+    profile
+        .hung_pieces
+        .sort_by_key(|hung| (std::cmp::Reverse(hung.count), hung.piece_type as u8));
+
+    let mut openings: Vec<OpeningRecord> = openings.into_values().collect();
+    openings.sort_by(|a, b| {
+        b.games
+            .cmp(&a.games)
+            .then_with(|| a.opening.cmp(&b.opening))
+            .then_with(|| (a.color as u8).cmp(&(b.color as u8)))
+    });
+    openings.truncate(FAVORITE_OPENINGS);
+    profile.favorite_openings = openings;
+
+    profile
+}
+
+/// Build a profile from JSON (an array of `PlayerGame`). Returns None if the
+/// JSON is invalid.
+pub fn build_profile_from_json(json: &str) -> Option<PlayerProfile> {
+    let games: Vec<PlayerGame> = serde_json::from_str(json).ok()?;
+    Some(build_profile(&games))
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{play, CHARIOT_BLUNDER};
+
+    #[test]
+    fn test_accuracy_scale() {
+        assert!((winning_chances(0) - 50.0).abs() < 1e-9);
+        assert!(move_accuracy(0, 0) > 99.9);
+        assert!(move_accuracy(100, 300) > 99.9);
+        assert!(move_accuracy(0, -1500) < 10.0);
+        // The same loss matters less when the game is already decided
+        assert!(move_accuracy(1500, 1200) > move_accuracy(150, -150));
+    }
+
+    #[test]
+    fn test_profile_records_results_and_blunders() {
+        let mut hung = play(&CHARIOT_BLUNDER);
+        hung.status = GameStatus::Resigned {
+            winner: Color::Black,
+        };
+        let mut won = hung.clone();
+        won.status = GameStatus::Resigned {
+            winner: Color::White,
+        };
+
+        let profile = build_profile(&[
+            PlayerGame::from_state(&hung, Color::White),
+            PlayerGame::from_state(&won, Color::White),
+            PlayerGame::from_state(&play(&[[0, 2, 0, 1], [0, -2, 0, -1]]), Color::Black),
+        ]);
+        assert_eq!(profile.games, 3);
+        assert_eq!((profile.as_white.wins, profile.as_white.losses), (1, 1));
+        assert_eq!(profile.as_white.win_rate, 0.5);
+        assert_eq!(profile.as_black.games, 1);
+        assert_eq!(profile.moves, 5);
+        assert!(profile.average_accuracy > 0.0 && profile.average_accuracy < 100.0);
+
+        // The same blunder in both White games
+        assert_eq!(profile.blunders, 2);
+        assert_eq!(profile.blunders_by_phase.opening, 2);
+        assert_eq!(profile.weakest_phase, Some(GamePhase::Opening));
+        assert_eq!(
+            profile.hung_pieces,
+            vec![HungPiece {
+                piece_type: PieceType::Chariot,
+                count: 2
+            }]
+        );
+
+        assert_eq!(profile.favorite_openings[0].games, 2);
+        assert_eq!(profile.favorite_openings[0].color, Color::White);
+
+        let json: serde_json::Value = serde_json::from_str(&profile.to_json()).unwrap();
+        assert_eq!(json["games"], 3);
+        assert!(build_profile_from_json("nope").is_none());
+    }
+
+    #[test]
+    fn test_weakest_phase() {
+        let errors = PhaseErrors {
+            opening: 2,
+            middlegame: 5,
+            endgame: 5,
+        };
+        assert_eq!(errors.worst(), Some(GamePhase::Middlegame));
+        assert_eq!(PhaseErrors::default().worst(), None);
+    }
+}
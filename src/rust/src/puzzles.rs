@@ -0,0 +1,547 @@
+//! Underchex Puzzles and Problem Composition
+//!
+//! Provides:
+//! - An exhaustive mate solver ("can the side to move force mate in N?")
+//! - Composition validation for direct-mate stipulations ("#3"), reporting
+//!   cooks (extra key moves), short solutions, duals and tries with their
+//!   refutations
+//! - A daily puzzle chosen deterministically from a pool by date, with answer
+//!   checking that accepts any move the solver proves still wins
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::moves::{apply_move, generate_all_legal_moves, is_in_check};
+use crate::notation::parse_hexfen;
+use crate::rng::{fnv1a, SeededRng};
+use crate::types::{BoardState, Color, HexCoord, Move, PieceType};
+
+// ============================================================================
+// Mate Solver
+// ============================================================================
+
+/// Check if `color` is checkmated.
+pub fn is_checkmated(board: &BoardState, color: Color) -> bool {
+    is_in_check(board, color) && generate_all_legal_moves(board, color).is_empty()
+}
+
+/// Check if a move by `attacker` forces mate within `moves` attacker moves
+/// (counting this one).
+pub fn move_forces_mate(board: &BoardState, mv: &Move, attacker: Color, moves: u32) -> bool {
+    if moves == 0 {
+        return false;
+    }
+    let defender = attacker.opposite();
+    let after = apply_move(board, mv);
+    let replies = generate_all_legal_moves(&after, defender);
+
+    if replies.is_empty() {
+        return is_in_check(&after, defender);
+    }
+    if moves == 1 {
+        return false;
+    }
+
+    replies
+        .iter()
+        .all(|reply| can_force_mate(&apply_move(&after, reply), attacker, moves - 1))
+}
+
+/// Check if `attacker` (to move) can force mate within `moves` moves.
+pub fn can_force_mate(board: &BoardState, attacker: Color, moves: u32) -> bool {
+    generate_all_legal_moves(board, attacker)
+        .iter()
+        .any(|mv| move_forces_mate(board, mv, attacker, moves))
+}
+
+/// All first moves for `attacker` that force mate within `moves` moves.
+pub fn find_mating_moves(board: &BoardState, attacker: Color, moves: u32) -> Vec<Move> {
+    generate_all_legal_moves(board, attacker)
+        .into_iter()
+        .filter(|mv| move_forces_mate(board, mv, attacker, moves))
+        .collect()
+}
+
+/// Length of the shortest forced mate for `attacker`, searching up to `max_moves`.
+pub fn find_shortest_mate(board: &BoardState, attacker: Color, max_moves: u32) -> Option<u32> {
+    (1..=max_moves).find(|&n| can_force_mate(board, attacker, n))
+}
+
+// ============================================================================
+// Stipulations
+// ============================================================================
+
+/// A problem stipulation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Stipulation {
+    /// Side to move mates in N moves against any defense ("#N")
+    MateIn(u32),
+}
+
+/// Longest stipulation accepted: validating a puzzle searches every line to
+/// this many moves.
+pub const MAX_STIPULATION_MOVES: u32 = 4;
+
+/// Parse a stipulation such as "#3" or "mate in 3", of 1 to
+/// [`MAX_STIPULATION_MOVES`] moves.
+pub fn parse_stipulation(text: &str) -> Option<Stipulation> {
+    let text = text.trim().to_lowercase();
+    let count = text
+        .strip_prefix('#')
+        .or_else(|| text.strip_prefix("mate in"))?
+        .trim()
+        .parse::<u32>()
+        .ok()?;
+    (1..=MAX_STIPULATION_MOVES)
+        .contains(&count)
+        .then_some(Stipulation::MateIn(count))
+}
+
+// ============================================================================
+// Composition Validation
+// ============================================================================
+
+/// A defense after which the attacker has more than one way to continue.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Dual {
+    pub defense: Move,
+    pub continuations: Vec<Move>,
+}
+
+/// A non-key first move that fails only to a few defenses.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Try {
+    pub attempt: Move,
+    pub refutations: Vec<Move>,
+}
+
+/// Result of validating a composed problem.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompositionReport {
+    pub stipulation: Stipulation,
+    /// True if the stipulation is met with a unique key, no short mate and no duals
+    pub sound: bool,
+    /// All first moves that fulfil the stipulation
+    pub key_moves: Vec<Move>,
+    /// Key moves beyond the first (alternative solutions)
+    pub cooks: Vec<Move>,
+    /// Shortest mate, if shorter than stipulated
+    pub short_mate: Option<u32>,
+    /// Defenses to the key answered by more than one mating continuation
+    pub duals: Vec<Dual>,
+    /// Non-key moves with a single refutation
+    pub tries: Vec<Try>,
+}
+
+/// Attacker continuations after `defense` that mate in the fewest moves possible
+/// within `remaining`.
+fn shortest_continuations(board: &BoardState, attacker: Color, remaining: u32) -> Vec<Move> {
+    match find_shortest_mate(board, attacker, remaining) {
+        Some(n) => find_mating_moves(board, attacker, n),
+        None => Vec::new(),
+    }
+}
+
+/// Defenses to `attempt` after which the attacker can no longer mate in time.
+fn find_refutations(board: &BoardState, attempt: &Move, attacker: Color, moves: u32) -> Vec<Move> {
+    let after = apply_move(board, attempt);
+    let replies = generate_all_legal_moves(&after, attacker.opposite());
+    if replies.is_empty() {
+        // Stalemate or a mate that came too late; nothing to refute
+        return Vec::new();
+    }
+    replies
+        .into_iter()
+        .filter(|reply| {
+            moves <= 1 || !can_force_mate(&apply_move(&after, reply), attacker, moves - 1)
+        })
+        .collect()
+}
+
+/// Validate a composed problem for the side to move.
+///
+/// Proves whether the stipulation holds, lists every key move (more than one
+/// is a cook), reports a shorter mate, duals after the key (first defensive
+/// level only) and tries that fail to exactly one refutation.
+/// Returns None if the stipulation cannot be parsed.
+pub fn validate_composition(
+    board: &BoardState,
+    side_to_move: Color,
+    stipulation: &str,
+) -> Option<CompositionReport> {
+    let stipulation = parse_stipulation(stipulation)?;
+    let Stipulation::MateIn(moves) = stipulation;
+
+    let (key_moves, others): (Vec<Move>, Vec<Move>) = generate_all_legal_moves(board, side_to_move)
+        .into_iter()
+        .partition(|mv| move_forces_mate(board, mv, side_to_move, moves));
+
+    let short_mate = if moves > 1 {
+        find_shortest_mate(board, side_to_move, moves - 1)
+    } else {
+        None
+    };
+
+    let mut duals = Vec::new();
+    if let [key] = key_moves.as_slice() {
+        let after_key = apply_move(board, key);
+        for defense in generate_all_legal_moves(&after_key, side_to_move.opposite()) {
+            let position = apply_move(&after_key, &defense);
+            let continuations = shortest_continuations(&position, side_to_move, moves - 1);
+            if continuations.len() > 1 {
+                duals.push(Dual {
+                    defense,
+                    continuations,
+                });
+            }
+        }
+    }
+
+    let tries = others
+        .into_iter()
+        .filter_map(|attempt| {
+            let refutations = find_refutations(board, &attempt, side_to_move, moves);
+            (refutations.len() == 1).then_some(Try {
+                attempt,
+                refutations,
+            })
+        })
+        .collect();
+
+    let cooks = key_moves.iter().skip(1).cloned().collect();
+    let sound = key_moves.len() == 1 && short_mate.is_none() && duals.is_empty();
+
+    Some(CompositionReport {
+        stipulation,
+        sound,
+        key_moves,
+        cooks,
+        short_mate,
+        duals,
+        tries,
+    })
+}
+
+// ============================================================================
+// Daily Puzzle
+// ============================================================================
+
+/// Most puzzles checked for the daily puzzle, as each check is a search.
+pub const DAILY_CANDIDATES: usize = 16;
+
+/// Puzzle served when a pool has no valid puzzle: Q(2,-4)-(0,-2) mates.
+const FALLBACK_PUZZLE_FEN: &str = "4kpQ2/3p5/9/9/9/9/9/9/4K4 w";
+
+/// A puzzle: a position and the stipulation to fulfil from it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Puzzle {
+    pub id: String,
+    /// Position in HexFEN, including the side to move
+    pub fen: String,
+    /// Stipulation such as "#2" (see [`parse_stipulation`])
+    pub stipulation: String,
+}
+
+/// Verdict on a submitted puzzle answer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "camelCase")]
+pub enum PuzzleAnswer {
+    /// The move forces mate in `mate_in` moves (counting itself), within the
+    /// stipulation
+    Correct { mate_in: u32 },
+    /// A legal move that no longer forces mate in time
+    Incorrect,
+    /// Not a legal move in the puzzle position, or the puzzle is invalid
+    Illegal,
+}
+
+impl Puzzle {
+    /// Position, side to move and stipulated number of moves. None if the
+    /// HexFEN or the stipulation cannot be parsed.
+    fn setup(&self) -> Option<(BoardState, Color, u32)> {
+        let (board, side_to_move) = parse_hexfen(&self.fen)?;
+        let Stipulation::MateIn(moves) = parse_stipulation(&self.stipulation)?;
+        Some((board, side_to_move, moves))
+    }
+
+    /// Check that the puzzle parses and the side to move can fulfil its
+    /// stipulation.
+    pub fn is_valid(&self) -> bool {
+        self.setup()
+            .is_some_and(|(board, side, moves)| can_force_mate(&board, side, moves))
+    }
+
+    /// Every first move that solves the puzzle.
+    pub fn solutions(&self) -> Vec<Move> {
+        self.setup()
+            .map(|(board, side, moves)| find_mating_moves(&board, side, moves))
+            .unwrap_or_default()
+    }
+
+    /// Check a first move. Any move the solver proves still mates within the
+    /// stipulation is correct, not only the composer's intended key.
+    pub fn check_answer(
+        &self,
+        from: HexCoord,
+        to: HexCoord,
+        promotion: Option<PieceType>,
+    ) -> PuzzleAnswer {
+        let Some((board, side, moves)) = self.setup() else {
+            return PuzzleAnswer::Illegal;
+        };
+        let Some(mv) = generate_all_legal_moves(&board, side)
+            .into_iter()
+            .find(|mv| mv.from == from && mv.to == to && mv.promotion == promotion)
+        else {
+            return PuzzleAnswer::Illegal;
+        };
+        match (1..=moves).find(|&n| move_forces_mate(&board, &mv, side, n)) {
+            Some(mate_in) => PuzzleAnswer::Correct { mate_in },
+            None => PuzzleAnswer::Incorrect,
+        }
+    }
+}
+
+/// A pool of puzzles, serialized as a JSON array.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PuzzleSet {
+    pub puzzles: Vec<Puzzle>,
+}
+
+impl PuzzleSet {
+    /// Parse a JSON array of puzzles.
+    pub fn from_json(json: &str) -> Option<Self> {
+        serde_json::from_str(json).ok()
+    }
+}
+
+/// The puzzle served when a pool has none that is valid.
+pub fn fallback_puzzle() -> Puzzle {
+    Puzzle {
+        id: "fallback".to_string(),
+        fen: FALLBACK_PUZZLE_FEN.to_string(),
+        stipulation: "#1".to_string(),
+    }
+}
+
+/// Seed for a date. ISO dates are normalized ("2026-1-5" and "2026-01-05"
+/// agree); any other text is used as given.
+fn daily_seed(date: &str) -> u64 {
+    let date = date.trim();
+    match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+        Ok(day) => fnv1a(&day.format("%Y-%m-%d").to_string()),
+        Err(_) => fnv1a(date),
+    }
+}
+
+/// The puzzle of the day.
+///
+/// Every client selects the same puzzle for a date and pool: a date hash
+/// picks a starting index, and the first valid puzzle among the
+/// [`DAILY_CANDIDATES`] from there on (wrapping around) is served. Falls back
+/// to [`fallback_puzzle`] if none is valid.
+pub fn daily(date: &str, pool: &PuzzleSet) -> Puzzle {
+    let count = pool.puzzles.len();
+    let start = SeededRng::new(daily_seed(date)).next_below(count);
+    (0..count.min(DAILY_CANDIDATES))
+        .filter_map(|offset| pool.puzzles.get((start + offset) % count))
+        .find(|puzzle| puzzle.is_valid())
+        .cloned()
+        .unwrap_or_else(fallback_puzzle)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::board_to_hexfen;
+    use crate::types::Piece;
+
+    /// Black king boxed in by its own pawns; Q(2,-4)-(0,-2) mates.
+    fn create_mate_in_one() -> BoardState {
+        let mut board = BoardState::new();
+        board.insert("0,4".to_string(), Piece::new(PieceType::King, Color::White));
+        board.insert(
+            "2,-4".to_string(),
+            Piece::new(PieceType::Queen, Color::White),
+        );
+        board.insert(
+            "0,-4".to_string(),
+            Piece::new(PieceType::King, Color::Black),
+        );
+        board.insert(
+            "-1,-3".to_string(),
+            Piece::new(PieceType::Pawn, Color::Black),
+        );
+        board.insert(
+            "1,-4".to_string(),
+            Piece::new(PieceType::Pawn, Color::Black),
+        );
+        board
+    }
+
+    #[test]
+    fn test_parse_stipulation() {
+        assert_eq!(parse_stipulation("#3"), Some(Stipulation::MateIn(3)));
+        assert_eq!(parse_stipulation("Mate in 2"), Some(Stipulation::MateIn(2)));
+        assert_eq!(parse_stipulation("#0"), None);
+        assert_eq!(parse_stipulation("#4"), Some(Stipulation::MateIn(4)));
+        assert_eq!(parse_stipulation("#5"), None);
+        assert_eq!(parse_stipulation("h#2"), None);
+    }
+
+    #[test]
+    fn test_mate_solver() {
+        let board = create_mate_in_one();
+        assert!(can_force_mate(&board, Color::White, 1));
+        assert_eq!(find_shortest_mate(&board, Color::White, 2), Some(1));
+
+        let keys = find_mating_moves(&board, Color::White, 1);
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].to, HexCoord::new(0, -2));
+
+        let after = apply_move(&board, &keys[0]);
+        assert!(is_checkmated(&after, Color::Black));
+    }
+
+    #[test]
+    fn test_sound_composition() {
+        let report = validate_composition(&create_mate_in_one(), Color::White, "#1").unwrap();
+        assert!(report.sound);
+        assert_eq!(report.key_moves.len(), 1);
+        assert!(report.cooks.is_empty());
+        assert!(report.short_mate.is_none());
+    }
+
+    #[test]
+    fn test_cooked_composition() {
+        // A second queen mates on the same square along another line.
+        let mut board = create_mate_in_one();
+        board.insert(
+            "-4,2".to_string(),
+            Piece::new(PieceType::Queen, Color::White),
+        );
+
+        let report = validate_composition(&board, Color::White, "#1").unwrap();
+        assert!(!report.sound);
+        assert!(report
+            .key_moves
+            .iter()
+            .any(|m| m.from == HexCoord::new(-4, 2) && m.to == HexCoord::new(0, -2)));
+        assert_eq!(report.cooks.len(), report.key_moves.len() - 1);
+    }
+
+    #[test]
+    fn test_short_mate_is_flagged() {
+        let report = validate_composition(&create_mate_in_one(), Color::White, "#2").unwrap();
+        assert!(!report.sound);
+        assert_eq!(report.short_mate, Some(1));
+    }
+
+    #[test]
+    fn test_invalid_stipulation() {
+        assert!(validate_composition(&create_mate_in_one(), Color::White, "win").is_none());
+    }
+
+    fn puzzle(id: &str, fen: &str, stipulation: &str) -> Puzzle {
+        Puzzle {
+            id: id.to_string(),
+            fen: fen.to_string(),
+            stipulation: stipulation.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_fallback_puzzle_is_valid() {
+        let fallback = fallback_puzzle();
+        assert!(fallback.is_valid());
+        let (board, side) = parse_hexfen(&fallback.fen).unwrap();
+        assert_eq!(board, create_mate_in_one());
+        assert_eq!(side, Color::White);
+    }
+
+    #[test]
+    fn test_daily_is_deterministic() {
+        let fen = board_to_hexfen(&create_mate_in_one(), Color::White);
+        let pool = PuzzleSet {
+            puzzles: (0..10)
+                .map(|n| puzzle(&n.to_string(), &fen, "#1"))
+                .collect(),
+        };
+        let today = daily("2026-10-17", &pool);
+        assert_eq!(daily("2026-10-17", &pool), today);
+        assert_eq!(daily(" 2026-10-17 ", &pool), today);
+        let picks: std::collections::HashSet<String> = (1..=28)
+            .map(|day| daily(&format!("2026-02-{}", day), &pool).id)
+            .collect();
+        assert!(picks.len() > 1);
+    }
+
+    #[test]
+    fn test_daily_skips_invalid_puzzles() {
+        let fen = board_to_hexfen(&create_mate_in_one(), Color::White);
+        let pool = PuzzleSet {
+            puzzles: vec![
+                puzzle("bad-fen", "nonsense", "#1"),
+                puzzle("no-mate", "4k4/9/9/9/9/9/9/9/4K4 w", "#1"),
+                puzzle("bad-stipulation", &fen, "win"),
+                puzzle("good", &fen, "#1"),
+            ],
+        };
+        for day in 1..=10 {
+            assert_eq!(daily(&format!("2026-03-{:02}", day), &pool).id, "good");
+        }
+        assert_eq!(
+            daily("2026-10-17", &PuzzleSet::default()),
+            fallback_puzzle()
+        );
+        // Only a bounded run of puzzles is checked
+        let invalid = PuzzleSet {
+            puzzles: (0..DAILY_CANDIDATES * 4)
+                .map(|n| puzzle(&n.to_string(), "4k4/9/9/9/9/9/9/9/4K4 w", "#4"))
+                .collect(),
+        };
+        assert_eq!(daily("2026-10-17", &invalid), fallback_puzzle());
+
+        let json = serde_json::to_string(&pool).unwrap();
+        assert!(json.starts_with('['));
+        assert_eq!(PuzzleSet::from_json(&json), Some(pool));
+    }
+
+    #[test]
+    fn test_check_answer_accepts_alternate_wins() {
+        // A second queen mates on the same square along another line.
+        let mut board = create_mate_in_one();
+        board.insert(
+            "-4,2".to_string(),
+            Piece::new(PieceType::Queen, Color::White),
+        );
+        let fen = board_to_hexfen(&board, Color::White);
+        let cooked = puzzle("cooked", &fen, "#2");
+        let target = HexCoord::new(0, -2);
+
+        for from in [HexCoord::new(2, -4), HexCoord::new(-4, 2)] {
+            assert_eq!(
+                cooked.check_answer(from, target, None),
+                PuzzleAnswer::Correct { mate_in: 1 }
+            );
+        }
+        assert!(cooked.solutions().len() >= 2);
+        assert_eq!(
+            cooked.check_answer(HexCoord::new(0, 4), HexCoord::new(0, 3), None),
+            PuzzleAnswer::Incorrect
+        );
+        assert_eq!(
+            cooked.check_answer(HexCoord::new(0, 4), HexCoord::new(0, 0), None),
+            PuzzleAnswer::Illegal
+        );
+        assert_eq!(
+            puzzle("bad", "nonsense", "#1").check_answer(target, target, None),
+            PuzzleAnswer::Illegal
+        );
+    }
+}
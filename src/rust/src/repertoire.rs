@@ -0,0 +1,480 @@
+//! Underchex Opening Repertoire Trainer
+//!
+//! A user's chosen opening lines for one color, stored as a tree of moves
+//! from the standard starting position. Every position where the user is to
+//! move and the repertoire has an answer is a quiz card with spaced-repetition
+//! scheduling (SM-2): answering correctly pushes the next review further out,
+//! a wrong answer brings it back to the next day.
+//!
+//! Times are milliseconds since the Unix epoch and passed in by the caller.
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::create_new_game;
+use crate::moves::{apply_move, generate_all_legal_moves};
+use crate::notation::board_to_hexfen;
+use crate::types::{BoardState, Color, HexCoord, Move, PieceType};
+
+// ============================================================================
+// Scheduling
+// ============================================================================
+
+pub const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// Starting ease factor of a new card.
+pub const INITIAL_EASE: f64 = 2.5;
+
+/// Lowest ease factor, reached by cards that are failed repeatedly.
+pub const MIN_EASE: f64 = 1.3;
+
+/// Answer quality given for a correct quiz answer (SM-2 scale 0-5).
+pub const CORRECT_QUALITY: u8 = 4;
+
+/// Answer quality given for a wrong quiz answer.
+pub const WRONG_QUALITY: u8 = 1;
+
+/// Spaced-repetition state of a quiz position.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReviewSchedule {
+    /// Consecutive successful reviews
+    pub repetitions: u32,
+    pub interval_days: u32,
+    pub ease: f64,
+    /// When the position is next due (0 for a position never reviewed)
+    pub due_ms: i64,
+}
+
+impl Default for ReviewSchedule {
+    fn default() -> Self {
+        Self {
+            repetitions: 0,
+            interval_days: 0,
+            ease: INITIAL_EASE,
+            due_ms: 0,
+        }
+    }
+}
+
+impl ReviewSchedule {
+    /// Apply a review with an SM-2 quality (0-5, 3 or more is a pass).
+    pub fn review(&mut self, quality: u8, now_ms: i64) {
+        let quality = quality.min(5);
+        if quality < 3 {
+            self.repetitions = 0;
+            self.interval_days = 1;
+        } else {
+            self.interval_days = match self.repetitions {
+                0 => 1,
+                1 => 6,
+                _ => (f64::from(self.interval_days) * self.ease).round() as u32,
+            };
+            self.repetitions += 1;
+        }
+        let miss = f64::from(5 - quality);
+        self.ease = (self.ease + 0.1 - miss * (0.08 + miss * 0.02)).max(MIN_EASE);
+        self.due_ms = now_ms + i64::from(self.interval_days) * DAY_MS;
+    }
+
+    pub fn is_due(&self, now_ms: i64) -> bool {
+        self.due_ms <= now_ms
+    }
+}
+
+// ============================================================================
+// Repertoire Tree
+// ============================================================================
+
+/// A position in the repertoire.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RepertoireNode {
+    pub id: usize,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    /// Move leading to this node from its parent (None for the root)
+    pub mv: Option<Move>,
+    pub board: BoardState,
+    pub turn: Color,
+    /// Scheduling of this position as a quiz; only used when the repertoire's
+    /// side is to move and has an answer here
+    #[serde(default)]
+    pub schedule: ReviewSchedule,
+}
+
+/// A quiz question: what does the repertoire play in this position?
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Quiz {
+    pub node: usize,
+    pub hexfen: String,
+    /// Moves from the starting position to the quiz position
+    pub line: Vec<Move>,
+}
+
+/// Result of answering a quiz.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuizResult {
+    pub correct: bool,
+    /// The repertoire's moves in the quiz position
+    pub expected: Vec<Move>,
+    /// Node reached by a correct answer
+    pub next_node: Option<usize>,
+    /// When the position is next due
+    pub due_ms: i64,
+}
+
+/// Opening lines chosen by a user playing one color.
+///
+/// Nodes are never reused: removing a line detaches it, so node ids stay
+/// stable for the lifetime of the repertoire.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Repertoire {
+    /// The side the user plays
+    pub color: Color,
+    pub nodes: Vec<RepertoireNode>,
+}
+
+impl Repertoire {
+    /// Create an empty repertoire for a color, rooted at the standard
+    /// starting position.
+    pub fn new(color: Color) -> Self {
+        let game = create_new_game();
+        Self {
+            color,
+            nodes: vec![RepertoireNode {
+                id: 0,
+                parent: None,
+                children: Vec::new(),
+                mv: None,
+                board: game.board,
+                turn: game.turn,
+                schedule: ReviewSchedule::default(),
+            }],
+        }
+    }
+
+    /// Add a move from a node, reusing an existing child with the same move.
+    /// Returns the child's id, or None if the node is not in the repertoire
+    /// or the move is illegal.
+    pub fn add_move(
+        &mut self,
+        parent: usize,
+        from: HexCoord,
+        to: HexCoord,
+        promotion: Option<PieceType>,
+    ) -> Option<usize> {
+        if !self.is_attached(parent) {
+            return None;
+        }
+        if let Some(existing) = self.child_with_move(parent, from, to, promotion) {
+            return Some(existing);
+        }
+
+        let node = self.nodes.get(parent)?;
+        let mv = generate_all_legal_moves(&node.board, node.turn)
+            .into_iter()
+            .find(|m| m.from == from && m.to == to && m.promotion == promotion)?;
+        let id = self.nodes.len();
+        let child = RepertoireNode {
+            id,
+            parent: Some(parent),
+            children: Vec::new(),
+            board: apply_move(&node.board, &mv),
+            turn: node.turn.opposite(),
+            mv: Some(mv),
+            schedule: ReviewSchedule::default(),
+        };
+        self.nodes.push(child);
+        self.nodes.get_mut(parent)?.children.push(id);
+        Some(id)
+    }
+
+    /// Add a line of moves from the starting position. Returns the id of the
+    /// last node, or None if a move is illegal (earlier moves stay added).
+    pub fn add_line(&mut self, moves: &[(HexCoord, HexCoord, Option<PieceType>)]) -> Option<usize> {
+        moves.iter().try_fold(0, |node, &(from, to, promotion)| {
+            self.add_move(node, from, to, promotion)
+        })
+    }
+
+    /// Detach a node and its subtree. The root cannot be removed.
+    pub fn remove(&mut self, id: usize) -> bool {
+        if !self.is_attached(id) {
+            return false;
+        }
+        let parent = self.nodes.get(id).and_then(|node| node.parent);
+        match parent.and_then(|parent| self.nodes.get_mut(parent)) {
+            Some(parent) => {
+                parent.children.retain(|&child| child != id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Whether a node exists and is still connected to the root.
+    fn is_attached(&self, id: usize) -> bool {
+        let mut current = id;
+        loop {
+            let Some(node) = self.nodes.get(current) else {
+                return false;
+            };
+            match node.parent {
+                None => return current == 0,
+                Some(parent)
+                    if self
+                        .nodes
+                        .get(parent)
+                        .is_some_and(|parent| parent.children.contains(&current)) =>
+                {
+                    current = parent;
+                }
+                Some(_) => return false,
+            }
+        }
+    }
+
+    fn child_with_move(
+        &self,
+        parent: usize,
+        from: HexCoord,
+        to: HexCoord,
+        promotion: Option<PieceType>,
+    ) -> Option<usize> {
+        self.nodes
+            .get(parent)?
+            .children
+            .iter()
+            .copied()
+            .find(|&child| {
+                self.nodes.get(child).is_some_and(|node| {
+                    node.mv
+                        .as_ref()
+                        .is_some_and(|m| m.from == from && m.to == to && m.promotion == promotion)
+                })
+            })
+    }
+
+    /// Moves from the starting position to a node.
+    pub fn line_to(&self, id: usize) -> Vec<Move> {
+        let mut line = Vec::new();
+        let mut node = self.nodes.get(id);
+        while let Some(current) = node {
+            if let Some(mv) = &current.mv {
+                line.push(mv.clone());
+            }
+            node = current.parent.and_then(|parent| self.nodes.get(parent));
+        }
+        line.reverse();
+        line
+    }
+
+    // ========================================================================
+    // Quizzing
+    // ========================================================================
+
+    /// Whether a node is a quiz position: the user is to move and the
+    /// repertoire has an answer.
+    pub fn is_quiz_position(&self, id: usize) -> bool {
+        self.is_attached(id)
+            && self
+                .nodes
+                .get(id)
+                .is_some_and(|node| node.turn == self.color && !node.children.is_empty())
+    }
+
+    /// Quiz positions due for review, earliest first.
+    pub fn due_positions(&self, now_ms: i64) -> Vec<usize> {
+        let mut due: Vec<(i64, usize)> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|&(id, node)| self.is_quiz_position(id) && node.schedule.is_due(now_ms))
+            .map(|(id, node)| (node.schedule.due_ms, id))
+            .collect();
+        due.sort();
+        due.into_iter().map(|(_, id)| id).collect()
+    }
+
+    /// The next position to quiz, if any is due.
+    pub fn next_quiz(&self, now_ms: i64) -> Option<Quiz> {
+        let node = *self.due_positions(now_ms).first()?;
+        let position = self.nodes.get(node)?;
+        Some(Quiz {
+            node,
+            hexfen: board_to_hexfen(&position.board, position.turn),
+            line: self.line_to(node),
+        })
+    }
+
+    /// Grade an answer to the quiz at a node against the repertoire and
+    /// reschedule the position. Returns None if the node is not a quiz
+    /// position.
+    pub fn answer(
+        &mut self,
+        node: usize,
+        from: HexCoord,
+        to: HexCoord,
+        promotion: Option<PieceType>,
+        now_ms: i64,
+    ) -> Option<QuizResult> {
+        if !self.is_quiz_position(node) {
+            return None;
+        }
+        let next_node = self.child_with_move(node, from, to, promotion);
+        let quality = if next_node.is_some() {
+            CORRECT_QUALITY
+        } else {
+            WRONG_QUALITY
+        };
+        self.review(node, quality, now_ms);
+
+        let position = self.nodes.get(node)?;
+        let expected = position
+            .children
+            .iter()
+            .filter_map(|&child| self.nodes.get(child)?.mv.clone())
+            .collect();
+        Some(QuizResult {
+            correct: next_node.is_some(),
+            expected,
+            next_node,
+            due_ms: position.schedule.due_ms,
+        })
+    }
+
+    /// Reschedule a quiz position with an explicit SM-2 quality (0-5).
+    /// Returns false if the node is not a quiz position.
+    pub fn review(&mut self, node: usize, quality: u8, now_ms: i64) -> bool {
+        if !self.is_quiz_position(node) {
+            return false;
+        }
+        match self.nodes.get_mut(node) {
+            Some(position) => {
+                position.schedule.review(quality, now_ms);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // ========================================================================
+    // Persistence
+    // ========================================================================
+
+    /// Serialize the repertoire, including scheduling, for saving.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// Load a repertoire saved with [`Self::to_json`]. Returns None if the
+    /// JSON is invalid or its node links are inconsistent.
+    pub fn from_json(json: &str) -> Option<Self> {
+        let repertoire: Self = serde_json::from_str(json).ok()?;
+        let nodes = &repertoire.nodes;
+        let consistent = nodes.first().is_some_and(|root| root.parent.is_none())
+            && nodes.iter().enumerate().all(|(index, node)| {
+                node.id == index
+                    && node.parent.is_none_or(|parent| parent < index)
+                    && node
+                        .children
+                        .iter()
+                        .all(|&child| nodes.get(child).is_some_and(|c| c.parent == Some(index)))
+            });
+        consistent.then_some(repertoire)
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(q1: i32, r1: i32, q2: i32, r2: i32) -> (HexCoord, HexCoord, Option<PieceType>) {
+        (HexCoord::new(q1, r1), HexCoord::new(q2, r2), None)
+    }
+
+    fn sample() -> Repertoire {
+        let mut repertoire = Repertoire::new(Color::White);
+        repertoire
+            .add_line(&[cells(0, 2, 0, 1), cells(0, -2, 0, -1), cells(1, 2, 1, 1)])
+            .unwrap();
+        repertoire
+            .add_line(&[cells(0, 2, 0, 1), cells(1, -2, 1, -1), cells(-1, 2, -1, 1)])
+            .unwrap();
+        repertoire
+    }
+
+    #[test]
+    fn test_lines_share_nodes() {
+        let repertoire = sample();
+        assert_eq!(repertoire.nodes.len(), 6);
+        assert_eq!(repertoire.nodes[1].children.len(), 2);
+        assert_eq!(repertoire.line_to(5).len(), 3);
+
+        let mut illegal = repertoire.clone();
+        assert!(illegal.add_line(&[cells(0, 2, 0, -3)]).is_none());
+        assert_eq!(illegal.nodes.len(), 6);
+    }
+
+    #[test]
+    fn test_quiz_and_scheduling() {
+        let mut repertoire = sample();
+        let now = 1_000 * DAY_MS;
+        // The root and the two positions after black's replies
+        assert_eq!(repertoire.due_positions(now), vec![0, 2, 4]);
+
+        let quiz = repertoire.next_quiz(now).unwrap();
+        assert_eq!(quiz.node, 0);
+        assert!(quiz.line.is_empty());
+
+        let result = repertoire
+            .answer(0, HexCoord::new(0, 2), HexCoord::new(0, 1), None, now)
+            .unwrap();
+        assert!(result.correct);
+        assert_eq!(result.next_node, Some(1));
+        assert_eq!(result.due_ms, now + DAY_MS);
+
+        let result = repertoire
+            .answer(2, HexCoord::new(-1, 2), HexCoord::new(-1, 1), None, now)
+            .unwrap();
+        assert!(!result.correct);
+        assert_eq!(result.expected.len(), 1);
+        assert_eq!(repertoire.due_positions(now), vec![4]);
+
+        // Not the user's move
+        assert!(repertoire
+            .answer(1, HexCoord::new(0, -2), HexCoord::new(0, -1), None, now)
+            .is_none());
+    }
+
+    #[test]
+    fn test_sm2_intervals() {
+        let mut schedule = ReviewSchedule::default();
+        schedule.review(5, 0);
+        assert_eq!(schedule.interval_days, 1);
+        schedule.review(5, 0);
+        assert_eq!(schedule.interval_days, 6);
+        schedule.review(5, 0);
+        assert_eq!(schedule.interval_days, 16);
+        schedule.review(0, 0);
+        assert_eq!((schedule.repetitions, schedule.interval_days), (0, 1));
+        for _ in 0..10 {
+            schedule.review(0, 0);
+        }
+        assert_eq!(schedule.ease, MIN_EASE);
+    }
+
+    #[test]
+    fn test_remove_and_json_roundtrip() {
+        let mut repertoire = sample();
+        assert!(repertoire.remove(4));
+        assert!(!repertoire.remove(0));
+        assert_eq!(repertoire.due_positions(0), vec![0, 2]);
+
+        let loaded = Repertoire::from_json(&repertoire.to_json()).unwrap();
+        assert_eq!(loaded, repertoire);
+        assert!(Repertoire::from_json(r#"{"color":"White","nodes":[]}"#).is_none());
+    }
+}
@@ -0,0 +1,735 @@
+//! Underchex Saved Games
+//!
+//! A save is the full game state as JSON: moves, clocks and the repetition
+//! history, so that a resumed game plays on exactly as before.
+//!
+//! Long games and correspondence archives can be saved compressed: the moves
+//! are stored as indices into the legal moves of their positions, and the
+//! repetition history is rebuilt by replaying them. Compressed saves load
+//! like any other, as every [`GameState`] deserialization decompresses them.
+//!
+//! Autosave snapshots are small binary records of a game in progress, meant
+//! to be written after every move and restored after a crash or refresh.
+//!
+//! With the `signed-saves` feature a save can also be signed with a key
+//! (HMAC-SHA256), so that tournament clients can detect a tampered local
+//! save (edited clocks or history) before resuming a rated game. A signed
+//! save is a JSON envelope `{ version, state, signature }` where `state` is
+//! the save's JSON text, signed exactly as stored.
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::{create_position_record, create_variant_game};
+use crate::packed::{cell_of, piece_code, piece_from_code, slot_of, PackedBoard, SLOTS};
+use crate::rng::fnv1a_bytes;
+use crate::share::{
+    base64_decode, base64_encode, decode_move_indices, encode_move_indices, rules_bytes,
+    rules_from_bytes, VARIANTS,
+};
+use crate::types::{
+    BoardState, Color, GameState, GameStatus, Hands, HexCoord, Move, Piece, PositionRecord,
+    StalemateRule, TurnPhase, Variant, WinConditions,
+};
+use crate::zobrist::boards_equal;
+
+/// Save a game as JSON.
+pub fn save_game(state: &GameState) -> String {
+    serde_json::to_string(state).unwrap_or_else(|_| "null".to_string())
+}
+
+/// Load a game saved with [`save_game`]. Returns None if the JSON is invalid.
+pub fn load_game(json: &str) -> Option<GameState> {
+    serde_json::from_str(json).ok()
+}
+
+// ============================================================================
+// Compressed Saves
+// ============================================================================
+
+/// Position a compressed history starts from, when it is not the variant's
+/// starting position.
+#[derive(Serialize, Deserialize)]
+struct StartPosition {
+    board: BoardState,
+    turn: Color,
+    #[serde(default)]
+    hands: Hands,
+}
+
+/// A game's moves as indices into the legal moves of their positions (see
+/// [`encode_game_compact`](crate::share::encode_game_compact)).
+#[derive(Serialize, Deserialize)]
+struct CompressedHistory {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    start: Option<StartPosition>,
+    moves: usize,
+    /// Base64 of the move indices
+    indices: String,
+}
+
+/// Serialized form of a [`GameState`], whose history may be compressed.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct StoredGameState {
+    board: BoardState,
+    turn: Color,
+    move_number: u32,
+    half_move_clock: u32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    history: Vec<Move>,
+    status: GameStatus,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    position_history: Vec<PositionRecord>,
+    #[serde(default)]
+    variant: Variant,
+    #[serde(default)]
+    hands: Hands,
+    #[serde(default)]
+    phase: TurnPhase,
+    #[serde(default)]
+    stalemate_rule: StalemateRule,
+    #[serde(default)]
+    win_conditions: WinConditions,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    compressed_history: Option<CompressedHistory>,
+}
+
+impl TryFrom<StoredGameState> for GameState {
+    type Error = String;
+
+    fn try_from(stored: StoredGameState) -> Result<Self, String> {
+        let mut state = GameState {
+            board: stored.board,
+            turn: stored.turn,
+            move_number: stored.move_number,
+            half_move_clock: stored.half_move_clock,
+            history: stored.history,
+            status: stored.status,
+            position_history: stored.position_history,
+            variant: stored.variant,
+            hands: stored.hands,
+            phase: stored.phase,
+            stalemate_rule: stored.stalemate_rule,
+            win_conditions: stored.win_conditions,
+        };
+        if let Some(compressed) = stored.compressed_history {
+            let replayed = decompress(&state, &compressed)
+                .filter(|replayed| {
+                    boards_equal(&replayed.board, &state.board)
+                        && replayed.turn == state.turn
+                        && replayed.hands == state.hands
+                })
+                .ok_or("invalid compressed history")?;
+            state.history = replayed.history;
+            state.position_history = replayed.position_history;
+        }
+        Ok(state)
+    }
+}
+
+/// Replay a compressed history of `state`.
+fn decompress(state: &GameState, compressed: &CompressedHistory) -> Option<GameState> {
+    let mut start = create_variant_game(state.variant);
+    if let Some(position) = &compressed.start {
+        start = GameState {
+            position_history: vec![create_position_record(&position.board, position.turn)],
+            board: position.board.clone(),
+            turn: position.turn,
+            hands: position.hands.clone(),
+            ..start
+        };
+    }
+    start.stalemate_rule = state.stalemate_rule;
+    start.win_conditions = state.win_conditions;
+    let indices = base64_decode(&compressed.indices)?;
+    decode_move_indices(start, compressed.moves, &indices)
+}
+
+/// Compress a game's history, or None if replaying it would not restore the
+/// state exactly (e.g. a game resumed from HexFEN, whose earlier positions
+/// are known only by hash, or one awaiting a boulder placement).
+fn compress(state: &GameState) -> Option<StoredGameState> {
+    let start = state.unwound_start();
+    let (indices, replayed) = encode_move_indices(&start, &state.history)?;
+    let restored = boards_equal(&replayed.board, &state.board)
+        && replayed.turn == state.turn
+        && replayed.hands == state.hands
+        && replayed.phase == state.phase
+        && replayed.position_history.len() == state.position_history.len()
+        && replayed
+            .position_history
+            .iter()
+            .zip(&state.position_history)
+            .all(|(replayed, recorded)| replayed.same_position(recorded));
+    if !restored {
+        return None;
+    }
+
+    let standard = create_variant_game(state.variant);
+    let start = (!boards_equal(&start.board, &standard.board)
+        || start.turn != standard.turn
+        || start.hands != standard.hands)
+        .then_some(StartPosition {
+            board: start.board,
+            turn: start.turn,
+            hands: start.hands,
+        });
+    Some(StoredGameState {
+        board: state.board.clone(),
+        turn: state.turn,
+        move_number: state.move_number,
+        half_move_clock: state.half_move_clock,
+        history: Vec::new(),
+        status: state.status.clone(),
+        position_history: Vec::new(),
+        variant: state.variant,
+        hands: state.hands.clone(),
+        phase: state.phase,
+        stalemate_rule: state.stalemate_rule,
+        win_conditions: state.win_conditions,
+        compressed_history: Some(CompressedHistory {
+            start,
+            moves: state.history.len(),
+            indices: base64_encode(&indices),
+        }),
+    })
+}
+
+/// Save a game as JSON with its history compressed, typically a tenth of
+/// the size of [`save_game`] for long games. Falls back to an uncompressed
+/// save for a history that cannot be compressed.
+pub fn save_game_compressed(state: &GameState) -> String {
+    compress(state)
+        .and_then(|stored| serde_json::to_string(&stored).ok())
+        .unwrap_or_else(|| save_game(state))
+}
+
+// ============================================================================
+// Autosave Snapshots
+// ============================================================================
+
+/// Leading bytes of an autosave snapshot.
+const AUTOSAVE_MAGIC: &[u8; 4] = b"UXAS";
+
+/// Version of the autosave snapshots written.
+pub const AUTOSAVE_VERSION: u8 = 1;
+
+/// Moves of the history an autosave snapshot keeps.
+pub const AUTOSAVE_HISTORY_TAIL: usize = 16;
+
+/// Bytes before a snapshot's body: magic, version, sequence number and
+/// checksum.
+const AUTOSAVE_HEADER_BYTES: usize = 4 + 1 + 4 + 8;
+
+/// Byte of an absent piece, promotion or cell.
+const ABSENT: u8 = 0xFF;
+
+/// A game restored from an autosave snapshot.
+#[derive(Debug, Clone)]
+pub struct AutosaveSnapshot {
+    /// Sequence number the snapshot was written with
+    pub seq: u32,
+    pub state: GameState,
+}
+
+/// Write a minimal binary snapshot of a game, cheap enough to write after
+/// every move and restore after a crash or page refresh: the magic "UXAS",
+/// the version, `seq` and an FNV-1a checksum of the body (little-endian),
+/// then the body: variant, side to move and phase, rules, move number,
+/// half-move clock, status, packed board, hands, the repetition history since
+/// the last capture or pawn move (hashes only, as in HexFEN) and the last
+/// [`AUTOSAVE_HISTORY_TAIL`] moves. Writers increase `seq` with every
+/// snapshot, so the newest valid one can be chosen (see [`newest_autosave`]).
+/// Returns None if the board cannot be packed.
+pub fn autosave_snapshot(state: &GameState, seq: u32) -> Option<Vec<u8>> {
+    let mut body = vec![
+        VARIANTS.iter().position(|&v| v == state.variant)? as u8,
+        u8::from(state.turn == Color::Black)
+            | u8::from(state.phase == TurnPhase::PlaceBoulder) << 1,
+    ];
+    body.extend(rules_bytes(state)?);
+    write_varint(&mut body, u64::from(state.move_number));
+    write_varint(&mut body, u64::from(state.half_move_clock));
+    let status = match state.status {
+        GameStatus::Ongoing => Vec::new(),
+        ref status => serde_json::to_vec(status).ok()?,
+    };
+    write_bytes(&mut body, &status);
+    write_bytes(&mut body, &state.board.to_packed_bytes()?);
+
+    let mut hand: Vec<(u8, u8)> = Vec::new();
+    for color in [Color::White, Color::Black] {
+        for (&piece_type, &count) in state.hands.get(color) {
+            hand.push((piece_code(&Piece::new(piece_type, color))?, count));
+        }
+    }
+    hand.sort_unstable();
+    write_varint(&mut body, hand.len() as u64);
+    for (code, count) in hand {
+        body.extend([code, count]);
+    }
+
+    let repetitions = state.half_move_clock as usize + 1;
+    let records = state
+        .position_history
+        .get(state.position_history.len().saturating_sub(repetitions)..)?;
+    write_varint(&mut body, records.len() as u64);
+    for record in records {
+        body.extend(record.hash.to_le_bytes());
+        body.push(u8::from(record.in_check) | u8::from(record.side_to_move == Color::Black) << 1);
+    }
+
+    let moves = state
+        .history
+        .get(state.history.len().saturating_sub(AUTOSAVE_HISTORY_TAIL)..)?;
+    write_varint(&mut body, moves.len() as u64);
+    for mv in moves {
+        let promotion = mv.promotion.map(|piece_type| Piece {
+            piece_type,
+            ..mv.piece
+        });
+        body.extend([
+            slot_of(mv.from)? as u8,
+            slot_of(mv.to)? as u8,
+            piece_code(&mv.piece)?,
+            optional_code(mv.captured.as_ref())?,
+            optional_code(promotion.as_ref())?,
+            match mv.boulder {
+                Some(cell) => slot_of(cell)? as u8,
+                None => ABSENT,
+            },
+            u8::from(mv.is_drop),
+        ]);
+    }
+
+    let mut bytes = AUTOSAVE_MAGIC.to_vec();
+    bytes.push(AUTOSAVE_VERSION);
+    bytes.extend(seq.to_le_bytes());
+    bytes.extend(fnv1a_bytes(&body).to_le_bytes());
+    bytes.extend(body);
+    Some(bytes)
+}
+
+/// Restore a game from an autosave snapshot. The history holds only the
+/// snapshot's last moves, so undo reaches no further back. Returns None for
+/// a torn or corrupt snapshot (bad checksum), another version or malformed
+/// data.
+pub fn restore_autosave(bytes: &[u8]) -> Option<AutosaveSnapshot> {
+    let (header, body) = bytes.split_at_checked(AUTOSAVE_HEADER_BYTES)?;
+    let (magic, header) = header.split_at_checked(AUTOSAVE_MAGIC.len())?;
+    let (&version, header) = header.split_first()?;
+    if magic != AUTOSAVE_MAGIC || version != AUTOSAVE_VERSION {
+        return None;
+    }
+    let (seq, checksum) = header.split_at_checked(4)?;
+    let seq = u32::from_le_bytes(seq.try_into().ok()?);
+    let checksum = u64::from_le_bytes(checksum.try_into().ok()?);
+    if fnv1a_bytes(body) != checksum {
+        return None;
+    }
+
+    let mut reader = ByteReader { bytes: body };
+    let variant = *VARIANTS.get(usize::from(reader.byte()?))?;
+    let flags = reader.byte()?;
+    let (stalemate_rule, win_conditions) =
+        rules_from_bytes([reader.byte()?, reader.byte()?, reader.byte()?])?;
+    let move_number = u32::try_from(reader.varint()?).ok()?;
+    let half_move_clock = u32::try_from(reader.varint()?).ok()?;
+    let status = match reader.bytes()? {
+        [] => GameStatus::Ongoing,
+        json => serde_json::from_slice(json).ok()?,
+    };
+    let board = BoardState::from_packed_bytes(reader.bytes()?)?;
+
+    let mut hands = Hands::default();
+    for _ in 0..reader.varint()? {
+        let piece = piece_from_code(reader.byte()?)?;
+        let count = reader.byte()?;
+        hands.get_mut(piece.color).insert(piece.piece_type, count);
+    }
+
+    let mut position_history = Vec::new();
+    for _ in 0..reader.varint()? {
+        let hash = u64::from_le_bytes(reader.take(8)?.try_into().ok()?);
+        let flags = reader.byte()?;
+        position_history.push(PositionRecord {
+            key: String::new(),
+            hash,
+            side_to_move: if flags & 2 != 0 {
+                Color::Black
+            } else {
+                Color::White
+            },
+            in_check: flags & 1 != 0,
+        });
+    }
+
+    let mut history = Vec::new();
+    for _ in 0..reader.varint()? {
+        let [from, to, piece, captured, promotion, boulder, is_drop] =
+            reader.take(7)?.try_into().ok()?;
+        history.push(Move {
+            from: read_cell(from)?,
+            to: read_cell(to)?,
+            piece: piece_from_code(piece)?,
+            captured: read_optional_piece(captured)?,
+            promotion: read_optional_piece(promotion)?.map(|piece| piece.piece_type),
+            is_drop: is_drop != 0,
+            boulder: match boulder {
+                ABSENT => None,
+                slot => Some(read_cell(slot)?),
+            },
+        });
+    }
+    if !reader.bytes.is_empty() {
+        return None;
+    }
+
+    Some(AutosaveSnapshot {
+        seq,
+        state: GameState {
+            board,
+            turn: if flags & 1 != 0 {
+                Color::Black
+            } else {
+                Color::White
+            },
+            move_number,
+            half_move_clock,
+            history,
+            status,
+            position_history,
+            variant,
+            hands,
+            phase: if flags & 2 != 0 {
+                TurnPhase::PlaceBoulder
+            } else {
+                TurnPhase::Move
+            },
+            stalemate_rule,
+            win_conditions,
+        },
+    })
+}
+
+/// The valid snapshot with the highest sequence number among `blobs`, e.g.
+/// the slots written alternately by an autosaver. None if none is valid.
+pub fn newest_autosave<'a>(blobs: impl IntoIterator<Item = &'a [u8]>) -> Option<AutosaveSnapshot> {
+    blobs
+        .into_iter()
+        .filter_map(restore_autosave)
+        .max_by_key(|snapshot| snapshot.seq)
+}
+
+fn optional_code(piece: Option<&Piece>) -> Option<u8> {
+    piece.map_or(Some(ABSENT), piece_code)
+}
+
+/// The piece of a code or [`ABSENT`]. None for an unknown code.
+fn read_optional_piece(code: u8) -> Option<Option<Piece>> {
+    match code {
+        ABSENT => Some(None),
+        code => piece_from_code(code).map(Some),
+    }
+}
+
+fn read_cell(slot: u8) -> Option<HexCoord> {
+    let slot = usize::from(slot);
+    (slot < SLOTS).then(|| cell_of(slot))
+}
+
+/// Append `value` in LEB128.
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let low = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(low);
+            return;
+        }
+        bytes.push(low | 0x80);
+    }
+}
+
+/// Append `data` after its length.
+fn write_bytes(bytes: &mut Vec<u8>, data: &[u8]) {
+    write_varint(bytes, data.len() as u64);
+    bytes.extend_from_slice(data);
+}
+
+/// Reads what the snapshot writer wrote, front to back.
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, count: usize) -> Option<&'a [u8]> {
+        let (taken, rest) = self.bytes.split_at_checked(count)?;
+        self.bytes = rest;
+        Some(taken)
+    }
+
+    fn byte(&mut self) -> Option<u8> {
+        self.take(1)?.first().copied()
+    }
+
+    fn varint(&mut self) -> Option<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= u64::from(byte & 0x7F) << shift;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    /// Bytes written by [`write_bytes`].
+    fn bytes(&mut self) -> Option<&'a [u8]> {
+        let count = usize::try_from(self.varint()?).ok()?;
+        self.take(count)
+    }
+}
+
+// ============================================================================
+// Signed Saves
+// ============================================================================
+
+#[cfg(feature = "signed-saves")]
+pub use signed::*;
+
+#[cfg(feature = "signed-saves")]
+mod signed {
+    use hmac::{Hmac, Mac};
+    use serde::{Deserialize, Serialize};
+    use sha2::Sha256;
+
+    use super::{load_game, save_game};
+    use crate::types::GameState;
+
+    /// Version of the signed save envelope.
+    pub const SIGNED_SAVE_VERSION: u32 = 1;
+
+    #[derive(Serialize, Deserialize)]
+    struct SignedSave {
+        version: u32,
+        state: String,
+        /// Hex HMAC-SHA256 of `state`
+        signature: String,
+    }
+
+    /// HMAC of a save's text, or None for a key the MAC cannot take.
+    fn mac(key: &[u8], state: &str) -> Option<Hmac<Sha256>> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).ok()?;
+        mac.update(state.as_bytes());
+        Some(mac)
+    }
+
+    fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+        if !hex.len().is_multiple_of(2) {
+            return None;
+        }
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+            .collect()
+    }
+
+    /// Save a game signed with `key`.
+    pub fn save_signed(state: &GameState, key: &[u8]) -> String {
+        let state = save_game(state);
+        let signature = mac(key, &state)
+            .map(|mac| {
+                mac.finalize()
+                    .into_bytes()
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect()
+            })
+            .unwrap_or_default();
+        serde_json::to_string(&SignedSave {
+            version: SIGNED_SAVE_VERSION,
+            state,
+            signature,
+        })
+        .unwrap_or_else(|_| "null".to_string())
+    }
+
+    /// Load a game saved with [`save_signed`] under the same key. Fails with
+    /// "malformed", "unsupportedVersion", "badSignature" (tampered, or signed
+    /// with another key) or "invalidState".
+    pub fn load_signed(json: &str, key: &[u8]) -> Result<GameState, String> {
+        let save: SignedSave = serde_json::from_str(json).map_err(|_| "malformed")?;
+        if save.version != SIGNED_SAVE_VERSION {
+            return Err("unsupportedVersion".to_string());
+        }
+        let signature = decode_hex(&save.signature).ok_or("badSignature")?;
+        let verified =
+            mac(key, &save.state).is_some_and(|mac| mac.verify_slice(&signature).is_ok());
+        if !verified {
+            return Err("badSignature".to_string());
+        }
+        load_game(&save.state).ok_or_else(|| "invalidState".to_string())
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{create_new_game, get_legal_moves, make_move};
+    use crate::rng::SeededRng;
+    use crate::session::replay_move;
+    use crate::types::HexCoord;
+
+    fn played_game() -> GameState {
+        let game = create_new_game();
+        make_move(&game, HexCoord::new(0, 2), HexCoord::new(0, 1)).unwrap()
+    }
+
+    #[test]
+    fn test_save_roundtrip() {
+        let game = played_game();
+        let loaded = load_game(&save_game(&game)).unwrap();
+        assert_eq!(loaded.history, game.history);
+        assert_eq!(loaded.position_history, game.position_history);
+        assert_eq!(loaded.half_move_clock, game.half_move_clock);
+        assert!(load_game("nope").is_none());
+    }
+
+    #[test]
+    fn test_compressed_save() {
+        let mut rng = SeededRng::new(5);
+        let mut game = create_new_game();
+        for _ in 0..100 {
+            let moves = get_legal_moves(&game);
+            let Some(mv) = rng.choose(&moves) else { break };
+            game = replay_move(&game, mv).unwrap();
+        }
+        let saved = save_game_compressed(&game);
+        assert!(saved.len() * 10 < save_game(&game).len());
+        let loaded = load_game(&saved).unwrap();
+        assert_eq!(loaded.history, game.history);
+        assert_eq!(loaded.position_history, game.position_history);
+        assert_eq!(loaded.status, game.status);
+        assert_eq!(loaded.move_number, game.move_number);
+
+        // Corrupt indices no longer replay to the saved board
+        let mut stored: serde_json::Value = serde_json::from_str(&saved).unwrap();
+        stored["compressed_history"]["indices"] = "AAAA".into();
+        assert!(load_game(&stored.to_string()).is_none());
+    }
+
+    #[test]
+    fn test_compressed_save_from_position() {
+        let mut board = create_new_game().board;
+        board.remove("0,-2");
+        let custom = crate::game::create_game_from_position(board, Color::White);
+        let custom = make_move(&custom, HexCoord::new(0, 2), HexCoord::new(0, 1)).unwrap();
+        let loaded = load_game(&save_game_compressed(&custom)).unwrap();
+        assert_eq!(loaded.history, custom.history);
+        assert_eq!(loaded.position_history, custom.position_history);
+
+        // A move awaiting its boulder placement is saved uncompressed
+        let boulder = create_variant_game(Variant::Boulder);
+        let moved = make_move(&boulder, HexCoord::new(0, 2), HexCoord::new(0, 1)).unwrap();
+        assert_eq!(moved.phase, TurnPhase::PlaceBoulder);
+        assert_eq!(save_game_compressed(&moved), save_game(&moved));
+    }
+
+    #[test]
+    fn test_autosave_roundtrip() {
+        let mut rng = SeededRng::new(9);
+        let mut game = create_variant_game(Variant::Crazyhouse { drop_mate: true });
+        game.stalemate_rule = StalemateRule::Fractional {
+            stalemater_quarters: 3,
+        };
+        game.win_conditions.bare_king = true;
+        for _ in 0..40 {
+            let moves = get_legal_moves(&game);
+            let Some(mv) = rng.choose(&moves) else { break };
+            game = replay_move(&game, mv).unwrap();
+        }
+        let blob = autosave_snapshot(&game, 7).unwrap();
+        assert!(blob.len() < 400);
+        let snapshot = restore_autosave(&blob).unwrap();
+        assert_eq!(snapshot.seq, 7);
+        let restored = snapshot.state;
+        assert!(boards_equal(&restored.board, &game.board));
+        assert_eq!(restored.hands, game.hands);
+        assert_eq!(restored.status, game.status);
+        assert_eq!(restored.stalemate_rule, game.stalemate_rule);
+        assert_eq!(restored.win_conditions, game.win_conditions);
+        assert_eq!(
+            (restored.move_number, restored.half_move_clock),
+            (game.move_number, game.half_move_clock)
+        );
+        assert_eq!(
+            restored.history[..],
+            game.history[game.history.len() - AUTOSAVE_HISTORY_TAIL..]
+        );
+        let recorded = game.position_history.last().unwrap();
+        assert!(restored
+            .position_history
+            .last()
+            .unwrap()
+            .same_position(recorded));
+    }
+
+    #[test]
+    fn test_newest_autosave() {
+        let game = played_game();
+        let older = autosave_snapshot(&game, 1).unwrap();
+        let newer = autosave_snapshot(&create_new_game(), 2).unwrap();
+        let mut torn = autosave_snapshot(&game, 3).unwrap();
+        let last = torn.len() - 1;
+        torn[last] ^= 1;
+        assert!(restore_autosave(&torn).is_none());
+        assert!(restore_autosave(&newer[..10]).is_none());
+
+        let newest = newest_autosave([&older[..], &newer[..], &torn[..]]).unwrap();
+        assert_eq!(newest.seq, 2);
+        assert!(newest.state.history.is_empty());
+        assert_eq!(
+            newest_autosave([&older[..]]).unwrap().state.history,
+            game.history
+        );
+        assert!(newest_autosave([&torn[..]]).is_none());
+    }
+
+    #[cfg(feature = "signed-saves")]
+    #[test]
+    fn test_signed_save_detects_tampering() {
+        let game = played_game();
+        let key = b"tournament key";
+        let saved = save_signed(&game, key);
+        let loaded = load_signed(&saved, key).unwrap();
+        assert_eq!(loaded.history, game.history);
+
+        let error = |json: &str, key: &[u8]| load_signed(json, key).err();
+        assert_eq!(error(&saved, b"other key").as_deref(), Some("badSignature"));
+        assert_eq!(error("nope", key).as_deref(), Some("malformed"));
+
+        // Edit the clock inside the signed state
+        let mut envelope: serde_json::Value = serde_json::from_str(&saved).unwrap();
+        let state = envelope["state"]
+            .as_str()
+            .unwrap()
+            .replace("\"half_move_clock\":0", "\"half_move_clock\":99");
+        assert_ne!(state, envelope["state"].as_str().unwrap());
+        envelope["state"] = state.into();
+        assert_eq!(
+            error(&envelope.to_string(), key).as_deref(),
+            Some("badSignature")
+        );
+
+        envelope["version"] = 2.into();
+        assert_eq!(
+            error(&envelope.to_string(), key).as_deref(),
+            Some("unsupportedVersion")
+        );
+    }
+}
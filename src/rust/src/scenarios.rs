@@ -0,0 +1,324 @@
+//! Underchex Scenarios
+//!
+//! Challenges defined as data, for a challenges mode: a start position, a
+//! goal such as "win within 20 moves" or "survive 15 moves against the hard
+//! AI", and constraints on the player's moves such as "never move your
+//! queen". A [`ScenarioRun`] plays a challenge: it rejects moves that break a
+//! constraint, answers the player's moves with the AI's and reports success
+//! or failure.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ai::{get_ai_move_for_variant, AIDifficulty, TranspositionTable};
+use crate::game::{create_game_from_position, try_make_move, GameResult};
+use crate::messages::{move_message, piece_type_key, Message};
+use crate::notation::parse_hexfen;
+use crate::session::replay_move;
+use crate::types::{BoardState, Color, GameState, HexCoord, PieceType};
+
+/// Transposition table entries of the opponent's searches.
+const OPPONENT_TT_SIZE: usize = 50_000;
+
+// ============================================================================
+// Definitions
+// ============================================================================
+
+/// What the player must achieve.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ScenarioGoal {
+    /// Win the game, within `moves` of the player's moves if given
+    Win { moves: Option<u32> },
+    /// Make `moves` moves without losing; an earlier draw or win also counts
+    Survive { moves: u32 },
+}
+
+/// A rule the player's moves must keep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ScenarioConstraint {
+    /// Pieces of this type may not move (moves with them are rejected)
+    NeverMove { piece: PieceType },
+    /// The player may not capture (captures are rejected)
+    NoCaptures,
+    /// Losing a piece of this type fails the challenge
+    KeepPiece { piece: PieceType },
+}
+
+/// A challenge.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Scenario {
+    pub id: String,
+    /// Start position in HexFEN, including the side to move
+    pub fen: String,
+    pub player: Color,
+    pub goal: ScenarioGoal,
+    #[serde(default)]
+    pub constraints: Vec<ScenarioConstraint>,
+    pub opponent: AIDifficulty,
+}
+
+// ============================================================================
+// Runtime
+// ============================================================================
+
+/// How a challenge stands.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum ScenarioStatus {
+    InProgress,
+    Succeeded,
+    /// "scenarioLost", "scenarioDrawn", "scenarioOutOfMoves" or
+    /// "scenarioPieceLost" (with the piece)
+    Failed {
+        reason: Message,
+    },
+}
+
+/// A challenge being played.
+pub struct ScenarioRun {
+    pub scenario: Scenario,
+    pub state: GameState,
+    pub status: ScenarioStatus,
+    /// Moves the player has made
+    pub moves: u32,
+    tt: TranspositionTable,
+}
+
+impl ScenarioRun {
+    /// Start a challenge, playing the opponent's first move if it is to move.
+    /// None if the HexFEN cannot be parsed.
+    pub fn start(scenario: Scenario) -> Option<Self> {
+        let (board, turn) = parse_hexfen(&scenario.fen)?;
+        let mut run = Self {
+            scenario,
+            state: create_game_from_position(board, turn),
+            status: ScenarioStatus::InProgress,
+            moves: 0,
+            tt: TranspositionTable::new(OPPONENT_TT_SIZE),
+        };
+        run.update();
+        if run.state.turn != run.scenario.player {
+            run.play_opponent();
+        }
+        Some(run)
+    }
+
+    /// Play the player's move and the opponent's reply. Rejects moves that
+    /// break a constraint ("constraintNeverMove" with the piece,
+    /// "constraintNoCaptures"), and moves after the challenge is over
+    /// ("gameOver") as well as illegal ones.
+    pub fn play_move(
+        &mut self,
+        from: HexCoord,
+        to: HexCoord,
+        promotion: Option<PieceType>,
+    ) -> Result<(), Message> {
+        let board = &self.state.board;
+        if self.status != ScenarioStatus::InProgress {
+            return Err(Message::new("gameOver"));
+        }
+        let moving = board.get(&from.to_key()).map(|piece| piece.piece_type);
+        let capture = board
+            .get(&to.to_key())
+            .is_some_and(|piece| piece.color != self.scenario.player);
+        for constraint in &self.scenario.constraints {
+            match *constraint {
+                ScenarioConstraint::NeverMove { piece } if moving == Some(piece) => {
+                    return Err(
+                        Message::new("constraintNeverMove").with("piece", piece_type_key(piece))
+                    );
+                }
+                ScenarioConstraint::NoCaptures if capture => {
+                    return Err(Message::new("constraintNoCaptures"));
+                }
+                _ => {}
+            }
+        }
+        self.state = try_make_move(&self.state, from, to, promotion)
+            .map_err(|reason| move_message(&reason, board, from, to))?;
+        self.moves += 1;
+        self.update();
+        if let (ScenarioStatus::InProgress, ScenarioGoal::Win { moves: Some(moves) }) =
+            (&self.status, self.scenario.goal)
+        {
+            if self.moves >= moves {
+                self.status = ScenarioStatus::Failed {
+                    reason: Message::new("scenarioOutOfMoves"),
+                };
+            }
+        }
+        self.play_opponent();
+        Ok(())
+    }
+
+    /// Play the opponent's move, if the challenge goes on.
+    fn play_opponent(&mut self) {
+        if self.status != ScenarioStatus::InProgress {
+            return;
+        }
+        self.tt.set_game_rules(&self.state);
+        let result = get_ai_move_for_variant(
+            &self.state.board,
+            self.state.turn,
+            self.scenario.opponent,
+            &mut self.tt,
+            &self.state.position_history,
+            self.state.variant,
+        );
+        let Some(state) = result
+            .best_move
+            .and_then(|mv| replay_move(&self.state, &mv))
+        else {
+            return;
+        };
+        self.state = state;
+        self.update();
+        if let (ScenarioStatus::InProgress, ScenarioGoal::Survive { moves }) =
+            (&self.status, self.scenario.goal)
+        {
+            if self.moves >= moves {
+                self.status = ScenarioStatus::Succeeded;
+            }
+        }
+    }
+
+    /// Judge the challenge by the game's result and the pieces kept.
+    fn update(&mut self) {
+        let player = self.scenario.player;
+        let fail = |key: &str| ScenarioStatus::Failed {
+            reason: Message::new(key),
+        };
+        self.status = if let Some(result) = GameResult::from_status(&self.state.status) {
+            match (result.winner, self.scenario.goal) {
+                (Some(winner), _) if winner == player => ScenarioStatus::Succeeded,
+                (Some(_), _) => fail("scenarioLost"),
+                (None, ScenarioGoal::Win { .. }) => fail("scenarioDrawn"),
+                (None, ScenarioGoal::Survive { .. }) => ScenarioStatus::Succeeded,
+            }
+        } else if let Some(piece) = self.lost_piece() {
+            ScenarioStatus::Failed {
+                reason: Message::new("scenarioPieceLost").with("piece", piece_type_key(piece)),
+            }
+        } else {
+            ScenarioStatus::InProgress
+        };
+    }
+
+    /// A piece type the player must keep but has lost, if any.
+    fn lost_piece(&self) -> Option<PieceType> {
+        let player = self.scenario.player;
+        let count = |piece_type: PieceType, board: &BoardState| {
+            board
+                .values()
+                .filter(|piece| piece.color == player && piece.piece_type == piece_type)
+                .count()
+        };
+        let (start, _) = parse_hexfen(&self.scenario.fen)?;
+        self.scenario
+            .constraints
+            .iter()
+            .find_map(|constraint| match *constraint {
+                ScenarioConstraint::KeepPiece { piece }
+                    if count(piece, &self.state.board) < count(piece, &start) =>
+                {
+                    Some(piece)
+                }
+                _ => None,
+            })
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::create_new_game;
+    use crate::notation::board_to_hexfen;
+
+    /// White's queen mates on (0, -2).
+    const MATE_IN_ONE: &str = "4kpQ2/3p5/9/9/9/9/9/9/4K4 w";
+
+    fn scenario(fen: &str, goal: ScenarioGoal, constraints: Vec<ScenarioConstraint>) -> Scenario {
+        Scenario {
+            id: "test".to_string(),
+            fen: fen.to_string(),
+            player: Color::White,
+            goal,
+            constraints,
+            opponent: AIDifficulty::Easy,
+        }
+    }
+
+    #[test]
+    fn test_scenario_from_json() {
+        let json = r#"{"id":"queenless","fen":"4kpQ2/3p5/9/9/9/9/9/9/4K4 w",
+            "player":"White","goal":{"type":"win","moves":20},
+            "constraints":[{"type":"neverMove","piece":"Queen"},{"type":"noCaptures"}],
+            "opponent":"hard"}"#;
+        let scenario: Scenario = serde_json::from_str(json).unwrap();
+        assert_eq!(scenario.goal, ScenarioGoal::Win { moves: Some(20) });
+        assert_eq!(scenario.constraints[1], ScenarioConstraint::NoCaptures);
+        assert_eq!(scenario.opponent, AIDifficulty::Hard);
+    }
+
+    #[test]
+    fn test_win_within_moves() {
+        let win = ScenarioGoal::Win { moves: Some(1) };
+        let mut run = ScenarioRun::start(scenario(MATE_IN_ONE, win, Vec::new())).unwrap();
+        run.play_move(HexCoord::new(2, -4), HexCoord::new(0, -2), None)
+            .unwrap();
+        assert_eq!(run.status, ScenarioStatus::Succeeded);
+
+        // A quiet king move spends the only move
+        let mut run = ScenarioRun::start(scenario(MATE_IN_ONE, win, Vec::new())).unwrap();
+        run.play_move(HexCoord::new(0, 4), HexCoord::new(0, 3), None)
+            .unwrap();
+        assert_eq!(
+            run.status,
+            ScenarioStatus::Failed {
+                reason: Message::new("scenarioOutOfMoves")
+            }
+        );
+        let rejected = run.play_move(HexCoord::new(0, 3), HexCoord::new(0, 2), None);
+        assert_eq!(rejected.unwrap_err().key, "gameOver");
+    }
+
+    #[test]
+    fn test_constraints_are_enforced() {
+        let constraints = vec![ScenarioConstraint::NeverMove {
+            piece: PieceType::Queen,
+        }];
+        let goal = ScenarioGoal::Win { moves: None };
+        let mut run = ScenarioRun::start(scenario(MATE_IN_ONE, goal, constraints)).unwrap();
+        let rejected = run
+            .play_move(HexCoord::new(2, -4), HexCoord::new(0, -2), None)
+            .unwrap_err();
+        assert_eq!(rejected.key, "constraintNeverMove");
+        assert_eq!(rejected.params["piece"], "queen");
+        assert_eq!(run.moves, 0);
+        assert_eq!(run.status, ScenarioStatus::InProgress);
+
+        // Illegal moves are rejected as in a game
+        let illegal = run
+            .play_move(HexCoord::new(0, 4), HexCoord::new(0, 0), None)
+            .unwrap_err();
+        assert_eq!(run.moves, 0);
+        assert_ne!(illegal.key, "constraintNeverMove");
+    }
+
+    #[test]
+    fn test_survive_moves() {
+        let start = board_to_hexfen(&create_new_game().board, Color::White);
+        let goal = ScenarioGoal::Survive { moves: 1 };
+        let mut run = ScenarioRun::start(scenario(&start, goal, Vec::new())).unwrap();
+        run.play_move(HexCoord::new(0, 2), HexCoord::new(0, 1), None)
+            .unwrap();
+        assert_eq!(run.state.history.len(), 2);
+        assert_eq!(run.status, ScenarioStatus::Succeeded);
+    }
+}
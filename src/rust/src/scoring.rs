@@ -0,0 +1,228 @@
+//! Underchex Score Attack
+//!
+//! Scoring for the single-player "beat the bot fast" mode. A run scores only
+//! if the player checkmates the bot, and earns bonuses for using few moves,
+//! little time, accurate moves and keeping material. The rules live here so
+//! that leaderboards do not depend on client-side arithmetic: the score is
+//! computed from a replay of the run's moves, never from a claimed result.
+//!
+//! Every part of the score is an integer computed the same way on every
+//! platform. The elapsed time is the only input that cannot be checked from
+//! the moves; servers should measure it themselves.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ai::{evaluate_quiet, get_piece_value};
+use crate::game::create_new_game;
+use crate::moves::apply_move;
+use crate::profile::move_accuracy;
+use crate::session::replay_move;
+use crate::types::{BoardState, Color, GameState, GameStatus, Move, Variant};
+
+/// Points for winning.
+pub const WIN_POINTS: u32 = 10_000;
+
+/// Player moves after which no move bonus is left.
+pub const MOVE_PAR: u32 = 40;
+
+/// Points per player move under [`MOVE_PAR`].
+pub const POINTS_PER_MOVE_SAVED: u32 = 100;
+
+/// Time after which no time bonus is left.
+pub const TIME_PAR_MS: u64 = 600_000;
+
+/// Points per second under [`TIME_PAR_MS`].
+pub const POINTS_PER_SECOND_SAVED: u32 = 5;
+
+/// Points per point of average accuracy (0-100).
+pub const POINTS_PER_ACCURACY: u32 = 50;
+
+/// Points per percent of starting material kept.
+pub const POINTS_PER_MATERIAL_PERCENT: u32 = 30;
+
+/// A score attack run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoreAttackRun {
+    /// Moves played from the standard starting position
+    pub moves: Vec<Move>,
+    /// The color the player had
+    pub player: Color,
+    /// Time the run took
+    pub time_ms: u64,
+}
+
+/// The score of a run and how it was made up.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScoreBreakdown {
+    /// Whether the player checkmated the bot (otherwise the score is 0)
+    pub won: bool,
+    pub player_moves: u32,
+    /// Average accuracy of the player's moves, 0-100
+    pub accuracy: u32,
+    /// Share of the player's starting material left, in percent
+    pub material_kept: u32,
+    pub win_points: u32,
+    pub move_bonus: u32,
+    pub time_bonus: u32,
+    pub accuracy_bonus: u32,
+    pub material_bonus: u32,
+    pub total: u32,
+}
+
+/// Total value of a color's pieces.
+fn material(board: &BoardState, color: Color) -> i32 {
+    board
+        .values()
+        .filter(|piece| piece.color == color && !piece.is_boulder())
+        .map(|piece| get_piece_value(piece.piece_type))
+        .sum()
+}
+
+/// Score a run. Returns None if its moves do not replay legally.
+pub fn score_run(run: &ScoreAttackRun) -> Option<ScoreBreakdown> {
+    score_from(&create_new_game(), run)
+}
+
+fn score_from(start: &GameState, run: &ScoreAttackRun) -> Option<ScoreBreakdown> {
+    let mut state = start.clone();
+    let starting_material = material(&state.board, run.player);
+    let sign = if run.player == Color::White { 1 } else { -1 };
+    let mut accuracy_sum = 0.0;
+    let mut player_moves = 0u32;
+
+    for mv in &run.moves {
+        if mv.piece.color == run.player {
+            let after = apply_move(&state.board, mv);
+            let before_score = sign * evaluate_quiet(&state.board, run.player, Variant::Standard);
+            let after_score =
+                sign * evaluate_quiet(&after, run.player.opposite(), Variant::Standard);
+            accuracy_sum += move_accuracy(before_score, after_score);
+            player_moves += 1;
+        }
+        state = replay_move(&state, mv)?;
+    }
+
+    let mut breakdown = ScoreBreakdown {
+        won: state.status == GameStatus::Checkmate { winner: run.player },
+        player_moves,
+        ..Default::default()
+    };
+    if player_moves > 0 {
+        breakdown.accuracy = (accuracy_sum / f64::from(player_moves)).round() as u32;
+    }
+    if starting_material > 0 {
+        breakdown.material_kept =
+            (100 * material(&state.board, run.player) / starting_material).clamp(0, 100) as u32;
+    }
+    if !breakdown.won {
+        return Some(breakdown);
+    }
+
+    breakdown.win_points = WIN_POINTS;
+    breakdown.move_bonus = MOVE_PAR.saturating_sub(player_moves) * POINTS_PER_MOVE_SAVED;
+    breakdown.time_bonus =
+        (TIME_PAR_MS.saturating_sub(run.time_ms) / 1000) as u32 * POINTS_PER_SECOND_SAVED;
+    breakdown.accuracy_bonus = breakdown.accuracy * POINTS_PER_ACCURACY;
+    breakdown.material_bonus = breakdown.material_kept * POINTS_PER_MATERIAL_PERCENT;
+    breakdown.total = breakdown.win_points
+        + breakdown.move_bonus
+        + breakdown.time_bonus
+        + breakdown.accuracy_bonus
+        + breakdown.material_bonus;
+    Some(breakdown)
+}
+
+/// Score a run given as JSON (see [`ScoreAttackRun`]). Returns None if the
+/// JSON is invalid or the moves do not replay legally.
+pub fn score_run_from_json(json: &str) -> Option<ScoreBreakdown> {
+    score_run(&serde_json::from_str(json).ok()?)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{create_game_from_position, get_legal_moves};
+    use crate::notation::parse_hexfen;
+    use crate::puzzles::fallback_puzzle;
+    use crate::test_support::play;
+    use crate::types::HexCoord;
+
+    #[test]
+    fn test_unfinished_run_scores_nothing() {
+        let run = ScoreAttackRun {
+            moves: play(&[[0, 2, 0, 1], [0, -2, 0, -1]]).history,
+            player: Color::White,
+            time_ms: 5_000,
+        };
+        let score = score_run(&run).unwrap();
+        assert!(!score.won);
+        assert_eq!(score.player_moves, 1);
+        assert_eq!(score.material_kept, 100);
+        assert!(score.accuracy > 0);
+        assert_eq!(score.total, 0);
+    }
+
+    #[test]
+    fn test_won_run() {
+        let (board, turn) = parse_hexfen(&fallback_puzzle().fen).unwrap();
+        let start = create_game_from_position(board, turn);
+        let mate = get_legal_moves(&start)
+            .into_iter()
+            .find(|mv| {
+                replay_move(&start, mv).unwrap().status
+                    == GameStatus::Checkmate {
+                        winner: Color::White,
+                    }
+            })
+            .unwrap();
+        let run = ScoreAttackRun {
+            moves: vec![mate],
+            player: Color::White,
+            time_ms: 60_000,
+        };
+        let score = score_from(&start, &run).unwrap();
+        assert!(score.won);
+        assert_eq!(score.win_points, WIN_POINTS);
+        assert_eq!(score.move_bonus, (MOVE_PAR - 1) * POINTS_PER_MOVE_SAVED);
+        assert_eq!(score.time_bonus, 540 * POINTS_PER_SECOND_SAVED);
+        assert_eq!(score.material_kept, 100);
+        assert_eq!(
+            score.total,
+            score.win_points
+                + score.move_bonus
+                + score.time_bonus
+                + score.accuracy_bonus
+                + score.material_bonus
+        );
+
+        // A slower run scores less, a run past the par time gets no time bonus
+        let slow = score_from(
+            &start,
+            &ScoreAttackRun {
+                time_ms: TIME_PAR_MS + 1,
+                ..run
+            },
+        )
+        .unwrap();
+        assert_eq!(slow.time_bonus, 0);
+        assert!(slow.total < score.total);
+    }
+
+    #[test]
+    fn test_illegal_runs_are_rejected() {
+        let mut moves = play(&[[0, 2, 0, 1]]).history;
+        // Claim a move the position does not allow
+        moves[0].to = HexCoord::new(0, -3);
+        let run = ScoreAttackRun {
+            moves,
+            player: Color::White,
+            time_ms: 1_000,
+        };
+        assert!(score_run(&run).is_none());
+        assert!(score_run_from_json("nope").is_none());
+    }
+}
@@ -0,0 +1,382 @@
+//! Underchex Self-Play Data
+//!
+//! Self-play games as raw material for the tuning, book-building and puzzle
+//! pipelines. Each game starts with a few random moves and pits two randomly
+//! chosen skill levels (or named bots, see [`create_bot`]) against each
+//! other, so that games differ and cover positions of uneven play. A game is kept as a compact game record (see
+//! [`encode_game_compact`]) with its result, and yields training positions
+//! labelled with the search score and the game's result. [`SelfPlayDedup`]
+//! keeps repeated games and positions out of the output.
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ai::{evaluate_position_with_hands, AIDifficulty, AIOptions};
+use crate::bots::{create_bot, play_bot_game, SearchLimits};
+use crate::game::{create_new_game, game_result};
+use crate::moves::is_in_check;
+use crate::notation::board_to_hexfen;
+use crate::rng::{fnv1a, SeededRng};
+use crate::session::replay_move;
+use crate::share::{base64_encode, encode_game_compact, ordered_moves};
+use crate::simulation::simulate_game_from;
+use crate::types::{Color, GameState, GameStatus};
+
+// ============================================================================
+// Configuration
+// ============================================================================
+
+/// How self-play games are set up.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelfPlayConfig {
+    /// Most random moves played before the engines take over (at least 2)
+    pub opening_plies: usize,
+    /// Longest game, counting the opening; longer games are drawn
+    pub max_plies: usize,
+    /// Levels each side's level is drawn from
+    pub levels: Vec<AIDifficulty>,
+    /// Bot names each side's bot is drawn from; when set, used instead of
+    /// `levels`
+    pub bots: Vec<String>,
+    /// Training positions sampled per game
+    pub positions_per_game: usize,
+}
+
+impl Default for SelfPlayConfig {
+    fn default() -> Self {
+        Self {
+            opening_plies: 8,
+            max_plies: 200,
+            levels: vec![AIDifficulty::Easy, AIDifficulty::Medium, AIDifficulty::Hard],
+            bots: Vec::new(),
+            positions_per_game: 8,
+        }
+    }
+}
+
+// ============================================================================
+// Games
+// ============================================================================
+
+/// A finished self-play game.
+#[derive(Debug, Clone)]
+pub struct SelfPlayGame {
+    /// The game from the starting position, with its final status
+    pub state: GameState,
+    /// Random moves it opened with
+    pub opening_plies: usize,
+    /// Level ("easy", "medium", "hard") or bot name of each side
+    pub white: String,
+    pub black: String,
+    /// Search score after each engine move, from White's side (the static
+    /// evaluation for bot games)
+    pub scores: Vec<i32>,
+}
+
+impl SelfPlayGame {
+    /// White's points: 1, 0.5 or 0.
+    pub fn white_points(&self) -> f64 {
+        match game_result(&self.state).and_then(|result| result.winner) {
+            Some(Color::White) => 1.0,
+            Some(Color::Black) => 0.0,
+            None => 0.5,
+        }
+    }
+}
+
+/// Play up to `plies` random legal moves from the starting position,
+/// stopping before a move that would end the game.
+pub fn random_opening(plies: usize, rng: &mut SeededRng) -> GameState {
+    let mut state = create_new_game();
+    for _ in 0..plies {
+        let moves = ordered_moves(&state);
+        let Some(next) = rng.choose(&moves).and_then(|mv| replay_move(&state, mv)) else {
+            break;
+        };
+        if next.status != GameStatus::Ongoing {
+            break;
+        }
+        state = next;
+    }
+    state
+}
+
+/// Play a self-play game: a random opening of 2 to `opening_plies` moves,
+/// then levels drawn from `levels` for each side, or bots drawn from `bots`
+/// when set. Returns None without levels, or for an unknown bot name.
+pub fn play_self_play_game(config: &SelfPlayConfig, rng: &mut SeededRng) -> Option<SelfPlayGame> {
+    if !config.bots.is_empty() {
+        return play_bot_self_play_game(config, rng);
+    }
+    let white = *rng.choose(&config.levels)?;
+    let black = *rng.choose(&config.levels)?;
+    let opening = self_play_opening(config, rng);
+    let opening_plies = opening.history.len();
+
+    let game = simulate_game_from(
+        &opening,
+        &AIOptions::new(white),
+        &AIOptions::new(black),
+        config.max_plies.saturating_sub(opening_plies),
+    );
+    let mut state = opening;
+    for mv in &game.moves {
+        state = replay_move(&state, mv)?;
+    }
+    state.status = game.status;
+    Some(SelfPlayGame {
+        state,
+        opening_plies,
+        white: level_name(white),
+        black: level_name(black),
+        scores: game.scores,
+    })
+}
+
+/// A self-play game between bots drawn from `bots`.
+fn play_bot_self_play_game(config: &SelfPlayConfig, rng: &mut SeededRng) -> Option<SelfPlayGame> {
+    let white = rng.choose(&config.bots)?.clone();
+    let black = rng.choose(&config.bots)?.clone();
+    let mut white_bot = create_bot(&white, rng.next_u64())?;
+    let mut black_bot = create_bot(&black, rng.next_u64())?;
+    let opening = self_play_opening(config, rng);
+    let opening_plies = opening.history.len();
+
+    let mut state = play_bot_game(
+        &opening,
+        white_bot.as_mut(),
+        black_bot.as_mut(),
+        &SearchLimits::default(),
+        config.max_plies.saturating_sub(opening_plies),
+    );
+    if state.status == GameStatus::Ongoing {
+        state.status = GameStatus::Draw {
+            reason: "moveLimit".to_string(),
+        };
+    }
+    let mut position = opening;
+    let mut scores = Vec::new();
+    for mv in state.history.iter().skip(opening_plies) {
+        position = replay_move(&position, mv)?;
+        scores.push(evaluate_position_with_hands(
+            &position.board,
+            &position.hands,
+        ));
+    }
+    Some(SelfPlayGame {
+        state,
+        opening_plies,
+        white,
+        black,
+        scores,
+    })
+}
+
+/// The random opening of a self-play game: 2 to `opening_plies` moves.
+fn self_play_opening(config: &SelfPlayConfig, rng: &mut SeededRng) -> GameState {
+    let plies = 2 + rng.next_below(config.opening_plies.saturating_sub(1).max(1));
+    random_opening(plies, rng)
+}
+
+fn level_name(level: AIDifficulty) -> String {
+    format!("{:?}", level).to_lowercase()
+}
+
+// ============================================================================
+// Records
+// ============================================================================
+
+/// A game's record line: its compact record in unpadded base64url and its
+/// result ("1-0", "0-1" or "1/2-1/2"), separated by a space. None for a game
+/// without a compact record.
+pub fn game_record_line(game: &SelfPlayGame) -> Option<String> {
+    let code = base64_encode(&encode_game_compact(&game.state)?);
+    let result = game_result(&game.state).map_or_else(|| "*".to_string(), |result| result.score);
+    Some(format!("{} {}", code, result))
+}
+
+/// A position from a self-play game, labelled for training.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrainingPosition {
+    /// Position in HexFEN, including the side to move
+    pub fen: String,
+    /// Search score of the move played from it, from White's side
+    pub score: i32,
+    /// White's points in the game: 1, 0.5 or 0
+    pub result: f64,
+}
+
+/// Sample up to `count` quiet positions of a game's engine moves: neither
+/// side in check, and not right after a capture.
+pub fn training_positions(
+    game: &SelfPlayGame,
+    count: usize,
+    rng: &mut SeededRng,
+) -> Vec<TrainingPosition> {
+    let mut state = create_new_game();
+    let mut candidates = Vec::new();
+    for (ply, mv) in game.state.history.iter().enumerate() {
+        let engine_ply = ply.checked_sub(game.opening_plies);
+        let quiet = state
+            .history
+            .last()
+            .is_none_or(|last| last.captured.is_none())
+            && !is_in_check(&state.board, state.turn);
+        if let (Some(index), true) = (engine_ply, quiet) {
+            if let Some(&score) = game.scores.get(index) {
+                candidates.push(TrainingPosition {
+                    fen: board_to_hexfen(&state.board, state.turn),
+                    score,
+                    result: game.white_points(),
+                });
+            }
+        }
+        let Some(next) = replay_move(&state, mv) else {
+            break;
+        };
+        state = next;
+    }
+
+    let mut sample = Vec::new();
+    while sample.len() < count && !candidates.is_empty() {
+        let index = rng.next_below(candidates.len());
+        sample.push(candidates.swap_remove(index));
+    }
+    sample
+}
+
+// ============================================================================
+// Deduplication
+// ============================================================================
+
+/// Games and positions already written, by hash.
+#[derive(Debug, Clone, Default)]
+pub struct SelfPlayDedup {
+    games: HashSet<u64>,
+    positions: HashSet<u64>,
+}
+
+impl SelfPlayDedup {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Note a game by its record line (see [`game_record_line`]). Returns
+    /// whether it is new.
+    pub fn insert_game(&mut self, line: &str) -> bool {
+        let code = line.split_whitespace().next().unwrap_or_default();
+        self.games.insert(fnv1a(code))
+    }
+
+    /// Note a position by its HexFEN. Returns whether it is new.
+    pub fn insert_position(&mut self, fen: &str) -> bool {
+        self.positions.insert(fnv1a(fen))
+    }
+
+    pub fn games(&self) -> usize {
+        self.games.len()
+    }
+
+    pub fn positions(&self) -> usize {
+        self.positions.len()
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::share::{base64_decode, decode_game_compact};
+
+    fn quick_config() -> SelfPlayConfig {
+        SelfPlayConfig {
+            opening_plies: 4,
+            max_plies: 10,
+            levels: vec![AIDifficulty::Easy],
+            bots: Vec::new(),
+            positions_per_game: 3,
+        }
+    }
+
+    #[test]
+    fn test_self_play_game_record() {
+        let config = quick_config();
+        let game = play_self_play_game(&config, &mut SeededRng::new(5)).unwrap();
+        assert!((2..=4).contains(&game.opening_plies));
+        assert!(game.state.history.len() <= config.max_plies);
+        assert_ne!(game.state.status, GameStatus::Ongoing);
+        assert_eq!(
+            game.scores.len(),
+            game.state.history.len() - game.opening_plies
+        );
+
+        // The record replays to the same moves
+        let line = game_record_line(&game).unwrap();
+        let (code, result) = line.split_once(' ').unwrap();
+        assert_eq!(result, "1/2-1/2");
+        let decoded = decode_game_compact(&base64_decode(code).unwrap()).unwrap();
+        assert_eq!(decoded.history, game.state.history);
+
+        // The same seed plays the same game; another opens differently
+        let again = play_self_play_game(&config, &mut SeededRng::new(5)).unwrap();
+        assert_eq!(game_record_line(&again).unwrap(), line);
+        let other = play_self_play_game(&config, &mut SeededRng::new(6)).unwrap();
+        assert_ne!(game_record_line(&other).unwrap(), line);
+        assert!(play_self_play_game(
+            &SelfPlayConfig {
+                levels: Vec::new(),
+                ..config
+            },
+            &mut SeededRng::new(5)
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_bot_self_play_game() {
+        let config = SelfPlayConfig {
+            bots: vec!["random".to_string(), "greedy".to_string()],
+            ..quick_config()
+        };
+        let game = play_self_play_game(&config, &mut SeededRng::new(3)).unwrap();
+        assert!(config.bots.contains(&game.white) && config.bots.contains(&game.black));
+        assert_ne!(game.state.status, GameStatus::Ongoing);
+        assert_eq!(
+            game.scores.len(),
+            game.state.history.len() - game.opening_plies
+        );
+        assert!(game_record_line(&game).is_some());
+
+        let unknown = SelfPlayConfig {
+            bots: vec!["nobody".to_string()],
+            ..quick_config()
+        };
+        assert!(play_self_play_game(&unknown, &mut SeededRng::new(3)).is_none());
+    }
+
+    #[test]
+    fn test_training_positions_and_dedup() {
+        let config = quick_config();
+        let mut rng = SeededRng::new(9);
+        let game = play_self_play_game(&config, &mut rng).unwrap();
+        let positions = training_positions(&game, config.positions_per_game, &mut rng);
+        assert!(!positions.is_empty() && positions.len() <= 3);
+        for position in &positions {
+            assert_eq!(position.result, 0.5);
+            assert!(crate::notation::parse_hexfen(&position.fen).is_some());
+        }
+
+        let mut dedup = SelfPlayDedup::new();
+        let line = game_record_line(&game).unwrap();
+        assert!(dedup.insert_game(&line));
+        assert!(!dedup.insert_game(&line));
+        assert!(dedup.insert_position(&positions[0].fen));
+        assert!(!dedup.insert_position(&positions[0].fen));
+        assert_eq!((dedup.games(), dedup.positions()), (1, 1));
+    }
+}
@@ -0,0 +1,1312 @@
+//! Underchex Online Sessions
+//!
+//! Server-side game sessions for online play, and the state synchronization
+//! protocol clients use to catch up after reconnecting:
+//!
+//! 1. The client sends a [`SyncRequest`] with the number of moves it has and
+//!    a hash of its position.
+//! 2. The server answers with only the missing moves, or the full state if the
+//!    client is ahead of the server or its position does not match.
+//! 3. The client replays the missing moves through the normal move validation
+//!    and checks the resulting hash, falling back to a full-state request if
+//!    anything disagrees.
+//!
+//! For asynchronous (correspondence) play, moves can also be submitted as a
+//! [`MoveProof`] that commits to the hash of the resulting position. The server
+//! rejects a proof whose hash differs from its own result, and chains every
+//! accepted move into a [`Transcript`] that either party can replay and verify.
+//!
+//! Spectators follow a session through a [`BroadcastView`] that trails the
+//! live game by some moves or seconds.
+//!
+//! An [`Exhibition`] groups sessions for one exhibitor playing several boards
+//! at once on a shared thinking-time budget.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::bots::{Bot, SearchLimits};
+use crate::clock::{ClockSettings, GameClock};
+use crate::game::{
+    armageddon_result, game_result, make_boulder_placement, make_drop, make_move_with_promotion,
+    GameResult,
+};
+use crate::rng::fnv1a;
+use crate::stats::{time_usage, TimeReport, TimedHistory};
+use crate::types::{Color, GameState, GameStatus, Move, TurnPhase};
+
+// ============================================================================
+// Position Hashing
+// ============================================================================
+
+/// 64-bit FNV-1a hash of a string, as 16 hex digits (JSON-safe for clients
+/// whose numbers are doubles).
+fn fnv1a_hex(text: &str) -> String {
+    format!("{:016x}", fnv1a(text))
+}
+
+/// Hash of the position after `move_index` completed moves, built from the
+/// recorded position key (board, side to move and, in drop variants, hands).
+fn hash_at(key: &str, move_index: usize) -> String {
+    fnv1a_hex(&format!("{}#{}", key, move_index))
+}
+
+/// Hash identifying a game's current position for synchronization.
+/// A state waiting for a boulder placement never matches a completed turn.
+pub fn state_hash(state: &GameState) -> String {
+    let key = state
+        .position_history
+        .last()
+        .map(|record| record.key.as_str())
+        .unwrap_or("");
+    match state.phase {
+        TurnPhase::Move => hash_at(key, state.history.len()),
+        TurnPhase::PlaceBoulder => fnv1a_hex(&format!("{}#{}#boulder", key, state.history.len())),
+    }
+}
+
+/// Hash the server expects from a client that has `move_index` moves.
+fn server_hash_at(state: &GameState, move_index: usize) -> Option<String> {
+    if move_index == state.history.len() {
+        return Some(state_hash(state));
+    }
+    // position_history has one record per completed turn after the start
+    state
+        .position_history
+        .get(move_index)
+        .map(|record| hash_at(&record.key, move_index))
+}
+
+// ============================================================================
+// Sync Protocol
+// ============================================================================
+
+/// Sent by a (re)connecting client.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncRequest {
+    /// Number of moves the client has applied
+    pub last_move_index: usize,
+    /// The client's [`state_hash`]; None requests the full state
+    pub position_hash: Option<String>,
+}
+
+/// Server answer to a [`SyncRequest`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SyncResponse {
+    /// The client already has the current position; `status` carries
+    /// endings without a move, such as a resignation
+    UpToDate {
+        status: GameStatus,
+        position_hash: String,
+    },
+    /// The moves the client is missing, starting at `from_index`
+    Moves {
+        from_index: usize,
+        moves: Vec<Move>,
+        status: GameStatus,
+        position_hash: String,
+    },
+    /// The client's state cannot be patched; replace it
+    Full {
+        state: Box<GameState>,
+        position_hash: String,
+    },
+}
+
+/// Build the request a client sends when (re)connecting.
+pub fn create_sync_request(client: &GameState) -> SyncRequest {
+    SyncRequest {
+        last_move_index: client.history.len(),
+        position_hash: Some(state_hash(client)),
+    }
+}
+
+/// Answer a client's sync request from the authoritative server state.
+pub fn respond_to_sync(server: &GameState, request: &SyncRequest) -> SyncResponse {
+    let position_hash = state_hash(server);
+    let index = request.last_move_index;
+
+    let matches = request
+        .position_hash
+        .as_ref()
+        .is_some_and(|hash| server_hash_at(server, index).as_ref() == Some(hash));
+    if !matches {
+        return SyncResponse::Full {
+            state: Box::new(server.clone()),
+            position_hash,
+        };
+    }
+
+    if index == server.history.len() {
+        SyncResponse::UpToDate {
+            status: server.status.clone(),
+            position_hash,
+        }
+    } else {
+        SyncResponse::Moves {
+            from_index: index,
+            moves: server.history.get(index..).unwrap_or_default().to_vec(),
+            status: server.status.clone(),
+            position_hash,
+        }
+    }
+}
+
+/// Replay one recorded move (drop, move with optional promotion, and boulder
+/// placement) through the normal validation.
+pub fn replay_move(state: &GameState, mv: &Move) -> Option<GameState> {
+    let state = if mv.is_drop {
+        make_drop(state, mv.piece, mv.to)?
+    } else {
+        make_move_with_promotion(state, mv.from, mv.to, mv.promotion)?
+    };
+    match mv.boulder {
+        Some(cell) => make_boulder_placement(&state, cell),
+        None => Some(state),
+    }
+}
+
+/// Apply a sync response to the client's state. Returns None if the moves do
+/// not replay legally or the resulting position's hash does not match; the
+/// client should then request the full state (see [`full_sync_request`]).
+pub fn apply_sync_response(client: &GameState, response: &SyncResponse) -> Option<GameState> {
+    let (state, expected_hash) = match response {
+        SyncResponse::UpToDate {
+            status,
+            position_hash,
+        } => {
+            let mut state = client.clone();
+            if state.status == GameStatus::Ongoing {
+                state.status = status.clone();
+            }
+            (state, position_hash)
+        }
+        SyncResponse::Moves {
+            from_index,
+            moves,
+            status,
+            position_hash,
+        } => {
+            if *from_index != client.history.len() {
+                return None;
+            }
+            let mut state = moves
+                .iter()
+                .try_fold(client.clone(), |state, mv| replay_move(&state, mv))?;
+            if state.status == GameStatus::Ongoing {
+                // Resignations and other non-move endings
+                state.status = status.clone();
+            }
+            (state, position_hash)
+        }
+        SyncResponse::Full {
+            state,
+            position_hash,
+        } => ((**state).clone(), position_hash),
+    };
+
+    (state_hash(&state) == *expected_hash).then_some(state)
+}
+
+/// Request that always receives the full state.
+pub fn full_sync_request() -> SyncRequest {
+    SyncRequest {
+        last_move_index: 0,
+        position_hash: None,
+    }
+}
+
+// ============================================================================
+// Move Proofs
+// ============================================================================
+
+/// A move together with a commitment to the position it produces.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MoveProof {
+    /// Sequence number the move was made on (moves played before it)
+    pub seq: usize,
+    pub mv: Move,
+    /// [`state_hash`] of the position after the move
+    pub position_hash: String,
+}
+
+/// An accepted proof, linked to every proof before it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptEntry {
+    pub proof: MoveProof,
+    /// Hash of the previous entry's chain hash (or the start position's hash)
+    /// and this proof
+    pub chain_hash: String,
+}
+
+/// Verifiable record of a game: its starting state and every accepted move.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Transcript {
+    pub start: GameState,
+    pub entries: Vec<TranscriptEntry>,
+}
+
+/// Where and why a transcript failed verification.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TranscriptError {
+    /// Index of the offending entry
+    pub index: usize,
+    /// "sequenceGap", "illegalMove", "positionMismatch" or "chainMismatch"
+    pub reason: String,
+}
+
+/// Build the proof a client submits for a move made on `state`. Returns None
+/// if the move does not replay legally.
+pub fn create_move_proof(state: &GameState, mv: &Move) -> Option<MoveProof> {
+    let after = replay_move(state, mv)?;
+    Some(MoveProof {
+        seq: state.history.len(),
+        mv: mv.clone(),
+        position_hash: state_hash(&after),
+    })
+}
+
+/// Chain hash of a proof following `previous`.
+fn link_hash(previous: &str, proof: &MoveProof) -> String {
+    let mv = serde_json::to_string(&proof.mv).unwrap_or_default();
+    fnv1a_hex(&format!(
+        "{}|{}|{}|{}",
+        previous, proof.seq, mv, proof.position_hash
+    ))
+}
+
+impl Transcript {
+    pub fn new(start: GameState) -> Self {
+        Self {
+            start,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Chain hash of the latest entry (the start position's hash when empty).
+    /// Two parties agreeing on it agree on the whole game so far.
+    pub fn head(&self) -> String {
+        self.entries
+            .last()
+            .map(|entry| entry.chain_hash.clone())
+            .unwrap_or_else(|| state_hash(&self.start))
+    }
+
+    fn push(&mut self, proof: MoveProof) {
+        let chain_hash = link_hash(&self.head(), &proof);
+        self.entries.push(TranscriptEntry { proof, chain_hash });
+    }
+
+    /// Replay the transcript from its start, checking every move, position
+    /// hash and chain link. Returns the final state.
+    pub fn verify(&self) -> Result<GameState, TranscriptError> {
+        let mut state = self.start.clone();
+        let mut previous = state_hash(&state);
+        for (index, entry) in self.entries.iter().enumerate() {
+            let fail = |reason: &str| TranscriptError {
+                index,
+                reason: reason.to_string(),
+            };
+            let proof = &entry.proof;
+            if proof.seq != state.history.len() {
+                return Err(fail("sequenceGap"));
+            }
+            state = replay_move(&state, &proof.mv).ok_or_else(|| fail("illegalMove"))?;
+            if state_hash(&state) != proof.position_hash {
+                return Err(fail("positionMismatch"));
+            }
+            if link_hash(&previous, proof) != entry.chain_hash {
+                return Err(fail("chainMismatch"));
+            }
+            previous = entry.chain_hash.clone();
+        }
+        Ok(state)
+    }
+}
+
+// ============================================================================
+// Sessions
+// ============================================================================
+
+/// Result of submitting a move to a session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubmitResult {
+    pub accepted: bool,
+    /// Sequence number (move count) of the session after the submission
+    pub seq: usize,
+    pub reason: Option<String>,
+}
+
+/// Authoritative server-side game with sequence-numbered updates.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameSession {
+    pub state: GameState,
+    /// Clock for timed games
+    #[serde(default)]
+    pub clock: Option<GameClock>,
+    /// Every move accepted since the session was created (absent in sessions
+    /// saved before transcripts were recorded)
+    #[serde(default)]
+    pub transcript: Option<Transcript>,
+}
+
+impl GameSession {
+    pub fn new(state: GameState) -> Self {
+        Self {
+            transcript: Some(Transcript::new(state.clone())),
+            state,
+            clock: None,
+        }
+    }
+
+    /// Create a timed session whose clock starts for the side to move at
+    /// server time `now` (milliseconds).
+    pub fn with_clock(state: GameState, settings: ClockSettings, now: u64) -> Self {
+        let mut clock = GameClock::new(settings);
+        clock.start(state.turn, now);
+        Self {
+            transcript: Some(Transcript::new(state.clone())),
+            state,
+            clock: Some(clock),
+        }
+    }
+
+    /// Time usage of a timed game so far, for the post-game report.
+    pub fn time_report(&self) -> Option<TimeReport> {
+        let clock = self.clock.as_ref()?;
+        Some(time_usage(&TimedHistory::from_clock(&self.state, clock)))
+    }
+
+    /// Side a draw counts as a win for, in an armageddon game.
+    pub fn draw_winner(&self) -> Option<Color> {
+        self.clock
+            .as_ref()
+            .and_then(|clock| clock.settings.draw_winner())
+    }
+
+    /// Result of the game, or None while it is ongoing. In an armageddon
+    /// game a draw is a win for Black.
+    pub fn result(&self) -> Option<GameResult> {
+        match self.draw_winner() {
+            Some(winner) => armageddon_result(&self.state, winner),
+            None => game_result(&self.state),
+        }
+    }
+
+    /// Sequence number of the current state: the number of moves played.
+    pub fn seq(&self) -> usize {
+        self.state.history.len()
+    }
+
+    /// Apply a client's move made on top of sequence number `seq`. A move
+    /// for an older sequence number (e.g. a retry after a dropped connection)
+    /// is rejected as stale rather than applied twice.
+    pub fn submit_move(&mut self, seq: usize, mv: &Move) -> SubmitResult {
+        if seq != self.seq() {
+            return self.reject("staleSequence");
+        }
+        match replay_move(&self.state, mv) {
+            Some(state) => self.accept(mv, state),
+            None => self.reject("illegalMove"),
+        }
+    }
+
+    /// Apply a move proof (see [`create_move_proof`]). The proof is rejected
+    /// with "positionMismatch" if the position it commits to differs from the
+    /// server's result, which means the client is out of sync or tampered
+    /// with the move.
+    pub fn submit_move_proof(&mut self, proof: &MoveProof) -> SubmitResult {
+        if proof.seq != self.seq() {
+            return self.reject("staleSequence");
+        }
+        let Some(state) = replay_move(&self.state, &proof.mv) else {
+            return self.reject("illegalMove");
+        };
+        if state_hash(&state) != proof.position_hash {
+            return self.reject("positionMismatch");
+        }
+        self.accept(&proof.mv, state)
+    }
+
+    /// Submit a move in a timed game, received at server time `now` with the
+    /// thinking time the client reported. The move is validated before the
+    /// clock is pressed; if the mover's time ran out (after lag compensation)
+    /// the game is lost on time and the move is rejected.
+    pub fn submit_timed_move(
+        &mut self,
+        seq: usize,
+        mv: &Move,
+        now: u64,
+        client_elapsed_ms: Option<u64>,
+    ) -> SubmitResult {
+        // Whether a running clock has run out is left to the press, which
+        // allows for lag
+        if let GameStatus::Timeout { .. } = self.state.status {
+            return self.reject("timeout");
+        }
+        if seq != self.seq() {
+            return self.reject("staleSequence");
+        }
+        let Some(state) = replay_move(&self.state, mv) else {
+            return self.reject("illegalMove");
+        };
+
+        if let Some(clock) = &mut self.clock {
+            let running = clock.running.is_some();
+            if running && clock.press(now, client_elapsed_ms, seq).is_none() {
+                let loser = self.state.turn;
+                self.state.status = GameStatus::Timeout {
+                    winner: loser.opposite(),
+                };
+                return self.reject("timeout");
+            }
+            if state.status != GameStatus::Ongoing {
+                clock.running = None;
+            }
+        }
+        self.accept(mv, state)
+    }
+
+    /// Let a bot play the side to move, as if it had submitted its move. In
+    /// an armageddon game the bot knows which side a draw suffices for.
+    pub fn play_bot_move(&mut self, bot: &mut dyn Bot, limits: &SearchLimits) -> SubmitResult {
+        let limits = SearchLimits {
+            draw_winner: self.draw_winner(),
+            ..*limits
+        };
+        match bot.choose_move(&self.state, &limits) {
+            Some(mv) => self.submit_move(self.seq(), &mv),
+            None => self.reject("noMove"),
+        }
+    }
+
+    /// End the game on time if the running side's clock has run out at `now`.
+    /// Returns true if the game is lost on time.
+    pub fn check_timeout(&mut self, now: u64) -> bool {
+        if let GameStatus::Timeout { .. } = self.state.status {
+            return true;
+        }
+        let Some(clock) = &mut self.clock else {
+            return false;
+        };
+        if self.state.status != GameStatus::Ongoing {
+            return false;
+        }
+        match clock.flagged(now) {
+            Some(loser) => {
+                clock.white_ms = clock.remaining(Color::White, now);
+                clock.black_ms = clock.remaining(Color::Black, now);
+                clock.running = None;
+                self.state.status = GameStatus::Timeout {
+                    winner: loser.opposite(),
+                };
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Make `state`, the result of `mv`, current and record the move.
+    fn accept(&mut self, mv: &Move, state: GameState) -> SubmitResult {
+        if let Some(transcript) = &mut self.transcript {
+            transcript.push(MoveProof {
+                seq: self.state.history.len(),
+                mv: mv.clone(),
+                position_hash: state_hash(&state),
+            });
+        }
+        self.state = state;
+        SubmitResult {
+            accepted: true,
+            seq: self.seq(),
+            reason: None,
+        }
+    }
+
+    fn reject(&self, reason: &str) -> SubmitResult {
+        SubmitResult {
+            accepted: false,
+            seq: self.seq(),
+            reason: Some(reason.to_string()),
+        }
+    }
+
+    /// Answer a reconnecting client.
+    pub fn sync(&self, request: &SyncRequest) -> SyncResponse {
+        respond_to_sync(&self.state, request)
+    }
+}
+
+// ============================================================================
+// Broadcasts
+// ============================================================================
+
+/// How far a broadcast trails the live game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BroadcastDelay {
+    /// Withhold the last moves
+    Moves(usize),
+    /// Withhold the moves made within this many milliseconds
+    Millis(u64),
+}
+
+/// Clock readings shown with a broadcast view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BroadcastClock {
+    pub white_ms: u64,
+    pub black_ms: u64,
+    /// Side whose clock was running, for the viewer to count down
+    pub running: Option<Color>,
+}
+
+/// The public view of a game for spectators, some way behind the live game
+/// so that it cannot be used for live assistance.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BroadcastView {
+    /// The game as of the delayed moment
+    pub state: GameState,
+    /// Number of moves withheld
+    pub hidden_moves: usize,
+    /// Clocks as of the delayed moment, None for untimed games
+    pub clock: Option<BroadcastClock>,
+}
+
+/// Server times of a clock's first turn and of every recorded press, worked
+/// back from the start of the turn after the last press.
+fn press_times(clock: &GameClock) -> (u64, Vec<u64>) {
+    let mut time = clock.turn_started_at;
+    let mut presses = Vec::with_capacity(clock.records.len());
+    for record in clock.records.iter().rev() {
+        presses.push(time);
+        time = time.saturating_sub(record.server_elapsed_ms);
+    }
+    presses.reverse();
+    (time, presses)
+}
+
+/// Server time each of the first `moves` moves was made: its clock press,
+/// or for a move without a record the next recorded press (or the start of
+/// the running turn).
+fn move_times(clock: &GameClock, moves: usize) -> Vec<u64> {
+    let (_, presses) = press_times(clock);
+    let pressed: HashMap<usize, u64> = clock
+        .records
+        .iter()
+        .map(|record| record.move_index)
+        .zip(presses)
+        .collect();
+    let mut times = vec![clock.turn_started_at; moves];
+    let mut next = clock.turn_started_at;
+    for (index, time) in times.iter_mut().enumerate().rev() {
+        next = pressed.get(&index).copied().unwrap_or(next);
+        *time = next;
+    }
+    times
+}
+
+/// The clocks after the first `visible` moves, with `turn`'s running turn
+/// counted down to `moment` if given.
+fn clock_after(
+    clock: &GameClock,
+    visible: usize,
+    turn: Color,
+    moment: Option<u64>,
+) -> BroadcastClock {
+    let (mut turn_started_at, presses) = press_times(clock);
+    let mut shown = BroadcastClock {
+        white_ms: clock.settings.initial_ms,
+        black_ms: clock.settings.initial_ms,
+        running: Some(turn),
+    };
+    for (record, time) in clock.records.iter().zip(presses) {
+        if record.move_index >= visible {
+            break;
+        }
+        match record.color {
+            Color::White => shown.white_ms = record.remaining_ms,
+            Color::Black => shown.black_ms = record.remaining_ms,
+        }
+        turn_started_at = time;
+    }
+    if let Some(moment) = moment {
+        let elapsed = moment.saturating_sub(turn_started_at);
+        match turn {
+            Color::White => shown.white_ms = shown.white_ms.saturating_sub(elapsed),
+            Color::Black => shown.black_ms = shown.black_ms.saturating_sub(elapsed),
+        }
+    }
+    shown
+}
+
+impl GameSession {
+    /// The game as spectators may see it at server time `now`: behind the
+    /// live game by `delay`, with the clocks as they stood then (still at
+    /// the last shown move for a delay in moves). A finished game is shown
+    /// in full. A delay in milliseconds needs a clock; returns None for an
+    /// untimed game, or if the history does not replay.
+    pub fn broadcast_view(&self, delay: BroadcastDelay, now: u64) -> Option<BroadcastView> {
+        let moves = self.state.history.len();
+        let finished = self.state.status != GameStatus::Ongoing;
+        let (visible, moment) = match delay {
+            _ if finished => (moves, None),
+            BroadcastDelay::Moves(hidden) => (moves.saturating_sub(hidden), None),
+            BroadcastDelay::Millis(ms) => {
+                let moment = now.saturating_sub(ms);
+                let times = move_times(self.clock.as_ref()?, moves);
+                let visible = times.iter().take_while(|&&time| time <= moment).count();
+                (visible, Some(moment))
+            }
+        };
+
+        let state = if visible == moves {
+            self.state.clone()
+        } else {
+            let start = match &self.transcript {
+                Some(transcript) if transcript.start.history.len() <= visible => {
+                    transcript.start.clone()
+                }
+                _ => self.state.unwound_start(),
+            };
+            self.state
+                .history
+                .get(start.history.len()..visible)?
+                .iter()
+                .try_fold(start, |state, mv| replay_move(&state, mv))?
+        };
+        let clock = self.clock.as_ref().map(|clock| {
+            if finished {
+                BroadcastClock {
+                    white_ms: clock.white_ms,
+                    black_ms: clock.black_ms,
+                    running: None,
+                }
+            } else {
+                clock_after(clock, visible, state.turn, moment)
+            }
+        });
+
+        Some(BroadcastView {
+            state,
+            hidden_moves: moves - visible,
+            clock,
+        })
+    }
+}
+
+// ============================================================================
+// Simultaneous Exhibitions
+// ============================================================================
+
+/// Moves an exhibitor bot plans its shared budget for on each ongoing board.
+const EXHIBITION_MOVES_AHEAD: u64 = 20;
+
+/// One board of a simultaneous exhibition.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExhibitionBoard {
+    pub session: GameSession,
+    /// Side the exhibitor plays on this board
+    pub exhibitor: Color,
+}
+
+impl ExhibitionBoard {
+    /// Whether the game is ongoing with the exhibitor to move.
+    pub fn awaits_exhibitor(&self) -> bool {
+        self.session.state.status == GameStatus::Ongoing
+            && self.session.state.turn == self.exhibitor
+    }
+}
+
+/// The exhibitor's results so far.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExhibitionScore {
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+    pub ongoing: usize,
+}
+
+/// One exhibitor (a player or an engine) against several opponents at once.
+/// The exhibitor's thinking time is a single budget, running whenever at
+/// least one board awaits the exhibitor's move; when it runs out, every
+/// unfinished board is lost on time. Opponents are untimed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Exhibition {
+    pub boards: Vec<ExhibitionBoard>,
+    /// Exhibitor's thinking time left (milliseconds), None if untimed
+    pub budget_ms: Option<u64>,
+    /// Server time since when the budget has been running
+    pub running_since: Option<u64>,
+    /// Board the exhibitor last moved on
+    pub last_board: Option<usize>,
+}
+
+impl Exhibition {
+    /// Create an exhibition without boards, with a shared thinking-time
+    /// budget for the exhibitor (None for untimed).
+    pub fn new(budget_ms: Option<u64>) -> Self {
+        Self {
+            budget_ms,
+            ..Self::default()
+        }
+    }
+
+    /// Add a board at server time `now` with the exhibitor playing
+    /// `exhibitor`. Returns the board's index.
+    pub fn add_board(&mut self, state: GameState, exhibitor: Color, now: u64) -> usize {
+        self.charge(now);
+        self.boards.push(ExhibitionBoard {
+            session: GameSession::new(state),
+            exhibitor,
+        });
+        self.charge(now);
+        self.boards.len() - 1
+    }
+
+    /// Exhibitor's thinking time left at `now`, None if untimed.
+    pub fn remaining(&self, now: u64) -> Option<u64> {
+        let budget = self.budget_ms?;
+        let elapsed = self
+            .running_since
+            .map_or(0, |since| now.saturating_sub(since));
+        Some(budget.saturating_sub(elapsed))
+    }
+
+    /// Boards awaiting the exhibitor's move, in board order.
+    pub fn awaiting_exhibitor(&self) -> Vec<usize> {
+        self.boards
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.awaits_exhibitor())
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Board the exhibitor should play next: the first one awaiting a move
+    /// after the board last played, going round the boards in order as in
+    /// an over-the-board exhibition. None if no board awaits the exhibitor.
+    pub fn next_board(&self) -> Option<usize> {
+        let start = self.last_board.map_or(0, |board| board + 1);
+        let count = self.boards.len();
+        (0..count)
+            .map(|offset| (start + offset) % count)
+            .find(|&index| {
+                self.boards
+                    .get(index)
+                    .is_some_and(|entry| entry.awaits_exhibitor())
+            })
+    }
+
+    /// Submit the exhibitor's move on a board at server time `now`. Rejected
+    /// with "timeout" once the budget is spent, and with "notYourTurn" if the
+    /// board awaits the opponent.
+    pub fn submit_exhibitor_move(
+        &mut self,
+        board: usize,
+        seq: usize,
+        mv: &Move,
+        now: u64,
+    ) -> SubmitResult {
+        self.submit(board, seq, mv, now, true)
+    }
+
+    /// Submit an opponent's move on a board at server time `now`.
+    pub fn submit_opponent_move(
+        &mut self,
+        board: usize,
+        seq: usize,
+        mv: &Move,
+        now: u64,
+    ) -> SubmitResult {
+        self.submit(board, seq, mv, now, false)
+    }
+
+    /// Search limits for an exhibitor bot at `now`: `max_depth`, with time
+    /// for [`EXHIBITION_MOVES_AHEAD`] more moves on every ongoing board.
+    pub fn bot_limits(&self, max_depth: i32, now: u64) -> SearchLimits {
+        let ongoing = self
+            .boards
+            .iter()
+            .filter(|board| board.session.state.status == GameStatus::Ongoing)
+            .count() as u64;
+        SearchLimits {
+            time_limit_ms: self
+                .remaining(now)
+                .map(|remaining| remaining / (ongoing.max(1) * EXHIBITION_MOVES_AHEAD)),
+            ..SearchLimits::depth(max_depth)
+        }
+    }
+
+    /// Let a bot exhibitor move on the next board (see [`next_board`]),
+    /// with its move submitted at `now`. Returns the board and the result,
+    /// or None if no board awaits the exhibitor.
+    ///
+    /// [`next_board`]: Exhibition::next_board
+    pub fn play_bot_move(
+        &mut self,
+        bot: &mut dyn Bot,
+        limits: &SearchLimits,
+        now: u64,
+    ) -> Option<(usize, SubmitResult)> {
+        let board = self.next_board()?;
+        let session = &self.boards.get(board)?.session;
+        let seq = session.seq();
+        let result = match bot.choose_move(&session.state, limits) {
+            Some(mv) => self.submit_exhibitor_move(board, seq, &mv, now),
+            None => self.reject(board, "noMove"),
+        };
+        Some((board, result))
+    }
+
+    /// End every unfinished board on time if the budget has run out at
+    /// `now`. Returns true if the exhibitor has lost on time.
+    pub fn check_timeout(&mut self, now: u64) -> bool {
+        if self.remaining(now) != Some(0) {
+            return false;
+        }
+        self.charge(now);
+        for board in &mut self.boards {
+            if board.session.state.status == GameStatus::Ongoing {
+                board.session.state.status = GameStatus::Timeout {
+                    winner: board.exhibitor.opposite(),
+                };
+            }
+        }
+        self.running_since = None;
+        true
+    }
+
+    /// The exhibitor's wins, draws and losses so far.
+    pub fn score(&self) -> ExhibitionScore {
+        let mut score = ExhibitionScore::default();
+        for board in &self.boards {
+            let winner = match &board.session.state.status {
+                GameStatus::Ongoing => {
+                    score.ongoing += 1;
+                    continue;
+                }
+                GameStatus::Checkmate { winner }
+                | GameStatus::Resigned { winner }
+                | GameStatus::Timeout { winner }
+                | GameStatus::VariantWin { winner, .. } => *winner,
+                GameStatus::Stalemate | GameStatus::Draw { .. } => {
+                    score.draws += 1;
+                    continue;
+                }
+            };
+            if winner == board.exhibitor {
+                score.wins += 1;
+            } else {
+                score.losses += 1;
+            }
+        }
+        score
+    }
+
+    fn submit(
+        &mut self,
+        board: usize,
+        seq: usize,
+        mv: &Move,
+        now: u64,
+        by_exhibitor: bool,
+    ) -> SubmitResult {
+        let Some(entry) = self.boards.get(board) else {
+            return self.reject(board, "unknownBoard");
+        };
+        if (entry.session.state.turn == entry.exhibitor) != by_exhibitor {
+            return self.reject(board, "notYourTurn");
+        }
+        if self.check_timeout(now) {
+            return self.reject(board, "timeout");
+        }
+        self.charge(now);
+        let Some(entry) = self.boards.get_mut(board) else {
+            return self.reject(board, "unknownBoard");
+        };
+        let result = entry.session.submit_move(seq, mv);
+        if result.accepted && by_exhibitor {
+            self.last_board = Some(board);
+        }
+        self.charge(now);
+        result
+    }
+
+    fn reject(&self, board: usize, reason: &str) -> SubmitResult {
+        SubmitResult {
+            accepted: false,
+            seq: self
+                .boards
+                .get(board)
+                .map_or(0, |entry| entry.session.seq()),
+            reason: Some(reason.to_string()),
+        }
+    }
+
+    /// Deduct the time the budget has been running up to `now`, and keep it
+    /// running only while a board awaits the exhibitor.
+    fn charge(&mut self, now: u64) {
+        self.budget_ms = self.remaining(now);
+        self.running_since =
+            (self.budget_ms.is_some() && !self.awaiting_exhibitor().is_empty()).then_some(now);
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{create_new_game, create_variant_game, make_move, resign};
+    use crate::types::{HexCoord, Variant};
+
+    fn pawn_move(game: &GameState, fq: i32, fr: i32, tq: i32, tr: i32) -> Move {
+        let after = make_move(game, HexCoord::new(fq, fr), HexCoord::new(tq, tr)).unwrap();
+        after.history.last().unwrap().clone()
+    }
+
+    #[test]
+    fn test_incremental_sync() {
+        let mut session = GameSession::new(create_new_game());
+        let client = session.state.clone();
+
+        let first = pawn_move(&session.state, 0, 2, 0, 1);
+        assert!(session.submit_move(0, &first).accepted);
+        let second = pawn_move(&session.state, 0, -2, 0, -1);
+        assert!(session.submit_move(1, &second).accepted);
+        // A retried submission for an old sequence number is not applied twice
+        let retry = session.submit_move(1, &second);
+        assert_eq!(retry.reason.as_deref(), Some("staleSequence"));
+        assert_eq!(retry.seq, 2);
+
+        let response = session.sync(&create_sync_request(&client));
+        match &response {
+            SyncResponse::Moves { moves, .. } => assert_eq!(moves.len(), 2),
+            other => panic!("expected moves, got {:?}", other),
+        }
+        let synced = apply_sync_response(&client, &response).unwrap();
+        assert_eq!(synced.board, session.state.board);
+        assert!(matches!(
+            session.sync(&create_sync_request(&synced)),
+            SyncResponse::UpToDate { .. }
+        ));
+
+        // A client with every move still learns of a resignation
+        session.state = resign(&session.state, Color::White);
+        let response = session.sync(&create_sync_request(&synced));
+        assert!(matches!(response, SyncResponse::UpToDate { .. }));
+        let resigned = apply_sync_response(&synced, &response).unwrap();
+        assert_eq!(resigned.status, session.state.status);
+        assert_ne!(resigned.status, GameStatus::Ongoing);
+    }
+
+    #[test]
+    fn test_full_state_fallback() {
+        let mut session = GameSession::new(create_new_game());
+        let mv = pawn_move(&session.state, 0, 2, 0, 1);
+        session.submit_move(0, &mv);
+
+        // A client that diverged (different move) gets the full state
+        let diverged =
+            make_move(&create_new_game(), HexCoord::new(1, 2), HexCoord::new(1, 1)).unwrap();
+        let response = session.sync(&create_sync_request(&diverged));
+        assert!(matches!(response, SyncResponse::Full { .. }));
+        let synced = apply_sync_response(&diverged, &response).unwrap();
+        assert_eq!(synced.board, session.state.board);
+
+        // Tampered hashes are rejected
+        let tampered = SyncResponse::UpToDate {
+            status: GameStatus::Ongoing,
+            position_hash: "0".repeat(16),
+        };
+        assert!(apply_sync_response(&synced, &tampered).is_none());
+        assert!(matches!(
+            session.sync(&full_sync_request()),
+            SyncResponse::Full { .. }
+        ));
+    }
+
+    #[test]
+    fn test_sync_status_and_boulder_moves() {
+        let mut session = GameSession::new(create_variant_game(Variant::Boulder));
+        let client = session.state.clone();
+        let moved = make_move(&session.state, HexCoord::new(0, 2), HexCoord::new(0, 1)).unwrap();
+        let placed = make_boulder_placement(&moved, HexCoord::new(0, 0)).unwrap();
+        let mv = placed.history[0].clone();
+        assert!(session.submit_move(0, &mv).accepted);
+        session.state = resign(&session.state, Color::Black);
+
+        let response = session.sync(&create_sync_request(&client));
+        let synced = apply_sync_response(&client, &response).unwrap();
+        assert_eq!(synced.board, placed.board);
+        assert_eq!(synced.status, session.state.status);
+    }
+
+    #[test]
+    fn test_bot_session() {
+        let mut session = GameSession::new(create_new_game());
+        let mut bot = crate::bots::RandomBot::new(7);
+        for seq in 1..=4 {
+            let result = session.play_bot_move(&mut bot, &SearchLimits::default());
+            assert!(result.accepted);
+            assert_eq!(result.seq, seq);
+        }
+    }
+
+    #[test]
+    fn test_timed_session() {
+        let settings = ClockSettings::new(10_000, 1_000);
+        let mut session = GameSession::with_clock(create_new_game(), settings, 0);
+        let first = pawn_move(&session.state, 0, 2, 0, 1);
+
+        // Illegal and stale moves do not touch the clock
+        let mut illegal = first.clone();
+        illegal.to = HexCoord::new(0, -1);
+        assert!(!session.submit_timed_move(0, &illegal, 100, None).accepted);
+        assert!(session.clock.as_ref().unwrap().records.is_empty());
+
+        let result = session.submit_timed_move(0, &first, 3_000, Some(2_800));
+        assert!(result.accepted);
+        let clock = session.clock.as_ref().unwrap();
+        assert_eq!(clock.white_ms, 10_000 - 2_800 + 1_000);
+        assert_eq!(clock.running, Some(Color::Black));
+
+        // Black takes too long, even allowing for lag
+        let reply = pawn_move(&session.state, 0, -2, 0, -1);
+        let late = session.submit_timed_move(1, &reply, 14_000, Some(11_000));
+        assert_eq!(late.reason.as_deref(), Some("timeout"));
+        assert_eq!(
+            session.state.status,
+            GameStatus::Timeout {
+                winner: Color::White
+            }
+        );
+        assert!(session.check_timeout(20_000));
+    }
+
+    #[test]
+    fn test_timed_move_saved_by_lag() {
+        let settings = ClockSettings::new(10_000, 0);
+        let mut session = GameSession::with_clock(create_new_game(), settings, 0);
+        let first = pawn_move(&session.state, 0, 2, 0, 1);
+
+        // Past the deadline at the server, but 400ms of it was lag
+        let result = session.submit_timed_move(0, &first, 10_300, Some(9_900));
+        assert!(result.accepted);
+        assert_eq!(session.state.status, GameStatus::Ongoing);
+        assert_eq!(session.clock.as_ref().unwrap().white_ms, 100);
+    }
+
+    #[test]
+    fn test_check_timeout_without_move() {
+        let mut session =
+            GameSession::with_clock(create_new_game(), ClockSettings::new(5_000, 0), 0);
+        assert!(!session.check_timeout(4_999));
+        assert!(session.check_timeout(5_000));
+        assert_eq!(session.clock.as_ref().unwrap().white_ms, 0);
+        assert!(!GameSession::new(create_new_game()).check_timeout(u64::MAX));
+    }
+
+    #[test]
+    fn test_armageddon_session() {
+        let settings = ClockSettings::armageddon(6_000, 5_000, 0);
+        let mut session = GameSession::with_clock(create_new_game(), settings, 0);
+        assert_eq!(session.clock.as_ref().unwrap().black_ms, 5_000);
+        assert_eq!(session.draw_winner(), Some(Color::Black));
+        assert_eq!(session.result(), None);
+
+        let drawn = GameStatus::Draw {
+            reason: "fiftyMoveRule".to_string(),
+        };
+        session.state.status = drawn.clone();
+        let result = session.result().unwrap();
+        assert_eq!(result.winner, Some(Color::Black));
+        assert_eq!(result.score, "0-1");
+
+        let mut plain = GameSession::new(create_new_game());
+        plain.state.status = drawn;
+        assert_eq!(plain.draw_winner(), None);
+        assert_eq!(plain.result().unwrap().winner, None);
+    }
+
+    #[test]
+    fn test_move_proofs_and_transcript() {
+        let mut session = GameSession::new(create_new_game());
+        let client = session.state.clone();
+
+        let first = pawn_move(&client, 0, 2, 0, 1);
+        let proof = create_move_proof(&client, &first).unwrap();
+        assert_eq!(proof.seq, 0);
+        assert!(session.submit_move_proof(&proof).accepted);
+        assert_eq!(proof.position_hash, state_hash(&session.state));
+
+        // A proof committing to a different position is refused
+        let reply = pawn_move(&session.state, 0, -2, 0, -1);
+        let mut forged = create_move_proof(&session.state, &reply).unwrap();
+        forged.position_hash = "0".repeat(16);
+        let refused = session.submit_move_proof(&forged);
+        assert_eq!(refused.reason.as_deref(), Some("positionMismatch"));
+        assert_eq!(refused.seq, 1);
+        assert!(session.submit_move(1, &reply).accepted);
+
+        let transcript = session.transcript.clone().unwrap();
+        assert_eq!(transcript.entries.len(), 2);
+        assert_ne!(transcript.head(), state_hash(&transcript.start));
+        assert_eq!(transcript.verify().unwrap().board, session.state.board);
+
+        // Altering any recorded move breaks verification
+        let mut tampered = transcript.clone();
+        tampered.entries[0].proof.mv = pawn_move(&client, 1, 2, 1, 1);
+        let error = tampered.verify().unwrap_err();
+        assert_eq!(
+            (error.index, error.reason.as_str()),
+            (0, "positionMismatch")
+        );
+
+        let mut relinked = transcript.clone();
+        relinked.entries[1].chain_hash = transcript.entries[0].chain_hash.clone();
+        assert_eq!(relinked.verify().unwrap_err().reason, "chainMismatch");
+
+        let json = serde_json::to_string(&session).unwrap();
+        let restored: GameSession = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            restored.transcript.map(|transcript| transcript.head()),
+            Some(transcript.head())
+        );
+    }
+
+    #[test]
+    fn test_broadcast_delay() {
+        let settings = ClockSettings::new(60_000, 0);
+        let mut session = GameSession::with_clock(create_new_game(), settings, 0);
+        for (seq, (fq, fr, tq, tr), now) in [
+            (0, (0, 2, 0, 1), 3_000),
+            (1, (0, -2, 0, -1), 5_000),
+            (2, (1, 2, 1, 1), 9_000),
+        ] {
+            let mv = pawn_move(&session.state, fq, fr, tq, tr);
+            assert!(session.submit_timed_move(seq, &mv, now, None).accepted);
+        }
+
+        let view = session
+            .broadcast_view(BroadcastDelay::Moves(1), 10_000)
+            .unwrap();
+        assert_eq!(view.hidden_moves, 1);
+        assert_eq!(view.state.history.len(), 2);
+        assert_eq!(view.state.turn, Color::White);
+        assert_eq!(
+            view.clock,
+            Some(BroadcastClock {
+                white_ms: 57_000,
+                black_ms: 58_000,
+                running: Some(Color::White),
+            })
+        );
+
+        // Five seconds behind at 12s shows the position at 7s
+        let view = session
+            .broadcast_view(BroadcastDelay::Millis(5_000), 12_000)
+            .unwrap();
+        assert_eq!(view.hidden_moves, 1);
+        assert_eq!(view.clock.unwrap().white_ms, 55_000);
+        let view = session
+            .broadcast_view(BroadcastDelay::Millis(5_000), 14_000)
+            .unwrap();
+        assert_eq!(view.hidden_moves, 0);
+        assert_eq!(view.clock.unwrap().black_ms, 58_000);
+        assert!(GameSession::new(create_new_game())
+            .broadcast_view(BroadcastDelay::Millis(5_000), 0)
+            .is_none());
+
+        // A finished game is shown in full
+        session.state = resign(&session.state, Color::Black);
+        let view = session
+            .broadcast_view(BroadcastDelay::Moves(2), 20_000)
+            .unwrap();
+        assert_eq!(view.hidden_moves, 0);
+        assert_eq!(view.clock.unwrap().running, None);
+    }
+
+    #[test]
+    fn test_exhibition_shared_budget() {
+        let mut exhibition = Exhibition::new(Some(10_000));
+        for _ in 0..2 {
+            exhibition.add_board(create_new_game(), Color::White, 0);
+        }
+        assert_eq!(exhibition.awaiting_exhibitor(), vec![0, 1]);
+        assert_eq!(exhibition.next_board(), Some(0));
+
+        let opening = pawn_move(&create_new_game(), 0, 2, 0, 1);
+        assert!(
+            exhibition
+                .submit_exhibitor_move(0, 0, &opening, 1_000)
+                .accepted
+        );
+        assert_eq!(exhibition.remaining(1_000), Some(9_000));
+        assert_eq!(exhibition.next_board(), Some(1));
+        let early = exhibition.submit_opponent_move(1, 0, &opening, 1_500);
+        assert_eq!(early.reason.as_deref(), Some("notYourTurn"));
+        assert!(
+            exhibition
+                .submit_exhibitor_move(1, 0, &opening, 3_000)
+                .accepted
+        );
+
+        // Nothing awaits the exhibitor, so the budget stands still
+        assert_eq!(exhibition.next_board(), None);
+        assert_eq!(exhibition.remaining(50_000), Some(7_000));
+        let reply = pawn_move(&exhibition.boards[0].session.state, 0, -2, 0, -1);
+        assert!(
+            exhibition
+                .submit_opponent_move(0, 1, &reply, 5_000)
+                .accepted
+        );
+        assert_eq!(exhibition.remaining(6_000), Some(6_000));
+        assert_eq!(exhibition.bot_limits(4, 5_000).time_limit_ms, Some(175));
+
+        assert!(!exhibition.check_timeout(11_999));
+        assert!(exhibition.check_timeout(12_000));
+        assert_eq!(
+            exhibition.score(),
+            ExhibitionScore {
+                losses: 2,
+                ..ExhibitionScore::default()
+            }
+        );
+        let late = exhibition.submit_opponent_move(1, 1, &reply, 12_500);
+        assert_eq!(late.reason.as_deref(), Some("timeout"));
+    }
+
+    #[test]
+    fn test_exhibition_bot() {
+        let mut exhibition = Exhibition::new(None);
+        for exhibitor in [Color::White, Color::Black, Color::White] {
+            exhibition.add_board(create_new_game(), exhibitor, 0);
+        }
+        let mut exhibitor = crate::bots::RandomBot::new(3);
+        let mut opponent = crate::bots::RandomBot::new(5);
+        let limits = exhibition.bot_limits(1, 0);
+        assert_eq!(limits.time_limit_ms, None);
+
+        let (board, result) = exhibition
+            .play_bot_move(&mut exhibitor, &limits, 0)
+            .unwrap();
+        assert_eq!((board, result.accepted), (0, true));
+        let (board, _) = exhibition
+            .play_bot_move(&mut exhibitor, &limits, 0)
+            .unwrap();
+        assert_eq!(board, 2);
+        assert!(exhibition
+            .play_bot_move(&mut exhibitor, &limits, 0)
+            .is_none());
+
+        // Board 1's opponent opens, then the exhibitor comes round again
+        let board = &exhibition.boards[1].session;
+        let mv = opponent.choose_move(&board.state, &limits).unwrap();
+        assert!(exhibition.submit_opponent_move(1, 0, &mv, 0).accepted);
+        assert_eq!(exhibition.next_board(), Some(1));
+        assert_eq!(exhibition.score().ongoing, 3);
+    }
+}
@@ -0,0 +1,614 @@
+//! Underchex Share Codes
+//!
+//! Short URL-safe codes for sharing a position in a link: the board, the
+//! side to move and optionally the last move, without the game history or
+//! clocks that HexFEN carries. A code is the unpadded URL-safe base64 of:
+//! - 1 byte: format version ([`SHARE_CODE_VERSION`])
+//! - 1 byte: flags, bit 0 set if Black is to move, bit 1 if a last move follows
+//! - 2 bytes if there is a last move: the indexes of its cells in
+//!   [`get_all_cells`] order
+//! - the packed board (see [`PackedBoard`])
+//!
+//! The starting position takes 42 characters. Decoding dispatches on the
+//! version, so codes of older versions stay readable.
+//!
+//! Whole games are transferred (e.g. by QR code from an over-the-board game)
+//! in a compact binary record of their rules and moves, each move the index
+//! of the move among the legal moves of its position (see
+//! [`encode_game_compact`]).
+
+use serde::{Deserialize, Serialize};
+
+use crate::board::get_all_cells;
+use crate::game::{
+    create_variant_game, get_boulder_placements, get_legal_moves, make_boulder_placement,
+};
+use crate::packed::{piece_code, piece_from_code, PackedBoard};
+use crate::session::replay_move;
+use crate::types::{
+    BoardState, Color, GameState, HexCoord, Move, Piece, StalemateRule, TurnPhase, Variant,
+    WinConditions,
+};
+
+/// Version of the codes written.
+pub const SHARE_CODE_VERSION: u8 = 1;
+
+const BLACK_TO_MOVE: u8 = 1;
+const HAS_LAST_MOVE: u8 = 2;
+
+/// The base64url alphabet.
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// A position shared by code.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedPosition {
+    pub board: BoardState,
+    pub turn: Color,
+    /// Cells the last move went from and to, for highlighting
+    pub last_move: Option<(HexCoord, HexCoord)>,
+}
+
+impl SharedPosition {
+    /// A game's position and last move (drops and boulder placements have no
+    /// from cell, so they are left out).
+    pub fn from_game(state: &GameState) -> Self {
+        Self {
+            board: state.board.clone(),
+            turn: state.turn,
+            last_move: state
+                .history
+                .last()
+                .filter(|mv| !mv.is_drop)
+                .map(|mv| (mv.from, mv.to)),
+        }
+    }
+}
+
+/// Encode a position as a share code. Returns None if the board cannot be
+/// packed or the last move's cells are off the board.
+pub fn encode_share_code(position: &SharedPosition) -> Option<String> {
+    let mut flags = 0;
+    if position.turn == Color::Black {
+        flags |= BLACK_TO_MOVE;
+    }
+    let mut last_move = Vec::new();
+    if let Some((from, to)) = position.last_move {
+        flags |= HAS_LAST_MOVE;
+        last_move = vec![cell_index(from)?, cell_index(to)?];
+    }
+    let mut bytes = vec![SHARE_CODE_VERSION, flags];
+    bytes.extend(last_move);
+    bytes.extend(position.board.to_packed_bytes()?);
+    Some(base64_encode(&bytes))
+}
+
+/// Decode a share code of any version. Returns None for a malformed code or
+/// an unknown version.
+pub fn decode_share_code(code: &str) -> Option<SharedPosition> {
+    let bytes = base64_decode(code)?;
+    match bytes.split_first()? {
+        (1, rest) => decode_v1(rest),
+        _ => None,
+    }
+}
+
+fn decode_v1(bytes: &[u8]) -> Option<SharedPosition> {
+    let (&flags, mut rest) = bytes.split_first()?;
+    if flags & !(BLACK_TO_MOVE | HAS_LAST_MOVE) != 0 {
+        return None;
+    }
+    let last_move = if flags & HAS_LAST_MOVE != 0 {
+        let cells = get_all_cells();
+        let (&[from, to], tail) = rest.split_first_chunk()?;
+        rest = tail;
+        Some((*cells.get(from as usize)?, *cells.get(to as usize)?))
+    } else {
+        None
+    };
+    Some(SharedPosition {
+        board: BoardState::from_packed_bytes(rest)?,
+        turn: if flags & BLACK_TO_MOVE != 0 {
+            Color::Black
+        } else {
+            Color::White
+        },
+        last_move,
+    })
+}
+
+fn cell_index(cell: HexCoord) -> Option<u8> {
+    get_all_cells()
+        .iter()
+        .position(|&c| c == cell)
+        .map(|index| index as u8)
+}
+
+// ============================================================================
+// Compact Game Records
+// ============================================================================
+
+/// Version of the compact game records written.
+pub const COMPACT_GAME_VERSION: u8 = 3;
+
+/// Variants in code order.
+pub(crate) const VARIANTS: [Variant; 9] = [
+    Variant::Standard,
+    Variant::Crazyhouse { drop_mate: true },
+    Variant::Crazyhouse { drop_mate: false },
+    Variant::KingOfTheHill,
+    Variant::RacingKings,
+    Variant::Boulder,
+    Variant::Cannons,
+    Variant::Shogi { drops: true },
+    Variant::Shogi { drops: false },
+];
+
+/// Bits needed for an index among `count` choices.
+fn index_bits(count: usize) -> usize {
+    (usize::BITS - count.saturating_sub(1).leading_zeros()) as usize
+}
+
+/// A position's legal moves in a canonical order (move generation follows
+/// the board's hash map, whose order varies between runs).
+pub(crate) fn ordered_moves(state: &GameState) -> Vec<Move> {
+    let mut moves = get_legal_moves(state);
+    moves.sort_by_key(|mv| {
+        let promotion = mv
+            .promotion
+            .and_then(|piece_type| piece_code(&Piece::new(piece_type, Color::White)));
+        (
+            mv.is_drop,
+            (mv.from.q, mv.from.r),
+            (mv.to.q, mv.to.r),
+            piece_code(&mv.piece),
+            promotion,
+        )
+    });
+    moves
+}
+
+/// The boulder placements pending in a position, in a canonical order.
+fn ordered_placements(state: &GameState) -> Vec<HexCoord> {
+    let mut cells = get_boulder_placements(state);
+    cells.sort_by_key(|cell| (cell.q, cell.r));
+    cells
+}
+
+/// Whether a legal move is the recorded one, boulder aside.
+fn same_move(legal: &Move, recorded: &Move) -> bool {
+    legal.from == recorded.from
+        && legal.to == recorded.to
+        && legal.is_drop == recorded.is_drop
+        && legal.promotion == recorded.promotion
+        && (!legal.is_drop || legal.piece == recorded.piece)
+}
+
+/// Byte of no piece type to capture all of, in [`rules_bytes`].
+const NO_CAPTURE_ALL: u8 = 0xFF;
+
+/// A game's rules in three bytes: the stalemating side's quarter points
+/// under its stalemate rule, 1 if baring the king wins (else 0), and the code
+/// of the piece type to capture all of, or 0xFF. None if the type has no code.
+pub(crate) fn rules_bytes(state: &GameState) -> Option<[u8; 3]> {
+    let capture_all = match state.win_conditions.capture_all {
+        Some(piece_type) => piece_code(&Piece::new(piece_type, Color::White))?,
+        None => NO_CAPTURE_ALL,
+    };
+    Some([
+        state.stalemate_rule.stalemater_quarters(),
+        u8::from(state.win_conditions.bare_king),
+        capture_all,
+    ])
+}
+
+/// The rules of [`rules_bytes`]. None for an invalid byte or unplayable win
+/// conditions.
+pub(crate) fn rules_from_bytes(
+    [quarters, bare_king, capture_all]: [u8; 3],
+) -> Option<(StalemateRule, WinConditions)> {
+    let capture_all = match capture_all {
+        NO_CAPTURE_ALL => None,
+        code => Some(piece_from_code(code)?.piece_type),
+    };
+    let conditions = WinConditions {
+        bare_king: bare_king != 0,
+        capture_all,
+    };
+    let rule = StalemateRule::from_quarters(quarters)?;
+    conditions.is_valid().then_some((rule, conditions))
+}
+
+/// Encode a game's moves compactly, for QR codes: a version byte, the
+/// variant, the game's rules (see [`rules_bytes`]), the number of moves
+/// (LEB128), then for every move its index
+/// among the position's legal moves (and, in the boulder variant, the
+/// boulder placement's index among the free cells) in just enough bits,
+/// least significant bit first. A 40-move game takes about 60 bytes.
+///
+/// Only the moves are kept: the game must start from its variant's starting
+/// position, and endings other than by a move (resignation, timeout, agreed
+/// draws) are lost. Returns None for a game from another position or whose
+/// history does not replay.
+pub fn encode_game_compact(state: &GameState) -> Option<Vec<u8>> {
+    let variant = VARIANTS.iter().position(|&v| v == state.variant)? as u8;
+    let mut bytes = vec![COMPACT_GAME_VERSION, variant];
+    bytes.extend(rules_bytes(state)?);
+    let mut count = state.history.len();
+    loop {
+        let low = (count & 0x7F) as u8;
+        count >>= 7;
+        if count == 0 {
+            bytes.push(low);
+            break;
+        }
+        bytes.push(low | 0x80);
+    }
+
+    let start = GameState {
+        stalemate_rule: state.stalemate_rule,
+        win_conditions: state.win_conditions,
+        ..create_variant_game(state.variant)
+    };
+    let (bits, game) = encode_move_indices(&start, &state.history)?;
+    if game.board != state.board || game.turn != state.turn {
+        return None;
+    }
+    bytes.extend(bits);
+    Some(bytes)
+}
+
+/// Decode a game encoded by [`encode_game_compact`], replaying its moves.
+/// Version 1 records, which had no rules, are played by the default ones, and
+/// version 2 records, which had only the stalemate rule, by the default win
+/// conditions.
+/// Returns None for malformed data or an unknown version.
+pub fn decode_game_compact(bytes: &[u8]) -> Option<GameState> {
+    let (&version, rest) = bytes.split_first()?;
+    let (&variant, mut rest) = rest.split_first()?;
+    let (stalemate_rule, win_conditions) = match version {
+        1 => Default::default(),
+        2 => {
+            let (&quarters, tail) = rest.split_first()?;
+            rest = tail;
+            (StalemateRule::from_quarters(quarters)?, Default::default())
+        }
+        3 => {
+            let (&rules, tail) = rest.split_first_chunk()?;
+            rest = tail;
+            rules_from_bytes(rules)?
+        }
+        _ => return None,
+    };
+    let mut count = 0usize;
+    for shift in (0..).step_by(7) {
+        let (&byte, tail) = rest.split_first()?;
+        rest = tail;
+        count |= usize::from(byte & 0x7F).checked_shl(shift)?;
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+
+    let start = GameState {
+        stalemate_rule,
+        win_conditions,
+        ..create_variant_game(*VARIANTS.get(variant as usize)?)
+    };
+    decode_move_indices(start, count, rest)
+}
+
+/// The bits of [`encode_game_compact`] for a history played from `start`,
+/// with the position it reaches. None if the history does not replay.
+pub(crate) fn encode_move_indices(
+    start: &GameState,
+    history: &[Move],
+) -> Option<(Vec<u8>, GameState)> {
+    let mut bits = BitWriter::default();
+    let mut game = start.clone();
+    for recorded in history {
+        let moves = ordered_moves(&game);
+        let index = moves.iter().position(|mv| same_move(mv, recorded))?;
+        bits.write(index as u64, index_bits(moves.len()));
+        game = replay_move(&game, moves.get(index)?)?;
+        if game.phase == TurnPhase::PlaceBoulder {
+            let cells = ordered_placements(&game);
+            let index = cells
+                .iter()
+                .position(|&cell| Some(cell) == recorded.boulder)?;
+            bits.write(index as u64, index_bits(cells.len()));
+            game = make_boulder_placement(&game, *cells.get(index)?)?;
+        }
+    }
+    Some((bits.bytes, game))
+}
+
+/// Replay `count` moves encoded by [`encode_move_indices`] from `start`.
+/// Returns None for malformed data.
+pub(crate) fn decode_move_indices(
+    start: GameState,
+    count: usize,
+    bytes: &[u8],
+) -> Option<GameState> {
+    let mut bits = BitReader::new(bytes);
+    let mut game = start;
+    for _ in 0..count {
+        let moves = ordered_moves(&game);
+        let index = bits.read(index_bits(moves.len()))? as usize;
+        game = replay_move(&game, moves.get(index)?)?;
+        if game.phase == TurnPhase::PlaceBoulder {
+            let cells = ordered_placements(&game);
+            let index = bits.read(index_bits(cells.len()))? as usize;
+            game = make_boulder_placement(&game, *cells.get(index)?)?;
+        }
+    }
+    // Only the padding of the last byte may remain
+    (bits.remaining() < 8).then_some(game)
+}
+
+/// Writes values of any width, least significant bit first.
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    bits: usize,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u64, width: usize) {
+        for bit in 0..width {
+            if self.bits.is_multiple_of(8) {
+                self.bytes.push(0);
+            }
+            if value & (1 << bit) != 0 {
+                if let Some(last) = self.bytes.last_mut() {
+                    *last |= 1 << (self.bits % 8);
+                }
+            }
+            self.bits += 1;
+        }
+    }
+}
+
+/// Reads what [`BitWriter`] wrote.
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    /// The next `width` bits, or None past the end.
+    fn read(&mut self, width: usize) -> Option<u64> {
+        let mut value = 0;
+        for bit in 0..width {
+            let byte = self.bytes.get(self.position / 8)?;
+            if byte & (1 << (self.position % 8)) != 0 {
+                value |= 1 << bit;
+            }
+            self.position += 1;
+        }
+        Some(value)
+    }
+
+    fn remaining(&self) -> usize {
+        self.bytes.len() * 8 - self.position
+    }
+}
+
+// ============================================================================
+// Base64
+// ============================================================================
+
+/// Unpadded base64url encoding.
+pub(crate) fn base64_encode(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity((bytes.len() * 4).div_ceil(3));
+    for chunk in bytes.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, &byte)| {
+            bits | (u32::from(byte) << (16 - 8 * i))
+        });
+        for i in 0..=chunk.len() {
+            text.extend(
+                ALPHABET
+                    .get(((bits >> (18 - 6 * i)) & 63) as usize)
+                    .map(|&symbol| symbol as char),
+            );
+        }
+    }
+    text
+}
+
+/// Decode unpadded base64url. Returns None for other characters or a
+/// length no encoding has.
+pub(crate) fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    if text.len() % 4 == 1 {
+        return None;
+    }
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    for chunk in text.as_bytes().chunks(4) {
+        let mut bits = 0u32;
+        for (i, &c) in chunk.iter().enumerate() {
+            let value = ALPHABET.iter().position(|&a| a == c)? as u32;
+            bits |= value << (18 - 6 * i);
+        }
+        for i in 0..chunk.len() - 1 {
+            bytes.push((bits >> (16 - 8 * i)) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{create_new_game, make_move};
+    use crate::rng::SeededRng;
+    use crate::types::PieceType;
+
+    #[test]
+    fn test_base64_roundtrip() {
+        assert_eq!(base64_encode(b"Man"), "TWFu");
+        assert_eq!(base64_encode(b"Ma"), "TWE");
+        assert_eq!(base64_encode(&[0xFB, 0xFF]), "-_8");
+        for len in 0..8 {
+            let bytes: Vec<u8> = (0..len).map(|i| i * 31 + 17).collect();
+            assert_eq!(base64_decode(&base64_encode(&bytes)), Some(bytes));
+        }
+        assert_eq!(base64_decode("TWFuT"), None);
+        assert_eq!(base64_decode("TW+u"), None);
+    }
+
+    #[test]
+    fn test_share_code_roundtrip() {
+        let start = SharedPosition::from_game(&create_new_game());
+        let code = encode_share_code(&start).unwrap();
+        assert_eq!(code.len(), 42);
+        assert!(code
+            .bytes()
+            .all(|c| c.is_ascii_alphanumeric() || c == b'-' || c == b'_'));
+        assert_eq!(decode_share_code(&code), Some(start));
+
+        let state =
+            make_move(&create_new_game(), HexCoord::new(0, 2), HexCoord::new(0, 1)).unwrap();
+        let position = SharedPosition::from_game(&state);
+        assert_eq!(
+            position.last_move,
+            Some((HexCoord::new(0, 2), HexCoord::new(0, 1)))
+        );
+        let decoded = decode_share_code(&encode_share_code(&position).unwrap()).unwrap();
+        assert_eq!(decoded.turn, Color::Black);
+        assert_eq!(decoded, position);
+    }
+
+    #[test]
+    fn test_share_code_versions() {
+        // A version 1 code of the starting position stays readable
+        let code = "AQAAAB0_WrT4cQEAAEDaB8ah8QECHwjzAVGsfOCMBw";
+        assert_eq!(
+            decode_share_code(code),
+            Some(SharedPosition::from_game(&create_new_game()))
+        );
+
+        let mut bytes = base64_decode(code).unwrap();
+        bytes[0] = 2;
+        assert_eq!(decode_share_code(&base64_encode(&bytes)), None);
+        bytes[0] = 1;
+        bytes[1] = 4;
+        assert_eq!(decode_share_code(&base64_encode(&bytes)), None);
+        assert_eq!(decode_share_code(""), None);
+        assert_eq!(decode_share_code("not a code"), None);
+    }
+
+    #[test]
+    fn test_index_bits() {
+        assert_eq!(index_bits(0), 0);
+        assert_eq!(index_bits(1), 0);
+        assert_eq!(index_bits(2), 1);
+        assert_eq!(index_bits(3), 2);
+        assert_eq!(index_bits(64), 6);
+        assert_eq!(index_bits(65), 7);
+    }
+
+    #[test]
+    fn test_compact_game_roundtrip() {
+        let empty = encode_game_compact(&create_new_game()).unwrap();
+        assert_eq!(empty, vec![COMPACT_GAME_VERSION, 0, 2, 0, 0xFF, 0]);
+        assert_eq!(
+            decode_game_compact(&empty).map(|game| game.history.len()),
+            Some(0)
+        );
+
+        // A random game of 60 plies
+        let mut rng = SeededRng::new(11);
+        let mut game = create_new_game();
+        for _ in 0..60 {
+            let moves = ordered_moves(&game);
+            let Some(mv) = rng.choose(&moves) else { break };
+            game = replay_move(&game, mv).unwrap();
+        }
+        let bytes = encode_game_compact(&game).unwrap();
+        assert!(bytes.len() <= 4 + game.history.len());
+        let decoded = decode_game_compact(&bytes).unwrap();
+        assert_eq!(decoded.history, game.history);
+        assert_eq!(decoded.board, game.board);
+        assert_eq!(decoded.status, game.status);
+
+        // Truncated or padded data is rejected
+        assert!(decode_game_compact(&bytes[..bytes.len() - 1]).is_none());
+        assert!(decode_game_compact(&[bytes.clone(), vec![0]].concat()).is_none());
+        assert!(decode_game_compact(&[4, 0, 2, 0, 0xFF, 0]).is_none());
+
+        // Version 1 records have no rules
+        let v1 = [&[1, 0][..], &bytes[5..]].concat();
+        assert_eq!(decode_game_compact(&v1).unwrap().history, game.history);
+        // And version 2 records only the stalemate rule
+        let v2 = [&[2, 0, 2][..], &bytes[5..]].concat();
+        assert_eq!(decode_game_compact(&v2).unwrap().history, game.history);
+    }
+
+    #[test]
+    fn test_compact_game_keeps_stalemate_rule() {
+        for rule in [
+            StalemateRule::StalematerWins,
+            StalemateRule::Fractional {
+                stalemater_quarters: 3,
+            },
+        ] {
+            let game = GameState {
+                stalemate_rule: rule,
+                ..create_new_game()
+            };
+            let game = make_move(&game, HexCoord::new(0, 2), HexCoord::new(0, 1)).unwrap();
+            let decoded = decode_game_compact(&encode_game_compact(&game).unwrap()).unwrap();
+            assert_eq!(decoded.stalemate_rule, rule);
+            assert_eq!(decoded.history, game.history);
+        }
+        assert!(decode_game_compact(&[COMPACT_GAME_VERSION, 0, 5, 0, 0xFF, 0]).is_none());
+    }
+
+    #[test]
+    fn test_compact_game_keeps_win_conditions() {
+        let win_conditions = WinConditions {
+            bare_king: true,
+            capture_all: Some(PieceType::Chariot),
+        };
+        let game = GameState {
+            win_conditions,
+            ..create_new_game()
+        };
+        let game = make_move(&game, HexCoord::new(0, 2), HexCoord::new(0, 1)).unwrap();
+        let decoded = decode_game_compact(&encode_game_compact(&game).unwrap()).unwrap();
+        assert_eq!(decoded.win_conditions, win_conditions);
+        assert_eq!(decoded.history, game.history);
+        assert!(decode_game_compact(&[COMPACT_GAME_VERSION, 0, 2, 0, 0x1F, 0]).is_none());
+        assert!(decode_game_compact(&[COMPACT_GAME_VERSION, 0, 2, 0, 1, 0]).is_none());
+    }
+
+    #[test]
+    fn test_compact_boulder_game() {
+        let mut game = create_variant_game(Variant::Boulder);
+        for _ in 0..4 {
+            let mv = ordered_moves(&game)[0].clone();
+            game = replay_move(&game, &mv).unwrap();
+            let cell = *ordered_placements(&game).last().unwrap();
+            game = make_boulder_placement(&game, cell).unwrap();
+        }
+        let decoded = decode_game_compact(&encode_game_compact(&game).unwrap()).unwrap();
+        assert_eq!(decoded.variant, Variant::Boulder);
+        assert_eq!(decoded.history, game.history);
+        assert_eq!(decoded.board, game.board);
+
+        // A game from another position cannot be encoded
+        let mut board = create_new_game().board;
+        board.remove("0,-2");
+        let custom = crate::game::create_game_from_position(board, Color::White);
+        let custom = make_move(&custom, HexCoord::new(0, 2), HexCoord::new(0, 1)).unwrap();
+        assert!(encode_game_compact(&custom).is_none());
+    }
+}
@@ -0,0 +1,183 @@
+//! Underchex AI Simulation
+//!
+//! Plays AI-vs-AI games headlessly, for attract-mode demo boards and quick
+//! smoke tests of engine changes. Games that would drag on are adjudicated:
+//! a side both engines' searches find clearly lost resigns, a claimable draw
+//! is claimed, and the game is drawn at the move limit.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ai::{get_ai_move_with_options, AIOptions, TranspositionTable};
+use crate::game::{claim_draw, create_new_game};
+use crate::session::replay_move;
+use crate::types::{Color, GameState, GameStatus, Move};
+
+/// Search score (from White's side, in centipawns) beyond which a position
+/// counts as decided.
+pub const ADJUDICATION_SCORE: i32 = 1500;
+
+/// Consecutive plies the score must stay decided before the losing side
+/// resigns, so that both engines have agreed.
+pub const ADJUDICATION_PLIES: usize = 4;
+
+/// Entries of each side's transposition table.
+const SIMULATION_TT_SIZE: usize = 50_000;
+
+/// A simulated game.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SimulatedGame {
+    /// Moves played from the starting position
+    pub moves: Vec<Move>,
+    pub status: GameStatus,
+    /// Why the game was decided by adjudication rather than played out:
+    /// "decisiveScore", "fiftyMoveRule" or "moveLimit"
+    pub adjudication: Option<String>,
+    /// Search score after each move, from White's side
+    pub scores: Vec<i32>,
+}
+
+/// Play a game from the standard starting position between two AI
+/// configurations, for at most `max_plies` moves.
+pub fn simulate_game(white: &AIOptions, black: &AIOptions, max_plies: usize) -> SimulatedGame {
+    simulate_game_from(&create_new_game(), white, black, max_plies)
+}
+
+/// Play a game on from `start` (see [`simulate_game`]).
+pub fn simulate_game_from(
+    start: &GameState,
+    white: &AIOptions,
+    black: &AIOptions,
+    max_plies: usize,
+) -> SimulatedGame {
+    let mut state = start.clone();
+    let mut tables = [
+        TranspositionTable::new(SIMULATION_TT_SIZE),
+        TranspositionTable::new(SIMULATION_TT_SIZE),
+    ];
+    for tt in &mut tables {
+        tt.set_game_rules(start);
+    }
+    let mut scores = Vec::new();
+    let mut adjudication = None;
+    let mut decided_plies = 0;
+
+    while state.status == GameStatus::Ongoing {
+        if let Some(claimed) = claim_draw(&state) {
+            state = claimed;
+            adjudication = Some("fiftyMoveRule");
+            break;
+        }
+        if scores.len() >= max_plies {
+            state.status = GameStatus::Draw {
+                reason: "moveLimit".to_string(),
+            };
+            adjudication = Some("moveLimit");
+            break;
+        }
+
+        let (options, tt) = match state.turn {
+            Color::White => (white, &mut tables[0]),
+            Color::Black => (black, &mut tables[1]),
+        };
+        let result = get_ai_move_with_options(
+            &state.board,
+            state.turn,
+            options,
+            tt,
+            &state.position_history,
+            state.variant,
+        );
+        let Some(next) = result.best_move.and_then(|mv| replay_move(&state, &mv)) else {
+            // No playable move in an ongoing game: the side forfeits
+            state.status = GameStatus::Resigned {
+                winner: state.turn.opposite(),
+            };
+            break;
+        };
+        state = next;
+        scores.push(result.score);
+
+        // The side that keeps finding itself lost resigns
+        let previous = scores.iter().rev().nth(1).copied();
+        let decided = result.score.abs() >= ADJUDICATION_SCORE
+            && previous.is_none_or(|previous| previous.signum() == result.score.signum());
+        decided_plies = if decided { decided_plies + 1 } else { 0 };
+        if decided_plies >= ADJUDICATION_PLIES && state.status == GameStatus::Ongoing {
+            let winner = if result.score > 0 {
+                Color::White
+            } else {
+                Color::Black
+            };
+            state.status = GameStatus::Resigned { winner };
+            adjudication = Some("decisiveScore");
+        }
+    }
+
+    SimulatedGame {
+        moves: state
+            .history
+            .get(start.history.len()..)
+            .unwrap_or_default()
+            .to_vec(),
+        status: state.status,
+        adjudication: adjudication.map(str::to_string),
+        scores,
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::AIDifficulty;
+    use crate::game::create_game_from_position;
+    use crate::types::{BoardState, Piece, PieceType};
+
+    #[test]
+    fn test_simulation_is_repeatable() {
+        let easy = AIOptions::new(AIDifficulty::Easy);
+        let game = simulate_game(&easy, &easy, 6);
+        assert_eq!(game.moves.len(), 6);
+        assert_eq!(game.scores.len(), 6);
+        assert_eq!(game.adjudication.as_deref(), Some("moveLimit"));
+        assert_eq!(
+            game.status,
+            GameStatus::Draw {
+                reason: "moveLimit".to_string()
+            }
+        );
+        assert_eq!(simulate_game(&easy, &easy, 6), game);
+    }
+
+    #[test]
+    fn test_simulation_adjudicates_decided_games() {
+        // Two queens against a bare king
+        let mut board = BoardState::new();
+        board.insert("0,4".to_string(), Piece::new(PieceType::King, Color::White));
+        board.insert(
+            "1,2".to_string(),
+            Piece::new(PieceType::Queen, Color::White),
+        );
+        board.insert(
+            "-1,2".to_string(),
+            Piece::new(PieceType::Queen, Color::White),
+        );
+        board.insert(
+            "0,-4".to_string(),
+            Piece::new(PieceType::King, Color::Black),
+        );
+        let start = create_game_from_position(board, Color::White);
+
+        let easy = AIOptions::new(AIDifficulty::Easy);
+        let game = simulate_game_from(&start, &easy, &easy, 40);
+        let winner = match game.status {
+            GameStatus::Checkmate { winner } | GameStatus::Resigned { winner } => winner,
+            status => panic!("not decided: {:?}", status),
+        };
+        assert_eq!(winner, Color::White);
+        assert!(game.moves.len() < 40);
+    }
+}
@@ -0,0 +1,485 @@
+//! Underchex Corpus Statistics
+//!
+//! Per-hex heatmaps over a corpus of games: where pieces stand, where
+//! captures happen, where checks are delivered from, and where each piece
+//! type moves to.
+//!
+//! Per-game time usage from clock records: time per move and per phase, and
+//! moves that lost ground while their player was in time trouble.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ai::{evaluate_quiet, get_piece_value};
+use crate::board::is_valid_cell;
+use crate::clock::GameClock;
+use crate::game::reconstruct_positions;
+use crate::moves::{apply_move, is_in_check};
+use crate::types::{
+    BoardState, Color, GameState, HexCoord, Move, PieceType, Variant, BOARD_RADIUS,
+};
+
+// ============================================================================
+// Heatmaps
+// ============================================================================
+
+/// Per-hex counts laid out as a grid for visualization.
+///
+/// `cells[r + 4][q + 4]` holds the count for cell (q, r); cells outside the
+/// hexagon are `None` (serialized as `null`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Heatmap {
+    pub cells: Vec<Vec<Option<u32>>>,
+}
+
+impl Default for Heatmap {
+    fn default() -> Self {
+        let cells = (-BOARD_RADIUS..=BOARD_RADIUS)
+            .map(|r| {
+                (-BOARD_RADIUS..=BOARD_RADIUS)
+                    .map(|q| is_valid_cell(HexCoord::new(q, r)).then_some(0))
+                    .collect()
+            })
+            .collect();
+        Self { cells }
+    }
+}
+
+impl Heatmap {
+    fn slot(&mut self, coord: HexCoord) -> Option<&mut u32> {
+        let row = usize::try_from(coord.r + BOARD_RADIUS).ok()?;
+        let col = usize::try_from(coord.q + BOARD_RADIUS).ok()?;
+        self.cells.get_mut(row)?.get_mut(col)?.as_mut()
+    }
+
+    /// Increment the count for a cell (cells off the board are ignored).
+    pub fn increment(&mut self, coord: HexCoord) {
+        if let Some(count) = self.slot(coord) {
+            *count += 1;
+        }
+    }
+
+    /// Count for a cell (0 for cells off the board).
+    pub fn get(&self, coord: HexCoord) -> u32 {
+        let row = (coord.r + BOARD_RADIUS) as usize;
+        let col = (coord.q + BOARD_RADIUS) as usize;
+        self.cells
+            .get(row)
+            .and_then(|r| r.get(col))
+            .copied()
+            .flatten()
+            .unwrap_or(0)
+    }
+
+    /// Sum of all counts.
+    pub fn total(&self) -> u32 {
+        self.cells.iter().flatten().flatten().sum()
+    }
+}
+
+/// Movement heatmap for one piece type.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PieceMovementHeatmap {
+    pub piece_type: PieceType,
+    /// Destination cells of this piece type's moves
+    pub heatmap: Heatmap,
+}
+
+/// All heatmaps computed over a corpus.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeatmapSet {
+    pub games: u32,
+    pub positions: u32,
+    /// Cells occupied by white pieces, summed over every position
+    pub white_occupation: Heatmap,
+    /// Cells occupied by black pieces, summed over every position
+    pub black_occupation: Heatmap,
+    /// Cells where a capture took place
+    pub captures: Heatmap,
+    /// Cells from which a check was delivered (destination of the checking move)
+    pub checks: Heatmap,
+    /// Destination cells per piece type
+    pub movement: Vec<PieceMovementHeatmap>,
+}
+
+impl HeatmapSet {
+    fn movement_for(&mut self, piece_type: PieceType) -> Option<&mut Heatmap> {
+        let index = match self
+            .movement
+            .iter()
+            .position(|m| m.piece_type == piece_type)
+        {
+            Some(index) => index,
+            None => {
+                self.movement.push(PieceMovementHeatmap {
+                    piece_type,
+                    heatmap: Heatmap::default(),
+                });
+                self.movement.len() - 1
+            }
+        };
+        self.movement.get_mut(index).map(|entry| &mut entry.heatmap)
+    }
+
+    /// Serialize to JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|_| "{}".to_string())
+    }
+}
+
+/// Compute heatmaps over a corpus of games.
+pub fn square_heatmaps(games: &[GameState]) -> HeatmapSet {
+    let mut set = HeatmapSet::default();
+
+    for game in games {
+        set.games += 1;
+        let positions = reconstruct_positions(game);
+
+        for board in &positions {
+            set.positions += 1;
+            for (key, piece) in board {
+                if let Some(coord) = HexCoord::from_key(key) {
+                    match piece.color {
+                        Color::White => set.white_occupation.increment(coord),
+                        Color::Black => set.black_occupation.increment(coord),
+                    }
+                }
+            }
+        }
+
+        for (mv, board) in game.history.iter().zip(&positions) {
+            if mv.captured.is_some() {
+                set.captures.increment(mv.to);
+            }
+            if is_in_check(&apply_move(board, mv), mv.piece.color.opposite()) {
+                set.checks.increment(mv.to);
+            }
+            if let Some(heatmap) = set.movement_for(mv.piece.piece_type) {
+                heatmap.increment(mv.to);
+            }
+        }
+    }
+
+    set
+}
+
+// ============================================================================
+// Time Usage
+// ============================================================================
+
+/// Plies counted as the opening.
+pub const OPENING_PLIES: usize = 20;
+
+/// Non-pawn material on the board (both sides, centipawns) at or below which
+/// a position counts as an endgame.
+pub const ENDGAME_MATERIAL: i32 = 2600;
+
+/// Time left below which a side is in time trouble.
+pub const DEFAULT_TIME_TROUBLE_MS: u64 = 10_000;
+
+/// Evaluation drop (centipawns, mover's perspective) that counts as a blunder.
+pub const DEFAULT_BLUNDER_DROP: i32 = 200;
+
+/// Stage of the game a move was played in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GamePhase {
+    Opening,
+    Middlegame,
+    Endgame,
+}
+
+impl GamePhase {
+    /// Lowercase name: "opening", "middlegame" or "endgame".
+    pub fn key(self) -> &'static str {
+        match self {
+            GamePhase::Opening => "opening",
+            GamePhase::Middlegame => "middlegame",
+            GamePhase::Endgame => "endgame",
+        }
+    }
+}
+
+/// Phase of the position before the `ply`-th move (0-based).
+pub fn game_phase(board: &BoardState, ply: usize) -> GamePhase {
+    if ply < OPENING_PLIES {
+        return GamePhase::Opening;
+    }
+    let material: i32 = board
+        .values()
+        .filter(|piece| piece.piece_type != PieceType::Pawn)
+        .map(|piece| get_piece_value(piece.piece_type))
+        .sum();
+    if material <= ENDGAME_MATERIAL {
+        GamePhase::Endgame
+    } else {
+        GamePhase::Middlegame
+    }
+}
+
+/// A move with the time its player spent on it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimedMove {
+    pub mv: Move,
+    /// Time charged to the mover's clock
+    pub spent_ms: u64,
+    /// Time the mover had left when the move was made (before any increment)
+    pub remaining_ms: u64,
+}
+
+/// A game's moves with their clock times, from its starting position.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimedHistory {
+    pub start: BoardState,
+    pub moves: Vec<TimedMove>,
+}
+
+impl TimedHistory {
+    /// Pair a game's moves with the clock's records. The history stops at
+    /// the first move without a record.
+    pub fn from_clock(game: &GameState, clock: &GameClock) -> Self {
+        let start = reconstruct_positions(game)
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| game.board.clone());
+        let moves = game
+            .history
+            .iter()
+            .enumerate()
+            .map_while(|(index, mv)| {
+                let record = clock.records.iter().find(|r| r.move_index == index)?;
+                Some(TimedMove {
+                    mv: mv.clone(),
+                    spent_ms: record.charged_ms,
+                    remaining_ms: record
+                        .remaining_ms
+                        .saturating_sub(clock.settings.increment_ms),
+                })
+            })
+            .collect();
+        Self { start, moves }
+    }
+}
+
+/// Moves and time spent in one phase.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhaseTime {
+    pub moves: u32,
+    pub total_ms: u64,
+}
+
+impl PhaseTime {
+    fn add(&mut self, spent_ms: u64) {
+        self.moves += 1;
+        self.total_ms += spent_ms;
+    }
+}
+
+/// Time usage of one side.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SideTimeUsage {
+    pub moves: u32,
+    pub total_ms: u64,
+    pub average_ms: u64,
+    pub longest_ms: u64,
+    pub opening: PhaseTime,
+    pub middlegame: PhaseTime,
+    pub endgame: PhaseTime,
+}
+
+impl SideTimeUsage {
+    fn add(&mut self, phase: GamePhase, spent_ms: u64) {
+        self.moves += 1;
+        self.total_ms += spent_ms;
+        self.average_ms = self.total_ms / u64::from(self.moves);
+        self.longest_ms = self.longest_ms.max(spent_ms);
+        match phase {
+            GamePhase::Opening => self.opening.add(spent_ms),
+            GamePhase::Middlegame => self.middlegame.add(spent_ms),
+            GamePhase::Endgame => self.endgame.add(spent_ms),
+        }
+    }
+}
+
+/// A move that lost ground while its player was short of time.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeTroubleBlunder {
+    /// Index of the move in the history
+    pub move_index: usize,
+    pub color: Color,
+    pub remaining_ms: u64,
+    /// Evaluation lost by the move, in centipawns from the mover's side
+    pub eval_drop: i32,
+}
+
+/// Time usage over a game, for the post-game report.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeReport {
+    pub white: SideTimeUsage,
+    pub black: SideTimeUsage,
+    pub time_trouble_blunders: Vec<TimeTroubleBlunder>,
+}
+
+impl TimeReport {
+    pub fn side(&self, color: Color) -> &SideTimeUsage {
+        match color {
+            Color::White => &self.white,
+            Color::Black => &self.black,
+        }
+    }
+
+    /// Serialize to JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|_| "{}".to_string())
+    }
+}
+
+/// Time usage with the default time-trouble and blunder thresholds.
+pub fn time_usage(history: &TimedHistory) -> TimeReport {
+    time_usage_with(history, DEFAULT_TIME_TROUBLE_MS, DEFAULT_BLUNDER_DROP)
+}
+
+/// Time usage per side and phase. Moves made with less than
+/// `time_trouble_ms` left are evaluated before and after (with a quiescence
+/// search), and flagged if they lose at least `blunder_drop` centipawns.
+pub fn time_usage_with(
+    history: &TimedHistory,
+    time_trouble_ms: u64,
+    blunder_drop: i32,
+) -> TimeReport {
+    let mut report = TimeReport::default();
+    let mut board = history.start.clone();
+
+    for (index, timed) in history.moves.iter().enumerate() {
+        let color = timed.mv.piece.color;
+        let phase = game_phase(&board, index);
+        let next = apply_move(&board, &timed.mv);
+        match color {
+            Color::White => report.white.add(phase, timed.spent_ms),
+            Color::Black => report.black.add(phase, timed.spent_ms),
+        }
+
+        if timed.remaining_ms < time_trouble_ms {
+            let sign = if color == Color::White { 1 } else { -1 };
+            let before = sign * evaluate_quiet(&board, color, Variant::Standard);
+            let after = sign * evaluate_quiet(&next, color.opposite(), Variant::Standard);
+            if before - after >= blunder_drop {
+                report.time_trouble_blunders.push(TimeTroubleBlunder {
+                    move_index: index,
+                    color,
+                    remaining_ms: timed.remaining_ms,
+                    eval_drop: before - after,
+                });
+            }
+        }
+        board = next;
+    }
+
+    report
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ClockSettings;
+    use crate::game::{create_new_game, make_move};
+    use crate::types::Piece;
+
+    #[test]
+    fn test_heatmap_grid_layout() {
+        let mut heatmap = Heatmap::default();
+        assert_eq!(heatmap.cells.len(), 9);
+        assert_eq!(heatmap.cells.iter().flatten().flatten().count(), 61);
+        assert_eq!(heatmap.cells[0][0], None); // (-4, -4) is off the board
+
+        heatmap.increment(HexCoord::new(1, -2));
+        heatmap.increment(HexCoord::new(5, 5));
+        assert_eq!(heatmap.get(HexCoord::new(1, -2)), 1);
+        assert_eq!(heatmap.total(), 1);
+    }
+
+    #[test]
+    fn test_square_heatmaps() {
+        let mut game = create_new_game();
+        for [fq, fr, tq, tr] in [[0, 2, 0, 1], [0, -2, 0, -1], [0, 1, 0, 0], [0, -1, 0, 0]] {
+            game = make_move(&game, HexCoord::new(fq, fr), HexCoord::new(tq, tr)).unwrap();
+        }
+
+        let set = square_heatmaps(&[game.clone(), game]);
+        assert_eq!(set.games, 2);
+        assert_eq!(set.positions, 10);
+        assert_eq!(set.captures.get(HexCoord::new(0, 0)), 2);
+        assert_eq!(set.captures.total(), 2);
+        assert!(set.white_occupation.get(HexCoord::new(0, 1)) > 0);
+
+        let pawn_moves = &set.movement[0];
+        assert_eq!(pawn_moves.piece_type, PieceType::Pawn);
+        assert_eq!(pawn_moves.heatmap.total(), 8);
+
+        let json: serde_json::Value = serde_json::from_str(&set.to_json()).unwrap();
+        assert!(json["captures"]["cells"][0][0].is_null());
+    }
+
+    fn queen_blunder(remaining_ms: u64) -> TimedHistory {
+        let mut start = BoardState::new();
+        let mut put = |q, r, piece| start.insert(HexCoord::new(q, r).to_key(), piece);
+        put(-3, 3, Piece::new(PieceType::King, Color::White));
+        put(0, 3, Piece::new(PieceType::Queen, Color::White));
+        put(3, -3, Piece::new(PieceType::King, Color::Black));
+        put(0, 0, Piece::new(PieceType::Pawn, Color::Black));
+        let queen = Piece::new(PieceType::Queen, Color::White);
+        TimedHistory {
+            start,
+            moves: vec![TimedMove {
+                mv: Move::new(queen, HexCoord::new(0, 3), HexCoord::new(0, 1)),
+                spent_ms: 4_000,
+                remaining_ms,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_time_trouble_blunder() {
+        let report = time_usage(&queen_blunder(5_000));
+        assert_eq!(report.white.moves, 1);
+        assert_eq!(report.white.opening.total_ms, 4_000);
+        let blunder = &report.time_trouble_blunders[0];
+        assert_eq!((blunder.move_index, blunder.color), (0, Color::White));
+        assert!(blunder.eval_drop > 800);
+
+        assert!(time_usage(&queen_blunder(60_000))
+            .time_trouble_blunders
+            .is_empty());
+    }
+
+    #[test]
+    fn test_time_usage_from_clock() {
+        let mut game = create_new_game();
+        let mut clock = GameClock::new(ClockSettings::new(60_000, 1_000));
+        clock.start(Color::White, 0);
+        let mut now = 0;
+        let moves = [[0, 2, 0, 1], [0, -2, 0, -1], [1, 2, 1, 1], [1, -2, 1, -1]];
+        for (index, (&[fq, fr, tq, tr], spent)) in
+            moves.iter().zip([1_000, 3_000, 5_000, 7_000]).enumerate()
+        {
+            game = make_move(&game, HexCoord::new(fq, fr), HexCoord::new(tq, tr)).unwrap();
+            now += spent;
+            clock.press(now, None, index).unwrap();
+        }
+
+        let history = TimedHistory::from_clock(&game, &clock);
+        assert_eq!(history.start, create_new_game().board);
+        assert_eq!(history.moves[1].remaining_ms, 57_000);
+
+        let report = time_usage(&history);
+        assert_eq!(
+            (report.white.total_ms, report.white.average_ms),
+            (6_000, 3_000)
+        );
+        assert_eq!(report.black.longest_ms, 7_000);
+        assert_eq!(report.side(Color::Black).opening.moves, 2);
+        assert!(report.time_trouble_blunders.is_empty());
+    }
+}
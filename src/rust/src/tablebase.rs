@@ -3,7 +3,7 @@
 //! Provides perfect endgame play for positions with few pieces:
 //! - Precomputed Win/Draw/Loss (WDL) tables
 //! - Distance to Mate (DTM) information
-//! - Retrograde analysis for tablebase generation
+//! - Retrograde analysis for tablebase generation, whole or in small steps
 //! - Integration with AI search for endgame positions
 //!
 //! Supported endgames (initial implementation):
@@ -12,16 +12,25 @@
 //! - KLvK (King+Lance vs King) - Usually win, some draws
 //! - KCvK (King+Chariot vs King) - Usually win, some draws
 //! - KNvK (King+Knight vs King) - Draw (insufficient material on hex board)
+//! - KPvK (King+Pawn vs King) - Promotions lead into the tablebases above
 //!
 //! Signed-by: agent #35 claude-sonnet-4 via opencode 20260122T09:21:50
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Arc;
 
-use crate::ai::{TranspositionTable, CHECKMATE_VALUE};
-use crate::board::get_all_cells;
+use crate::ai::{CancelToken, CHECKMATE_VALUE};
+use crate::board::{cell_class, get_all_cells, hex_distance, occupied_cells, CellRegion};
+use crate::memory::tablebase_budget;
+use crate::messages::{color_key, piece_type_key, Message};
 use crate::moves::{apply_move, generate_all_legal_moves, is_in_check};
-use crate::types::{BoardState, Color, HexCoord, LanceVariant, Move, Piece, PieceType};
+use crate::pieces::get_piece_definition;
+use crate::trace::{trace_event, trace_span};
+use crate::types::{
+    is_promotion_zone, BoardState, Color, Direction, HexCoord, LanceVariant, Move, Piece,
+    PieceType, StalemateRule, PROMOTION_TARGETS,
+};
 
 // ============================================================================
 // Tablebase Types
@@ -75,9 +84,10 @@ pub struct PieceTablebase {
     pub name: String,
     /// Piece configuration description
     pub description: String,
-    /// Map from position hash to entry
-    pub entries: HashMap<String, TablebaseEntry>,
-    /// Number of entries
+    /// Packed entries by canonical position index (see [`TablebaseLayout`]);
+    /// 0 for illegal or non-canonical index slots
+    pub entries: Vec<u32>,
+    /// Number of positions (legal canonical slots)
     pub size: usize,
     /// Generation metadata
     pub metadata: TablebaseMetadata,
@@ -94,7 +104,7 @@ pub struct TablebaseMetadata {
 }
 
 /// Configuration for which piece configurations to support.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TablebaseConfig {
     /// Piece types for the stronger side (excluding king)
     pub stronger_side: Vec<PieceType>,
@@ -129,10 +139,65 @@ pub fn get_tablebase(name: &str) -> Option<PieceTablebase> {
     TABLEBASES.lock().ok()?.get(name).cloned()
 }
 
-/// Store a tablebase.
+/// Store a tablebase. Under a memory budget, the largest other tablebases
+/// are evicted to make room, and one that cannot fit is not kept.
 pub fn set_tablebase(tablebase: PieceTablebase) {
     if let Ok(mut tablebases) = TABLEBASES.lock() {
-        tablebases.insert(tablebase.name.clone(), tablebase);
+        let name = tablebase.name.clone();
+        tablebases.insert(name.clone(), tablebase);
+        evict_to_budget(&mut tablebases, Some(&name));
+    }
+}
+
+/// Estimated bytes held by a tablebase.
+pub(crate) fn tablebase_bytes(tablebase: &PieceTablebase) -> usize {
+    tablebase.entries.capacity() * std::mem::size_of::<u32>()
+        + tablebase.name.capacity()
+        + tablebase.description.capacity()
+        + tablebase.metadata.generated_at.capacity()
+}
+
+/// Estimated bytes held by the loaded tablebases.
+pub(crate) fn tablebase_memory_usage() -> usize {
+    TABLEBASES
+        .lock()
+        .map(|tablebases| tablebases.values().map(tablebase_bytes).sum())
+        .unwrap_or(0)
+}
+
+/// Evict loaded tablebases, largest first, until they fit the memory
+/// budget's share (see [`crate::memory`]).
+pub(crate) fn enforce_tablebase_budget() {
+    if let Ok(mut tablebases) = TABLEBASES.lock() {
+        evict_to_budget(&mut tablebases, None);
+    }
+}
+
+/// Evict the largest tablebases other than `keep` until the rest fit the
+/// budget; `keep` itself goes first if it could never fit.
+fn evict_to_budget(tablebases: &mut HashMap<String, PieceTablebase>, keep: Option<&str>) {
+    let Some(budget) = tablebase_budget() else {
+        return;
+    };
+    if let Some(name) = keep {
+        if tablebases
+            .get(name)
+            .is_some_and(|tablebase| tablebase_bytes(tablebase) > budget)
+        {
+            tablebases.remove(name);
+        }
+    }
+    let mut total: usize = tablebases.values().map(tablebase_bytes).sum();
+    while total > budget {
+        let largest = tablebases
+            .iter()
+            .filter(|(name, _)| Some(name.as_str()) != keep)
+            .max_by_key(|(_, tablebase)| tablebase_bytes(tablebase))
+            .map(|(name, _)| name.clone());
+        let Some(evicted) = largest.and_then(|name| tablebases.remove(&name)) else {
+            break;
+        };
+        total -= tablebase_bytes(&evicted);
     }
 }
 
@@ -152,20 +217,453 @@ pub fn clear_tablebases() {
 }
 
 // ============================================================================
-// Position Encoding
+// Canonical Position Index
+// ============================================================================
+
+/// Number of cells on the board.
+const CELL_COUNT: usize = 61;
+
+/// Board symmetries: rotations by k * 60 degrees (0..6) and, from 6, the same
+/// rotations after a reflection.
+pub const SYMMETRY_COUNT: u8 = 12;
+
+/// Rotation by 180 degrees, which maps each side's home onto the other's. A
+/// position with the colors swapped and this applied plays identically.
+pub const HALF_TURN: u8 = 3;
+
+fn rotate(cell: HexCoord) -> HexCoord {
+    HexCoord::new(-cell.r, cell.q + cell.r)
+}
+
+fn reflect(cell: HexCoord) -> HexCoord {
+    HexCoord::new(cell.q, -cell.q - cell.r)
+}
+
+/// Apply a board symmetry (0..12) to a cell or offset.
+pub fn apply_symmetry(symmetry: u8, cell: HexCoord) -> HexCoord {
+    let mut cell = if symmetry >= 6 { reflect(cell) } else { cell };
+    for _ in 0..symmetry % 6 {
+        cell = rotate(cell);
+    }
+    cell
+}
+
+/// Undo [`apply_symmetry`].
+pub fn invert_symmetry(symmetry: u8, cell: HexCoord) -> HexCoord {
+    let mut cell = cell;
+    for _ in 0..(6 - symmetry % 6) % 6 {
+        cell = rotate(cell);
+    }
+    if symmetry >= 6 {
+        reflect(cell)
+    } else {
+        cell
+    }
+}
+
+/// Whether a symmetry maps a piece's movement onto itself.
+pub fn symmetry_preserves_piece(symmetry: u8, piece: &Piece) -> bool {
+    if piece.piece_type == PieceType::Pawn {
+        return symmetry == 0;
+    }
+    let preserved = |offsets: Vec<(i32, i32)>| {
+        let mut mapped: Vec<(i32, i32)> = offsets
+            .iter()
+            .map(|&(dq, dr)| {
+                let cell = apply_symmetry(symmetry, HexCoord::new(dq, dr));
+                (cell.q, cell.r)
+            })
+            .collect();
+        let mut offsets = offsets;
+        mapped.sort_unstable();
+        offsets.sort_unstable();
+        mapped == offsets
+    };
+    let deltas = |dirs: &[Direction]| dirs.iter().map(Direction::delta).collect();
+    let definition = get_piece_definition(piece);
+    preserved(deltas(definition.steps))
+        && preserved(deltas(definition.slides))
+        && preserved(deltas(definition.hops))
+        && preserved(definition.leaps.to_vec())
+}
+
+/// A position's place in a tablebase: its index, and how the board was
+/// transformed to reach the canonical form stored there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CanonicalPosition {
+    pub index: usize,
+    /// Symmetry applied after any color swap
+    pub symmetry: u8,
+    /// Whether colors were swapped (and the board turned half a turn) so the
+    /// stronger side is white
+    pub swapped: bool,
+}
+
+/// Dense index layout for one configuration: the canonical king pair, then
+/// each piece's cell (and lance variant), then the side to move. Only
+/// symmetries that preserve every piece's movement are used, and the stronger
+/// side is always white.
+#[derive(Debug)]
+pub struct TablebaseLayout {
+    cells: Vec<HexCoord>,
+    /// Cell index by (q + 4) * 9 + (r + 4), or `u8::MAX` off the board
+    cell_lookup: Vec<u8>,
+    /// Pieces of the stronger side, in configuration order
+    pieces: Vec<PieceType>,
+    symmetries: Vec<u8>,
+    /// Canonical (white king, black king) cell indexes
+    king_pairs: Vec<(u8, u8)>,
+    /// Index into `king_pairs` by white king * 61 + black king, or `u32::MAX`
+    pair_lookup: Vec<u32>,
+}
+
+impl TablebaseLayout {
+    pub fn new(config: &TablebaseConfig) -> Self {
+        let cells = get_all_cells();
+        let mut cell_lookup = vec![u8::MAX; 81];
+        for (index, cell) in cells.iter().enumerate() {
+            if let Some(slot) = cell_lookup.get_mut(((cell.q + 4) * 9 + cell.r + 4) as usize) {
+                *slot = index as u8;
+            }
+        }
+
+        let mut pieces = config.stronger_side.clone();
+        pieces.sort_by_key(|p| piece_abbrev(*p));
+        let mut kinds = vec![Piece::new(PieceType::King, Color::White)];
+        for &piece_type in &pieces {
+            if piece_type == PieceType::Lance {
+                kinds.push(Piece::lance(Color::White, LanceVariant::A));
+                kinds.push(Piece::lance(Color::White, LanceVariant::B));
+            } else {
+                kinds.push(Piece::new(piece_type, Color::White));
+            }
+        }
+        let symmetries: Vec<u8> = (0..SYMMETRY_COUNT)
+            .filter(|&s| kinds.iter().all(|piece| symmetry_preserves_piece(s, piece)))
+            .collect();
+
+        let mut layout = Self {
+            cells: cells.clone(),
+            cell_lookup,
+            pieces,
+            symmetries,
+            king_pairs: Vec::new(),
+            pair_lookup: vec![u32::MAX; CELL_COUNT * CELL_COUNT],
+        };
+        for (white, &w) in cells.iter().enumerate() {
+            for (black, &b) in cells.iter().enumerate() {
+                if hex_distance(w, b) <= 1 {
+                    continue;
+                }
+                let canonical = layout
+                    .symmetries
+                    .iter()
+                    .filter_map(|&s| {
+                        Some((
+                            layout.cell_index(apply_symmetry(s, w))?,
+                            layout.cell_index(apply_symmetry(s, b))?,
+                        ))
+                    })
+                    .min();
+                if canonical == Some((white as u8, black as u8)) {
+                    let pair = layout.king_pairs.len() as u32;
+                    if let Some(slot) = layout.pair_lookup.get_mut(white * CELL_COUNT + black) {
+                        *slot = pair;
+                    }
+                    layout.king_pairs.push((white as u8, black as u8));
+                }
+            }
+        }
+        layout
+    }
+
+    fn cell_index(&self, cell: HexCoord) -> Option<u8> {
+        if cell.q.abs() > 4 || cell.r.abs() > 4 {
+            return None;
+        }
+        let index = *self
+            .cell_lookup
+            .get(((cell.q + 4) * 9 + cell.r + 4) as usize)?;
+        (index != u8::MAX).then_some(index)
+    }
+
+    fn cell(&self, index: u8) -> Option<HexCoord> {
+        self.cells.get(index as usize).copied()
+    }
+
+    /// Number of values a piece's slot takes: its cell, times 2 lance variants.
+    fn slot_size(piece_type: PieceType) -> usize {
+        if piece_type == PieceType::Lance {
+            CELL_COUNT * 2
+        } else {
+            CELL_COUNT
+        }
+    }
+
+    fn piece_space(&self) -> usize {
+        self.pieces.iter().map(|&p| Self::slot_size(p)).product()
+    }
+
+    /// Size of the index space (including illegal and non-canonical slots).
+    pub fn size(&self) -> usize {
+        self.king_pairs.len() * self.piece_space() * 2
+    }
+
+    /// Number of symmetries used to fold the king pairs.
+    pub fn symmetry_count(&self) -> usize {
+        self.symmetries.len()
+    }
+
+    /// Map a board cell into the canonical frame.
+    fn to_canonical(&self, position: &CanonicalPosition, cell: HexCoord) -> HexCoord {
+        let cell = if position.swapped {
+            apply_symmetry(HALF_TURN, cell)
+        } else {
+            cell
+        };
+        apply_symmetry(position.symmetry, cell)
+    }
+
+    /// Map a canonical-frame cell back onto the probed board.
+    pub fn from_canonical(&self, position: &CanonicalPosition, cell: HexCoord) -> HexCoord {
+        let cell = invert_symmetry(position.symmetry, cell);
+        if position.swapped {
+            apply_symmetry(HALF_TURN, cell)
+        } else {
+            cell
+        }
+    }
+
+    /// Locate a position, or None if it does not have this configuration's
+    /// pieces (with all non-king pieces on one side).
+    pub fn index_of(&self, board: &BoardState, side_to_move: Color) -> Option<CanonicalPosition> {
+        let mut kings: (Option<HexCoord>, Option<HexCoord>) = (None, None);
+        let mut others: Vec<(Piece, HexCoord)> = Vec::new();
+        for (key, piece) in board {
+            let cell = HexCoord::from_key(key)?;
+            if piece.piece_type == PieceType::King {
+                let slot = match piece.color {
+                    Color::White => &mut kings.0,
+                    Color::Black => &mut kings.1,
+                };
+                if slot.replace(cell).is_some() {
+                    return None;
+                }
+            } else {
+                others.push((*piece, cell));
+            }
+        }
+
+        let stronger = others
+            .first()
+            .map_or(Color::White, |(piece, _)| piece.color);
+        if others.iter().any(|(piece, _)| piece.color != stronger) {
+            return None;
+        }
+        let mut types: Vec<PieceType> = others.iter().map(|(piece, _)| piece.piece_type).collect();
+        types.sort_by_key(|p| piece_abbrev(*p));
+        if types != self.pieces {
+            return None;
+        }
+
+        let swapped = stronger == Color::Black;
+        let (white_king, black_king) = match swapped {
+            false => (kings.0?, kings.1?),
+            true => (kings.1?, kings.0?),
+        };
+        let side_bit = usize::from((side_to_move == Color::Black) != swapped);
+
+        let mut best: Option<CanonicalPosition> = None;
+        for &symmetry in &self.symmetries {
+            let position = CanonicalPosition {
+                index: 0,
+                symmetry,
+                swapped,
+            };
+            let map = |cell| self.cell_index(self.to_canonical(&position, cell));
+            let (Some(w), Some(b)) = (map(white_king), map(black_king)) else {
+                continue;
+            };
+            let pair = self
+                .pair_lookup
+                .get(w as usize * CELL_COUNT + b as usize)
+                .copied()
+                .unwrap_or(u32::MAX);
+            if pair == u32::MAX {
+                continue;
+            }
+
+            // Identical pieces are interchangeable: order their codes
+            let mut codes: Vec<(PieceType, usize)> = Vec::with_capacity(others.len());
+            for (piece, cell) in &others {
+                // Lances without a variant (e.g. from promotion) move as B
+                let variant = usize::from(piece.variant != Some(LanceVariant::A));
+                let code = match piece.piece_type {
+                    PieceType::Lance => map(*cell)? as usize * 2 + variant,
+                    _ => map(*cell)? as usize,
+                };
+                codes.push((piece.piece_type, code));
+            }
+            codes.sort_by_key(|&(piece_type, code)| (piece_abbrev(piece_type), code));
+
+            let piece_index = codes.iter().fold(0, |index, &(piece_type, code)| {
+                index * Self::slot_size(piece_type) + code
+            });
+            let index = ((pair as usize * self.piece_space() + piece_index) * 2) + side_bit;
+            if best.is_none_or(|b| index < b.index) {
+                best = Some(CanonicalPosition { index, ..position });
+            }
+        }
+        best
+    }
+
+    /// The canonical-frame position at an index, or None if pieces overlap.
+    pub fn decode(&self, index: usize) -> Option<(BoardState, Color)> {
+        let side_to_move = if index.is_multiple_of(2) {
+            Color::White
+        } else {
+            Color::Black
+        };
+        let rest = index / 2;
+        let (white_king, black_king) = *self.king_pairs.get(rest / self.piece_space())?;
+
+        let mut board = BoardState::new();
+        board.insert(
+            self.cell(white_king)?.to_key(),
+            Piece::new(PieceType::King, Color::White),
+        );
+        board.insert(
+            self.cell(black_king)?.to_key(),
+            Piece::new(PieceType::King, Color::Black),
+        );
+
+        let mut piece_index = rest % self.piece_space();
+        for &piece_type in self.pieces.iter().rev() {
+            let code = piece_index % Self::slot_size(piece_type);
+            piece_index /= Self::slot_size(piece_type);
+            let (cell, piece) = match piece_type {
+                PieceType::Lance => {
+                    let variant = if code.is_multiple_of(2) {
+                        LanceVariant::A
+                    } else {
+                        LanceVariant::B
+                    };
+                    (code / 2, Piece::lance(Color::White, variant))
+                }
+                _ => (code, Piece::new(piece_type, Color::White)),
+            };
+            if board
+                .insert(self.cell(cell as u8)?.to_key(), piece)
+                .is_some()
+            {
+                return None;
+            }
+        }
+        Some((board, side_to_move))
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref LAYOUTS: std::sync::Mutex<HashMap<String, Arc<TablebaseLayout>>> =
+        std::sync::Mutex::new(HashMap::new());
+}
+
+/// Get the (cached) index layout of a configuration.
+pub fn get_layout(config: &TablebaseConfig) -> Arc<TablebaseLayout> {
+    let mut layouts = LAYOUTS.lock().unwrap_or_else(|e| e.into_inner());
+    layouts
+        .entry(config.name.clone())
+        .or_insert_with(|| Arc::new(TablebaseLayout::new(config)))
+        .clone()
+}
+
+// ============================================================================
+// Packed Entries
 // ============================================================================
 
-/// Generate a hash key for tablebase lookup.
-/// Uses the same approach as TranspositionTable for consistency.
-pub fn get_tablebase_key(board: &BoardState, side_to_move: Color) -> String {
-    let hash = TranspositionTable::generate_hash(board);
-    let side = match side_to_move {
-        Color::White => "w",
-        Color::Black => "b",
+// Entries are stored as u32: bits 0-1 outcome (0 = no entry), 2-11 DTM,
+// 12 has-move flag, 13-18 and 19-24 move cells, 25-27 promotion.
+const WDL_MASK: u32 = 0b11;
+const DTM_SHIFT: u32 = 2;
+const DTM_MAX: u32 = 0x3FF;
+const MOVE_FLAG: u32 = 1 << 12;
+const FROM_SHIFT: u32 = 13;
+const TO_SHIFT: u32 = 19;
+const PROMOTION_SHIFT: u32 = 25;
+const CELL_MASK: u32 = 0x3F;
+
+/// Pack an entry whose move is in the layout's canonical frame.
+fn pack_entry(layout: &TablebaseLayout, entry: &TablebaseEntry) -> u32 {
+    let wdl = match entry.wdl {
+        WDLOutcome::Win => 1,
+        WDLOutcome::Draw => 2,
+        WDLOutcome::Loss => 3,
+    };
+    let mut packed = wdl | (entry.dtm.clamp(0, DTM_MAX as i32) as u32) << DTM_SHIFT;
+    if let Some(mv) = &entry.best_move {
+        let from = layout.cell_index(HexCoord::new(mv.from_q, mv.from_r));
+        let to = layout.cell_index(HexCoord::new(mv.to_q, mv.to_r));
+        if let (Some(from), Some(to)) = (from, to) {
+            let promotion = mv
+                .promotion
+                .and_then(|p| PROMOTION_TARGETS.iter().position(|&t| t == p))
+                .map_or(0, |i| i as u32 + 1);
+            packed |= MOVE_FLAG
+                | u32::from(from) << FROM_SHIFT
+                | u32::from(to) << TO_SHIFT
+                | promotion << PROMOTION_SHIFT;
+        }
+    }
+    packed
+}
+
+/// Unpack an entry, mapping its move back onto the probed board.
+fn unpack_entry(
+    layout: &TablebaseLayout,
+    position: &CanonicalPosition,
+    packed: u32,
+) -> Option<TablebaseEntry> {
+    let wdl = match packed & WDL_MASK {
+        1 => WDLOutcome::Win,
+        2 => WDLOutcome::Draw,
+        3 => WDLOutcome::Loss,
+        _ => return None,
+    };
+    let dtm = match wdl {
+        WDLOutcome::Draw => -1,
+        _ => ((packed >> DTM_SHIFT) & DTM_MAX) as i32,
     };
-    format!("{}-{}", hash, side)
+    let best_move = if packed & MOVE_FLAG != 0 {
+        let cell = |shift: u32| {
+            let cell = layout.cell(((packed >> shift) & CELL_MASK) as u8)?;
+            Some(layout.from_canonical(position, cell))
+        };
+        let (from, to) = (cell(FROM_SHIFT)?, cell(TO_SHIFT)?);
+        let promotion = match (packed >> PROMOTION_SHIFT) & 0b111 {
+            0 => None,
+            code => PROMOTION_TARGETS.get(code as usize - 1).copied(),
+        };
+        Some(SerializedMove {
+            from_q: from.q,
+            from_r: from.r,
+            to_q: to.q,
+            to_r: to.r,
+            promotion,
+        })
+    } else {
+        None
+    };
+    Some(TablebaseEntry {
+        wdl,
+        dtm,
+        best_move,
+    })
 }
 
+// ============================================================================
+// Configurations
+// ============================================================================
+
 /// Detect the piece configuration of a position.
 /// Returns None if not a supported tablebase configuration.
 pub fn detect_configuration(board: &BoardState) -> Option<TablebaseConfig> {
@@ -205,15 +703,7 @@ pub fn detect_configuration(board: &BoardState) -> Option<TablebaseConfig> {
     stronger_sorted.sort_by_key(|p| piece_abbrev(*p));
     weaker_sorted.sort_by_key(|p| piece_abbrev(*p));
 
-    // Generate configuration name
-    let mut name = "K".to_string();
-    for p in &stronger_sorted {
-        name.push_str(piece_abbrev(*p));
-    }
-    name.push_str("vK");
-    for p in &weaker_sorted {
-        name.push_str(piece_abbrev(*p));
-    }
+    let name = configuration_name(&stronger_sorted, &weaker_sorted);
 
     // Check if this configuration is supported (max 5 pieces for now)
     let total_pieces = 2 + stronger_sorted.len() + weaker_sorted.len(); // 2 kings
@@ -233,6 +723,43 @@ pub fn detect_configuration(board: &BoardState) -> Option<TablebaseConfig> {
     })
 }
 
+/// Name of a configuration, e.g. "KQvK", with each side's pieces sorted.
+fn configuration_name(stronger_side: &[PieceType], weaker_side: &[PieceType]) -> String {
+    let side = |pieces: &[PieceType]| {
+        let mut abbrevs: Vec<&str> = pieces.iter().map(|p| piece_abbrev(*p)).collect();
+        abbrevs.sort_unstable();
+        abbrevs.concat()
+    };
+    format!("K{}vK{}", side(stronger_side), side(weaker_side))
+}
+
+/// Configurations that a capture or a pawn promotion leads into from a
+/// configuration. Their tablebases are needed to generate it exactly.
+pub fn tablebase_dependencies(config: &TablebaseConfig) -> Vec<String> {
+    let mut dependencies: Vec<String> = Vec::new();
+    let mut add = |pieces: Vec<PieceType>| {
+        let name = configuration_name(&pieces, &config.weaker_side);
+        if !dependencies.contains(&name) {
+            dependencies.push(name);
+        }
+    };
+    for (i, &piece_type) in config.stronger_side.iter().enumerate() {
+        let mut captured = config.stronger_side.clone();
+        captured.remove(i);
+        add(captured);
+        if piece_type == PieceType::Pawn {
+            for &promoted in PROMOTION_TARGETS {
+                let mut pieces = config.stronger_side.clone();
+                if let Some(piece) = pieces.get_mut(i) {
+                    *piece = promoted;
+                }
+                add(pieces);
+            }
+        }
+    }
+    dependencies
+}
+
 fn piece_abbrev(piece_type: PieceType) -> &'static str {
     match piece_type {
         PieceType::Queen => "Q",
@@ -241,6 +768,13 @@ fn piece_abbrev(piece_type: PieceType) -> &'static str {
         PieceType::Knight => "N",
         PieceType::Pawn => "P",
         PieceType::King => "K",
+        PieceType::Boulder => "X",
+        PieceType::Wazir => "W",
+        PieceType::Ferz => "F",
+        PieceType::Dabbaba => "D",
+        PieceType::Cannon => "H",
+        PieceType::PromotedKnight => "E",
+        PieceType::PromotedLance => "R",
     }
 }
 
@@ -248,98 +782,30 @@ fn piece_abbrev(piece_type: PieceType) -> &'static str {
 // Retrograde Analysis
 // ============================================================================
 
-/// Generate all positions for a given piece configuration.
+/// Generate all legal positions of a configuration, one per symmetry class,
+/// in index order and in the canonical frame.
 pub fn generate_all_positions(config: &TablebaseConfig) -> Vec<(BoardState, Color)> {
-    let mut positions = Vec::new();
-    let all_cells = get_all_cells();
-
-    // Enumerate all white king positions
-    for white_king_pos in &all_cells {
-        // Enumerate all black king positions (must not be adjacent to white king)
-        for black_king_pos in &all_cells {
-            // Kings cannot be on same cell
-            if white_king_pos == black_king_pos {
-                continue;
-            }
-
-            // Kings cannot be adjacent (would be check)
-            let dq = (white_king_pos.q - black_king_pos.q).abs();
-            let dr = (white_king_pos.r - black_king_pos.r).abs();
-            let ds = ((-white_king_pos.q - white_king_pos.r)
-                - (-black_king_pos.q - black_king_pos.r))
-                .abs();
-            if dq.max(dr).max(ds) <= 1 {
-                continue;
-            }
-
-            // Generate positions with additional pieces
-            let remaining_cells: Vec<HexCoord> = all_cells
-                .iter()
-                .filter(|c| *c != white_king_pos && *c != black_king_pos)
-                .cloned()
-                .collect();
+    let layout = get_layout(config);
+    (0..layout.size())
+        .filter_map(|index| canonical_position(&layout, index))
+        .collect()
+}
 
-            if config.stronger_side.is_empty() {
-                // KvK - just yield the position with both sides to move
-                for side_to_move in [Color::White, Color::Black] {
-                    let mut board = BoardState::new();
-                    board.insert(
-                        white_king_pos.to_key(),
-                        Piece::new(PieceType::King, Color::White),
-                    );
-                    board.insert(
-                        black_king_pos.to_key(),
-                        Piece::new(PieceType::King, Color::Black),
-                    );
-
-                    if !is_illegal_position(&board, side_to_move) {
-                        positions.push((board, side_to_move));
-                    }
-                }
-            } else if config.stronger_side.len() == 1 {
-                // K + 1 piece vs K
-                let piece_type = config.stronger_side[0];
-
-                for piece_pos in &remaining_cells {
-                    for side_to_move in [Color::White, Color::Black] {
-                        // Handle lance variants
-                        let variants: Vec<Option<LanceVariant>> = if piece_type == PieceType::Lance
-                        {
-                            vec![Some(LanceVariant::A), Some(LanceVariant::B)]
-                        } else {
-                            vec![None]
-                        };
-
-                        for variant in &variants {
-                            let mut board = BoardState::new();
-                            board.insert(
-                                white_king_pos.to_key(),
-                                Piece::new(PieceType::King, Color::White),
-                            );
-                            board.insert(
-                                black_king_pos.to_key(),
-                                Piece::new(PieceType::King, Color::Black),
-                            );
-
-                            let piece = if let Some(v) = variant {
-                                Piece::lance(Color::White, *v)
-                            } else {
-                                Piece::new(piece_type, Color::White)
-                            };
-                            board.insert(piece_pos.to_key(), piece);
-
-                            if !is_illegal_position(&board, side_to_move) {
-                                positions.push((board, side_to_move));
-                            }
-                        }
-                    }
-                }
-            }
-            // Can extend for more pieces as needed
-        }
-    }
+/// The legal position at an index slot, if the slot holds a canonical one.
+fn canonical_position(layout: &TablebaseLayout, index: usize) -> Option<(BoardState, Color)> {
+    let (board, side_to_move) = layout.decode(index)?;
+    let canonical = layout.index_of(&board, side_to_move)?;
+    (canonical.index == index
+        && !has_unpromoted_pawn(&board)
+        && !is_illegal_position(&board, side_to_move))
+    .then_some((board, side_to_move))
+}
 
-    positions
+/// Check for a pawn on its promotion row, where it would have promoted.
+fn has_unpromoted_pawn(board: &BoardState) -> bool {
+    occupied_cells(board).any(|(cell, piece)| {
+        piece.piece_type == PieceType::Pawn && is_promotion_zone(cell, piece.color)
+    })
 }
 
 /// Check if a position is illegal (side NOT to move is in check).
@@ -348,181 +814,505 @@ fn is_illegal_position(board: &BoardState, side_to_move: Color) -> bool {
     is_in_check(board, opponent)
 }
 
-/// Determine the outcome of a terminal position.
-fn get_terminal_outcome(board: &BoardState, side_to_move: Color) -> Option<(WDLOutcome, i32)> {
+/// Determine the outcome of a terminal position. A fractional stalemate
+/// rule counts as a draw, the nearest outcome.
+fn get_terminal_outcome(
+    board: &BoardState,
+    side_to_move: Color,
+    stalemate_rule: StalemateRule,
+) -> Option<(WDLOutcome, i32)> {
     let moves = generate_all_legal_moves(board, side_to_move);
 
     if moves.is_empty() {
         if is_in_check(board, side_to_move) {
             // Checkmate - side to move loses
             return Some((WDLOutcome::Loss, 0));
-        } else {
-            // Stalemate - draw
-            return Some((WDLOutcome::Draw, -1));
         }
+        // Stalemate - scored by the rule, for the stalemating side
+        return Some(match stalemate_rule.stalemater_quarters() {
+            4 => (WDLOutcome::Loss, 0),
+            0 => (WDLOutcome::Win, 0),
+            _ => (WDLOutcome::Draw, -1),
+        });
     }
 
     None // Not terminal
 }
 
+/// Name of the tablebase for configuration `name` under a stalemate rule.
+/// Rules that score stalemates as draws share the standard tablebase; the
+/// others get one of their own, so it never answers standard probes.
+pub fn ruled_tablebase_name(name: &str, stalemate_rule: StalemateRule) -> String {
+    match stalemate_rule.stalemater_quarters() {
+        4 => format!("{}-stalematerWins", name),
+        0 => format!("{}-stalematerLoses", name),
+        _ => name.to_string(),
+    }
+}
+
 /// Generate a tablebase for a given configuration using retrograde analysis.
 pub fn generate_tablebase(config: &TablebaseConfig) -> PieceTablebase {
     use std::time::Instant;
     let start_time = Instant::now();
+    trace_span!("generate_tablebase", name = %config.name);
 
-    let mut tablebase = PieceTablebase {
-        name: config.name.clone(),
-        description: format!("Endgame tablebase for {}", config.name),
-        entries: HashMap::new(),
-        size: 0,
-        metadata: TablebaseMetadata {
-            generated_at: chrono::Utc::now().to_rfc3339(),
-            generation_time_ms: 0,
-            win_count: 0,
-            draw_count: 0,
-            loss_count: 0,
-        },
-    };
+    let mut builder = TablebaseBuilder::new(config);
+    builder.step_units(usize::MAX);
+    let mut tablebase = builder.tablebase;
+    tablebase.metadata.generation_time_ms = start_time.elapsed().as_millis() as u64;
+
+    tablebase
+}
 
-    // Phase 1: Initialize all positions and find terminal positions
-    let all_positions = generate_all_positions(config);
-    let mut position_map: HashMap<String, (BoardState, Color)> = HashMap::new();
-    let mut unknown_positions: std::collections::HashSet<String> = std::collections::HashSet::new();
+/// Generate a tablebase like [`generate_tablebase`], stopping early if
+/// `cancel` is cancelled. Returns the builder, which is done unless it was
+/// cancelled and can then be saved or stepped further.
+pub fn generate_tablebase_cancellable(
+    config: &TablebaseConfig,
+    cancel: &CancelToken,
+) -> TablebaseBuilder {
+    let mut builder = TablebaseBuilder::new(config);
+    builder.set_cancel_token(Some(cancel.clone()));
+    builder.step_until(i64::MAX);
+    builder
+}
 
-    for (board, side_to_move) in all_positions {
-        let key = get_tablebase_key(&board, side_to_move);
-        position_map.insert(key.clone(), (board.clone(), side_to_move));
+// ============================================================================
+// Incremental Generation
+// ============================================================================
 
-        // Check if terminal
-        if let Some((wdl, dtm)) = get_terminal_outcome(&board, side_to_move) {
-            tablebase.entries.insert(
-                key,
-                TablebaseEntry {
-                    wdl,
-                    dtm,
-                    best_move: None,
+/// Where a move leads: a position of the tablebase being built, or one in
+/// another configuration (after a capture or promotion) with its outcome and
+/// DTM, if that tablebase is loaded.
+#[derive(Debug, Clone, Copy)]
+enum ChildTarget {
+    Index(usize),
+    Other(Option<(WDLOutcome, i32)>),
+}
+
+type ChildMove = (ChildTarget, SerializedMove);
+
+/// Retrograde analysis gives up after this many passes.
+const MAX_ITERATIONS: u32 = 500;
+
+/// Units of work between clock checks in time-budgeted steps.
+const UNITS_PER_CLOCK_CHECK: usize = 32;
+
+/// Stage of an incremental tablebase build.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BuildPhase {
+    /// Visiting index slots in order: terminal positions are resolved and the
+    /// rest queued for retrograde analysis
+    Enumerate {
+        next_index: usize,
+    },
+    /// Retrograde pass `iteration`, which resolves exactly the positions with
+    /// DTM `iteration`, at queued position `next_slot`
+    Retrograde {
+        iteration: u32,
+        next_slot: usize,
+    },
+    Done,
+}
+
+/// Tablebase generation split into small units of work, so it can run a
+/// little at a time (e.g. in idle frames) and be saved and resumed later.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TablebaseBuilder {
+    config: TablebaseConfig,
+    tablebase: PieceTablebase,
+    phase: BuildPhase,
+    /// Indices of the positions not yet resolved
+    unknown: Vec<usize>,
+    /// Packed results of the current pass by slot in `unknown`. Passes only
+    /// read results of earlier passes, so these are applied when it ends
+    resolved: Vec<(usize, u32)>,
+    /// Moves of the queued positions by slot. Not saved, and rebuilt on resume
+    #[serde(skip)]
+    children: Vec<Vec<ChildMove>>,
+    /// Tablebases of the dependencies, copied from the loaded tablebases when
+    /// the build first steps (or resumes)
+    #[serde(skip)]
+    subtables: Option<HashMap<String, PieceTablebase>>,
+    /// Rule stalemates are scored by
+    #[serde(default)]
+    stalemate_rule: StalemateRule,
+    /// Stops the build between units of work. Not saved
+    #[serde(skip)]
+    cancel: Option<CancelToken>,
+}
+
+impl TablebaseBuilder {
+    pub fn new(config: &TablebaseConfig) -> Self {
+        let layout = get_layout(config);
+        Self {
+            config: config.clone(),
+            tablebase: PieceTablebase {
+                name: config.name.clone(),
+                description: format!("Endgame tablebase for {}", config.name),
+                entries: vec![0; layout.size()],
+                size: 0,
+                metadata: TablebaseMetadata {
+                    generated_at: chrono::Utc::now().to_rfc3339(),
+                    generation_time_ms: 0,
+                    win_count: 0,
+                    draw_count: 0,
+                    loss_count: 0,
                 },
-            );
-            match wdl {
-                WDLOutcome::Loss => tablebase.metadata.loss_count += 1,
-                WDLOutcome::Draw => tablebase.metadata.draw_count += 1,
-                WDLOutcome::Win => tablebase.metadata.win_count += 1,
-            }
-        } else {
-            unknown_positions.insert(key);
+            },
+            phase: BuildPhase::Enumerate { next_index: 0 },
+            unknown: Vec::new(),
+            resolved: Vec::new(),
+            children: Vec::new(),
+            subtables: None,
+            stalemate_rule: StalemateRule::Draw,
+            cancel: None,
         }
     }
 
-    // Phase 2: Retrograde analysis
-    let max_iterations = 500;
-    let mut changed = true;
-    let mut iteration = 0;
+    /// Score stalemates by `stalemate_rule` instead of as draws. The
+    /// tablebase is named by [`ruled_tablebase_name`], and its subtables are
+    /// the loaded ones of the same rule.
+    pub fn with_stalemate_rule(mut self, stalemate_rule: StalemateRule) -> Self {
+        self.stalemate_rule = stalemate_rule;
+        self.tablebase.name = ruled_tablebase_name(&self.config.name, stalemate_rule);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.tablebase.name
+    }
 
-    while changed && iteration < max_iterations {
-        changed = false;
-        iteration += 1;
+    pub fn phase(&self) -> BuildPhase {
+        self.phase
+    }
 
-        let mut to_resolve: Vec<String> = Vec::new();
+    pub fn is_done(&self) -> bool {
+        self.phase == BuildPhase::Done
+    }
 
-        for key in &unknown_positions {
-            let (board, side_to_move) = match position_map.get(key) {
-                Some(pos) => pos,
-                None => continue,
-            };
+    /// Number of index slots, the length of the enumeration phase.
+    pub fn index_size(&self) -> usize {
+        self.tablebase.entries.len()
+    }
 
-            let moves = generate_all_legal_moves(board, *side_to_move);
+    /// Number of queued positions not yet resolved.
+    pub fn unresolved(&self) -> usize {
+        self.unknown.len()
+    }
 
-            let mut has_winning_move = false;
-            let mut all_moves_lose = true;
-            let mut best_move_info: Option<(SerializedMove, i32)> = None;
-            let mut max_dtm = 0;
+    /// Stop stepping when `token` is cancelled; the build stays resumable.
+    pub fn set_cancel_token(&mut self, token: Option<CancelToken>) {
+        self.cancel = token;
+    }
 
-            for mv in &moves {
-                let new_board = apply_move(board, mv);
-                let new_key = get_tablebase_key(&new_board, side_to_move.opposite());
+    /// Whether the build's cancel token was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(CancelToken::is_cancelled)
+    }
 
-                let opponent_entry = tablebase.entries.get(&new_key);
+    /// The tablebase, once the build is done.
+    pub fn into_tablebase(self) -> Option<PieceTablebase> {
+        self.is_done().then_some(self.tablebase)
+    }
 
-                match opponent_entry {
-                    None => {
-                        // Unknown position - can't conclude yet
-                        all_moves_lose = false;
-                    }
-                    Some(entry) => match entry.wdl {
-                        WDLOutcome::Loss => {
-                            // Opponent is lost = we win
-                            has_winning_move = true;
-                            let new_dtm = entry.dtm + 1;
-                            if best_move_info.is_none()
-                                || new_dtm < best_move_info.as_ref().unwrap().1
-                            {
-                                best_move_info = Some((SerializedMove::from_move(mv), new_dtm));
-                            }
-                        }
-                        WDLOutcome::Win => {
-                            // Opponent wins = this move loses for us
-                            max_dtm = max_dtm.max(entry.dtm);
-                        }
-                        WDLOutcome::Draw => {
-                            // Draw - better than losing
-                            all_moves_lose = false;
-                        }
-                    },
-                }
+    /// Do up to `units` units of work (one index slot or queued position
+    /// each), fewer if cancelled. Returns true once the build is done.
+    pub fn step_units(&mut self, units: usize) -> bool {
+        trace_span!("tablebase_step", name = %self.tablebase.name);
+        let layout = get_layout(&self.config);
+        if self.subtables.is_none() {
+            let subtables = tablebase_dependencies(&self.config)
+                .into_iter()
+                .filter_map(|name| {
+                    let tablebase =
+                        get_tablebase(&ruled_tablebase_name(&name, self.stalemate_rule))?;
+                    Some((name, tablebase))
+                })
+                .collect();
+            self.subtables = Some(subtables);
+        }
+        for _ in 0..units {
+            if self.is_cancelled() || !self.work(&layout) {
+                break;
+            }
+        }
+        self.is_done()
+    }
+
+    /// Work until `budget_ms` milliseconds have passed or the build is done.
+    /// Returns true once the build is done.
+    pub fn step(&mut self, budget_ms: u64) -> bool {
+        self.step_until(chrono::Utc::now().timestamp_millis() + budget_ms as i64)
+    }
+
+    /// Work until the wall clock (ms since the epoch) reaches `deadline_ms`,
+    /// the build is done or it is cancelled. Returns true once the build is
+    /// done.
+    pub fn step_until(&mut self, deadline_ms: i64) -> bool {
+        loop {
+            let start = chrono::Utc::now().timestamp_millis();
+            let done = self.step_units(UNITS_PER_CLOCK_CHECK);
+            let now = chrono::Utc::now().timestamp_millis();
+            self.tablebase.metadata.generation_time_ms += (now - start).max(0) as u64;
+            if done || now >= deadline_ms || self.is_cancelled() {
+                return done;
             }
+        }
+    }
 
-            if has_winning_move {
-                if let Some((best_move, dtm)) = best_move_info {
-                    to_resolve.push(key.clone());
-                    tablebase.entries.insert(
-                        key.clone(),
-                        TablebaseEntry {
-                            wdl: WDLOutcome::Win,
-                            dtm,
-                            best_move: Some(best_move),
-                        },
-                    );
-                    tablebase.metadata.win_count += 1;
-                    changed = true;
+    /// One unit of work. Returns false if the build is already done.
+    fn work(&mut self, layout: &TablebaseLayout) -> bool {
+        match self.phase {
+            BuildPhase::Done => return false,
+            BuildPhase::Enumerate { next_index } if next_index >= layout.size() => {
+                trace_event!(
+                    positions = self.tablebase.size,
+                    queued = self.unknown.len(),
+                    "enumeration done"
+                );
+                self.phase = BuildPhase::Retrograde {
+                    iteration: 1,
+                    next_slot: 0,
+                };
+            }
+            BuildPhase::Enumerate { next_index } => {
+                self.phase = BuildPhase::Enumerate {
+                    next_index: next_index + 1,
+                };
+                self.enumerate(layout, next_index);
+            }
+            // After a resume, first rebuild the moves of the queued positions
+            BuildPhase::Retrograde { .. } if self.children.len() < self.unknown.len() => {
+                let children = self
+                    .unknown
+                    .get(self.children.len())
+                    .and_then(|&index| layout.decode(index))
+                    .map(|(board, side_to_move)| {
+                        child_moves(layout, &self.subtables, &board, side_to_move)
+                    })
+                    .unwrap_or_default();
+                self.children.push(children);
+            }
+            BuildPhase::Retrograde {
+                iteration,
+                next_slot,
+            } if next_slot < self.unknown.len() => {
+                if let Some(entry) = self.resolve(layout, next_slot) {
+                    self.resolved.push((next_slot, pack_entry(layout, &entry)));
                 }
-            } else if all_moves_lose && !moves.is_empty() {
-                to_resolve.push(key.clone());
-                tablebase.entries.insert(
-                    key.clone(),
-                    TablebaseEntry {
-                        wdl: WDLOutcome::Loss,
-                        dtm: max_dtm + 1,
-                        best_move: None,
-                    },
+                self.phase = BuildPhase::Retrograde {
+                    iteration,
+                    next_slot: next_slot + 1,
+                };
+            }
+            BuildPhase::Retrograde { iteration, .. } => {
+                trace_event!(
+                    iteration,
+                    resolved = self.resolved.len(),
+                    queued = self.unknown.len(),
+                    "retrograde pass done"
                 );
-                tablebase.metadata.loss_count += 1;
-                changed = true;
+                if self.resolved.is_empty() || iteration >= MAX_ITERATIONS {
+                    self.finish(layout);
+                } else {
+                    self.end_pass();
+                    self.phase = BuildPhase::Retrograde {
+                        iteration: iteration + 1,
+                        next_slot: 0,
+                    };
+                }
             }
         }
+        true
+    }
 
-        // Remove resolved positions from unknown set
-        for key in to_resolve {
-            unknown_positions.remove(&key);
+    /// Resolve a terminal position or queue it for retrograde analysis.
+    fn enumerate(&mut self, layout: &TablebaseLayout, index: usize) {
+        let Some((board, side_to_move)) = canonical_position(layout, index) else {
+            return;
+        };
+        self.tablebase.size += 1;
+        match get_terminal_outcome(&board, side_to_move, self.stalemate_rule) {
+            Some((wdl, dtm)) => {
+                let entry = TablebaseEntry {
+                    wdl,
+                    dtm,
+                    best_move: None,
+                };
+                if let Some(slot) = self.tablebase.entries.get_mut(index) {
+                    *slot = pack_entry(layout, &entry);
+                }
+            }
+            None => {
+                // Moves of positions queued before a resume are rebuilt later
+                if self.children.len() == self.unknown.len() {
+                    self.children
+                        .push(child_moves(layout, &self.subtables, &board, side_to_move));
+                }
+                self.unknown.push(index);
+            }
+        }
+    }
+
+    /// Result of a queued position given the results of earlier passes.
+    /// Children are always canonical and never need their moves mapped.
+    fn resolve(&self, layout: &TablebaseLayout, slot: usize) -> Option<TablebaseEntry> {
+        let canonical = CanonicalPosition {
+            index: 0,
+            symmetry: 0,
+            swapped: false,
+        };
+        let children = self.children.get(slot)?;
+        let mut all_moves_lose = true;
+        let mut best_move_info: Option<(&SerializedMove, i32)> = None;
+        let mut max_dtm = 0;
+
+        for (target, mv) in children {
+            let opponent_entry = match *target {
+                ChildTarget::Index(index) => self
+                    .tablebase
+                    .entries
+                    .get(index)
+                    .and_then(|&packed| unpack_entry(layout, &canonical, packed))
+                    .map(|entry| (entry.wdl, entry.dtm)),
+                ChildTarget::Other(outcome) => outcome,
+            };
+
+            match opponent_entry {
+                None => {
+                    // Unknown position - can't conclude yet
+                    all_moves_lose = false;
+                }
+                Some((wdl, opponent_dtm)) => match wdl {
+                    WDLOutcome::Loss => {
+                        // Opponent is lost = we win. Ties are broken by the
+                        // move's cells so generation is reproducible
+                        let new_dtm = opponent_dtm + 1;
+                        let key = |m: &SerializedMove| (m.from_q, m.from_r, m.to_q, m.to_r);
+                        if best_move_info
+                            .is_none_or(|(best, dtm)| (new_dtm, key(mv)) < (dtm, key(best)))
+                        {
+                            best_move_info = Some((mv, new_dtm));
+                        }
+                    }
+                    WDLOutcome::Win => {
+                        // Opponent wins = this move loses for us
+                        max_dtm = max_dtm.max(opponent_dtm);
+                    }
+                    WDLOutcome::Draw => {
+                        // Draw - better than losing
+                        all_moves_lose = false;
+                    }
+                },
+            }
+        }
+
+        if let Some((best_move, dtm)) = best_move_info {
+            Some(TablebaseEntry {
+                wdl: WDLOutcome::Win,
+                dtm,
+                best_move: Some(best_move.clone()),
+            })
+        } else if all_moves_lose && !children.is_empty() {
+            Some(TablebaseEntry {
+                wdl: WDLOutcome::Loss,
+                dtm: max_dtm + 1,
+                best_move: None,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Apply the results of a pass and drop the positions it resolved.
+    fn end_pass(&mut self) {
+        let mut keep = vec![true; self.unknown.len()];
+        for (slot, packed) in self.resolved.drain(..) {
+            let index = self.unknown.get(slot).copied();
+            if let Some(entry) = index.and_then(|index| self.tablebase.entries.get_mut(index)) {
+                *entry = packed;
+            }
+            if let Some(kept) = keep.get_mut(slot) {
+                *kept = false;
+            }
         }
+        let mut slots = keep.iter();
+        self.unknown.retain(|_| *slots.next().unwrap_or(&true));
+        let mut slots = keep.iter();
+        self.children.retain(|_| *slots.next().unwrap_or(&true));
     }
 
-    // Phase 3: All remaining unknown positions are draws
-    for key in unknown_positions {
-        tablebase.entries.insert(
-            key,
-            TablebaseEntry {
+    /// All remaining unknown positions are draws.
+    fn finish(&mut self, layout: &TablebaseLayout) {
+        let draw = pack_entry(
+            layout,
+            &TablebaseEntry {
                 wdl: WDLOutcome::Draw,
                 dtm: -1,
                 best_move: None,
             },
         );
-        tablebase.metadata.draw_count += 1;
+        for index in self.unknown.drain(..) {
+            if let Some(entry) = self.tablebase.entries.get_mut(index) {
+                *entry = draw;
+            }
+        }
+        self.children.clear();
+
+        let metadata = &mut self.tablebase.metadata;
+        for &packed in &self.tablebase.entries {
+            match packed & WDL_MASK {
+                1 => metadata.win_count += 1,
+                2 => metadata.draw_count += 1,
+                3 => metadata.loss_count += 1,
+                _ => {}
+            }
+        }
+        trace_event!(
+            wins = metadata.win_count,
+            draws = metadata.draw_count,
+            losses = metadata.loss_count,
+            "build done"
+        );
+        self.phase = BuildPhase::Done;
     }
+}
 
-    tablebase.size = tablebase.entries.len();
-    tablebase.metadata.generation_time_ms = start_time.elapsed().as_millis() as u64;
+/// Moves of a position and where they lead, looking up positions in other
+/// configurations in the dependency tablebases.
+fn child_moves(
+    layout: &TablebaseLayout,
+    subtables: &Option<HashMap<String, PieceTablebase>>,
+    board: &BoardState,
+    side_to_move: Color,
+) -> Vec<ChildMove> {
+    let opponent = side_to_move.opposite();
+    generate_all_legal_moves(board, side_to_move)
+        .iter()
+        .map(|mv| {
+            let new_board = apply_move(board, mv);
+            let target = match layout.index_of(&new_board, opponent) {
+                Some(position) => ChildTarget::Index(position.index),
+                None => ChildTarget::Other(
+                    detect_configuration(&new_board)
+                        .and_then(|config| subtables.as_ref()?.get(&config.name))
+                        .and_then(|tablebase| tablebase.probe(&new_board, opponent))
+                        .map(|entry| (entry.wdl, entry.dtm)),
+                ),
+            };
+            (target, SerializedMove::from_move(mv))
+        })
+        .collect()
+}
 
-    tablebase
+impl PieceTablebase {
+    /// Look up a position of this tablebase's configuration.
+    pub fn probe(&self, board: &BoardState, side_to_move: Color) -> Option<TablebaseEntry> {
+        let layout = get_layout(&parse_configuration(&self.name)?);
+        let position = layout.index_of(board, side_to_move)?;
+        unpack_entry(&layout, &position, *self.entries.get(position.index)?)
+    }
 }
 
 // ============================================================================
@@ -543,25 +1333,16 @@ pub fn probe_tablebase(board: &BoardState, side_to_move: Color) -> TablebaseProb
         }
     };
 
-    // Get the tablebase for this configuration
-    let tablebase = match get_tablebase(&config.name) {
-        Some(tb) => tb,
-        None => {
-            return TablebaseProbeResult {
-                found: false,
-                entry: None,
-                tablebase_name: None,
-            }
-        }
-    };
-
-    // Look up the position
-    let key = get_tablebase_key(board, side_to_move);
+    // Look up the position in the loaded tablebase for this configuration
+    let entry = TABLEBASES
+        .lock()
+        .ok()
+        .and_then(|tablebases| tablebases.get(&config.name)?.probe(board, side_to_move));
 
-    if let Some(entry) = tablebase.entries.get(&key) {
+    if let Some(entry) = entry {
         TablebaseProbeResult {
             found: true,
-            entry: Some(entry.clone()),
+            entry: Some(entry),
             tablebase_name: Some(config.name),
         }
     } else {
@@ -573,21 +1354,169 @@ pub fn probe_tablebase(board: &BoardState, side_to_move: Color) -> TablebaseProb
     }
 }
 
-/// Get the tablebase evaluation for a position.
-/// Returns a score in centipawns, where positive is good for side_to_move.
-pub fn get_tablebase_score(board: &BoardState, side_to_move: Color) -> Option<i32> {
-    let result = probe_tablebase(board, side_to_move);
+/// Get the tablebase evaluation for a position.
+/// Returns a score in centipawns, where positive is good for side_to_move.
+pub fn get_tablebase_score(board: &BoardState, side_to_move: Color) -> Option<i32> {
+    let result = probe_tablebase(board, side_to_move);
+
+    if !result.found {
+        return None;
+    }
+
+    let entry = result.entry?;
+
+    Some(match entry.wdl {
+        WDLOutcome::Win => CHECKMATE_VALUE - entry.dtm,
+        WDLOutcome::Draw => 0,
+        WDLOutcome::Loss => -CHECKMATE_VALUE + entry.dtm,
+    })
+}
+
+// ============================================================================
+// Endgame Explanations
+// ============================================================================
+
+/// The plan behind a tablebase-won position, for endgame lessons.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EndgameExplanation {
+    pub winner: Color,
+    /// Distance to mate (plies) with best play
+    pub dtm: i32,
+    /// Steps of the plan, in the order the best line completes them
+    pub plan: Vec<Message>,
+    /// Best line from the position, ending in mate
+    pub line: Vec<Move>,
+}
+
+/// The legal move a stored best move stands for.
+fn resolve_move(board: &BoardState, side_to_move: Color, stored: &SerializedMove) -> Option<Move> {
+    generate_all_legal_moves(board, side_to_move)
+        .into_iter()
+        .find(|mv| {
+            mv.from == HexCoord::new(stored.from_q, stored.from_r)
+                && mv.to == HexCoord::new(stored.to_q, stored.to_r)
+                && mv.promotion == stored.promotion
+        })
+}
+
+fn king_cell(board: &BoardState, color: Color) -> Option<HexCoord> {
+    board.iter().find_map(|(key, piece)| {
+        (piece.piece_type == PieceType::King && piece.color == color)
+            .then(|| HexCoord::from_key(key))
+            .flatten()
+    })
+}
+
+/// Explain how a tablebase-won position is won, for either side to move:
+/// the best line down the DTM gradient and the plan read off it (the
+/// defending king driven outward, the attacking king brought up,
+/// promotions, and where mate falls). None unless the position's
+/// tablebase is loaded and the position is not a draw.
+pub fn explain(board: &BoardState, side: Color) -> Option<EndgameExplanation> {
+    explain_with(board, side, |position, to_move| {
+        probe_tablebase(position, to_move).entry
+    })
+}
 
-    if !result.found {
-        return None;
+fn explain_with(
+    board: &BoardState,
+    side: Color,
+    probe: impl Fn(&BoardState, Color) -> Option<TablebaseEntry>,
+) -> Option<EndgameExplanation> {
+    let entry = probe(board, side)?;
+    let winner = match entry.wdl {
+        WDLOutcome::Win => side,
+        WDLOutcome::Loss => side.opposite(),
+        WDLOutcome::Draw => return None,
+    };
+    let loser = winner.opposite();
+    let dtm = entry.dtm;
+
+    // Follow the best moves, each a step down in distance to mate. Only
+    // the winning side's moves are stored; the defender delays mate longest
+    let mut positions = vec![board.clone()];
+    let mut line = Vec::new();
+    let mut to_move = side;
+    let mut current = entry;
+    while current.dtm > 0 && line.len() < dtm as usize {
+        let position = positions.last()?;
+        let stored = current
+            .best_move
+            .as_ref()
+            .and_then(|stored| resolve_move(position, to_move, stored));
+        let step = stored
+            .into_iter()
+            .chain(generate_all_legal_moves(position, to_move))
+            .find_map(|mv| {
+                let next = apply_move(position, &mv);
+                let entry = probe(&next, to_move.opposite())?;
+                (entry.wdl != current.wdl && entry.dtm == current.dtm - 1)
+                    .then_some((mv, next, entry))
+            });
+        let Some((mv, next, next_entry)) = step else {
+            break;
+        };
+        to_move = to_move.opposite();
+        line.push(mv);
+        positions.push(next);
+        current = next_entry;
     }
 
-    let entry = result.entry?;
+    let mut steps: Vec<(usize, Message)> = Vec::new();
+    let kings: Vec<_> = positions
+        .iter()
+        .map(|position| Some((king_cell(position, winner)?, king_cell(position, loser)?)))
+        .collect::<Option<_>>()?;
+    let (first, last) = (*kings.first()?, *kings.last()?);
+    let ring = |cell: HexCoord| hex_distance(cell, HexCoord::new(0, 0));
+
+    // The first ply a measure reaches its final value, if that is an improvement
+    let reached = |measure: &dyn Fn((HexCoord, HexCoord)) -> i32| {
+        let target = measure(last);
+        kings.iter().position(|&k| measure(k) == target)
+    };
+    if ring(last.1) > ring(first.1) {
+        if let Some(ply) = reached(&|(_, defender)| ring(defender)) {
+            steps.push((
+                ply,
+                Message::new("planConfineKing").with("color", color_key(loser)),
+            ));
+        }
+    }
+    if hex_distance(last.0, last.1) < hex_distance(first.0, first.1) {
+        if let Some(ply) = reached(&|(attacker, defender)| hex_distance(attacker, defender)) {
+            steps.push((
+                ply,
+                Message::new("planApproachKing").with("color", color_key(winner)),
+            ));
+        }
+    }
+    for (ply, mv) in line.iter().enumerate() {
+        if let Some(promotion) = mv.promotion {
+            steps.push((
+                ply + 1,
+                Message::new("planPromote").with("piece", piece_type_key(promotion)),
+            ));
+        }
+    }
+    steps.sort_by_key(|&(ply, _)| ply);
+    let mut plan: Vec<Message> = steps.into_iter().map(|(_, step)| step).collect();
+
+    let mated = positions.last()?;
+    if current.dtm == 0 && is_in_check(mated, loser) {
+        let key = match cell_class(last.1).map(|class| class.region) {
+            Some(CellRegion::Corner) => "planMateOnCorner",
+            Some(CellRegion::Edge) => "planMateOnEdge",
+            _ => "planMate",
+        };
+        plan.push(Message::new(key).with_cell("cell", last.1));
+    }
 
-    Some(match entry.wdl {
-        WDLOutcome::Win => CHECKMATE_VALUE - entry.dtm,
-        WDLOutcome::Draw => 0,
-        WDLOutcome::Loss => -CHECKMATE_VALUE + entry.dtm,
+    Some(EndgameExplanation {
+        winner,
+        dtm,
+        plan,
+        line,
     })
 }
 
@@ -595,48 +1524,23 @@ pub fn get_tablebase_score(board: &BoardState, side_to_move: Color) -> Option<i3
 // Tablebase Initialization
 // ============================================================================
 
+/// Common endgame tablebases, cheapest and most useful first.
+pub const COMMON_TABLEBASES: [&str; 6] = ["KvK", "KQvK", "KLvK", "KCvK", "KNvK", "KPvK"];
+
 /// Generate and load common endgame tablebases.
 pub fn initialize_tablebases() {
-    let configs = vec![
-        TablebaseConfig {
-            stronger_side: vec![],
-            weaker_side: vec![],
-            name: "KvK".to_string(),
-        },
-        TablebaseConfig {
-            stronger_side: vec![PieceType::Queen],
-            weaker_side: vec![],
-            name: "KQvK".to_string(),
-        },
-        TablebaseConfig {
-            stronger_side: vec![PieceType::Lance],
-            weaker_side: vec![],
-            name: "KLvK".to_string(),
-        },
-        TablebaseConfig {
-            stronger_side: vec![PieceType::Chariot],
-            weaker_side: vec![],
-            name: "KCvK".to_string(),
-        },
-        TablebaseConfig {
-            stronger_side: vec![PieceType::Knight],
-            weaker_side: vec![],
-            name: "KNvK".to_string(),
-        },
-    ];
-
-    for config in configs {
-        let tablebase = generate_tablebase(&config);
-        set_tablebase(tablebase);
+    for name in COMMON_TABLEBASES {
+        generate_tablebase_on_demand(name);
     }
 }
 
-/// Generate a single tablebase on demand.
-pub fn generate_tablebase_on_demand(name: &str) -> Option<PieceTablebase> {
-    // Parse the configuration from the name
-    // Format: K[pieces]vK[pieces]
-    let re = regex::Regex::new(r"^K([QLCNP]*)vK([QLCNP]*)$").ok()?;
-    let caps = re.captures(name)?;
+/// Parse a configuration name of the form K[pieces]vK[pieces] (e.g. "KQvK").
+pub fn parse_configuration(name: &str) -> Option<TablebaseConfig> {
+    lazy_static::lazy_static! {
+        static ref CONFIGURATION_NAME: Option<regex::Regex> =
+            regex::Regex::new(r"^K([QLCNP]*)vK([QLCNP]*)$").ok();
+    }
+    let caps = CONFIGURATION_NAME.as_ref()?.captures(name)?;
 
     let piece_map: HashMap<char, PieceType> = [
         ('Q', PieceType::Queen),
@@ -648,38 +1552,207 @@ pub fn generate_tablebase_on_demand(name: &str) -> Option<PieceTablebase> {
     .into_iter()
     .collect();
 
-    let stronger_str = caps.get(1)?.as_str();
-    let weaker_str = caps.get(2)?.as_str();
-
-    let stronger_side: Vec<PieceType> = stronger_str
-        .chars()
-        .filter_map(|c| piece_map.get(&c).copied())
-        .collect();
-
-    let weaker_side: Vec<PieceType> = weaker_str
-        .chars()
-        .filter_map(|c| piece_map.get(&c).copied())
-        .collect();
+    let pieces = |text: &str| -> Vec<PieceType> {
+        text.chars()
+            .filter_map(|c| piece_map.get(&c).copied())
+            .collect()
+    };
 
-    let config = TablebaseConfig {
-        stronger_side,
-        weaker_side,
+    Some(TablebaseConfig {
+        stronger_side: pieces(caps.get(1)?.as_str()),
+        weaker_side: pieces(caps.get(2)?.as_str()),
         name: name.to_string(),
-    };
+    })
+}
 
+/// Generate a single tablebase on demand, after any of its dependencies that
+/// are not loaded (see [`tablebase_dependencies`]).
+pub fn generate_tablebase_on_demand(name: &str) -> Option<PieceTablebase> {
+    let config = parse_configuration(name)?;
+    let loaded = get_loaded_tablebases();
+    for dependency in tablebase_dependencies(&config) {
+        if !loaded.contains(&dependency) {
+            generate_tablebase_on_demand(&dependency)?;
+        }
+    }
     let tablebase = generate_tablebase(&config);
     set_tablebase(tablebase.clone());
 
     Some(tablebase)
 }
 
+// ============================================================================
+// Background Generation
+// ============================================================================
+
+/// Queue of tablebases built a little at a time, loading each one as it is
+/// completed. Configurations that are already loaded are skipped.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TablebaseBuildQueue {
+    /// Configurations still to build, in order
+    pub pending: Vec<String>,
+    /// Build in progress
+    pub current: Option<TablebaseBuilder>,
+    /// Configurations built and loaded by this queue
+    pub completed: Vec<String>,
+}
+
+/// Progress of a build queue.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TablebaseBuildStatus {
+    /// Tablebase being built
+    pub building: Option<String>,
+    pub phase: Option<BuildPhase>,
+    /// Index slots of the tablebase being built (the enumeration length)
+    pub index_size: usize,
+    /// Its queued positions not yet resolved
+    pub unresolved: usize,
+    pub pending: Vec<String>,
+    pub completed: Vec<String>,
+    pub done: bool,
+}
+
+/// Saved state of a build queue, with the tablebases it completed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SavedTablebaseBuild {
+    queue: TablebaseBuildQueue,
+    tablebases: Vec<PieceTablebase>,
+}
+
+impl TablebaseBuildQueue {
+    pub fn new(names: &[&str]) -> Self {
+        Self {
+            pending: names.iter().map(|name| name.to_string()).collect(),
+            current: None,
+            completed: Vec::new(),
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.current.is_none() && self.pending.is_empty()
+    }
+
+    /// Build for up to `budget_ms` milliseconds, loading any tablebase that
+    /// is completed.
+    pub fn step(&mut self, budget_ms: u64) -> TablebaseBuildStatus {
+        let deadline = chrono::Utc::now().timestamp_millis() + budget_ms as i64;
+        loop {
+            if self.current.is_none() && !self.start_next() {
+                break;
+            }
+            let Some(builder) = self.current.as_mut() else {
+                break;
+            };
+            if !builder.step_until(deadline) {
+                break;
+            }
+            if let Some(tablebase) = self.current.take().and_then(|b| b.into_tablebase()) {
+                self.completed.push(tablebase.name.clone());
+                set_tablebase(tablebase);
+            }
+            if chrono::Utc::now().timestamp_millis() >= deadline {
+                break;
+            }
+        }
+        self.status()
+    }
+
+    /// Start the next pending configuration that is not loaded yet, queueing
+    /// its missing dependencies ahead of it first.
+    fn start_next(&mut self) -> bool {
+        let loaded = get_loaded_tablebases();
+        while !self.pending.is_empty() {
+            let name = self.pending.remove(0);
+            if loaded.contains(&name) {
+                continue;
+            }
+            let Some(config) = parse_configuration(&name) else {
+                continue;
+            };
+            let missing: Vec<String> = tablebase_dependencies(&config)
+                .into_iter()
+                .filter(|dependency| !loaded.contains(dependency))
+                .collect();
+            if missing.is_empty() {
+                self.current = Some(TablebaseBuilder::new(&config));
+                return true;
+            }
+            self.pending
+                .splice(0..0, missing.into_iter().chain(std::iter::once(name)));
+        }
+        false
+    }
+
+    pub fn status(&self) -> TablebaseBuildStatus {
+        TablebaseBuildStatus {
+            building: self.current.as_ref().map(|b| b.name().to_string()),
+            phase: self.current.as_ref().map(|b| b.phase()),
+            index_size: self.current.as_ref().map_or(0, |b| b.index_size()),
+            unresolved: self.current.as_ref().map_or(0, |b| b.unresolved()),
+            pending: self.pending.clone(),
+            completed: self.completed.clone(),
+            done: self.is_done(),
+        }
+    }
+
+    /// Serialize the queue and the tablebases it completed to JSON.
+    pub fn save(&self) -> Option<String> {
+        let saved = SavedTablebaseBuild {
+            queue: self.clone(),
+            tablebases: self
+                .completed
+                .iter()
+                .filter_map(|name| get_tablebase(name))
+                .collect(),
+        };
+        serde_json::to_string(&saved).ok()
+    }
+
+    /// Restore a queue saved with [`TablebaseBuildQueue::save`], loading the
+    /// tablebases it had completed.
+    pub fn load(json: &str) -> Option<Self> {
+        let saved: SavedTablebaseBuild = serde_json::from_str(json).ok()?;
+        for tablebase in saved.tablebases {
+            set_tablebase(tablebase);
+        }
+        Some(saved.queue)
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref BUILD_QUEUE: std::sync::Mutex<TablebaseBuildQueue> =
+        std::sync::Mutex::new(TablebaseBuildQueue::new(&COMMON_TABLEBASES));
+}
+
+/// Build the common tablebases for up to `budget_ms` milliseconds. Call
+/// repeatedly (e.g. in idle frames) until the status reports done.
+pub fn tablebase_build_step(budget_ms: u64) -> Option<TablebaseBuildStatus> {
+    Some(BUILD_QUEUE.lock().ok()?.step(budget_ms))
+}
+
+/// Save the progress of the background build as JSON.
+pub fn save_tablebase_build() -> Option<String> {
+    BUILD_QUEUE.lock().ok()?.save()
+}
+
+/// Resume a background build saved with [`save_tablebase_build`].
+pub fn load_tablebase_build(json: &str) -> bool {
+    match (TablebaseBuildQueue::load(json), BUILD_QUEUE.lock()) {
+        (Some(queue), Ok(mut current)) => {
+            *current = queue;
+            true
+        }
+        _ => false,
+    }
+}
+
 // ============================================================================
 // Statistics
 // ============================================================================
 
 /// Get statistics about loaded tablebases.
 pub fn get_tablebase_statistics() -> TablebaseStatistics {
-    let tablebases = TABLEBASES.lock().unwrap();
+    let tablebases = TABLEBASES.lock().unwrap_or_else(|e| e.into_inner());
 
     let mut total_entries = 0;
     let mut stats = Vec::new();
@@ -772,6 +1845,145 @@ pub fn import_tablebase_from_json(json: &str) -> Option<PieceTablebase> {
     serde_json::from_str(json).ok()
 }
 
+/// Magic bytes opening a binary tablebase.
+const TABLEBASE_MAGIC: &[u8; 4] = b"UXTB";
+
+/// Export a tablebase in the compact binary format: magic, name length (u8)
+/// and name, then position count, win, draw and loss counts and entry count
+/// (u32 little-endian each), then the packed entries (u32 little-endian).
+/// Entries sit at fixed offsets, so a file can be memory-mapped and probed
+/// with [`TablebaseView`] without loading it.
+pub fn export_tablebase_to_bytes(tablebase: &PieceTablebase) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(64 + tablebase.entries.len() * 4);
+    bytes.extend_from_slice(TABLEBASE_MAGIC);
+    bytes.push(tablebase.name.len() as u8);
+    bytes.extend_from_slice(tablebase.name.as_bytes());
+    for count in [
+        tablebase.size,
+        tablebase.metadata.win_count,
+        tablebase.metadata.draw_count,
+        tablebase.metadata.loss_count,
+        tablebase.entries.len(),
+    ] {
+        bytes.extend_from_slice(&(count as u32).to_le_bytes());
+    }
+    for entry in &tablebase.entries {
+        bytes.extend_from_slice(&entry.to_le_bytes());
+    }
+    bytes
+}
+
+/// A binary tablebase probed in place (e.g. from a memory-mapped file).
+#[derive(Debug, Clone, Copy)]
+pub struct TablebaseView<'a> {
+    pub name: &'a str,
+    counts: [usize; 4],
+    entries: &'a [u8],
+}
+
+impl<'a> TablebaseView<'a> {
+    /// Parse the header of a binary tablebase (see [`export_tablebase_to_bytes`]).
+    pub fn parse(bytes: &'a [u8]) -> Option<Self> {
+        let rest = bytes.strip_prefix(TABLEBASE_MAGIC)?;
+        let (&name_len, rest) = rest.split_first()?;
+        let (name, rest) = rest.split_at_checked(name_len as usize)?;
+        let name = std::str::from_utf8(name).ok()?;
+
+        let mut counts = [0usize; 5];
+        let mut rest = rest;
+        for count in &mut counts {
+            let (value, tail) = rest.split_first_chunk::<4>()?;
+            *count = u32::from_le_bytes(*value) as usize;
+            rest = tail;
+        }
+        let entries = rest.get(..counts[4].checked_mul(4)?)?;
+        parse_configuration(name)?;
+
+        Some(Self {
+            name,
+            counts: [counts[0], counts[1], counts[2], counts[3]],
+            entries,
+        })
+    }
+
+    fn entry(&self, index: usize) -> Option<u32> {
+        let bytes = self.entries.get(index * 4..index * 4 + 4)?;
+        Some(u32::from_le_bytes(bytes.try_into().ok()?))
+    }
+
+    /// Look up a position without loading the tablebase.
+    pub fn probe(&self, board: &BoardState, side_to_move: Color) -> Option<TablebaseEntry> {
+        let layout = get_layout(&parse_configuration(self.name)?);
+        let position = layout.index_of(board, side_to_move)?;
+        unpack_entry(&layout, &position, self.entry(position.index)?)
+    }
+
+    /// Load the whole tablebase.
+    pub fn to_tablebase(&self) -> PieceTablebase {
+        PieceTablebase {
+            name: self.name.to_string(),
+            description: format!("Endgame tablebase for {}", self.name),
+            entries: self
+                .entries
+                .chunks_exact(4)
+                .filter_map(|chunk| chunk.try_into().ok().map(u32::from_le_bytes))
+                .collect(),
+            size: self.counts[0],
+            metadata: TablebaseMetadata {
+                generated_at: String::new(),
+                generation_time_ms: 0,
+                win_count: self.counts[1],
+                draw_count: self.counts[2],
+                loss_count: self.counts[3],
+            },
+        }
+    }
+}
+
+/// Import a tablebase from the binary format.
+pub fn import_tablebase_from_bytes(bytes: &[u8]) -> Option<PieceTablebase> {
+    TablebaseView::parse(bytes).map(|view| view.to_tablebase())
+}
+
+// ============================================================================
+// Embedded Tablebases
+// ============================================================================
+
+#[cfg(feature = "embedded-tablebases")]
+mod embedded {
+    include!(concat!(env!("OUT_DIR"), "/embedded_tablebases.rs"));
+}
+
+/// Tablebases bundled into the build with the `embedded-tablebases` feature,
+/// as (name, binary tablebase). Empty without the feature.
+pub fn embedded_tablebases() -> &'static [(&'static str, &'static [u8])] {
+    #[cfg(feature = "embedded-tablebases")]
+    {
+        embedded::EMBEDDED_TABLEBASES
+    }
+    #[cfg(not(feature = "embedded-tablebases"))]
+    {
+        &[]
+    }
+}
+
+/// Load the embedded tablebases that are not loaded yet. Returns the number
+/// loaded.
+pub fn load_embedded_tablebases() -> usize {
+    let loaded = get_loaded_tablebases();
+    let mut count = 0;
+    for (name, bytes) in embedded_tablebases() {
+        if loaded.iter().any(|l| l == name) {
+            continue;
+        }
+        if let Some(tablebase) = import_tablebase_from_bytes(bytes) {
+            set_tablebase(tablebase);
+            count += 1;
+        }
+    }
+    count
+}
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -780,6 +1992,59 @@ pub fn import_tablebase_from_json(json: &str) -> Option<PieceTablebase> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_terminal_outcome_stalemate_rules() {
+        // Black's cornered king has no moves but is not in check
+        let mut board = BoardState::new();
+        board.insert("0,4".to_string(), Piece::new(PieceType::King, Color::White));
+        board.insert(
+            "0,-4".to_string(),
+            Piece::new(PieceType::King, Color::Black),
+        );
+        board.insert(
+            "-1,-2".to_string(),
+            Piece::new(PieceType::Queen, Color::White),
+        );
+        let outcome = |rule| get_terminal_outcome(&board, Color::Black, rule).map(|(wdl, _)| wdl);
+        assert_eq!(outcome(StalemateRule::Draw), Some(WDLOutcome::Draw));
+        assert_eq!(
+            outcome(StalemateRule::StalematerWins),
+            Some(WDLOutcome::Loss)
+        );
+        assert_eq!(
+            outcome(StalemateRule::StalematerLoses),
+            Some(WDLOutcome::Win)
+        );
+        assert_eq!(
+            outcome(StalemateRule::Fractional {
+                stalemater_quarters: 3
+            }),
+            Some(WDLOutcome::Draw)
+        );
+    }
+
+    #[test]
+    fn test_builder_stalemate_rule_names() {
+        let config = parse_configuration("KQvK").unwrap();
+        let builder = |rule| TablebaseBuilder::new(&config).with_stalemate_rule(rule);
+        assert_eq!(builder(StalemateRule::Draw).name(), "KQvK");
+        assert_eq!(
+            builder(StalemateRule::Fractional {
+                stalemater_quarters: 1
+            })
+            .name(),
+            "KQvK"
+        );
+        assert_eq!(
+            builder(StalemateRule::StalematerWins).name(),
+            "KQvK-stalematerWins"
+        );
+        assert_eq!(
+            builder(StalemateRule::StalematerLoses).name(),
+            "KQvK-stalematerLoses"
+        );
+    }
+
     fn create_kvk_position() -> BoardState {
         let mut board = BoardState::new();
         board.insert(
@@ -927,4 +2192,325 @@ mod tests {
         assert_eq!(restored.name, tablebase.name);
         assert_eq!(restored.size, tablebase.size);
     }
+
+    fn kqvk_config() -> TablebaseConfig {
+        TablebaseConfig {
+            stronger_side: vec![PieceType::Queen],
+            weaker_side: vec![],
+            name: "KQvK".to_string(),
+        }
+    }
+
+    /// KQvK is slow to generate, so the tests share one copy.
+    fn kqvk_tablebase() -> &'static PieceTablebase {
+        static TABLEBASE: std::sync::OnceLock<PieceTablebase> = std::sync::OnceLock::new();
+        TABLEBASE.get_or_init(|| generate_tablebase(&kqvk_config()))
+    }
+
+    #[test]
+    fn test_explain_kqvk() {
+        let tablebase = kqvk_tablebase();
+        let probe = |board: &BoardState, side| tablebase.probe(board, side);
+        // The black king starts in the middle of the board
+        let mut board = BoardState::new();
+        for (q, r, piece_type, color) in [
+            (4, -4, PieceType::King, Color::White),
+            (3, -4, PieceType::Queen, Color::White),
+            (0, 0, PieceType::King, Color::Black),
+        ] {
+            board.insert(HexCoord::new(q, r).to_key(), Piece::new(piece_type, color));
+        }
+        let explanation = explain_with(&board, Color::White, probe).unwrap();
+
+        assert_eq!(explanation.winner, Color::White);
+        assert!(explanation.dtm > 0);
+        // The best line runs all the way to mate
+        assert_eq!(explanation.line.len(), explanation.dtm as usize);
+        let mut position = board.clone();
+        let mut side = Color::White;
+        for mv in &explanation.line {
+            assert!(generate_all_legal_moves(&position, side).contains(mv));
+            position = apply_move(&position, mv);
+            side = side.opposite();
+        }
+        assert!(is_in_check(&position, Color::Black));
+        assert!(generate_all_legal_moves(&position, Color::Black).is_empty());
+
+        let last = explanation.plan.last().unwrap();
+        assert!(last.key.starts_with("planMate"));
+        assert!(explanation
+            .plan
+            .iter()
+            .any(|step| step.key == "planConfineKing"));
+
+        // The defender to move gets the same plan, from the winner's side
+        let after = apply_move(&board, &explanation.line[0]);
+        let defended = explain_with(&after, Color::Black, probe).unwrap();
+        assert_eq!(defended.winner, Color::White);
+        assert_eq!(defended.dtm, explanation.dtm - 1);
+    }
+
+    #[test]
+    fn test_explain_draw() {
+        let tablebase = generate_tablebase(&TablebaseConfig {
+            stronger_side: vec![],
+            weaker_side: vec![],
+            name: "KvK".to_string(),
+        });
+        let probe = |board: &BoardState, side| tablebase.probe(board, side);
+        assert!(explain_with(&create_kvk_position(), Color::White, probe).is_none());
+    }
+
+    #[test]
+    fn test_symmetries_depend_on_pieces() {
+        let layout = |pieces: Vec<PieceType>| {
+            TablebaseLayout::new(&TablebaseConfig {
+                stronger_side: pieces,
+                weaker_side: vec![],
+                name: String::new(),
+            })
+        };
+        assert_eq!(layout(vec![]).symmetry_count(), 12);
+        assert_eq!(layout(vec![PieceType::Queen]).symmetry_count(), 12);
+        assert_eq!(layout(vec![PieceType::Chariot]).symmetry_count(), 4);
+        assert_eq!(layout(vec![PieceType::Lance]).symmetry_count(), 2);
+        assert_eq!(layout(vec![PieceType::Pawn]).symmetry_count(), 1);
+
+        for s in 0..SYMMETRY_COUNT {
+            let cell = HexCoord::new(2, -3);
+            assert_eq!(invert_symmetry(s, apply_symmetry(s, cell)), cell);
+        }
+    }
+
+    #[test]
+    fn test_index_round_trip() {
+        let layout = get_layout(&kqvk_config());
+        let positions = generate_all_positions(&kqvk_config());
+        // Folding by symmetry leaves far fewer slots than raw placements
+        assert!(layout.size() < 61 * 61 * 61 * 2 / 8);
+        for (board, side) in positions.iter().step_by(97) {
+            let position = layout.index_of(board, *side).unwrap();
+            assert_eq!(position.symmetry, 0);
+            let (decoded, decoded_side) = layout.decode(position.index).unwrap();
+            assert_eq!(&decoded, board);
+            assert_eq!(decoded_side, *side);
+        }
+    }
+
+    #[test]
+    fn test_symmetric_positions_share_entries() {
+        let tablebase = kqvk_tablebase();
+        assert!(tablebase.metadata.win_count > 0);
+
+        // Move the black king off the queen's line so white may be to move
+        let mut board = create_kqvk_position();
+        let king = board.remove(&HexCoord::new(3, 0).to_key()).unwrap();
+        board.insert(HexCoord::new(3, -1).to_key(), king);
+        let entry = tablebase.probe(&board, Color::White).unwrap();
+        assert_eq!(entry.wdl, WDLOutcome::Win);
+
+        // Rotated copy, and the mirror image with black holding the queen
+        for (symmetry, swap) in [(2, false), (7, false), (HALF_TURN, true)] {
+            let mut transformed = BoardState::new();
+            for (key, piece) in &board {
+                let cell = apply_symmetry(symmetry, HexCoord::from_key(key).unwrap());
+                let color = if swap {
+                    piece.color.opposite()
+                } else {
+                    piece.color
+                };
+                transformed.insert(cell.to_key(), Piece::new(piece.piece_type, color));
+            }
+            let side = if swap { Color::Black } else { Color::White };
+            let other = tablebase.probe(&transformed, side).unwrap();
+            assert_eq!(other.wdl, entry.wdl);
+            assert_eq!(other.dtm, entry.dtm);
+
+            // The best move is mapped onto the probed board
+            let mv = other.best_move.unwrap();
+            let from = HexCoord::new(mv.from_q, mv.from_r);
+            let to = HexCoord::new(mv.to_q, mv.to_r);
+            let piece = transformed.get(&from.to_key()).unwrap();
+            assert_eq!(piece.color, side);
+            let legal = generate_all_legal_moves(&transformed, side);
+            assert!(legal.iter().any(|m| m.from == from && m.to == to));
+        }
+    }
+
+    #[test]
+    fn test_binary_round_trip() {
+        let tablebase = kqvk_tablebase();
+        let bytes = export_tablebase_to_bytes(tablebase);
+        assert!(bytes.len() < export_tablebase_to_json(tablebase).len());
+
+        let view = TablebaseView::parse(&bytes).unwrap();
+        let board = create_kqvk_position();
+        assert_eq!(
+            view.probe(&board, Color::Black).map(|e| (e.wdl, e.dtm)),
+            tablebase
+                .probe(&board, Color::Black)
+                .map(|e| (e.wdl, e.dtm))
+        );
+
+        let restored = import_tablebase_from_bytes(&bytes).unwrap();
+        assert_eq!(restored.entries, tablebase.entries);
+        assert_eq!(restored.size, tablebase.size);
+        assert!(TablebaseView::parse(&bytes[..bytes.len() - 1]).is_none());
+        assert!(TablebaseView::parse(b"nope").is_none());
+    }
+
+    #[test]
+    fn test_incremental_build_resumes() {
+        let resume = |builder: &TablebaseBuilder| {
+            let json = serde_json::to_string(builder).unwrap();
+            serde_json::from_str::<TablebaseBuilder>(&json).unwrap()
+        };
+
+        let mut builder = TablebaseBuilder::new(&kqvk_config());
+        assert!(!builder.step_units(builder.index_size() / 2));
+        builder = resume(&builder);
+        while !matches!(builder.phase(), BuildPhase::Retrograde { iteration: 3, .. }) {
+            assert!(!builder.step_units(1000));
+        }
+        builder = resume(&builder);
+        while !builder.step_units(1000) {}
+
+        let expected = kqvk_tablebase();
+        let tablebase = builder.into_tablebase().unwrap();
+        assert_eq!(tablebase.entries, expected.entries);
+        assert_eq!(tablebase.size, expected.size);
+        assert_eq!(tablebase.metadata.win_count, expected.metadata.win_count);
+    }
+
+    #[test]
+    fn test_cancelled_build_resumes() {
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        let mut builder = generate_tablebase_cancellable(&kqvk_config(), &cancel);
+        assert!(!builder.is_done());
+        assert!(!builder.step_units(1000));
+        assert!(matches!(
+            builder.phase(),
+            BuildPhase::Enumerate { next_index: 0 }
+        ));
+
+        cancel.reset();
+        while !builder.step_units(100_000) {}
+        assert_eq!(
+            builder.into_tablebase().unwrap().entries,
+            kqvk_tablebase().entries
+        );
+    }
+
+    #[test]
+    fn test_build_queue() {
+        let mut queue = TablebaseBuildQueue::new(&["KvK", "nonsense"]);
+        let mut status = queue.status();
+        assert!(!status.done);
+        for _ in 0..1000 {
+            status = queue.step(50);
+            if status.done {
+                break;
+            }
+        }
+        assert!(status.done);
+        assert!(status.pending.is_empty());
+        assert!(get_tablebase("KvK").is_some());
+
+        let restored = TablebaseBuildQueue::load(&queue.save().unwrap()).unwrap();
+        assert!(restored.is_done());
+        assert_eq!(restored.completed, queue.completed);
+        assert!(TablebaseBuildQueue::load("{").is_none());
+    }
+
+    #[test]
+    fn test_tablebase_dependencies() {
+        let kpvk = parse_configuration("KPvK").unwrap();
+        assert_eq!(
+            tablebase_dependencies(&kpvk),
+            vec!["KvK", "KQvK", "KCvK", "KLvK", "KNvK"]
+        );
+        let kqvk = parse_configuration("KQvK").unwrap();
+        assert_eq!(tablebase_dependencies(&kqvk), vec!["KvK"]);
+        assert!(tablebase_dependencies(&parse_configuration("KvK").unwrap()).is_empty());
+    }
+
+    #[test]
+    fn test_pawn_and_promoted_lance_slots() {
+        let layout = get_layout(&parse_configuration("KPvK").unwrap());
+        let mut board = create_kvk_position();
+        board.insert(
+            HexCoord::new(2, -3).to_key(),
+            Piece::new(PieceType::Pawn, Color::White),
+        );
+        let index = layout.index_of(&board, Color::White).unwrap().index;
+        assert!(canonical_position(&layout, index).is_some());
+
+        // A pawn on its promotion row would already have promoted
+        board.remove(&HexCoord::new(2, -3).to_key());
+        board.insert(
+            HexCoord::new(2, -4).to_key(),
+            Piece::new(PieceType::Pawn, Color::White),
+        );
+        let index = layout.index_of(&board, Color::White).unwrap().index;
+        assert!(canonical_position(&layout, index).is_none());
+
+        // A lance promoted from a pawn has no variant and moves as lance B
+        let layout = get_layout(&parse_configuration("KLvK").unwrap());
+        let lance_at = |piece: Piece| {
+            let mut board = create_kvk_position();
+            board.insert(HexCoord::new(2, -4).to_key(), piece);
+            layout.index_of(&board, Color::Black).unwrap().index
+        };
+        assert_eq!(
+            lance_at(Piece::new(PieceType::Lance, Color::White)),
+            lance_at(Piece::lance(Color::White, LanceVariant::B))
+        );
+    }
+
+    #[test]
+    fn test_promotion_crosses_into_subtable() {
+        let kqvk = kqvk_tablebase();
+        let kpvk = parse_configuration("KPvK").unwrap();
+        let layout = get_layout(&kpvk);
+        let mut builder = TablebaseBuilder::new(&kpvk);
+        builder.subtables = Some(HashMap::from([(kqvk.name.clone(), kqvk.clone())]));
+
+        let mut board = BoardState::new();
+        for (cell, piece) in [
+            ((0, 0), Piece::new(PieceType::King, Color::White)),
+            ((0, -3), Piece::new(PieceType::Pawn, Color::White)),
+            ((-4, 4), Piece::new(PieceType::King, Color::Black)),
+        ] {
+            board.insert(HexCoord::new(cell.0, cell.1).to_key(), piece);
+        }
+        let mut promoted = board.clone();
+        promoted.remove(&HexCoord::new(0, -3).to_key());
+        promoted.insert(
+            HexCoord::new(0, -4).to_key(),
+            Piece::new(PieceType::Queen, Color::White),
+        );
+        let after = kqvk.probe(&promoted, Color::Black).unwrap();
+        assert_eq!(after.wdl, WDLOutcome::Loss);
+
+        // Only the queen promotion reaches a known result, and it wins
+        let index = layout.index_of(&board, Color::White).unwrap().index;
+        builder.enumerate(&layout, index);
+        let entry = builder.resolve(&layout, 0).unwrap();
+        assert_eq!(entry.wdl, WDLOutcome::Win);
+        assert_eq!(entry.dtm, after.dtm + 1);
+        assert_eq!(entry.best_move.unwrap().promotion, Some(PieceType::Queen));
+    }
+
+    #[test]
+    fn test_embedded_tablebases_parse() {
+        // The feature embeds at least the shipped KvK
+        #[cfg(feature = "embedded-tablebases")]
+        assert!(!embedded_tablebases().is_empty());
+        for (name, bytes) in embedded_tablebases() {
+            let view = TablebaseView::parse(bytes).unwrap();
+            assert_eq!(view.name, *name);
+        }
+        assert!(load_embedded_tablebases() <= embedded_tablebases().len());
+    }
 }
@@ -0,0 +1,240 @@
+//! Underchex Tactics Suite
+//!
+//! A built-in suite of tactical positions with their expected best moves, in
+//! the manner of the "Win at Chess" test suite, and a runner that scores the
+//! engine on it. Search changes such as pruning and extensions must keep
+//! every position solved at [`DEFAULT_TACTICS_DEPTH`].
+
+use serde::{Deserialize, Serialize};
+
+use crate::ai::{find_best_move_iterative, TranspositionTable};
+use crate::notation::parse_hexfen;
+use crate::pieces::get_type_definition;
+use crate::types::{HexCoord, Move, PieceType, Variant};
+
+/// Search depth the suite is expected to pass at.
+pub const DEFAULT_TACTICS_DEPTH: i32 = 4;
+
+/// Entries of the transposition table each position gets.
+const TACTICS_TT_SIZE: usize = 100_000;
+
+// ============================================================================
+// Positions
+// ============================================================================
+
+/// A move a tactical position expects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExpectedMove {
+    pub from: (i32, i32),
+    pub to: (i32, i32),
+    pub promotion: Option<PieceType>,
+}
+
+const fn expect(from: (i32, i32), to: (i32, i32)) -> ExpectedMove {
+    ExpectedMove {
+        from,
+        to,
+        promotion: None,
+    }
+}
+
+impl ExpectedMove {
+    fn matches(&self, mv: &Move) -> bool {
+        mv.from == HexCoord::new(self.from.0, self.from.1)
+            && mv.to == HexCoord::new(self.to.0, self.to.1)
+            && mv.promotion == self.promotion
+    }
+}
+
+/// A position of the suite.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TacticalPosition {
+    pub id: &'static str,
+    pub theme: &'static str,
+    /// Position in HexFEN, including the side to move
+    pub fen: &'static str,
+    /// Moves that solve it; any one counts
+    pub best_moves: &'static [ExpectedMove],
+}
+
+/// The suite, easiest first.
+pub const TACTICS_SUITE: [TacticalPosition; 7] = [
+    TacticalPosition {
+        id: "hanging-queen",
+        theme: "capture",
+        fen: "4k4/6p2/9/5q3/9/4N4/9/1P7/4K4 w",
+        best_moves: &[expect((0, 1), (1, -1))],
+    },
+    TacticalPosition {
+        id: "mate-in-one",
+        theme: "mate",
+        fen: "4kpQ2/3p5/9/9/9/9/9/9/4K4 w",
+        best_moves: &[expect((2, -4), (0, -2))],
+    },
+    TacticalPosition {
+        id: "knight-fork",
+        theme: "fork",
+        fen: "5k3/7p1/9/2q2N3/9/9/9/1P7/4K4 w",
+        best_moves: &[expect((1, -1), (0, -2))],
+    },
+    TacticalPosition {
+        id: "knight-fork-black",
+        theme: "fork",
+        fen: "4k4/7p1/9/9/9/3n2Q2/9/1P7/3K5 b",
+        best_moves: &[expect((-1, 1), (0, 2))],
+    },
+    TacticalPosition {
+        id: "promotion",
+        theme: "promotion",
+        fen: "9/5P3/9/8c/k8/9/2p6/9/4K4 w",
+        best_moves: &[ExpectedMove {
+            from: (1, -3),
+            to: (1, -4),
+            promotion: Some(PieceType::Queen),
+        }],
+    },
+    TacticalPosition {
+        id: "queen-skewer",
+        theme: "skewer",
+        fen: "4q4/9/4k4/9/9/9/6Q2/1P7/3K5 w",
+        best_moves: &[expect((2, 2), (0, 2))],
+    },
+    TacticalPosition {
+        id: "mate-in-two",
+        theme: "mate",
+        fen: "8k/9/6K2/7p1/9/9/9/9/3Q5 w",
+        best_moves: &[expect((-1, 4), (-1, -3))],
+    },
+];
+
+// ============================================================================
+// Runner
+// ============================================================================
+
+/// How the engine did on one position.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TacticResult {
+    pub id: String,
+    pub theme: String,
+    pub solved: bool,
+    /// The engine's move as "q,r>q,r" (with "=Q" and the like for a
+    /// promotion), if it found one
+    pub found: Option<String>,
+    pub score: i32,
+    pub nodes: u64,
+}
+
+/// How the engine did on the suite.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SuiteReport {
+    pub depth: i32,
+    pub positions: usize,
+    pub solved: usize,
+    pub nodes: u64,
+    pub results: Vec<TacticResult>,
+}
+
+impl SuiteReport {
+    /// Ids of the positions not solved.
+    pub fn failures(&self) -> Vec<&str> {
+        self.results
+            .iter()
+            .filter(|result| !result.solved)
+            .map(|result| result.id.as_str())
+            .collect()
+    }
+}
+
+fn coordinate_move(mv: &Move) -> String {
+    let promotion = mv.promotion.map_or_else(String::new, |piece_type| {
+        format!("={}", get_type_definition(piece_type).symbol)
+    });
+    format!("{}>{}{}", mv.from.to_key(), mv.to.to_key(), promotion)
+}
+
+/// Search one position to `depth` with a fresh transposition table under the
+/// current engine options.
+pub fn run_tactic(position: &TacticalPosition, depth: i32) -> TacticResult {
+    let mut result = TacticResult {
+        id: position.id.to_string(),
+        theme: position.theme.to_string(),
+        solved: false,
+        found: None,
+        score: 0,
+        nodes: 0,
+    };
+    let Some((board, side)) = parse_hexfen(position.fen) else {
+        return result;
+    };
+    let mut tt = TranspositionTable::new(TACTICS_TT_SIZE);
+    let search = find_best_move_iterative(
+        &board,
+        side,
+        depth,
+        u64::MAX,
+        &mut tt,
+        true,
+        &[],
+        Variant::Standard,
+    );
+    result.solved = search
+        .best_move
+        .as_ref()
+        .is_some_and(|mv| position.best_moves.iter().any(|best| best.matches(mv)));
+    result.found = search.best_move.as_ref().map(coordinate_move);
+    result.score = search.score;
+    result.nodes = search.stats.nodes_searched;
+    result
+}
+
+/// Run the whole suite at `depth`.
+pub fn run_tactics_suite(depth: i32) -> SuiteReport {
+    let results: Vec<TacticResult> = TACTICS_SUITE
+        .iter()
+        .map(|position| run_tactic(position, depth))
+        .collect();
+    SuiteReport {
+        depth,
+        positions: results.len(),
+        solved: results.iter().filter(|result| result.solved).count(),
+        nodes: results.iter().map(|result| result.nodes).sum(),
+        results,
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::generate_all_legal_moves;
+
+    #[test]
+    fn test_suite_positions_are_sound() {
+        for position in &TACTICS_SUITE {
+            let (board, side) = parse_hexfen(position.fen).expect(position.id);
+            let legal = generate_all_legal_moves(&board, side);
+            for best in position.best_moves {
+                assert!(
+                    legal.iter().any(|mv| best.matches(mv)),
+                    "{}: expected move is not legal",
+                    position.id
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_engine_solves_suite() {
+        let report = run_tactics_suite(DEFAULT_TACTICS_DEPTH);
+        assert_eq!(report.positions, TACTICS_SUITE.len());
+        assert!(report.failures().is_empty(), "{:?}", report.failures());
+        assert_eq!(report.solved, report.positions);
+        let promotion = report.results.iter().find(|r| r.id == "promotion").unwrap();
+        assert_eq!(promotion.found.as_deref(), Some("1,-3>1,-4=Q"));
+    }
+}
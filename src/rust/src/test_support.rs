@@ -0,0 +1,20 @@
+//! Fixtures shared by the unit tests.
+
+use crate::game::{create_new_game, make_move};
+use crate::types::{GameState, HexCoord};
+
+/// White's chariot walks into a capture on (2, 0), and Black takes it.
+pub(crate) const CHARIOT_BLUNDER: [[i32; 4]; 4] =
+    [[0, 2, 0, 1], [2, -3, 3, -2], [-2, 4, 2, 0], [3, -2, 2, 0]];
+
+/// Play a sequence of [from_q, from_r, to_q, to_r] moves from `state`.
+pub(crate) fn play_from(state: &GameState, moves: &[[i32; 4]]) -> GameState {
+    moves.iter().fold(state.clone(), |s, &[fq, fr, tq, tr]| {
+        make_move(&s, HexCoord::new(fq, fr), HexCoord::new(tq, tr)).unwrap()
+    })
+}
+
+/// Play a sequence of moves from the starting position.
+pub(crate) fn play(moves: &[[i32; 4]]) -> GameState {
+    play_from(&create_new_game(), moves)
+}
@@ -0,0 +1,243 @@
+//! Underchex Tracing
+//!
+//! Optional `tracing` instrumentation (the `trace` feature): spans for search
+//! iterations, tablebase generation and move validation, with events for their
+//! results. Without the feature the macros below expand to nothing.
+//!
+//! [`init_tracing`] installs a subscriber that writes each event, prefixed
+//! with the spans it happened in, to `console.log` under WASM and to stderr
+//! natively, and reports how long each span was entered.
+
+/// Enter a span until the end of the enclosing block.
+macro_rules! trace_span {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "trace")]
+        let _span = tracing::debug_span!($($arg)*).entered();
+    };
+}
+
+/// Emit a debug event.
+macro_rules! trace_event {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "trace")]
+        tracing::debug!($($arg)*);
+    };
+}
+
+pub(crate) use trace_event;
+pub(crate) use trace_span;
+
+/// Install the console subscriber, showing events up to `level` ("error",
+/// "warn", "info", "debug" or "trace"). Returns false for an unknown level,
+/// if a subscriber is already installed, or without the `trace` feature.
+pub fn init_tracing(level: &str) -> bool {
+    #[cfg(feature = "trace")]
+    {
+        match level.parse() {
+            Ok(max_level) => {
+                tracing::subscriber::set_global_default(console::ConsoleSubscriber::new(max_level))
+                    .is_ok()
+            }
+            Err(_) => false,
+        }
+    }
+    #[cfg(not(feature = "trace"))]
+    {
+        let _ = level;
+        false
+    }
+}
+
+// ============================================================================
+// Console Subscriber
+// ============================================================================
+
+#[cfg(feature = "trace")]
+mod console {
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::fmt::{Debug, Write};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Mutex;
+
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Level, Metadata, Subscriber};
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen::prelude::wasm_bindgen]
+    extern "C" {
+        #[wasm_bindgen(js_namespace = console, js_name = log)]
+        fn console_log(line: &str);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn console_log(line: &str) {
+        eprintln!("{}", line);
+    }
+
+    fn now_ms() -> i64 {
+        chrono::Utc::now().timestamp_millis()
+    }
+
+    /// Formats fields as " message key=value ...".
+    struct FieldWriter(String);
+
+    impl Visit for FieldWriter {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            if field.name() == "message" {
+                let _ = write!(self.0, " {}", value);
+            } else {
+                let _ = write!(self.0, " {}={}", field.name(), value);
+            }
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            if field.name() == "message" {
+                let _ = write!(self.0, " {:?}", value);
+            } else {
+                let _ = write!(self.0, " {}={:?}", field.name(), value);
+            }
+        }
+    }
+
+    struct SpanData {
+        /// Name and fields
+        label: String,
+        entered_at: i64,
+    }
+
+    thread_local! {
+        /// Spans entered on this thread, innermost last
+        static STACK: RefCell<Vec<u64>> = const { RefCell::new(Vec::new()) };
+    }
+
+    pub struct ConsoleSubscriber {
+        max_level: Level,
+        next_id: AtomicU64,
+        spans: Mutex<HashMap<u64, SpanData>>,
+    }
+
+    impl ConsoleSubscriber {
+        pub fn new(max_level: Level) -> Self {
+            Self {
+                max_level,
+                next_id: AtomicU64::new(1),
+                spans: Mutex::new(HashMap::new()),
+            }
+        }
+
+        fn label(&self, id: u64) -> Option<String> {
+            let spans = self.spans.lock().ok()?;
+            spans.get(&id).map(|span| span.label.clone())
+        }
+    }
+
+    impl Subscriber for ConsoleSubscriber {
+        fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+            *metadata.level() <= self.max_level
+        }
+
+        fn new_span(&self, attributes: &Attributes<'_>) -> Id {
+            let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+            let mut fields = FieldWriter(attributes.metadata().name().to_string());
+            attributes.record(&mut fields);
+            if let Ok(mut spans) = self.spans.lock() {
+                spans.insert(
+                    id,
+                    SpanData {
+                        label: fields.0,
+                        entered_at: 0,
+                    },
+                );
+            }
+            Id::from_u64(id)
+        }
+
+        fn record(&self, span: &Id, values: &Record<'_>) {
+            let mut fields = FieldWriter(String::new());
+            values.record(&mut fields);
+            if let Ok(mut spans) = self.spans.lock() {
+                if let Some(span) = spans.get_mut(&span.into_u64()) {
+                    span.label.push_str(&fields.0);
+                }
+            }
+        }
+
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut fields = FieldWriter(String::new());
+            event.record(&mut fields);
+            let path: Vec<String> = STACK
+                .with(|stack| stack.borrow().clone())
+                .into_iter()
+                .filter_map(|id| self.label(id))
+                .collect();
+            let prefix = if path.is_empty() {
+                String::new()
+            } else {
+                format!(" [{}]", path.join(" > "))
+            };
+            console_log(&format!(
+                "{}{}{}",
+                event.metadata().level(),
+                prefix,
+                fields.0
+            ));
+        }
+
+        fn enter(&self, span: &Id) {
+            let id = span.into_u64();
+            STACK.with(|stack| stack.borrow_mut().push(id));
+            if let Ok(mut spans) = self.spans.lock() {
+                if let Some(span) = spans.get_mut(&id) {
+                    span.entered_at = now_ms();
+                }
+            }
+        }
+
+        fn exit(&self, span: &Id) {
+            let id = span.into_u64();
+            STACK.with(|stack| {
+                let mut stack = stack.borrow_mut();
+                if let Some(position) = stack.iter().rposition(|&entered| entered == id) {
+                    stack.remove(position);
+                }
+            });
+            let finished = self.spans.lock().ok().and_then(|spans| {
+                spans
+                    .get(&id)
+                    .map(|span| (span.label.clone(), now_ms() - span.entered_at))
+            });
+            if let Some((label, elapsed)) = finished {
+                console_log(&format!("{} {} ms", label, elapsed));
+            }
+        }
+
+        fn try_close(&self, id: Id) -> bool {
+            if let Ok(mut spans) = self.spans.lock() {
+                spans.remove(&id.into_u64());
+            }
+            true
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_init_tracing() {
+        assert!(!init_tracing("loud"));
+        // Succeeds once with the feature; never without it
+        let installed = init_tracing("warn");
+        assert_eq!(installed, cfg!(feature = "trace"));
+        assert!(!init_tracing("warn"));
+    }
+}
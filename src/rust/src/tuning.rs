@@ -0,0 +1,261 @@
+//! Underchex Move Ordering Tuning
+//!
+//! A fixed bench suite of positions, and a harness that tunes the move
+//! ordering weights (the `mvvLvaScale`, `killerBonus`, `countermoveBonus`
+//! and `historyScale` engine options) against it. Better ordering finds
+//! cutoffs sooner, so the harness keeps whichever weights search the suite in
+//! the fewest nodes. Weights are tried with
+//! [`with_options`](crate::options::with_options), so the process-wide
+//! options never change; the caller sets the tuned weights if it wants them.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ai::{find_best_move_iterative, SearchStats, TranspositionTable};
+use crate::game::create_new_game;
+use crate::moves::generate_all_legal_moves;
+use crate::options::{list_options, with_options, EngineOption};
+use crate::rng::SeededRng;
+use crate::types::{BoardState, Color, Variant};
+use crate::zobrist::random_game_positions;
+
+/// Positions in the default bench suite.
+pub const DEFAULT_BENCH_POSITIONS: usize = 16;
+
+/// Search depth of the default bench.
+pub const DEFAULT_BENCH_DEPTH: i32 = 3;
+
+/// The options the harness tunes, with their first step size.
+const TUNED_OPTIONS: [(EngineOption, i32); 4] = [
+    (EngineOption::MvvLvaScale, 8),
+    (EngineOption::KillerBonus, 4000),
+    (EngineOption::CountermoveBonus, 4000),
+    (EngineOption::HistoryScale, 200),
+];
+
+/// Entries of the transposition table each bench position gets.
+const BENCH_TT_SIZE: usize = 50_000;
+
+/// The bench suite: `count` positions spread over seeded random games from
+/// the starting position, skipping finished ones. A seed always gives the
+/// same suite.
+pub fn bench_suite(count: usize, seed: u64) -> Vec<(BoardState, Color)> {
+    let mut rng = SeededRng::new(seed);
+    let start = create_new_game();
+    let mut suite = Vec::new();
+    // Give up on seeds whose games keep ending at once
+    for _ in 0..count * 4 {
+        if suite.len() >= count {
+            break;
+        }
+        let plies = 6 + rng.next_below(30);
+        let positions = random_game_positions(&start.board, start.turn, plies, &mut rng);
+        if let Some((board, turn)) = positions.last() {
+            if !generate_all_legal_moves(board, *turn).is_empty() {
+                suite.push((board.clone(), *turn));
+            }
+        }
+    }
+    suite
+}
+
+/// Search counters over a bench suite.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BenchReport {
+    pub positions: usize,
+    pub depth: i32,
+    pub nodes: u64,
+    pub beta_cutoffs: u64,
+    pub first_move_cutoffs: u64,
+    /// Share of cutoffs made by the first move tried (0 without cutoffs)
+    pub first_move_cutoff_rate: f64,
+    /// Average move-list position of a cutoff move
+    pub average_cutoff_index: f64,
+    /// Cutoffs made by the countermove of the previous move
+    pub countermove_cutoffs: u64,
+    /// Nodes searched with the countermove bonus set to 0, to compare with
+    /// `nodes` (None where only the current options were benched)
+    pub nodes_without_countermoves: Option<u64>,
+}
+
+impl BenchReport {
+    /// Share of the nodes saved by the countermove bonus, if measured.
+    pub fn countermove_node_cut(&self) -> Option<f64> {
+        self.nodes_without_countermoves
+            .filter(|&without| without > 0)
+            .map(|without| 1.0 - self.nodes as f64 / without as f64)
+    }
+}
+
+/// Search every position of a suite to `depth` (by iterative deepening, each
+/// with a fresh transposition table) under the current options, then again
+/// without the countermove bonus to measure what it saves.
+pub fn run_bench(suite: &[(BoardState, Color)], depth: i32) -> BenchReport {
+    let mut report = bench_current(suite, depth);
+    report.nodes_without_countermoves = Some(if EngineOption::CountermoveBonus.get() == 0 {
+        report.nodes
+    } else {
+        with_options(&[(EngineOption::CountermoveBonus, 0)], || {
+            bench_current(suite, depth)
+        })
+        .nodes
+    });
+    report
+}
+
+/// Bench a suite under the current options only.
+fn bench_current(suite: &[(BoardState, Color)], depth: i32) -> BenchReport {
+    let mut totals = SearchStats::default();
+    for (board, turn) in suite {
+        let mut tt = TranspositionTable::new(BENCH_TT_SIZE);
+        let result = find_best_move_iterative(
+            board,
+            *turn,
+            depth,
+            u64::MAX,
+            &mut tt,
+            true,
+            &[],
+            Variant::Standard,
+        );
+        totals.accumulate(&result.stats);
+    }
+    let average_cutoff_index = if totals.beta_cutoffs > 0 {
+        totals.cutoff_index_sum as f64 / totals.beta_cutoffs as f64
+    } else {
+        0.0
+    };
+    BenchReport {
+        positions: suite.len(),
+        depth,
+        nodes: totals.nodes_searched,
+        beta_cutoffs: totals.beta_cutoffs,
+        first_move_cutoffs: totals.first_move_cutoffs,
+        first_move_cutoff_rate: totals.first_move_cutoff_rate().unwrap_or(0.0),
+        average_cutoff_index,
+        countermove_cutoffs: totals.countermove_cutoffs,
+        nodes_without_countermoves: None,
+    }
+}
+
+/// Outcome of tuning.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TuningResult {
+    /// The tuned options and their best values
+    pub weights: Vec<(String, i32)>,
+    /// The bench before and after tuning
+    pub baseline: BenchReport,
+    pub tuned: BenchReport,
+}
+
+/// Tune the ordering weights on a suite by coordinate descent, starting from
+/// their current values: each round tries moving each weight up and down by
+/// its step, keeps any change that searches fewer nodes, then halves the
+/// steps. The best weights are returned, not set.
+pub fn tune_move_ordering(
+    suite: &[(BoardState, Color)],
+    depth: i32,
+    rounds: usize,
+) -> TuningResult {
+    let bounds = |option: EngineOption| {
+        list_options()
+            .into_iter()
+            .find(|info| info.name == option.name())
+            .map_or((0, 0), |info| (info.min, info.max))
+    };
+    let baseline = run_bench(suite, depth);
+    let mut best_nodes = baseline.nodes;
+    let mut weights = TUNED_OPTIONS.map(|(option, _)| (option, option.get()));
+    let mut steps = TUNED_OPTIONS.map(|(_, step)| step);
+
+    for _ in 0..rounds {
+        for (index, step) in steps.iter_mut().enumerate() {
+            let Some(&(option, current)) = weights.get(index) else {
+                continue;
+            };
+            let (min, max) = bounds(option);
+            for candidate in [current + *step, current - *step] {
+                let candidate = candidate.clamp(min, max);
+                if candidate == current {
+                    continue;
+                }
+                let mut trial = weights;
+                if let Some(weight) = trial.get_mut(index) {
+                    weight.1 = candidate;
+                }
+                let nodes = with_options(&trial, || bench_current(suite, depth)).nodes;
+                if nodes < best_nodes {
+                    best_nodes = nodes;
+                    weights = trial;
+                    break;
+                }
+            }
+            *step = (*step / 2).max(1);
+        }
+    }
+
+    TuningResult {
+        weights: weights
+            .iter()
+            .map(|(option, value)| (option.name().to_string(), *value))
+            .collect(),
+        baseline,
+        // Bench the best weights again to measure their countermove savings
+        tuned: with_options(&weights, || run_bench(suite, depth)),
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bench_suite_is_repeatable() {
+        let suite = bench_suite(4, 7);
+        assert_eq!(suite.len(), 4);
+        assert_eq!(bench_suite(4, 7), suite);
+        assert_ne!(bench_suite(4, 8), suite);
+    }
+
+    #[test]
+    fn test_run_bench_counts_cutoffs() {
+        let report = run_bench(&bench_suite(2, 1), 2);
+        assert_eq!(report.positions, 2);
+        assert!(report.nodes > 0);
+        assert!(report.first_move_cutoffs <= report.beta_cutoffs);
+        assert!((0.0..=1.0).contains(&report.first_move_cutoff_rate));
+        assert!(report.nodes_without_countermoves.is_some());
+    }
+
+    #[test]
+    fn test_tuning_without_rounds_keeps_weights() {
+        let suite = bench_suite(1, 3);
+        let result = tune_move_ordering(&suite, 2, 0);
+        assert_eq!(result.tuned, result.baseline);
+        let names: Vec<&str> = result
+            .weights
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "mvvLvaScale",
+                "killerBonus",
+                "countermoveBonus",
+                "historyScale"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tuning_leaves_options_alone() {
+        let before = TUNED_OPTIONS.map(|(option, _)| option.get());
+        let result = tune_move_ordering(&bench_suite(1, 3), 2, 1);
+        assert!(result.tuned.nodes <= result.baseline.nodes);
+        assert_eq!(TUNED_OPTIONS.map(|(option, _)| option.get()), before);
+    }
+}
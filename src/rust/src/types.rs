@@ -2,6 +2,7 @@
 //!
 //! Signed-by: agent #21 claude-sonnet-4 via opencode 20260122T06:31:01
 
+use crate::pieces::get_piece_definition;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use wasm_bindgen::prelude::*;
@@ -38,13 +39,97 @@ impl HexCoord {
 
     /// Parse from string "q,r"
     pub fn from_key(key: &str) -> Option<Self> {
-        let parts: Vec<&str> = key.split(',').collect();
-        if parts.len() != 2 {
-            return None;
+        let (q, r) = key.split_once(',')?;
+        Some(Self {
+            q: q.parse().ok()?,
+            r: r.parse().ok()?,
+        })
+    }
+}
+
+// ============================================================================
+// Coordinate Systems
+// ============================================================================
+
+/// Offset (column/row) layouts, as named by Red Blob Games. Board hexes are
+/// flat-topped, so the column layouts (`OddQ`, `EvenQ`) match the board;
+/// the row layouts are for pointy-top renderers that rotate the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OffsetLayout {
+    /// Odd columns shoved down
+    OddQ,
+    /// Even columns shoved down
+    EvenQ,
+    /// Odd rows shoved right
+    OddR,
+    /// Even rows shoved right
+    EvenR,
+}
+
+impl OffsetLayout {
+    /// Parse a layout name ("oddQ", "evenQ", "oddR" or "evenR").
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "oddQ" => Some(OffsetLayout::OddQ),
+            "evenQ" => Some(OffsetLayout::EvenQ),
+            "oddR" => Some(OffsetLayout::OddR),
+            "evenR" => Some(OffsetLayout::EvenR),
+            _ => None,
+        }
+    }
+}
+
+/// Offset coordinates of a cell. The board center is at (0, 0).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct OffsetCoord {
+    pub col: i32,
+    pub row: i32,
+}
+
+impl HexCoord {
+    /// Cube coordinates (q, r, s).
+    pub fn to_cube(&self) -> (i32, i32, i32) {
+        (self.q, self.r, self.s())
+    }
+
+    /// From cube coordinates. Returns None unless q + r + s == 0.
+    pub fn from_cube(q: i32, r: i32, s: i32) -> Option<Self> {
+        (q.checked_add(r)?.checked_add(s)? == 0).then_some(Self { q, r })
+    }
+
+    /// Offset coordinates in the given layout.
+    pub fn to_offset(&self, layout: OffsetLayout) -> OffsetCoord {
+        let (q, r) = (self.q, self.r);
+        match layout {
+            OffsetLayout::OddQ => OffsetCoord {
+                col: q,
+                row: r + (q - (q & 1)) / 2,
+            },
+            OffsetLayout::EvenQ => OffsetCoord {
+                col: q,
+                row: r + (q + (q & 1)) / 2,
+            },
+            OffsetLayout::OddR => OffsetCoord {
+                col: q + (r - (r & 1)) / 2,
+                row: r,
+            },
+            OffsetLayout::EvenR => OffsetCoord {
+                col: q + (r + (r & 1)) / 2,
+                row: r,
+            },
+        }
+    }
+
+    /// From offset coordinates in the given layout.
+    pub fn from_offset(offset: OffsetCoord, layout: OffsetLayout) -> Self {
+        let OffsetCoord { col, row } = offset;
+        match layout {
+            OffsetLayout::OddQ => Self::new(col, row - (col - (col & 1)) / 2),
+            OffsetLayout::EvenQ => Self::new(col, row - (col + (col & 1)) / 2),
+            OffsetLayout::OddR => Self::new(col - (row - (row & 1)) / 2, row),
+            OffsetLayout::EvenR => Self::new(col - (row + (row & 1)) / 2, row),
         }
-        let q = parts[0].parse().ok()?;
-        let r = parts[1].parse().ok()?;
-        Some(Self { q, r })
     }
 }
 
@@ -128,6 +213,22 @@ pub enum PieceType {
     Knight,
     Lance,
     Chariot,
+    /// Neutral blocker (boulder variant): never moves by itself, cannot be
+    /// captured and blocks all movement through its cell
+    Boulder,
+    /// Fairy piece: one step in any direction (a non-royal king)
+    Wazir,
+    /// Fairy piece: one step along the diagonals (NE, NW, SE, SW)
+    Ferz,
+    /// Fairy piece: leaps exactly two cells in any direction
+    Dabbaba,
+    /// Moves like a lance without capturing; captures by jumping exactly one
+    /// intervening piece (cannons variant)
+    Cannon,
+    /// Knight promoted in the shogi variant: also steps one cell in any direction
+    PromotedKnight,
+    /// Lance promoted in the shogi variant: also steps one cell in any direction
+    PromotedLance,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -175,25 +276,29 @@ impl Piece {
         }
     }
 
-    /// Get directions this piece can move in (for sliders)
+    /// Create the neutral boulder. Its color is meaningless and always white.
+    pub fn boulder() -> Self {
+        Self::new(PieceType::Boulder, Color::White)
+    }
+
+    /// Check if this piece is the neutral boulder.
+    pub fn is_boulder(&self) -> bool {
+        self.piece_type == PieceType::Boulder
+    }
+
+    /// Get directions this piece can move in (slides, hops, or single steps
+    /// for stepping pieces)
     pub fn directions(&self) -> &'static [Direction] {
-        match self.piece_type {
-            PieceType::King | PieceType::Queen => Direction::all(),
-            PieceType::Chariot => Direction::diagonals(),
-            PieceType::Lance => match self.variant {
-                Some(LanceVariant::A) => Direction::lance_a(),
-                Some(LanceVariant::B) | None => Direction::lance_b(),
-            },
-            _ => &[],
-        }
+        let definition = get_piece_definition(self);
+        [definition.slides, definition.hops, definition.steps]
+            .into_iter()
+            .find(|dirs| !dirs.is_empty())
+            .unwrap_or(&[])
     }
 
     /// Check if this piece is a slider (can move multiple squares)
     pub fn is_slider(&self) -> bool {
-        matches!(
-            self.piece_type,
-            PieceType::Queen | PieceType::Lance | PieceType::Chariot
-        )
+        !get_piece_definition(self).slides.is_empty()
     }
 }
 
@@ -231,6 +336,12 @@ pub struct Move {
     pub piece: Piece,
     pub captured: Option<Piece>,
     pub promotion: Option<PieceType>,
+    /// Piece dropped from the hand onto `to` (`from` equals `to`)
+    #[serde(default)]
+    pub is_drop: bool,
+    /// Cell the boulder was placed on after this move (boulder variant)
+    #[serde(default)]
+    pub boulder: Option<HexCoord>,
 }
 
 impl Move {
@@ -241,6 +352,16 @@ impl Move {
             piece,
             captured: None,
             promotion: None,
+            is_drop: false,
+            boulder: None,
+        }
+    }
+
+    /// Create a drop of a piece from the hand onto an empty cell.
+    pub fn new_drop(piece: Piece, to: HexCoord) -> Self {
+        Self {
+            is_drop: true,
+            ..Self::new(piece, to, to)
         }
     }
 
@@ -253,6 +374,11 @@ impl Move {
         self.promotion = Some(promotion);
         self
     }
+
+    pub fn with_boulder(mut self, boulder: HexCoord) -> Self {
+        self.boulder = Some(boulder);
+        self
+    }
 }
 
 /// Valid promotion targets for pawns
@@ -270,17 +396,278 @@ pub const PROMOTION_TARGETS: &[PieceType] = &[
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum GameStatus {
     Ongoing,
-    Checkmate { winner: Color },
+    Checkmate {
+        winner: Color,
+    },
     Stalemate,
-    Draw { reason: String },
-    Resigned { winner: Color },
+    Draw {
+        reason: String,
+    },
+    Resigned {
+        winner: Color,
+    },
+    /// The loser's clock ran out
+    Timeout {
+        winner: Color,
+    },
+    /// Won by a variant-specific victory condition
+    VariantWin {
+        winner: Color,
+        reason: String,
+    },
+}
+
+// ============================================================================
+// Variants
+// ============================================================================
+
+/// Rule set a game is played under.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum Variant {
+    #[default]
+    Standard,
+    /// Captured pieces go to the capturer's hand and can be dropped on empty
+    /// cells instead of moving. `drop_mate` allows delivering mate by a drop.
+    Crazyhouse { drop_mate: bool },
+    /// Moving your king onto the central hex wins immediately.
+    KingOfTheHill,
+    /// No checks allowed; the first king to reach the far edge wins.
+    RacingKings,
+    /// After each move the mover places the neutral boulder on any empty
+    /// cell (other than where it stood), blocking all movement through it.
+    Boulder,
+    /// The standard rules with each side's knights replaced by cannons.
+    Cannons,
+    /// Knights and lances may promote on entering the far zone. With `drops`
+    /// captured pieces go to the hand (demoted) as in crazyhouse.
+    Shogi { drops: bool },
+}
+
+/// Every name [`Variant::from_name`] accepts.
+pub const VARIANT_NAMES: [&str; 9] = [
+    "standard",
+    "crazyhouse",
+    "crazyhouseNoDropMate",
+    "kingOfTheHill",
+    "racingKings",
+    "boulder",
+    "cannons",
+    "shogi",
+    "shogiNoDrops",
+];
+
+impl Variant {
+    /// Parse a variant name ("standard", "crazyhouse", "shogi", ...).
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "standard" => Some(Variant::Standard),
+            "crazyhouse" => Some(Variant::Crazyhouse { drop_mate: true }),
+            "crazyhouseNoDropMate" => Some(Variant::Crazyhouse { drop_mate: false }),
+            "kingOfTheHill" => Some(Variant::KingOfTheHill),
+            "racingKings" => Some(Variant::RacingKings),
+            "boulder" => Some(Variant::Boulder),
+            "cannons" => Some(Variant::Cannons),
+            "shogi" => Some(Variant::Shogi { drops: true }),
+            "shogiNoDrops" => Some(Variant::Shogi { drops: false }),
+            _ => None,
+        }
+    }
+
+    /// Check if pieces can be dropped from the hand.
+    pub fn has_drops(&self) -> bool {
+        matches!(
+            self,
+            Variant::Crazyhouse { .. } | Variant::Shogi { drops: true }
+        )
+    }
+
+    /// Check if knights and lances may promote in the far zone.
+    pub fn has_piece_promotion(&self) -> bool {
+        matches!(self, Variant::Shogi { .. })
+    }
+
+    /// Check if a drop may deliver checkmate.
+    pub fn allows_drop_mate(&self) -> bool {
+        match self {
+            Variant::Crazyhouse { drop_mate } => *drop_mate,
+            _ => true,
+        }
+    }
+}
+
+/// How a stalemate is scored. Communities differ: a draw as usual, a win or
+/// a loss for the side that delivers it, or a fraction of a point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum StalemateRule {
+    #[default]
+    Draw,
+    /// The stalemated side loses.
+    StalematerWins,
+    /// The stalemated side wins (the old English rule).
+    StalematerLoses,
+    /// The stalemating side scores `stalemater_quarters` quarter points (at
+    /// most 4) and the stalemated side the rest.
+    Fractional { stalemater_quarters: u8 },
+}
+
+/// Every name [`StalemateRule::from_name`] accepts.
+pub const STALEMATE_RULE_NAMES: [&str; 5] =
+    ["draw", "stalematerWins", "stalematerLoses", "1/4", "3/4"];
+
+impl StalemateRule {
+    /// Parse a rule name: "draw", "stalematerWins", "stalematerLoses", or
+    /// the stalemating side's share of the point as "1/4" or "3/4".
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "draw" => Some(StalemateRule::Draw),
+            "stalematerWins" => Some(StalemateRule::StalematerWins),
+            "stalematerLoses" => Some(StalemateRule::StalematerLoses),
+            "1/4" => Some(StalemateRule::Fractional {
+                stalemater_quarters: 1,
+            }),
+            "3/4" => Some(StalemateRule::Fractional {
+                stalemater_quarters: 3,
+            }),
+            _ => None,
+        }
+    }
+
+    /// The rule under which the stalemating side scores `quarters` quarter
+    /// points. None above 4.
+    pub fn from_quarters(quarters: u8) -> Option<Self> {
+        match quarters {
+            0 => Some(StalemateRule::StalematerLoses),
+            2 => Some(StalemateRule::Draw),
+            4 => Some(StalemateRule::StalematerWins),
+            1 | 3 => Some(StalemateRule::Fractional {
+                stalemater_quarters: quarters,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Quarter points the stalemating side scores, from 0 to 4.
+    pub fn stalemater_quarters(self) -> u8 {
+        match self {
+            StalemateRule::Draw => 2,
+            StalemateRule::StalematerWins => 4,
+            StalemateRule::StalematerLoses => 0,
+            StalemateRule::Fractional {
+                stalemater_quarters,
+            } => stalemater_quarters.min(4),
+        }
+    }
+}
+
+/// Optional ways to win besides checkmate, as in some historical variants.
+/// The default adds none.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct WinConditions {
+    /// Reducing the opponent to a bare king wins (shatranj)
+    #[serde(default)]
+    pub bare_king: bool,
+    /// Capturing every opposing piece of this type wins
+    #[serde(default)]
+    pub capture_all: Option<PieceType>,
+}
+
+impl WinConditions {
+    /// Whether the conditions can be played: kings and boulders cannot be
+    /// captured, so they cannot be the type to capture.
+    pub fn is_valid(self) -> bool {
+        !matches!(
+            self.capture_all,
+            Some(PieceType::King) | Some(PieceType::Boulder)
+        )
+    }
+}
+
+/// Pieces held in hand, by type.
+pub type Hand = HashMap<PieceType, u8>;
+
+/// Both players' hands.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hands {
+    pub white: Hand,
+    pub black: Hand,
+}
+
+impl Hands {
+    pub fn get(&self, color: Color) -> &Hand {
+        match color {
+            Color::White => &self.white,
+            Color::Black => &self.black,
+        }
+    }
+
+    pub fn get_mut(&mut self, color: Color) -> &mut Hand {
+        match color {
+            Color::White => &mut self.white,
+            Color::Black => &mut self.black,
+        }
+    }
+
+    /// Add a piece to a player's hand.
+    pub fn add(&mut self, color: Color, piece_type: PieceType) {
+        *self.get_mut(color).entry(piece_type).or_insert(0) += 1;
+    }
+
+    /// Take a piece from a player's hand. Returns false if none is held.
+    pub fn remove(&mut self, color: Color, piece_type: PieceType) -> bool {
+        let hand = self.get_mut(color);
+        match hand.get_mut(&piece_type) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                if *count == 0 {
+                    hand.remove(&piece_type);
+                }
+                true
+            }
+            _ => false,
+        }
+    }
 }
 
 // ============================================================================
 // Game State
 // ============================================================================
 
+/// Phase of the current player's turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TurnPhase {
+    #[default]
+    Move,
+    /// The move is made; the boulder must be placed before the turn ends
+    PlaceBoulder,
+}
+
+/// A position that occurred in a game, used for repetition detection.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PositionRecord {
+    /// Position key (board hash plus side to move)
+    pub key: String,
+    /// Zobrist hash of the position, compared before the key
+    #[serde(default)]
+    pub hash: u64,
+    pub side_to_move: Color,
+    /// Whether the side to move was in check
+    pub in_check: bool,
+}
+
+impl PositionRecord {
+    /// Whether two records are the same position: equal hashes, confirmed by
+    /// the exact key so a hash collision is never mistaken for a repetition.
+    /// Records restored from HexFEN carry only the hash (an empty key).
+    pub fn same_position(&self, other: &PositionRecord) -> bool {
+        self.hash == other.hash
+            && (self.key == other.key || self.key.is_empty() || other.key.is_empty())
+    }
+}
+
+/// Deserialization also accepts saves with a compressed history (see
+/// [`save_game_compressed`](crate::save::save_game_compressed)).
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "crate::save::StoredGameState")]
 pub struct GameState {
     pub board: BoardState,
     pub turn: Color,
@@ -288,4 +675,18 @@ pub struct GameState {
     pub half_move_clock: u32,
     pub history: Vec<Move>,
     pub status: GameStatus,
+    /// Every position reached so far, including the starting position
+    #[serde(default)]
+    pub position_history: Vec<PositionRecord>,
+    #[serde(default)]
+    pub variant: Variant,
+    /// Pieces in hand (drop variants only)
+    #[serde(default)]
+    pub hands: Hands,
+    #[serde(default)]
+    pub phase: TurnPhase,
+    #[serde(default)]
+    pub stalemate_rule: StalemateRule,
+    #[serde(default)]
+    pub win_conditions: WinConditions,
 }
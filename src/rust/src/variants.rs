@@ -0,0 +1,439 @@
+//! Underchex Variant Rules
+//!
+//! Victory conditions, move restrictions and other board rules specific to a
+//! [`Variant`]. Drop rules for crazyhouse live with move generation in `moves.rs`.
+
+use crate::board::{get_all_cells, is_valid_cell};
+use crate::moves::{
+    apply_move, find_boulder, find_king, generate_all_legal_moves,
+    generate_all_legal_moves_with_drops, is_in_check, is_occupied,
+};
+use crate::pieces::get_piece_definition;
+use crate::types::{
+    BoardState, Color, Hands, HexCoord, Move, PieceType, Variant, WinConditions, BOARD_RADIUS,
+};
+
+/// Result of a variant-specific end condition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VariantOutcome {
+    /// The game is won, with a reason for [`GameStatus::VariantWin`]
+    ///
+    /// [`GameStatus::VariantWin`]: crate::types::GameStatus::VariantWin
+    Win(Color, &'static str),
+    /// The game is drawn, with a reason for [`GameStatus::Draw`]
+    ///
+    /// [`GameStatus::Draw`]: crate::types::GameStatus::Draw
+    Draw(&'static str),
+}
+
+// ============================================================================
+// King of the Hill
+// ============================================================================
+
+/// The hill: a king standing here wins the game.
+pub const KING_OF_THE_HILL_CENTER: HexCoord = HexCoord { q: 0, r: 0 };
+
+/// Color whose king stands on the hill, if any.
+pub fn king_on_hill(board: &BoardState) -> Option<Color> {
+    [Color::White, Color::Black]
+        .into_iter()
+        .find(|&color| find_king(board, color) == Some(KING_OF_THE_HILL_CENTER))
+}
+
+// ============================================================================
+// Racing Kings
+// ============================================================================
+
+/// Row both kings race towards (the far edge from the starting rows).
+pub const RACING_KINGS_GOAL_ROW: i32 = -BOARD_RADIUS;
+
+/// Check if a color's king has reached the goal row.
+pub fn king_reached_goal(board: &BoardState, color: Color) -> bool {
+    find_king(board, color).is_some_and(|king| king.r == RACING_KINGS_GOAL_ROW)
+}
+
+/// Check if `color` (to move) can bring its king to the goal row this move.
+fn can_reach_goal(board: &BoardState, color: Color) -> bool {
+    generate_variant_legal_moves(board, color, Variant::RacingKings, &Hands::default())
+        .iter()
+        .any(|mv| mv.piece.piece_type == PieceType::King && mv.to.r == RACING_KINGS_GOAL_ROW)
+}
+
+/// Racing kings result. White moves first, so when white's king arrives black
+/// gets one more move to draw by arriving as well.
+fn racing_kings_outcome(board: &BoardState, side_to_move: Color) -> Option<VariantOutcome> {
+    match (
+        king_reached_goal(board, Color::White),
+        king_reached_goal(board, Color::Black),
+    ) {
+        (true, true) => Some(VariantOutcome::Draw("bothKingsReachedGoal")),
+        (false, true) => Some(VariantOutcome::Win(Color::Black, "kingReachedGoal")),
+        (true, false) => {
+            if side_to_move == Color::Black && can_reach_goal(board, Color::Black) {
+                None
+            } else {
+                Some(VariantOutcome::Win(Color::White, "kingReachedGoal"))
+            }
+        }
+        (false, false) => None,
+    }
+}
+
+// ============================================================================
+// Boulder
+// ============================================================================
+
+/// Check if the boulder can be placed on a cell (any empty cell; the cell it
+/// currently stands on is occupied, so the boulder always has to move).
+pub fn can_place_boulder(board: &BoardState, to: HexCoord) -> bool {
+    is_valid_cell(to) && !is_occupied(board, to)
+}
+
+/// All cells the boulder can be placed on.
+pub fn generate_boulder_placements(board: &BoardState) -> Vec<HexCoord> {
+    get_all_cells()
+        .into_iter()
+        .filter(|&cell| can_place_boulder(board, cell))
+        .collect()
+}
+
+/// Check that a move keeps the mover's king safe once the boulder is lifted.
+/// The boulder must leave its cell after the move, so it cannot be relied on
+/// to shield the king; any new placement only adds a blocker and is then safe.
+fn is_safe_without_boulder(board: &BoardState, mv: &Move) -> bool {
+    let mut after = apply_move(board, mv);
+    if mv.boulder.is_none() {
+        if let Some(boulder) = find_boulder(&after) {
+            after.remove(&boulder.to_key());
+        }
+    }
+    !is_in_check(&after, mv.piece.color)
+}
+
+// ============================================================================
+// Shogi-style Promotion
+// ============================================================================
+
+/// Depth of the far zone in which knights and lances may promote.
+pub const SHOGI_PROMOTION_ROWS: i32 = 2;
+
+/// Check if a cell lies in a color's far promotion zone.
+pub fn in_shogi_promotion_zone(coord: HexCoord, color: Color) -> bool {
+    match color {
+        Color::White => coord.r < -BOARD_RADIUS + SHOGI_PROMOTION_ROWS,
+        Color::Black => coord.r > BOARD_RADIUS - SHOGI_PROMOTION_ROWS,
+    }
+}
+
+/// Add the promoting alternative of every move that brings a promotable
+/// piece into its far zone. Promotion is optional, so the plain moves stay.
+pub fn with_piece_promotions(moves: Vec<Move>) -> Vec<Move> {
+    let mut result = Vec::with_capacity(moves.len());
+    for mv in moves {
+        let promotion = get_piece_definition(&mv.piece).promotes_to;
+        if let Some(promo_type) =
+            promotion.filter(|_| !mv.is_drop && in_shogi_promotion_zone(mv.to, mv.piece.color))
+        {
+            result.push(mv.clone().with_promotion(promo_type));
+        }
+        result.push(mv);
+    }
+    result
+}
+
+// ============================================================================
+// Optional Win Conditions
+// ============================================================================
+
+/// Whether a side has nothing but its king left on the board. Pieces in hand
+/// do not count.
+pub fn has_bare_king(board: &BoardState, color: Color) -> bool {
+    board.values().all(|piece| {
+        piece.color != color || piece.piece_type == PieceType::King || piece.is_boulder()
+    })
+}
+
+/// Why `color` has lost under the optional win conditions, if it has.
+fn win_condition_loss(
+    board: &BoardState,
+    conditions: WinConditions,
+    color: Color,
+) -> Option<&'static str> {
+    if conditions.bare_king && has_bare_king(board, color) {
+        return Some("bareKing");
+    }
+    let piece_type = conditions.capture_all?;
+    board
+        .values()
+        .all(|piece| piece.color != color || piece.piece_type != piece_type)
+        .then_some("allCaptured")
+}
+
+/// Check a game's optional win conditions: a side reduced to a bare king, or
+/// left without any piece of the `capture_all` type, loses. If both sides
+/// are, the game is drawn.
+pub fn win_condition_outcome(
+    board: &BoardState,
+    conditions: WinConditions,
+) -> Option<VariantOutcome> {
+    match (
+        win_condition_loss(board, conditions, Color::White),
+        win_condition_loss(board, conditions, Color::Black),
+    ) {
+        (Some(_), Some(_)) => Some(VariantOutcome::Draw("bothArmiesReduced")),
+        (Some(reason), None) => Some(VariantOutcome::Win(Color::Black, reason)),
+        (None, Some(reason)) => Some(VariantOutcome::Win(Color::White, reason)),
+        (None, None) => None,
+    }
+}
+
+// ============================================================================
+// Rules
+// ============================================================================
+
+/// Check a variant's own end conditions (checkmate and stalemate are handled
+/// separately).
+pub fn variant_outcome(
+    board: &BoardState,
+    variant: Variant,
+    side_to_move: Color,
+) -> Option<VariantOutcome> {
+    match variant {
+        Variant::KingOfTheHill => {
+            king_on_hill(board).map(|color| VariantOutcome::Win(color, "kingOfTheHill"))
+        }
+        Variant::RacingKings => racing_kings_outcome(board, side_to_move),
+        Variant::Standard
+        | Variant::Crazyhouse { .. }
+        | Variant::Boulder
+        | Variant::Cannons
+        | Variant::Shogi { .. } => None,
+    }
+}
+
+/// Check if a legal move is also allowed by a variant's extra restrictions.
+/// In racing kings no move may give check; in the boulder variant the king
+/// must not depend on the boulder's current cell for its safety.
+pub fn is_move_allowed(board: &BoardState, mv: &Move, variant: Variant) -> bool {
+    match variant {
+        Variant::RacingKings => !is_in_check(&apply_move(board, mv), mv.piece.color.opposite()),
+        Variant::Boulder => is_safe_without_boulder(board, mv),
+        _ => true,
+    }
+}
+
+/// Generate all legal moves for a player under a variant's rules, including
+/// drops from `hands` in drop variants and optional piece promotions.
+pub fn generate_variant_legal_moves(
+    board: &BoardState,
+    color: Color,
+    variant: Variant,
+    hands: &Hands,
+) -> Vec<Move> {
+    let mut moves = if variant.has_drops() {
+        generate_all_legal_moves_with_drops(board, color, hands, variant.allows_drop_mate())
+    } else {
+        generate_all_legal_moves(board, color)
+    };
+    moves.retain(|mv| is_move_allowed(board, mv, variant));
+    if variant.has_piece_promotion() {
+        moves = with_piece_promotions(moves);
+    }
+    moves
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Piece;
+
+    fn kings(white: &str, black: &str) -> BoardState {
+        let mut board = BoardState::new();
+        board.insert(white.to_string(), Piece::new(PieceType::King, Color::White));
+        board.insert(black.to_string(), Piece::new(PieceType::King, Color::Black));
+        board
+    }
+
+    #[test]
+    fn test_king_of_the_hill_outcome() {
+        let board = kings("0,4", "0,-4");
+        assert_eq!(
+            variant_outcome(&board, Variant::KingOfTheHill, Color::White),
+            None
+        );
+
+        let board = kings("0,4", "0,0");
+        assert_eq!(
+            variant_outcome(&board, Variant::KingOfTheHill, Color::White),
+            Some(VariantOutcome::Win(Color::Black, "kingOfTheHill"))
+        );
+        assert_eq!(
+            variant_outcome(&board, Variant::Standard, Color::White),
+            None
+        );
+    }
+
+    #[test]
+    fn test_racing_kings_outcome() {
+        let race =
+            |white, black, side| variant_outcome(&kings(white, black), Variant::RacingKings, side);
+
+        // Black can still catch up
+        assert_eq!(race("0,-4", "3,-3", Color::Black), None);
+        // Black is too far behind
+        assert_eq!(
+            race("0,-4", "3,0", Color::Black),
+            Some(VariantOutcome::Win(Color::White, "kingReachedGoal"))
+        );
+        assert_eq!(
+            race("2,-4", "4,-4", Color::White),
+            Some(VariantOutcome::Draw("bothKingsReachedGoal"))
+        );
+        assert_eq!(
+            race("0,2", "3,-4", Color::White),
+            Some(VariantOutcome::Win(Color::Black, "kingReachedGoal"))
+        );
+    }
+
+    #[test]
+    fn test_win_condition_outcome() {
+        let mut board = kings("0,4", "0,-4");
+        board.insert(
+            "0,0".to_string(),
+            Piece::new(PieceType::Queen, Color::White),
+        );
+        let bare_king = WinConditions {
+            bare_king: true,
+            capture_all: None,
+        };
+        assert_eq!(
+            win_condition_outcome(&board, WinConditions::default()),
+            None
+        );
+        assert_eq!(
+            win_condition_outcome(&board, bare_king),
+            Some(VariantOutcome::Win(Color::White, "bareKing"))
+        );
+
+        let no_knights = WinConditions {
+            bare_king: false,
+            capture_all: Some(PieceType::Knight),
+        };
+        board.insert(
+            "1,-3".to_string(),
+            Piece::new(PieceType::Knight, Color::Black),
+        );
+        assert_eq!(
+            win_condition_outcome(&board, no_knights),
+            Some(VariantOutcome::Win(Color::Black, "allCaptured"))
+        );
+        assert_eq!(win_condition_outcome(&board, bare_king), None);
+        assert_eq!(
+            win_condition_outcome(&kings("0,4", "0,-4"), bare_king),
+            Some(VariantOutcome::Draw("bothArmiesReduced"))
+        );
+    }
+
+    #[test]
+    fn test_boulder_blocks_and_cannot_be_captured() {
+        let mut board = kings("0,4", "0,-4");
+        board.insert(
+            "0,0".to_string(),
+            Piece::new(PieceType::Queen, Color::White),
+        );
+        board.insert("0,-2".to_string(), Piece::boulder());
+
+        let moves =
+            generate_variant_legal_moves(&board, Color::White, Variant::Boulder, &Hands::default());
+        assert!(moves.iter().any(|m| m.to == HexCoord::new(0, -1)));
+        assert!(!moves.iter().any(|m| m.to == HexCoord::new(0, -2)));
+        assert!(!moves.iter().any(|m| m.to == HexCoord::new(0, -3)));
+        assert!(!is_in_check(&board, Color::Black));
+
+        let placements = generate_boulder_placements(&board);
+        assert_eq!(placements.len(), 61 - board.len());
+        assert!(!can_place_boulder(&board, HexCoord::new(0, -2)));
+    }
+
+    #[test]
+    fn test_boulder_cannot_shield_king() {
+        // The boulder shields the white king from the rook-like chariot line;
+        // since it must move, the king may not stay behind it.
+        let mut board = kings("0,4", "0,-4");
+        board.insert(
+            "2,2".to_string(),
+            Piece::new(PieceType::Chariot, Color::Black),
+        );
+        board.insert("1,3".to_string(), Piece::boulder());
+        board.insert(
+            "-3,0".to_string(),
+            Piece::new(PieceType::Knight, Color::White),
+        );
+
+        let moves =
+            generate_variant_legal_moves(&board, Color::White, Variant::Boulder, &Hands::default());
+        assert!(!moves.is_empty());
+        assert!(moves.iter().all(|m| m.piece.piece_type == PieceType::King));
+    }
+
+    #[test]
+    fn test_shogi_promotion_is_optional() {
+        let mut board = kings("0,4", "0,-4");
+        board.insert(
+            "0,-1".to_string(),
+            Piece::new(PieceType::Knight, Color::White),
+        );
+        let shogi = Variant::Shogi { drops: false };
+
+        let moves = generate_variant_legal_moves(&board, Color::White, shogi, &Hands::default());
+        let into_zone: Vec<&Move> = moves
+            .iter()
+            .filter(|m| m.to == HexCoord::new(1, -3))
+            .collect();
+        assert_eq!(into_zone.len(), 2);
+        assert!(into_zone
+            .iter()
+            .any(|m| m.promotion == Some(PieceType::PromotedKnight)));
+        // Outside the zone there is no choice, and other variants never promote
+        assert_eq!(
+            moves
+                .iter()
+                .filter(|m| m.to == HexCoord::new(2, -2))
+                .count(),
+            1
+        );
+        assert!(generate_variant_legal_moves(
+            &board,
+            Color::White,
+            Variant::Standard,
+            &Hands::default()
+        )
+        .iter()
+        .all(|m| m.promotion.is_none()));
+    }
+
+    #[test]
+    fn test_racing_kings_forbids_checks() {
+        let mut board = kings("0,4", "0,-4");
+        board.insert(
+            "2,0".to_string(),
+            Piece::new(PieceType::Queen, Color::White),
+        );
+
+        let moves = generate_variant_legal_moves(
+            &board,
+            Color::White,
+            Variant::RacingKings,
+            &Hands::default(),
+        );
+        assert!(!moves.is_empty());
+        assert!(moves
+            .iter()
+            .all(|mv| !is_in_check(&apply_move(&board, mv), Color::Black)));
+        assert!(moves.len() < generate_all_legal_moves(&board, Color::White).len());
+    }
+}
@@ -0,0 +1,339 @@
+//! Underchex Board Vision Training
+//!
+//! Support for blindfold and board-vision training: checking a player's
+//! recollection of where the pieces stand, quizzes on the cells a piece
+//! attacks, and knight-path puzzles.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::attacks::get_attacked_cells;
+use crate::board::{get_all_cells, get_knight_targets, knight_path, occupied_cells};
+use crate::rng::SeededRng;
+use crate::types::{BoardState, Color, HexCoord, Piece, PieceType};
+
+/// Sort cells into a stable order for results.
+fn sorted(mut cells: Vec<HexCoord>) -> Vec<HexCoord> {
+    cells.sort_by_key(|cell| (cell.q, cell.r));
+    cells
+}
+
+/// Percentage of correct items, rounded, when `wrong` items were also given
+/// and `expected` items were wanted; 100 when nothing was wanted or given.
+fn percent(correct: usize, wrong: usize, expected: usize) -> u32 {
+    let total = expected + wrong;
+    if total == 0 {
+        return 100;
+    }
+    ((correct * 100) as f64 / total as f64).round() as u32
+}
+
+// ============================================================================
+// Piece Lists
+// ============================================================================
+
+/// Result of checking a claimed piece list against the board.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PieceListCheck {
+    /// Cells claimed with the piece that stands there
+    pub correct: Vec<HexCoord>,
+    /// Cells claimed with a piece that does not stand there
+    pub wrong: Vec<HexCoord>,
+    /// Cells whose piece was not claimed
+    pub missed: Vec<HexCoord>,
+    /// Percentage of the claims and pieces that were right
+    pub score: u32,
+}
+
+impl PieceListCheck {
+    pub fn is_perfect(&self) -> bool {
+        self.wrong.is_empty() && self.missed.is_empty()
+    }
+}
+
+/// Check a player's claimed piece locations (a board of the pieces they
+/// believe stand where) against the real board. With a `color`, only that
+/// side's pieces count as missed, for recalling one side at a time. The
+/// boulder counts like any piece.
+pub fn verify_piece_list(
+    board: &BoardState,
+    claimed: &BoardState,
+    color: Option<Color>,
+) -> PieceListCheck {
+    let actual: Vec<(HexCoord, Piece)> = occupied_cells(board).collect();
+    let mut correct = Vec::new();
+    let mut wrong = Vec::new();
+    for (cell, piece) in occupied_cells(claimed) {
+        if actual.contains(&(cell, piece)) {
+            correct.push(cell);
+        } else {
+            wrong.push(cell);
+        }
+    }
+    let missed: Vec<HexCoord> = actual
+        .iter()
+        .filter(|(cell, piece)| {
+            color.is_none_or(|color| piece.color == color && !piece.is_boulder())
+                && !correct.contains(cell)
+        })
+        .map(|&(cell, _)| cell)
+        .collect();
+    let expected = correct.len() + missed.len();
+    PieceListCheck {
+        score: percent(correct.len(), wrong.len(), expected),
+        correct: sorted(correct),
+        wrong: sorted(wrong),
+        missed: sorted(missed),
+    }
+}
+
+// ============================================================================
+// Attack Quizzes
+// ============================================================================
+
+/// A "which cells does this piece attack?" question.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttackQuiz {
+    pub cell: HexCoord,
+    pub piece: Piece,
+    /// The answer: every cell the piece attacks, friendly pieces included
+    pub attacked: Vec<HexCoord>,
+}
+
+/// Grade of an answer to a quiz listing cells.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuizGrade {
+    pub correct: Vec<HexCoord>,
+    /// Cells given that are not in the answer
+    pub wrong: Vec<HexCoord>,
+    /// Cells of the answer not given
+    pub missed: Vec<HexCoord>,
+    /// Percentage of the cells given and wanted that were right
+    pub score: u32,
+}
+
+impl AttackQuiz {
+    /// The quiz for the piece on `cell`, or None if the cell is empty or
+    /// holds the boulder.
+    pub fn for_piece(board: &BoardState, cell: HexCoord) -> Option<Self> {
+        let piece = *board.get(&cell.to_key())?;
+        if piece.is_boulder() {
+            return None;
+        }
+        let attacked: HashSet<HexCoord> = get_attacked_cells(board, &piece, cell)
+            .into_iter()
+            .collect();
+        Some(Self {
+            cell,
+            piece,
+            attacked: sorted(attacked.into_iter().collect()),
+        })
+    }
+
+    /// Grade an answer; repeated cells count once.
+    pub fn grade(&self, answer: &[HexCoord]) -> QuizGrade {
+        let given: HashSet<HexCoord> = answer.iter().copied().collect();
+        let (correct, wrong): (Vec<HexCoord>, Vec<HexCoord>) = given
+            .into_iter()
+            .partition(|cell| self.attacked.contains(cell));
+        let missed: Vec<HexCoord> = self
+            .attacked
+            .iter()
+            .filter(|cell| !correct.contains(cell))
+            .copied()
+            .collect();
+        QuizGrade {
+            score: percent(correct.len(), wrong.len(), self.attacked.len()),
+            correct: sorted(correct),
+            wrong: sorted(wrong),
+            missed,
+        }
+    }
+}
+
+/// A quiz on a piece of the board picked from `seed`: any piece but a king
+/// or the boulder that attacks at least one cell. Returns None if there is
+/// none.
+pub fn attack_quiz(board: &BoardState, seed: u64) -> Option<AttackQuiz> {
+    let mut candidates: Vec<AttackQuiz> = occupied_cells(board)
+        .filter(|(_, piece)| piece.piece_type != PieceType::King)
+        .filter_map(|(cell, _)| AttackQuiz::for_piece(board, cell))
+        .filter(|quiz| !quiz.attacked.is_empty())
+        .collect();
+    // Board iteration order varies, so pick from a stable order
+    candidates.sort_by_key(|quiz| (quiz.cell.q, quiz.cell.r));
+    let mut rng = SeededRng::new(seed);
+    rng.choose(&candidates).cloned()
+}
+
+// ============================================================================
+// Knight Paths
+// ============================================================================
+
+/// Tries at picking cells before [`knight_path_puzzle`] gives up.
+const KNIGHT_PUZZLE_TRIES: usize = 100;
+
+/// Take a knight from one cell to another in the fewest leaps, without
+/// landing on the blocked cells.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KnightPathPuzzle {
+    pub from: HexCoord,
+    pub to: HexCoord,
+    pub blocked: Vec<HexCoord>,
+    /// Fewest leaps needed
+    pub moves: u32,
+}
+
+/// Verdict on a knight route.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "camelCase")]
+pub enum KnightPathAnswer {
+    /// Reaches the target in the fewest leaps
+    Optimal,
+    /// Reaches the target in more leaps than needed
+    Longer { moves: u32 },
+    /// Leap `step` (from 0) is not a knight leap or lands on a blocked cell
+    Illegal { step: usize },
+    /// Every leap is legal but the route ends elsewhere
+    Incomplete,
+}
+
+impl KnightPathPuzzle {
+    /// Check a route: the cells leapt to, ending with the target.
+    pub fn check(&self, route: &[HexCoord]) -> KnightPathAnswer {
+        let mut cell = self.from;
+        for (step, &next) in route.iter().enumerate() {
+            if !get_knight_targets(cell).contains(&next) || self.blocked.contains(&next) {
+                return KnightPathAnswer::Illegal { step };
+            }
+            cell = next;
+        }
+        if cell != self.to {
+            KnightPathAnswer::Incomplete
+        } else if route.len() as u32 <= self.moves {
+            KnightPathAnswer::Optimal
+        } else {
+            KnightPathAnswer::Longer {
+                moves: route.len() as u32,
+            }
+        }
+    }
+
+    /// A shortest route, as found by [`knight_path`].
+    pub fn solution(&self) -> Option<Vec<HexCoord>> {
+        knight_path(self.from, self.to, &self.blocked.iter().copied().collect())
+    }
+}
+
+/// A knight-path puzzle picked from `seed` whose shortest route takes
+/// exactly `moves` leaps, with `blocked` cells in the way. Returns None if
+/// no such puzzle turns up, e.g. for more leaps than the board allows.
+pub fn knight_path_puzzle(seed: u64, moves: u32, blocked: usize) -> Option<KnightPathPuzzle> {
+    let mut rng = SeededRng::new(seed);
+    let cells = get_all_cells();
+    for _ in 0..KNIGHT_PUZZLE_TRIES {
+        let from = *rng.choose(&cells)?;
+        let mut blocked_cells = HashSet::new();
+        while blocked_cells.len() < blocked.min(cells.len() - 1) {
+            let cell = *rng.choose(&cells)?;
+            if cell != from {
+                blocked_cells.insert(cell);
+            }
+        }
+        let targets: Vec<HexCoord> = cells
+            .iter()
+            .filter(|&&cell| !blocked_cells.contains(&cell))
+            .filter(|&&cell| {
+                knight_path(from, cell, &blocked_cells)
+                    .is_some_and(|path| path.len() == moves as usize)
+            })
+            .copied()
+            .collect();
+        if let Some(&to) = rng.choose(&targets) {
+            return Some(KnightPathPuzzle {
+                from,
+                to,
+                blocked: sorted(blocked_cells.into_iter().collect()),
+                moves,
+            });
+        }
+    }
+    None
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::create_new_game;
+
+    #[test]
+    fn test_verify_piece_list() {
+        let board = create_new_game().board;
+        let check = verify_piece_list(&board, &board, None);
+        assert!(check.is_perfect());
+        assert_eq!(check.score, 100);
+
+        // One piece forgotten, one put on the wrong cell
+        let mut claimed = board.clone();
+        let (moved, piece) = occupied_cells(&board)
+            .find(|(_, piece)| piece.color == Color::White && piece.piece_type == PieceType::Knight)
+            .unwrap();
+        claimed.remove(&moved.to_key());
+        claimed.insert(HexCoord::new(0, 0).to_key(), piece);
+        let check = verify_piece_list(&board, &claimed, None);
+        assert_eq!(check.wrong, vec![HexCoord::new(0, 0)]);
+        assert_eq!(check.missed, vec![moved]);
+        assert!(check.score < 100);
+
+        // Recalling White only: Black's pieces are not missed
+        let white: BoardState = claimed
+            .into_iter()
+            .filter(|(_, piece)| piece.color == Color::White)
+            .collect();
+        let check = verify_piece_list(&board, &white, Some(Color::White));
+        assert_eq!(check.missed, vec![moved]);
+    }
+
+    #[test]
+    fn test_attack_quiz() {
+        let board = create_new_game().board;
+        let quiz = attack_quiz(&board, 7).unwrap();
+        assert_eq!(attack_quiz(&board, 7), Some(quiz.clone()));
+        assert_ne!(quiz.piece.piece_type, PieceType::King);
+        assert!(AttackQuiz::for_piece(&board, HexCoord::new(0, 0)).is_none());
+
+        let grade = quiz.grade(&quiz.attacked);
+        assert_eq!(grade.score, 100);
+        assert!(grade.wrong.is_empty() && grade.missed.is_empty());
+
+        let off = get_all_cells()
+            .into_iter()
+            .find(|cell| !quiz.attacked.contains(cell))
+            .unwrap();
+        let grade = quiz.grade(&[quiz.attacked[0], quiz.attacked[0], off]);
+        assert_eq!(grade.correct, vec![quiz.attacked[0]]);
+        assert_eq!(grade.wrong, vec![off]);
+        assert_eq!(grade.missed.len(), quiz.attacked.len() - 1);
+        assert!(grade.score < 100);
+    }
+
+    #[test]
+    fn test_knight_path_puzzle() {
+        let puzzle = knight_path_puzzle(3, 3, 4).unwrap();
+        assert_eq!(knight_path_puzzle(3, 3, 4), Some(puzzle.clone()));
+        assert_eq!(puzzle.blocked.len(), 4);
+        let solution = puzzle.solution().unwrap();
+        assert_eq!(solution.len(), 3);
+        assert_eq!(puzzle.check(&solution), KnightPathAnswer::Optimal);
+        assert_eq!(puzzle.check(&solution[..2]), KnightPathAnswer::Incomplete);
+        assert_eq!(
+            puzzle.check(&[puzzle.to]),
+            KnightPathAnswer::Illegal { step: 0 }
+        );
+        assert!(knight_path_puzzle(3, 40, 0).is_none());
+    }
+}
@@ -0,0 +1,409 @@
+//! Underchex Position Hashing
+//!
+//! Zobrist hashes of positions: the XOR of a fixed pseudo-random key per
+//! (cell, piece), per side to move and per count of each piece in hand. Hashes
+//! are fast to compare but can collide, so anything that must be exact pairs a
+//! matching hash with [`boards_equal`] or [`positions_equal`].
+//!
+//! Cells are identified by their parsed coordinates, so board keys that name
+//! the same cell in different formats hash and compare the same.
+//!
+//! [`collision_stats`] measures collisions over positions from random games,
+//! as a check on the key quality.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::board::occupied_cells;
+use crate::moves::{apply_move, generate_all_legal_moves};
+use crate::rng::SeededRng;
+use crate::types::{BoardState, Color, GameState, Hands, HexCoord, LanceVariant, Piece, PieceType};
+
+// ============================================================================
+// Zobrist Keys
+// ============================================================================
+
+const SEED: u64 = 0x5EED_0F4E_58C4_E5A1;
+
+const SIDE_TAG: u64 = 1 << 40;
+const HAND_TAG: u64 = 2 << 40;
+const CHECK_TAG: u64 = 4 << 40;
+
+/// Deterministic pseudo-random key for a feature code (SplitMix64 finalizer),
+/// equivalent to a precomputed table of random numbers.
+fn zobrist_key(code: u64) -> u64 {
+    let mut z = (SEED ^ code).wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+fn color_bit(color: Color) -> u64 {
+    match color {
+        Color::White => 0,
+        Color::Black => 1,
+    }
+}
+
+/// Feature code of a piece standing on a cell.
+fn piece_code(cell: HexCoord, piece: &Piece) -> u64 {
+    let variant = match piece.variant {
+        None => 0,
+        Some(LanceVariant::A) => 1,
+        Some(LanceVariant::B) => 2,
+    };
+    ((cell.q + 128) as u64 & 0xFF)
+        | (((cell.r + 128) as u64 & 0xFF) << 8)
+        | ((piece.piece_type as u64) << 16)
+        | (variant << 24)
+        | (color_bit(piece.color) << 28)
+}
+
+/// Key for a piece standing on a cell.
+pub fn piece_key(cell: HexCoord, piece: &Piece) -> u64 {
+    zobrist_key(piece_code(cell, piece))
+}
+
+/// Key for a piece standing on a cell in the check hashes, independent of
+/// [`piece_key`].
+fn check_key(cell: HexCoord, piece: &Piece) -> u64 {
+    zobrist_key(CHECK_TAG | piece_code(cell, piece))
+}
+
+// ============================================================================
+// Hashing
+// ============================================================================
+
+/// Hash of the pieces on a board. Entries whose key is not a cell are ignored.
+pub fn hash_board(board: &BoardState) -> u64 {
+    board
+        .iter()
+        .filter_map(|(key, piece)| HexCoord::from_key(key).map(|cell| piece_key(cell, piece)))
+        .fold(0, |hash, key| hash ^ key)
+}
+
+/// [`hash_board`] and a second, independent hash of the same pieces, so a
+/// cache indexed by the first can verify its entries with the second.
+pub(crate) fn hash_board_checked(board: &BoardState) -> (u64, u64) {
+    occupied_cells(board).fold((0, 0), |(hash, check), (cell, piece)| {
+        (
+            hash ^ piece_key(cell, &piece),
+            check ^ check_key(cell, &piece),
+        )
+    })
+}
+
+/// Hash of the pawns on a board alone, for caching pawn evaluation, with a
+/// check hash as in [`hash_board_checked`].
+pub(crate) fn hash_pawns_checked(board: &BoardState) -> (u64, u64) {
+    occupied_cells(board)
+        .filter(|(_, piece)| piece.piece_type == PieceType::Pawn)
+        .fold((0, 0), |(hash, check), (cell, piece)| {
+            (
+                hash ^ piece_key(cell, &piece),
+                check ^ check_key(cell, &piece),
+            )
+        })
+}
+
+/// Hash contribution of the side to move.
+pub fn hash_side(side_to_move: Color) -> u64 {
+    match side_to_move {
+        Color::White => 0,
+        Color::Black => zobrist_key(SIDE_TAG),
+    }
+}
+
+/// Hash contribution of the pieces in hand (0 for empty hands).
+pub fn hash_hands(hands: &Hands) -> u64 {
+    [Color::White, Color::Black]
+        .into_iter()
+        .flat_map(|color| {
+            hands.get(color).iter().filter(|(_, &count)| count > 0).map(
+                move |(&piece_type, &count)| {
+                    zobrist_key(
+                        HAND_TAG
+                            | (color_bit(color) << 32)
+                            | ((piece_type as u64) << 16)
+                            | u64::from(count),
+                    )
+                },
+            )
+        })
+        .fold(0, |hash, key| hash ^ key)
+}
+
+/// Hash of a board with a side to move.
+pub fn hash_position(board: &BoardState, side_to_move: Color) -> u64 {
+    hash_board(board) ^ hash_side(side_to_move)
+}
+
+impl GameState {
+    /// Zobrist hash of the position: board, side to move and pieces in hand.
+    /// Equal positions always hash equal; confirm with [`positions_equal`].
+    pub fn position_hash(&self) -> u64 {
+        hash_position(&self.board, self.turn) ^ hash_hands(&self.hands)
+    }
+}
+
+// ============================================================================
+// Exact Comparison
+// ============================================================================
+
+fn canonical_board(board: &BoardState) -> Option<HashMap<HexCoord, Piece>> {
+    board
+        .iter()
+        .map(|(key, piece)| HexCoord::from_key(key).map(|cell| (cell, *piece)))
+        .collect()
+}
+
+/// Whether two boards hold exactly the same pieces on the same cells.
+pub fn boards_equal(a: &BoardState, b: &BoardState) -> bool {
+    if a == b {
+        return true;
+    }
+    match (canonical_board(a), canonical_board(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Whether two game states are the same position: board, side to move and
+/// pieces in hand.
+pub fn positions_equal(a: &GameState, b: &GameState) -> bool {
+    a.turn == b.turn && a.hands == b.hands && boards_equal(&a.board, &b.board)
+}
+
+// ============================================================================
+// Collision Statistics
+// ============================================================================
+
+/// Positions reached by playing random legal moves from `start` for up to
+/// `plies` plies (fewer if the game ends), starting position included.
+/// Moves are picked in a fixed order, so a seed always gives the same game.
+pub fn random_game_positions(
+    start: &BoardState,
+    side_to_move: Color,
+    plies: usize,
+    rng: &mut SeededRng,
+) -> Vec<(BoardState, Color)> {
+    let mut positions = vec![(start.clone(), side_to_move)];
+    let mut board = start.clone();
+    let mut turn = side_to_move;
+    for _ in 0..plies {
+        let mut moves = generate_all_legal_moves(&board, turn);
+        moves.sort_by_key(|mv| {
+            (
+                mv.from.q,
+                mv.from.r,
+                mv.to.q,
+                mv.to.r,
+                mv.promotion.map(|p| p as u8),
+            )
+        });
+        let Some(mv) = rng.choose(&moves) else {
+            break;
+        };
+        board = apply_move(&board, mv);
+        turn = turn.opposite();
+        positions.push((board.clone(), turn));
+    }
+    positions
+}
+
+/// Hash collisions among the positions of random games.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CollisionReport {
+    pub games: usize,
+    /// Positions hashed, repeats included
+    pub positions: usize,
+    pub distinct_positions: usize,
+    /// Distinct positions sharing a full 64-bit hash with an earlier one
+    pub collisions: usize,
+    /// Collisions expected of ideal random 64-bit hashes
+    pub expected_collisions: f64,
+    /// Collisions of the low 32 bits, as seen by a table indexed by them
+    pub collisions_32: usize,
+    /// Collisions expected of ideal random 32-bit hashes
+    pub expected_collisions_32: f64,
+}
+
+/// Exact text key of a position, to tell real collisions from repeats.
+fn position_key(board: &BoardState, side_to_move: Color) -> String {
+    let mut pieces: Vec<String> = board
+        .iter()
+        .map(|(key, piece)| format!("{}={:?}", key, piece))
+        .collect();
+    pieces.sort();
+    format!("{:?}|{}", side_to_move, pieces.join(";"))
+}
+
+/// Expected number of colliding pairs among `n` ideal `bits`-bit hashes.
+fn expected_collisions(n: usize, bits: i32) -> f64 {
+    let n = n as f64;
+    n * (n - 1.0) / 2.0 / 2f64.powi(bits)
+}
+
+/// Hash the positions of `games` random games of up to `plies` plies from
+/// `start` and count collisions between distinct positions.
+pub fn collision_stats(
+    start: &BoardState,
+    side_to_move: Color,
+    games: usize,
+    plies: usize,
+    seed: u64,
+) -> CollisionReport {
+    let mut rng = SeededRng::new(seed);
+    let mut by_hash: HashMap<u64, String> = HashMap::new();
+    let mut by_low: HashMap<u32, String> = HashMap::new();
+    let mut report = CollisionReport {
+        games,
+        ..Default::default()
+    };
+
+    for _ in 0..games {
+        for (board, turn) in random_game_positions(start, side_to_move, plies, &mut rng) {
+            report.positions += 1;
+            let hash = hash_position(&board, turn);
+            let fen = position_key(&board, turn);
+            match by_hash.get(&hash) {
+                Some(existing) if *existing == fen => continue,
+                Some(_) => report.collisions += 1,
+                None => {
+                    by_hash.insert(hash, fen.clone());
+                }
+            }
+            report.distinct_positions += 1;
+            match by_low.get(&(hash as u32)) {
+                Some(existing) if *existing != fen => report.collisions_32 += 1,
+                Some(_) => {}
+                None => {
+                    by_low.insert(hash as u32, fen);
+                }
+            }
+        }
+    }
+
+    report.expected_collisions = expected_collisions(report.distinct_positions, 64);
+    report.expected_collisions_32 = expected_collisions(report.distinct_positions, 32);
+    report
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{create_new_game, create_variant_game, make_move};
+    use crate::types::Variant;
+
+    #[test]
+    fn test_transpositions_hash_equal() {
+        let game = create_new_game();
+        let mv = |state: &GameState, from: (i32, i32), to: (i32, i32)| {
+            make_move(
+                state,
+                HexCoord::new(from.0, from.1),
+                HexCoord::new(to.0, to.1),
+            )
+            .unwrap()
+        };
+        let a = mv(
+            &mv(&mv(&game, (0, 2), (0, 1)), (0, -2), (0, -1)),
+            (1, 2),
+            (1, 1),
+        );
+        let b = mv(
+            &mv(&mv(&game, (1, 2), (1, 1)), (0, -2), (0, -1)),
+            (0, 2),
+            (0, 1),
+        );
+        assert_eq!(a.position_hash(), b.position_hash());
+        assert!(positions_equal(&a, &b));
+
+        let other = mv(&a, (0, -1), (0, 0));
+        assert_ne!(a.position_hash(), other.position_hash());
+        assert!(!positions_equal(&a, &other));
+        assert_ne!(
+            game.position_hash(),
+            hash_position(&game.board, Color::Black)
+        );
+    }
+
+    #[test]
+    fn test_pawn_hash_ignores_other_pieces() {
+        let game = create_new_game();
+        let mut board = game.board.clone();
+        board.retain(|_, piece| piece.piece_type == PieceType::Pawn);
+        assert_eq!(hash_pawns_checked(&game.board), hash_pawns_checked(&board));
+        assert_eq!(hash_pawns_checked(&board), hash_board_checked(&board));
+        assert_eq!(hash_board_checked(&board).0, hash_board(&board));
+        assert_ne!(hash_board_checked(&board).1, hash_board(&board));
+
+        let knight_move = apply_move(
+            &game.board,
+            &generate_all_legal_moves(&game.board, Color::White)
+                .into_iter()
+                .find(|mv| mv.piece.piece_type == PieceType::Knight)
+                .unwrap(),
+        );
+        assert_eq!(
+            hash_pawns_checked(&knight_move),
+            hash_pawns_checked(&game.board)
+        );
+        assert_ne!(hash_board(&knight_move), hash_board(&game.board));
+    }
+
+    #[test]
+    fn test_key_formatting_is_canonical() {
+        let game = create_new_game();
+        let mut reformatted = BoardState::new();
+        for (key, piece) in &game.board {
+            let cell = HexCoord::from_key(key).unwrap();
+            reformatted.insert(format!("{:+},{:+}", cell.q, cell.r), *piece);
+        }
+        assert_eq!(hash_board(&reformatted), hash_board(&game.board));
+        assert!(boards_equal(&reformatted, &game.board));
+    }
+
+    #[test]
+    fn test_hands_and_lance_variants_distinguish() {
+        let mut game = create_variant_game(Variant::Crazyhouse { drop_mate: true });
+        let before = game.position_hash();
+        game.hands.add(Color::White, PieceType::Knight);
+        assert_ne!(game.position_hash(), before);
+        game.hands.add(Color::White, PieceType::Knight);
+        let two = game.position_hash();
+        game.hands.remove(Color::White, PieceType::Knight);
+        assert_ne!(game.position_hash(), two);
+
+        let cell = HexCoord::new(0, 0);
+        let a = Piece::lance(Color::White, LanceVariant::A);
+        let b = Piece::lance(Color::White, LanceVariant::B);
+        assert_ne!(piece_key(cell, &a), piece_key(cell, &b));
+    }
+
+    #[test]
+    fn test_collision_stats() {
+        let start = create_new_game().board;
+        let mut rng = SeededRng::new(3);
+        let game = random_game_positions(&start, Color::White, 12, &mut rng);
+        assert_eq!(game.len(), 13);
+        assert_eq!(game[1].1, Color::Black);
+        let mut again = SeededRng::new(3);
+        assert_eq!(
+            random_game_positions(&start, Color::White, 12, &mut again),
+            game
+        );
+
+        let report = collision_stats(&start, Color::White, 20, 30, 1);
+        assert_eq!(report.positions, 20 * 31);
+        // Every game repeats the starting position
+        assert!(report.distinct_positions <= report.positions - 19);
+        assert_eq!(report.collisions, 0);
+        assert!(report.expected_collisions < 1e-9);
+    }
+}
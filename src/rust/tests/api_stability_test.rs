@@ -0,0 +1,142 @@
+//! Snapshot of the public API: every name `underchex_wasm::prelude` exports,
+//! and the crate root's exports of the stable names and of the internal
+//! modules. The imports are checked at compile time, so removing or renaming
+//! an item breaks this test, and the crate root's and prelude's declarations
+//! must match the checked-in `public_api.txt`, so adding one is made
+//! together with these lists.
+
+use std::fs;
+use std::path::Path;
+
+use underchex_wasm::prelude::{
+    board_to_hexfen, create_game_from_position, create_new_game, create_variant_game, english,
+    evaluate_position, game_result, game_to_hexfen, get_ai_move, get_legal_moves, hash_position,
+    make_move, make_move_with_promotion, move_to_san, parse_game_hexfen, parse_hexfen,
+    positions_equal, try_make_move, AIDifficulty, BoardState, CancelToken, Color, Engine,
+    EngineSearch, GameResult, GameState, GameStatus, HexCoord, Message, Move, Piece, PieceType,
+    ScoreKind, SearchResult, StalemateRule, TranspositionTable, Variant, WinConditions,
+};
+
+/// The crate root re-exports the stable names, and from the internal
+/// modules only these.
+#[allow(unused_imports)]
+mod crate_root {
+    use underchex_wasm::{
+        board_to_hexfen, create_game_from_position, create_new_game, create_variant_game, english,
+        evaluate_position, game_result, game_to_hexfen, get_ai_move, get_legal_moves,
+        hash_position, make_move, make_move_with_promotion, move_to_san, parse_game_hexfen,
+        parse_hexfen, positions_equal, try_make_move, AIDifficulty, BoardState, CancelToken, Color,
+        Engine, EngineSearch, GameResult, GameState, GameStatus, HexCoord, Message, Move, Piece,
+        PieceType, ScoreKind, SearchResult, StalemateRule, TranspositionTable, Variant,
+        WinConditions,
+    };
+
+    // trace, tuning and zobrist
+    use underchex_wasm::{
+        boards_equal, hash_board, init_tracing, run_bench, tune_move_ordering, BenchReport,
+        TuningResult,
+    };
+}
+
+#[test]
+fn test_prelude_signatures() {
+    // The entry points keep their shapes
+    let _: fn() -> GameState = create_new_game;
+    let _: fn(Variant) -> GameState = create_variant_game;
+    let _: fn(BoardState, Color) -> GameState = create_game_from_position;
+    let _: fn(&GameState, HexCoord, HexCoord) -> Option<GameState> = make_move;
+    let _: fn(&GameState, HexCoord, HexCoord, Option<PieceType>) -> Option<GameState> =
+        make_move_with_promotion;
+    let _: fn(&GameState, HexCoord, HexCoord, Option<PieceType>) -> Result<GameState, String> =
+        try_make_move;
+    let _: fn(&GameState) -> Vec<Move> = get_legal_moves;
+    let _: fn(&GameState) -> Option<GameResult> = game_result;
+    let _: fn(&str) -> Option<(BoardState, Color)> = parse_hexfen;
+    let _: fn(&str) -> Option<GameState> = parse_game_hexfen;
+    let _: fn(&BoardState, Color) -> String = board_to_hexfen;
+    let _: fn(&GameState) -> String = game_to_hexfen;
+    let _: fn(&BoardState, &Move) -> String = move_to_san;
+    let _: fn(&BoardState) -> i32 = evaluate_position;
+    let _: fn(&Message) -> String = english;
+    let _: fn(&BoardState, Color) -> u64 = hash_position;
+    let _: fn(&GameState, &GameState) -> bool = positions_equal;
+    let _: fn(&BoardState, Color, AIDifficulty, &mut TranspositionTable) -> SearchResult =
+        get_ai_move;
+
+    // And work together
+    let game = create_new_game();
+    let mut engine = Engine::new();
+    let search: EngineSearch = engine.analyze(&game, 1);
+    let best = search.result.best_move.unwrap();
+    assert!(try_make_move(&game, best.from, best.to, best.promotion).is_ok());
+    assert_eq!(search.result.score_kind, ScoreKind::Centipawns);
+    let _: CancelToken = engine.cancel_token();
+    let _: (Piece, GameStatus, StalemateRule, WinConditions) = (
+        Piece::new(PieceType::King, Color::White),
+        GameStatus::Ongoing,
+        StalemateRule::Draw,
+        WinConditions::default(),
+    );
+    assert_eq!(english(&Message::new("gameOver")), "The game is over.");
+    assert!(positions_equal(&game, &create_new_game()));
+    assert_ne!(
+        hash_position(&game.board, Color::White),
+        hash_position(&game.board, Color::Black)
+    );
+}
+
+/// The public declarations of a crate file, one per entry: `mod <name>`,
+/// `<kind> <name>` for items defined there and `use <path>` for each
+/// re-exported name. Only top-level (unindented) declarations count.
+fn public_declarations(source: &str) -> Vec<String> {
+    let mut declarations = Vec::new();
+    let mut lines = source.lines();
+    while let Some(line) = lines.next() {
+        let Some(rest) = line.strip_prefix("pub ") else {
+            continue;
+        };
+        if let Some(rest) = rest.strip_prefix("use ") {
+            let mut statement = rest.to_string();
+            while !statement.contains(';') {
+                statement.push_str(lines.next().unwrap_or(";"));
+            }
+            let statement: String = statement.trim_end_matches(';').split_whitespace().collect();
+            match statement.split_once("::{") {
+                Some((path, names)) => {
+                    for name in names.trim_end_matches('}').split(',') {
+                        if !name.is_empty() {
+                            declarations.push(format!("use {}::{}", path, name));
+                        }
+                    }
+                }
+                None => declarations.push(format!("use {}", statement)),
+            }
+            continue;
+        }
+        let mut words = rest.split(|c: char| !c.is_alphanumeric() && c != '_');
+        if let (Some(kind), Some(name)) = (words.next(), words.next()) {
+            declarations.push(format!("{} {}", kind, name));
+        }
+    }
+    declarations
+}
+
+#[test]
+fn test_public_surface_matches_list() {
+    let src = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+    let mut surface = Vec::new();
+    for file in ["lib.rs", "prelude.rs"] {
+        let source = fs::read_to_string(src.join(file)).unwrap();
+        for declaration in public_declarations(&source) {
+            surface.push(format!("{}: {}", file, declaration));
+        }
+    }
+    let listed: Vec<&str> = include_str!("public_api.txt")
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+    assert_eq!(
+        surface, listed,
+        "the public surface changed; update tests/public_api.txt if intended"
+    );
+}
@@ -15,10 +15,8 @@ use std::fs;
 use std::path::Path;
 
 // Import from the crate
-use underchex_wasm::{
-    apply_move, generate_all_legal_moves, BoardState, Color, HexCoord, LanceVariant, Piece,
-    PieceType,
-};
+use underchex_wasm::moves::{apply_move, generate_all_legal_moves};
+use underchex_wasm::types::{BoardState, Color, HexCoord, LanceVariant, Piece, PieceType};
 
 // Import tablebase functions
 use underchex_wasm::tablebase::{
@@ -42,7 +40,7 @@ enum TablebaseTestCase {
     #[serde(rename = "tablebaseConfig")]
     Config(TablebaseConfigCase),
     #[serde(rename = "tablebaseWDL")]
-    WDL(TablebaseWDLCase),
+    Wdl(TablebaseWDLCase),
     #[serde(rename = "tablebaseMove")]
     Move(TablebaseMoveCase),
 }
@@ -465,7 +463,7 @@ fn test_tablebase_wdl_from_spec_full() {
     let mut count = 0;
 
     for tc in &suite.test_cases {
-        if let TablebaseTestCase::WDL(case) = tc {
+        if let TablebaseTestCase::Wdl(case) = tc {
             let board = build_board_from_spec(&case.setup.pieces);
             let turn = string_to_color(&case.setup.turn);
 
@@ -854,7 +852,7 @@ fn test_tablebase_coverage_report() {
     let wdl_tests = suite
         .test_cases
         .iter()
-        .filter(|tc| matches!(tc, TablebaseTestCase::WDL(_)))
+        .filter(|tc| matches!(tc, TablebaseTestCase::Wdl(_)))
         .count();
 
     let move_tests = suite
@@ -881,5 +879,5 @@ fn test_tablebase_coverage_report() {
     );
     println!("====================================================\n");
 
-    assert!(suite.test_cases.len() > 0);
+    assert!(!suite.test_cases.is_empty());
 }
@@ -11,9 +11,9 @@ use std::fs;
 use std::path::Path;
 
 // Import from the crate
-use underchex_wasm::{
-    is_valid_cell, validate_move, BoardState, Color, HexCoord, LanceVariant, Piece, PieceType,
-};
+use underchex_wasm::board::is_valid_cell;
+use underchex_wasm::moves::validate_move;
+use underchex_wasm::types::{BoardState, Color, HexCoord, LanceVariant, Piece, PieceType};
 
 // ============================================================================
 // Test Case Structures (matching JSON spec)
@@ -589,5 +589,5 @@ fn test_coverage_report() {
     println!("Total spec tests: {}", suite.test_cases.len());
     println!("=========================================\n");
 
-    assert!(suite.test_cases.len() > 0);
+    assert!(!suite.test_cases.is_empty());
 }
@@ -0,0 +1,66 @@
+//! Memory Budget Tests
+//!
+//! The memory budget is process-wide, so the tests that set it run here, in
+//! their own test binary, one after another.
+
+use underchex_wasm::memory::{eval_cache_capacity, MemoryBudget};
+use underchex_wasm::options::EngineOption;
+use underchex_wasm::tablebase::{
+    get_loaded_tablebases, set_tablebase, PieceTablebase, TablebaseMetadata,
+};
+use underchex_wasm::WasmGame;
+
+/// A tablebase of `megabytes` of entries.
+fn tablebase(name: &str, megabytes: usize) -> PieceTablebase {
+    let entries = vec![0; megabytes * 1024 * 1024 / 4];
+    PieceTablebase {
+        name: name.to_string(),
+        description: String::new(),
+        size: entries.len(),
+        entries,
+        metadata: TablebaseMetadata {
+            generated_at: String::new(),
+            generation_time_ms: 0,
+            win_count: 0,
+            draw_count: 0,
+            loss_count: 0,
+        },
+    }
+}
+
+#[test]
+fn test_memory_budget() {
+    let mut game = WasmGame::new();
+    let usage: serde_json::Value = serde_json::from_str(&game.get_memory_usage()).unwrap();
+    assert!(usage["budgetBytes"].is_null());
+
+    // Unbudgeted tablebases all stay loaded
+    set_tablebase(tablebase("KAvK", 2));
+    set_tablebase(tablebase("KBvK", 3));
+    assert_eq!(get_loaded_tablebases().len(), 2);
+
+    assert!(!game.set_memory_budget(1));
+    assert!(game.set_memory_budget(8));
+    let budget = MemoryBudget::from_megabytes(8);
+    assert_eq!(EngineOption::TTSize.get() as usize, budget.tt_entries);
+    assert_eq!(
+        eval_cache_capacity(),
+        (budget.pawn_cache_entries, budget.eval_cache_entries)
+    );
+
+    // 3.2MB for tablebases: the larger one is evicted
+    assert_eq!(get_loaded_tablebases(), ["KAvK"]);
+    set_tablebase(tablebase("KNvK", 1));
+    let mut loaded = get_loaded_tablebases();
+    loaded.sort();
+    assert_eq!(loaded, ["KAvK", "KNvK"]);
+    // One that cannot fit at all is not kept
+    set_tablebase(tablebase("KQvK", 4));
+    assert!(!get_loaded_tablebases().contains(&"KQvK".to_string()));
+
+    game.get_ai_move("easy");
+    let usage: serde_json::Value = serde_json::from_str(&game.get_memory_usage()).unwrap();
+    assert_eq!(usage["budgetBytes"], 8 * 1024 * 1024);
+    assert!(usage["ttEntries"].as_u64().unwrap() > 0);
+    assert!(usage["totalBytes"].as_u64().unwrap() <= 8 * 1024 * 1024);
+}
@@ -0,0 +1,144 @@
+//! Move Generation Differential Test
+//!
+//! Compares the engine's move generator with the naive reference in
+//! `support` over thousands of random positions: positions of random games,
+//! random placements of every piece type (fairy pieces and the boulder
+//! included), and generated quiet positions. Performance rewrites of move generation must keep them equal.
+
+mod support;
+
+use support::{reference_in_check, reference_legal_moves, RefMove};
+use underchex_wasm::board::get_all_cells;
+use underchex_wasm::game::create_new_game;
+use underchex_wasm::moves::{generate_all_legal_moves, is_in_check};
+use underchex_wasm::positions::{generate_random_position, PositionConstraints};
+use underchex_wasm::rng::SeededRng;
+use underchex_wasm::types::{BoardState, Color, LanceVariant, Piece, PieceType};
+use underchex_wasm::zobrist::random_game_positions;
+
+const GAMES: usize = 20;
+const GAME_PLIES: usize = 60;
+const PLACEMENTS: usize = 1500;
+const GENERATED: u64 = 40;
+
+/// Piece types of the random placements, kings aside.
+const PLACED_TYPES: [PieceType; 11] = [
+    PieceType::Pawn,
+    PieceType::Queen,
+    PieceType::Knight,
+    PieceType::Lance,
+    PieceType::Chariot,
+    PieceType::Wazir,
+    PieceType::Ferz,
+    PieceType::Dabbaba,
+    PieceType::Cannon,
+    PieceType::PromotedKnight,
+    PieceType::PromotedLance,
+];
+
+fn assert_same_moves(board: &BoardState, color: Color, label: &str) {
+    let mut engine: Vec<RefMove> = generate_all_legal_moves(board, color)
+        .iter()
+        .map(RefMove::from_move)
+        .collect();
+    engine.sort();
+    let reference = reference_legal_moves(board, color);
+    let missing: Vec<&RefMove> = reference.iter().filter(|mv| !engine.contains(mv)).collect();
+    let extra: Vec<&RefMove> = engine.iter().filter(|mv| !reference.contains(mv)).collect();
+    assert!(
+        engine == reference,
+        "{}: {:?} to move on {:?}\nmissing: {:?}\nextra: {:?}",
+        label,
+        color,
+        board,
+        missing,
+        extra
+    );
+    assert_eq!(
+        is_in_check(board, color),
+        reference_in_check(board, color),
+        "{}: check on {:?}",
+        label,
+        board
+    );
+}
+
+/// Both kings and up to ten other pieces on random cells, sometimes with
+/// the boulder.
+fn random_placement(rng: &mut SeededRng) -> BoardState {
+    let mut cells = get_all_cells();
+    let mut board = BoardState::new();
+    let mut place = |piece: Piece, rng: &mut SeededRng| {
+        let cell = cells.swap_remove(rng.next_below(cells.len()));
+        board.insert(cell.to_key(), piece);
+    };
+    place(Piece::new(PieceType::King, Color::White), rng);
+    place(Piece::new(PieceType::King, Color::Black), rng);
+    if rng.next_below(4) == 0 {
+        place(Piece::boulder(), rng);
+    }
+    for _ in 0..rng.next_below(11) {
+        let color = if rng.next_below(2) == 0 {
+            Color::White
+        } else {
+            Color::Black
+        };
+        let piece_type = PLACED_TYPES[rng.next_below(PLACED_TYPES.len())];
+        let piece = match piece_type {
+            PieceType::Lance | PieceType::PromotedLance => {
+                let variant = if rng.next_below(2) == 0 {
+                    LanceVariant::A
+                } else {
+                    LanceVariant::B
+                };
+                Piece {
+                    variant: Some(variant),
+                    ..Piece::new(piece_type, color)
+                }
+            }
+            _ => Piece::new(piece_type, color),
+        };
+        place(piece, rng);
+    }
+    board
+}
+
+#[test]
+fn test_random_game_positions_match_reference() {
+    let start = create_new_game().board;
+    let mut rng = SeededRng::new(3742);
+    let mut positions = 0;
+    for game in 0..GAMES {
+        for (board, color) in random_game_positions(&start, Color::White, GAME_PLIES, &mut rng) {
+            assert_same_moves(&board, color, &format!("game {}", game));
+            positions += 1;
+        }
+    }
+    assert!(positions > GAMES * GAME_PLIES / 2);
+}
+
+#[test]
+fn test_random_placements_match_reference() {
+    let mut rng = SeededRng::new(3742);
+    for placement in 0..PLACEMENTS {
+        let board = random_placement(&mut rng);
+        for color in [Color::White, Color::Black] {
+            assert_same_moves(&board, color, &format!("placement {}", placement));
+        }
+    }
+}
+
+#[test]
+fn test_generated_positions_match_reference() {
+    let constraints = PositionConstraints {
+        max_pieces: 6,
+        piece_types: PLACED_TYPES.to_vec(),
+        ..Default::default()
+    };
+    for seed in 0..GENERATED {
+        let game = generate_random_position(seed, &constraints).unwrap();
+        for color in [Color::White, Color::Black] {
+            assert_same_moves(&game.board, color, &format!("generated {}", seed));
+        }
+    }
+}
@@ -0,0 +1,85 @@
+//! Singular Extension Tests
+//!
+//! Engine options are process-wide, so the searches that switch singular
+//! extensions on run here, in their own test binary, one after another.
+
+use underchex_wasm::ai::{find_best_move_iterative, SearchResult, TranspositionTable};
+use underchex_wasm::options::set_option;
+use underchex_wasm::{BoardState, Color, HexCoord, Piece, PieceType, Variant};
+
+/// White to move can take a queen that is attacking its own queen: the
+/// capture is the only move that does not lose material.
+fn queen_trade_position() -> BoardState {
+    let mut board = BoardState::new();
+    board.insert("0,4".to_string(), Piece::new(PieceType::King, Color::White));
+    board.insert(
+        "0,0".to_string(),
+        Piece::new(PieceType::Queen, Color::White),
+    );
+    board.insert(
+        "4,-4".to_string(),
+        Piece::new(PieceType::King, Color::Black),
+    );
+    board.insert(
+        "0,-2".to_string(),
+        Piece::new(PieceType::Queen, Color::Black),
+    );
+    board.insert(
+        "-3,1".to_string(),
+        Piece::new(PieceType::Pawn, Color::Black),
+    );
+    board
+}
+
+fn search(board: &BoardState, depth: i32) -> SearchResult {
+    let mut tt = TranspositionTable::new(50_000);
+    find_best_move_iterative(
+        board,
+        Color::White,
+        depth,
+        u64::MAX,
+        &mut tt,
+        true,
+        &[],
+        Variant::Standard,
+    )
+}
+
+#[test]
+fn test_singular_extensions() {
+    let board = queen_trade_position();
+    let capture = |result: &SearchResult| {
+        result
+            .best_move
+            .as_ref()
+            .map(|mv| (mv.from, mv.to, mv.captured.map(|piece| piece.piece_type)))
+    };
+    let expected = Some((
+        HexCoord::new(0, 0),
+        HexCoord::new(0, -2),
+        Some(PieceType::Queen),
+    ));
+
+    // Off by default
+    let plain = search(&board, 5);
+    assert_eq!(plain.stats.singular_extensions, 0);
+    assert_eq!(capture(&plain), expected);
+
+    set_option("singularExtensions", "true").unwrap();
+    let extended = search(&board, 5);
+    set_option("singularExtensions", "false").unwrap();
+
+    // The forced recaptures are extended, and the tactic is still found
+    assert!(extended.stats.singular_extensions > 0);
+    assert_eq!(capture(&extended), expected);
+    assert!(extended.score > 500);
+
+    // A margin no alternative can clear turns the extension off again
+    set_option("singularExtensions", "true").unwrap();
+    set_option("singularMargin", "2000").unwrap();
+    let strict = search(&board, 5);
+    set_option("singularExtensions", "false").unwrap();
+    set_option("singularMargin", "100").unwrap();
+    assert!(strict.stats.singular_extensions < extended.stats.singular_extensions);
+    assert_eq!(capture(&strict), expected);
+}
@@ -0,0 +1,300 @@
+//! Reference Move Generator
+//!
+//! A deliberately naive move generator written straight from the rules, to
+//! check the engine's optimized one against. It shares none of the engine's
+//! move code or piece tables: every piece's movement is spelled out below,
+//! and a move is legal if no pseudo-legal reply of the opponent lands on the
+//! mover's king. Keep it simple rather than fast.
+
+use underchex_wasm::types::{BoardState, Color, HexCoord, LanceVariant, Move, Piece, PieceType};
+
+const RADIUS: i32 = 4;
+
+const N: (i32, i32) = (0, -1);
+const S: (i32, i32) = (0, 1);
+const NE: (i32, i32) = (1, -1);
+const SW: (i32, i32) = (-1, 1);
+const NW: (i32, i32) = (-1, 0);
+const SE: (i32, i32) = (1, 0);
+
+const ALL: [(i32, i32); 6] = [N, S, NE, SW, NW, SE];
+const DIAGONALS: [(i32, i32); 4] = [NE, NW, SE, SW];
+const LANCE_A: [(i32, i32); 4] = [N, S, NW, SE];
+const LANCE_B: [(i32, i32); 4] = [N, S, NE, SW];
+const KNIGHT: [(i32, i32); 6] = [(1, -2), (-1, -1), (2, -1), (1, 1), (-1, 2), (-2, 1)];
+const PROMOTIONS: [PieceType; 4] = [
+    PieceType::Queen,
+    PieceType::Chariot,
+    PieceType::Lance,
+    PieceType::Knight,
+];
+
+/// A move as the reference sees it, comparable across generators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RefMove {
+    pub from: (i32, i32),
+    pub to: (i32, i32),
+    /// Promotion and captured piece, by name, so that moves sort
+    pub promotion: Option<&'static str>,
+    pub captured: Option<&'static str>,
+}
+
+impl RefMove {
+    /// The engine's move in the reference's terms.
+    pub fn from_move(mv: &Move) -> Self {
+        Self {
+            from: (mv.from.q, mv.from.r),
+            to: (mv.to.q, mv.to.r),
+            promotion: mv.promotion.map(type_name),
+            captured: mv.captured.map(|piece| type_name(piece.piece_type)),
+        }
+    }
+}
+
+fn type_name(piece_type: PieceType) -> &'static str {
+    match piece_type {
+        PieceType::Pawn => "pawn",
+        PieceType::King => "king",
+        PieceType::Queen => "queen",
+        PieceType::Knight => "knight",
+        PieceType::Lance => "lance",
+        PieceType::Chariot => "chariot",
+        PieceType::Boulder => "boulder",
+        PieceType::Wazir => "wazir",
+        PieceType::Ferz => "ferz",
+        PieceType::Dabbaba => "dabbaba",
+        PieceType::Cannon => "cannon",
+        PieceType::PromotedKnight => "promotedKnight",
+        PieceType::PromotedLance => "promotedLance",
+    }
+}
+
+fn on_board((q, r): (i32, i32)) -> bool {
+    q.abs() <= RADIUS && r.abs() <= RADIUS && (q + r).abs() <= RADIUS
+}
+
+fn at(board: &BoardState, (q, r): (i32, i32)) -> Option<Piece> {
+    board.get(&HexCoord::new(q, r).to_key()).copied()
+}
+
+fn add((q, r): (i32, i32), (dq, dr): (i32, i32)) -> (i32, i32) {
+    (q + dq, r + dr)
+}
+
+/// How a piece moves: one-cell steps, slides, leaps and cannon hops.
+struct Movement {
+    steps: Vec<(i32, i32)>,
+    slides: Vec<(i32, i32)>,
+    leaps: Vec<(i32, i32)>,
+    hops: Vec<(i32, i32)>,
+}
+
+fn movement(piece: Piece) -> Movement {
+    let lance = if piece.variant == Some(LanceVariant::A) {
+        LANCE_A.to_vec()
+    } else {
+        LANCE_B.to_vec()
+    };
+    let none = Movement {
+        steps: Vec::new(),
+        slides: Vec::new(),
+        leaps: Vec::new(),
+        hops: Vec::new(),
+    };
+    match piece.piece_type {
+        PieceType::King | PieceType::Wazir => Movement {
+            steps: ALL.to_vec(),
+            ..none
+        },
+        PieceType::Ferz => Movement {
+            steps: DIAGONALS.to_vec(),
+            ..none
+        },
+        PieceType::Queen => Movement {
+            slides: ALL.to_vec(),
+            ..none
+        },
+        PieceType::Chariot => Movement {
+            slides: DIAGONALS.to_vec(),
+            ..none
+        },
+        PieceType::Lance => Movement {
+            slides: lance,
+            ..none
+        },
+        PieceType::Knight => Movement {
+            leaps: KNIGHT.to_vec(),
+            ..none
+        },
+        PieceType::Dabbaba => Movement {
+            leaps: ALL.iter().map(|&(dq, dr)| (2 * dq, 2 * dr)).collect(),
+            ..none
+        },
+        PieceType::Cannon => Movement {
+            hops: LANCE_B.to_vec(),
+            ..none
+        },
+        PieceType::PromotedKnight => Movement {
+            steps: ALL.to_vec(),
+            leaps: KNIGHT.to_vec(),
+            ..none
+        },
+        PieceType::PromotedLance => Movement {
+            steps: ALL.to_vec(),
+            slides: lance,
+            ..none
+        },
+        // Pawns have their own rules; the boulder never moves
+        PieceType::Pawn | PieceType::Boulder => none,
+    }
+}
+
+/// Whether a piece of `color` may land on a cell: empty, or an enemy piece
+/// (the boulder is nobody's).
+fn can_land(board: &BoardState, cell: (i32, i32), color: Color) -> bool {
+    match at(board, cell) {
+        None => true,
+        Some(other) => other.piece_type != PieceType::Boulder && other.color != color,
+    }
+}
+
+fn push(
+    moves: &mut Vec<RefMove>,
+    board: &BoardState,
+    from: (i32, i32),
+    to: (i32, i32),
+    promotion: Option<PieceType>,
+) {
+    moves.push(RefMove {
+        from,
+        to,
+        promotion: promotion.map(type_name),
+        captured: at(board, to).map(|piece| type_name(piece.piece_type)),
+    });
+}
+
+fn pawn_moves(board: &BoardState, piece: Piece, from: (i32, i32), moves: &mut Vec<RefMove>) {
+    let (forward, captures, last_row) = match piece.color {
+        Color::White => (N, [N, NE, NW], -RADIUS),
+        Color::Black => (S, [S, SE, SW], RADIUS),
+    };
+    let mut add_move = |to: (i32, i32)| {
+        if to.1 == last_row {
+            for promotion in PROMOTIONS {
+                push(moves, board, from, to, Some(promotion));
+            }
+        } else {
+            push(moves, board, from, to, None);
+        }
+    };
+    let ahead = add(from, forward);
+    if on_board(ahead) && at(board, ahead).is_none() {
+        add_move(ahead);
+    }
+    for direction in captures {
+        let target = add(from, direction);
+        if on_board(target) && at(board, target).is_some() && can_land(board, target, piece.color) {
+            add_move(target);
+        }
+    }
+}
+
+/// Moves of one piece, ignoring whether its king is left in check.
+fn pseudo_legal_moves(board: &BoardState, piece: Piece, from: (i32, i32)) -> Vec<RefMove> {
+    let mut moves = Vec::new();
+    if piece.piece_type == PieceType::Pawn {
+        pawn_moves(board, piece, from, &mut moves);
+        return moves;
+    }
+    let movement = movement(piece);
+    for offset in movement.steps.iter().chain(&movement.leaps) {
+        let to = add(from, *offset);
+        if on_board(to) && can_land(board, to, piece.color) {
+            push(&mut moves, board, from, to, None);
+        }
+    }
+    for &direction in &movement.slides {
+        let mut to = add(from, direction);
+        while on_board(to) {
+            if can_land(board, to, piece.color) {
+                push(&mut moves, board, from, to, None);
+            }
+            if at(board, to).is_some() {
+                break;
+            }
+            to = add(to, direction);
+        }
+    }
+    for &direction in &movement.hops {
+        let mut to = add(from, direction);
+        // Moves through empty cells up to the screen...
+        while on_board(to) && at(board, to).is_none() {
+            push(&mut moves, board, from, to, None);
+            to = add(to, direction);
+        }
+        // ...and captures the next piece beyond it
+        to = add(to, direction);
+        while on_board(to) && at(board, to).is_none() {
+            to = add(to, direction);
+        }
+        if on_board(to) && can_land(board, to, piece.color) {
+            push(&mut moves, board, from, to, None);
+        }
+    }
+    moves
+}
+
+/// Pieces of a side, wherever the board has them: the standard setup puts a
+/// few on cells past the hexagon's edge, and they move like any other.
+fn pieces(board: &BoardState, color: Color) -> Vec<((i32, i32), Piece)> {
+    board
+        .iter()
+        .filter(|(_, piece)| piece.color == color && piece.piece_type != PieceType::Boulder)
+        .filter_map(|(key, piece)| {
+            let cell = HexCoord::from_key(key)?;
+            Some(((cell.q, cell.r), *piece))
+        })
+        .collect()
+}
+
+fn play(board: &BoardState, mv: &RefMove) -> BoardState {
+    let mut next = board.clone();
+    let from = HexCoord::new(mv.from.0, mv.from.1).to_key();
+    let Some(mut piece) = next.remove(&from) else {
+        return next;
+    };
+    if let Some(promotion) = mv.promotion {
+        let promoted = PROMOTIONS
+            .into_iter()
+            .find(|&piece_type| type_name(piece_type) == promotion)
+            .unwrap_or(PieceType::Queen);
+        piece = Piece::new(promoted, piece.color);
+    }
+    next.insert(HexCoord::new(mv.to.0, mv.to.1).to_key(), piece);
+    next
+}
+
+/// Whether any move of `color`'s opponent would land on `color`'s king.
+pub fn reference_in_check(board: &BoardState, color: Color) -> bool {
+    let king = pieces(board, color)
+        .into_iter()
+        .find(|(_, piece)| piece.piece_type == PieceType::King);
+    let Some((king, _)) = king else {
+        return false;
+    };
+    pieces(board, color.opposite())
+        .into_iter()
+        .flat_map(|(from, piece)| pseudo_legal_moves(board, piece, from))
+        .any(|mv| mv.to == king)
+}
+
+/// Every legal move of `color`, sorted.
+pub fn reference_legal_moves(board: &BoardState, color: Color) -> Vec<RefMove> {
+    let mut moves: Vec<RefMove> = pieces(board, color)
+        .into_iter()
+        .flat_map(|(from, piece)| pseudo_legal_moves(board, piece, from))
+        .filter(|mv| !reference_in_check(&play(board, mv), color))
+        .collect();
+    moves.sort();
+    moves
+}